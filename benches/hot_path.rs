@@ -0,0 +1,128 @@
+//! The per-trade path: parsing a trades frame, the socket's threshold filter,
+//! fanning an alert out over a coin's subscribers and formatting it.
+//!
+//! `cargo bench --bench hot_path`; `-- --save-baseline before` then
+//! `-- --baseline before` compares a refactor against the previous numbers.
+
+use criterion::{black_box, criterion_group, criterion_main, BenchmarkId, Criterion, Throughput};
+use hl_tg_bot::{
+    database::{Destination, UserSettings, UserSubscription},
+    delivery,
+    formatting,
+    hyperliquid::websocket::trades_frame,
+};
+
+const MIN_NOTIONAL_USD: f64 = 100_000.0;
+
+// a frame of `fills` BTC fills where one in `whale_every` clears the threshold
+fn frame(fills: usize, whale_every: usize) -> String {
+    let data: Vec<String> = (0..fills)
+        .map(|i| {
+            let sz = if i % whale_every == 0 { "2.5" } else { "0.01234" };
+            format!(
+                r#"{{"coin":"BTC","side":"{}","px":"61234.5","sz":"{}","time":1718000000{:03},"tid":{},"hash":"0x{:064x}","users":["0x{:040x}","0x{:040x}"]}}"#,
+                if i % 2 == 0 { "B" } else { "A" },
+                sz,
+                i % 1000,
+                900_000 + i,
+                i,
+                i,
+                i + 1
+            )
+        })
+        .collect();
+    format!(r#"{{"channel":"trades","data":[{}]}}"#, data.join(","))
+}
+
+fn parsing(c: &mut Criterion) {
+    let mut group = c.benchmark_group("parse_trades_frame");
+    for fills in [1, 10, 100] {
+        let text = frame(fills, 10);
+        group.throughput(Throughput::Bytes(text.len() as u64));
+        group.bench_with_input(BenchmarkId::from_parameter(fills), &text, |b, text| {
+            b.iter(|| trades_frame(black_box(text)))
+        });
+    }
+    group.finish();
+}
+
+fn threshold_filter(c: &mut Criterion) {
+    let mut group = c.benchmark_group("threshold_filter");
+    for fills in [10, 100] {
+        let text = frame(fills, 10);
+        let trades = trades_frame(&text).expect("trades frame");
+        group.throughput(Throughput::Elements(fills as u64));
+        group.bench_with_input(BenchmarkId::from_parameter(fills), &trades, |b, trades| {
+            b.iter(|| {
+                trades
+                    .iter()
+                    .filter(|trade| trade.notional_usd().is_some_and(|notional| notional >= black_box(MIN_NOTIONAL_USD)))
+                    .map(|trade| trade.to_trade())
+                    .collect::<Vec<_>>()
+            })
+        });
+    }
+    group.finish();
+}
+
+// a coin's subscribers as the fan-out query returns them: some with their own
+// threshold, some with extra destinations, a few on /priority
+fn subscribers(count: usize) -> Vec<UserSubscription> {
+    (0..count as i64)
+        .map(|i| UserSubscription {
+            telegram_user_id: i + 1,
+            telegram_chat_id: i + 1,
+            settings: UserSettings {
+                full_precision: i % 2 == 0,
+                charts_enabled: i % 5 == 0,
+                ..UserSettings::default()
+            },
+            min_notional_usd: (i % 4 == 0).then_some(250_000.0),
+            destinations: (0..i % 3)
+                .map(|d| Destination {
+                    id: i * 10 + d,
+                    chat_id: (d == 0).then_some(-(i * 10 + d)),
+                    webhook_url: (d == 1).then(|| format!("https://hooks.example.com/{}", i)),
+                    coin: None,
+                    full_precision: None,
+                    charts_enabled: Some(false),
+                })
+                .collect(),
+            priority: i % 20 == 0,
+            muted_until: None,
+        })
+        .collect()
+}
+
+fn fan_out(c: &mut Criterion) {
+    let mut group = c.benchmark_group("fan_out");
+    for count in [10, 1_000, 10_000] {
+        let subscribers = subscribers(count);
+        group.throughput(Throughput::Elements(count as u64));
+        group.bench_with_input(BenchmarkId::from_parameter(count), &subscribers, |b, subscribers| {
+            // the coordinator's steps between the subscriber lookup and delivery
+            b.iter(|| delivery::fan_out(subscribers, black_box(150_000.0)))
+        });
+    }
+    group.finish();
+}
+
+fn message_formatting(c: &mut Criterion) {
+    let mut group = c.benchmark_group("formatting");
+    group.bench_function("format_usd_compact", |b| b.iter(|| formatting::format_usd(black_box(1_234_567.89), false)));
+    group.bench_function("format_usd_full", |b| b.iter(|| formatting::format_usd(black_box(1_234_567.89), true)));
+    group.bench_function("format_price", |b| b.iter(|| formatting::format_price(black_box("61234.5"))));
+    // the body of a trade alert, as TelegramBot::trade_message lays it out
+    // once its fx rate and details are looked up
+    let text = frame(1, 1);
+    let trade = trades_frame(&text).expect("trades frame")[0].to_trade();
+    group.bench_function("trade_alert_text", |b| {
+        b.iter(|| {
+            formatting::trade_alert_text(black_box(&trade), black_box(153_086.25), false, None, Vec::new(), None).plain()
+        })
+    });
+    group.finish();
+}
+
+criterion_group!(benches, parsing, threshold_filter, fan_out, message_formatting);
+criterion_main!(benches);
@@ -0,0 +1,5 @@
+CREATE TABLE IF NOT EXISTS user_settings (
+    telegram_user_id BIGINT PRIMARY KEY,
+    currency TEXT,
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
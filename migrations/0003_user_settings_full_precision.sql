@@ -0,0 +1 @@
+ALTER TABLE user_settings ADD COLUMN IF NOT EXISTS full_precision BOOLEAN NOT NULL DEFAULT FALSE;
@@ -0,0 +1 @@
+ALTER TABLE user_settings ADD COLUMN IF NOT EXISTS charts_enabled BOOLEAN NOT NULL DEFAULT FALSE;
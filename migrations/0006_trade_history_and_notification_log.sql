@@ -0,0 +1,28 @@
+CREATE TABLE IF NOT EXISTS trade_history (
+    id BIGSERIAL PRIMARY KEY,
+    coin TEXT NOT NULL,
+    side TEXT NOT NULL,
+    px TEXT NOT NULL,
+    sz TEXT NOT NULL,
+    notional_usd DOUBLE PRECISION NOT NULL,
+    tid BIGINT,
+    hash TEXT,
+    trade_time TIMESTAMPTZ,
+    recorded_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX IF NOT EXISTS trade_history_coin_time_idx ON trade_history (coin, recorded_at DESC);
+
+CREATE TABLE IF NOT EXISTS notification_log (
+    id BIGSERIAL PRIMARY KEY,
+    telegram_user_id BIGINT NOT NULL,
+    telegram_chat_id BIGINT NOT NULL,
+    coin TEXT NOT NULL,
+    trade_key BIGINT NOT NULL,
+    delivered BOOLEAN NOT NULL,
+    latency_ms BIGINT,
+    error TEXT,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX IF NOT EXISTS notification_log_chat_time_idx ON notification_log (telegram_chat_id, created_at DESC);
@@ -0,0 +1 @@
+ALTER TABLE notification_log ADD COLUMN IF NOT EXISTS attempts INT NOT NULL DEFAULT 1;
@@ -0,0 +1,10 @@
+CREATE TABLE IF NOT EXISTS linked_accounts (
+    telegram_user_id BIGINT NOT NULL,
+    address TEXT NOT NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    PRIMARY KEY (telegram_user_id, address)
+);
+
+ALTER TABLE user_settings ADD COLUMN IF NOT EXISTS funding_summary TEXT;
+ALTER TABLE user_settings ADD COLUMN IF NOT EXISTS funding_summary_chat_id BIGINT;
+ALTER TABLE user_settings ADD COLUMN IF NOT EXISTS funding_summary_sent_at TIMESTAMPTZ;
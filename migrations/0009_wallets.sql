@@ -0,0 +1,12 @@
+ALTER TABLE trade_history ADD COLUMN IF NOT EXISTS buyer TEXT;
+ALTER TABLE trade_history ADD COLUMN IF NOT EXISTS seller TEXT;
+
+CREATE TABLE IF NOT EXISTS watched_wallets (
+    telegram_user_id BIGINT NOT NULL,
+    telegram_chat_id BIGINT NOT NULL,
+    address TEXT NOT NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    PRIMARY KEY (telegram_user_id, address)
+);
+
+CREATE INDEX IF NOT EXISTS watched_wallets_address_idx ON watched_wallets (address);
@@ -0,0 +1,18 @@
+CREATE TABLE IF NOT EXISTS destinations (
+    id BIGSERIAL PRIMARY KEY,
+    telegram_user_id BIGINT NOT NULL,
+    chat_id BIGINT,
+    webhook_url TEXT,
+    -- NULL fans out every subscription, otherwise just this coin
+    coin TEXT,
+    -- NULL falls back to the user's own setting
+    full_precision BOOLEAN,
+    charts_enabled BOOLEAN,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    CHECK ((chat_id IS NULL) <> (webhook_url IS NULL))
+);
+
+CREATE INDEX IF NOT EXISTS destinations_user_idx ON destinations (telegram_user_id);
+
+ALTER TABLE notification_log ALTER COLUMN telegram_chat_id DROP NOT NULL;
+ALTER TABLE notification_log ADD COLUMN IF NOT EXISTS webhook_url TEXT;
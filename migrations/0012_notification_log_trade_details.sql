@@ -0,0 +1,5 @@
+-- lets /coinstats report alert size and direction straight from the log
+ALTER TABLE notification_log ADD COLUMN IF NOT EXISTS side TEXT;
+ALTER TABLE notification_log ADD COLUMN IF NOT EXISTS notional_usd DOUBLE PRECISION;
+
+CREATE INDEX IF NOT EXISTS notification_log_user_coin_idx ON notification_log (telegram_user_id, coin, created_at DESC);
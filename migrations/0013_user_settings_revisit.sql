@@ -0,0 +1 @@
+ALTER TABLE user_settings ADD COLUMN IF NOT EXISTS revisit_alerts BOOLEAN NOT NULL DEFAULT FALSE;
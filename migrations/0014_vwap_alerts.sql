@@ -0,0 +1,10 @@
+CREATE TABLE IF NOT EXISTS vwap_alerts (
+    telegram_user_id BIGINT NOT NULL,
+    telegram_chat_id BIGINT NOT NULL,
+    coin TEXT NOT NULL,
+    threshold_pct DOUBLE PRECISION NOT NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    PRIMARY KEY (telegram_user_id, coin)
+);
+
+CREATE INDEX IF NOT EXISTS vwap_alerts_coin_idx ON vwap_alerts (coin);
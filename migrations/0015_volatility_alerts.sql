@@ -0,0 +1,11 @@
+CREATE TABLE IF NOT EXISTS volatility_alerts (
+    telegram_user_id BIGINT NOT NULL,
+    telegram_chat_id BIGINT NOT NULL,
+    coin TEXT NOT NULL,
+    -- alert once recent volatility is this many times the baseline
+    multiple DOUBLE PRECISION NOT NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    PRIMARY KEY (telegram_user_id, coin)
+);
+
+CREATE INDEX IF NOT EXISTS volatility_alerts_coin_idx ON volatility_alerts (coin);
@@ -0,0 +1,12 @@
+CREATE TABLE IF NOT EXISTS user_roles (
+    telegram_user_id BIGINT PRIMARY KEY,
+    role TEXT NOT NULL CHECK (role IN ('owner', 'admin', 'moderator')),
+    granted_by BIGINT NOT NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE TABLE IF NOT EXISTS banned_users (
+    telegram_user_id BIGINT PRIMARY KEY,
+    banned_by BIGINT NOT NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
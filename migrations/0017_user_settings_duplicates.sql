@@ -0,0 +1 @@
+ALTER TABLE user_settings ADD COLUMN IF NOT EXISTS duplicate_alerts TEXT NOT NULL DEFAULT 'both';
@@ -0,0 +1 @@
+ALTER TABLE user_settings ADD COLUMN IF NOT EXISTS show_leverage BOOLEAN NOT NULL DEFAULT FALSE;
@@ -0,0 +1 @@
+ALTER TABLE user_subscriptions ADD COLUMN IF NOT EXISTS min_notional_usd DOUBLE PRECISION;
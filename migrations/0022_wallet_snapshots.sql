@@ -0,0 +1,19 @@
+CREATE TABLE IF NOT EXISTS wallet_snapshots (
+    id BIGSERIAL PRIMARY KEY,
+    address TEXT NOT NULL,
+    account_value_usd DOUBLE PRECISION NOT NULL,
+    taken_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX IF NOT EXISTS wallet_snapshots_address_idx ON wallet_snapshots (address, taken_at DESC);
+
+CREATE TABLE IF NOT EXISTS wallet_snapshot_positions (
+    snapshot_id BIGINT NOT NULL REFERENCES wallet_snapshots (id) ON DELETE CASCADE,
+    coin TEXT NOT NULL,
+    -- signed, negative when short
+    size DOUBLE PRECISION NOT NULL,
+    entry_px DOUBLE PRECISION,
+    position_value_usd DOUBLE PRECISION NOT NULL,
+    unrealized_pnl_usd DOUBLE PRECISION NOT NULL,
+    PRIMARY KEY (snapshot_id, coin)
+);
@@ -0,0 +1,3 @@
+-- realized pnl is for the fills since the wallet's previous snapshot
+ALTER TABLE wallet_snapshots ADD COLUMN IF NOT EXISTS realized_pnl_usd DOUBLE PRECISION NOT NULL DEFAULT 0;
+ALTER TABLE wallet_snapshots ADD COLUMN IF NOT EXISTS unrealized_pnl_usd DOUBLE PRECISION NOT NULL DEFAULT 0;
@@ -0,0 +1,16 @@
+ALTER TABLE user_settings ADD COLUMN IF NOT EXISTS ticker_mode BOOLEAN;
+
+-- one running-totals message per coin per chat, edited in place
+CREATE TABLE IF NOT EXISTS ticker_messages (
+    chat_id BIGINT NOT NULL,
+    coin TEXT NOT NULL,
+    message_id INTEGER,
+    day DATE NOT NULL,
+    buys INTEGER NOT NULL DEFAULT 0,
+    buy_usd DOUBLE PRECISION NOT NULL DEFAULT 0,
+    sells INTEGER NOT NULL DEFAULT 0,
+    sell_usd DOUBLE PRECISION NOT NULL DEFAULT 0,
+    last_trade TEXT,
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    PRIMARY KEY (chat_id, coin)
+);
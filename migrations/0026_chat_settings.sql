@@ -0,0 +1,7 @@
+-- settings that belong to a group or channel rather than to whoever set up its alerts
+CREATE TABLE IF NOT EXISTS chat_settings (
+    chat_id BIGINT PRIMARY KEY,
+    pin_summaries BOOLEAN NOT NULL DEFAULT FALSE,
+    pinned_summary_id INTEGER,
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
@@ -0,0 +1,4 @@
+-- fan-out looks subscribers up by coin on every alert-worthy trade
+CREATE INDEX IF NOT EXISTS user_subscriptions_coin_idx ON user_subscriptions (coin);
+-- per-user listings and deletes; narrower than the (telegram_user_id, coin) unique index
+CREATE INDEX IF NOT EXISTS user_subscriptions_user_idx ON user_subscriptions (telegram_user_id);
@@ -0,0 +1,4 @@
+-- unsubscribing keeps the row, and with it the coin's threshold, so a later
+-- resubscribe picks up where it left off; NULL removed_at is an active subscription
+ALTER TABLE user_subscriptions ADD COLUMN IF NOT EXISTS removed_at TIMESTAMPTZ;
+ALTER TABLE user_subscriptions ADD COLUMN IF NOT EXISTS resubscribed_at TIMESTAMPTZ;
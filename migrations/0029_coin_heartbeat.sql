@@ -0,0 +1,3 @@
+ALTER TABLE user_settings ADD COLUMN IF NOT EXISTS heartbeat BOOLEAN;
+-- last "still quiet" note for the coin, so a quiet spell gets one a day
+ALTER TABLE user_subscriptions ADD COLUMN IF NOT EXISTS heartbeat_sent_at TIMESTAMPTZ;
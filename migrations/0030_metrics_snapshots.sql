@@ -0,0 +1,22 @@
+-- coordinator counters per interval, so totals survive restarts
+CREATE TABLE IF NOT EXISTS metrics_snapshots (
+    id BIGSERIAL PRIMARY KEY,
+    taken_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    interval_secs INTEGER NOT NULL,
+    trades_seen BIGINT NOT NULL,
+    large_trades BIGINT NOT NULL,
+    alerts_sent BIGINT NOT NULL,
+    alerts_retried BIGINT NOT NULL,
+    alerts_failed BIGINT NOT NULL,
+    p95_latency_ms BIGINT
+);
+
+CREATE INDEX IF NOT EXISTS metrics_snapshots_taken_at_idx ON metrics_snapshots (taken_at);
+
+CREATE TABLE IF NOT EXISTS metrics_snapshot_coins (
+    snapshot_id BIGINT NOT NULL REFERENCES metrics_snapshots (id) ON DELETE CASCADE,
+    coin TEXT NOT NULL,
+    trades BIGINT NOT NULL,
+    volume_usd DOUBLE PRECISION NOT NULL,
+    PRIMARY KEY (snapshot_id, coin)
+);
@@ -0,0 +1,16 @@
+-- per-coin buy/sell totals per utc day, rolled up nightly from trade_history
+CREATE TABLE IF NOT EXISTS trade_daily_rollups (
+    day DATE NOT NULL,
+    coin TEXT NOT NULL,
+    buys BIGINT NOT NULL,
+    buy_usd DOUBLE PRECISION NOT NULL,
+    sells BIGINT NOT NULL,
+    sell_usd DOUBLE PRECISION NOT NULL,
+    rolled_up_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    PRIMARY KEY (day, coin)
+);
+
+CREATE INDEX IF NOT EXISTS trade_daily_rollups_coin_idx ON trade_daily_rollups (coin, day DESC);
+
+-- the rollup reads whole days across every coin
+CREATE INDEX IF NOT EXISTS trade_history_recorded_at_idx ON trade_history (recorded_at);
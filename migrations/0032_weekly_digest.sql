@@ -0,0 +1,4 @@
+-- opt-in weekly digest; the offset places "sunday evening" in the user's own time
+ALTER TABLE user_settings ADD COLUMN IF NOT EXISTS weekly_digest_offset_mins INTEGER;
+ALTER TABLE user_settings ADD COLUMN IF NOT EXISTS weekly_digest_chat_id BIGINT;
+ALTER TABLE user_settings ADD COLUMN IF NOT EXISTS weekly_digest_sent_at TIMESTAMPTZ;
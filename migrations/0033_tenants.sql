@@ -0,0 +1,56 @@
+-- several bots can share this database; each connection names its tenant in
+-- hl.tenant and row-level security keeps it to that tenant's rows. connections
+-- that don't set it (migrations, single-bot setups) are the default tenant
+CREATE OR REPLACE FUNCTION hl_tenant() RETURNS TEXT
+    LANGUAGE sql STABLE
+    AS $$ SELECT COALESCE(NULLIF(current_setting('hl.tenant', true), ''), 'default') $$;
+
+DO $$
+DECLARE
+    tbl TEXT;
+BEGIN
+    FOREACH tbl IN ARRAY ARRAY[
+        'user_subscriptions',
+        'user_settings',
+        'imbalance_alerts',
+        'trade_history',
+        'notification_log',
+        'linked_accounts',
+        'watched_wallets',
+        'destinations',
+        'vwap_alerts',
+        'volatility_alerts',
+        'user_roles',
+        'banned_users',
+        'wallet_snapshots',
+        'wallet_snapshot_positions',
+        'ticker_messages',
+        'chat_settings',
+        'metrics_snapshots',
+        'metrics_snapshot_coins',
+        'trade_daily_rollups'
+    ] LOOP
+        EXECUTE format('ALTER TABLE %I ADD COLUMN IF NOT EXISTS tenant_id TEXT NOT NULL DEFAULT hl_tenant()', tbl);
+        EXECUTE format('ALTER TABLE %I ENABLE ROW LEVEL SECURITY', tbl);
+        -- the bot's role usually owns the tables, and owners skip policies unless forced
+        EXECUTE format('ALTER TABLE %I FORCE ROW LEVEL SECURITY', tbl);
+        EXECUTE format('DROP POLICY IF EXISTS tenant_isolation ON %I', tbl);
+        EXECUTE format('CREATE POLICY tenant_isolation ON %I USING (tenant_id = hl_tenant())', tbl);
+    END LOOP;
+END $$;
+
+-- natural keys are per tenant now: the same telegram user can use both bots
+ALTER TABLE user_subscriptions
+    DROP CONSTRAINT IF EXISTS user_subscriptions_telegram_user_id_coin_key,
+    ADD CONSTRAINT user_subscriptions_tenant_user_coin_key UNIQUE (tenant_id, telegram_user_id, coin);
+ALTER TABLE user_settings DROP CONSTRAINT user_settings_pkey, ADD PRIMARY KEY (tenant_id, telegram_user_id);
+ALTER TABLE imbalance_alerts DROP CONSTRAINT imbalance_alerts_pkey, ADD PRIMARY KEY (tenant_id, telegram_user_id, coin);
+ALTER TABLE linked_accounts DROP CONSTRAINT linked_accounts_pkey, ADD PRIMARY KEY (tenant_id, telegram_user_id, address);
+ALTER TABLE watched_wallets DROP CONSTRAINT watched_wallets_pkey, ADD PRIMARY KEY (tenant_id, telegram_user_id, address);
+ALTER TABLE vwap_alerts DROP CONSTRAINT vwap_alerts_pkey, ADD PRIMARY KEY (tenant_id, telegram_user_id, coin);
+ALTER TABLE volatility_alerts DROP CONSTRAINT volatility_alerts_pkey, ADD PRIMARY KEY (tenant_id, telegram_user_id, coin);
+ALTER TABLE user_roles DROP CONSTRAINT user_roles_pkey, ADD PRIMARY KEY (tenant_id, telegram_user_id);
+ALTER TABLE banned_users DROP CONSTRAINT banned_users_pkey, ADD PRIMARY KEY (tenant_id, telegram_user_id);
+ALTER TABLE ticker_messages DROP CONSTRAINT ticker_messages_pkey, ADD PRIMARY KEY (tenant_id, chat_id, coin);
+ALTER TABLE chat_settings DROP CONSTRAINT chat_settings_pkey, ADD PRIMARY KEY (tenant_id, chat_id);
+ALTER TABLE trade_daily_rollups DROP CONSTRAINT trade_daily_rollups_pkey, ADD PRIMARY KEY (tenant_id, day, coin);
@@ -0,0 +1,24 @@
+-- /pricealert thresholds; repeat_secs set makes one re-arm after a cooldown
+-- instead of being removed when it fires
+CREATE TABLE IF NOT EXISTS price_alerts (
+    id BIGSERIAL PRIMARY KEY,
+    telegram_user_id BIGINT NOT NULL,
+    telegram_chat_id BIGINT NOT NULL,
+    coin TEXT NOT NULL,
+    above BOOLEAN NOT NULL,
+    price DOUBLE PRECISION NOT NULL,
+    repeat_secs BIGINT,
+    -- armed, cooling or rearming; see price_alerts.rs
+    state TEXT NOT NULL DEFAULT 'armed',
+    fired_at TIMESTAMPTZ,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    tenant_id TEXT NOT NULL DEFAULT hl_tenant()
+);
+
+CREATE INDEX IF NOT EXISTS price_alerts_coin_idx ON price_alerts (coin);
+CREATE INDEX IF NOT EXISTS price_alerts_user_idx ON price_alerts (telegram_user_id);
+
+ALTER TABLE price_alerts ENABLE ROW LEVEL SECURITY;
+ALTER TABLE price_alerts FORCE ROW LEVEL SECURITY;
+DROP POLICY IF EXISTS tenant_isolation ON price_alerts;
+CREATE POLICY tenant_isolation ON price_alerts USING (tenant_id = hl_tenant());
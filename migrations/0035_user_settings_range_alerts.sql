@@ -0,0 +1,2 @@
+-- opt-in alerts when a subscribed coin breaks its rolling 30d high or low
+ALTER TABLE user_settings ADD COLUMN IF NOT EXISTS range_alerts BOOLEAN;
@@ -0,0 +1,2 @@
+-- /digestmode: trade alerts are held and sent as one message every this many minutes
+ALTER TABLE user_settings ADD COLUMN IF NOT EXISTS digest_mins INTEGER;
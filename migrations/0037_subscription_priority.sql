@@ -0,0 +1,2 @@
+-- /priority coins skip digests, tickers and the daily cap, and jump the send queue
+ALTER TABLE user_subscriptions ADD COLUMN IF NOT EXISTS priority BOOLEAN NOT NULL DEFAULT FALSE;
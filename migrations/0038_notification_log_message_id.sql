@@ -0,0 +1,6 @@
+-- the telegram message each alert went out as, so follow-ups can edit or reply to it
+ALTER TABLE notification_log ADD COLUMN IF NOT EXISTS message_id INTEGER;
+
+CREATE INDEX IF NOT EXISTS notification_log_chat_coin_idx
+    ON notification_log (telegram_chat_id, coin, created_at DESC)
+    WHERE message_id IS NOT NULL;
@@ -0,0 +1,18 @@
+-- /mute filters, applied to every coin after the per-coin thresholds: all of
+-- `keywords` (space separated, lowercase) have to appear in the alert text and,
+-- when set, the trade has to be under `under_usd`
+CREATE TABLE IF NOT EXISTS mute_rules (
+    id BIGSERIAL PRIMARY KEY,
+    telegram_user_id BIGINT NOT NULL,
+    keywords TEXT NOT NULL DEFAULT '',
+    under_usd DOUBLE PRECISION,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    tenant_id TEXT NOT NULL DEFAULT hl_tenant()
+);
+
+CREATE INDEX IF NOT EXISTS mute_rules_user_idx ON mute_rules (telegram_user_id);
+
+ALTER TABLE mute_rules ENABLE ROW LEVEL SECURITY;
+ALTER TABLE mute_rules FORCE ROW LEVEL SECURITY;
+DROP POLICY IF EXISTS tenant_isolation ON mute_rules;
+CREATE POLICY tenant_isolation ON mute_rules USING (tenant_id = hl_tenant());
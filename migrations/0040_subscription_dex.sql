@@ -0,0 +1,6 @@
+-- builder-deployed perp markets are subscribed as `DEX:COIN`; the dex is kept
+-- alongside, derived from the coin so it can't drift from it
+ALTER TABLE user_subscriptions ADD COLUMN IF NOT EXISTS dex TEXT
+    GENERATED ALWAYS AS (
+        CASE WHEN position(':' IN coin) > 0 THEN lower(split_part(coin, ':', 1)) END
+    ) STORED;
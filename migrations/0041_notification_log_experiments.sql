@@ -0,0 +1,9 @@
+-- the alert format experiment and variant each alert went out under, and the
+-- taps on its buttons, which /experiment compares
+ALTER TABLE notification_log ADD COLUMN IF NOT EXISTS experiment TEXT;
+ALTER TABLE notification_log ADD COLUMN IF NOT EXISTS variant TEXT;
+ALTER TABLE notification_log ADD COLUMN IF NOT EXISTS taps INTEGER NOT NULL DEFAULT 0;
+
+CREATE INDEX IF NOT EXISTS notification_log_experiment_idx
+    ON notification_log (experiment, variant)
+    WHERE experiment IS NOT NULL;
@@ -0,0 +1,2 @@
+-- set by an alert's "Mute 1h" button: the subscription sits out the fan-out until then
+ALTER TABLE user_subscriptions ADD COLUMN IF NOT EXISTS muted_until TIMESTAMPTZ;
@@ -0,0 +1,4 @@
+-- a group or channel's own lines above and below its alerts, and their emoji
+ALTER TABLE chat_settings ADD COLUMN IF NOT EXISTS alert_prefix TEXT;
+ALTER TABLE chat_settings ADD COLUMN IF NOT EXISTS alert_suffix TEXT;
+ALTER TABLE chat_settings ADD COLUMN IF NOT EXISTS emoji_style TEXT NOT NULL DEFAULT 'off';
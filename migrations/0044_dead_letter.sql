@@ -0,0 +1,29 @@
+-- alerts that used up their retries, with what's needed to send them again;
+-- /deadletters lists, reprocesses or purges them
+CREATE TABLE IF NOT EXISTS dead_letter (
+    id BIGSERIAL PRIMARY KEY,
+    telegram_user_id BIGINT NOT NULL,
+    telegram_chat_id BIGINT,
+    webhook_url TEXT,
+    coin TEXT NOT NULL,
+    side TEXT NOT NULL,
+    px TEXT NOT NULL,
+    sz TEXT NOT NULL,
+    trade_time TIMESTAMPTZ,
+    tid BIGINT,
+    hash TEXT,
+    notional_usd DOUBLE PRECISION NOT NULL,
+    trade_key BIGINT NOT NULL,
+    priority BOOLEAN NOT NULL DEFAULT FALSE,
+    attempts INTEGER NOT NULL,
+    error TEXT NOT NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    tenant_id TEXT NOT NULL DEFAULT hl_tenant()
+);
+
+CREATE INDEX IF NOT EXISTS dead_letter_user_idx ON dead_letter (telegram_user_id);
+
+ALTER TABLE dead_letter ENABLE ROW LEVEL SECURITY;
+ALTER TABLE dead_letter FORCE ROW LEVEL SECURITY;
+DROP POLICY IF EXISTS tenant_isolation ON dead_letter;
+CREATE POLICY tenant_isolation ON dead_letter USING (tenant_id = hl_tenant());
@@ -0,0 +1,2 @@
+-- opt-in alerts when a subscribed coin's mark price strays from its oracle price
+ALTER TABLE user_settings ADD COLUMN IF NOT EXISTS divergence_alerts BOOLEAN;
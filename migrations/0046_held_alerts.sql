@@ -0,0 +1,25 @@
+-- alerts held for a /digestmode digest that hasn't gone out yet, so a restart
+-- doesn't lose them; rows go once the digest is sent or dead-lettered
+CREATE TABLE IF NOT EXISTS held_alerts (
+    id BIGSERIAL PRIMARY KEY,
+    telegram_user_id BIGINT NOT NULL,
+    telegram_chat_id BIGINT NOT NULL,
+    coin TEXT NOT NULL,
+    side TEXT NOT NULL,
+    px TEXT NOT NULL,
+    sz TEXT NOT NULL,
+    trade_time TIMESTAMPTZ,
+    tid BIGINT,
+    hash TEXT,
+    notional_usd DOUBLE PRECISION NOT NULL,
+    trade_key BIGINT NOT NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    tenant_id TEXT NOT NULL DEFAULT hl_tenant()
+);
+
+CREATE INDEX IF NOT EXISTS held_alerts_user_idx ON held_alerts (telegram_user_id);
+
+ALTER TABLE held_alerts ENABLE ROW LEVEL SECURITY;
+ALTER TABLE held_alerts FORCE ROW LEVEL SECURITY;
+DROP POLICY IF EXISTS tenant_isolation ON held_alerts;
+CREATE POLICY tenant_isolation ON held_alerts USING (tenant_id = hl_tenant());
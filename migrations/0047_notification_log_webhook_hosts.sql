@@ -0,0 +1,5 @@
+-- the log only needs to know which webhook an alert went to; the path and
+-- query of a webhook url usually carry its token, so keep just scheme and host
+UPDATE notification_log
+SET webhook_url = regexp_replace(webhook_url, '^([a-zA-Z][a-zA-Z0-9+.-]*://)([^/?#@]*@)?([^/?#:]+).*$', '\1\3/…')
+WHERE webhook_url IS NOT NULL AND webhook_url NOT LIKE '%/…';
@@ -0,0 +1,200 @@
+use std::collections::{HashMap, VecDeque};
+use std::sync::{Arc, Mutex};
+use tokio::time::Duration;
+use crate::hyperliquid::CoinSymbol;
+
+const BUCKET_SECS: i64 = 5 * 60;
+const WINDOW_BUCKETS: i64 = 24 * 60 * 60 / BUCKET_SECS;
+
+// weight of the newest 5 minute bucket in the smoothed hourly pace; about a
+// 15 minute half-life
+const PACE_ALPHA: f64 = 0.2;
+
+#[derive(Debug, Clone, Copy)]
+struct Bucket {
+    slot: i64,
+    volume_usd: f64,
+    trades: u64,
+}
+
+#[derive(Debug, Default)]
+struct CoinActivity {
+    buckets: VecDeque<Bucket>,
+    first_slot: Option<i64>,
+    // unix secs the feed went down at, while it's down
+    down_since: Option<i64>,
+    // (from, to) unix secs of outages inside the window, oldest first
+    gaps: VecDeque<(i64, i64)>,
+    // volume/hour as of the end of the last closed bucket
+    pace_per_hour: f64,
+    // since startup, for the persisted metrics
+    total_volume_usd: f64,
+    total_trades: u64,
+}
+
+impl CoinActivity {
+    fn record(&mut self, slot: i64, volume_usd: f64, trades: u64) {
+        self.first_slot.get_or_insert(slot);
+        self.total_volume_usd += volume_usd;
+        self.total_trades += trades;
+        match self.buckets.back_mut() {
+            Some(bucket) if bucket.slot == slot => {
+                bucket.volume_usd += volume_usd;
+                bucket.trades += trades;
+                return;
+            }
+            Some(bucket) => {
+                let closed = *bucket;
+                self.pace_per_hour = smoothed(self.pace_per_hour, closed, slot);
+            }
+            None => {}
+        }
+        self.buckets.push_back(Bucket { slot, volume_usd, trades });
+        while self.buckets.front().is_some_and(|bucket| bucket.slot <= slot - WINDOW_BUCKETS) {
+            self.buckets.pop_front();
+        }
+    }
+}
+
+impl CoinActivity {
+    // seconds of the window from `from` to `now` the feed was up for
+    fn covered_secs(&self, from: i64, now: i64) -> i64 {
+        let down: i64 = self
+            .gaps
+            .iter()
+            .copied()
+            .chain(self.down_since.map(|since| (since, now)))
+            .map(|(start, end)| (end.min(now) - start.max(from)).max(0))
+            .sum();
+        (now - from - down).max(0)
+    }
+}
+
+// folds a closed bucket into the pace, then decays it over the quiet buckets
+// up to (not including) `slot`
+fn smoothed(pace_per_hour: f64, closed: Bucket, slot: i64) -> f64 {
+    let buckets_per_hour = (3600 / BUCKET_SECS) as f64;
+    let pace = PACE_ALPHA * closed.volume_usd * buckets_per_hour + (1.0 - PACE_ALPHA) * pace_per_hour;
+    let quiet = (slot - closed.slot - 1).clamp(0, WINDOW_BUCKETS) as i32;
+    pace * (1.0 - PACE_ALPHA).powi(quiet)
+}
+
+/// What the live trade feed has seen of a coin over the last 24h.
+#[derive(Debug, Clone, Copy)]
+pub struct LiveActivity {
+    pub volume_usd: f64,
+    pub trades: u64,
+    /// How much of the 24h the feed has been up for; less after a restart,
+    /// a fresh subscription or an outage.
+    pub covered: Duration,
+    /// Exponentially smoothed volume per hour, for how busy it is right now.
+    pub pace_per_hour: f64,
+}
+
+impl LiveActivity {
+    pub fn covers_day(&self) -> bool {
+        self.covered >= Duration::from_secs((24 * 60 * 60 - BUCKET_SECS) as u64)
+    }
+
+    /// "24h", or "3h" while the window is still filling.
+    pub fn window_text(&self) -> String {
+        let hours = if self.covers_day() { 24 } else { (self.covered.as_secs() / 3600).max(1) };
+        format!("{}h", hours)
+    }
+}
+
+/// Rolling 24h volume and trade counts per coin from the trade feeds, every
+/// fill included, so figures stay current when the REST contexts lag.
+#[derive(Clone, Default)]
+pub struct ActivityTracker {
+    coins: Arc<Mutex<HashMap<CoinSymbol, CoinActivity>>>,
+}
+
+impl std::fmt::Debug for ActivityTracker {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ActivityTracker").finish_non_exhaustive()
+    }
+}
+
+impl ActivityTracker {
+    pub fn new() -> Self {
+        ActivityTracker::default()
+    }
+
+    /// Adds a frame's worth of fills for `coin`.
+    pub fn record(&self, coin: &str, volume_usd: f64, trades: u64) {
+        let slot = chrono::Utc::now().timestamp() / BUCKET_SECS;
+        let Ok(mut coins) = self.coins.lock() else {
+            return;
+        };
+        let activity = match coins.get_mut(coin) {
+            Some(activity) => activity,
+            None => coins.entry(CoinSymbol::new(coin)).or_default(),
+        };
+        activity.record(slot, volume_usd, trades);
+    }
+
+    /// The coin's feed is subscribed and receiving. Coverage counts from the
+    /// first time this is said, and resumes after an outage.
+    pub fn feed_up(&self, coin: &CoinSymbol) {
+        let now = chrono::Utc::now().timestamp();
+        let Ok(mut coins) = self.coins.lock() else {
+            return;
+        };
+        let activity = coins.entry(coin.clone()).or_default();
+        activity.first_slot.get_or_insert(now / BUCKET_SECS);
+        if let Some(since) = activity.down_since.take() {
+            activity.gaps.push_back((since, now));
+        }
+        while activity.gaps.front().is_some_and(|(_, end)| *end <= now - WINDOW_BUCKETS * BUCKET_SECS) {
+            activity.gaps.pop_front();
+        }
+    }
+
+    /// The coin's feed lost its connection or was unsubscribed; until it's
+    /// back, a quiet spell isn't counted as covered.
+    pub fn feed_down(&self, coin: &CoinSymbol) {
+        let now = chrono::Utc::now().timestamp();
+        let Ok(mut coins) = self.coins.lock() else {
+            return;
+        };
+        if let Some(activity) = coins.get_mut(coin) {
+            activity.down_since.get_or_insert(now);
+        }
+    }
+
+    /// Running (trades, volume) per coin since startup.
+    pub fn totals(&self) -> HashMap<CoinSymbol, (u64, f64)> {
+        let Ok(coins) = self.coins.lock() else {
+            return HashMap::new();
+        };
+        coins
+            .iter()
+            .map(|(coin, activity)| (coin.clone(), (activity.total_trades, activity.total_volume_usd)))
+            .collect()
+    }
+
+    pub fn get(&self, coin: &str) -> Option<LiveActivity> {
+        let now = chrono::Utc::now().timestamp();
+        let slot = now / BUCKET_SECS;
+        let coins = self.coins.lock().ok()?;
+        let activity = coins.get(coin)?;
+        let first_slot = activity.first_slot?;
+
+        let live: Vec<&Bucket> = activity.buckets.iter().filter(|bucket| bucket.slot > slot - WINDOW_BUCKETS).collect();
+        // the open bucket only counts toward the pace once it closes
+        let pace_per_hour = match activity.buckets.back() {
+            Some(last) if last.slot < slot => smoothed(activity.pace_per_hour, *last, slot),
+            _ => activity.pace_per_hour,
+        };
+        let from = (first_slot * BUCKET_SECS).max(now - WINDOW_BUCKETS * BUCKET_SECS);
+        let covered_secs = activity.covered_secs(from, now);
+
+        Some(LiveActivity {
+            volume_usd: live.iter().map(|bucket| bucket.volume_usd).sum(),
+            trades: live.iter().map(|bucket| bucket.trades).sum(),
+            covered: Duration::from_secs(covered_secs as u64),
+            pace_per_hour,
+        })
+    }
+}
@@ -0,0 +1,228 @@
+use anyhow::Result;
+use std::collections::{hash_map::Entry, BTreeMap, HashMap};
+use std::sync::Arc;
+use tokio::sync::Mutex;
+use tokio::time::{Duration, Instant};
+use tracing::{info, warn};
+use crate::{
+    database::{Database, HeldAlert, UserSettings},
+    delivery::PendingAlert,
+    formatting,
+    hyperliquid::CoinSymbol,
+};
+
+#[derive(Default)]
+struct CoinTotals {
+    buys: u32,
+    buy_usd: f64,
+    sells: u32,
+    sell_usd: f64,
+    largest: Option<(bool, f64, String)>,
+}
+
+/// One user's alerts for one chat, waiting for their digest. Each alert
+/// keeps the id of its held_alerts row.
+pub struct HeldAlerts {
+    pub telegram_user_id: i64,
+    pub chat_id: i64,
+    started: Instant,
+    every: Duration,
+    full_precision: bool,
+    pub alerts: Vec<(i64, PendingAlert)>,
+    /// Sends of this digest that have failed so far.
+    pub failed_sends: u32,
+}
+
+impl HeldAlerts {
+    pub fn text(&self) -> String {
+        let usd = |value: f64| formatting::format_usd(value, self.full_precision);
+
+        let mut coins: BTreeMap<&CoinSymbol, CoinTotals> = BTreeMap::new();
+        for (_, alert) in &self.alerts {
+            let (trade, notional_usd) = (&alert.trade, alert.notional_usd);
+            let buy = trade.side == "B";
+            let totals = coins.entry(&trade.coin).or_default();
+            if buy {
+                totals.buys += 1;
+                totals.buy_usd += notional_usd;
+            } else {
+                totals.sells += 1;
+                totals.sell_usd += notional_usd;
+            }
+            if totals.largest.as_ref().is_none_or(|(_, largest, _)| notional_usd > *largest) {
+                totals.largest = Some((buy, notional_usd, trade.px.clone()));
+            }
+        }
+
+        // busiest coin first
+        let mut coins: Vec<(&CoinSymbol, CoinTotals)> = coins.into_iter().collect();
+        coins.sort_by(|(_, a), (_, b)| (b.buy_usd + b.sell_usd).total_cmp(&(a.buy_usd + a.sell_usd)));
+
+        let mut text = format!("Alert Digest · last {}m", self.every.as_secs() / 60);
+        let mut total_usd = 0.0;
+        for (coin, totals) in &coins {
+            let coin_usd = totals.buy_usd + totals.sell_usd;
+            total_usd += coin_usd;
+            text.push_str(&format!(
+                "\n\n{}: {} alert{}, {}\nBuys: {} ({}) · Sells: {} ({}) · Net: {}",
+                coin,
+                totals.buys + totals.sells,
+                if totals.buys + totals.sells == 1 { "" } else { "s" },
+                usd(coin_usd),
+                usd(totals.buy_usd),
+                totals.buys,
+                usd(totals.sell_usd),
+                totals.sells,
+                formatting::format_signed_usd(totals.buy_usd - totals.sell_usd)
+            ));
+            if let Some((buy, notional_usd, px)) = &totals.largest {
+                text.push_str(&format!(
+                    "\nLargest: {} {} @ ${}",
+                    if *buy { "BUY" } else { "SELL" },
+                    usd(*notional_usd),
+                    formatting::format_price(px)
+                ));
+            }
+        }
+        text.push_str(&format!(
+            "\n\nTotal: {} alerts across {} coin{}, {}",
+            self.alerts.len(),
+            coins.len(),
+            if coins.len() == 1 { "" } else { "s" },
+            usd(total_usd)
+        ));
+        text
+    }
+}
+
+/// Holds trade alerts for users on /digestmode until each user's next
+/// digest, which covers all their coins. Buffers are keyed by user (and the
+/// chat the alerts were headed to) and only grouped by coin when they're
+/// rendered. Held alerts are kept in the held_alerts table too, so a restart
+/// picks them back up; `AlertDelivery` sends the digests and records each
+/// alert as delivered only once its digest is out.
+#[derive(Clone)]
+pub struct AlertDigest {
+    database: Database,
+    held: Arc<Mutex<HashMap<(i64, i64), HeldAlerts>>>,
+}
+
+impl AlertDigest {
+    pub fn new(database: Database) -> Self {
+        AlertDigest {
+            database,
+            held: Arc::new(Mutex::new(HashMap::new())),
+        }
+    }
+
+    /// Puts alerts a previous run was holding back into their buffers, with
+    /// their users' current settings.
+    pub async fn reload(&self) -> Result<usize> {
+        let rows = self.database.get_held_alerts().await?;
+        let mut settings: HashMap<i64, UserSettings> = HashMap::new();
+        for row in &rows {
+            if let Entry::Vacant(entry) = settings.entry(row.telegram_user_id) {
+                entry.insert(self.database.get_user_settings(row.telegram_user_id).await?);
+            }
+        }
+
+        let mut held = self.held.lock().await;
+        for row in &rows {
+            let user_settings = &settings[&row.telegram_user_id];
+            let alerts = buffer(&mut held, row.telegram_user_id, row.telegram_chat_id, user_settings);
+            // digests started before the restart keep their schedule
+            let age = (chrono::Utc::now() - row.created_at).to_std().unwrap_or_default();
+            if let Some(started) = Instant::now().checked_sub(age) {
+                alerts.started = alerts.started.min(started);
+            }
+            alerts.alerts.push((row.id, PendingAlert::from_held(row, user_settings.clone())));
+        }
+        if !rows.is_empty() {
+            info!("picked {} held digest alerts back up", rows.len());
+        }
+        Ok(rows.len())
+    }
+
+    /// Holds the alert until the user's next digest; a changed interval
+    /// applies to the digest already being collected. Fails if the alert
+    /// couldn't be stored, so it can be retried like a failed send.
+    pub async fn record(&self, chat_id: i64, alert: &PendingAlert) -> Result<()> {
+        let trade = &alert.trade;
+        let id = self
+            .database
+            .add_held_alert(&HeldAlert {
+                id: 0,
+                telegram_user_id: alert.telegram_user_id,
+                telegram_chat_id: chat_id,
+                coin: trade.coin.clone(),
+                side: trade.side.clone(),
+                px: trade.px.clone(),
+                sz: trade.sz.clone(),
+                trade_time: trade.time.and_then(chrono::DateTime::from_timestamp_millis),
+                tid: trade.tid.map(|tid| tid as i64),
+                hash: trade.hash.clone(),
+                notional_usd: alert.notional_usd,
+                trade_key: alert.trade_key as i64,
+                created_at: chrono::Utc::now(),
+            })
+            .await?;
+
+        let mut held = self.held.lock().await;
+        let alerts = buffer(&mut held, alert.telegram_user_id, chat_id, &alert.settings);
+        alerts.alerts.push((id, PendingAlert { chart: None, ..alert.clone() }));
+        Ok(())
+    }
+
+    /// Takes every buffer whose interval is up, for sending.
+    pub async fn take_due(&self) -> Vec<HeldAlerts> {
+        let mut held = self.held.lock().await;
+        let keys: Vec<(i64, i64)> = held
+            .iter()
+            .filter(|(_, alerts)| alerts.started.elapsed() >= alerts.every)
+            .map(|(key, _)| *key)
+            .collect();
+        keys.into_iter().filter_map(|key| held.remove(&key)).collect()
+    }
+
+    /// Puts back a digest that couldn't be sent, ahead of anything held since.
+    pub async fn hold_again(&self, mut alerts: HeldAlerts) {
+        let mut held = self.held.lock().await;
+        if let Some(newer) = held.remove(&(alerts.telegram_user_id, alerts.chat_id)) {
+            alerts.every = newer.every;
+            alerts.full_precision = newer.full_precision;
+            alerts.alerts.extend(newer.alerts);
+        }
+        held.insert((alerts.telegram_user_id, alerts.chat_id), alerts);
+    }
+
+    /// Forgets the stored copies of alerts whose digest is done with.
+    pub async fn release(&self, alerts: &HeldAlerts) {
+        let ids: Vec<i64> = alerts.alerts.iter().map(|(id, _)| *id).collect();
+        if let Err(e) = self.database.remove_held_alerts(&ids).await {
+            warn!(
+                "couldn't clear held alerts for user {}, they may be sent again after a restart: {}",
+                alerts.telegram_user_id, e
+            );
+        }
+    }
+}
+
+fn buffer<'a>(
+    held: &'a mut HashMap<(i64, i64), HeldAlerts>,
+    telegram_user_id: i64,
+    chat_id: i64,
+    settings: &UserSettings,
+) -> &'a mut HeldAlerts {
+    let alerts = held.entry((telegram_user_id, chat_id)).or_insert_with(|| HeldAlerts {
+        telegram_user_id,
+        chat_id,
+        started: Instant::now(),
+        every: Duration::ZERO,
+        full_precision: false,
+        alerts: Vec::new(),
+        failed_sends: 0,
+    });
+    alerts.every = Duration::from_secs(settings.digest_mins.unwrap_or(0) as u64 * 60);
+    alerts.full_precision = settings.full_precision;
+    alerts
+}
@@ -0,0 +1,91 @@
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::BTreeMap;
+use tracing::info;
+use crate::database::Database;
+
+const ARCHIVE_VERSION: u32 = 1;
+
+// user-owned state, in an order that restores cleanly; trade_history and
+// notification_log are logs that rebuild themselves and can run to millions of rows
+const ARCHIVED_TABLES: &[&str] = &[
+    "user_subscriptions",
+    "user_settings",
+    "imbalance_alerts",
+    "vwap_alerts",
+    "volatility_alerts",
+    "price_alerts",
+    "linked_accounts",
+    "watched_wallets",
+    "destinations",
+    "mute_rules",
+    "chat_settings",
+    "user_roles",
+    "banned_users",
+];
+
+/// Portable dump of the bot's tables, written by `backup` and read by `restore`.
+#[derive(Debug, Serialize, Deserialize)]
+struct Archive {
+    version: u32,
+    created_at: chrono::DateTime<chrono::Utc>,
+    tables: BTreeMap<String, Vec<Value>>,
+}
+
+/// Handles `hl-tg-bot backup <file>` and `hl-tg-bot restore <file>`. Returns
+/// false when the arguments aren't a subcommand, so the bot should start.
+pub async fn run_subcommand(database: &Database, args: &[String]) -> Result<bool> {
+    match args {
+        [command, path] if command == "backup" => backup(database, path).await?,
+        [command, path] if command == "restore" => restore(database, path).await?,
+        [command, ..] if command == "backup" || command == "restore" => {
+            anyhow::bail!("usage: hl-tg-bot {} <file>", command)
+        }
+        _ => return Ok(false),
+    }
+    Ok(true)
+}
+
+async fn backup(database: &Database, path: &str) -> Result<()> {
+    let mut tables = BTreeMap::new();
+    for table in ARCHIVED_TABLES {
+        let rows = database.dump_table(table).await?;
+        info!("backed up {} rows from {}", rows.len(), table);
+        tables.insert(table.to_string(), rows);
+    }
+
+    let archive = Archive {
+        version: ARCHIVE_VERSION,
+        created_at: chrono::Utc::now(),
+        tables,
+    };
+    tokio::fs::write(path, serde_json::to_vec_pretty(&archive)?)
+        .await
+        .with_context(|| format!("couldn't write backup to {}", path))?;
+    info!("backup written to {}", path);
+    Ok(())
+}
+
+async fn restore(database: &Database, path: &str) -> Result<()> {
+    let contents = tokio::fs::read(path)
+        .await
+        .with_context(|| format!("couldn't read backup {}", path))?;
+    let mut archive: Archive = serde_json::from_slice(&contents).with_context(|| format!("{} isn't a backup archive", path))?;
+    if archive.version > ARCHIVE_VERSION {
+        anyhow::bail!("{} was written by a newer version (archive v{})", path, archive.version);
+    }
+    if let Some(unknown) = archive.tables.keys().find(|table| !ARCHIVED_TABLES.contains(&table.as_str())) {
+        anyhow::bail!("{} has an unknown table {}", path, unknown);
+    }
+
+    let tables: Vec<(&str, Vec<Value>)> = ARCHIVED_TABLES
+        .iter()
+        .filter_map(|table| Some((*table, archive.tables.remove(*table)?)))
+        .collect();
+    for (table, inserted) in database.restore_tables(&tables).await? {
+        info!("restored {} rows into {}", inserted, table);
+    }
+    info!("restore from {} (taken {}) complete", path, archive.created_at);
+    Ok(())
+}
@@ -0,0 +1,242 @@
+use anyhow::{Context, Result};
+use chrono::{DateTime, NaiveDate, Utc};
+use serde::Deserialize;
+use std::collections::{BTreeMap, HashSet};
+use std::fs::File;
+use std::io::{BufRead, BufReader};
+use std::path::{Path, PathBuf};
+use tracing::{info, warn};
+use crate::{config::Config, database::Database, formatting, hyperliquid::WsTrade};
+
+const USAGE: &str = "usage: hl-tg-bot backtest <telegram_user_id> [--min-usd 100000,250000] [--days 7] [recording files or dirs...]";
+
+// history only goes back as far as anyone cares to tune against
+const DEFAULT_DAYS: i64 = 7;
+
+#[derive(Deserialize)]
+struct RecordedFrame {
+    channel: String,
+    data: Vec<WsTrade>,
+}
+
+// one would-be alert candidate: a trade, or a taker order's merged fills
+struct Candidate {
+    coin: String,
+    day: NaiveDate,
+    notional_usd: f64,
+}
+
+struct BacktestArgs {
+    telegram_user_id: i64,
+    thresholds: Vec<f64>,
+    days: i64,
+    paths: Vec<PathBuf>,
+}
+
+/// Handles `hl-tg-bot backtest ...`: replays trades against a user's
+/// subscriptions and prints how many alerts each threshold would have sent
+/// per day. Returns false when the arguments aren't this subcommand.
+pub async fn run_subcommand(config: &Config, database: &Database, args: &[String]) -> Result<bool> {
+    let Some((command, rest)) = args.split_first() else {
+        return Ok(false);
+    };
+    if command != "backtest" {
+        return Ok(false);
+    }
+
+    let args = parse_args(config, rest)?;
+    let coins = database.get_user_subscriptions(args.telegram_user_id).await?;
+    if coins.is_empty() {
+        anyhow::bail!("user {} has no subscriptions to backtest", args.telegram_user_id);
+    }
+    let subscribed: HashSet<String> = coins.iter().map(|coin| coin.to_uppercase()).collect();
+
+    let candidates = if args.paths.is_empty() {
+        // the live bot only records trades over its own minimum
+        let live_min = config.defaults.min_trade_value_usd;
+        if args.thresholds.iter().any(|threshold| *threshold < live_min) {
+            warn!(
+                "trade history only holds trades over {}, lower thresholds will undercount; pass recordings instead",
+                formatting::format_usd(live_min, false)
+            );
+        }
+        database
+            .get_recorded_trades(&coins, args.days)
+            .await?
+            .into_iter()
+            .map(|(coin, traded_at, notional_usd)| Candidate {
+                coin,
+                day: traded_at.date_naive(),
+                notional_usd,
+            })
+            .collect()
+    } else {
+        let merge_fills = config.clustering.window_ms > 0;
+        let mut candidates = Vec::new();
+        for path in recording_files(&args.paths)? {
+            let before = candidates.len();
+            read_recording(&path, merge_fills, &subscribed, &mut candidates)?;
+            info!("read {} trades from {}", candidates.len() - before, path.display());
+        }
+        candidates
+    };
+
+    println!("{}", report(&args.thresholds, &candidates));
+    Ok(true)
+}
+
+fn parse_args(config: &Config, args: &[String]) -> Result<BacktestArgs> {
+    let Some((user, mut rest)) = args.split_first() else {
+        anyhow::bail!(USAGE);
+    };
+    let telegram_user_id = user.parse().context(USAGE)?;
+    let mut thresholds = vec![config.defaults.min_trade_value_usd];
+    let mut days = DEFAULT_DAYS;
+    let mut paths = Vec::new();
+
+    while let Some((arg, tail)) = rest.split_first() {
+        match (arg.as_str(), tail.first()) {
+            ("--min-usd", Some(value)) => {
+                thresholds = value
+                    .split(',')
+                    .map(|threshold| threshold.trim().parse::<f64>())
+                    .collect::<Result<_, _>>()
+                    .context(USAGE)?;
+                rest = &tail[1..];
+            }
+            ("--days", Some(value)) => {
+                days = value.parse().context(USAGE)?;
+                rest = &tail[1..];
+            }
+            (flag, _) if flag.starts_with("--") => anyhow::bail!(USAGE),
+            (path, _) => {
+                paths.push(PathBuf::from(path));
+                rest = tail;
+            }
+        }
+    }
+
+    thresholds.sort_by(f64::total_cmp);
+    Ok(BacktestArgs {
+        telegram_user_id,
+        thresholds,
+        days,
+        paths,
+    })
+}
+
+// directories are expanded to the recordings inside them, oldest first
+fn recording_files(paths: &[PathBuf]) -> Result<Vec<PathBuf>> {
+    let mut files = Vec::new();
+    for path in paths {
+        if !path.is_dir() {
+            files.push(path.clone());
+            continue;
+        }
+        let mut recordings: Vec<PathBuf> = std::fs::read_dir(path)
+            .with_context(|| format!("couldn't read {}", path.display()))?
+            .filter_map(|entry| Some(entry.ok()?.path()))
+            .filter(|file| file.to_string_lossy().ends_with(".jsonl.zst"))
+            .collect();
+        recordings.sort();
+        files.extend(recordings);
+    }
+    Ok(files)
+}
+
+fn read_recording(path: &Path, merge_fills: bool, subscribed: &HashSet<String>, candidates: &mut Vec<Candidate>) -> Result<()> {
+    let file = File::open(path).with_context(|| format!("couldn't open {}", path.display()))?;
+    let reader = BufReader::new(zstd::Decoder::new(file)?);
+
+    for line in reader.lines() {
+        // a recording cut off mid-write still has everything before the break
+        let line = match line {
+            Ok(line) => line,
+            Err(e) => {
+                warn!("stopped reading {} early: {}", path.display(), e);
+                break;
+            }
+        };
+        let Ok(frame) = serde_json::from_str::<RecordedFrame>(&line) else {
+            continue;
+        };
+        if frame.channel != "trades" {
+            continue;
+        }
+
+        // same rule the live feed applies: a taker order's fills count together
+        let orders: Vec<&[WsTrade]> = if merge_fills {
+            frame
+                .data
+                .chunk_by(|a, b| a.side == b.side && a.taker().is_some_and(|taker| Some(taker) == b.taker()))
+                .collect()
+        } else {
+            frame.data.chunks(1).collect()
+        };
+
+        for order in orders {
+            let Some(first) = order.first() else {
+                continue;
+            };
+            let coin = first.coin.to_uppercase();
+            if !subscribed.contains(&coin) {
+                continue;
+            }
+            let Some(day) = first.time.and_then(DateTime::<Utc>::from_timestamp_millis).map(|time| time.date_naive()) else {
+                continue;
+            };
+            candidates.push(Candidate {
+                coin,
+                day,
+                notional_usd: order.iter().filter_map(|trade| trade.notional_usd().ok()).sum(),
+            });
+        }
+    }
+    Ok(())
+}
+
+fn report(thresholds: &[f64], candidates: &[Candidate]) -> String {
+    // day -> alerts at each threshold
+    let mut per_day: BTreeMap<NaiveDate, Vec<usize>> = BTreeMap::new();
+    let mut per_coin: BTreeMap<&str, Vec<usize>> = BTreeMap::new();
+    for candidate in candidates {
+        let day = per_day.entry(candidate.day).or_insert_with(|| vec![0; thresholds.len()]);
+        let coin = per_coin.entry(&candidate.coin).or_insert_with(|| vec![0; thresholds.len()]);
+        for (i, threshold) in thresholds.iter().enumerate() {
+            if candidate.notional_usd >= *threshold {
+                day[i] += 1;
+                coin[i] += 1;
+            }
+        }
+    }
+
+    if per_day.is_empty() {
+        return "No trades to backtest.".to_string();
+    }
+
+    let header: Vec<String> = thresholds.iter().map(|threshold| format!(">={}", formatting::format_usd(*threshold, false))).collect();
+    let row = |label: &str, counts: &[usize]| {
+        let counts: Vec<String> = counts.iter().map(|count| format!("{:>10}", count)).collect();
+        format!("{:<12}{}", label, counts.join(""))
+    };
+
+    let mut lines = vec![format!("{:<12}{}", "", header.iter().map(|h| format!("{:>10}", h)).collect::<String>())];
+    for (day, counts) in &per_day {
+        lines.push(row(&day.to_string(), counts));
+    }
+
+    lines.push(String::new());
+    for (coin, counts) in &per_coin {
+        lines.push(row(coin, counts));
+    }
+
+    let averages: Vec<String> = (0..thresholds.len())
+        .map(|i| {
+            let total: usize = per_day.values().map(|counts| counts[i]).sum();
+            format!("{:>10.1}", total as f64 / per_day.len() as f64)
+        })
+        .collect();
+    lines.push(String::new());
+    lines.push(format!("{:<12}{}", "avg/day", averages.join("")));
+    lines.join("\n")
+}
@@ -0,0 +1,216 @@
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use crate::database::Database;
+
+// bumped if the layout ever changes in a way older files can't be read as
+pub const BACKUP_VERSION: u32 = 1;
+
+/// Everything a user has set up, as written by /export and read back by /import.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct UserBackup {
+    pub version: u32,
+    #[serde(default)]
+    pub subscriptions: Vec<String>,
+    #[serde(default)]
+    pub priority_coins: Vec<String>,
+    #[serde(default)]
+    pub settings: SettingsBackup,
+    #[serde(default)]
+    pub imbalance_alerts: Vec<ThresholdBackup>,
+    #[serde(default)]
+    pub vwap_alerts: Vec<ThresholdBackup>,
+    #[serde(default)]
+    pub volatility_alerts: Vec<VolatilityBackup>,
+    #[serde(default)]
+    pub linked_addresses: Vec<String>,
+    #[serde(default)]
+    pub watched_wallets: Vec<String>,
+    #[serde(default)]
+    pub funding_summary: Option<String>,
+    #[serde(default)]
+    pub weekly_digest_offset_mins: Option<i32>,
+    #[serde(default)]
+    pub destinations: Vec<DestinationBackup>,
+    #[serde(default)]
+    pub mute_rules: Vec<MuteBackup>,
+    #[serde(default)]
+    pub muted_subscriptions: Vec<SubscriptionMuteBackup>,
+    #[serde(default)]
+    pub subscription_thresholds: Vec<SubscriptionThresholdBackup>,
+    #[serde(default)]
+    pub price_alerts: Vec<PriceAlertBackup>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Default)]
+#[serde(default)]
+pub struct SettingsBackup {
+    pub currency: Option<String>,
+    pub full_precision: bool,
+    pub charts_enabled: bool,
+    pub revisit_alerts: bool,
+    pub route_chat_id: Option<i64>,
+    pub duplicate_alerts: Option<String>,
+    pub show_leverage: bool,
+    pub daily_alert_cap: Option<u32>,
+    pub autotune_alerts_per_day: Option<u32>,
+    pub silent_below_usd: Option<f64>,
+    pub ticker_mode: bool,
+    pub heartbeat: bool,
+    pub range_alerts: bool,
+    pub digest_mins: Option<u32>,
+    pub divergence_alerts: bool,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ThresholdBackup {
+    pub coin: String,
+    pub threshold_pct: f64,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct VolatilityBackup {
+    pub coin: String,
+    pub multiple: f64,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct DestinationBackup {
+    pub chat_id: Option<i64>,
+    pub webhook_url: Option<String>,
+    pub coin: Option<String>,
+    pub full_precision: Option<bool>,
+    pub charts_enabled: Option<bool>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct MuteBackup {
+    pub keywords: Vec<String>,
+    pub under_usd: Option<f64>,
+}
+
+/// A subscription's own alert minimum, in USD.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct SubscriptionThresholdBackup {
+    pub coin: String,
+    pub min_notional_usd: f64,
+}
+
+/// A subscription muted from an alert's buttons, until `until`.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct SubscriptionMuteBackup {
+    pub coin: String,
+    pub until: chrono::DateTime<chrono::Utc>,
+}
+
+/// A /pricealert, with `repeat_secs` unset for a one-shot.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct PriceAlertBackup {
+    pub coin: String,
+    pub above: bool,
+    pub price: f64,
+    pub repeat_secs: Option<i64>,
+}
+
+impl UserBackup {
+    pub async fn collect(database: &Database, telegram_user_id: i64) -> Result<Self> {
+        let settings = database.get_user_settings(telegram_user_id).await?;
+
+        Ok(UserBackup {
+            version: BACKUP_VERSION,
+            subscriptions: database.get_user_subscriptions(telegram_user_id).await?,
+            priority_coins: database.get_priority_coins(telegram_user_id).await?,
+            settings: SettingsBackup {
+                currency: settings.currency,
+                full_precision: settings.full_precision,
+                charts_enabled: settings.charts_enabled,
+                revisit_alerts: settings.revisit_alerts,
+                route_chat_id: settings.route_chat_id,
+                duplicate_alerts: Some(settings.duplicate_alerts.as_str().to_string()),
+                show_leverage: settings.show_leverage,
+                daily_alert_cap: settings.daily_alert_cap,
+                autotune_alerts_per_day: settings.autotune_alerts_per_day,
+                silent_below_usd: settings.silent_below_usd,
+                ticker_mode: settings.ticker_mode,
+                heartbeat: settings.heartbeat,
+                range_alerts: settings.range_alerts,
+                digest_mins: settings.digest_mins,
+                divergence_alerts: settings.divergence_alerts,
+            },
+            imbalance_alerts: database
+                .get_user_imbalance_alerts(telegram_user_id)
+                .await?
+                .into_iter()
+                .map(|alert| ThresholdBackup {
+                    coin: alert.coin.to_string(),
+                    threshold_pct: alert.threshold_pct,
+                })
+                .collect(),
+            vwap_alerts: database
+                .get_user_vwap_alerts(telegram_user_id)
+                .await?
+                .into_iter()
+                .map(|alert| ThresholdBackup {
+                    coin: alert.coin.to_string(),
+                    threshold_pct: alert.threshold_pct,
+                })
+                .collect(),
+            volatility_alerts: database
+                .get_user_volatility_alerts(telegram_user_id)
+                .await?
+                .into_iter()
+                .map(|alert| VolatilityBackup {
+                    coin: alert.coin.to_string(),
+                    multiple: alert.multiple,
+                })
+                .collect(),
+            linked_addresses: database.get_linked_accounts(telegram_user_id).await?,
+            watched_wallets: database.get_watched_wallets(telegram_user_id).await?,
+            funding_summary: database.get_funding_summary(telegram_user_id).await?,
+            weekly_digest_offset_mins: database.get_weekly_digest(telegram_user_id).await?,
+            destinations: database
+                .get_user_destinations(telegram_user_id)
+                .await?
+                .into_iter()
+                .map(|destination| DestinationBackup {
+                    chat_id: destination.chat_id,
+                    webhook_url: destination.webhook_url,
+                    coin: destination.coin,
+                    full_precision: destination.full_precision,
+                    charts_enabled: destination.charts_enabled,
+                })
+                .collect(),
+            mute_rules: database
+                .get_user_mute_rules(telegram_user_id)
+                .await?
+                .into_iter()
+                .map(|rule| MuteBackup {
+                    keywords: rule.keywords,
+                    under_usd: rule.under_usd,
+                })
+                .collect(),
+            muted_subscriptions: database
+                .get_subscription_mutes(telegram_user_id)
+                .await?
+                .into_iter()
+                .map(|(coin, until)| SubscriptionMuteBackup { coin, until })
+                .collect(),
+            subscription_thresholds: database
+                .get_subscription_thresholds(telegram_user_id)
+                .await?
+                .into_iter()
+                .map(|(coin, min_notional_usd)| SubscriptionThresholdBackup { coin, min_notional_usd })
+                .collect(),
+            price_alerts: database
+                .get_user_price_alerts(telegram_user_id)
+                .await?
+                .into_iter()
+                .map(|alert| PriceAlertBackup {
+                    coin: alert.coin.to_string(),
+                    above: alert.above,
+                    price: alert.price,
+                    repeat_secs: alert.repeat_secs,
+                })
+                .collect(),
+        })
+    }
+}
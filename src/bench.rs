@@ -0,0 +1,74 @@
+use anyhow::{Context, Result};
+use std::time::Duration;
+use crate::database::Database;
+
+const USAGE: &str = "usage: hl-tg-bot bench-fanout [--rows 100000] [--coins 50] [--iterations 200]";
+
+struct BenchArgs {
+    rows: i64,
+    coins: i64,
+    iterations: usize,
+}
+
+/// Handles `hl-tg-bot bench-fanout ...`: measures subscriber fan-out latency
+/// against a seeded table, inside a transaction that's rolled back afterwards.
+/// Returns false when the arguments aren't this subcommand.
+pub async fn run_subcommand(database: &Database, args: &[String]) -> Result<bool> {
+    let Some((command, rest)) = args.split_first() else {
+        return Ok(false);
+    };
+    if command != "bench-fanout" {
+        return Ok(false);
+    }
+
+    let args = parse_args(rest)?;
+    let result = database.benchmark_fanout(args.rows, args.coins, args.iterations).await?;
+
+    let mut timings = result.timings;
+    timings.sort();
+    let percentile = |pct: usize| timings[(timings.len() * pct / 100).min(timings.len() - 1)];
+    println!(
+        "fan-out over {} subscription rows ({} coins): {} subscribers for the queried coin",
+        args.rows, args.coins, result.subscribers
+    );
+    println!(
+        "{} runs  p50 {}  p95 {}  p99 {}  max {}",
+        timings.len(),
+        millis(percentile(50)),
+        millis(percentile(95)),
+        millis(percentile(99)),
+        millis(timings[timings.len() - 1])
+    );
+    println!("\n{}", result.plan.join("\n"));
+    Ok(true)
+}
+
+fn millis(duration: Duration) -> String {
+    format!("{:.2}ms", duration.as_secs_f64() * 1000.0)
+}
+
+fn parse_args(args: &[String]) -> Result<BenchArgs> {
+    let mut parsed = BenchArgs {
+        rows: 100_000,
+        coins: 50,
+        iterations: 200,
+    };
+
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        let value = args.next().with_context(|| USAGE.to_string())?;
+        match arg.as_str() {
+            "--rows" => parsed.rows = value.parse().with_context(|| format!("invalid --rows: {}", value))?,
+            "--coins" => parsed.coins = value.parse().with_context(|| format!("invalid --coins: {}", value))?,
+            "--iterations" => {
+                parsed.iterations = value.parse().with_context(|| format!("invalid --iterations: {}", value))?
+            }
+            _ => anyhow::bail!("{}", USAGE),
+        }
+    }
+
+    if parsed.rows < 1 || parsed.coins < 1 || parsed.iterations < 1 {
+        anyhow::bail!("--rows, --coins and --iterations must all be at least 1");
+    }
+    Ok(parsed)
+}
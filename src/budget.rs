@@ -0,0 +1,95 @@
+use std::collections::{BTreeMap, HashMap};
+use std::sync::{Arc, Mutex};
+use crate::{formatting, hyperliquid::CoinSymbol};
+
+#[derive(Default)]
+struct DailyTally {
+    // where the end-of-day summary goes
+    chat_id: i64,
+    cap: u32,
+    sent: u32,
+    // coin -> (trades, total notional) held back once the cap was hit
+    suppressed: BTreeMap<CoinSymbol, (u32, f64)>,
+}
+
+/// Summary of what a capped user missed today.
+pub struct SuppressedSummary {
+    pub telegram_user_id: i64,
+    pub chat_id: i64,
+    pub message: String,
+}
+
+/// Per-user alert counts for the current UTC day, for `/dailycap`. Kept in
+/// memory, so a restart gives everyone a fresh budget.
+#[derive(Clone, Default)]
+pub struct AlertBudget {
+    tallies: Arc<Mutex<HashMap<i64, DailyTally>>>,
+}
+
+impl AlertBudget {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Whether a user with `cap` can still get a trade alert today. Trades over
+    /// the cap are remembered for the end-of-day summary instead.
+    pub fn admit(&self, telegram_user_id: i64, chat_id: i64, cap: u32, coin: &CoinSymbol, notional_usd: f64) -> bool {
+        let Ok(mut tallies) = self.tallies.lock() else {
+            return true;
+        };
+        let tally = tallies.entry(telegram_user_id).or_default();
+        tally.chat_id = chat_id;
+        tally.cap = cap;
+
+        if tally.sent < cap {
+            tally.sent += 1;
+            return true;
+        }
+        let (trades, notional) = tally.suppressed.entry(coin.clone()).or_default();
+        *trades += 1;
+        *notional += notional_usd;
+        false
+    }
+
+    /// Gives back an alert `admit` let through that couldn't be delivered.
+    pub fn refund(&self, telegram_user_id: i64) {
+        let Ok(mut tallies) = self.tallies.lock() else {
+            return;
+        };
+        if let Some(tally) = tallies.get_mut(&telegram_user_id) {
+            tally.sent = tally.sent.saturating_sub(1);
+        }
+    }
+
+    /// Starts a new day, returning a summary for everyone who went over.
+    pub fn roll_over(&self) -> Vec<SuppressedSummary> {
+        let Ok(mut tallies) = self.tallies.lock() else {
+            return Vec::new();
+        };
+
+        tallies
+            .drain()
+            .filter(|(_, tally)| !tally.suppressed.is_empty())
+            .map(|(telegram_user_id, tally)| {
+                let total: u32 = tally.suppressed.values().map(|(trades, _)| trades).sum();
+                let lines: Vec<String> = tally
+                    .suppressed
+                    .iter()
+                    .map(|(coin, (trades, notional))| {
+                        format!("{}: {} trades, {}", coin, trades, formatting::format_usd(*notional, false))
+                    })
+                    .collect();
+                SuppressedSummary {
+                    telegram_user_id,
+                    chat_id: tally.chat_id,
+                    message: format!(
+                        "Daily Alert Summary\n\nYou reached your cap of {} alerts today, so {} more were held back:\n\n{}",
+                        tally.cap,
+                        total,
+                        lines.join("\n")
+                    ),
+                }
+            })
+            .collect()
+    }
+}
@@ -0,0 +1,83 @@
+use anyhow::Result;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use tokio::time::{Duration, Instant};
+use tracing::debug;
+use crate::{
+    config::CandleCacheConfig,
+    hyperliquid::{Candle, HyperliquidClient},
+};
+
+struct CachedCandles {
+    candles: Vec<Candle>,
+    // widest window anyone has asked for, so callers with different
+    // lookbacks on the same coin share one fetch
+    lookback_ms: i64,
+    fetched_at: Instant,
+}
+
+type Slot = Arc<tokio::sync::Mutex<Option<CachedCandles>>>;
+
+// candle snapshots shared by charts, /price and volatility alerts, cached per
+// (coin, interval) with concurrent misses coalesced into one request
+#[derive(Clone)]
+pub struct CandleCache {
+    hyperliquid_client: HyperliquidClient,
+    config: CandleCacheConfig,
+    slots: Arc<Mutex<HashMap<(String, String), Slot>>>,
+}
+
+impl CandleCache {
+    pub fn new(hyperliquid_client: HyperliquidClient, config: CandleCacheConfig) -> Self {
+        CandleCache {
+            hyperliquid_client,
+            config,
+            slots: Arc::new(Mutex::new(HashMap::new())),
+        }
+    }
+
+    fn ttl(&self, interval: &str) -> Duration {
+        let secs = self.config.ttl_secs.get(interval).copied().unwrap_or(self.config.default_ttl_secs);
+        Duration::from_secs(secs)
+    }
+
+    fn slot(&self, coin: &str, interval: &str) -> Slot {
+        let mut slots = self.slots.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+        slots.entry((coin.to_string(), interval.to_string())).or_default().clone()
+    }
+
+    /// Candles for `coin` that opened within the last `lookback`, oldest first.
+    pub async fn recent(&self, coin: &str, interval: &str, lookback: Duration) -> Result<Vec<Candle>> {
+        let coin = coin.to_uppercase();
+        let lookback_ms = lookback.as_millis() as i64;
+        let ttl = self.ttl(interval);
+        let slot = self.slot(&coin, interval);
+
+        // held across the fetch, so concurrent misses for a key wait on the one request
+        let mut cached = slot.lock().await;
+        let fresh = cached
+            .as_ref()
+            .is_some_and(|cached| cached.fetched_at.elapsed() < ttl && cached.lookback_ms >= lookback_ms);
+
+        let end_time = chrono::Utc::now().timestamp_millis();
+        if !fresh {
+            let lookback_ms = cached.as_ref().map_or(lookback_ms, |cached| cached.lookback_ms.max(lookback_ms));
+            let candles = self
+                .hyperliquid_client
+                .candle_snapshot(&coin, interval, end_time - lookback_ms, end_time)
+                .await?;
+            debug!("fetched {} {} candles for {}", candles.len(), interval, coin);
+            *cached = Some(CachedCandles {
+                candles,
+                lookback_ms,
+                fetched_at: Instant::now(),
+            });
+        }
+
+        let start_time = end_time - lookback_ms;
+        Ok(cached
+            .as_ref()
+            .map(|cached| cached.candles.iter().filter(|c| c.open_time >= start_time).cloned().collect())
+            .unwrap_or_default())
+    }
+}
@@ -0,0 +1,168 @@
+use std::collections::{BTreeMap, HashMap};
+use std::sync::Arc;
+use tokio::sync::Mutex;
+use tokio::time::{Duration, Instant};
+use crate::{
+    config::CatchUpConfig,
+    delivery::PendingAlert,
+    formatting,
+    hyperliquid::{CoinSymbol, WsTrade},
+};
+
+#[derive(Default)]
+struct CoinBackfill {
+    buys: u32,
+    sells: u32,
+    total_usd: f64,
+    largest: Option<(bool, f64, String)>,
+}
+
+/// One chat's replayed alerts for one user, waiting for the burst to end.
+pub struct Backfill {
+    pub telegram_user_id: i64,
+    pub chat_id: i64,
+    last_added: Instant,
+    pub alerts: Vec<PendingAlert>,
+    /// Sends of this catch-up that have failed so far.
+    pub failed_sends: u32,
+}
+
+impl Backfill {
+    pub fn text(&self) -> String {
+        let full_precision = self.alerts.last().is_some_and(|alert| alert.settings.full_precision);
+        let usd = |value: f64| formatting::format_usd(value, full_precision);
+
+        let mut coins: BTreeMap<&CoinSymbol, CoinBackfill> = BTreeMap::new();
+        for alert in &self.alerts {
+            let (trade, notional_usd) = (&alert.trade, alert.notional_usd);
+            let buy = trade.side == "B";
+            let coin = coins.entry(&trade.coin).or_default();
+            if buy {
+                coin.buys += 1;
+            } else {
+                coin.sells += 1;
+            }
+            coin.total_usd += notional_usd;
+            if coin.largest.as_ref().is_none_or(|(_, largest, _)| notional_usd > *largest) {
+                coin.largest = Some((buy, notional_usd, trade.px.clone()));
+            }
+        }
+
+        // biggest coin first
+        let mut coins: Vec<(&CoinSymbol, CoinBackfill)> = coins.into_iter().collect();
+        coins.sort_by(|(_, a), (_, b)| b.total_usd.total_cmp(&a.total_usd));
+
+        let mut text = "Catch-up · while reconnecting".to_string();
+        for (coin, backfill) in coins {
+            let trades = backfill.buys + backfill.sells;
+            text.push_str(&format!(
+                "\n\n{} {} whale trade{} totaling {} occurred ({} buys, {} sells)",
+                trades,
+                coin,
+                if trades == 1 { "" } else { "s" },
+                usd(backfill.total_usd),
+                backfill.buys,
+                backfill.sells
+            ));
+            if let Some((buy, notional_usd, px)) = &backfill.largest {
+                text.push_str(&format!(
+                    "\nLargest: {} {} @ ${}",
+                    if *buy { "BUY" } else { "SELL" },
+                    usd(*notional_usd),
+                    formatting::format_price(px)
+                ));
+            }
+        }
+        text
+    }
+}
+
+/// Collects alerts for trades a feed replayed after a restart or reconnect,
+/// so each chat gets one catch-up message once the burst has died down
+/// instead of a stale alert per trade. `AlertDelivery` sends them and
+/// records each alert as delivered only once its catch-up is out.
+#[derive(Clone)]
+pub struct CatchUp {
+    config: CatchUpConfig,
+    held: Arc<Mutex<HashMap<(i64, i64), Backfill>>>,
+    // when each coin's trade feed last (re)subscribed, i.e. started replaying
+    resubscribed: Arc<std::sync::Mutex<HashMap<CoinSymbol, Instant>>>,
+}
+
+impl CatchUp {
+    pub fn new(config: CatchUpConfig) -> Self {
+        CatchUp {
+            config,
+            held: Arc::new(Mutex::new(HashMap::new())),
+            resubscribed: Arc::new(std::sync::Mutex::new(HashMap::new())),
+        }
+    }
+
+    pub fn enabled(&self) -> bool {
+        self.config.enabled
+    }
+
+    /// Notes that hl confirmed the coin's trade feed, so the trades it sends
+    /// next may be ones we missed while the feed was down.
+    pub fn feed_resubscribed(&self, coin: &CoinSymbol) {
+        if let Ok(mut resubscribed) = self.resubscribed.lock() {
+            resubscribed.insert(coin.clone(), Instant::now());
+        }
+    }
+
+    /// Whether the trade was replayed rather than seen live: old, and
+    /// arriving while its feed is catching up after a (re)subscribe.
+    pub fn is_backfill(&self, trade: &WsTrade) -> bool {
+        if !self.config.enabled {
+            return false;
+        }
+        let replay_window = Duration::from_secs(self.config.replay_window_secs);
+        let replaying = self
+            .resubscribed
+            .lock()
+            .ok()
+            .and_then(|resubscribed| resubscribed.get(&trade.coin).copied())
+            .is_some_and(|at| at.elapsed() <= replay_window);
+        let stale_after_ms = self.config.stale_after_secs.saturating_mul(1000) as i64;
+        replaying
+            && trade
+                .time
+                .is_some_and(|time| chrono::Utc::now().timestamp_millis() - time > stale_after_ms)
+    }
+
+    pub async fn record(&self, chat_id: i64, alert: &PendingAlert) {
+        let mut held = self.held.lock().await;
+        let backfill = held.entry((alert.telegram_user_id, chat_id)).or_insert_with(|| Backfill {
+            telegram_user_id: alert.telegram_user_id,
+            chat_id,
+            last_added: Instant::now(),
+            alerts: Vec::new(),
+            failed_sends: 0,
+        });
+        backfill.last_added = Instant::now();
+        backfill.alerts.push(PendingAlert { chart: None, ..alert.clone() });
+    }
+
+    /// Takes every chat's backfill that has been quiet long enough, for sending.
+    pub async fn take_due(&self) -> Vec<Backfill> {
+        let quiet = Duration::from_secs(self.config.quiet_secs);
+        let mut held = self.held.lock().await;
+        let keys: Vec<(i64, i64)> = held
+            .iter()
+            .filter(|(_, backfill)| backfill.last_added.elapsed() >= quiet)
+            .map(|(key, _)| *key)
+            .collect();
+        keys.into_iter().filter_map(|key| held.remove(&key)).collect()
+    }
+
+    /// Puts back a catch-up that couldn't be sent, ahead of anything held
+    /// since; it's tried again after another quiet spell.
+    pub async fn hold_again(&self, mut backfill: Backfill) {
+        let mut held = self.held.lock().await;
+        backfill.last_added = Instant::now();
+        if let Some(newer) = held.remove(&(backfill.telegram_user_id, backfill.chat_id)) {
+            backfill.alerts.extend(newer.alerts);
+        }
+        held.insert((backfill.telegram_user_id, backfill.chat_id), backfill);
+    }
+}
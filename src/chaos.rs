@@ -0,0 +1,46 @@
+use std::sync::OnceLock;
+use tokio::time::{sleep, Duration};
+use tracing::warn;
+use crate::config::ChaosConfig;
+
+// only built with `--features chaos`; the first config in wins, so tenants
+// share the base config's odds
+static CONFIG: OnceLock<ChaosConfig> = OnceLock::new();
+
+pub fn init(config: &ChaosConfig) {
+    if config.is_active() {
+        warn!(
+            "chaos mode on: ws drops {}, db delays {} ({}ms), telegram failures {}",
+            config.ws_drop_probability, config.db_delay_probability, config.db_delay_ms, config.telegram_failure_probability
+        );
+    }
+    let _ = CONFIG.set(config.clone());
+}
+
+fn roll(probability: impl Fn(&ChaosConfig) -> f64) -> bool {
+    CONFIG
+        .get()
+        .map(probability)
+        .is_some_and(|probability| probability > 0.0 && rand::random::<f64>() < probability)
+}
+
+/// Whether the ws connection should drop instead of handling this message.
+pub fn drop_ws_connection() -> bool {
+    roll(|config| config.ws_drop_probability)
+}
+
+/// Holds up a db connection checkout, sometimes.
+pub async fn delay_db_query() {
+    if roll(|config| config.db_delay_probability) {
+        let delay_ms = CONFIG.get().map_or(0, |config| config.db_delay_ms);
+        sleep(Duration::from_millis(delay_ms)).await;
+    }
+}
+
+/// Fails a telegram send before it's made, sometimes.
+pub fn fail_telegram_send() -> anyhow::Result<()> {
+    if roll(|config| config.telegram_failure_probability) {
+        anyhow::bail!("chaos: injected telegram send failure");
+    }
+    Ok(())
+}
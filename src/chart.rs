@@ -0,0 +1,127 @@
+use anyhow::{anyhow, Result};
+use futures_util::future::{BoxFuture, FutureExt, Shared};
+use plotters::prelude::*;
+use std::collections::HashMap;
+use std::io::Cursor;
+use std::sync::{Arc, Mutex};
+use tokio::time::{Duration, Instant};
+use tracing::{info, warn};
+use crate::{candles::CandleCache, hyperliquid::Candle};
+
+const CHART_WIDTH: u32 = 640;
+const CHART_HEIGHT: u32 = 360;
+const CHART_CANDLES: u64 = 48;
+const CHART_TTL: Duration = Duration::from_secs(5 * 60);
+
+/// A chart rendering in the background, shared by every alert for a trade
+/// so sends can wait for it without holding up the trade itself.
+pub type PendingChart = Shared<BoxFuture<'static, Option<Arc<Vec<u8>>>>>;
+
+struct CachedChart {
+    png: Arc<Vec<u8>>,
+    rendered_at: Instant,
+}
+
+// renders 1h candle charts for alerts, cached per coin so one whale trade
+// doesn't render the same image once per subscriber
+#[derive(Clone)]
+pub struct ChartRenderer {
+    candles: CandleCache,
+    cache: Arc<Mutex<HashMap<String, CachedChart>>>,
+}
+
+impl ChartRenderer {
+    pub fn new(candles: CandleCache) -> Self {
+        ChartRenderer {
+            candles,
+            cache: Arc::new(Mutex::new(HashMap::new())),
+        }
+    }
+
+    pub async fn trade_chart(&self, coin: &str) -> Result<Arc<Vec<u8>>> {
+        let coin = coin.to_uppercase();
+        if let Some(png) = self.cached(&coin) {
+            return Ok(png);
+        }
+
+        // the cache isn't locked while fetching, so a slow coin holds up no
+        // other; concurrent fetches for one coin share a request in CandleCache
+        let candles = self
+            .candles
+            .recent(&coin, "1h", Duration::from_secs(CHART_CANDLES * 60 * 60))
+            .await?;
+
+        let png = tokio::task::spawn_blocking(move || render_candles(&candles)).await??;
+        let png = Arc::new(png);
+        info!("rendered {} chart ({} bytes)", coin, png.len());
+
+        if let Ok(mut cache) = self.cache.lock() {
+            cache.insert(coin, CachedChart {
+                png: png.clone(),
+                rendered_at: Instant::now(),
+            });
+        }
+
+        Ok(png)
+    }
+
+    /// Starts rendering a coin's chart in its own task.
+    pub fn spawn_trade_chart(&self, coin: &str) -> PendingChart {
+        let renderer = self.clone();
+        let coin = coin.to_string();
+        let task = tokio::spawn(async move {
+            match renderer.trade_chart(&coin).await {
+                Ok(png) => Some(png),
+                Err(e) => {
+                    warn!("couldn't render {} chart: {}", coin, e);
+                    None
+                }
+            }
+        });
+        task.map(|rendered| rendered.ok().flatten()).boxed().shared()
+    }
+
+    fn cached(&self, coin: &str) -> Option<Arc<Vec<u8>>> {
+        let cache = self.cache.lock().ok()?;
+        let cached = cache.get(coin)?;
+        (cached.rendered_at.elapsed() < CHART_TTL).then(|| cached.png.clone())
+    }
+}
+
+fn render_candles(candles: &[Candle]) -> Result<Vec<u8>> {
+    let ohlc: Vec<(f64, f64, f64, f64)> = candles.iter().filter_map(Candle::ohlc).collect();
+    if ohlc.is_empty() {
+        return Err(anyhow!("no candles to render"));
+    }
+
+    let low = ohlc.iter().map(|c| c.2).fold(f64::INFINITY, f64::min);
+    let high = ohlc.iter().map(|c| c.1).fold(f64::NEG_INFINITY, f64::max);
+    let padding = ((high - low) * 0.05).max(high * 0.0005);
+
+    let mut buffer = vec![0u8; (CHART_WIDTH * CHART_HEIGHT * 3) as usize];
+    {
+        let root = BitMapBackend::with_buffer(&mut buffer, (CHART_WIDTH, CHART_HEIGHT)).into_drawing_area();
+        root.fill(&WHITE).map_err(|e| anyhow!("chart error: {}", e))?;
+
+        let mut chart = ChartBuilder::on(&root)
+            .margin(12)
+            .build_cartesian_2d(-1..ohlc.len() as i32, (low - padding)..(high + padding))
+            .map_err(|e| anyhow!("chart error: {}", e))?;
+
+        let candle_width = (CHART_WIDTH as usize / (ohlc.len() + 2)).saturating_sub(2).max(2) as u32;
+        chart
+            .draw_series(ohlc.iter().enumerate().map(|(i, (open, high, low, close))| {
+                CandleStick::new(i as i32, *open, *high, *low, *close, GREEN.filled(), RED.filled(), candle_width)
+            }))
+            .map_err(|e| anyhow!("chart error: {}", e))?;
+
+        root.present().map_err(|e| anyhow!("chart error: {}", e))?;
+    }
+
+    let image = image::RgbImage::from_raw(CHART_WIDTH, CHART_HEIGHT, buffer)
+        .ok_or_else(|| anyhow!("chart buffer has the wrong size"))?;
+    let mut png = Vec::new();
+    image.write_to(&mut Cursor::new(&mut png), image::ImageOutputFormat::Png)?;
+
+    Ok(png)
+}
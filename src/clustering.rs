@@ -0,0 +1,119 @@
+use std::collections::HashMap;
+use tokio::time::{Duration, Instant};
+use crate::hyperliquid::{CoinSymbol, WsTrade};
+
+// a taker order sweeping the book arrives as one fill per resting order
+struct Cluster {
+    first: WsTrade,
+    taker: String,
+    size: f64,
+    notional: f64,
+    fills: u32,
+    // quote the average at the precision the exchange used
+    decimals: usize,
+    opened_at: Instant,
+}
+
+impl Cluster {
+    fn open(trade: WsTrade, taker: String, size: f64, px: f64) -> Self {
+        Cluster {
+            decimals: price_decimals(&trade.px),
+            first: trade,
+            taker,
+            size,
+            notional: px * size,
+            fills: 1,
+            opened_at: Instant::now(),
+        }
+    }
+
+    fn same_order(&self, trade: &WsTrade) -> bool {
+        self.first.side == trade.side && trade.taker() == Some(self.taker.as_str())
+    }
+
+    fn into_trade(self) -> WsTrade {
+        if self.fills == 1 {
+            return self.first;
+        }
+        WsTrade {
+            px: format!("{:.*}", self.decimals, self.notional / self.size),
+            sz: self.size.to_string(),
+            fills: self.fills,
+            ..self.first
+        }
+    }
+}
+
+fn price_decimals(px: &str) -> usize {
+    px.split_once('.').map_or(0, |(_, frac)| frac.len())
+}
+
+/// Merges consecutive fills from the same taker on a coin into one trade with
+/// the total size and average price.
+pub struct TradeClusterer {
+    window: Duration,
+    open: HashMap<CoinSymbol, Cluster>,
+}
+
+impl TradeClusterer {
+    pub fn new(window: Duration) -> Self {
+        TradeClusterer {
+            window,
+            open: HashMap::new(),
+        }
+    }
+
+    /// How often `flush_expired` should run to keep alerts within about a window late.
+    pub fn flush_interval(&self) -> Duration {
+        (self.window / 2).max(Duration::from_millis(50))
+    }
+
+    /// Adds a fill, returning any trades that are now complete.
+    pub fn push(&mut self, trade: WsTrade) -> Vec<WsTrade> {
+        let parsed = trade.px.parse::<f64>().ok().zip(trade.sz.parse::<f64>().ok());
+        let (Some(taker), Some((px, size)), false) = (trade.taker(), parsed, self.window.is_zero()) else {
+            // nothing to merge on, pass it through but keep the coin's order intact
+            let mut ready: Vec<WsTrade> = self.open.remove(&trade.coin).map(Cluster::into_trade).into_iter().collect();
+            ready.push(trade);
+            return ready;
+        };
+        let taker = taker.to_string();
+
+        let mut ready = Vec::new();
+        match self.open.get_mut(&trade.coin) {
+            Some(cluster) if cluster.same_order(&trade) => {
+                cluster.size += size;
+                cluster.notional += px * size;
+                cluster.fills += 1;
+                cluster.decimals = cluster.decimals.max(price_decimals(&trade.px));
+                return ready;
+            }
+            Some(_) => {
+                // a different taker hit the coin, so the previous order is done
+                if let Some(cluster) = self.open.remove(&trade.coin) {
+                    ready.push(cluster.into_trade());
+                }
+            }
+            None => {}
+        }
+
+        self.open.insert(trade.coin.clone(), Cluster::open(trade, taker, size, px));
+        ready
+    }
+
+    /// Closes clusters whose window has run out.
+    pub fn flush_expired(&mut self) -> Vec<WsTrade> {
+        let expired: Vec<CoinSymbol> = self
+            .open
+            .iter()
+            .filter(|(_, cluster)| cluster.opened_at.elapsed() >= self.window)
+            .map(|(coin, _)| coin.clone())
+            .collect();
+
+        expired
+            .into_iter()
+            .filter_map(|coin| self.open.remove(&coin))
+            .map(Cluster::into_trade)
+            .collect()
+    }
+}
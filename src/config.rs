@@ -1,7 +1,15 @@
 use anyhow::Result;
 use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use crate::secrets;
 use config::{Config as ConfigBuilder, File};
 
+// picks which config.<env>.toml gets layered over the base config.toml
+const ENV_VAR: &str = "APP_ENV";
+
+/// The bot configured by config.toml itself, and every row from before tenants.
+pub const DEFAULT_TENANT: &str = "default";
+
 #[derive(Debug, Deserialize, Serialize, Clone)]
 pub struct Config {
     pub telegram: TelegramConfig,
@@ -10,23 +18,282 @@ pub struct Config {
     pub defaults: DefaultsConfig,
     pub retry: RetryConfig,
     pub commands: CommandsConfig,
+    #[serde(default)]
+    pub fx: FxConfig,
+    #[serde(default)]
+    pub imbalance: ImbalanceConfig,
+    #[serde(default)]
+    pub metrics: MetricsConfig,
+    #[serde(default)]
+    pub dedup: DedupConfig,
+    #[serde(default)]
+    pub history: HistoryConfig,
+    #[serde(default)]
+    pub schedules: Vec<ScheduleConfig>,
+    #[serde(default)]
+    pub outbound: OutboundConfig,
+    #[serde(default)]
+    pub logging: LoggingConfig,
+    #[serde(default)]
+    pub revisit: RevisitConfig,
+    #[serde(default)]
+    pub vwap: VwapConfig,
+    #[serde(default)]
+    pub volatility: VolatilityConfig,
+    #[serde(default)]
+    pub price_alerts: PriceAlertConfig,
+    #[serde(default)]
+    pub liquidations: LiquidationConfig,
+    #[serde(default)]
+    pub clustering: ClusteringConfig,
+    #[serde(default)]
+    pub candles: CandleCacheConfig,
+    #[serde(default)]
+    pub watchdog: WatchdogConfig,
+    #[serde(default)]
+    pub feed_health: FeedHealthConfig,
+    #[serde(default)]
+    pub chaos: ChaosConfig,
+    #[serde(default)]
+    pub throttling: ThrottlingConfig,
+    #[serde(default)]
+    pub recording: RecordingConfig,
+    #[serde(default)]
+    pub market_alerts: MarketAlertsConfig,
+    #[serde(default)]
+    pub range_alerts: RangeAlertsConfig,
+    #[serde(default)]
+    pub wallet_summaries: WalletSummaryConfig,
+    #[serde(default)]
+    pub ledger_alerts: LedgerAlertsConfig,
+    #[serde(default)]
+    pub ticker: TickerConfig,
+    #[serde(default)]
+    pub links: LinksConfig,
+    #[serde(default)]
+    pub warmup: WarmupConfig,
+    #[serde(default)]
+    pub experiment: ExperimentConfig,
+    #[serde(default)]
+    pub catch_up: CatchUpConfig,
+    #[serde(default)]
+    pub tenants: Vec<TenantConfig>,
+}
+
+/// Another bot run from the same process, sharing the database and the ws feeds.
+#[derive(Debug, Deserialize, Serialize, Clone)]
+pub struct TenantConfig {
+    /// Tags the tenant's rows; lowercase letters, digits, `-` and `_`.
+    pub id: String,
+    /// Layered over config.toml (and the profile) like a profile is, so it
+    /// only lists what this bot does differently: at least `telegram.bot_token`,
+    /// usually admins, defaults and links. `database` and `hyperliquid` always
+    /// come from the base config.
+    pub config_file: String,
 }
 
 #[derive(Debug, Deserialize, Serialize, Clone)]
 pub struct TelegramConfig {
+    /// May be a secret reference (env:, file:, vault:, aws-sm:).
+    #[serde(default)]
     pub bot_token: String,
+    /// Read the token from this file instead.
+    #[serde(default)]
+    pub bot_token_file: Option<String>,
+    /// Second bot that takes over sending alerts if the primary token keeps
+    /// failing. Users have to have started it too. May be a secret reference.
+    #[serde(default)]
+    pub backup_bot_token: String,
+    #[serde(default)]
+    pub backup_bot_token_file: Option<String>,
+    /// Consecutive token/network errors before switching to the backup bot.
+    #[serde(default = "default_failover_after_errors")]
+    pub failover_after_errors: u32,
+    /// Self-hosted Bot API server (e.g. "http://localhost:8081") to use
+    /// instead of api.telegram.org, for both bots.
+    #[serde(default)]
+    pub api_url: Option<String>,
+    /// Always owners; other roles are granted with /role.
+    #[serde(default)]
+    pub admin_user_ids: Vec<i64>,
+    /// Mute, raise threshold, chart and unsubscribe buttons under each alert.
+    #[serde(default = "default_alert_buttons")]
+    pub alert_buttons: bool,
 }
 
 #[derive(Debug, Deserialize, Serialize, Clone)]
 pub struct HyperliquidConfig {
     pub websocket_url: String,
     pub rest_api_url: String,
+    #[serde(default = "default_max_subscriptions_per_connection")]
+    pub max_subscriptions_per_connection: usize,
+    #[serde(default)]
+    pub tls: TlsConfig,
+    /// Builder-deployed perp dexes (e.g. "xyz") whose markets users can
+    /// subscribe to as `<dex>:<coin>`, on top of the main dex.
+    #[serde(default)]
+    pub perp_dexs: Vec<String>,
+    #[serde(default)]
+    pub payload_logging: PayloadLoggingConfig,
+}
+
+#[derive(Debug, Deserialize, Serialize, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum PayloadLogMode {
+    /// A truncated preview of one unparsable ws payload in `sample_every`, at debug.
+    #[default]
+    Sampled,
+    /// The previews above, plus every payload in full to its own rotating files.
+    Verbose,
+}
+
+#[derive(Debug, Deserialize, Serialize, Clone)]
+#[serde(default)]
+pub struct PayloadLoggingConfig {
+    pub mode: PayloadLogMode,
+    /// Characters of a payload kept in the log line.
+    pub preview_chars: usize,
+    pub sample_every: u64,
+    /// Verbose mode only.
+    pub directory: String,
+    pub max_file_mb: u64,
+    /// Oldest files beyond this many are deleted.
+    pub max_files: usize,
+}
+
+impl Default for PayloadLoggingConfig {
+    fn default() -> Self {
+        PayloadLoggingConfig {
+            mode: PayloadLogMode::Sampled,
+            preview_chars: 200,
+            sample_every: 100,
+            directory: "payloads".to_string(),
+            max_file_mb: 64,
+            max_files: 5,
+        }
+    }
+}
+
+#[derive(Debug, Deserialize, Serialize, Clone)]
+#[serde(default)]
+pub struct TlsConfig {
+    /// TLS library for the websocket and REST connections: native-tls, which
+    /// trusts the system roots, or rustls, which trusts Mozilla's.
+    pub backend: String,
+    /// SHA-256 certificate fingerprints (hex, colons optional) the endpoints
+    /// must present, e.g. a TLS-inspecting proxy's, trusted in place of the
+    /// roots. Checked during the handshake, so they need the rustls backend.
+    pub pinned_sha256: Vec<String>,
+}
+
+impl Default for TlsConfig {
+    fn default() -> Self {
+        TlsConfig {
+            backend: "native-tls".to_string(),
+            pinned_sha256: Vec::new(),
+        }
+    }
+}
+
+impl TelegramConfig {
+    pub fn api_url(&self) -> Result<Option<reqwest::Url>> {
+        let Some(url) = self.api_url.as_deref().filter(|url| !url.is_empty()) else {
+            return Ok(None);
+        };
+        reqwest::Url::parse(url)
+            .map(Some)
+            .map_err(|e| anyhow::anyhow!("telegram.api_url {} isn't a valid url: {}", url, e))
+    }
+}
+
+#[derive(Debug, Deserialize, Serialize, Clone)]
+#[serde(default)]
+pub struct LinksConfig {
+    /// Link each coin's Hyperliquid trade page in alerts and /info.
+    pub enabled: bool,
+    pub app_url: String,
+    /// Referral code added to those links as `?ref=`, for operators of public bots.
+    pub referral_code: String,
+    /// Set false to leave the code off the links without removing it.
+    pub include_referral: bool,
+}
+
+impl Default for LinksConfig {
+    fn default() -> Self {
+        LinksConfig {
+            enabled: true,
+            app_url: "https://app.hyperliquid.xyz".to_string(),
+            referral_code: String::new(),
+            include_referral: true,
+        }
+    }
+}
+
+impl LinksConfig {
+    /// The coin's trade page, or None with links turned off.
+    pub fn trade_url(&self, coin: &str) -> Result<Option<reqwest::Url>> {
+        if !self.enabled {
+            return Ok(None);
+        }
+        let mut url = reqwest::Url::parse(&self.app_url)
+            .map_err(|e| anyhow::anyhow!("links.app_url {} isn't a valid url: {}", self.app_url, e))?;
+        url.path_segments_mut()
+            .map_err(|_| anyhow::anyhow!("links.app_url {} can't take a path", self.app_url))?
+            .pop_if_empty()
+            .extend(["trade", coin]);
+        if self.include_referral && !self.referral_code.is_empty() {
+            url.query_pairs_mut().append_pair("ref", &self.referral_code);
+        }
+        Ok(Some(url))
+    }
+}
+
+fn default_failover_after_errors() -> u32 {
+    5
+}
+
+fn default_alert_buttons() -> bool {
+    true
+}
+
+fn default_max_subscriptions_per_connection() -> usize {
+    50
+}
+
+fn default_instance_lock() -> bool {
+    true
+}
+
+fn default_standby_poll_secs() -> u64 {
+    2
 }
 
 #[derive(Debug, Deserialize, Serialize, Clone)]
 pub struct DatabaseConfig {
+    /// Like the other secrets, may be a reference or come from `url_file`.
+    #[serde(default)]
     pub url: String,
+    #[serde(default)]
+    pub url_file: Option<String>,
+    #[serde(default)]
     pub api_key: String,
+    #[serde(default)]
+    pub api_key_file: Option<String>,
+    /// Optional read-only replica for history, leaderboard and stats queries,
+    /// keeping them off the primary that subscriber lookups depend on.
+    #[serde(default)]
+    pub replica_url: String,
+    #[serde(default)]
+    pub replica_url_file: Option<String>,
+    /// Refuse to start while another process runs the same bot token against
+    /// this database. Needs session-level advisory locks, so turn it off
+    /// behind a transaction-pooling proxy.
+    #[serde(default = "default_instance_lock")]
+    pub instance_lock: bool,
+    /// With `--standby`, how often to try for the running instance's lock;
+    /// the running instance checks its hold on the lock as often.
+    #[serde(default = "default_standby_poll_secs")]
+    pub standby_poll_secs: u64,
 }
 
 #[derive(Debug, Deserialize, Serialize, Clone)]
@@ -50,13 +317,678 @@ pub struct CommandsConfig {
     pub help_command: String,
 }
 
+#[derive(Debug, Deserialize, Serialize, Clone)]
+#[serde(default)]
+pub struct FxConfig {
+    pub api_url: String,
+    pub refresh_interval_secs: u64,
+    pub max_staleness_secs: u64,
+}
+
+impl Default for FxConfig {
+    fn default() -> Self {
+        FxConfig {
+            api_url: "https://open.er-api.com/v6/latest/USD".to_string(),
+            refresh_interval_secs: 60 * 60,
+            max_staleness_secs: 3 * 60 * 60,
+        }
+    }
+}
+
+#[derive(Debug, Deserialize, Serialize, Clone)]
+#[serde(default)]
+pub struct ImbalanceConfig {
+    /// Only book levels within this many bps of mid count towards depth.
+    pub band_bps: f64,
+    pub cooldown_secs: u64,
+}
+
+impl Default for ImbalanceConfig {
+    fn default() -> Self {
+        ImbalanceConfig {
+            band_bps: 50.0,
+            cooldown_secs: 15 * 60,
+        }
+    }
+}
+
+#[derive(Debug, Deserialize, Serialize, Clone)]
+#[serde(default)]
+pub struct MetricsConfig {
+    /// Warn when trade-to-delivery latency exceeds this.
+    pub latency_slo_ms: u64,
+    /// How many recent alert latencies feed the percentiles.
+    pub latency_window: usize,
+    /// How often counters are written to `metrics_snapshots`; 0 turns it off.
+    pub snapshot_interval_secs: u64,
+    /// Snapshots older than this are dropped.
+    pub snapshot_retention_days: i64,
+}
+
+impl Default for MetricsConfig {
+    fn default() -> Self {
+        MetricsConfig {
+            latency_slo_ms: 5_000,
+            latency_window: 1_000,
+            snapshot_interval_secs: 5 * 60,
+            snapshot_retention_days: 90,
+        }
+    }
+}
+
+#[derive(Debug, Deserialize, Serialize, Clone)]
+#[serde(default)]
+pub struct DedupConfig {
+    /// How long a delivered (chat, trade) pair is remembered.
+    pub ttl_secs: u64,
+}
+
+impl Default for DedupConfig {
+    fn default() -> Self {
+        DedupConfig { ttl_secs: 10 * 60 }
+    }
+}
+
+#[derive(Debug, Deserialize, Serialize, Clone)]
+#[serde(default)]
+pub struct HistoryConfig {
+    /// Buffered trade-history and notification-log rows are written at least this often.
+    pub flush_interval_ms: u64,
+    /// ...or as soon as this many rows of one kind are waiting.
+    pub batch_size: usize,
+}
+
+impl Default for HistoryConfig {
+    fn default() -> Self {
+        HistoryConfig {
+            flush_interval_ms: 1_000,
+            batch_size: 500,
+        }
+    }
+}
+
+#[derive(Debug, Deserialize, Serialize, Clone)]
+#[serde(default)]
+pub struct OutboundConfig {
+    /// Bot-wide send rate; telegram starts refusing at around 30/s.
+    pub messages_per_sec: f64,
+    /// Further sends to a chat at the same priority are refused past this backlog.
+    pub max_pending_per_chat: usize,
+}
+
+impl Default for OutboundConfig {
+    fn default() -> Self {
+        OutboundConfig {
+            messages_per_sec: 25.0,
+            max_pending_per_chat: 50,
+        }
+    }
+}
+
+#[derive(Debug, Deserialize, Serialize, Clone)]
+#[serde(default)]
+pub struct VwapConfig {
+    /// Rolling window the vwap is computed over.
+    pub window_secs: u64,
+    pub cooldown_secs: u64,
+    /// How often price is compared against vwap.
+    pub check_interval_secs: u64,
+}
+
+impl Default for VwapConfig {
+    fn default() -> Self {
+        VwapConfig {
+            window_secs: 60 * 60,
+            cooldown_secs: 30 * 60,
+            check_interval_secs: 15,
+        }
+    }
+}
+
+#[derive(Debug, Deserialize, Serialize, Clone)]
+#[serde(default)]
+pub struct VolatilityConfig {
+    /// Recent realized volatility is measured over this many 1m candles...
+    pub recent_minutes: u64,
+    /// ...and compared against the volatility of the hours before it.
+    pub baseline_hours: u64,
+    pub cooldown_secs: u64,
+    pub check_interval_secs: u64,
+}
+
+impl Default for VolatilityConfig {
+    fn default() -> Self {
+        VolatilityConfig {
+            recent_minutes: 60,
+            baseline_hours: 24,
+            cooldown_secs: 2 * 60 * 60,
+            check_interval_secs: 5 * 60,
+        }
+    }
+}
+
+#[derive(Debug, Deserialize, Serialize, Clone)]
+#[serde(default)]
+pub struct PriceAlertConfig {
+    /// How often /pricealert thresholds are checked against the latest mids.
+    pub check_interval_secs: u64,
+    pub max_per_user: usize,
+}
+
+impl Default for PriceAlertConfig {
+    fn default() -> Self {
+        PriceAlertConfig {
+            check_interval_secs: 5,
+            max_per_user: 20,
+        }
+    }
+}
+
+#[derive(Debug, Deserialize, Serialize, Clone)]
+#[serde(default)]
+pub struct WatchdogConfig {
+    /// The coordinator loop counts as stalled after this long without a heartbeat.
+    pub stall_secs: u64,
+    pub check_interval_secs: u64,
+    /// Exit on a stall so the process supervisor restarts the bot.
+    pub restart_on_stall: bool,
+}
+
+impl Default for WatchdogConfig {
+    fn default() -> Self {
+        WatchdogConfig {
+            stall_secs: 120,
+            check_interval_secs: 10,
+            restart_on_stall: false,
+        }
+    }
+}
+
+#[derive(Debug, Deserialize, Serialize, Clone)]
+#[serde(default)]
+pub struct FeedHealthConfig {
+    pub check_interval_secs: u64,
+    /// Feeds whose usual gap between messages is at most this are watched for
+    /// going quiet; sleepier feeds are quiet often enough that it means nothing.
+    pub busy_gap_secs: u64,
+    /// Messages a feed needs before its usual gap counts as known.
+    pub min_messages: u64,
+    /// Quiet means no message for this many usual gaps...
+    pub quiet_multiple: f64,
+    /// ...and for at least this long.
+    pub min_quiet_secs: u64,
+}
+
+impl Default for FeedHealthConfig {
+    fn default() -> Self {
+        FeedHealthConfig {
+            check_interval_secs: 10,
+            busy_gap_secs: 5,
+            min_messages: 100,
+            quiet_multiple: 20.0,
+            min_quiet_secs: 60,
+        }
+    }
+}
+
+/// Fault injection for rehearsing failures; only acted on in builds with the
+/// `chaos` feature. Every probability is 0..=1 and defaults to 0.
+#[derive(Debug, Deserialize, Serialize, Clone)]
+#[serde(default)]
+pub struct ChaosConfig {
+    /// Chance each ws message drops its connection instead of being handled.
+    pub ws_drop_probability: f64,
+    /// Chance a db connection checkout is held up for `db_delay_ms`.
+    pub db_delay_probability: f64,
+    pub db_delay_ms: u64,
+    /// Chance a telegram send fails without being made.
+    pub telegram_failure_probability: f64,
+}
+
+impl ChaosConfig {
+    pub fn is_active(&self) -> bool {
+        self.ws_drop_probability > 0.0 || self.db_delay_probability > 0.0 || self.telegram_failure_probability > 0.0
+    }
+}
+
+impl Default for ChaosConfig {
+    fn default() -> Self {
+        ChaosConfig {
+            ws_drop_probability: 0.0,
+            db_delay_probability: 0.0,
+            db_delay_ms: 2000,
+            telegram_failure_probability: 0.0,
+        }
+    }
+}
+
+#[derive(Debug, Deserialize, Serialize, Clone, Default)]
+#[serde(default)]
+pub struct ThrottlingConfig {
+    /// Sub-threshold fills per second each trade feed feeds into statistics
+    /// like vwap; the rest are skipped. 0 means no cap. Alert-sized fills are
+    /// never throttled.
+    pub max_sampled_fills_per_sec: u32,
+    /// Per-coin overrides of the above, e.g. BTC = 200.
+    pub coins: HashMap<String, u32>,
+}
+
+impl ThrottlingConfig {
+    pub fn limit_for(&self, coin: &str) -> Option<u32> {
+        let limit = self
+            .coins
+            .iter()
+            .find(|(listed, _)| listed.eq_ignore_ascii_case(coin))
+            .map_or(self.max_sampled_fills_per_sec, |(_, limit)| *limit);
+        (limit > 0).then_some(limit)
+    }
+}
+
+#[derive(Debug, Deserialize, Serialize, Clone)]
+#[serde(default)]
+pub struct MarketAlertsConfig {
+    /// Warn subscribers when funding nears its cap or OI hits its cap, and
+    /// /divergence users when mark strays from oracle.
+    pub enabled: bool,
+    pub poll_interval_secs: u64,
+    /// hl clamps the hourly funding rate to this, either way.
+    pub funding_cap: f64,
+    /// Warn once funding reaches this share of the cap.
+    pub funding_warn_ratio: f64,
+    /// Alert /divergence users once a coin's mark is this many percent off its oracle.
+    pub divergence_pct: f64,
+}
+
+impl Default for MarketAlertsConfig {
+    fn default() -> Self {
+        MarketAlertsConfig {
+            enabled: true,
+            poll_interval_secs: 60,
+            funding_cap: 0.04,
+            funding_warn_ratio: 0.9,
+            divergence_pct: 1.0,
+        }
+    }
+}
+
+#[derive(Debug, Deserialize, Serialize, Clone)]
+#[serde(default)]
+pub struct RangeAlertsConfig {
+    /// Alert /rangealerts users when a coin breaks its rolling high or low.
+    pub enabled: bool,
+    pub window_days: i64,
+    /// How often live mids are compared against the range...
+    pub check_interval_secs: u64,
+    /// ...and how often the range itself is rebuilt from daily candles.
+    pub refresh_mins: u64,
+    /// A coin that keeps pushing its high alerts again only after this long.
+    pub cooldown_hours: u64,
+}
+
+impl Default for RangeAlertsConfig {
+    fn default() -> Self {
+        RangeAlertsConfig {
+            enabled: true,
+            window_days: 30,
+            check_interval_secs: 30,
+            refresh_mins: 60,
+            cooldown_hours: 6,
+        }
+    }
+}
+
+#[derive(Debug, Deserialize, Serialize, Clone)]
+#[serde(default)]
+pub struct WalletSummaryConfig {
+    /// Snapshot watched wallets and tell watchers how their positions moved.
+    pub enabled: bool,
+    pub interval_hours: u64,
+    /// Snapshots older than this are deleted.
+    pub retention_days: i64,
+}
+
+impl Default for WalletSummaryConfig {
+    fn default() -> Self {
+        WalletSummaryConfig {
+            enabled: true,
+            interval_hours: 4,
+            retention_days: 30,
+        }
+    }
+}
+
+#[derive(Debug, Deserialize, Serialize, Clone)]
+#[serde(default)]
+pub struct LedgerAlertsConfig {
+    /// Tell watchers about watched wallets' deposits, withdrawals and transfers.
+    pub enabled: bool,
+    pub poll_interval_secs: u64,
+    /// Smaller moves aren't worth a message.
+    pub min_usd: f64,
+}
+
+impl Default for LedgerAlertsConfig {
+    fn default() -> Self {
+        LedgerAlertsConfig {
+            enabled: true,
+            poll_interval_secs: 120,
+            min_usd: 100_000.0,
+        }
+    }
+}
+
+#[derive(Debug, Deserialize, Serialize, Clone)]
+#[serde(default)]
+pub struct TickerConfig {
+    /// A ticker message is edited at most this often; trades in between are batched.
+    pub min_edit_interval_secs: u64,
+}
+
+impl Default for TickerConfig {
+    fn default() -> Self {
+        TickerConfig { min_edit_interval_secs: 5 }
+    }
+}
+
+#[derive(Debug, Deserialize, Serialize, Clone)]
+#[serde(default)]
+pub struct RecordingConfig {
+    /// Write raw trade frames for every subscribed coin to disk.
+    pub enabled: bool,
+    pub directory: String,
+    /// Start a new file once this much (uncompressed) has been written...
+    pub max_file_mb: u64,
+    /// ...or once the file is this old, whichever comes first.
+    pub max_file_age_secs: u64,
+    /// zstd level, 1-22.
+    pub compression_level: i32,
+}
+
+impl Default for RecordingConfig {
+    fn default() -> Self {
+        RecordingConfig {
+            enabled: false,
+            directory: "recordings".to_string(),
+            max_file_mb: 256,
+            max_file_age_secs: 3600,
+            compression_level: 3,
+        }
+    }
+}
+
+#[derive(Debug, Deserialize, Serialize, Clone)]
+#[serde(default)]
+pub struct WarmupConfig {
+    /// Load the coin list, asset contexts and mids before taking commands.
+    pub enabled: bool,
+    pub timeout_secs: u64,
+    /// Start anyway, flagged as degraded in /stats, if the warmup doesn't
+    /// finish in time; otherwise startup fails.
+    pub allow_degraded: bool,
+}
+
+impl Default for WarmupConfig {
+    fn default() -> Self {
+        WarmupConfig {
+            enabled: true,
+            timeout_secs: 20,
+            allow_degraded: true,
+        }
+    }
+}
+
+/// An A/B test of the trade alert format. Each chat gets one variant for as
+/// long as the experiment runs, and taps on its alerts' buttons are counted
+/// per variant for /experiment.
+#[derive(Debug, Deserialize, Serialize, Clone)]
+#[serde(default)]
+pub struct ExperimentConfig {
+    /// Tags the alerts in the notification log; empty runs no experiment.
+    /// Renaming it starts the counts over.
+    pub name: String,
+    /// Each variant's alert template, empty for the built-in format. Takes
+    /// {title}, {coin}, {liquidation}, {amount}, {side}, {price} and {details}.
+    pub variant_a: String,
+    pub variant_b: String,
+    /// Share of chats given variant b, 0-100.
+    pub b_percent: u8,
+}
+
+impl Default for ExperimentConfig {
+    fn default() -> Self {
+        ExperimentConfig {
+            name: String::new(),
+            variant_a: String::new(),
+            variant_b: String::new(),
+            b_percent: 50,
+        }
+    }
+}
+
+/// Trades that reach us late, replayed after a restart or a feed reconnect,
+/// are collected per chat into one catch-up message instead of an alert each.
+#[derive(Debug, Deserialize, Serialize, Clone)]
+#[serde(default)]
+pub struct CatchUpConfig {
+    pub enabled: bool,
+    /// A trade this much older than when we first try to send it counts as backfill.
+    pub stale_after_secs: u64,
+    /// ...but only while its feed is replaying, this long after the feed
+    /// (re)subscribed; a live trade that's slow to send still goes out alone.
+    pub replay_window_secs: u64,
+    /// The catch-up message goes out once a chat's backfill has been quiet this long.
+    pub quiet_secs: u64,
+}
+
+impl Default for CatchUpConfig {
+    fn default() -> Self {
+        CatchUpConfig {
+            enabled: true,
+            stale_after_secs: 30,
+            replay_window_secs: 15,
+            quiet_secs: 10,
+        }
+    }
+}
+
+#[derive(Debug, Deserialize, Serialize, Clone)]
+#[serde(default)]
+pub struct CandleCacheConfig {
+    /// How long candles stay cached, per interval (e.g. "1m" = 30).
+    pub ttl_secs: HashMap<String, u64>,
+    /// For intervals not listed above.
+    pub default_ttl_secs: u64,
+}
+
+impl Default for CandleCacheConfig {
+    fn default() -> Self {
+        CandleCacheConfig {
+            ttl_secs: HashMap::from([("1m".to_string(), 30), ("1h".to_string(), 5 * 60)]),
+            default_ttl_secs: 60,
+        }
+    }
+}
+
+#[derive(Debug, Deserialize, Serialize, Clone)]
+#[serde(default)]
+pub struct ClusteringConfig {
+    /// Fills from the same taker arriving within this window of the first are
+    /// merged into one alert. 0 alerts on every fill separately.
+    pub window_ms: u64,
+}
+
+impl Default for ClusteringConfig {
+    fn default() -> Self {
+        ClusteringConfig { window_ms: 500 }
+    }
+}
+
+#[derive(Debug, Deserialize, Serialize, Clone, Default)]
+#[serde(default)]
+pub struct LiquidationConfig {
+    /// Fills with one of these on either side are treated as liquidations.
+    /// Empty turns liquidation tagging and totals off.
+    pub liquidator_addresses: Vec<String>,
+}
+
+#[derive(Debug, Deserialize, Serialize, Clone)]
+#[serde(default)]
+pub struct RevisitConfig {
+    /// How long after a whale trade a return to its price still alerts.
+    pub window_hours: u64,
+    /// Price has to move at least this far off the level before a return counts.
+    pub arm_distance_bps: f64,
+}
+
+impl Default for RevisitConfig {
+    fn default() -> Self {
+        RevisitConfig {
+            window_hours: 24,
+            arm_distance_bps: 50.0,
+        }
+    }
+}
+
+#[derive(Debug, Deserialize, Serialize, Clone)]
+#[serde(default)]
+pub struct LoggingConfig {
+    /// tracing env-filter directive, e.g. "hl_tg_bot=debug,info".
+    pub level: String,
+}
+
+impl Default for LoggingConfig {
+    fn default() -> Self {
+        LoggingConfig {
+            level: "hyperliquid_telegram_bot=debug,info".to_string(),
+        }
+    }
+}
+
+#[derive(Debug, Deserialize, Serialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum ReportKind {
+    /// Price, volume, open interest and funding, busiest coins first.
+    Market,
+    /// Same snapshot ordered by funding rate.
+    Funding,
+}
+
+#[derive(Debug, Deserialize, Serialize, Clone)]
+pub struct ScheduleConfig {
+    pub name: String,
+    /// Cron expression with a leading seconds field, in UTC (e.g. "0 0 0 * * *").
+    pub cron: String,
+    pub report: ReportKind,
+    /// Coins to include; empty means the top `top_n` by 24h volume.
+    #[serde(default)]
+    pub coins: Vec<String>,
+    #[serde(default = "default_report_top_n")]
+    pub top_n: usize,
+    pub chat_ids: Vec<i64>,
+}
+
+fn default_report_top_n() -> usize {
+    10
+}
+
 impl Config {
-    pub fn load() -> Result<Self> {
-        let config = ConfigBuilder::builder()
-            .add_source(File::with_name("config"))
-            .build()?;
+    /// Loads `config.toml`, then `config.<APP_ENV>.toml` on top of it when
+    /// `APP_ENV` is set, so each environment only lists what it changes.
+    pub async fn load() -> Result<Self> {
+        let mut builder = ConfigBuilder::builder().add_source(File::with_name("config"));
+        if let Some(env) = Self::environment() {
+            builder = builder.add_source(File::with_name(&format!("config.{}", env)));
+        }
+        let config = builder.build()?;
 
-        let config: Config = config.try_deserialize()?;
+        let mut config: Config = config.try_deserialize()?;
+        config.resolve_secrets().await?;
+        config.telegram.api_url()?;
+        config.links.trade_url("BTC")?;
         Ok(config)
     }
+
+    // swaps secret references for their values so nothing downstream has to care
+    async fn resolve_secrets(&mut self) -> Result<()> {
+        let telegram = &mut self.telegram;
+        telegram.bot_token =
+            secrets::resolve_field("telegram.bot_token", &telegram.bot_token, telegram.bot_token_file.as_deref()).await?;
+        if !telegram.backup_bot_token.is_empty() || telegram.backup_bot_token_file.is_some() {
+            telegram.backup_bot_token = secrets::resolve_field(
+                "telegram.backup_bot_token",
+                &telegram.backup_bot_token,
+                telegram.backup_bot_token_file.as_deref(),
+            )
+            .await?;
+        }
+
+        let database = &mut self.database;
+        database.url = secrets::resolve_field("database.url", &database.url, database.url_file.as_deref()).await?;
+        if !database.api_key.is_empty() || database.api_key_file.is_some() {
+            database.api_key =
+                secrets::resolve_field("database.api_key", &database.api_key, database.api_key_file.as_deref()).await?;
+        }
+        if !database.replica_url.is_empty() || database.replica_url_file.is_some() {
+            database.replica_url = secrets::resolve_field(
+                "database.replica_url",
+                &database.replica_url,
+                database.replica_url_file.as_deref(),
+            )
+            .await?;
+        }
+        Ok(())
+    }
+
+    /// Each `[[tenants]]` entry's full config, base first, then its own file.
+    pub async fn load_tenants(&self) -> Result<Vec<(String, Config)>> {
+        let mut tenants: Vec<(String, Config)> = Vec::new();
+        for tenant in &self.tenants {
+            let valid_id = !tenant.id.is_empty()
+                && tenant
+                    .id
+                    .chars()
+                    .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '-' || c == '_');
+            if !valid_id || tenant.id == DEFAULT_TENANT {
+                anyhow::bail!("tenant id {:?} must be lowercase letters, digits, - or _ and not {}", tenant.id, DEFAULT_TENANT);
+            }
+            if tenants.iter().any(|(id, _)| *id == tenant.id) {
+                anyhow::bail!("tenant {} is configured twice", tenant.id);
+            }
+
+            let mut builder = ConfigBuilder::builder().add_source(File::with_name("config"));
+            if let Some(env) = Self::environment() {
+                builder = builder.add_source(File::with_name(&format!("config.{}", env)));
+            }
+            let mut config: Config = builder
+                .add_source(File::with_name(&tenant.config_file))
+                .build()?
+                .try_deserialize()?;
+            config.resolve_secrets().await?;
+            config.telegram.api_url()?;
+            config.links.trade_url("BTC")?;
+
+            if config.telegram.bot_token == self.telegram.bot_token
+                || tenants.iter().any(|(_, other)| other.telegram.bot_token == config.telegram.bot_token)
+            {
+                anyhow::bail!("tenant {} needs its own telegram.bot_token", tenant.id);
+            }
+
+            // one pool and one set of connections for everyone
+            config.database = self.database.clone();
+            config.hyperliquid = self.hyperliquid.clone();
+            // the default tenant's recorder already has the raw frames
+            config.recording.enabled = false;
+            config.tenants = Vec::new();
+            tenants.push((tenant.id.clone(), config));
+        }
+        Ok(tenants)
+    }
+
+    /// The active profile from `APP_ENV`, if any.
+    pub fn environment() -> Option<String> {
+        std::env::var(ENV_VAR)
+            .ok()
+            .map(|env| env.trim().to_lowercase())
+            .filter(|env| !env.is_empty())
+    }
 }
\ No newline at end of file
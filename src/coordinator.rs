@@ -1,19 +1,79 @@
 use anyhow::Result;
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use std::sync::Arc;
-use tokio::sync::{mpsc, RwLock};
-use tracing::{info, error, warn};
+use tokio::sync::{mpsc, Mutex, RwLock};
+use tokio::time::{interval, Duration, Instant, MissedTickBehavior};
+use tracing::{debug, info, error, warn};
 
 use crate::{
-    database::Database,
+    activity::ActivityTracker,
+    alert_digest::AlertDigest,
+    catch_up::CatchUp,
+    budget::AlertBudget,
+    clustering::TradeClusterer,
+    database::{Database, UserSubscription},
+    dedup::DeliveryGuard,
+    delivery::{self, AlertDelivery, PendingAlert},
+    history::HistoryWriter,
+    recording::TradeRecorder,
+    imbalance::ImbalanceMonitor,
+    metrics::Metrics,
+    outbound::Priority,
+    prices::{LevelRevisit, LevelWatch, PriceEngine},
+    price_alerts::PriceWatcher,
+    volatility::VolatilityMonitor,
+    vwap::{VwapMonitor, VwapTracker},
+    watchdog::Heartbeat,
+    redact,
     telegram::TelegramBot,
-    hyperliquid::{WebSocketManager, WsTrade},
+    ticker::TickerBoard,
+    candles::CandleCache,
+    hyperliquid::{CoinSymbol, FeedEvent, FeedKind, FillSampler, SubscriptionError, TradeFilter, UnknownCoin, WebSocketManager, WsBook, WsTrade},
     config::Config,
 };
 
+/// State changes handlers push to the coordinator, so it acts on them at once
+/// instead of finding out from the db partway through a trade.
 #[derive(Debug, Clone)]
-pub enum SubscriptionEvent {
-    UserSubscribed { coin: String },
+pub enum CoordinatorCommand {
+    UserSubscribed { coin: CoinSymbol },
+    /// A subscription's own threshold moved, by the user or /autotune.
+    ThresholdChanged { telegram_user_id: i64, coin: CoinSymbol, min_notional_usd: Option<f64> },
+    /// The user gets no trade alerts at all (banned, data deleted, every coin
+    /// dropped), or, with `paused: false`, is back.
+    UserPaused { telegram_user_id: i64, paused: bool },
+    /// Something else the fan-out reads about the user changed: a setting, a
+    /// destination, their routing, a single unsubscribe.
+    SettingsInvalidated { telegram_user_id: i64 },
+    FeedRestartRequested { feed_key: String },
+    ImbalanceAlertChanged { coin: CoinSymbol },
+    VwapAlertChanged { coin: CoinSymbol },
+    VolatilityAlertChanged { coin: CoinSymbol },
+    PriceAlertChanged { coin: CoinSymbol },
+    ResyncRequested { reply_chat_id: i64 },
+    /// Send one dead letter again, or all of them with `id: None`.
+    DeadLettersReprocess { id: Option<i64>, reply_chat_id: i64 },
+}
+
+/// What a dead letter retry did with each letter it took out of the table.
+#[derive(Debug, Default, PartialEq, Eq)]
+struct DeadLetterRetry {
+    requeued: usize,
+    dropped: usize,
+    in_flight: usize,
+}
+
+// a backstop for changes no command covers, e.g. made by hand in the db
+const SUBSCRIBER_CACHE_TTL: Duration = Duration::from_secs(5 * 60);
+
+type SubscriberCache = HashMap<CoinSymbol, (Instant, Arc<Vec<UserSubscription>>)>;
+
+/// Receiving ends of the coordinator's channels, consumed by `start`. They exist from
+/// `new`, so anything sent while the rest of the app is starting up is buffered.
+pub struct CoordinatorInbox {
+    commands: mpsc::UnboundedReceiver<CoordinatorCommand>,
+    trades: mpsc::UnboundedReceiver<WsTrade>,
+    books: mpsc::UnboundedReceiver<WsBook>,
 }
 
 pub struct TradeCoordinator {
@@ -21,60 +81,237 @@ pub struct TradeCoordinator {
     telegram_bot: TelegramBot,
     ws_manager: Arc<WebSocketManager>,
     config: Config,
-    active_feeds: Arc<RwLock<HashMap<String, bool>>>,
-    trade_tx: Arc<RwLock<Option<mpsc::UnboundedSender<WsTrade>>>>,
+    metrics: Metrics,
+    price_engine: PriceEngine,
+    delivery_guard: DeliveryGuard,
+    history: HistoryWriter,
+    recorder: Option<TradeRecorder>,
+    alert_budget: AlertBudget,
+    delivery: AlertDelivery,
+    catch_up: CatchUp,
+    active_feeds: Arc<RwLock<HashMap<CoinSymbol, bool>>>,
+    // each coin's subscribers as the fan-out query last returned them, kept
+    // until a command says something they depend on changed
+    subscribers: Arc<RwLock<SubscriberCache>>,
+    // who's on each coin whether or not its list above is cached, so the last
+    // one leaving stops the feed straight away
+    coin_subscribers: Arc<RwLock<HashMap<CoinSymbol, HashSet<i64>>>>,
+    // created up front so feeds can start before the loop is running; fills
+    // just queue until it is
+    trade_tx: mpsc::UnboundedSender<WsTrade>,
+    book_tx: mpsc::UnboundedSender<WsBook>,
+    active_book_feeds: Arc<RwLock<HashSet<CoinSymbol>>>,
+    imbalance_monitor: Arc<Mutex<ImbalanceMonitor>>,
+    vwap_tracker: VwapTracker,
+    activity: ActivityTracker,
+    vwap_monitor: Arc<Mutex<VwapMonitor>>,
+    volatility_monitor: Arc<Mutex<VolatilityMonitor>>,
+    price_watcher: Arc<Mutex<PriceWatcher>>,
+    heartbeat: Heartbeat,
 }
 
+// the loop beats at least this often while idle, well inside the watchdog's stall window
+const HEARTBEAT_INTERVAL: Duration = Duration::from_secs(5);
+
 impl TradeCoordinator {
     pub fn new(
         database: Database,
         telegram_bot: TelegramBot,
-        ws_manager: WebSocketManager,
+        ws_manager: Arc<WebSocketManager>,
         config: Config,
-    ) -> (Self, mpsc::UnboundedSender<SubscriptionEvent>, mpsc::UnboundedReceiver<SubscriptionEvent>) {
-        let (event_tx, event_rx) = mpsc::unbounded_channel();
+        metrics: Metrics,
+        price_engine: PriceEngine,
+        candles: CandleCache,
+    ) -> (Self, mpsc::UnboundedSender<CoordinatorCommand>, CoordinatorInbox) {
+        let (command_tx, command_rx) = mpsc::unbounded_channel();
+        let (trade_tx, trade_rx) = mpsc::unbounded_channel();
+        let (book_tx, book_rx) = mpsc::unbounded_channel();
+        let imbalance_monitor = ImbalanceMonitor::new(database.clone(), config.imbalance.clone());
+        let vwap_tracker = VwapTracker::new(&config.vwap);
+        let vwap_monitor = VwapMonitor::new(database.clone(), config.vwap.clone());
+        let volatility_monitor = VolatilityMonitor::new(database.clone(), candles, config.volatility.clone());
+        let price_watcher = PriceWatcher::new(database.clone());
+        let delivery_guard = DeliveryGuard::new(Duration::from_secs(config.dedup.ttl_secs));
+        let history = HistoryWriter::spawn(database.clone(), config.history.clone());
+        let recorder = TradeRecorder::spawn(config.recording.clone());
+        let catch_up = CatchUp::new(config.catch_up.clone());
+        let alert_budget = AlertBudget::new();
+        let delivery = AlertDelivery::spawn(
+            database.clone(),
+            telegram_bot.clone(),
+            config.clone(),
+            metrics.clone(),
+            history.clone(),
+            delivery_guard.clone(),
+            TickerBoard::spawn(database.clone(), telegram_bot.clone(), config.ticker.clone()),
+            AlertDigest::new(database.clone()),
+            catch_up.clone(),
+            alert_budget.clone(),
+        );
         
+        let activity = telegram_bot.activity();
         let coordinator = TradeCoordinator {
             database,
             telegram_bot,
-            ws_manager: Arc::new(ws_manager),
+            ws_manager,
             config,
+            metrics,
+            price_engine,
+            delivery_guard,
+            history,
+            recorder,
+            alert_budget,
+            delivery,
+            catch_up,
             active_feeds: Arc::new(RwLock::new(HashMap::new())),
-            trade_tx: Arc::new(RwLock::new(None)),
+            subscribers: Arc::new(RwLock::new(HashMap::new())),
+            coin_subscribers: Arc::new(RwLock::new(HashMap::new())),
+            trade_tx,
+            book_tx,
+            active_book_feeds: Arc::new(RwLock::new(HashSet::new())),
+            imbalance_monitor: Arc::new(Mutex::new(imbalance_monitor)),
+            vwap_tracker,
+            activity,
+            vwap_monitor: Arc::new(Mutex::new(vwap_monitor)),
+            volatility_monitor: Arc::new(Mutex::new(volatility_monitor)),
+            price_watcher: Arc::new(Mutex::new(price_watcher)),
+            heartbeat: Heartbeat::new(),
         };
         
-        (coordinator, event_tx, event_rx)
+        let inbox = CoordinatorInbox {
+            commands: command_rx,
+            trades: trade_rx,
+            books: book_rx,
+        };
+
+        (coordinator, command_tx, inbox)
     }
 
-    pub async fn start(self, mut event_rx: mpsc::UnboundedReceiver<SubscriptionEvent>) -> Result<()> {
-        let active_coins = self.database.get_active_coins().await?;
+    pub fn heartbeat(&self) -> Heartbeat {
+        self.heartbeat.clone()
+    }
 
-        let (trade_tx, mut trade_rx) = mpsc::unbounded_channel::<WsTrade>();
-        
-        {
-            let mut sender_lock = self.trade_tx.write().await;
-            *sender_lock = Some(trade_tx.clone());
+    pub fn alert_budget(&self) -> AlertBudget {
+        self.alert_budget.clone()
+    }
+
+    pub async fn start(
+        self,
+        inbox: CoordinatorInbox,
+        mut feed_event_rx: mpsc::UnboundedReceiver<FeedEvent>,
+        mut revisit_rx: mpsc::UnboundedReceiver<LevelRevisit>,
+    ) -> Result<()> {
+        let CoordinatorInbox {
+            commands: mut command_rx,
+            trades: mut trade_rx,
+            books: mut book_rx,
+        } = inbox;
+
+        // commands and trades sent before now have been queuing on the inbox, so a
+        // db hiccup here is retried rather than dropping them with the coordinator
+        let mut attempts = 0;
+        while let Err(e) = self.start_feeds_from_db().await {
+            attempts += 1;
+            if attempts >= self.config.retry.max_attempts {
+                return Err(e);
+            }
+            let delay = self
+                .config
+                .retry
+                .base_delay_ms
+                .saturating_mul(2_u64.saturating_pow(attempts - 1))
+                .min(self.config.retry.max_delay_ms);
+            warn!("couldn't start feeds from db (attempt {}): {}, retrying in {}ms", attempts, e, delay);
+            tokio::time::sleep(Duration::from_millis(delay)).await;
         }
 
-        for coin in &active_coins {
-            self.start_websocket_for_coin(coin).await;
+        // replay anything users did during startup before the buffered trades,
+        // so those trades are filtered against current subscriptions
+        let mut replayed = 0;
+        while let Ok(command) = command_rx.try_recv() {
+            replayed += 1;
+            if let Err(e) = self.handle_command(command).await {
+                error!("error handling coordinator command: {}", e);
+            }
+        }
+        if replayed > 0 || !trade_rx.is_empty() {
+            info!("replayed {} coordinator commands from startup, {} trades queued", replayed, trade_rx.len());
         }
 
+        let mut vwap_ticker = interval(Duration::from_secs(self.config.vwap.check_interval_secs.max(1)));
+        vwap_ticker.set_missed_tick_behavior(MissedTickBehavior::Delay);
+        let mut volatility_ticker = interval(Duration::from_secs(self.config.volatility.check_interval_secs.max(1)));
+        volatility_ticker.set_missed_tick_behavior(MissedTickBehavior::Delay);
+        let mut price_alert_ticker = interval(Duration::from_secs(self.config.price_alerts.check_interval_secs.max(1)));
+        price_alert_ticker.set_missed_tick_behavior(MissedTickBehavior::Delay);
+        let mut clusterer = TradeClusterer::new(Duration::from_millis(self.config.clustering.window_ms));
+        let mut cluster_ticker = interval(clusterer.flush_interval());
+        cluster_ticker.set_missed_tick_behavior(MissedTickBehavior::Delay);
+        let mut heartbeat_ticker = interval(HEARTBEAT_INTERVAL);
+        heartbeat_ticker.set_missed_tick_behavior(MissedTickBehavior::Delay);
+
         info!("coordinator listening...");
         loop {
+            self.heartbeat.beat();
             tokio::select! {
+                // nothing to do but keep the watchdog happy while it's quiet
+                _ = heartbeat_ticker.tick() => {}
+
                 Some(trade) = trade_rx.recv() => {
-                    if let Err(e) = self.process_trade(trade).await {
-                        error!("error processing trade: {}", e);
+                    for trade in clusterer.push(trade) {
+                        if let Err(e) = self.process_trade(trade).await {
+                            error!("error processing trade: {}", e);
+                        }
                     }
                 }
+
+                _ = cluster_ticker.tick() => {
+                    for trade in clusterer.flush_expired() {
+                        if let Err(e) = self.process_trade(trade).await {
+                            error!("error processing trade: {}", e);
+                        }
+                    }
+                }
+                
+                Some(book) = book_rx.recv() => {
+                    self.process_book(book).await;
+                }
                 
-                Some(event) = event_rx.recv() => {
-                    if let Err(e) = self.handle_subscription_event(event).await {
-                        error!("error handling subscription event: {}", e);
+                Some(command) = command_rx.recv() => {
+                    if let Err(e) = self.handle_command(command).await {
+                        error!("error handling coordinator command: {}", e);
                     }
                 }
                 
+                Some(feed_event) = feed_event_rx.recv() => {
+                    self.handle_feed_event(feed_event).await;
+                }
+
+                Some(revisit) = revisit_rx.recv() => {
+                    let telegram_bot = self.telegram_bot.clone();
+                    tokio::spawn(async move {
+                        if let Err(e) = telegram_bot.send_revisit_notification(&revisit).await {
+                            error!("couldn't send {} revisit alert to chat {}: {}", revisit.watch.coin, revisit.watch.chat_id, e);
+                        }
+                    });
+                }
+
+                _ = vwap_ticker.tick() => {
+                    self.check_vwap().await;
+                }
+
+                _ = price_alert_ticker.tick() => {
+                    // its db writes shouldn't hold up trades either
+                    let coordinator = self.clone();
+                    tokio::spawn(async move { coordinator.check_price_alerts().await });
+                }
+
+                _ = volatility_ticker.tick() => {
+                    // candle fetches are slow enough that they shouldn't hold up trades
+                    let coordinator = self.clone();
+                    tokio::spawn(async move { coordinator.check_volatility().await });
+                }
+                
                 else => {
                     break;
                 }
@@ -84,13 +321,428 @@ impl TradeCoordinator {
         Ok(())
     }
 
-    async fn handle_subscription_event(&self, event: SubscriptionEvent) -> Result<()> {
-        match event {
-            SubscriptionEvent::UserSubscribed { coin } => {
+    async fn handle_command(&self, command: CoordinatorCommand) -> Result<()> {
+        match command {
+            CoordinatorCommand::UserSubscribed { coin } => {
                 info!("handle user subscription to {}", coin);
+                self.subscribers.write().await.remove(&coin);
+                self.subscribers_for_coin(&coin).await?;
+                self.check_coin_subscription(&coin).await?;
+            }
+            CoordinatorCommand::ThresholdChanged { telegram_user_id, coin, min_notional_usd } => {
+                debug!("user {} {} threshold now {:?}", telegram_user_id, coin, min_notional_usd);
+                if let Some((_, subscribers)) = self.subscribers.write().await.get_mut(&coin) {
+                    for subscriber in Arc::make_mut(subscribers)
+                        .iter_mut()
+                        .filter(|subscriber| subscriber.telegram_user_id == telegram_user_id)
+                    {
+                        subscriber.min_notional_usd = min_notional_usd;
+                    }
+                }
+            }
+            CoordinatorCommand::UserPaused { telegram_user_id, paused: true } => {
+                info!("user {} paused, dropping them from the fan-out", telegram_user_id);
+                for (_, subscribers) in self.subscribers.write().await.values_mut() {
+                    if subscribers.iter().any(|subscriber| subscriber.telegram_user_id == telegram_user_id) {
+                        Arc::make_mut(subscribers).retain(|subscriber| subscriber.telegram_user_id != telegram_user_id);
+                    }
+                }
+                let mut emptied = Vec::new();
+                for (coin, users) in self.coin_subscribers.write().await.iter_mut() {
+                    if users.remove(&telegram_user_id) && users.is_empty() {
+                        emptied.push(coin.clone());
+                    }
+                }
+                for coin in emptied {
+                    self.stop_orphaned_feed(&coin).await;
+                }
+            }
+            CoordinatorCommand::UserPaused { telegram_user_id, paused: false } => {
+                // which coins they're back on is only in the db
+                info!("user {} unpaused, reloading subscribers", telegram_user_id);
+                self.subscribers.write().await.clear();
+                self.load_coin_subscribers().await?;
+                for coin in self.database.get_active_coins().await? {
+                    self.check_coin_subscription(&coin).await?;
+                }
+            }
+            CoordinatorCommand::SettingsInvalidated { telegram_user_id } => {
+                self.subscribers.write().await.retain(|_, (_, subscribers)| {
+                    !subscribers.iter().any(|subscriber| subscriber.telegram_user_id == telegram_user_id)
+                });
+                // an unsubscribe looks like any other change from here, so the
+                // user's coins are reloaded to see whether one lost its last subscriber
+                let coins: Vec<CoinSymbol> = self
+                    .coin_subscribers
+                    .read()
+                    .await
+                    .iter()
+                    .filter(|(_, users)| users.contains(&telegram_user_id))
+                    .map(|(coin, _)| coin.clone())
+                    .collect();
+                for coin in coins {
+                    self.subscribers.write().await.remove(&coin);
+                    if self.subscribers_for_coin(&coin).await?.is_empty() {
+                        self.stop_orphaned_feed(&coin).await;
+                    }
+                }
+            }
+            CoordinatorCommand::FeedRestartRequested { feed_key } => {
+                info!("restarting feed {}", feed_key);
+                self.restart_feed(&feed_key).await?;
+            }
+            CoordinatorCommand::ImbalanceAlertChanged { coin } => {
+                info!("handle imbalance alert change for {}", coin);
+                self.refresh_book_feed(&coin).await?;
+            }
+            CoordinatorCommand::VolatilityAlertChanged { coin } => {
+                info!("handle volatility alert change for {}", coin);
+                self.volatility_monitor.lock().await.reload_coin(&coin).await?;
+            }
+            CoordinatorCommand::PriceAlertChanged { coin } => {
+                info!("handle price alert change for {}", coin);
+                self.price_watcher.lock().await.reload_coin(&coin).await?;
+            }
+            CoordinatorCommand::VwapAlertChanged { coin } => {
+                info!("handle vwap alert change for {}", coin);
+                if self.vwap_monitor.lock().await.reload_coin(&coin).await? {
+                    self.check_coin_subscription(&coin).await?;
+                }
+            }
+            CoordinatorCommand::ResyncRequested { reply_chat_id } => {
+                info!("resyncing all feeds");
+                self.subscribers.write().await.clear();
+                let reply = match self.resync_feeds().await {
+                    Ok((trade_feeds, book_feeds)) => format!(
+                        "Resync complete: {} trade feeds and {} book feeds rebuilt.",
+                        trade_feeds, book_feeds
+                    ),
+                    Err(e) => {
+                        error!("resync failed: {}", e);
+                        format!("Resync failed: {}", redact::redact(&e.to_string()))
+                    }
+                };
+                self.telegram_bot.send_text(reply_chat_id, &reply, Priority::Reply).await?;
+            }
+            CoordinatorCommand::DeadLettersReprocess { id, reply_chat_id } => {
+                let reply = match self.reprocess_dead_letters(id).await {
+                    Ok(retry) if retry == DeadLetterRetry::default() => "No dead letters to reprocess.".to_string(),
+                    Ok(retry) => {
+                        let mut reply = format!("Requeued {} dead letters.", retry.requeued);
+                        if retry.dropped > 0 {
+                            reply.push_str(&format!(" Dropped {} whose subscription or destination is gone.", retry.dropped));
+                        }
+                        if retry.in_flight > 0 {
+                            reply.push_str(&format!(" Cleared {} whose alert was already being sent.", retry.in_flight));
+                        }
+                        reply
+                    }
+                    Err(e) => {
+                        error!("dead letter reprocess failed: {}", e);
+                        format!("Reprocess failed: {}", redact::redact(&e.to_string()))
+                    }
+                };
+                self.telegram_bot.send_text(reply_chat_id, &reply, Priority::Reply).await?;
+            }
+        }
+        Ok(())
+    }
+
+    // dead letters go back through delivery with the target's current
+    // settings; ones that fail again land back in the table. each leaves the
+    // table only once it's been handed over, dropped or found already in
+    // flight, so an error partway leaves the rest for the next retry
+    async fn reprocess_dead_letters(&self, id: Option<i64>) -> Result<DeadLetterRetry> {
+        let letters = self.database.get_dead_letters_to_retry(id).await?;
+        let mut retry = DeadLetterRetry::default();
+        for letter in letters {
+            let letter_id = letter.id;
+            let subscribers = self.subscribers_for_coin(&letter.coin).await?;
+            let settings = delivery::dead_letter_target(&letter).and_then(|target| {
+                subscribers
+                    .iter()
+                    .filter(|subscriber| subscriber.telegram_user_id == letter.telegram_user_id)
+                    .flat_map(delivery::alert_targets)
+                    .find(|(existing, _)| *existing == target)
+            });
+            match settings {
+                None => {
+                    info!("dropping dead letter {}: {} has no target for it any more", letter_id, letter.telegram_user_id);
+                    retry.dropped += 1;
+                }
+                Some((target, _)) if !self.delivery_guard.try_claim(&target, letter.trade_key as u64) => {
+                    debug!("skipping dead letter {}, its alert is already going to {}", letter_id, target);
+                    retry.in_flight += 1;
+                }
+                Some((target, settings)) => {
+                    self.delivery.deliver(PendingAlert::from_dead_letter(letter, target, settings));
+                    retry.requeued += 1;
+                }
+            }
+            self.database.purge_dead_letters(Some(letter_id)).await?;
+        }
+        Ok(retry)
+    }
+
+    // a trade feed the pool has lost track of is started fresh, if its coin
+    // still has a use for it
+    async fn restart_feed(&self, feed_key: &str) -> Result<()> {
+        let Err(e) = self.ws_manager.restart_feed(feed_key).await else {
+            return Ok(());
+        };
+        let coin = CoinSymbol::new(feed_key);
+        if self.active_feeds.write().await.remove(&coin).is_none() {
+            return Err(e);
+        }
+        warn!("{}, starting a new feed for {}", e, coin);
+        self.start_websocket_for_coin(&coin).await;
+        Ok(())
+    }
+
+    // the cached list when it's there, so a burst of trades on a coin costs
+    // one query rather than one each
+    async fn subscribers_for_coin(&self, coin: &CoinSymbol) -> Result<Arc<Vec<UserSubscription>>> {
+        if let Some((loaded_at, subscribers)) = self.subscribers.read().await.get(coin) {
+            if loaded_at.elapsed() < SUBSCRIBER_CACHE_TTL {
+                return Ok(subscribers.clone());
+            }
+        }
+        let subscribers = Arc::new(self.database.get_subscribers_for_coin(coin).await?);
+        self.subscribers
+            .write()
+            .await
+            .insert(coin.clone(), (Instant::now(), subscribers.clone()));
+        self.coin_subscribers.write().await.insert(
+            coin.clone(),
+            subscribers.iter().map(|subscriber| subscriber.telegram_user_id).collect(),
+        );
+        Ok(subscribers)
+    }
+
+    async fn load_coin_subscribers(&self) -> Result<()> {
+        let mut coin_subscribers: HashMap<CoinSymbol, HashSet<i64>> = HashMap::new();
+        for (coin, telegram_user_id) in self.database.get_coin_subscribers().await? {
+            coin_subscribers.entry(coin).or_default().insert(telegram_user_id);
+        }
+        *self.coin_subscribers.write().await = coin_subscribers;
+        Ok(())
+    }
+
+    // closes a coin's trade feed once nobody's subscribed, unless vwap still reads it
+    async fn stop_orphaned_feed(&self, coin: &CoinSymbol) {
+        if self.vwap_monitor.lock().await.is_watching(coin) || !self.active_feeds.read().await.contains_key(coin) {
+            return;
+        }
+        info!("no subscribers left for {}, stopping its feed", coin);
+
+        if let Err(e) = self.ws_manager.stop_trade_feed(coin).await {
+            error!("could close ws for {}: {}", coin, e);
+        } else {
+            self.active_feeds.write().await.remove(coin);
+            info!("closed ws for {}", coin);
+        }
+    }
+
+    async fn start_feeds_from_db(&self) -> Result<()> {
+        self.load_coin_subscribers().await?;
+        for coin in self.database.get_active_coins().await? {
+            self.start_websocket_for_coin(&coin).await;
+        }
+
+        for coin in self.database.get_vwap_coins().await? {
+            // vwap needs the coin's trade feed even with no subscribers on it
+            if self.vwap_monitor.lock().await.reload_coin(&coin).await? {
                 self.check_coin_subscription(&coin).await?;
             }
         }
+
+        for coin in self.database.get_volatility_coins().await? {
+            self.volatility_monitor.lock().await.reload_coin(&coin).await?;
+        }
+
+        for coin in self.database.get_price_alert_coins().await? {
+            self.price_watcher.lock().await.reload_coin(&coin).await?;
+        }
+
+        for coin in self.database.get_imbalance_coins().await? {
+            if let Err(e) = self.refresh_book_feed(&coin).await {
+                error!("couldn't start book feed for {}: {}", coin, e);
+            }
+        }
+
+        Ok(())
+    }
+
+    // tears down every trade/book feed and rebuilds them from the db
+    async fn resync_feeds(&self) -> Result<(usize, usize)> {
+        let trade_coins: Vec<CoinSymbol> = self.active_feeds.write().await.drain().map(|(coin, _)| coin).collect();
+        for coin in &trade_coins {
+            if let Err(e) = self.ws_manager.stop_trade_feed(coin).await {
+                warn!("couldn't stop {} feed during resync: {}", coin, e);
+            }
+        }
+
+        let book_coins: Vec<CoinSymbol> = self.active_book_feeds.write().await.drain().collect();
+        for coin in &book_coins {
+            if let Err(e) = self.ws_manager.stop_book_feed(coin).await {
+                warn!("couldn't stop {} book feed during resync: {}", coin, e);
+            }
+        }
+
+        info!("stopped {} trade and {} book feeds, rebuilding", trade_coins.len(), book_coins.len());
+        self.start_feeds_from_db().await?;
+
+        let trade_feeds = self.active_feeds.read().await.len();
+        let book_feeds = self.active_book_feeds.read().await.len();
+        Ok((trade_feeds, book_feeds))
+    }
+
+    async fn handle_feed_event(&self, event: FeedEvent) {
+        match event {
+            FeedEvent::Subscribed { kind, coin } => {
+                info!("hl confirmed {:?} subscription for {}", kind, coin.as_deref().unwrap_or("all coins"));
+                // hl follows a (re)subscribe with the trades we missed
+                if let (FeedKind::Trades, Some(coin)) = (kind, &coin) {
+                    self.catch_up.feed_resubscribed(coin);
+                }
+            }
+            FeedEvent::SubscriptionFailed { kind, coin, error } => {
+                error!("hl rejected {:?} subscription for {}: {}", kind, coin.as_deref().unwrap_or("all coins"), error);
+
+                // a feed hl refuses to serve just sits idle, so tear it down
+                if matches!(error, SubscriptionError::Other(_)) {
+                    return;
+                }
+                let Some(coin) = coin else {
+                    return;
+                };
+
+                let stopped = match kind {
+                    FeedKind::Trades => {
+                        self.active_feeds.write().await.remove(&coin);
+                        self.ws_manager.stop_trade_feed(&coin).await
+                    }
+                    FeedKind::L2Book => {
+                        self.active_book_feeds.write().await.remove(&coin);
+                        self.ws_manager.stop_book_feed(&coin).await
+                    }
+                    FeedKind::AllMids => Ok(()),
+                };
+
+                if let Err(e) = stopped {
+                    error!("couldn't stop rejected {:?} feed for {}: {}", kind, coin, e);
+                }
+            }
+            FeedEvent::Dropped { kind, coin } => {
+                error!("lost {:?} feed for {}", kind, coin.as_deref().unwrap_or("all coins"));
+
+                // forgotten, so the next subscribe or /resync starts it again
+                let Some(coin) = coin else {
+                    return;
+                };
+                match kind {
+                    FeedKind::Trades => {
+                        self.active_feeds.write().await.remove(&coin);
+                    }
+                    FeedKind::L2Book => {
+                        self.active_book_feeds.write().await.remove(&coin);
+                    }
+                    FeedKind::AllMids => {}
+                }
+            }
+        }
+    }
+
+    async fn process_book(&self, book: WsBook) {
+        let (triggered, band_bps) = {
+            let mut monitor = self.imbalance_monitor.lock().await;
+            (monitor.evaluate(&book), monitor.band_bps())
+        };
+
+        for (alert, imbalance) in triggered {
+            let telegram_bot = self.telegram_bot.clone();
+            tokio::spawn(async move {
+                if let Err(e) = telegram_bot
+                    .send_imbalance_notification(alert.telegram_chat_id, &alert.coin, &imbalance, band_bps)
+                    .await
+                {
+                    error!(
+                        "Failed to send imbalance notification to user {} in chat {}: {}",
+                        alert.telegram_user_id, alert.telegram_chat_id, e
+                    );
+                }
+            });
+        }
+    }
+
+    async fn check_vwap(&self) {
+        let triggered = self.vwap_monitor.lock().await.evaluate(&self.vwap_tracker);
+
+        for (alert, reading) in triggered {
+            let telegram_bot = self.telegram_bot.clone();
+            tokio::spawn(async move {
+                if let Err(e) = telegram_bot.send_vwap_notification(alert.telegram_chat_id, &alert.coin, &reading).await {
+                    error!(
+                        "Failed to send vwap notification to user {} in chat {}: {}",
+                        alert.telegram_user_id, alert.telegram_chat_id, e
+                    );
+                }
+            });
+        }
+    }
+
+    async fn check_price_alerts(&self) {
+        let fired = self.price_watcher.lock().await.evaluate(&self.price_engine).await;
+
+        for (alert, mid) in fired {
+            let telegram_bot = self.telegram_bot.clone();
+            tokio::spawn(async move {
+                if let Err(e) = telegram_bot.send_price_alert_notification(&alert, &mid).await {
+                    error!(
+                        "Failed to send price alert {} to user {} in chat {}: {}",
+                        alert.id, alert.telegram_user_id, alert.telegram_chat_id, e
+                    );
+                }
+            });
+        }
+    }
+
+    async fn check_volatility(&self) {
+        // the candle fetches go on with the monitor unlocked, so reloads
+        // don't queue behind them
+        let (coins, reader) = self.volatility_monitor.lock().await.snapshot();
+        let readings = reader.readings(coins).await;
+        let triggered = self.volatility_monitor.lock().await.evaluate(readings);
+
+        for (alert, reading) in triggered {
+            if let Err(e) = self
+                .telegram_bot
+                .send_volatility_notification(alert.telegram_chat_id, &alert.coin, &reading)
+                .await
+            {
+                error!(
+                    "Failed to send volatility notification to user {} in chat {}: {}",
+                    alert.telegram_user_id, alert.telegram_chat_id, e
+                );
+            }
+        }
+    }
+
+    // starts or stops the l2Book feed for a coin depending on whether anyone watches it
+    async fn refresh_book_feed(&self, coin: &CoinSymbol) -> Result<()> {
+        let has_watchers = self.imbalance_monitor.lock().await.reload_coin(coin).await?;
+        let feed_running = self.active_book_feeds.read().await.contains(coin);
+
+        if has_watchers && !feed_running {
+            self.ws_manager.start_book_feed(coin, self.book_tx.clone()).await?;
+            self.active_book_feeds.write().await.insert(coin.clone());
+            info!("book feed started for {}", coin);
+        } else if !has_watchers && feed_running {
+            self.active_book_feeds.write().await.remove(coin);
+            self.ws_manager.stop_book_feed(coin).await?;
+            info!("book feed stopped for {}", coin);
+        }
+
         Ok(())
     }
 
@@ -100,42 +752,146 @@ impl TradeCoordinator {
         if notional_usd < self.config.defaults.min_trade_value_usd {
             return Ok(());
         }
+        self.metrics.record_large_trade();
 
         info!("processing large {} trade: ${:.2}", trade.coin, notional_usd);
+        self.history.record_trade(&trade, notional_usd);
 
-        let subscribers = self.database.get_subscribers_for_coin(&trade.coin).await?;
+        if let Some(users) = &trade.users {
+            if let Err(e) = self.notify_wallet_watchers(&trade, users, notional_usd).await {
+                error!("couldn't notify wallet watchers for {} trade: {}", trade.coin, e);
+            }
+        }
+
+        let subscribers = self.subscribers_for_coin(&trade.coin).await?;
         
+        // a backstop for subscriptions removed behind the coordinator's back,
+        // e.g. by hand in the db
+        if subscribers.is_empty() {
+            self.stop_orphaned_feed(&trade.coin).await;
+            return Ok(());
+        }
+
+        // subscribers with their own, higher threshold sit this one out, as do
+        // ones who muted the coin from an alert; /priority coins go out
+        // whatever the user's cap, and don't spend any of it
+        let delivery::FanOut { subscribers, targets, priority_users } = delivery::fan_out(&subscribers, notional_usd);
         if subscribers.is_empty() {
-            warn!("No subscribers for {}, stopping WebSocket", trade.coin);
-            
-            // close ws if no one subbed
-            if let Err(e) = self.ws_manager.stop_trade_feed(&trade.coin).await {
-                error!("could close ws for {}: {}", trade.coin, e);
-            } else {
-                let mut active_feeds = self.active_feeds.write().await;
-                active_feeds.remove(&trade.coin.to_uppercase());
-                info!("closed ws for {}", trade.coin);
-            }
-            
             return Ok(());
         }
 
         info!("sending {} trade notification to {} subscribers", trade.coin, subscribers.len());
 
-        for subscriber in subscribers {
-            let telegram_bot = self.telegram_bot.clone();
-            let trade_clone = trade.clone();
-            let notional_clone = notional_usd;
+        let targets = delivery::resolve_duplicates(&self.telegram_bot, targets);
+
+        // /mute rules see the alert text, so they go after the per-coin
+        // thresholds, and before the daily cap so a muted alert costs nothing
+        let muted = self.telegram_bot.muted_by(&trade, notional_usd, &targets).await;
+        let mut unmuted = Vec::with_capacity(targets.len());
+        for ((user_id, target, settings), rule) in targets.into_iter().zip(muted) {
+            if let Some(rule) = rule {
+                debug!("{} alert to {} muted by user {}'s rule {}", trade.coin, target, user_id, rule.id);
+                continue;
+            }
+            unmuted.push((user_id, target, settings));
+        }
+        let targets = unmuted;
+
+        // daily caps, for users whose alerts count against one
+        let caps: HashMap<i64, (u32, i64)> = subscribers
+            .iter()
+            .filter(|subscriber| !subscriber.priority)
+            .filter_map(|subscriber| {
+                let cap = subscriber.settings.daily_alert_cap?;
+                Some((subscriber.telegram_user_id, (cap, subscriber.destination_chat_id())))
+            })
+            .collect();
 
+        // render once per trade, not once per subscriber, and off the trade
+        // path; each send waits for it on its own
+        let chart = targets
+            .iter()
+            .any(|(_, target, settings)| settings.charts_enabled && matches!(target, delivery::AlertTarget::Chat(_)))
+            .then(|| self.telegram_bot.spawn_trade_chart(&trade.coin));
+
+        let trade_key = trade.dedup_key();
+
+        // one follow-up per chat, even if several subscribers route there
+        let mut revisit_chats = HashSet::new();
+        for subscriber in subscribers.iter().filter(|s| s.settings.revisit_alerts) {
+            if revisit_chats.insert(subscriber.destination_chat_id()) {
+                self.price_engine.watch_level(LevelWatch {
+                    chat_id: subscriber.destination_chat_id(),
+                    coin: trade.coin.clone(),
+                    px: trade.px.clone(),
+                    side: trade.side.clone(),
+                    notional_usd,
+                });
+            }
+        }
+
+        // a trade spends one alert of a capped user's budget, however many
+        // targets it reaches, and only once a target is claimed, so replays
+        // and duplicates cost nothing
+        let mut admitted: HashMap<i64, bool> = HashMap::new();
+        for (telegram_user_id, target, settings) in targets {
+            // users routing to the same channel share a claim, so it gets the alert once
+            if !self.delivery_guard.try_claim(&target, trade_key) {
+                debug!("skipping duplicate {} alert for {}", trade.coin, target);
+                continue;
+            }
+
+            let mut spends_budget = false;
+            if let Some((cap, chat_id)) = caps.get(&telegram_user_id) {
+                let within = match admitted.get(&telegram_user_id) {
+                    Some(within) => *within,
+                    None => {
+                        let within = self.alert_budget.admit(telegram_user_id, *chat_id, *cap, &trade.coin, notional_usd);
+                        admitted.insert(telegram_user_id, within);
+                        spends_budget = within;
+                        within
+                    }
+                };
+                if !within {
+                    // someone else routing here may still be within theirs
+                    self.delivery_guard.release(&target, trade_key);
+                    continue;
+                }
+            }
+
+            self.delivery.deliver(PendingAlert {
+                telegram_user_id,
+                target,
+                settings,
+                trade: trade.clone(),
+                notional_usd,
+                chart: chart.clone(),
+                trade_key,
+                priority: priority_users.contains(&telegram_user_id),
+                attempts: 0,
+                catch_up: true,
+                spends_budget,
+            });
+        }
+
+        Ok(())
+    }
+
+    async fn notify_wallet_watchers(&self, trade: &WsTrade, users: &[String; 2], notional_usd: f64) -> Result<()> {
+        let watchers = self.database.get_wallet_watchers(users).await?;
+
+        for watcher in watchers {
+            let telegram_bot = self.telegram_bot.clone();
+            let trade = trade.clone();
             tokio::spawn(async move {
-                if let Err(e) = telegram_bot.send_trade_notification(
-                    subscriber.telegram_chat_id,
-                    &trade_clone.coin,
-                    &trade_clone.side,
-                    &trade_clone.px,
-                    notional_clone,
-                ).await {
-                    error!("Failed to send notification to chat {}: {}", subscriber.telegram_chat_id, e);
+                if let Err(e) = telegram_bot
+                    .send_wallet_notification(watcher.telegram_chat_id, &watcher.address, &trade, notional_usd)
+                    .await
+                {
+                    error!(
+                        "Failed to send wallet notification to user {} in chat {}: {}",
+                        watcher.telegram_user_id, watcher.telegram_chat_id, e
+                    );
                 }
             });
         }
@@ -143,44 +899,49 @@ impl TradeCoordinator {
         Ok(())
     }
 
-    async fn check_coin_subscription(&self, coin: &str) -> Result<()> {
-        let coin_upper = coin.to_uppercase();
-        
+    async fn check_coin_subscription(&self, coin: &CoinSymbol) -> Result<()> {
         {
             let active_feeds = self.active_feeds.read().await;
-            if active_feeds.contains_key(&coin_upper) {
-                info!("ws alr exists for {}", coin_upper);
+            if active_feeds.contains_key(coin) {
+                info!("ws alr exists for {}", coin);
                 return Ok(());
             }
         }
 
-        self.start_websocket_for_coin(&coin_upper).await;
+        self.start_websocket_for_coin(coin).await;
         
         Ok(())
     }
 
-    async fn start_websocket_for_coin(&self, coin: &str) {
-        let coin_upper = coin.to_uppercase();
-        
-        let trade_tx = {
-            let sender_lock = self.trade_tx.read().await;
-            match sender_lock.as_ref() {
-                Some(tx) => tx.clone(),
-                None => {
-                    error!("coordinator not up");
-                    return;
-                }
-            }
+    async fn start_websocket_for_coin(&self, coin: &CoinSymbol) {
+        // fills under the alert threshold are dropped before they're ever allocated
+        let filter = TradeFilter {
+            min_notional_usd: self.config.defaults.min_trade_value_usd,
+            seen: self.metrics.trade_counter(),
+            vwap: self.vwap_tracker.clone(),
+            activity: self.activity.clone(),
+            cluster_fills: self.config.clustering.window_ms > 0,
+            sampler: self.config.throttling.limit_for(coin).map(|limit| Arc::new(FillSampler::new(limit))),
+            recorder: self.recorder.clone(),
+            quote_rate: match self.telegram_bot.quote_asset(coin).await {
+                Some(asset) => Some(self.price_engine.quote_rate(&asset).await),
+                None => None,
+            },
         };
 
-        match self.ws_manager.start_trade_feed(&coin_upper, trade_tx).await {
+        match self.ws_manager.start_trade_feed(coin, filter, self.trade_tx.clone()).await {
             Ok(_) => {
                 let mut active_feeds = self.active_feeds.write().await;
-                active_feeds.insert(coin_upper.clone(), true);
-                info!("ws feed started for {}", coin_upper);
+                active_feeds.insert(coin.clone(), true);
+                info!("ws feed started for {}", coin);
+            }
+            // left off until something asks for the coin again, rather than
+            // retried against a symbol hl will never serve
+            Err(e) if e.is::<UnknownCoin>() => {
+                warn!("not starting a ws feed: {}", e);
             }
             Err(e) => {
-                error!("failed to start ws for {}: {}", coin_upper, e);
+                error!("failed to start ws for {}: {}", coin, e);
             }
         }
     }
@@ -193,8 +954,27 @@ impl Clone for TradeCoordinator {
             telegram_bot: self.telegram_bot.clone(),
             ws_manager: self.ws_manager.clone(),
             config: self.config.clone(),
+            metrics: self.metrics.clone(),
+            price_engine: self.price_engine.clone(),
+            delivery_guard: self.delivery_guard.clone(),
+            history: self.history.clone(),
+            recorder: self.recorder.clone(),
+            alert_budget: self.alert_budget.clone(),
+            delivery: self.delivery.clone(),
+            catch_up: self.catch_up.clone(),
             active_feeds: self.active_feeds.clone(),
+            subscribers: self.subscribers.clone(),
+            coin_subscribers: self.coin_subscribers.clone(),
             trade_tx: self.trade_tx.clone(),
+            book_tx: self.book_tx.clone(),
+            active_book_feeds: self.active_book_feeds.clone(),
+            imbalance_monitor: self.imbalance_monitor.clone(),
+            vwap_tracker: self.vwap_tracker.clone(),
+            activity: self.activity.clone(),
+            vwap_monitor: self.vwap_monitor.clone(),
+            volatility_monitor: self.volatility_monitor.clone(),
+            price_watcher: self.price_watcher.clone(),
+            heartbeat: self.heartbeat.clone(),
         }
     }
 }
\ No newline at end of file
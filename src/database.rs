@@ -1,108 +1,3585 @@
-use anyhow::Result;
-use sqlx::{PgPool, Row};
-use tracing::{info, error};
-use thiserror::Error;
-use crate::config::DatabaseConfig;
+use anyhow::{Context, Result};
+use std::collections::HashMap;
+use futures_util::future::BoxFuture;
+use sqlx::{postgres::PgPoolOptions, PgConnection, PgExecutor, PgPool, Postgres, QueryBuilder, Row, Transaction};
+use tracing::{info, warn};
+use crate::config::{DatabaseConfig, DEFAULT_TENANT};
+use crate::formatting::{Branding, EmojiStyle};
+use crate::hyperliquid::CoinSymbol;
+use crate::schema;
 
-#[derive(Error, Debug)]
-pub enum DatabaseError {
-    #[error("Database connection failed: {0}")]
-    ConnectionError(#[from] sqlx::Error),
+#[derive(Clone)]
+pub struct Database {
+    pool: PgPool, 
+    // analytics reads; the primary again when no replica is configured
+    replica: PgPool,
+}
+
+#[derive(Debug, Clone)]
+pub struct UserSubscription {
+    pub telegram_user_id: i64,
+    pub telegram_chat_id: i64,
+    pub settings: UserSettings,
+    /// Only alert on trades at least this big, when above the global minimum.
+    pub min_notional_usd: Option<f64>,
+    /// Extra places this subscription fans out to, on top of the primary chat.
+    pub destinations: Vec<Destination>,
+    /// A /priority coin: alerts go out at once, whatever batching or caps the user has.
+    pub priority: bool,
+    /// Muted from an alert's button; no alerts until then.
+    pub muted_until: Option<chrono::DateTime<chrono::Utc>>,
+}
+
+impl UserSubscription {
+    /// Where this subscriber's alerts should be sent, honouring /route.
+    pub fn destination_chat_id(&self) -> i64 {
+        self.settings.route_chat_id.unwrap_or(self.telegram_chat_id)
+    }
+
+    /// Whether a trade this big clears the subscriber's own threshold.
+    pub fn wants(&self, notional_usd: f64) -> bool {
+        self.min_notional_usd.is_none_or(|min| notional_usd >= min)
+    }
+
+    pub fn is_muted(&self) -> bool {
+        self.muted_until.is_some_and(|until| until > chrono::Utc::now())
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct AutotuneSubscription {
+    pub telegram_user_id: i64,
+    /// Where threshold change notices go.
+    pub chat_id: i64,
+    pub coin: String,
+    pub min_notional_usd: Option<f64>,
+    pub alerts_per_day: u32,
+}
+
+#[derive(Debug, Clone)]
+pub struct Destination {
+    pub id: i64,
+    pub chat_id: Option<i64>,
+    pub webhook_url: Option<String>,
+    pub coin: Option<String>,
+    pub full_precision: Option<bool>,
+    pub charts_enabled: Option<bool>,
+}
+
+impl Destination {
+    /// The user's settings with this destination's formatting overrides applied.
+    pub fn settings(&self, base: &UserSettings) -> UserSettings {
+        UserSettings {
+            full_precision: self.full_precision.unwrap_or(base.full_precision),
+            charts_enabled: self.charts_enabled.unwrap_or(base.charts_enabled),
+            ..base.clone()
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct UserSettings {
+    pub currency: Option<String>,
+    pub full_precision: bool,
+    pub charts_enabled: bool,
+    /// Group or channel alerts go to instead of the subscribing chat.
+    pub route_chat_id: Option<i64>,
+    pub revisit_alerts: bool,
+    pub duplicate_alerts: DuplicatePreference,
+    /// Add the coin's max leverage to trade alerts.
+    pub show_leverage: bool,
+    /// Most trade alerts per UTC day, the rest go into an end-of-day summary.
+    pub daily_alert_cap: Option<u32>,
+    /// Keep each coin's threshold tuned to about this many alerts a day.
+    pub autotune_alerts_per_day: Option<u32>,
+    /// Trade alerts under this arrive without a sound.
+    pub silent_below_usd: Option<f64>,
+    /// Keep one edited running-totals message per coin instead of an alert per trade.
+    pub ticker_mode: bool,
+    /// A daily note with price and volume for coins that have gone quiet.
+    pub heartbeat: bool,
+    /// Alerts when a subscribed coin makes a new high or low over `window_days`.
+    pub range_alerts: bool,
+    /// Hold trade alerts and send them as one digest every this many minutes.
+    pub digest_mins: Option<u32>,
+    /// Alerts when a subscribed coin's mark price strays from its oracle price.
+    pub divergence_alerts: bool,
+}
+
+/// Which copy to keep when a user would get an alert both in DM and in a
+/// group they're in.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum DuplicatePreference {
+    #[default]
+    Both,
+    /// Skip the DM, the group copy is enough.
+    Group,
+    /// Skip the group copy, as long as nobody else set it up.
+    Dm,
+}
+
+impl DuplicatePreference {
+    pub fn parse(preference: &str) -> Option<Self> {
+        match preference.trim().to_lowercase().as_str() {
+            "both" => Some(DuplicatePreference::Both),
+            "group" => Some(DuplicatePreference::Group),
+            "dm" => Some(DuplicatePreference::Dm),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            DuplicatePreference::Both => "both",
+            DuplicatePreference::Group => "group",
+            DuplicatePreference::Dm => "dm",
+        }
+    }
+}
+
+// user_settings columns as stored; every one is nullable since fan-out
+// left-joins users who never changed a setting
+#[derive(sqlx::FromRow)]
+struct SettingsRow {
+    currency: Option<String>,
+    full_precision: Option<bool>,
+    charts_enabled: Option<bool>,
+    route_chat_id: Option<i64>,
+    revisit_alerts: Option<bool>,
+    duplicate_alerts: Option<String>,
+    show_leverage: Option<bool>,
+    daily_alert_cap: Option<i32>,
+    autotune_alerts_per_day: Option<i32>,
+    silent_below_usd: Option<f64>,
+    ticker_mode: Option<bool>,
+    heartbeat: Option<bool>,
+    range_alerts: Option<bool>,
+    digest_mins: Option<i32>,
+    divergence_alerts: Option<bool>,
+}
+
+impl From<SettingsRow> for UserSettings {
+    fn from(row: SettingsRow) -> Self {
+        UserSettings {
+            currency: row.currency,
+            full_precision: row.full_precision.unwrap_or(false),
+            charts_enabled: row.charts_enabled.unwrap_or(false),
+            route_chat_id: row.route_chat_id,
+            revisit_alerts: row.revisit_alerts.unwrap_or(false),
+            duplicate_alerts: row
+                .duplicate_alerts
+                .as_deref()
+                .and_then(DuplicatePreference::parse)
+                .unwrap_or_default(),
+            show_leverage: row.show_leverage.unwrap_or(false),
+            daily_alert_cap: row.daily_alert_cap.map(|cap| cap.max(0) as u32),
+            autotune_alerts_per_day: row.autotune_alerts_per_day.map(|per_day| per_day.max(0) as u32),
+            silent_below_usd: row.silent_below_usd,
+            ticker_mode: row.ticker_mode.unwrap_or(false),
+            heartbeat: row.heartbeat.unwrap_or(false),
+            range_alerts: row.range_alerts.unwrap_or(false),
+            digest_mins: row.digest_mins.map(|mins| mins.max(0) as u32),
+            divergence_alerts: row.divergence_alerts.unwrap_or(false),
+        }
+    }
+}
+
+// one (subscription, destination) pair from the fan-out query
+#[derive(sqlx::FromRow)]
+struct SubscriberRow {
+    telegram_user_id: i64,
+    telegram_chat_id: i64,
+    min_notional_usd: Option<f64>,
+    priority: bool,
+    muted_until: Option<chrono::DateTime<chrono::Utc>>,
+    #[sqlx(flatten)]
+    settings: SettingsRow,
+    destination_id: Option<i64>,
+    destination_chat_id: Option<i64>,
+    destination_webhook_url: Option<String>,
+    destination_coin: Option<String>,
+    destination_full_precision: Option<bool>,
+    destination_charts_enabled: Option<bool>,
+}
+
+const SUBSCRIBERS_FOR_COIN: &str = r#"
+    SELECT us.telegram_user_id, us.telegram_chat_id, us.min_notional_usd, us.priority, us.muted_until, s.currency, s.full_precision, s.charts_enabled, s.route_chat_id, s.revisit_alerts, s.duplicate_alerts,
+        s.show_leverage, s.daily_alert_cap, s.autotune_alerts_per_day, s.silent_below_usd,
+        s.ticker_mode, s.heartbeat, s.range_alerts, s.digest_mins, s.divergence_alerts,
+        d.id AS destination_id, d.chat_id AS destination_chat_id, d.webhook_url AS destination_webhook_url,
+        d.coin AS destination_coin, d.full_precision AS destination_full_precision,
+        d.charts_enabled AS destination_charts_enabled
+    FROM user_subscriptions us
+    LEFT JOIN user_settings s ON s.telegram_user_id = us.telegram_user_id
+    LEFT JOIN destinations d ON d.telegram_user_id = us.telegram_user_id AND (d.coin IS NULL OR d.coin = us.coin)
+    WHERE us.coin = $1
+        AND us.removed_at IS NULL
+        AND NOT EXISTS (SELECT 1 FROM banned_users b WHERE b.telegram_user_id = us.telegram_user_id)
+    ORDER BY us.telegram_user_id, d.id
+"#;
+
+// fan-out runs on every alert-worthy trade, so the statement is prepared once
+// per connection and reused; generic over the executor so the benchmark can
+// run it inside its throwaway transaction
+async fn subscribers_for_coin<'e, E: PgExecutor<'e>>(executor: E, coin: &str) -> Result<Vec<UserSubscription>> {
+    let rows = sqlx::query_as::<_, SubscriberRow>(SUBSCRIBERS_FOR_COIN)
+        .persistent(true)
+        .bind(coin)
+        .fetch_all(executor)
+        .await?;
+
+    // one row per (subscription, destination), folded back into subscriptions
+    let mut subscriptions: Vec<UserSubscription> = Vec::new();
+    for row in rows {
+        let destination = row.destination_id.map(|id| Destination {
+            id,
+            chat_id: row.destination_chat_id,
+            webhook_url: row.destination_webhook_url,
+            coin: row.destination_coin,
+            full_precision: row.destination_full_precision,
+            charts_enabled: row.destination_charts_enabled,
+        });
+
+        match subscriptions.last_mut() {
+            Some(subscription) if subscription.telegram_user_id == row.telegram_user_id => {
+                subscription.destinations.extend(destination);
+            }
+            _ => subscriptions.push(UserSubscription {
+                telegram_user_id: row.telegram_user_id,
+                telegram_chat_id: row.telegram_chat_id,
+                settings: row.settings.into(),
+                min_notional_usd: row.min_notional_usd,
+                destinations: destination.into_iter().collect(),
+                priority: row.priority,
+                muted_until: row.muted_until,
+            }),
+        }
+    }
+
+    Ok(subscriptions)
+}
+
+#[derive(Debug, Clone)]
+pub struct ImbalanceAlert {
+    pub telegram_user_id: i64,
+    pub telegram_chat_id: i64,
+    pub coin: CoinSymbol,
+    pub threshold_pct: f64,
+}
+
+impl ImbalanceAlert {
+    fn from_row(row: &sqlx::postgres::PgRow) -> Self {
+        ImbalanceAlert {
+            telegram_user_id: row.get::<i64, _>("telegram_user_id"),
+            telegram_chat_id: row.get::<i64, _>("telegram_chat_id"),
+            coin: CoinSymbol::new(row.get::<&str, _>("coin")),
+            threshold_pct: row.get::<f64, _>("threshold_pct"),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct VwapAlert {
+    pub telegram_user_id: i64,
+    pub telegram_chat_id: i64,
+    pub coin: CoinSymbol,
+    pub threshold_pct: f64,
+}
+
+impl VwapAlert {
+    fn from_row(row: &sqlx::postgres::PgRow) -> Self {
+        VwapAlert {
+            telegram_user_id: row.get::<i64, _>("telegram_user_id"),
+            telegram_chat_id: row.get::<i64, _>("telegram_chat_id"),
+            coin: CoinSymbol::new(row.get::<&str, _>("coin")),
+            threshold_pct: row.get::<f64, _>("threshold_pct"),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct VolatilityAlert {
+    pub telegram_user_id: i64,
+    pub telegram_chat_id: i64,
+    pub coin: CoinSymbol,
+    pub multiple: f64,
+}
+
+impl VolatilityAlert {
+    fn from_row(row: &sqlx::postgres::PgRow) -> Self {
+        VolatilityAlert {
+            telegram_user_id: row.get::<i64, _>("telegram_user_id"),
+            telegram_chat_id: row.get::<i64, _>("telegram_chat_id"),
+            coin: CoinSymbol::new(row.get::<&str, _>("coin")),
+            multiple: row.get::<f64, _>("multiple"),
+        }
+    }
+}
+
+/// A /mute rule; see mutes.rs for how it's matched.
+#[derive(Debug, Clone)]
+pub struct MuteRule {
+    pub id: i64,
+    pub telegram_user_id: i64,
+    pub keywords: Vec<String>,
+    pub under_usd: Option<f64>,
+}
+
+impl MuteRule {
+    fn from_row(row: &sqlx::postgres::PgRow) -> Self {
+        MuteRule {
+            id: row.get::<i64, _>("id"),
+            telegram_user_id: row.get::<i64, _>("telegram_user_id"),
+            keywords: row.get::<&str, _>("keywords").split_whitespace().map(str::to_string).collect(),
+            under_usd: row.get::<Option<f64>, _>("under_usd"),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct PriceAlert {
+    pub id: i64,
+    pub telegram_user_id: i64,
+    pub telegram_chat_id: i64,
+    pub coin: CoinSymbol,
+    pub above: bool,
+    pub price: f64,
+    pub repeat_secs: Option<i64>,
+    pub state: String,
+    pub fired_at: Option<chrono::DateTime<chrono::Utc>>,
+}
+
+impl PriceAlert {
+    fn from_row(row: &sqlx::postgres::PgRow) -> Self {
+        PriceAlert {
+            id: row.get::<i64, _>("id"),
+            telegram_user_id: row.get::<i64, _>("telegram_user_id"),
+            telegram_chat_id: row.get::<i64, _>("telegram_chat_id"),
+            coin: CoinSymbol::new(row.get::<&str, _>("coin")),
+            above: row.get::<bool, _>("above"),
+            price: row.get::<f64, _>("price"),
+            repeat_secs: row.get::<Option<i64>, _>("repeat_secs"),
+            state: row.get::<String, _>("state"),
+            fired_at: row.get::<Option<chrono::DateTime<chrono::Utc>>, _>("fired_at"),
+        }
+    }
+}
+
+const PRICE_ALERT_COLUMNS: &str =
+    "id, telegram_user_id, telegram_chat_id, coin, above, price, repeat_secs, state, fired_at";
+
+#[derive(Debug, Clone)]
+pub struct FundingSummarySchedule {
+    pub telegram_user_id: i64,
+    pub telegram_chat_id: i64,
+    pub period: String,
+}
+
+#[derive(Debug, Clone)]
+pub struct WeeklyDigestSchedule {
+    pub telegram_user_id: i64,
+    pub telegram_chat_id: i64,
+}
+
+/// A coin's large-trade buy/sell totals over a span of rolled-up days.
+#[derive(Debug, Clone)]
+pub struct CoinFlow {
+    pub coin: String,
+    pub buy_usd: f64,
+    pub sell_usd: f64,
+}
+
+impl CoinFlow {
+    pub fn net_usd(&self) -> f64 {
+        self.buy_usd - self.sell_usd
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct RecordedTrade {
+    pub coin: String,
+    pub side: String,
+    pub notional_usd: f64,
+    pub traded_at: chrono::DateTime<chrono::Utc>,
+}
+
+#[derive(Debug, Clone, Copy, Default)]
+pub struct LiquidationTotals {
+    pub longs_usd: f64,
+    pub shorts_usd: f64,
+}
+
+#[derive(Debug, Clone)]
+pub struct QuietSubscription {
+    pub telegram_user_id: i64,
+    pub chat_id: i64,
+    pub coin: String,
+    pub full_precision: bool,
+}
+
+#[derive(Debug, Clone)]
+pub struct SubscriptionRecord {
+    pub coin: String,
+    pub min_notional_usd: Option<f64>,
+    pub created_at: chrono::DateTime<chrono::Utc>,
+    pub resubscribed_at: Option<chrono::DateTime<chrono::Utc>>,
+    pub removed_at: Option<chrono::DateTime<chrono::Utc>>,
+}
+
+#[derive(Debug, Clone)]
+pub struct SubscriptionChurn {
+    pub active: i64,
+    pub subscribers: i64,
+    pub added: i64,
+    pub resubscribed: i64,
+    pub removed: i64,
+}
+
+/// A coin's large trades on one utc day, by side.
+#[derive(Debug, Clone)]
+pub struct TradeRollup {
+    pub day: chrono::NaiveDate,
+    pub buys: i64,
+    pub buy_usd: f64,
+    pub sells: i64,
+    pub sell_usd: f64,
+}
+
+impl TradeRollup {
+    /// Buy share of the day's notional, 0-100.
+    pub fn buy_pct(&self) -> Option<f64> {
+        let total = self.buy_usd + self.sell_usd;
+        (total > 0.0).then(|| self.buy_usd / total * 100.0)
+    }
+}
+
+/// Counter increments over one snapshot interval.
+#[derive(Debug, Clone, Default)]
+pub struct MetricsInterval {
+    pub interval_secs: i64,
+    pub trades_seen: i64,
+    pub large_trades: i64,
+    pub alerts_sent: i64,
+    pub alerts_retried: i64,
+    pub alerts_failed: i64,
+    pub p95_latency_ms: Option<i64>,
+    /// (coin, trades, volume)
+    pub coins: Vec<(String, i64, f64)>,
+}
+
+#[derive(Debug, Clone)]
+pub struct MetricsDay {
+    pub day: chrono::NaiveDate,
+    pub trades_seen: i64,
+    pub large_trades: i64,
+    pub alerts_sent: i64,
+    pub alerts_failed: i64,
+}
+
+#[derive(Debug, Clone)]
+pub struct CoinAlertStats {
+    pub alerts: i64,
+    pub avg_notional_usd: Option<f64>,
+    pub buys: i64,
+    pub sells: i64,
+}
+
+#[derive(Debug, Clone)]
+pub struct WalletActivity {
+    pub address: String,
+    pub trades: i64,
+    pub volume_usd: f64,
+}
+
+#[derive(Debug, Clone)]
+pub struct WalletWatcher {
+    pub telegram_user_id: i64,
+    pub telegram_chat_id: i64,
+    pub address: String,
 }
 
-#[derive(Clone)]
-pub struct Database {
-    pool: PgPool, 
-}
+#[derive(Debug, Clone)]
+pub struct WalletPosition {
+    pub coin: String,
+    /// Negative when short.
+    pub size: f64,
+    pub entry_px: Option<f64>,
+    pub position_value_usd: f64,
+    pub unrealized_pnl_usd: f64,
+}
+
+#[derive(Debug, Clone)]
+pub struct WalletSnapshot {
+    pub account_value_usd: f64,
+    /// Closed pnl less fees on fills since the previous snapshot.
+    pub realized_pnl_usd: f64,
+    pub taken_at: chrono::DateTime<chrono::Utc>,
+    pub positions: Vec<WalletPosition>,
+}
+
+impl WalletSnapshot {
+    pub fn unrealized_pnl_usd(&self) -> f64 {
+        self.positions.iter().map(|position| position.unrealized_pnl_usd).sum()
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct WalletPnl {
+    pub address: String,
+    pub realized_pnl_usd: f64,
+    pub unrealized_change_usd: f64,
+    /// Oldest snapshot the pnl is measured from.
+    pub since: chrono::DateTime<chrono::Utc>,
+}
+
+impl WalletPnl {
+    pub fn total_usd(&self) -> f64 {
+        self.realized_pnl_usd + self.unrealized_change_usd
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct ChatSettings {
+    /// Pin each summary or report posted here, unpinning the one before.
+    pub pin_summaries: bool,
+    pub pinned_summary_id: Option<i32>,
+    pub branding: Branding,
+}
+
+/// A chat's running totals for one coin, as shown in its ticker message.
+#[derive(Debug, Clone)]
+pub struct TickerRecord {
+    pub message_id: Option<i32>,
+    pub day: chrono::NaiveDate,
+    pub buys: i32,
+    pub buy_usd: f64,
+    pub sells: i32,
+    pub sell_usd: f64,
+    pub last_trade: Option<String>,
+}
+
+#[derive(Debug, Clone)]
+pub struct TradeRecord {
+    pub coin: CoinSymbol,
+    pub side: String,
+    pub px: String,
+    pub sz: String,
+    pub notional_usd: f64,
+    pub tid: Option<i64>,
+    pub hash: Option<String>,
+    pub buyer: Option<String>,
+    pub seller: Option<String>,
+    pub trade_time: Option<chrono::DateTime<chrono::Utc>>,
+}
+
+#[derive(Debug, Clone)]
+pub struct NotificationRecord {
+    pub telegram_user_id: i64,
+    pub telegram_chat_id: Option<i64>,
+    pub webhook_url: Option<String>,
+    pub coin: CoinSymbol,
+    pub trade_key: i64,
+    pub side: String,
+    pub notional_usd: f64,
+    pub delivered: bool,
+    pub attempts: i32,
+    pub latency_ms: Option<i64>,
+    pub error: Option<String>,
+    /// The telegram message the alert went out as; none for webhooks, tickers and digests.
+    pub message_id: Option<i32>,
+    /// The alert format experiment and variant the message went out under.
+    pub experiment: Option<String>,
+    pub variant: Option<String>,
+}
+
+/// One variant's alerts over an experiment so far.
+#[derive(Debug, Clone)]
+pub struct VariantResult {
+    pub variant: String,
+    pub alerts: i64,
+    pub chats: i64,
+    /// Alerts with at least one tap.
+    pub tapped: i64,
+    pub taps: i64,
+}
+
+/// The last alert message sent to a chat for a coin.
+#[derive(Debug, Clone)]
+pub struct AlertMessage {
+    pub message_id: i32,
+    pub trade_key: i64,
+    pub sent_at: chrono::DateTime<chrono::Utc>,
+}
+
+/// An alert that used up its retries, kept so it can be sent again.
+#[derive(Debug, Clone)]
+pub struct DeadLetter {
+    pub id: i64,
+    pub telegram_user_id: i64,
+    pub telegram_chat_id: Option<i64>,
+    pub webhook_url: Option<String>,
+    pub coin: CoinSymbol,
+    pub side: String,
+    pub px: String,
+    pub sz: String,
+    pub trade_time: Option<chrono::DateTime<chrono::Utc>>,
+    pub tid: Option<i64>,
+    pub hash: Option<String>,
+    pub notional_usd: f64,
+    pub trade_key: i64,
+    pub priority: bool,
+    pub attempts: i32,
+    pub error: String,
+    pub created_at: chrono::DateTime<chrono::Utc>,
+}
+
+impl DeadLetter {
+    fn from_row(row: &sqlx::postgres::PgRow) -> Self {
+        DeadLetter {
+            id: row.get::<i64, _>("id"),
+            telegram_user_id: row.get::<i64, _>("telegram_user_id"),
+            telegram_chat_id: row.get::<Option<i64>, _>("telegram_chat_id"),
+            webhook_url: row.get::<Option<String>, _>("webhook_url"),
+            coin: CoinSymbol::new(row.get::<&str, _>("coin")),
+            side: row.get::<String, _>("side"),
+            px: row.get::<String, _>("px"),
+            sz: row.get::<String, _>("sz"),
+            trade_time: row.get::<Option<chrono::DateTime<chrono::Utc>>, _>("trade_time"),
+            tid: row.get::<Option<i64>, _>("tid"),
+            hash: row.get::<Option<String>, _>("hash"),
+            notional_usd: row.get::<f64, _>("notional_usd"),
+            trade_key: row.get::<i64, _>("trade_key"),
+            priority: row.get::<bool, _>("priority"),
+            attempts: row.get::<i32, _>("attempts"),
+            error: row.get::<String, _>("error"),
+            created_at: row.get::<chrono::DateTime<chrono::Utc>, _>("created_at"),
+        }
+    }
+}
+
+const DEAD_LETTER_COLUMNS: &str = "id, telegram_user_id, telegram_chat_id, webhook_url, coin, side, px, sz, trade_time, tid, hash, \
+    notional_usd, trade_key, priority, attempts, error, created_at";
+
+/// An alert held for a digest, until the digest is sent.
+#[derive(Debug, Clone)]
+pub struct HeldAlert {
+    pub id: i64,
+    pub telegram_user_id: i64,
+    pub telegram_chat_id: i64,
+    pub coin: CoinSymbol,
+    pub side: String,
+    pub px: String,
+    pub sz: String,
+    pub trade_time: Option<chrono::DateTime<chrono::Utc>>,
+    pub tid: Option<i64>,
+    pub hash: Option<String>,
+    pub notional_usd: f64,
+    pub trade_key: i64,
+    pub created_at: chrono::DateTime<chrono::Utc>,
+}
+
+// every table keyed by the user that /deletedata clears
+const USER_DATA_TABLES: &[&str] = &[
+    "user_subscriptions",
+    "user_settings",
+    "imbalance_alerts",
+    "vwap_alerts",
+    "volatility_alerts",
+    "price_alerts",
+    "mute_rules",
+    "linked_accounts",
+    "watched_wallets",
+    "destinations",
+    "notification_log",
+    "dead_letter",
+    "held_alerts",
+];
+
+pub async fn add_subscription<'e, E: PgExecutor<'e>>(
+    executor: E,
+    telegram_user_id: i64,
+    telegram_chat_id: i64,
+    coin: &str,
+) -> Result<bool> {
+    let result = sqlx::query(
+        r#"
+        INSERT INTO user_subscriptions (telegram_user_id, telegram_chat_id, coin)
+        VALUES ($1, $2, $3)
+        ON CONFLICT (tenant_id, telegram_user_id, coin) DO UPDATE
+            SET removed_at = NULL, resubscribed_at = NOW(), telegram_chat_id = EXCLUDED.telegram_chat_id
+            WHERE user_subscriptions.removed_at IS NOT NULL
+        "#
+    )
+    .bind(telegram_user_id)
+    .bind(telegram_chat_id)
+    .bind(coin.to_uppercase())
+    .execute(executor)
+    .await?;
+
+    Ok(result.rows_affected() > 0)
+}
+
+pub async fn watch_wallet<'e, E: PgExecutor<'e>>(
+    executor: E,
+    telegram_user_id: i64,
+    telegram_chat_id: i64,
+    address: &str,
+) -> Result<bool> {
+    let result = sqlx::query(
+        r#"
+        INSERT INTO watched_wallets (telegram_user_id, telegram_chat_id, address)
+        VALUES ($1, $2, $3)
+        ON CONFLICT (tenant_id, telegram_user_id, address) DO NOTHING
+        "#
+    )
+    .bind(telegram_user_id)
+    .bind(telegram_chat_id)
+    .bind(address.to_lowercase())
+    .execute(executor)
+    .await?;
+
+    Ok(result.rows_affected() > 0)
+}
+
+pub async fn unwatch_wallet<'e, E: PgExecutor<'e>>(executor: E, telegram_user_id: i64, address: &str) -> Result<bool> {
+    let result = sqlx::query("DELETE FROM watched_wallets WHERE telegram_user_id = $1 AND address = $2")
+        .bind(telegram_user_id)
+        .bind(address.to_lowercase())
+        .execute(executor)
+        .await?;
+
+    Ok(result.rows_affected() > 0)
+}
+
+/// Marks or unmarks a subscription as /priority, false if not subscribed.
+pub async fn set_subscription_priority<'e, E: PgExecutor<'e>>(
+    executor: E,
+    telegram_user_id: i64,
+    coin: &str,
+    priority: bool,
+) -> Result<bool> {
+    let result = sqlx::query("UPDATE user_subscriptions SET priority = $3 WHERE telegram_user_id = $1 AND coin = $2 AND removed_at IS NULL")
+        .bind(telegram_user_id)
+        .bind(coin.to_uppercase())
+        .bind(priority)
+        .execute(executor)
+        .await?;
+
+    Ok(result.rows_affected() > 0)
+}
+
+pub async fn get_priority_coins<'e, E: PgExecutor<'e>>(executor: E, telegram_user_id: i64) -> Result<Vec<String>> {
+    let rows = sqlx::query("SELECT coin FROM user_subscriptions WHERE telegram_user_id = $1 AND priority AND removed_at IS NULL ORDER BY coin")
+        .bind(telegram_user_id)
+        .fetch_all(executor)
+        .await?;
+
+    let coins = rows.into_iter().map(|row| row.get::<String, _>("coin")).collect();
+    Ok(coins)
+}
+
+pub async fn set_user_currency<'e, E: PgExecutor<'e>>(
+    executor: E,
+    telegram_user_id: i64,
+    currency: Option<&str>,
+) -> Result<()> {
+    sqlx::query(
+        r#"
+        INSERT INTO user_settings (telegram_user_id, currency)
+        VALUES ($1, $2)
+        ON CONFLICT (tenant_id, telegram_user_id) DO UPDATE SET currency = EXCLUDED.currency, updated_at = NOW()
+        "#
+    )
+    .bind(telegram_user_id)
+    .bind(currency.map(|c| c.to_uppercase()))
+    .execute(executor)
+    .await?;
+
+    Ok(())
+}
+
+pub async fn set_full_precision<'e, E: PgExecutor<'e>>(
+    executor: E,
+    telegram_user_id: i64,
+    full_precision: bool,
+) -> Result<()> {
+    sqlx::query(
+        r#"
+        INSERT INTO user_settings (telegram_user_id, full_precision)
+        VALUES ($1, $2)
+        ON CONFLICT (tenant_id, telegram_user_id) DO UPDATE SET full_precision = EXCLUDED.full_precision, updated_at = NOW()
+        "#
+    )
+    .bind(telegram_user_id)
+    .bind(full_precision)
+    .execute(executor)
+    .await?;
+
+    Ok(())
+}
+
+pub async fn set_charts_enabled<'e, E: PgExecutor<'e>>(
+    executor: E,
+    telegram_user_id: i64,
+    charts_enabled: bool,
+) -> Result<()> {
+    sqlx::query(
+        r#"
+        INSERT INTO user_settings (telegram_user_id, charts_enabled)
+        VALUES ($1, $2)
+        ON CONFLICT (tenant_id, telegram_user_id) DO UPDATE SET charts_enabled = EXCLUDED.charts_enabled, updated_at = NOW()
+        "#
+    )
+    .bind(telegram_user_id)
+    .bind(charts_enabled)
+    .execute(executor)
+    .await?;
+
+    Ok(())
+}
+
+pub async fn set_revisit_alerts<'e, E: PgExecutor<'e>>(
+    executor: E,
+    telegram_user_id: i64,
+    revisit_alerts: bool,
+) -> Result<()> {
+    sqlx::query(
+        r#"
+        INSERT INTO user_settings (telegram_user_id, revisit_alerts)
+        VALUES ($1, $2)
+        ON CONFLICT (tenant_id, telegram_user_id) DO UPDATE SET revisit_alerts = EXCLUDED.revisit_alerts, updated_at = NOW()
+        "#
+    )
+    .bind(telegram_user_id)
+    .bind(revisit_alerts)
+    .execute(executor)
+    .await?;
+
+    Ok(())
+}
+
+pub async fn set_show_leverage<'e, E: PgExecutor<'e>>(
+    executor: E,
+    telegram_user_id: i64,
+    show_leverage: bool,
+) -> Result<()> {
+    sqlx::query(
+        r#"
+        INSERT INTO user_settings (telegram_user_id, show_leverage)
+        VALUES ($1, $2)
+        ON CONFLICT (tenant_id, telegram_user_id) DO UPDATE SET show_leverage = EXCLUDED.show_leverage, updated_at = NOW()
+        "#
+    )
+    .bind(telegram_user_id)
+    .bind(show_leverage)
+    .execute(executor)
+    .await?;
+
+    Ok(())
+}
+
+pub async fn set_ticker_mode<'e, E: PgExecutor<'e>>(
+    executor: E,
+    telegram_user_id: i64,
+    ticker_mode: bool,
+) -> Result<()> {
+    sqlx::query(
+        r#"
+        INSERT INTO user_settings (telegram_user_id, ticker_mode)
+        VALUES ($1, $2)
+        ON CONFLICT (tenant_id, telegram_user_id) DO UPDATE SET ticker_mode = EXCLUDED.ticker_mode, updated_at = NOW()
+        "#
+    )
+    .bind(telegram_user_id)
+    .bind(ticker_mode)
+    .execute(executor)
+    .await?;
+
+    Ok(())
+}
+
+pub async fn set_heartbeat<'e, E: PgExecutor<'e>>(executor: E, telegram_user_id: i64, heartbeat: bool) -> Result<()> {
+    sqlx::query(
+        r#"
+        INSERT INTO user_settings (telegram_user_id, heartbeat)
+        VALUES ($1, $2)
+        ON CONFLICT (tenant_id, telegram_user_id) DO UPDATE SET heartbeat = EXCLUDED.heartbeat, updated_at = NOW()
+        "#
+    )
+    .bind(telegram_user_id)
+    .bind(heartbeat)
+    .execute(executor)
+    .await?;
+
+    Ok(())
+}
+
+pub async fn set_range_alerts<'e, E: PgExecutor<'e>>(
+    executor: E,
+    telegram_user_id: i64,
+    range_alerts: bool,
+) -> Result<()> {
+    sqlx::query(
+        r#"
+        INSERT INTO user_settings (telegram_user_id, range_alerts)
+        VALUES ($1, $2)
+        ON CONFLICT (tenant_id, telegram_user_id) DO UPDATE SET range_alerts = EXCLUDED.range_alerts, updated_at = NOW()
+        "#
+    )
+    .bind(telegram_user_id)
+    .bind(range_alerts)
+    .execute(executor)
+    .await?;
+
+    Ok(())
+}
+
+pub async fn set_divergence_alerts<'e, E: PgExecutor<'e>>(
+    executor: E,
+    telegram_user_id: i64,
+    divergence_alerts: bool,
+) -> Result<()> {
+    sqlx::query(
+        r#"
+        INSERT INTO user_settings (telegram_user_id, divergence_alerts)
+        VALUES ($1, $2)
+        ON CONFLICT (tenant_id, telegram_user_id) DO UPDATE SET divergence_alerts = EXCLUDED.divergence_alerts, updated_at = NOW()
+        "#
+    )
+    .bind(telegram_user_id)
+    .bind(divergence_alerts)
+    .execute(executor)
+    .await?;
+
+    Ok(())
+}
+
+pub async fn set_autotune<'e, E: PgExecutor<'e>>(
+    executor: E,
+    telegram_user_id: i64,
+    alerts_per_day: Option<u32>,
+) -> Result<()> {
+    sqlx::query(
+        r#"
+        INSERT INTO user_settings (telegram_user_id, autotune_alerts_per_day)
+        VALUES ($1, $2)
+        ON CONFLICT (tenant_id, telegram_user_id) DO UPDATE SET autotune_alerts_per_day = EXCLUDED.autotune_alerts_per_day, updated_at = NOW()
+        "#
+    )
+    .bind(telegram_user_id)
+    .bind(alerts_per_day.map(|per_day| per_day as i32))
+    .execute(executor)
+    .await?;
+
+    Ok(())
+}
+
+pub async fn set_silent_below<'e, E: PgExecutor<'e>>(
+    executor: E,
+    telegram_user_id: i64,
+    silent_below_usd: Option<f64>,
+) -> Result<()> {
+    sqlx::query(
+        r#"
+        INSERT INTO user_settings (telegram_user_id, silent_below_usd)
+        VALUES ($1, $2)
+        ON CONFLICT (tenant_id, telegram_user_id) DO UPDATE SET silent_below_usd = EXCLUDED.silent_below_usd, updated_at = NOW()
+        "#
+    )
+    .bind(telegram_user_id)
+    .bind(silent_below_usd)
+    .execute(executor)
+    .await?;
+
+    Ok(())
+}
+
+pub async fn set_daily_alert_cap<'e, E: PgExecutor<'e>>(
+    executor: E,
+    telegram_user_id: i64,
+    daily_alert_cap: Option<u32>,
+) -> Result<()> {
+    sqlx::query(
+        r#"
+        INSERT INTO user_settings (telegram_user_id, daily_alert_cap)
+        VALUES ($1, $2)
+        ON CONFLICT (tenant_id, telegram_user_id) DO UPDATE SET daily_alert_cap = EXCLUDED.daily_alert_cap, updated_at = NOW()
+        "#
+    )
+    .bind(telegram_user_id)
+    .bind(daily_alert_cap.map(|cap| cap as i32))
+    .execute(executor)
+    .await?;
+
+    Ok(())
+}
+
+pub async fn set_digest_mins<'e, E: PgExecutor<'e>>(
+    executor: E,
+    telegram_user_id: i64,
+    digest_mins: Option<u32>,
+) -> Result<()> {
+    sqlx::query(
+        r#"
+        INSERT INTO user_settings (telegram_user_id, digest_mins)
+        VALUES ($1, $2)
+        ON CONFLICT (tenant_id, telegram_user_id) DO UPDATE SET digest_mins = EXCLUDED.digest_mins, updated_at = NOW()
+        "#
+    )
+    .bind(telegram_user_id)
+    .bind(digest_mins.map(|mins| mins as i32))
+    .execute(executor)
+    .await?;
+
+    Ok(())
+}
+
+pub async fn set_duplicate_alerts<'e, E: PgExecutor<'e>>(
+    executor: E,
+    telegram_user_id: i64,
+    preference: DuplicatePreference,
+) -> Result<()> {
+    sqlx::query(
+        r#"
+        INSERT INTO user_settings (telegram_user_id, duplicate_alerts)
+        VALUES ($1, $2)
+        ON CONFLICT (tenant_id, telegram_user_id) DO UPDATE SET duplicate_alerts = EXCLUDED.duplicate_alerts, updated_at = NOW()
+        "#
+    )
+    .bind(telegram_user_id)
+    .bind(preference.as_str())
+    .execute(executor)
+    .await?;
+
+    Ok(())
+}
+
+pub async fn set_route_chat_id<'e, E: PgExecutor<'e>>(
+    executor: E,
+    telegram_user_id: i64,
+    route_chat_id: Option<i64>,
+) -> Result<()> {
+    sqlx::query(
+        r#"
+        INSERT INTO user_settings (telegram_user_id, route_chat_id)
+        VALUES ($1, $2)
+        ON CONFLICT (tenant_id, telegram_user_id) DO UPDATE SET route_chat_id = EXCLUDED.route_chat_id, updated_at = NOW()
+        "#
+    )
+    .bind(telegram_user_id)
+    .bind(route_chat_id)
+    .execute(executor)
+    .await?;
+
+    Ok(())
+}
+
+pub async fn set_imbalance_alert<'e, E: PgExecutor<'e>>(
+    executor: E,
+    telegram_user_id: i64,
+    telegram_chat_id: i64,
+    coin: &str,
+    threshold_pct: f64,
+) -> Result<()> {
+    sqlx::query(
+        r#"
+        INSERT INTO imbalance_alerts (telegram_user_id, telegram_chat_id, coin, threshold_pct)
+        VALUES ($1, $2, $3, $4)
+        ON CONFLICT (tenant_id, telegram_user_id, coin) DO UPDATE
+        SET telegram_chat_id = EXCLUDED.telegram_chat_id, threshold_pct = EXCLUDED.threshold_pct
+        "#
+    )
+    .bind(telegram_user_id)
+    .bind(telegram_chat_id)
+    .bind(coin.to_uppercase())
+    .bind(threshold_pct)
+    .execute(executor)
+    .await?;
+
+    Ok(())
+}
+
+pub async fn set_vwap_alert<'e, E: PgExecutor<'e>>(
+    executor: E,
+    telegram_user_id: i64,
+    telegram_chat_id: i64,
+    coin: &str,
+    threshold_pct: f64,
+) -> Result<()> {
+    sqlx::query(
+        r#"
+        INSERT INTO vwap_alerts (telegram_user_id, telegram_chat_id, coin, threshold_pct)
+        VALUES ($1, $2, $3, $4)
+        ON CONFLICT (tenant_id, telegram_user_id, coin) DO UPDATE
+        SET telegram_chat_id = EXCLUDED.telegram_chat_id, threshold_pct = EXCLUDED.threshold_pct
+        "#
+    )
+    .bind(telegram_user_id)
+    .bind(telegram_chat_id)
+    .bind(coin.to_uppercase())
+    .bind(threshold_pct)
+    .execute(executor)
+    .await?;
+
+    Ok(())
+}
+
+pub async fn set_volatility_alert<'e, E: PgExecutor<'e>>(
+    executor: E,
+    telegram_user_id: i64,
+    telegram_chat_id: i64,
+    coin: &str,
+    multiple: f64,
+) -> Result<()> {
+    sqlx::query(
+        r#"
+        INSERT INTO volatility_alerts (telegram_user_id, telegram_chat_id, coin, multiple)
+        VALUES ($1, $2, $3, $4)
+        ON CONFLICT (tenant_id, telegram_user_id, coin) DO UPDATE
+        SET telegram_chat_id = EXCLUDED.telegram_chat_id, multiple = EXCLUDED.multiple
+        "#
+    )
+    .bind(telegram_user_id)
+    .bind(telegram_chat_id)
+    .bind(coin.to_uppercase())
+    .bind(multiple)
+    .execute(executor)
+    .await?;
+
+    Ok(())
+}
+
+pub async fn link_account<'e, E: PgExecutor<'e>>(executor: E, telegram_user_id: i64, address: &str) -> Result<bool> {
+    let result = sqlx::query(
+        r#"
+        INSERT INTO linked_accounts (telegram_user_id, address)
+        VALUES ($1, $2)
+        ON CONFLICT (tenant_id, telegram_user_id, address) DO NOTHING
+        "#
+    )
+    .bind(telegram_user_id)
+    .bind(address.to_lowercase())
+    .execute(executor)
+    .await?;
+
+    Ok(result.rows_affected() > 0)
+}
+
+/// `period` is "daily", "weekly", or `None` to turn summaries off. The
+/// clock starts now, so the first summary arrives one period later.
+pub async fn set_funding_summary<'e, E: PgExecutor<'e>>(
+    executor: E,
+    telegram_user_id: i64,
+    telegram_chat_id: i64,
+    period: Option<&str>,
+) -> Result<()> {
+    sqlx::query(
+        r#"
+        INSERT INTO user_settings (telegram_user_id, funding_summary, funding_summary_chat_id, funding_summary_sent_at)
+        VALUES ($1, $2, $3, NOW())
+        ON CONFLICT (tenant_id, telegram_user_id) DO UPDATE SET
+            funding_summary = EXCLUDED.funding_summary,
+            funding_summary_chat_id = EXCLUDED.funding_summary_chat_id,
+            funding_summary_sent_at = EXCLUDED.funding_summary_sent_at,
+            updated_at = NOW()
+        "#
+    )
+    .bind(telegram_user_id)
+    .bind(period)
+    .bind(telegram_chat_id)
+    .execute(executor)
+    .await?;
+
+    Ok(())
+}
+
+/// `offset_mins` is the user's utc offset; None turns the digest off.
+pub async fn set_weekly_digest<'e, E: PgExecutor<'e>>(
+    executor: E,
+    telegram_user_id: i64,
+    telegram_chat_id: i64,
+    offset_mins: Option<i32>,
+) -> Result<()> {
+    sqlx::query(
+        r#"
+        INSERT INTO user_settings (telegram_user_id, weekly_digest_offset_mins, weekly_digest_chat_id)
+        VALUES ($1, $2, $3)
+        ON CONFLICT (tenant_id, telegram_user_id) DO UPDATE SET
+            weekly_digest_offset_mins = EXCLUDED.weekly_digest_offset_mins,
+            weekly_digest_chat_id = EXCLUDED.weekly_digest_chat_id,
+            updated_at = NOW()
+        "#
+    )
+    .bind(telegram_user_id)
+    .bind(offset_mins)
+    .bind(telegram_chat_id)
+    .execute(executor)
+    .await?;
+
+    Ok(())
+}
+
+pub async fn get_user_destinations<'e, E: PgExecutor<'e>>(
+    executor: E,
+    telegram_user_id: i64,
+) -> Result<Vec<Destination>> {
+    let rows = sqlx::query(
+        r#"
+        SELECT id, chat_id, webhook_url, coin, full_precision, charts_enabled
+        FROM destinations
+        WHERE telegram_user_id = $1
+        ORDER BY id
+        "#
+    )
+        .bind(telegram_user_id)
+        .fetch_all(executor)
+        .await?;
+
+    let destinations = rows
+        .into_iter()
+        .map(|row| Destination {
+            id: row.get::<i64, _>("id"),
+            chat_id: row.get::<Option<i64>, _>("chat_id"),
+            webhook_url: row.get::<Option<String>, _>("webhook_url"),
+            coin: row.get::<Option<String>, _>("coin"),
+            full_precision: row.get::<Option<bool>, _>("full_precision"),
+            charts_enabled: row.get::<Option<bool>, _>("charts_enabled"),
+        })
+        .collect();
+
+    Ok(destinations)
+}
+
+pub async fn add_destination<'e, E: PgExecutor<'e>>(
+    executor: E,
+    telegram_user_id: i64,
+    chat_id: Option<i64>,
+    webhook_url: Option<&str>,
+    coin: Option<&str>,
+    full_precision: Option<bool>,
+    charts_enabled: Option<bool>,
+) -> Result<i64> {
+    let row = sqlx::query(
+        r#"
+        INSERT INTO destinations (telegram_user_id, chat_id, webhook_url, coin, full_precision, charts_enabled)
+        VALUES ($1, $2, $3, $4, $5, $6)
+        RETURNING id
+        "#
+    )
+    .bind(telegram_user_id)
+    .bind(chat_id)
+    .bind(webhook_url)
+    .bind(coin.map(str::to_uppercase))
+    .bind(full_precision)
+    .bind(charts_enabled)
+    .fetch_one(executor)
+    .await?;
+
+    Ok(row.get::<i64, _>("id"))
+}
+
+pub async fn add_mute_rule<'e, E: PgExecutor<'e>>(
+    executor: E,
+    telegram_user_id: i64,
+    keywords: &[String],
+    under_usd: Option<f64>,
+) -> Result<MuteRule> {
+    let row = sqlx::query(
+        "INSERT INTO mute_rules (telegram_user_id, keywords, under_usd) VALUES ($1, $2, $3)
+         RETURNING id, telegram_user_id, keywords, under_usd"
+    )
+        .bind(telegram_user_id)
+        .bind(keywords.join(" "))
+        .bind(under_usd)
+        .fetch_one(executor)
+        .await?;
+
+    Ok(MuteRule::from_row(&row))
+}
+
+/// Mutes an active subscription until `until`; false if there isn't one.
+pub async fn mute_subscription<'e, E: PgExecutor<'e>>(
+    executor: E,
+    telegram_user_id: i64,
+    coin: &str,
+    until: chrono::DateTime<chrono::Utc>,
+) -> Result<bool> {
+    let result = sqlx::query("UPDATE user_subscriptions SET muted_until = $3 WHERE telegram_user_id = $1 AND coin = $2 AND removed_at IS NULL")
+        .bind(telegram_user_id)
+        .bind(coin.to_uppercase())
+        .bind(until)
+        .execute(executor)
+        .await?;
+
+    Ok(result.rows_affected() > 0)
+}
+
+/// Sets or clears a subscription's own minimum, false if not subscribed.
+pub async fn set_subscription_threshold<'e, E: PgExecutor<'e>>(
+    executor: E,
+    telegram_user_id: i64,
+    coin: &str,
+    min_notional_usd: Option<f64>,
+) -> Result<bool> {
+    let result = sqlx::query("UPDATE user_subscriptions SET min_notional_usd = $3 WHERE telegram_user_id = $1 AND coin = $2 AND removed_at IS NULL")
+        .bind(telegram_user_id)
+        .bind(coin.to_uppercase())
+        .bind(min_notional_usd)
+        .execute(executor)
+        .await?;
+
+    Ok(result.rows_affected() > 0)
+}
+
+pub async fn add_price_alert<'e, E: PgExecutor<'e>>(
+    executor: E,
+    telegram_user_id: i64,
+    telegram_chat_id: i64,
+    coin: &str,
+    above: bool,
+    price: f64,
+    repeat_secs: Option<i64>,
+) -> Result<i64> {
+    let id = sqlx::query_scalar(
+        r#"
+        INSERT INTO price_alerts (telegram_user_id, telegram_chat_id, coin, above, price, repeat_secs)
+        VALUES ($1, $2, $3, $4, $5, $6)
+        RETURNING id
+        "#
+    )
+    .bind(telegram_user_id)
+    .bind(telegram_chat_id)
+    .bind(coin.to_uppercase())
+    .bind(above)
+    .bind(price)
+    .bind(repeat_secs)
+    .fetch_one(executor)
+    .await?;
+
+    Ok(id)
+}
+
+// first half of every advisory lock key the bot takes, so they can't collide
+// with another application's locks on a shared database
+const ADVISORY_LOCK_CLASS: i32 = 0x484c_5447; // "HLTG"
+
+/// Takes the session-level advisory lock for `key` on `connection`, where it
+/// stays until the session ends; false if another session holds it.
+pub async fn try_advisory_lock(connection: &mut PgConnection, key: &str) -> Result<bool> {
+    let locked = sqlx::query_scalar("SELECT pg_try_advisory_lock($1, hashtext($2))")
+        .bind(ADVISORY_LOCK_CLASS)
+        .bind(key)
+        .fetch_one(connection)
+        .await?;
+    Ok(locked)
+}
+
+pub struct FanoutBenchmark {
+    pub subscribers: usize,
+    pub timings: Vec<std::time::Duration>,
+    pub plan: Vec<String>,
+}
+
+impl Database {
+    pub async fn new(config: &DatabaseConfig) -> Result<Self> {
+        let database = Self::connect(config).await?;
+        database.migrate().await?;
+        Ok(database)
+    }
+
+    /// Connects as the default tenant without touching the schema, so a
+    /// standby can wait for the running instance before migrating under it.
+    pub async fn connect(config: &DatabaseConfig) -> Result<Self> {
+        info!("connecting to db...");
+        
+        let pool = connect(&config.url, DEFAULT_TENANT).await?;
+        
+        info!("connected to db");
+
+        let replica = if config.replica_url.is_empty() {
+            pool.clone()
+        } else {
+            // a replica outage shouldn't keep the bot from starting, the reads
+            // can run on the primary meanwhile
+            match connect(&config.replica_url, DEFAULT_TENANT).await {
+                Ok(replica) => {
+                    info!("connected to read replica");
+                    replica
+                }
+                Err(e) => {
+                    warn!("couldn't connect to read replica, running analytics on the primary: {}", e);
+                    pool.clone()
+                }
+            }
+        };
+
+        Ok(Database { pool, replica })
+    }
+
+    /// Applies pending migrations, then checks the schema has what this build needs.
+    pub async fn migrate(&self) -> Result<()> {
+        sqlx::migrate!("./migrations")
+            .run(&self.pool)
+            .await
+            .context("couldn't apply db migrations; the database may be from a newer build, or an applied migration was edited")?;
+        info!("db migrations applied");
+
+        schema::verify(self).await
+    }
+
+    /// Another bot's view of the same database: its own pools, pinned to `tenant`.
+    /// Migrations and the schema check are left to the default tenant's `migrate`.
+    pub async fn for_tenant(&self, config: &DatabaseConfig, tenant: &str) -> Result<Self> {
+        let pool = connect(&config.url, tenant).await?;
+
+        // superusers and BYPASSRLS roles see every tenant's rows regardless
+        let bypasses: bool =
+            sqlx::query_scalar("SELECT rolsuper OR rolbypassrls FROM pg_roles WHERE rolname = current_user")
+                .fetch_one(&pool)
+                .await?;
+        if bypasses {
+            anyhow::bail!(
+                "the db role bypasses row-level security, so tenant {} would see every tenant's data; \
+                 connect as a role without SUPERUSER or BYPASSRLS",
+                tenant
+            );
+        }
+
+        let replica = if config.replica_url.is_empty() {
+            pool.clone()
+        } else {
+            match connect(&config.replica_url, tenant).await {
+                Ok(replica) => replica,
+                Err(e) => {
+                    warn!("couldn't connect tenant {} to read replica, running analytics on the primary: {}", tenant, e);
+                    pool.clone()
+                }
+            }
+        };
+
+        info!("connected to db as tenant {}", tenant);
+        Ok(Database { pool, replica })
+    }
+
+    /// A connection taken out of the pool for good, e.g. to hold session-level
+    /// locks on for the life of the process.
+    pub async fn dedicated_connection(&self) -> Result<PgConnection> {
+        Ok(self.pool.acquire().await?.detach())
+    }
+
+    /// Every (table, column) in the current schema.
+    pub async fn schema_columns(&self) -> Result<Vec<(String, String)>> {
+        let rows = sqlx::query(
+            "SELECT table_name::TEXT AS table_name, column_name::TEXT AS column_name FROM information_schema.columns WHERE table_schema = current_schema()"
+        )
+            .fetch_all(&self.pool)
+            .await?;
+
+        Ok(rows
+            .into_iter()
+            .map(|row| (row.get::<String, _>("table_name"), row.get::<String, _>("column_name")))
+            .collect())
+    }
+
+    pub async fn schema_indexes(&self) -> Result<Vec<String>> {
+        let rows = sqlx::query("SELECT indexname::TEXT AS indexname FROM pg_indexes WHERE schemaname = current_schema()")
+            .fetch_all(&self.pool)
+            .await?;
+
+        Ok(rows.into_iter().map(|row| row.get::<String, _>("indexname")).collect())
+    }
+
+    /// Migrations sqlx recorded as started but not finished.
+    pub async fn failed_migrations(&self) -> Result<Vec<(i64, String)>> {
+        let rows = sqlx::query("SELECT version, description FROM _sqlx_migrations WHERE NOT success ORDER BY version")
+            .fetch_all(&self.pool)
+            .await?;
+
+        Ok(rows
+            .into_iter()
+            .map(|row| (row.get::<i64, _>("version"), row.get::<String, _>("description")))
+            .collect())
+    }
+
+    /// Runs `operation` in a transaction, committing if it returns Ok and
+    /// rolling back otherwise, so handlers can make several changes that land
+    /// together or not at all. The free functions in this module take the
+    /// transaction as their executor:
+    ///
+    /// ```ignore
+    /// database.transaction(move |tx| Box::pin(async move {
+    ///     database::add_subscription(&mut **tx, user_id, chat_id, "ETH").await?;
+    ///     database::watch_wallet(&mut **tx, user_id, chat_id, &address).await
+    /// })).await?;
+    /// ```
+    pub async fn transaction<T, F>(&self, operation: F) -> Result<T>
+    where
+        F: for<'t> FnOnce(&'t mut Transaction<'static, Postgres>) -> BoxFuture<'t, Result<T>>,
+    {
+        let mut tx = self.pool.begin().await?;
+        match operation(&mut tx).await {
+            Ok(value) => {
+                tx.commit().await?;
+                Ok(value)
+            }
+            Err(e) => {
+                // dropping would roll back too, but not until the connection is
+                // next used; do it now so locks are released straight away
+                if let Err(rollback) = tx.rollback().await {
+                    warn!("couldn't roll back db transaction: {}", rollback);
+                }
+                Err(e)
+            }
+        }
+    }
+
+    pub async fn add_subscription(
+        &self, 
+        telegram_user_id: i64, 
+        telegram_chat_id: i64, 
+        coin: &str
+    ) -> Result<bool> {
+        add_subscription(&self.pool, telegram_user_id, telegram_chat_id, coin).await
+    }
+
+    /// Subscribes to every coin or none of them, returning the ones that are new.
+    pub async fn add_subscriptions(&self, telegram_user_id: i64, telegram_chat_id: i64, coins: &[String]) -> Result<Vec<String>> {
+        let coins = coins.to_vec();
+        self.transaction(move |tx| {
+            Box::pin(async move {
+                let mut added = Vec::new();
+                for coin in coins {
+                    if add_subscription(&mut **tx, telegram_user_id, telegram_chat_id, &coin).await? {
+                        added.push(coin);
+                    }
+                }
+                Ok(added)
+            })
+        })
+        .await
+    }
+
+    /// Drops every subscription, returning the coins that were removed.
+    pub async fn remove_all_subscriptions(&self, telegram_user_id: i64) -> Result<Vec<String>> {
+        self.transaction(move |tx| {
+            Box::pin(async move {
+                let mut coins: Vec<String> =
+                    sqlx::query_scalar(
+                    "UPDATE user_subscriptions SET removed_at = NOW() WHERE telegram_user_id = $1 AND removed_at IS NULL RETURNING coin"
+                )
+                        .bind(telegram_user_id)
+                        .fetch_all(&mut **tx)
+                        .await?;
+                coins.sort();
+                Ok(coins)
+            })
+        })
+        .await
+    }
+
+    /// Deactivates rather than deletes, so the coin's threshold survives a resubscribe.
+    pub async fn remove_subscription(&self, telegram_user_id: i64, coin: &str) -> Result<bool> {
+        let result = sqlx::query(
+            "UPDATE user_subscriptions SET removed_at = NOW() WHERE telegram_user_id = $1 AND coin = $2 AND removed_at IS NULL"
+        )
+            .bind(telegram_user_id)
+            .bind(coin.to_uppercase())
+            .execute(&self.pool)
+            .await?;
+
+        Ok(result.rows_affected() > 0)
+    }
+
+    pub async fn get_user_subscriptions(&self, telegram_user_id: i64) -> Result<Vec<String>> {
+        let rows = sqlx::query("SELECT coin FROM user_subscriptions WHERE telegram_user_id = $1 AND removed_at IS NULL ORDER BY coin")
+            .bind(telegram_user_id)
+            .fetch_all(&self.pool)
+            .await?;
+
+        let coins = rows.into_iter().map(|row| row.get::<String, _>("coin")).collect();
+        Ok(coins)
+    }
+
+    /// Active subscriptions muted from the alert buttons, with when each mute ends.
+    pub async fn get_subscription_mutes(&self, telegram_user_id: i64) -> Result<Vec<(String, chrono::DateTime<chrono::Utc>)>> {
+        let rows = sqlx::query(
+            "SELECT coin, muted_until FROM user_subscriptions
+             WHERE telegram_user_id = $1 AND removed_at IS NULL AND muted_until > NOW() ORDER BY coin"
+        )
+            .bind(telegram_user_id)
+            .fetch_all(&self.pool)
+            .await?;
+
+        Ok(rows
+            .into_iter()
+            .map(|row| (row.get::<String, _>("coin"), row.get::<chrono::DateTime<chrono::Utc>, _>("muted_until")))
+            .collect())
+    }
+
+    /// Every coin the user has subscribed to, removed ones included, newest first.
+    pub async fn get_subscription_history(&self, telegram_user_id: i64) -> Result<Vec<SubscriptionRecord>> {
+        let rows = sqlx::query(
+            r#"
+            SELECT coin, min_notional_usd, created_at, resubscribed_at, removed_at
+            FROM user_subscriptions
+            WHERE telegram_user_id = $1
+            ORDER BY removed_at IS NOT NULL, COALESCE(removed_at, resubscribed_at, created_at) DESC
+            "#
+        )
+            .bind(telegram_user_id)
+            .fetch_all(&self.replica)
+            .await?;
+
+        Ok(rows
+            .into_iter()
+            .map(|row| SubscriptionRecord {
+                coin: row.get::<String, _>("coin"),
+                min_notional_usd: row.get::<Option<f64>, _>("min_notional_usd"),
+                created_at: row.get("created_at"),
+                resubscribed_at: row.get("resubscribed_at"),
+                removed_at: row.get("removed_at"),
+            })
+            .collect())
+    }
+
+    /// Subscriptions gained, regained and lost over the last `days`. A coin
+    /// removed and resubscribed within the window counts as resubscribed only.
+    pub async fn subscription_churn(&self, days: i64) -> Result<SubscriptionChurn> {
+        let row = sqlx::query(
+            r#"
+            SELECT COUNT(*) FILTER (WHERE removed_at IS NULL) AS active,
+                COUNT(DISTINCT telegram_user_id) FILTER (WHERE removed_at IS NULL) AS subscribers,
+                COUNT(*) FILTER (WHERE created_at > NOW() - make_interval(days => $1::INT)) AS added,
+                COUNT(*) FILTER (
+                    WHERE removed_at IS NULL AND resubscribed_at > NOW() - make_interval(days => $1::INT)
+                ) AS resubscribed,
+                COUNT(*) FILTER (WHERE removed_at > NOW() - make_interval(days => $1::INT)) AS removed
+            FROM user_subscriptions
+            "#
+        )
+            .bind(days)
+            .fetch_one(&self.replica)
+            .await?;
+
+        Ok(SubscriptionChurn {
+            active: row.get::<i64, _>("active"),
+            subscribers: row.get::<i64, _>("subscribers"),
+            added: row.get::<i64, _>("added"),
+            resubscribed: row.get::<i64, _>("resubscribed"),
+            removed: row.get::<i64, _>("removed"),
+        })
+    }
+
+    /// Mutes an active subscription until `until`; false if there isn't one.
+    pub async fn mute_subscription(&self, telegram_user_id: i64, coin: &str, until: chrono::DateTime<chrono::Utc>) -> Result<bool> {
+        mute_subscription(&self.pool, telegram_user_id, coin, until).await
+    }
+
+    /// Sets or clears a subscription's own minimum, false if not subscribed.
+    pub async fn set_subscription_threshold(&self, telegram_user_id: i64, coin: &str, min_notional_usd: Option<f64>) -> Result<bool> {
+        set_subscription_threshold(&self.pool, telegram_user_id, coin, min_notional_usd).await
+    }
+
+    /// Marks or unmarks a subscription as /priority, false if not subscribed.
+    pub async fn set_subscription_priority(&self, telegram_user_id: i64, coin: &str, priority: bool) -> Result<bool> {
+        set_subscription_priority(&self.pool, telegram_user_id, coin, priority).await
+    }
+
+    pub async fn get_priority_coins(&self, telegram_user_id: i64) -> Result<Vec<String>> {
+        get_priority_coins(&self.pool, telegram_user_id).await
+    }
+
+    /// Active subscriptions with their own minimum, and what it is.
+    pub async fn get_subscription_thresholds(&self, telegram_user_id: i64) -> Result<Vec<(String, f64)>> {
+        let rows = sqlx::query(
+            "SELECT coin, min_notional_usd FROM user_subscriptions
+             WHERE telegram_user_id = $1 AND removed_at IS NULL AND min_notional_usd IS NOT NULL ORDER BY coin"
+        )
+            .bind(telegram_user_id)
+            .fetch_all(&self.pool)
+            .await?;
+
+        Ok(rows
+            .into_iter()
+            .map(|row| (row.get::<String, _>("coin"), row.get::<f64, _>("min_notional_usd")))
+            .collect())
+    }
+
+    pub async fn get_subscription_threshold(&self, telegram_user_id: i64, coin: &str) -> Result<Option<f64>> {
+        let row = sqlx::query("SELECT min_notional_usd FROM user_subscriptions WHERE telegram_user_id = $1 AND coin = $2 AND removed_at IS NULL")
+            .bind(telegram_user_id)
+            .bind(coin.to_uppercase())
+            .fetch_optional(&self.pool)
+            .await?;
+
+        Ok(row.and_then(|row| row.get::<Option<f64>, _>("min_notional_usd")))
+    }
+
+    pub async fn get_subscribers_for_coin(&self, coin: &CoinSymbol) -> Result<Vec<UserSubscription>> {
+        subscribers_for_coin(&self.pool, coin.as_str()).await
+    }
+
+    /// Seeds `rows` throwaway subscriptions spread over `coins` coins, times
+    /// the fan-out query for the busiest coin `iterations` times and returns
+    /// the timings plus its plan. Runs in a transaction that's rolled back, so
+    /// nothing is left behind.
+    pub async fn benchmark_fanout(&self, rows: i64, coins: i64, iterations: usize) -> Result<FanoutBenchmark> {
+        let mut tx = self.pool.begin().await?;
+
+        // negative ids can't collide with real telegram users
+        sqlx::query(
+            "INSERT INTO user_subscriptions (telegram_user_id, telegram_chat_id, coin, min_notional_usd)
+             SELECT -g, -g, 'BENCH' || (g % $2), CASE WHEN g % 4 = 0 THEN 250000 END
+             FROM generate_series(1, $1) AS g"
+        )
+            .bind(rows)
+            .bind(coins)
+            .execute(&mut *tx)
+            .await?;
+        sqlx::query(
+            "INSERT INTO user_settings (telegram_user_id, full_precision, charts_enabled)
+             SELECT -g, g % 2 = 0, g % 5 = 0 FROM generate_series(1, $1, 3) AS g
+             ON CONFLICT (tenant_id, telegram_user_id) DO NOTHING"
+        )
+            .bind(rows)
+            .execute(&mut *tx)
+            .await?;
+        sqlx::query(
+            "INSERT INTO destinations (telegram_user_id, chat_id)
+             SELECT -g, -g - $1 FROM generate_series(1, $1, 10) AS g"
+        )
+            .bind(rows)
+            .execute(&mut *tx)
+            .await?;
+        sqlx::query("ANALYZE user_subscriptions, user_settings, destinations")
+            .execute(&mut *tx)
+            .await?;
+
+        let coin = "BENCH0";
+        let mut timings = Vec::with_capacity(iterations);
+        let mut subscribers = 0;
+        for _ in 0..iterations {
+            let started_at = std::time::Instant::now();
+            subscribers = subscribers_for_coin(&mut *tx, coin).await?.len();
+            timings.push(started_at.elapsed());
+        }
+
+        let plan = sqlx::query(&format!("EXPLAIN ANALYZE {}", SUBSCRIBERS_FOR_COIN))
+            .bind(coin)
+            .fetch_all(&mut *tx)
+            .await?
+            .into_iter()
+            .map(|row| row.get::<String, _>(0))
+            .collect();
+
+        tx.rollback().await?;
+        Ok(FanoutBenchmark { subscribers, timings, plan })
+    }
+
+    pub async fn get_user_settings(&self, telegram_user_id: i64) -> Result<UserSettings> {
+        let row = sqlx::query_as::<_, SettingsRow>("SELECT currency, full_precision, charts_enabled, route_chat_id, revisit_alerts, duplicate_alerts, show_leverage, daily_alert_cap, autotune_alerts_per_day, silent_below_usd, ticker_mode, heartbeat, range_alerts, digest_mins, divergence_alerts FROM user_settings WHERE telegram_user_id = $1")
+            .bind(telegram_user_id)
+            .fetch_optional(&self.pool)
+            .await?;
+
+        Ok(row.map(UserSettings::from).unwrap_or_default())
+    }
+
+    pub async fn set_user_currency(&self, telegram_user_id: i64, currency: Option<&str>) -> Result<()> {
+        set_user_currency(&self.pool, telegram_user_id, currency).await
+    }
+
+    pub async fn set_full_precision(&self, telegram_user_id: i64, full_precision: bool) -> Result<()> {
+        set_full_precision(&self.pool, telegram_user_id, full_precision).await
+    }
+
+    pub async fn set_charts_enabled(&self, telegram_user_id: i64, charts_enabled: bool) -> Result<()> {
+        set_charts_enabled(&self.pool, telegram_user_id, charts_enabled).await
+    }
+
+    pub async fn set_revisit_alerts(&self, telegram_user_id: i64, revisit_alerts: bool) -> Result<()> {
+        set_revisit_alerts(&self.pool, telegram_user_id, revisit_alerts).await
+    }
+
+    pub async fn set_show_leverage(&self, telegram_user_id: i64, show_leverage: bool) -> Result<()> {
+        set_show_leverage(&self.pool, telegram_user_id, show_leverage).await
+    }
+
+    pub async fn set_daily_alert_cap(&self, telegram_user_id: i64, daily_alert_cap: Option<u32>) -> Result<()> {
+        set_daily_alert_cap(&self.pool, telegram_user_id, daily_alert_cap).await
+    }
+
+    pub async fn set_ticker_mode(&self, telegram_user_id: i64, ticker_mode: bool) -> Result<()> {
+        set_ticker_mode(&self.pool, telegram_user_id, ticker_mode).await
+    }
+
+    pub async fn set_heartbeat(&self, telegram_user_id: i64, heartbeat: bool) -> Result<()> {
+        set_heartbeat(&self.pool, telegram_user_id, heartbeat).await
+    }
+
+    pub async fn set_range_alerts(&self, telegram_user_id: i64, range_alerts: bool) -> Result<()> {
+        set_range_alerts(&self.pool, telegram_user_id, range_alerts).await
+    }
+
+    pub async fn set_divergence_alerts(&self, telegram_user_id: i64, divergence_alerts: bool) -> Result<()> {
+        set_divergence_alerts(&self.pool, telegram_user_id, divergence_alerts).await
+    }
+
+    pub async fn set_digest_mins(&self, telegram_user_id: i64, digest_mins: Option<u32>) -> Result<()> {
+        set_digest_mins(&self.pool, telegram_user_id, digest_mins).await
+    }
+
+    /// Coins with at least one active subscriber who has /rangealerts on.
+    pub async fn get_range_alert_coins(&self) -> Result<Vec<CoinSymbol>> {
+        let rows = sqlx::query(
+            r#"
+            SELECT DISTINCT us.coin
+            FROM user_subscriptions us
+            JOIN user_settings s ON s.telegram_user_id = us.telegram_user_id
+            WHERE s.range_alerts AND us.removed_at IS NULL
+            ORDER BY us.coin
+            "#
+        )
+            .fetch_all(&self.pool)
+            .await?;
+
+        let coins = rows.iter().map(|row| CoinSymbol::new(row.get::<&str, _>("coin"))).collect();
+        Ok(coins)
+    }
+
+    /// Subscriptions of users with /heartbeat on that have had no trade over
+    /// their threshold (or `global_min` without one) for `hours`, and haven't
+    /// had a note about it within that time either.
+    pub async fn get_quiet_subscriptions(&self, global_min: f64, hours: i64) -> Result<Vec<QuietSubscription>> {
+        let rows = sqlx::query(
+            r#"
+            SELECT us.telegram_user_id, COALESCE(s.route_chat_id, us.telegram_chat_id) AS chat_id, us.coin,
+                COALESCE(s.full_precision, FALSE) AS full_precision
+            FROM user_subscriptions us
+            JOIN user_settings s ON s.telegram_user_id = us.telegram_user_id
+            WHERE s.heartbeat
+                AND us.removed_at IS NULL
+                AND COALESCE(us.resubscribed_at, us.created_at) < NOW() - make_interval(hours => $2::INT)
+                AND (us.heartbeat_sent_at IS NULL OR us.heartbeat_sent_at < NOW() - make_interval(hours => $2::INT))
+                AND NOT EXISTS (
+                    SELECT 1 FROM trade_history t
+                    WHERE t.coin = us.coin
+                        AND t.trade_time > NOW() - make_interval(hours => $2::INT)
+                        AND t.notional_usd >= COALESCE(us.min_notional_usd, $1)
+                )
+                AND NOT EXISTS (SELECT 1 FROM banned_users b WHERE b.telegram_user_id = us.telegram_user_id)
+            "#
+        )
+            .bind(global_min)
+            .bind(hours)
+            .fetch_all(&self.pool)
+            .await?;
+
+        Ok(rows
+            .into_iter()
+            .map(|row| QuietSubscription {
+                telegram_user_id: row.get::<i64, _>("telegram_user_id"),
+                chat_id: row.get::<i64, _>("chat_id"),
+                coin: row.get::<String, _>("coin"),
+                full_precision: row.get::<bool, _>("full_precision"),
+            })
+            .collect())
+    }
+
+    pub async fn mark_heartbeat_sent(&self, telegram_user_id: i64, coin: &str) -> Result<()> {
+        sqlx::query("UPDATE user_subscriptions SET heartbeat_sent_at = NOW() WHERE telegram_user_id = $1 AND coin = $2")
+            .bind(telegram_user_id)
+            .bind(coin)
+            .execute(&self.pool)
+            .await?;
+
+        Ok(())
+    }
+
+    pub async fn set_silent_below(&self, telegram_user_id: i64, silent_below_usd: Option<f64>) -> Result<()> {
+        set_silent_below(&self.pool, telegram_user_id, silent_below_usd).await
+    }
+
+    pub async fn set_autotune(&self, telegram_user_id: i64, alerts_per_day: Option<u32>) -> Result<()> {
+        set_autotune(&self.pool, telegram_user_id, alerts_per_day).await
+    }
+
+    /// Every subscription whose owner has /autotune on.
+    pub async fn get_autotune_subscriptions(&self) -> Result<Vec<AutotuneSubscription>> {
+        let rows = sqlx::query(
+            r#"
+            SELECT us.telegram_user_id, COALESCE(s.route_chat_id, us.telegram_chat_id) AS chat_id, us.coin,
+                us.min_notional_usd, s.autotune_alerts_per_day
+            FROM user_subscriptions us
+            JOIN user_settings s ON s.telegram_user_id = us.telegram_user_id
+            WHERE s.autotune_alerts_per_day IS NOT NULL
+                AND us.removed_at IS NULL
+                AND NOT EXISTS (SELECT 1 FROM banned_users b WHERE b.telegram_user_id = us.telegram_user_id)
+            "#
+        )
+            .fetch_all(&self.pool)
+            .await?;
+
+        let subscriptions = rows
+            .into_iter()
+            .map(|row| AutotuneSubscription {
+                telegram_user_id: row.get::<i64, _>("telegram_user_id"),
+                chat_id: row.get::<i64, _>("chat_id"),
+                coin: row.get::<String, _>("coin"),
+                min_notional_usd: row.get::<Option<f64>, _>("min_notional_usd"),
+                alerts_per_day: row.get::<i32, _>("autotune_alerts_per_day").max(1) as u32,
+            })
+            .collect();
+
+        Ok(subscriptions)
+    }
+
+    pub async fn set_duplicate_alerts(&self, telegram_user_id: i64, preference: DuplicatePreference) -> Result<()> {
+        set_duplicate_alerts(&self.pool, telegram_user_id, preference).await
+    }
+
+    pub async fn set_route_chat_id(&self, telegram_user_id: i64, route_chat_id: Option<i64>) -> Result<()> {
+        set_route_chat_id(&self.pool, telegram_user_id, route_chat_id).await
+    }
+
+    pub async fn set_imbalance_alert(
+        &self,
+        telegram_user_id: i64,
+        telegram_chat_id: i64,
+        coin: &str,
+        threshold_pct: f64,
+    ) -> Result<()> {
+        set_imbalance_alert(&self.pool, telegram_user_id, telegram_chat_id, coin, threshold_pct).await
+    }
+
+    pub async fn remove_imbalance_alert(&self, telegram_user_id: i64, coin: &str) -> Result<bool> {
+        let result = sqlx::query("DELETE FROM imbalance_alerts WHERE telegram_user_id = $1 AND coin = $2")
+            .bind(telegram_user_id)
+            .bind(coin.to_uppercase())
+            .execute(&self.pool)
+            .await?;
+
+        Ok(result.rows_affected() > 0)
+    }
+
+    pub async fn get_user_imbalance_alerts(&self, telegram_user_id: i64) -> Result<Vec<ImbalanceAlert>> {
+        let rows = sqlx::query(
+            "SELECT telegram_user_id, telegram_chat_id, coin, threshold_pct FROM imbalance_alerts WHERE telegram_user_id = $1 ORDER BY coin"
+        )
+            .bind(telegram_user_id)
+            .fetch_all(&self.pool)
+            .await?;
+
+        Ok(rows.iter().map(ImbalanceAlert::from_row).collect())
+    }
+
+    pub async fn get_imbalance_alerts_for_coin(&self, coin: &CoinSymbol) -> Result<Vec<ImbalanceAlert>> {
+        let rows = sqlx::query(
+            "SELECT telegram_user_id, telegram_chat_id, coin, threshold_pct FROM imbalance_alerts WHERE coin = $1"
+        )
+            .bind(coin.as_str())
+            .fetch_all(&self.pool)
+            .await?;
+
+        Ok(rows.iter().map(ImbalanceAlert::from_row).collect())
+    }
+
+    pub async fn get_imbalance_coins(&self) -> Result<Vec<CoinSymbol>> {
+        let rows = sqlx::query("SELECT DISTINCT coin FROM imbalance_alerts ORDER BY coin")
+            .fetch_all(&self.pool)
+            .await?;
+
+        let coins = rows.iter().map(|row| CoinSymbol::new(row.get::<&str, _>("coin"))).collect();
+        Ok(coins)
+    }
+
+    pub async fn set_vwap_alert(
+        &self,
+        telegram_user_id: i64,
+        telegram_chat_id: i64,
+        coin: &str,
+        threshold_pct: f64,
+    ) -> Result<()> {
+        set_vwap_alert(&self.pool, telegram_user_id, telegram_chat_id, coin, threshold_pct).await
+    }
+
+    pub async fn remove_vwap_alert(&self, telegram_user_id: i64, coin: &str) -> Result<bool> {
+        let result = sqlx::query("DELETE FROM vwap_alerts WHERE telegram_user_id = $1 AND coin = $2")
+            .bind(telegram_user_id)
+            .bind(coin.to_uppercase())
+            .execute(&self.pool)
+            .await?;
+
+        Ok(result.rows_affected() > 0)
+    }
+
+    pub async fn get_user_vwap_alerts(&self, telegram_user_id: i64) -> Result<Vec<VwapAlert>> {
+        let rows = sqlx::query(
+            "SELECT telegram_user_id, telegram_chat_id, coin, threshold_pct FROM vwap_alerts WHERE telegram_user_id = $1 ORDER BY coin"
+        )
+            .bind(telegram_user_id)
+            .fetch_all(&self.pool)
+            .await?;
+
+        Ok(rows.iter().map(VwapAlert::from_row).collect())
+    }
+
+    pub async fn get_vwap_alerts_for_coin(&self, coin: &CoinSymbol) -> Result<Vec<VwapAlert>> {
+        let rows = sqlx::query(
+            "SELECT telegram_user_id, telegram_chat_id, coin, threshold_pct FROM vwap_alerts WHERE coin = $1"
+        )
+            .bind(coin.as_str())
+            .fetch_all(&self.pool)
+            .await?;
+
+        Ok(rows.iter().map(VwapAlert::from_row).collect())
+    }
+
+    pub async fn get_vwap_coins(&self) -> Result<Vec<CoinSymbol>> {
+        let rows = sqlx::query("SELECT DISTINCT coin FROM vwap_alerts ORDER BY coin")
+            .fetch_all(&self.pool)
+            .await?;
+
+        let coins = rows.iter().map(|row| CoinSymbol::new(row.get::<&str, _>("coin"))).collect();
+        Ok(coins)
+    }
+
+    pub async fn set_volatility_alert(
+        &self,
+        telegram_user_id: i64,
+        telegram_chat_id: i64,
+        coin: &str,
+        multiple: f64,
+    ) -> Result<()> {
+        set_volatility_alert(&self.pool, telegram_user_id, telegram_chat_id, coin, multiple).await
+    }
+
+    pub async fn remove_volatility_alert(&self, telegram_user_id: i64, coin: &str) -> Result<bool> {
+        let result = sqlx::query("DELETE FROM volatility_alerts WHERE telegram_user_id = $1 AND coin = $2")
+            .bind(telegram_user_id)
+            .bind(coin.to_uppercase())
+            .execute(&self.pool)
+            .await?;
+
+        Ok(result.rows_affected() > 0)
+    }
+
+    pub async fn get_user_volatility_alerts(&self, telegram_user_id: i64) -> Result<Vec<VolatilityAlert>> {
+        let rows = sqlx::query(
+            "SELECT telegram_user_id, telegram_chat_id, coin, multiple FROM volatility_alerts WHERE telegram_user_id = $1 ORDER BY coin"
+        )
+            .bind(telegram_user_id)
+            .fetch_all(&self.pool)
+            .await?;
+
+        Ok(rows.iter().map(VolatilityAlert::from_row).collect())
+    }
+
+    pub async fn get_volatility_alerts_for_coin(&self, coin: &CoinSymbol) -> Result<Vec<VolatilityAlert>> {
+        let rows = sqlx::query(
+            "SELECT telegram_user_id, telegram_chat_id, coin, multiple FROM volatility_alerts WHERE coin = $1"
+        )
+            .bind(coin.as_str())
+            .fetch_all(&self.pool)
+            .await?;
+
+        Ok(rows.iter().map(VolatilityAlert::from_row).collect())
+    }
+
+    pub async fn get_volatility_coins(&self) -> Result<Vec<CoinSymbol>> {
+        let rows = sqlx::query("SELECT DISTINCT coin FROM volatility_alerts ORDER BY coin")
+            .fetch_all(&self.pool)
+            .await?;
+
+        let coins = rows.iter().map(|row| CoinSymbol::new(row.get::<&str, _>("coin"))).collect();
+        Ok(coins)
+    }
+
+    pub async fn add_price_alert(
+        &self,
+        telegram_user_id: i64,
+        telegram_chat_id: i64,
+        coin: &str,
+        above: bool,
+        price: f64,
+        repeat_secs: Option<i64>,
+    ) -> Result<i64> {
+        add_price_alert(&self.pool, telegram_user_id, telegram_chat_id, coin, above, price, repeat_secs).await
+    }
+
+    /// Removes every price alert the user has on `coin`, returning how many there were.
+    pub async fn remove_price_alerts(&self, telegram_user_id: i64, coin: &str) -> Result<u64> {
+        let result = sqlx::query("DELETE FROM price_alerts WHERE telegram_user_id = $1 AND coin = $2")
+            .bind(telegram_user_id)
+            .bind(coin.to_uppercase())
+            .execute(&self.pool)
+            .await?;
+
+        Ok(result.rows_affected())
+    }
+
+    /// Saves one check's worth of price alert changes together: one-shots
+    /// that fired are deleted, the rest moved to their new state, with
+    /// `fired_at` stamped on the ones that fired.
+    pub async fn save_price_alert_steps(&self, removed: Vec<i64>, moved: Vec<(i64, &'static str, bool)>) -> Result<()> {
+        self.transaction(move |tx| {
+            Box::pin(async move {
+                if !removed.is_empty() {
+                    sqlx::query("DELETE FROM price_alerts WHERE id = ANY($1)")
+                        .bind(&removed)
+                        .execute(&mut **tx)
+                        .await?;
+                }
+                if !moved.is_empty() {
+                    let ids: Vec<i64> = moved.iter().map(|(id, _, _)| *id).collect();
+                    let states: Vec<&str> = moved.iter().map(|(_, state, _)| *state).collect();
+                    let fired: Vec<bool> = moved.iter().map(|(_, _, fired)| *fired).collect();
+                    sqlx::query(
+                        "UPDATE price_alerts AS alert
+                         SET state = step.state, fired_at = CASE WHEN step.fired THEN NOW() ELSE alert.fired_at END
+                         FROM UNNEST($1::BIGINT[], $2::TEXT[], $3::BOOLEAN[]) AS step(id, state, fired)
+                         WHERE alert.id = step.id"
+                    )
+                        .bind(&ids)
+                        .bind(&states)
+                        .bind(&fired)
+                        .execute(&mut **tx)
+                        .await?;
+                }
+                Ok(())
+            })
+        })
+        .await
+    }
+
+    pub async fn get_user_price_alerts(&self, telegram_user_id: i64) -> Result<Vec<PriceAlert>> {
+        let rows = sqlx::query(&format!(
+            "SELECT {} FROM price_alerts WHERE telegram_user_id = $1 ORDER BY coin, price",
+            PRICE_ALERT_COLUMNS
+        ))
+            .bind(telegram_user_id)
+            .fetch_all(&self.pool)
+            .await?;
+
+        Ok(rows.iter().map(PriceAlert::from_row).collect())
+    }
+
+    pub async fn get_price_alerts_for_coin(&self, coin: &CoinSymbol) -> Result<Vec<PriceAlert>> {
+        let rows = sqlx::query(&format!("SELECT {} FROM price_alerts WHERE coin = $1", PRICE_ALERT_COLUMNS))
+            .bind(coin.as_str())
+            .fetch_all(&self.pool)
+            .await?;
+
+        Ok(rows.iter().map(PriceAlert::from_row).collect())
+    }
+
+    pub async fn add_mute_rule(&self, telegram_user_id: i64, keywords: &[String], under_usd: Option<f64>) -> Result<MuteRule> {
+        add_mute_rule(&self.pool, telegram_user_id, keywords, under_usd).await
+    }
+
+    /// Removes one rule, or all of the user's with `None`; returns how many went.
+    pub async fn remove_mute_rules(&self, telegram_user_id: i64, rule_id: Option<i64>) -> Result<u64> {
+        let result = sqlx::query("DELETE FROM mute_rules WHERE telegram_user_id = $1 AND ($2::BIGINT IS NULL OR id = $2)")
+            .bind(telegram_user_id)
+            .bind(rule_id)
+            .execute(&self.pool)
+            .await?;
+
+        Ok(result.rows_affected())
+    }
+
+    pub async fn get_user_mute_rules(&self, telegram_user_id: i64) -> Result<Vec<MuteRule>> {
+        let rows = sqlx::query("SELECT id, telegram_user_id, keywords, under_usd FROM mute_rules WHERE telegram_user_id = $1 ORDER BY id")
+            .bind(telegram_user_id)
+            .fetch_all(&self.pool)
+            .await?;
+
+        Ok(rows.iter().map(MuteRule::from_row).collect())
+    }
+
+    pub async fn get_mute_rules(&self) -> Result<Vec<MuteRule>> {
+        let rows = sqlx::query("SELECT id, telegram_user_id, keywords, under_usd FROM mute_rules ORDER BY id")
+            .fetch_all(&self.pool)
+            .await?;
+
+        Ok(rows.iter().map(MuteRule::from_row).collect())
+    }
+
+    pub async fn get_price_alert_coins(&self) -> Result<Vec<CoinSymbol>> {
+        let rows = sqlx::query("SELECT DISTINCT coin FROM price_alerts ORDER BY coin")
+            .fetch_all(&self.pool)
+            .await?;
+
+        let coins = rows.iter().map(|row| CoinSymbol::new(row.get::<&str, _>("coin"))).collect();
+        Ok(coins)
+    }
+
+    /// (coin, user) for every subscription the fan-out would see.
+    pub async fn get_coin_subscribers(&self) -> Result<Vec<(CoinSymbol, i64)>> {
+        let rows = sqlx::query(
+            "SELECT us.coin, us.telegram_user_id FROM user_subscriptions us
+             WHERE us.removed_at IS NULL
+                AND NOT EXISTS (SELECT 1 FROM banned_users b WHERE b.telegram_user_id = us.telegram_user_id)"
+        )
+            .fetch_all(&self.pool)
+            .await?;
+
+        Ok(rows
+            .iter()
+            .map(|row| (CoinSymbol::new(row.get::<&str, _>("coin")), row.get::<i64, _>("telegram_user_id")))
+            .collect())
+    }
+
+    pub async fn get_active_coins(&self) -> Result<Vec<CoinSymbol>> {
+        let rows = sqlx::query("SELECT DISTINCT coin FROM user_subscriptions WHERE removed_at IS NULL ORDER BY coin")
+            .fetch_all(&self.pool)
+            .await?;
+
+        let coins = rows.iter().map(|row| CoinSymbol::new(row.get::<&str, _>("coin"))).collect();
+        Ok(coins)
+    }
+
+    pub async fn link_account(&self, telegram_user_id: i64, address: &str) -> Result<bool> {
+        link_account(&self.pool, telegram_user_id, address).await
+    }
+
+    pub async fn unlink_account(&self, telegram_user_id: i64, address: &str) -> Result<bool> {
+        let result = sqlx::query("DELETE FROM linked_accounts WHERE telegram_user_id = $1 AND address = $2")
+            .bind(telegram_user_id)
+            .bind(address.to_lowercase())
+            .execute(&self.pool)
+            .await?;
+
+        Ok(result.rows_affected() > 0)
+    }
+
+    pub async fn get_linked_accounts(&self, telegram_user_id: i64) -> Result<Vec<String>> {
+        let rows = sqlx::query("SELECT address FROM linked_accounts WHERE telegram_user_id = $1 ORDER BY created_at")
+            .bind(telegram_user_id)
+            .fetch_all(&self.pool)
+            .await?;
+
+        let addresses = rows.into_iter().map(|row| row.get::<String, _>("address")).collect();
+        Ok(addresses)
+    }
+
+    /// `period` is "daily", "weekly", or `None` to turn summaries off. The
+    /// clock starts now, so the first summary arrives one period later.
+    pub async fn set_funding_summary(
+        &self,
+        telegram_user_id: i64,
+        telegram_chat_id: i64,
+        period: Option<&str>,
+    ) -> Result<()> {
+        set_funding_summary(&self.pool, telegram_user_id, telegram_chat_id, period).await
+    }
+
+    pub async fn get_funding_summary(&self, telegram_user_id: i64) -> Result<Option<String>> {
+        let row = sqlx::query("SELECT funding_summary FROM user_settings WHERE telegram_user_id = $1")
+            .bind(telegram_user_id)
+            .fetch_optional(&self.pool)
+            .await?;
+
+        Ok(row.and_then(|row| row.get::<Option<String>, _>("funding_summary")))
+    }
+
+    pub async fn get_due_funding_summaries(&self) -> Result<Vec<FundingSummarySchedule>> {
+        let rows = sqlx::query(
+            r#"
+            SELECT telegram_user_id, funding_summary_chat_id, funding_summary
+            FROM user_settings
+            WHERE funding_summary IS NOT NULL
+              AND funding_summary_chat_id IS NOT NULL
+              AND funding_summary_sent_at <= NOW() - CASE funding_summary
+                  WHEN 'weekly' THEN INTERVAL '7 days'
+                  ELSE INTERVAL '1 day'
+              END
+            "#
+        )
+            .fetch_all(&self.pool)
+            .await?;
+
+        let schedules = rows
+            .into_iter()
+            .map(|row| FundingSummarySchedule {
+                telegram_user_id: row.get::<i64, _>("telegram_user_id"),
+                telegram_chat_id: row.get::<i64, _>("funding_summary_chat_id"),
+                period: row.get::<String, _>("funding_summary"),
+            })
+            .collect();
+
+        Ok(schedules)
+    }
+
+    pub async fn mark_funding_summary_sent(&self, telegram_user_id: i64) -> Result<()> {
+        sqlx::query("UPDATE user_settings SET funding_summary_sent_at = NOW() WHERE telegram_user_id = $1")
+            .bind(telegram_user_id)
+            .execute(&self.pool)
+            .await?;
+
+        Ok(())
+    }
+
+    /// `offset_mins` is the user's utc offset; None turns the digest off.
+    pub async fn set_weekly_digest(&self, telegram_user_id: i64, telegram_chat_id: i64, offset_mins: Option<i32>) -> Result<()> {
+        set_weekly_digest(&self.pool, telegram_user_id, telegram_chat_id, offset_mins).await
+    }
+
+    pub async fn get_weekly_digest(&self, telegram_user_id: i64) -> Result<Option<i32>> {
+        let row = sqlx::query("SELECT weekly_digest_offset_mins FROM user_settings WHERE telegram_user_id = $1")
+            .bind(telegram_user_id)
+            .fetch_optional(&self.pool)
+            .await?;
+
+        Ok(row.and_then(|row| row.get::<Option<i32>, _>("weekly_digest_offset_mins")))
+    }
+
+    /// Users for whom it's now sunday evening (18:00 on) in their own time
+    /// and who haven't had this week's digest.
+    pub async fn get_due_weekly_digests(&self) -> Result<Vec<WeeklyDigestSchedule>> {
+        let rows = sqlx::query(
+            r#"
+            SELECT telegram_user_id, weekly_digest_chat_id
+            FROM (
+                SELECT telegram_user_id, weekly_digest_chat_id, weekly_digest_sent_at,
+                    (NOW() AT TIME ZONE 'UTC') + make_interval(mins => weekly_digest_offset_mins) AS local_now
+                FROM user_settings
+                WHERE weekly_digest_offset_mins IS NOT NULL AND weekly_digest_chat_id IS NOT NULL
+            ) digests
+            WHERE EXTRACT(ISODOW FROM local_now) = 7
+              AND EXTRACT(HOUR FROM local_now) >= 18
+              AND (weekly_digest_sent_at IS NULL OR weekly_digest_sent_at < NOW() - INTERVAL '1 day')
+            "#
+        )
+            .fetch_all(&self.pool)
+            .await?;
+
+        Ok(rows
+            .into_iter()
+            .map(|row| WeeklyDigestSchedule {
+                telegram_user_id: row.get::<i64, _>("telegram_user_id"),
+                telegram_chat_id: row.get::<i64, _>("weekly_digest_chat_id"),
+            })
+            .collect())
+    }
+
+    pub async fn mark_weekly_digest_sent(&self, telegram_user_id: i64) -> Result<()> {
+        sqlx::query("UPDATE user_settings SET weekly_digest_sent_at = NOW() WHERE telegram_user_id = $1")
+            .bind(telegram_user_id)
+            .execute(&self.pool)
+            .await?;
+
+        Ok(())
+    }
+
+    /// Buy/sell totals per coin from the rollups of the last `days` complete days.
+    pub async fn coin_flows(&self, coins: &[String], days: i64) -> Result<Vec<CoinFlow>> {
+        let rows = sqlx::query(
+            r#"
+            SELECT coin, SUM(buy_usd) AS buy_usd, SUM(sell_usd) AS sell_usd
+            FROM trade_daily_rollups
+            WHERE coin = ANY($1) AND day >= (NOW() AT TIME ZONE 'UTC')::date - $2::INT
+            GROUP BY coin
+            ORDER BY SUM(buy_usd) + SUM(sell_usd) DESC
+            "#
+        )
+            .bind(coins)
+            .bind(days)
+            .fetch_all(&self.replica)
+            .await?;
+
+        Ok(rows
+            .into_iter()
+            .map(|row| CoinFlow {
+                coin: row.get("coin"),
+                buy_usd: row.get("buy_usd"),
+                sell_usd: row.get("sell_usd"),
+            })
+            .collect())
+    }
+
+    /// The largest recorded trades on `coins` over the last `days`.
+    pub async fn biggest_trades(&self, coins: &[String], days: i64, limit: i64) -> Result<Vec<RecordedTrade>> {
+        let rows = sqlx::query(
+            r#"
+            SELECT coin, side, notional_usd, COALESCE(trade_time, recorded_at) AS traded_at
+            FROM trade_history
+            WHERE coin = ANY($1) AND recorded_at > NOW() - make_interval(days => $2::INT)
+            ORDER BY notional_usd DESC
+            LIMIT $3
+            "#
+        )
+            .bind(coins)
+            .bind(days)
+            .bind(limit)
+            .fetch_all(&self.replica)
+            .await?;
+
+        Ok(rows
+            .into_iter()
+            .map(|row| RecordedTrade {
+                coin: row.get("coin"),
+                side: row.get("side"),
+                notional_usd: row.get("notional_usd"),
+                traded_at: row.get("traded_at"),
+            })
+            .collect())
+    }
+
+    /// Alerts delivered to a user per coin over the last `days`, busiest first.
+    pub async fn alerts_received(&self, telegram_user_id: i64, days: i64) -> Result<Vec<(String, i64)>> {
+        let rows = sqlx::query(
+            r#"
+            SELECT coin, COUNT(DISTINCT trade_key) AS alerts
+            FROM notification_log
+            WHERE telegram_user_id = $1 AND delivered AND created_at > NOW() - make_interval(days => $2::INT)
+            GROUP BY coin
+            ORDER BY alerts DESC
+            "#
+        )
+            .bind(telegram_user_id)
+            .bind(days)
+            .fetch_all(&self.replica)
+            .await?;
+
+        Ok(rows.into_iter().map(|row| (row.get("coin"), row.get("alerts"))).collect())
+    }
+
+    /// Wallets behind the most large-trade volume on `coin` over the last `hours`.
+    pub async fn top_wallets(&self, coin: &str, hours: i64, limit: i64) -> Result<Vec<WalletActivity>> {
+        let rows = sqlx::query(
+            r#"
+            SELECT address, COUNT(*) AS trades, SUM(notional_usd) AS volume_usd
+            FROM (
+                SELECT buyer AS address, notional_usd FROM trade_history
+                WHERE coin = $1 AND recorded_at > NOW() - make_interval(hours => $2::INT) AND buyer IS NOT NULL
+                UNION ALL
+                SELECT seller AS address, notional_usd FROM trade_history
+                WHERE coin = $1 AND recorded_at > NOW() - make_interval(hours => $2::INT) AND seller IS NOT NULL
+            ) sides
+            GROUP BY address
+            ORDER BY volume_usd DESC
+            LIMIT $3
+            "#
+        )
+            .bind(coin.to_uppercase())
+            .bind(hours)
+            .bind(limit)
+            .fetch_all(&self.replica)
+            .await?;
+
+        let wallets = rows
+            .into_iter()
+            .map(|row| WalletActivity {
+                address: row.get::<String, _>("address"),
+                trades: row.get::<i64, _>("trades"),
+                volume_usd: row.get::<f64, _>("volume_usd"),
+            })
+            .collect();
+
+        Ok(wallets)
+    }
+
+    // one alert per trade, even if it fanned out to several destinations
+    pub async fn coin_alert_stats(&self, telegram_user_id: i64, coin: &str, days: i64) -> Result<CoinAlertStats> {
+        let row = sqlx::query(
+            r#"
+            SELECT COUNT(*) AS alerts,
+                AVG(notional_usd) AS avg_notional_usd,
+                COUNT(*) FILTER (WHERE side = 'B') AS buys,
+                COUNT(*) FILTER (WHERE side = 'A') AS sells
+            FROM (
+                SELECT DISTINCT ON (trade_key) side, notional_usd
+                FROM notification_log
+                WHERE telegram_user_id = $1 AND coin = $2 AND delivered
+                    AND created_at > NOW() - make_interval(days => $3::INT)
+            ) alerts
+            "#
+        )
+            .bind(telegram_user_id)
+            .bind(coin.to_uppercase())
+            .bind(days)
+            .fetch_one(&self.replica)
+            .await?;
+
+        Ok(CoinAlertStats {
+            alerts: row.get::<i64, _>("alerts"),
+            avg_notional_usd: row.get::<Option<f64>, _>("avg_notional_usd"),
+            buys: row.get::<i64, _>("buys"),
+            sells: row.get::<i64, _>("sells"),
+        })
+    }
+
+    /// Long vs short liquidation volume per coin from recorded trades.
+    pub async fn liquidation_totals(
+        &self,
+        coins: &[String],
+        liquidators: &[String],
+        hours: i64,
+    ) -> Result<HashMap<String, LiquidationTotals>> {
+        let liquidators: Vec<String> = liquidators.iter().map(|l| l.to_lowercase()).collect();
+        let rows = sqlx::query(
+            r#"
+            SELECT coin,
+                COALESCE(SUM(notional_usd) FILTER (WHERE buyer = ANY($2)), 0) AS longs_usd,
+                COALESCE(SUM(notional_usd) FILTER (WHERE seller = ANY($2)), 0) AS shorts_usd
+            FROM trade_history
+            WHERE coin = ANY($1) AND recorded_at > NOW() - make_interval(hours => $3::INT)
+                AND (buyer = ANY($2) OR seller = ANY($2))
+            GROUP BY coin
+            "#
+        )
+            .bind(coins)
+            .bind(&liquidators)
+            .bind(hours)
+            .fetch_all(&self.replica)
+            .await?;
+
+        let totals = rows
+            .into_iter()
+            .map(|row| {
+                let totals = LiquidationTotals {
+                    longs_usd: row.get::<f64, _>("longs_usd"),
+                    shorts_usd: row.get::<f64, _>("shorts_usd"),
+                };
+                (row.get::<String, _>("coin"), totals)
+            })
+            .collect();
+
+        Ok(totals)
+    }
+
+    /// Recomputes the rollups for the last `days` complete utc days from
+    /// trade_history, returning how many (day, coin) rows were written.
+    /// Safe to rerun; days already rolled up are overwritten.
+    pub async fn rollup_trade_days(&self, days: i64) -> Result<u64> {
+        let result = sqlx::query(
+            r#"
+            INSERT INTO trade_daily_rollups (day, coin, buys, buy_usd, sells, sell_usd)
+            SELECT (COALESCE(trade_time, recorded_at) AT TIME ZONE 'UTC')::date AS day, coin,
+                COUNT(*) FILTER (WHERE side = 'B'),
+                COALESCE(SUM(notional_usd) FILTER (WHERE side = 'B'), 0),
+                COUNT(*) FILTER (WHERE side <> 'B'),
+                COALESCE(SUM(notional_usd) FILTER (WHERE side <> 'B'), 0)
+            FROM trade_history
+            -- a day earlier so trades recorded late still land on their own day
+            WHERE recorded_at >= (NOW() AT TIME ZONE 'UTC')::date - ($1::INT + 1)
+                AND (COALESCE(trade_time, recorded_at) AT TIME ZONE 'UTC')::date
+                    BETWEEN (NOW() AT TIME ZONE 'UTC')::date - $1::INT AND (NOW() AT TIME ZONE 'UTC')::date - 1
+            GROUP BY 1, 2
+            ON CONFLICT (tenant_id, day, coin) DO UPDATE SET
+                buys = EXCLUDED.buys,
+                buy_usd = EXCLUDED.buy_usd,
+                sells = EXCLUDED.sells,
+                sell_usd = EXCLUDED.sell_usd,
+                rolled_up_at = NOW()
+            "#
+        )
+            .bind(days)
+            .execute(&self.pool)
+            .await?;
+
+        Ok(result.rows_affected())
+    }
+
+    /// Rolled-up days for `coin`, newest first.
+    pub async fn get_trade_rollups(&self, coin: &str, days: i64) -> Result<Vec<TradeRollup>> {
+        let rows = sqlx::query(
+            r#"
+            SELECT day, buys, buy_usd, sells, sell_usd
+            FROM trade_daily_rollups
+            WHERE coin = $1 AND day >= (NOW() AT TIME ZONE 'UTC')::date - $2::INT
+            ORDER BY day DESC
+            "#
+        )
+            .bind(coin.to_uppercase())
+            .bind(days)
+            .fetch_all(&self.replica)
+            .await?;
+
+        Ok(rows
+            .into_iter()
+            .map(|row| TradeRollup {
+                day: row.get("day"),
+                buys: row.get("buys"),
+                buy_usd: row.get("buy_usd"),
+                sells: row.get("sells"),
+                sell_usd: row.get("sell_usd"),
+            })
+            .collect())
+    }
+
+    /// (coin, trade time, notional) for recorded trades on `coins` over the last `days`.
+    pub async fn get_recorded_trades(
+        &self,
+        coins: &[String],
+        days: i64,
+    ) -> Result<Vec<(String, chrono::DateTime<chrono::Utc>, f64)>> {
+        let rows = sqlx::query(
+            r#"
+            SELECT coin, COALESCE(trade_time, recorded_at) AS traded_at, notional_usd
+            FROM trade_history
+            WHERE coin = ANY($1) AND recorded_at > NOW() - make_interval(days => $2::INT)
+            ORDER BY traded_at
+            "#
+        )
+            .bind(coins)
+            .bind(days)
+            .fetch_all(&self.replica)
+            .await?;
+
+        let trades = rows
+            .into_iter()
+            .map(|row| {
+                (
+                    row.get::<String, _>("coin"),
+                    row.get::<chrono::DateTime<chrono::Utc>, _>("traded_at"),
+                    row.get::<f64, _>("notional_usd"),
+                )
+            })
+            .collect();
+
+        Ok(trades)
+    }
+
+    pub async fn watch_wallet(&self, telegram_user_id: i64, telegram_chat_id: i64, address: &str) -> Result<bool> {
+        watch_wallet(&self.pool, telegram_user_id, telegram_chat_id, address).await
+    }
+
+    /// Watches every address or none of them, returning the ones that are new.
+    pub async fn watch_wallets(&self, telegram_user_id: i64, telegram_chat_id: i64, addresses: &[String]) -> Result<Vec<String>> {
+        let addresses = addresses.to_vec();
+        self.transaction(move |tx| {
+            Box::pin(async move {
+                let mut added = Vec::new();
+                for address in addresses {
+                    if watch_wallet(&mut **tx, telegram_user_id, telegram_chat_id, &address).await? {
+                        added.push(address);
+                    }
+                }
+                Ok(added)
+            })
+        })
+        .await
+    }
+
+    /// Unwatches the given addresses, or all of them when None, returning the
+    /// ones that were being watched.
+    pub async fn unwatch_wallets(&self, telegram_user_id: i64, addresses: Option<&[String]>) -> Result<Vec<String>> {
+        let addresses = addresses.map(<[String]>::to_vec);
+        self.transaction(move |tx| {
+            Box::pin(async move {
+                let removed = match addresses {
+                    Some(addresses) => {
+                        let mut removed = Vec::new();
+                        for address in addresses {
+                            if unwatch_wallet(&mut **tx, telegram_user_id, &address).await? {
+                                removed.push(address);
+                            }
+                        }
+                        removed
+                    }
+                    None => sqlx::query_scalar("DELETE FROM watched_wallets WHERE telegram_user_id = $1 RETURNING address")
+                        .bind(telegram_user_id)
+                        .fetch_all(&mut **tx)
+                        .await?,
+                };
+                Ok(removed)
+            })
+        })
+        .await
+    }
+
+    /// Erases everything stored for a user in one go, returning how many rows
+    /// went. Roles and bans are kept, they're the admins' records rather than
+    /// the user's.
+    pub async fn delete_user_data(&self, telegram_user_id: i64) -> Result<u64> {
+        self.transaction(move |tx| {
+            Box::pin(async move {
+                let mut deleted = 0;
+                for table in USER_DATA_TABLES {
+                    deleted += sqlx::query(&format!("DELETE FROM {} WHERE telegram_user_id = $1", table))
+                        .bind(telegram_user_id)
+                        .execute(&mut **tx)
+                        .await?
+                        .rows_affected();
+                }
+                Ok(deleted)
+            })
+        })
+        .await
+    }
+
+    pub async fn get_watched_wallets(&self, telegram_user_id: i64) -> Result<Vec<String>> {
+        let rows = sqlx::query("SELECT address FROM watched_wallets WHERE telegram_user_id = $1 ORDER BY created_at")
+            .bind(telegram_user_id)
+            .fetch_all(&self.pool)
+            .await?;
+
+        let addresses = rows.into_iter().map(|row| row.get::<String, _>("address")).collect();
+        Ok(addresses)
+    }
+
+    pub async fn get_wallet_watchers(&self, addresses: &[String]) -> Result<Vec<WalletWatcher>> {
+        let rows = sqlx::query(
+            "SELECT telegram_user_id, telegram_chat_id, address FROM watched_wallets WHERE address = ANY($1)"
+        )
+            .bind(addresses)
+            .fetch_all(&self.pool)
+            .await?;
+
+        let watchers = rows
+            .into_iter()
+            .map(|row| WalletWatcher {
+                telegram_user_id: row.get::<i64, _>("telegram_user_id"),
+                telegram_chat_id: row.get::<i64, _>("telegram_chat_id"),
+                address: row.get::<String, _>("address"),
+            })
+            .collect();
+
+        Ok(watchers)
+    }
+
+    /// Every watcher of every wallet, for the scheduled position summaries.
+    pub async fn get_all_wallet_watchers(&self) -> Result<Vec<WalletWatcher>> {
+        let rows = sqlx::query(
+            r#"
+            SELECT w.telegram_user_id, w.telegram_chat_id, w.address
+            FROM watched_wallets w
+            WHERE NOT EXISTS (SELECT 1 FROM banned_users b WHERE b.telegram_user_id = w.telegram_user_id)
+            ORDER BY w.address
+            "#
+        )
+            .fetch_all(&self.pool)
+            .await?;
 
-#[derive(Debug)]
-pub struct UserSubscription {
-    pub telegram_user_id: i64,
-    pub telegram_chat_id: i64,
-    pub coin: String,
-}
+        let watchers = rows
+            .into_iter()
+            .map(|row| WalletWatcher {
+                telegram_user_id: row.get::<i64, _>("telegram_user_id"),
+                telegram_chat_id: row.get::<i64, _>("telegram_chat_id"),
+                address: row.get::<String, _>("address"),
+            })
+            .collect();
 
-impl Database {
-    pub async fn new(config: &DatabaseConfig) -> Result<Self> {
-        info!("connecting to db...");
-        
-        let pool = PgPool::connect(&config.url).await?;
-        
-        info!("connected to db");
-        Ok(Database { pool })
+        Ok(watchers)
     }
 
-    pub async fn add_subscription(
-        &self, 
-        telegram_user_id: i64, 
-        telegram_chat_id: i64, 
-        coin: &str
-    ) -> Result<bool> {
+    pub async fn save_wallet_snapshot(&self, address: &str, snapshot: &WalletSnapshot) -> Result<()> {
+        let mut tx = self.pool.begin().await?;
+
+        let snapshot_id: i64 = sqlx::query(
+            r#"
+            INSERT INTO wallet_snapshots (address, account_value_usd, realized_pnl_usd, unrealized_pnl_usd, taken_at)
+            VALUES ($1, $2, $3, $4, $5)
+            RETURNING id
+            "#
+        )
+            .bind(address.to_lowercase())
+            .bind(snapshot.account_value_usd)
+            .bind(snapshot.realized_pnl_usd)
+            .bind(snapshot.unrealized_pnl_usd())
+            .bind(snapshot.taken_at)
+            .fetch_one(&mut *tx)
+            .await?
+            .get("id");
+
+        let positions = &snapshot.positions;
+        if !positions.is_empty() {
+            let mut query: QueryBuilder<Postgres> = QueryBuilder::new(
+                "INSERT INTO wallet_snapshot_positions (snapshot_id, coin, size, entry_px, position_value_usd, unrealized_pnl_usd) "
+            );
+            query.push_values(positions, |mut row, position| {
+                row.push_bind(snapshot_id)
+                    .push_bind(&position.coin)
+                    .push_bind(position.size)
+                    .push_bind(position.entry_px)
+                    .push_bind(position.position_value_usd)
+                    .push_bind(position.unrealized_pnl_usd);
+            });
+            query.build().execute(&mut *tx).await?;
+        }
+
+        tx.commit().await?;
+        Ok(())
+    }
+
+    pub async fn get_latest_wallet_snapshot(&self, address: &str) -> Result<Option<WalletSnapshot>> {
+        let Some(snapshot) = sqlx::query(
+            "SELECT id, account_value_usd, realized_pnl_usd, taken_at FROM wallet_snapshots WHERE address = $1 ORDER BY taken_at DESC LIMIT 1"
+        )
+            .bind(address.to_lowercase())
+            .fetch_optional(&self.pool)
+            .await?
+        else {
+            return Ok(None);
+        };
+
+        let rows = sqlx::query(
+            "SELECT coin, size, entry_px, position_value_usd, unrealized_pnl_usd FROM wallet_snapshot_positions WHERE snapshot_id = $1 ORDER BY coin"
+        )
+            .bind(snapshot.get::<i64, _>("id"))
+            .fetch_all(&self.pool)
+            .await?;
+
+        let positions = rows
+            .into_iter()
+            .map(|row| WalletPosition {
+                coin: row.get::<String, _>("coin"),
+                size: row.get::<f64, _>("size"),
+                entry_px: row.get::<Option<f64>, _>("entry_px"),
+                position_value_usd: row.get::<f64, _>("position_value_usd"),
+                unrealized_pnl_usd: row.get::<f64, _>("unrealized_pnl_usd"),
+            })
+            .collect();
+
+        Ok(Some(WalletSnapshot {
+            account_value_usd: snapshot.get::<f64, _>("account_value_usd"),
+            realized_pnl_usd: snapshot.get::<f64, _>("realized_pnl_usd"),
+            taken_at: snapshot.get::<chrono::DateTime<chrono::Utc>, _>("taken_at"),
+            positions,
+        }))
+    }
+
+    /// Realized plus change in unrealized pnl over the last `days` for every
+    /// watched wallet with at least two snapshots in that window, best first.
+    pub async fn wallet_pnl_board(&self, days: i64) -> Result<Vec<WalletPnl>> {
+        // a snapshot's realized pnl covers the time before it, so the window's
+        // first snapshot only contributes its unrealized pnl as the baseline
+        let rows = sqlx::query(
+            r#"
+            SELECT * FROM (
+                SELECT address,
+                    SUM(realized_pnl_usd) - (ARRAY_AGG(realized_pnl_usd ORDER BY taken_at))[1] AS realized_pnl_usd,
+                    (ARRAY_AGG(unrealized_pnl_usd ORDER BY taken_at DESC))[1]
+                        - (ARRAY_AGG(unrealized_pnl_usd ORDER BY taken_at))[1] AS unrealized_change_usd,
+                    MIN(taken_at) AS since
+                FROM wallet_snapshots
+                WHERE taken_at > NOW() - make_interval(days => $1::INT)
+                    AND address IN (SELECT address FROM watched_wallets)
+                GROUP BY address
+                HAVING COUNT(*) > 1
+            ) board
+            ORDER BY realized_pnl_usd + unrealized_change_usd DESC
+            "#
+        )
+            .bind(days)
+            .fetch_all(&self.replica)
+            .await?;
+
+        let board = rows
+            .into_iter()
+            .map(|row| WalletPnl {
+                address: row.get::<String, _>("address"),
+                realized_pnl_usd: row.get::<f64, _>("realized_pnl_usd"),
+                unrealized_change_usd: row.get::<f64, _>("unrealized_change_usd"),
+                since: row.get::<chrono::DateTime<chrono::Utc>, _>("since"),
+            })
+            .collect();
+
+        Ok(board)
+    }
+
+    pub async fn get_chat_settings(&self, chat_id: i64) -> Result<ChatSettings> {
+        let row = sqlx::query(
+            "SELECT pin_summaries, pinned_summary_id, alert_prefix, alert_suffix, emoji_style FROM chat_settings WHERE chat_id = $1"
+        )
+            .bind(chat_id)
+            .fetch_optional(&self.pool)
+            .await?;
+
+        Ok(row
+            .map(|row| ChatSettings {
+                pin_summaries: row.get::<bool, _>("pin_summaries"),
+                pinned_summary_id: row.get::<Option<i32>, _>("pinned_summary_id"),
+                branding: Branding {
+                    prefix: row.get::<Option<String>, _>("alert_prefix"),
+                    suffix: row.get::<Option<String>, _>("alert_suffix"),
+                    emoji: EmojiStyle::parse(&row.get::<String, _>("emoji_style")).unwrap_or_default(),
+                },
+            })
+            .unwrap_or_default())
+    }
+
+    pub async fn set_chat_branding(&self, chat_id: i64, branding: &Branding) -> Result<()> {
+        sqlx::query(
+            r#"
+            INSERT INTO chat_settings (chat_id, alert_prefix, alert_suffix, emoji_style)
+            VALUES ($1, $2, $3, $4)
+            ON CONFLICT (tenant_id, chat_id) DO UPDATE
+                SET alert_prefix = EXCLUDED.alert_prefix, alert_suffix = EXCLUDED.alert_suffix,
+                    emoji_style = EXCLUDED.emoji_style, updated_at = NOW()
+            "#
+        )
+        .bind(chat_id)
+        .bind(&branding.prefix)
+        .bind(&branding.suffix)
+        .bind(branding.emoji.as_str())
+        .execute(&self.pool)
+        .await?;
+
+        Ok(())
+    }
+
+    pub async fn set_pin_summaries(&self, chat_id: i64, pin_summaries: bool) -> Result<()> {
+        sqlx::query(
+            r#"
+            INSERT INTO chat_settings (chat_id, pin_summaries)
+            VALUES ($1, $2)
+            ON CONFLICT (tenant_id, chat_id) DO UPDATE SET pin_summaries = EXCLUDED.pin_summaries, updated_at = NOW()
+            "#
+        )
+        .bind(chat_id)
+        .bind(pin_summaries)
+        .execute(&self.pool)
+        .await?;
+
+        Ok(())
+    }
+
+    pub async fn set_pinned_summary(&self, chat_id: i64, message_id: Option<i32>) -> Result<()> {
+        sqlx::query("UPDATE chat_settings SET pinned_summary_id = $2, updated_at = NOW() WHERE chat_id = $1")
+            .bind(chat_id)
+            .bind(message_id)
+            .execute(&self.pool)
+            .await?;
+
+        Ok(())
+    }
+
+    pub async fn get_ticker(&self, chat_id: i64, coin: &str) -> Result<Option<TickerRecord>> {
+        let row = sqlx::query(
+            "SELECT message_id, day, buys, buy_usd, sells, sell_usd, last_trade FROM ticker_messages WHERE chat_id = $1 AND coin = $2"
+        )
+            .bind(chat_id)
+            .bind(coin)
+            .fetch_optional(&self.pool)
+            .await?;
+
+        Ok(row.map(|row| TickerRecord {
+            message_id: row.get::<Option<i32>, _>("message_id"),
+            day: row.get::<chrono::NaiveDate, _>("day"),
+            buys: row.get::<i32, _>("buys"),
+            buy_usd: row.get::<f64, _>("buy_usd"),
+            sells: row.get::<i32, _>("sells"),
+            sell_usd: row.get::<f64, _>("sell_usd"),
+            last_trade: row.get::<Option<String>, _>("last_trade"),
+        }))
+    }
+
+    /// Saves a chat's running totals; one saved before its message was
+    /// posted keeps any message id already stored.
+    pub async fn save_ticker(&self, chat_id: i64, coin: &str, ticker: &TickerRecord) -> Result<()> {
+        sqlx::query(
+            r#"
+            INSERT INTO ticker_messages (chat_id, coin, message_id, day, buys, buy_usd, sells, sell_usd, last_trade)
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9)
+            ON CONFLICT (tenant_id, chat_id, coin) DO UPDATE SET
+                message_id = COALESCE(EXCLUDED.message_id, ticker_messages.message_id),
+                day = EXCLUDED.day,
+                buys = EXCLUDED.buys,
+                buy_usd = EXCLUDED.buy_usd,
+                sells = EXCLUDED.sells,
+                sell_usd = EXCLUDED.sell_usd,
+                last_trade = EXCLUDED.last_trade,
+                updated_at = NOW()
+            "#
+        )
+        .bind(chat_id)
+        .bind(coin)
+        .bind(ticker.message_id)
+        .bind(ticker.day)
+        .bind(ticker.buys)
+        .bind(ticker.buy_usd)
+        .bind(ticker.sells)
+        .bind(ticker.sell_usd)
+        .bind(&ticker.last_trade)
+        .execute(&self.pool)
+        .await?;
+
+        Ok(())
+    }
+
+    pub async fn save_metrics_snapshot(&self, interval: &MetricsInterval) -> Result<()> {
+        let mut tx = self.pool.begin().await?;
+
+        let snapshot_id: i64 = sqlx::query(
+            r#"
+            INSERT INTO metrics_snapshots
+                (interval_secs, trades_seen, large_trades, alerts_sent, alerts_retried, alerts_failed, p95_latency_ms)
+            VALUES ($1, $2, $3, $4, $5, $6, $7)
+            RETURNING id
+            "#
+        )
+            .bind(interval.interval_secs as i32)
+            .bind(interval.trades_seen)
+            .bind(interval.large_trades)
+            .bind(interval.alerts_sent)
+            .bind(interval.alerts_retried)
+            .bind(interval.alerts_failed)
+            .bind(interval.p95_latency_ms)
+            .fetch_one(&mut *tx)
+            .await?
+            .get("id");
+
+        if !interval.coins.is_empty() {
+            let mut query: QueryBuilder<Postgres> =
+                QueryBuilder::new("INSERT INTO metrics_snapshot_coins (snapshot_id, coin, trades, volume_usd) ");
+            query.push_values(&interval.coins, |mut row, (coin, trades, volume_usd)| {
+                row.push_bind(snapshot_id).push_bind(coin).push_bind(trades).push_bind(volume_usd);
+            });
+            query.build().execute(&mut *tx).await?;
+        }
+
+        tx.commit().await?;
+        Ok(())
+    }
+
+    /// Persisted counters summed per UTC day, oldest first.
+    pub async fn metrics_by_day(&self, days: i64) -> Result<Vec<MetricsDay>> {
+        let rows = sqlx::query(
+            r#"
+            SELECT (taken_at AT TIME ZONE 'UTC')::date AS day,
+                SUM(trades_seen)::BIGINT AS trades_seen,
+                SUM(large_trades)::BIGINT AS large_trades,
+                SUM(alerts_sent)::BIGINT AS alerts_sent,
+                SUM(alerts_failed)::BIGINT AS alerts_failed
+            FROM metrics_snapshots
+            WHERE taken_at > NOW() - make_interval(days => $1::INT)
+            GROUP BY day
+            ORDER BY day
+            "#
+        )
+            .bind(days)
+            .fetch_all(&self.replica)
+            .await?;
+
+        Ok(rows
+            .into_iter()
+            .map(|row| MetricsDay {
+                day: row.get("day"),
+                trades_seen: row.get("trades_seen"),
+                large_trades: row.get("large_trades"),
+                alerts_sent: row.get("alerts_sent"),
+                alerts_failed: row.get("alerts_failed"),
+            })
+            .collect())
+    }
+
+    /// (coin, trades, volume) for the busiest coins in the persisted snapshots.
+    pub async fn busiest_coins(&self, days: i64, limit: i64) -> Result<Vec<(String, i64, f64)>> {
+        let rows = sqlx::query(
+            r#"
+            SELECT c.coin, SUM(c.trades)::BIGINT AS trades, SUM(c.volume_usd) AS volume_usd
+            FROM metrics_snapshot_coins c
+            JOIN metrics_snapshots s ON s.id = c.snapshot_id
+            WHERE s.taken_at > NOW() - make_interval(days => $1::INT)
+            GROUP BY c.coin
+            ORDER BY volume_usd DESC
+            LIMIT $2
+            "#
+        )
+            .bind(days)
+            .bind(limit)
+            .fetch_all(&self.replica)
+            .await?;
+
+        Ok(rows
+            .into_iter()
+            .map(|row| (row.get("coin"), row.get("trades"), row.get("volume_usd")))
+            .collect())
+    }
+
+    /// Drops metrics snapshots older than `days`, returning how many went.
+    pub async fn prune_metrics_snapshots(&self, days: i64) -> Result<u64> {
+        let result = sqlx::query("DELETE FROM metrics_snapshots WHERE taken_at < NOW() - make_interval(days => $1::INT)")
+            .bind(days)
+            .execute(&self.pool)
+            .await?;
+
+        Ok(result.rows_affected())
+    }
+
+    /// Drops snapshots older than `days`, returning how many went.
+    pub async fn prune_wallet_snapshots(&self, days: i64) -> Result<u64> {
+        let result = sqlx::query("DELETE FROM wallet_snapshots WHERE taken_at < NOW() - make_interval(days => $1::INT)")
+            .bind(days)
+            .execute(&self.pool)
+            .await?;
+
+        Ok(result.rows_affected())
+    }
+
+    pub async fn add_destination(
+        &self,
+        telegram_user_id: i64,
+        chat_id: Option<i64>,
+        webhook_url: Option<&str>,
+        coin: Option<&str>,
+        full_precision: Option<bool>,
+        charts_enabled: Option<bool>,
+    ) -> Result<i64> {
+        add_destination(&self.pool, telegram_user_id, chat_id, webhook_url, coin, full_precision, charts_enabled).await
+    }
+
+    pub async fn remove_destination(&self, telegram_user_id: i64, destination_id: i64) -> Result<bool> {
+        let result = sqlx::query("DELETE FROM destinations WHERE telegram_user_id = $1 AND id = $2")
+            .bind(telegram_user_id)
+            .bind(destination_id)
+            .execute(&self.pool)
+            .await?;
+
+        Ok(result.rows_affected() > 0)
+    }
+
+    pub async fn get_user_destinations(&self, telegram_user_id: i64) -> Result<Vec<Destination>> {
+        get_user_destinations(&self.pool, telegram_user_id).await
+    }
+
+    pub async fn get_roles(&self) -> Result<Vec<(i64, String)>> {
+        let rows = sqlx::query("SELECT telegram_user_id, role FROM user_roles")
+            .fetch_all(&self.pool)
+            .await?;
+
+        Ok(rows
+            .into_iter()
+            .map(|row| (row.get::<i64, _>("telegram_user_id"), row.get::<String, _>("role")))
+            .collect())
+    }
+
+    /// `role` of `None` removes the user's role.
+    pub async fn set_role(&self, telegram_user_id: i64, role: Option<&str>, granted_by: i64) -> Result<()> {
+        match role {
+            Some(role) => {
+                sqlx::query(
+                    r#"
+                    INSERT INTO user_roles (telegram_user_id, role, granted_by)
+                    VALUES ($1, $2, $3)
+                    ON CONFLICT (tenant_id, telegram_user_id) DO UPDATE SET role = EXCLUDED.role, granted_by = EXCLUDED.granted_by
+                    "#
+                )
+                .bind(telegram_user_id)
+                .bind(role)
+                .bind(granted_by)
+                .execute(&self.pool)
+                .await?;
+            }
+            None => {
+                sqlx::query("DELETE FROM user_roles WHERE telegram_user_id = $1")
+                    .bind(telegram_user_id)
+                    .execute(&self.pool)
+                    .await?;
+            }
+        }
+
+        Ok(())
+    }
+
+    pub async fn get_banned_users(&self) -> Result<Vec<i64>> {
+        let rows = sqlx::query("SELECT telegram_user_id FROM banned_users")
+            .fetch_all(&self.pool)
+            .await?;
+
+        Ok(rows.into_iter().map(|row| row.get::<i64, _>("telegram_user_id")).collect())
+    }
+
+    pub async fn ban_user(&self, telegram_user_id: i64, banned_by: i64) -> Result<bool> {
         let result = sqlx::query(
             r#"
-            INSERT INTO user_subscriptions (telegram_user_id, telegram_chat_id, coin)
-            VALUES ($1, $2, $3)
-            ON CONFLICT (telegram_user_id, coin) DO NOTHING
+            INSERT INTO banned_users (telegram_user_id, banned_by)
+            VALUES ($1, $2)
+            ON CONFLICT (tenant_id, telegram_user_id) DO NOTHING
             "#
         )
         .bind(telegram_user_id)
-        .bind(telegram_chat_id)
-        .bind(coin.to_uppercase())
+        .bind(banned_by)
         .execute(&self.pool)
         .await?;
 
         Ok(result.rows_affected() > 0)
     }
 
-    pub async fn remove_subscription(&self, telegram_user_id: i64, coin: &str) -> Result<bool> {
-        let result = sqlx::query("DELETE FROM user_subscriptions WHERE telegram_user_id = $1 AND coin = $2")
+    pub async fn unban_user(&self, telegram_user_id: i64) -> Result<bool> {
+        let result = sqlx::query("DELETE FROM banned_users WHERE telegram_user_id = $1")
             .bind(telegram_user_id)
-            .bind(coin.to_uppercase())
             .execute(&self.pool)
             .await?;
 
         Ok(result.rows_affected() > 0)
     }
 
-    pub async fn get_user_subscriptions(&self, telegram_user_id: i64) -> Result<Vec<String>> {
-        let rows = sqlx::query("SELECT coin FROM user_subscriptions WHERE telegram_user_id = $1 ORDER BY coin")
-            .bind(telegram_user_id)
+    /// Every chat with a subscription, for /broadcast.
+    pub async fn get_broadcast_chats(&self) -> Result<Vec<i64>> {
+        let rows = sqlx::query(
+            r#"
+            SELECT DISTINCT us.telegram_chat_id
+            FROM user_subscriptions us
+            WHERE us.removed_at IS NULL
+                AND NOT EXISTS (SELECT 1 FROM banned_users b WHERE b.telegram_user_id = us.telegram_user_id)
+            "#
+        )
             .fetch_all(&self.pool)
             .await?;
 
-        let coins = rows.into_iter().map(|row| row.get::<String, _>("coin")).collect();
-        Ok(coins)
+        Ok(rows.into_iter().map(|row| row.get::<i64, _>("telegram_chat_id")).collect())
     }
 
-    pub async fn get_subscribers_for_coin(&self, coin: &str) -> Result<Vec<UserSubscription>> {
-        let rows = sqlx::query("SELECT telegram_user_id, telegram_chat_id, coin FROM user_subscriptions WHERE coin = $1")
-            .bind(coin.to_uppercase())
+    /// Every row of `table` as a json object, for operator backups.
+    pub async fn dump_table(&self, table: &str) -> Result<Vec<serde_json::Value>> {
+        // table names come from a fixed list in archive.rs, never from input
+        let rows = sqlx::query(&format!("SELECT row_to_json(t)::TEXT AS row FROM {} t", table))
             .fetch_all(&self.pool)
             .await?;
 
-        let subscriptions = rows
-            .into_iter()
-            .map(|row| UserSubscription {
+        rows.iter()
+            .map(|row| Ok(serde_json::from_str(row.get::<&str, _>("row"))?))
+            .collect()
+    }
+
+    /// Loads dumped rows in one transaction, skipping rows that already exist.
+    /// Returns how many rows went into each table.
+    pub async fn restore_tables(&self, tables: &[(&str, Vec<serde_json::Value>)]) -> Result<Vec<(String, u64)>> {
+        let mut tx = self.pool.begin().await?;
+        let mut restored = Vec::new();
+
+        for (table, rows) in tables {
+            let Some(first) = rows.first().and_then(|row| row.as_object()) else {
+                restored.push((table.to_string(), 0));
+                continue;
+            };
+
+            // only columns this schema has, so archives from older or newer
+            // migrations still load; anything missing takes its default.
+            // generated columns (user_subscriptions.dex) can't be written
+            let known: Vec<String> = sqlx::query(
+                "SELECT column_name::TEXT AS column_name FROM information_schema.columns WHERE table_schema = current_schema() AND table_name = $1 AND is_generated = 'NEVER'"
+            )
+                .bind(table)
+                .fetch_all(&mut *tx)
+                .await?
+                .iter()
+                .map(|row| row.get::<String, _>("column_name"))
+                .collect();
+            let columns: Vec<&str> = first
+                .keys()
+                .filter(|column| known.contains(column))
+                .map(String::as_str)
+                .collect();
+            if columns.is_empty() {
+                anyhow::bail!("backup rows for {} share no columns with the schema", table);
+            }
+            let columns = columns.join(", ");
+
+            let result = sqlx::query(&format!(
+                "INSERT INTO {table} ({columns}) SELECT {columns} FROM json_populate_recordset(NULL::{table}, $1::JSON) ON CONFLICT DO NOTHING"
+            ))
+                .bind(serde_json::to_string(rows)?)
+                .execute(&mut *tx)
+                .await?;
+
+            // serial ids were restored verbatim, so move the sequence past them;
+            // only ever forward, since other tenants' rows are invisible here
+            if known.iter().any(|column| column == "id") {
+                sqlx::query(&format!(
+                    "SELECT setval(pg_get_serial_sequence('{table}', 'id'), GREATEST((SELECT MAX(id) FROM {table}), pg_sequence_last_value(pg_get_serial_sequence('{table}', 'id')::REGCLASS)))"
+                ))
+                    .execute(&mut *tx)
+                    .await?;
+            }
+
+            restored.push((table.to_string(), result.rows_affected()));
+        }
+
+        tx.commit().await?;
+        Ok(restored)
+    }
+
+    pub async fn insert_trade_history(&self, records: &[TradeRecord]) -> Result<()> {
+        if records.is_empty() {
+            return Ok(());
+        }
+
+        let mut query: QueryBuilder<Postgres> = QueryBuilder::new(
+            "INSERT INTO trade_history (coin, side, px, sz, notional_usd, tid, hash, buyer, seller, trade_time) "
+        );
+        query.push_values(records, |mut row, record| {
+            row.push_bind(record.coin.as_str())
+                .push_bind(&record.side)
+                .push_bind(&record.px)
+                .push_bind(&record.sz)
+                .push_bind(record.notional_usd)
+                .push_bind(record.tid)
+                .push_bind(&record.hash)
+                .push_bind(&record.buyer)
+                .push_bind(&record.seller)
+                .push_bind(record.trade_time);
+        });
+        query.build().execute(&self.pool).await?;
+
+        Ok(())
+    }
+
+    pub async fn insert_notification_logs(&self, records: &[NotificationRecord]) -> Result<()> {
+        if records.is_empty() {
+            return Ok(());
+        }
+
+        let mut query: QueryBuilder<Postgres> = QueryBuilder::new(
+            "INSERT INTO notification_log (telegram_user_id, telegram_chat_id, webhook_url, coin, trade_key, side, notional_usd, delivered, attempts, latency_ms, error, message_id, experiment, variant) "
+        );
+        query.push_values(records, |mut row, record| {
+            row.push_bind(record.telegram_user_id)
+                .push_bind(record.telegram_chat_id)
+                .push_bind(&record.webhook_url)
+                .push_bind(record.coin.as_str())
+                .push_bind(record.trade_key)
+                .push_bind(&record.side)
+                .push_bind(record.notional_usd)
+                .push_bind(record.delivered)
+                .push_bind(record.attempts)
+                .push_bind(record.latency_ms)
+                .push_bind(&record.error)
+                .push_bind(record.message_id)
+                .push_bind(&record.experiment)
+                .push_bind(&record.variant);
+        });
+        query.build().execute(&self.pool).await?;
+
+        Ok(())
+    }
+
+    /// Stores an alert that used up its retries; `id` and `created_at` are ignored.
+    pub async fn add_dead_letter(&self, letter: &DeadLetter) -> Result<()> {
+        sqlx::query(
+            r#"
+            INSERT INTO dead_letter (telegram_user_id, telegram_chat_id, webhook_url, coin, side, px, sz, trade_time, tid, hash,
+                notional_usd, trade_key, priority, attempts, error)
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, $13, $14, $15)
+            "#
+        )
+        .bind(letter.telegram_user_id)
+        .bind(letter.telegram_chat_id)
+        .bind(&letter.webhook_url)
+        .bind(letter.coin.as_str())
+        .bind(&letter.side)
+        .bind(&letter.px)
+        .bind(&letter.sz)
+        .bind(letter.trade_time)
+        .bind(letter.tid)
+        .bind(&letter.hash)
+        .bind(letter.notional_usd)
+        .bind(letter.trade_key)
+        .bind(letter.priority)
+        .bind(letter.attempts)
+        .bind(&letter.error)
+        .execute(&self.pool)
+        .await?;
+
+        Ok(())
+    }
+
+    /// The newest dead letters, up to `limit`, and how many there are in all.
+    pub async fn get_dead_letters(&self, limit: i64) -> Result<(Vec<DeadLetter>, i64)> {
+        let rows = sqlx::query(&format!("SELECT {} FROM dead_letter ORDER BY id DESC LIMIT $1", DEAD_LETTER_COLUMNS))
+            .bind(limit)
+            .fetch_all(&self.pool)
+            .await?;
+        let total = sqlx::query("SELECT COUNT(*) AS total FROM dead_letter")
+            .fetch_one(&self.pool)
+            .await?
+            .get::<i64, _>("total");
+
+        Ok((rows.iter().map(DeadLetter::from_row).collect(), total))
+    }
+
+    /// One dead letter, or all of them with `None`, oldest first, for sending
+    /// again. They stay in the table until each is dealt with.
+    pub async fn get_dead_letters_to_retry(&self, id: Option<i64>) -> Result<Vec<DeadLetter>> {
+        let rows = sqlx::query(&format!(
+            "SELECT {} FROM dead_letter WHERE $1::BIGINT IS NULL OR id = $1 ORDER BY id",
+            DEAD_LETTER_COLUMNS
+        ))
+            .bind(id)
+            .fetch_all(&self.pool)
+            .await?;
+
+        Ok(rows.iter().map(DeadLetter::from_row).collect())
+    }
+
+    /// Drops one dead letter, or all of them with `None`; returns how many went.
+    pub async fn purge_dead_letters(&self, id: Option<i64>) -> Result<u64> {
+        let result = sqlx::query("DELETE FROM dead_letter WHERE $1::BIGINT IS NULL OR id = $1")
+            .bind(id)
+            .execute(&self.pool)
+            .await?;
+
+        Ok(result.rows_affected())
+    }
+
+    /// Stores an alert held for a digest; `id` and `created_at` are ignored.
+    /// Returns the new row's id.
+    pub async fn add_held_alert(&self, alert: &HeldAlert) -> Result<i64> {
+        let id = sqlx::query_scalar(
+            r#"
+            INSERT INTO held_alerts (telegram_user_id, telegram_chat_id, coin, side, px, sz, trade_time, tid, hash,
+                notional_usd, trade_key)
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11)
+            RETURNING id
+            "#
+        )
+        .bind(alert.telegram_user_id)
+        .bind(alert.telegram_chat_id)
+        .bind(alert.coin.as_str())
+        .bind(&alert.side)
+        .bind(&alert.px)
+        .bind(&alert.sz)
+        .bind(alert.trade_time)
+        .bind(alert.tid)
+        .bind(&alert.hash)
+        .bind(alert.notional_usd)
+        .bind(alert.trade_key)
+        .fetch_one(&self.pool)
+        .await?;
+
+        Ok(id)
+    }
+
+    /// Every held alert, oldest first, to pick digests back up after a restart.
+    pub async fn get_held_alerts(&self) -> Result<Vec<HeldAlert>> {
+        let rows = sqlx::query(
+            r#"
+            SELECT id, telegram_user_id, telegram_chat_id, coin, side, px, sz, trade_time, tid, hash,
+                notional_usd, trade_key, created_at
+            FROM held_alerts
+            ORDER BY id
+            "#
+        )
+            .fetch_all(&self.pool)
+            .await?;
+
+        Ok(rows
+            .iter()
+            .map(|row| HeldAlert {
+                id: row.get::<i64, _>("id"),
                 telegram_user_id: row.get::<i64, _>("telegram_user_id"),
                 telegram_chat_id: row.get::<i64, _>("telegram_chat_id"),
-                coin: row.get::<String, _>("coin"),
+                coin: CoinSymbol::new(row.get::<&str, _>("coin")),
+                side: row.get::<String, _>("side"),
+                px: row.get::<String, _>("px"),
+                sz: row.get::<String, _>("sz"),
+                trade_time: row.get::<Option<chrono::DateTime<chrono::Utc>>, _>("trade_time"),
+                tid: row.get::<Option<i64>, _>("tid"),
+                hash: row.get::<Option<String>, _>("hash"),
+                notional_usd: row.get::<f64, _>("notional_usd"),
+                trade_key: row.get::<i64, _>("trade_key"),
+                created_at: row.get::<chrono::DateTime<chrono::Utc>, _>("created_at"),
             })
-            .collect();
+            .collect())
+    }
 
-        Ok(subscriptions)
+    /// Drops held alerts whose digest went out or was given up on.
+    pub async fn remove_held_alerts(&self, ids: &[i64]) -> Result<()> {
+        if ids.is_empty() {
+            return Ok(());
+        }
+        sqlx::query("DELETE FROM held_alerts WHERE id = ANY($1)")
+            .bind(ids)
+            .execute(&self.pool)
+            .await?;
+
+        Ok(())
+    }
+
+    /// Counts a button tap on an alert message. Alerts the history writer
+    /// hasn't flushed yet aren't there to count against.
+    pub async fn record_alert_tap(&self, chat_id: i64, message_id: i32) -> Result<()> {
+        sqlx::query("UPDATE notification_log SET taps = taps + 1 WHERE telegram_chat_id = $1 AND message_id = $2")
+            .bind(chat_id)
+            .bind(message_id)
+            .execute(&self.pool)
+            .await?;
+        Ok(())
     }
 
+    pub async fn experiment_results(&self, experiment: &str) -> Result<Vec<VariantResult>> {
+        let rows = sqlx::query(
+            r#"
+            SELECT variant,
+                COUNT(*) AS alerts,
+                COUNT(DISTINCT telegram_chat_id) AS chats,
+                COUNT(*) FILTER (WHERE taps > 0) AS tapped,
+                COALESCE(SUM(taps), 0)::BIGINT AS taps
+            FROM notification_log
+            WHERE experiment = $1 AND delivered
+            GROUP BY variant
+            ORDER BY variant
+            "#
+        )
+            .bind(experiment)
+            .fetch_all(&self.replica)
+            .await?;
 
-    pub async fn get_active_coins(&self) -> Result<Vec<String>> {
-        let rows = sqlx::query("SELECT DISTINCT coin FROM user_subscriptions ORDER BY coin")
-            .fetch_all(&self.pool)
+        Ok(rows
+            .into_iter()
+            .map(|row| VariantResult {
+                variant: row.get::<String, _>("variant"),
+                alerts: row.get::<i64, _>("alerts"),
+                chats: row.get::<i64, _>("chats"),
+                tapped: row.get::<i64, _>("tapped"),
+                taps: row.get::<i64, _>("taps"),
+            })
+            .collect())
+    }
+
+    /// The most recent alert for `coin` that went out as a telegram message in
+    /// `chat_id`, for edits and replies that follow up on it. Only sees alerts
+    /// the history writer has flushed.
+    pub async fn get_last_alert_message(&self, chat_id: i64, coin: &CoinSymbol) -> Result<Option<AlertMessage>> {
+        let row = sqlx::query(
+            "SELECT message_id, trade_key, created_at FROM notification_log
+             WHERE telegram_chat_id = $1 AND coin = $2 AND message_id IS NOT NULL
+             ORDER BY created_at DESC
+             LIMIT 1"
+        )
+            .bind(chat_id)
+            .bind(coin.as_str())
+            .fetch_optional(&self.pool)
             .await?;
 
-        let coins = rows.into_iter().map(|row| row.get::<String, _>("coin")).collect();
-        Ok(coins)
+        Ok(row.map(|row| AlertMessage {
+            message_id: row.get::<i32, _>("message_id"),
+            trade_key: row.get::<i64, _>("trade_key"),
+            sent_at: row.get::<chrono::DateTime<chrono::Utc>, _>("created_at"),
+        }))
     }
 }
 
+// sets hl.tenant on every new connection, which the row-level security
+// policies from migration 0033 filter on
+async fn connect(url: &str, tenant: &str) -> Result<PgPool> {
+    let tenant = tenant.to_string();
+    let options = PgPoolOptions::new()
+        .after_connect(move |connection, _| {
+            let tenant = tenant.clone();
+            Box::pin(async move {
+                sqlx::query("SELECT set_config('hl.tenant', $1, false)")
+                    .bind(tenant)
+                    .execute(connection)
+                    .await?;
+                Ok(())
+            })
+        });
+    #[cfg(feature = "chaos")]
+    let options = options.before_acquire(|_, _| {
+        Box::pin(async {
+            crate::chaos::delay_db_query().await;
+            Ok(true)
+        })
+    });
+    let pool = options.connect(url).await?;
+    Ok(pool)
+}
+
 pub async fn init(config: &DatabaseConfig) -> Result<Database> {
-    Database::new(config).await
+    Database::connect(config).await
 }
\ No newline at end of file
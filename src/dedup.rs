@@ -0,0 +1,52 @@
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use tokio::time::{Duration, Instant};
+use crate::delivery::AlertTarget;
+
+// prune expired claims once the map grows past this many entries
+const PRUNE_THRESHOLD: usize = 10_000;
+
+/// Remembers which (target, trade) pairs were already delivered so reconnect
+/// replays or double processing can't alert the same chat or webhook twice.
+#[derive(Clone)]
+pub struct DeliveryGuard {
+    ttl: Duration,
+    claimed: Arc<Mutex<HashMap<(AlertTarget, u64), Instant>>>,
+}
+
+impl DeliveryGuard {
+    pub fn new(ttl: Duration) -> Self {
+        DeliveryGuard {
+            ttl,
+            claimed: Arc::new(Mutex::new(HashMap::new())),
+        }
+    }
+
+    /// Returns false if this trade was already delivered to the target recently.
+    pub fn try_claim(&self, target: &AlertTarget, trade_key: u64) -> bool {
+        let Ok(mut claimed) = self.claimed.lock() else {
+            return true;
+        };
+
+        if claimed.len() >= PRUNE_THRESHOLD {
+            let ttl = self.ttl;
+            claimed.retain(|_, at| at.elapsed() < ttl);
+        }
+
+        let key = (target.clone(), trade_key);
+        match claimed.get(&key) {
+            Some(at) if at.elapsed() < self.ttl => false,
+            _ => {
+                claimed.insert(key, Instant::now());
+                true
+            }
+        }
+    }
+
+    /// Gives a claim back after a failed send so a later attempt can deliver.
+    pub fn release(&self, target: &AlertTarget, trade_key: u64) {
+        if let Ok(mut claimed) = self.claimed.lock() {
+            claimed.remove(&(target.clone(), trade_key));
+        }
+    }
+}
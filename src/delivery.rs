@@ -0,0 +1,803 @@
+use anyhow::Result;
+use reqwest::Client;
+use serde::Serialize;
+use std::collections::HashSet;
+use std::fmt;
+use std::sync::{Arc, Mutex};
+use teloxide::{ApiError, RequestError};
+use tokio::sync::mpsc;
+use tokio::time::{interval, sleep, timeout, Duration, MissedTickBehavior};
+use tracing::{error, info, warn};
+use crate::{
+    alert_digest::AlertDigest,
+    budget::AlertBudget,
+    catch_up::CatchUp,
+    chart::PendingChart,
+    config::Config,
+    database::{Database, DeadLetter, DuplicatePreference, HeldAlert, NotificationRecord, UserSettings, UserSubscription},
+    dedup::DeliveryGuard,
+    experiments,
+    history::HistoryWriter,
+    hyperliquid::{CoinSymbol, WsTrade},
+    metrics::Metrics,
+    outbound::Priority,
+    redact,
+    telegram::TelegramBot,
+    ticker::TickerBoard,
+};
+
+/// Somewhere a single alert gets delivered.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum AlertTarget {
+    Chat(i64),
+    Webhook(String),
+}
+
+impl fmt::Display for AlertTarget {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            AlertTarget::Chat(chat_id) => write!(f, "chat {}", chat_id),
+            AlertTarget::Webhook(url) => write!(f, "webhook {}", redact::url_host(url)),
+        }
+    }
+}
+
+/// Who a trade goes to, up to the steps that need the bot (duplicates and
+/// /mute rules).
+pub struct FanOut<'a> {
+    /// The subscribers whose own threshold the trade clears, and who haven't
+    /// muted the coin from an alert.
+    pub subscribers: Vec<&'a UserSubscription>,
+    /// Every target of those subscribers, with the user it's for.
+    pub targets: Vec<(i64, AlertTarget, UserSettings)>,
+    /// Those of them on /priority for the coin.
+    pub priority_users: HashSet<i64>,
+}
+
+/// Spreads a trade worth `notional_usd` over a coin's subscribers.
+pub fn fan_out(subscribers: &[UserSubscription], notional_usd: f64) -> FanOut<'_> {
+    let subscribers: Vec<_> = subscribers
+        .iter()
+        .filter(|subscriber| subscriber.wants(notional_usd) && !subscriber.is_muted())
+        .collect();
+    let targets = subscribers
+        .iter()
+        .flat_map(|subscriber| {
+            alert_targets(subscriber)
+                .into_iter()
+                .map(move |(target, settings)| (subscriber.telegram_user_id, target, settings))
+        })
+        .collect();
+    let priority_users = subscribers
+        .iter()
+        .filter(|subscriber| subscriber.priority)
+        .map(|subscriber| subscriber.telegram_user_id)
+        .collect();
+    FanOut { subscribers, targets, priority_users }
+}
+
+/// Every target a subscription fans out to, each with the settings used to format it.
+pub fn alert_targets(subscriber: &UserSubscription) -> Vec<(AlertTarget, UserSettings)> {
+    let mut targets = vec![(AlertTarget::Chat(subscriber.destination_chat_id()), subscriber.settings.clone())];
+    for destination in &subscriber.destinations {
+        let target = match (destination.chat_id, &destination.webhook_url) {
+            (Some(chat_id), _) => AlertTarget::Chat(chat_id),
+            (None, Some(url)) => AlertTarget::Webhook(url.clone()),
+            (None, None) => continue,
+        };
+        // a destination pointing at the primary chat shouldn't double up
+        if targets.iter().any(|(existing, _)| *existing == target) {
+            continue;
+        }
+        targets.push((target, destination.settings(&subscriber.settings)));
+    }
+    targets
+}
+
+/// Drops copies of an alert that users asked not to get twice: their DM when a
+/// group they're in gets it too, or a group copy only they set up when they
+/// already get a DM. Memberships come from what's already known, so until a
+/// lookup lands a user gets both copies rather than neither.
+pub fn resolve_duplicates(
+    telegram_bot: &TelegramBot,
+    targets: Vec<(i64, AlertTarget, UserSettings)>,
+) -> Vec<(i64, AlertTarget, UserSettings)> {
+    // group and channel ids are negative, a DM's chat id is the user's id
+    let is_dm = |user_id: i64, target: &AlertTarget| *target == AlertTarget::Chat(user_id);
+    let groups: HashSet<i64> = targets
+        .iter()
+        .filter_map(|(_, target, _)| match target {
+            AlertTarget::Chat(chat_id) if *chat_id < 0 => Some(*chat_id),
+            _ => None,
+        })
+        .collect();
+    if groups.is_empty() || targets.iter().all(|(_, _, settings)| settings.duplicate_alerts == DuplicatePreference::Both) {
+        return targets;
+    }
+
+    // group copies go first, so a dm-preferring user's group can't take a
+    // group-preferring member's DM down with it
+    let mut dropped_groups = HashSet::new();
+    for group in &groups {
+        let owners: Vec<i64> = targets
+            .iter()
+            .filter(|(_, target, _)| *target == AlertTarget::Chat(*group))
+            .map(|(user_id, _, _)| *user_id)
+            .collect();
+        let mut drop = true;
+        for owner in &owners {
+            let prefers_dm = targets.iter().any(|(user_id, target, settings)| {
+                user_id == owner && is_dm(*user_id, target) && settings.duplicate_alerts == DuplicatePreference::Dm
+            });
+            if !prefers_dm || !telegram_bot.is_chat_member(*group, *owner) {
+                drop = false;
+                break;
+            }
+        }
+        if drop {
+            dropped_groups.insert(*group);
+        }
+    }
+
+    let mut resolved = Vec::with_capacity(targets.len());
+    for (user_id, target, settings) in targets {
+        if let AlertTarget::Chat(chat_id) = target {
+            if dropped_groups.contains(&chat_id) {
+                continue;
+            }
+        }
+        if is_dm(user_id, &target) && settings.duplicate_alerts == DuplicatePreference::Group {
+            let mut in_group = false;
+            for group in groups.difference(&dropped_groups) {
+                if telegram_bot.is_chat_member(*group, user_id) {
+                    in_group = true;
+                    break;
+                }
+            }
+            if in_group {
+                continue;
+            }
+        }
+        resolved.push((user_id, target, settings));
+    }
+    resolved
+}
+
+// how long a send waits for its trade's chart before going without
+const CHART_WAIT: Duration = Duration::from_secs(10);
+
+// a slow or silent webhook gives up rather than holding its delivery task
+const WEBHOOK_CONNECT_TIMEOUT: Duration = Duration::from_secs(5);
+const WEBHOOK_TIMEOUT: Duration = Duration::from_secs(10);
+
+fn webhook_client() -> Client {
+    Client::builder()
+        .connect_timeout(WEBHOOK_CONNECT_TIMEOUT)
+        .timeout(WEBHOOK_TIMEOUT)
+        // a redirect could point the post somewhere validation never saw
+        .redirect(reqwest::redirect::Policy::none())
+        .dns_resolver(Arc::new(PublicResolver))
+        .build()
+        // only fails where Client::new() would panic too, without a tls backend
+        .expect("couldn't build the webhook http client")
+}
+
+// validation resolved the host once, when the webhook was added; a name
+// re-pointed since (dns rebinding) is caught here, on every connect
+struct PublicResolver;
+
+impl reqwest::dns::Resolve for PublicResolver {
+    fn resolve(&self, name: hyper::client::connect::dns::Name) -> reqwest::dns::Resolving {
+        Box::pin(async move {
+            let addresses: Vec<std::net::SocketAddr> = tokio::net::lookup_host((name.as_str(), 0))
+                .await?
+                .filter(|address| is_public(address.ip()))
+                .collect();
+            if addresses.is_empty() {
+                return Err("the webhook host has no public address".into());
+            }
+            Ok(Box::new(addresses.into_iter()) as reqwest::dns::Addrs)
+        })
+    }
+}
+
+/// Checks a webhook url before it's stored: https, with a host that only
+/// resolves to public addresses, so alerts can't be aimed at the bot's own
+/// network or the cloud metadata service.
+pub async fn validate_webhook_url(url: &str) -> std::result::Result<(), String> {
+    let parsed = url::Url::parse(url).map_err(|_| "That isn't a valid url.".to_string())?;
+    if parsed.scheme() != "https" {
+        return Err("Webhooks must use https://.".to_string());
+    }
+    let host = parsed.host_str().filter(|host| !host.is_empty()).ok_or_else(|| "The webhook url has no host.".to_string())?;
+    let port = parsed.port_or_known_default().unwrap_or(443);
+
+    let addresses: Vec<std::net::SocketAddr> = tokio::net::lookup_host((host.trim_matches(|c| c == '[' || c == ']'), port))
+        .await
+        .map_err(|_| format!("Couldn't resolve {}.", host))?
+        .collect();
+    if addresses.is_empty() {
+        return Err(format!("Couldn't resolve {}.", host));
+    }
+    if addresses.iter().any(|address| !is_public(address.ip())) {
+        return Err("Webhooks must point at a public address.".to_string());
+    }
+    Ok(())
+}
+
+fn is_public(ip: std::net::IpAddr) -> bool {
+    use std::net::IpAddr;
+    match ip {
+        IpAddr::V4(ip) => {
+            let [a, b, ..] = ip.octets();
+            !(ip.is_private()
+                || ip.is_loopback()
+                || ip.is_link_local()
+                || ip.is_unspecified()
+                || ip.is_broadcast()
+                || ip.is_multicast()
+                || ip.is_documentation()
+                || a == 0
+                // carrier-grade nat, 100.64.0.0/10
+                || (a == 100 && (64..128).contains(&b)))
+        }
+        IpAddr::V6(ip) => match ip.to_ipv4_mapped() {
+            Some(ip) => is_public(IpAddr::V4(ip)),
+            None => {
+                let segments = ip.segments();
+                !(ip.is_loopback()
+                    || ip.is_unspecified()
+                    || ip.is_multicast()
+                    // unique local fc00::/7 and link-local fe80::/10
+                    || (segments[0] & 0xfe00) == 0xfc00
+                    || (segments[0] & 0xffc0) == 0xfe80
+                    // nat64 64:ff9b::/96, which reaches any ipv4 address
+                    || segments[..6] == [0x64, 0xff9b, 0, 0, 0, 0]
+                    // documentation 2001:db8::/32
+                    || segments[..2] == [0x2001, 0xdb8])
+            }
+        },
+    }
+}
+
+#[derive(Serialize)]
+struct WebhookPayload<'a> {
+    coin: &'a str,
+    side: &'a str,
+    px: &'a str,
+    sz: &'a str,
+    notional_usd: f64,
+    time: Option<i64>,
+    hash: Option<&'a str>,
+    text: String,
+}
+
+#[derive(Clone)]
+pub struct PendingAlert {
+    pub telegram_user_id: i64,
+    pub target: AlertTarget,
+    pub settings: UserSettings,
+    pub trade: WsTrade,
+    pub notional_usd: f64,
+    pub chart: Option<PendingChart>,
+    pub trade_key: u64,
+    /// From a /priority subscription: skips digests and tickers and jumps the send queue.
+    pub priority: bool,
+    /// Sends tried so far.
+    pub attempts: u32,
+    /// Whether a replayed trade can be folded into the chat's catch-up
+    /// message; off for dead letters an admin sends again on purpose.
+    pub catch_up: bool,
+    /// Whether this alert took the user's /dailycap slot for its trade, given
+    /// back if it can't be delivered.
+    pub spends_budget: bool,
+}
+
+impl PendingAlert {
+    /// A dead letter to be sent again, with retries afresh. `settings` are the
+    /// target's current ones, since what the alert went out with isn't kept.
+    pub fn from_dead_letter(letter: DeadLetter, target: AlertTarget, settings: UserSettings) -> Self {
+        PendingAlert {
+            telegram_user_id: letter.telegram_user_id,
+            target,
+            settings,
+            trade: stored_trade(letter.coin, letter.side, letter.px, letter.sz, letter.trade_time, letter.tid, letter.hash),
+            notional_usd: letter.notional_usd,
+            chart: None,
+            trade_key: letter.trade_key as u64,
+            priority: letter.priority,
+            attempts: 0,
+            catch_up: false,
+            spends_budget: false,
+        }
+    }
+
+    /// An alert a previous run held for a digest, as it was first tried.
+    pub fn from_held(held: &HeldAlert, settings: UserSettings) -> Self {
+        PendingAlert {
+            telegram_user_id: held.telegram_user_id,
+            target: AlertTarget::Chat(held.telegram_chat_id),
+            settings,
+            trade: stored_trade(
+                held.coin.clone(),
+                held.side.clone(),
+                held.px.clone(),
+                held.sz.clone(),
+                held.trade_time,
+                held.tid,
+                held.hash.clone(),
+            ),
+            notional_usd: held.notional_usd,
+            chart: None,
+            trade_key: held.trade_key as u64,
+            priority: false,
+            attempts: 1,
+            catch_up: false,
+            spends_budget: false,
+        }
+    }
+}
+
+// a trade rebuilt from the columns dead_letter and held_alerts keep
+fn stored_trade(
+    coin: CoinSymbol,
+    side: String,
+    px: String,
+    sz: String,
+    trade_time: Option<chrono::DateTime<chrono::Utc>>,
+    tid: Option<i64>,
+    hash: Option<String>,
+) -> WsTrade {
+    WsTrade {
+        coin,
+        side,
+        px,
+        sz,
+        time: trade_time.map(|time| time.timestamp_millis()),
+        tid: tid.map(|tid| tid as u64),
+        hash,
+        users: None,
+        fills: 1,
+        // the notional is already in usd
+        quote_usd: 1.0,
+    }
+}
+
+// telegram errors no retry will fix: the bot was blocked or removed, or the
+// chat is gone
+fn is_permanent(e: &anyhow::Error) -> bool {
+    matches!(
+        e.downcast_ref::<RequestError>(),
+        Some(RequestError::Api(
+            ApiError::BotBlocked
+                | ApiError::BotKicked
+                | ApiError::BotKickedFromSupergroup
+                | ApiError::ChatNotFound
+                | ApiError::GroupDeactivated
+                | ApiError::UserDeactivated
+                | ApiError::CantInitiateConversation
+                | ApiError::NotEnoughRightsToPostMessages
+        ))
+    )
+}
+
+/// Where a dead letter was headed.
+pub fn dead_letter_target(letter: &DeadLetter) -> Option<AlertTarget> {
+    match (letter.telegram_chat_id, &letter.webhook_url) {
+        (Some(chat_id), _) => Some(AlertTarget::Chat(chat_id)),
+        (None, Some(url)) => Some(AlertTarget::Webhook(url.clone())),
+        (None, None) => None,
+    }
+}
+
+// sends trade alerts, pushing failures onto a retry queue with exponential
+// backoff until `retry.max_attempts` is used up, then into the dead_letter table
+#[derive(Clone)]
+pub struct AlertDelivery {
+    database: Database,
+    telegram_bot: TelegramBot,
+    config: Config,
+    metrics: Metrics,
+    history: HistoryWriter,
+    delivery_guard: DeliveryGuard,
+    ticker: TickerBoard,
+    digest: AlertDigest,
+    catch_up: CatchUp,
+    alert_budget: AlertBudget,
+    http_client: Client,
+    retry_tx: mpsc::UnboundedSender<PendingAlert>,
+    // dropped alerts not yet reported to admins, sent as one message a minute
+    dropped: Arc<Mutex<Vec<String>>>,
+}
+
+impl AlertDelivery {
+    #[allow(clippy::too_many_arguments)]
+    pub fn spawn(
+        database: Database,
+        telegram_bot: TelegramBot,
+        config: Config,
+        metrics: Metrics,
+        history: HistoryWriter,
+        delivery_guard: DeliveryGuard,
+        ticker: TickerBoard,
+        digest: AlertDigest,
+        catch_up: CatchUp,
+        alert_budget: AlertBudget,
+    ) -> Self {
+        let (retry_tx, retry_rx) = mpsc::unbounded_channel();
+        let delivery = AlertDelivery {
+            database,
+            telegram_bot,
+            config,
+            metrics,
+            history,
+            delivery_guard,
+            ticker,
+            digest,
+            catch_up,
+            alert_budget,
+            http_client: webhook_client(),
+            retry_tx,
+            dropped: Arc::new(Mutex::new(Vec::new())),
+        };
+        tokio::spawn(run_retry_queue(delivery.clone(), retry_rx));
+        tokio::spawn(run_dropped_reports(delivery.clone()));
+        tokio::spawn(run_digests(delivery.clone()));
+        if delivery.catch_up.enabled() {
+            tokio::spawn(run_catch_ups(delivery.clone()));
+        }
+        delivery
+    }
+
+    pub fn deliver(&self, alert: PendingAlert) {
+        let delivery = self.clone();
+        tokio::spawn(async move { delivery.attempt(alert).await });
+    }
+
+    async fn attempt(&self, mut alert: PendingAlert) {
+        alert.attempts += 1;
+
+        let result = match &alert.target {
+            // tickers and digests don't send a message of the alert's own
+            AlertTarget::Chat(chat_id) if alert.settings.ticker_mode && !alert.priority => self
+                .ticker
+                .record(*chat_id, &alert.trade, alert.notional_usd, &alert.settings)
+                .await
+                .map(|_| None),
+            // recorded as delivered once the digest goes out
+            AlertTarget::Chat(chat_id) if alert.settings.digest_mins.is_some() && !alert.priority => {
+                match self.digest.record(*chat_id, &alert).await {
+                    Ok(()) => return,
+                    Err(e) => Err(e),
+                }
+            }
+            // a replayed trade joins the chat's catch-up instead, recorded as
+            // delivered once that goes out; retries are late only because of
+            // us, so they still go out on their own
+            AlertTarget::Chat(chat_id) if alert.catch_up && alert.attempts == 1 && self.catch_up.is_backfill(&alert.trade) => {
+                self.catch_up.record(*chat_id, &alert).await;
+                return;
+            }
+            AlertTarget::Chat(chat_id) => {
+                let chart = match alert.chart.clone() {
+                    // sent without one rather than held up by a slow render
+                    Some(chart) if alert.settings.charts_enabled => timeout(CHART_WAIT, chart).await.ok().flatten(),
+                    _ => None,
+                };
+                self.telegram_bot
+                    .send_trade_notification(
+                        *chat_id,
+                        &alert.trade,
+                        alert.notional_usd,
+                        &alert.settings,
+                        chart,
+                        if alert.priority { Priority::Reply } else { Priority::Alert },
+                    )
+                    .await
+                    .map(Some)
+            }
+            AlertTarget::Webhook(url) => self.post_webhook(url, &alert).await.map(|_| None),
+        };
+
+        match result {
+            Ok(message_id) => self.record_sent(&alert, message_id),
+            // a blocked bot or a deleted chat won't be fixed by trying again
+            Err(e) if alert.attempts < self.config.retry.max_attempts && !is_permanent(&e) => {
+                warn!(
+                    "{} alert to {} failed (attempt {}/{}), queueing retry: {}",
+                    alert.trade.coin, alert.target, alert.attempts, self.config.retry.max_attempts, e
+                );
+                self.metrics.record_alert_retried();
+                let _ = self.retry_tx.send(alert);
+            }
+            Err(e) => self.give_up(alert, e.to_string()).await,
+        }
+    }
+
+    async fn post_webhook(&self, url: &str, alert: &PendingAlert) -> Result<()> {
+        let trade = &alert.trade;
+        let payload = WebhookPayload {
+            coin: trade.coin.as_str(),
+            side: &trade.side,
+            px: &trade.px,
+            sz: &trade.sz,
+            notional_usd: alert.notional_usd,
+            time: trade.time,
+            hash: trade.hash.as_deref(),
+            text: self.telegram_bot.trade_message(trade, alert.notional_usd, &alert.settings).await,
+        };
+
+        // reqwest errors quote the url, which ends up in logs, admin DMs and dead letters
+        self.http_client
+            .post(url)
+            .json(&payload)
+            .send()
+            .await
+            .and_then(reqwest::Response::error_for_status)
+            .map_err(reqwest::Error::without_url)?;
+        info!("posted {} trade alert to webhook {}", trade.coin, redact::url_host(url));
+        Ok(())
+    }
+
+    fn record_sent(&self, alert: &PendingAlert, message_id: Option<i32>) {
+        let latency_slo_ms = self.config.metrics.latency_slo_ms;
+        let latency_ms = alert
+            .trade
+            .time
+            .map(|time| (chrono::Utc::now().timestamp_millis() - time).max(0) as u64);
+        if let Some(latency_ms) = latency_ms.filter(|ms| *ms > latency_slo_ms) {
+            warn!(
+                "{} alert to {} took {}ms (slo {}ms)",
+                alert.trade.coin, alert.target, latency_ms, latency_slo_ms
+            );
+        }
+        self.metrics.record_alert_sent(latency_ms);
+
+        let mut record = self.notification_record(alert, true);
+        record.latency_ms = latency_ms.map(|ms| ms as i64);
+        record.message_id = message_id;
+        // only alerts sent as their own message show a variant's format
+        if let (AlertTarget::Chat(chat_id), Some(_)) = (&alert.target, message_id) {
+            if let Some(variant) = experiments::assign(&self.config.experiment, *chat_id) {
+                record.experiment = Some(self.config.experiment.name.clone());
+                record.variant = Some(variant.as_str().to_string());
+            }
+        }
+        self.history.record_notification(record);
+    }
+
+    // an alert that went out inside a digest; late on purpose, so it stays
+    // out of the latency numbers
+    fn record_held_sent(&self, alert: &PendingAlert) {
+        self.metrics.record_alert_sent(None);
+        self.history.record_notification(self.notification_record(alert, true));
+    }
+
+    async fn send_digests(&self) {
+        for alerts in self.digest.take_due().await {
+            let (telegram_user_id, chat_id) = (alerts.telegram_user_id, alerts.chat_id);
+            match self.telegram_bot.send_text(chat_id, &alerts.text(), Priority::Digest).await {
+                Ok(()) => {
+                    info!("sent alert digest for user {} to chat {}", telegram_user_id, chat_id);
+                    self.digest.release(&alerts).await;
+                    for (_, alert) in &alerts.alerts {
+                        self.record_held_sent(alert);
+                    }
+                }
+                Err(e) if alerts.failed_sends + 1 < self.config.retry.max_attempts => {
+                    warn!(
+                        "couldn't send alert digest for user {} to chat {} (attempt {}/{}), holding it for the next round: {}",
+                        telegram_user_id, chat_id, alerts.failed_sends + 1, self.config.retry.max_attempts, e
+                    );
+                    let mut alerts = alerts;
+                    alerts.failed_sends += 1;
+                    self.digest.hold_again(alerts).await;
+                }
+                Err(e) => {
+                    self.digest.release(&alerts).await;
+                    for (_, mut alert) in alerts.alerts {
+                        alert.attempts = alerts.failed_sends + 1;
+                        self.give_up(alert, e.to_string()).await;
+                    }
+                }
+            }
+        }
+    }
+
+    async fn send_catch_ups(&self) {
+        for backfill in self.catch_up.take_due().await {
+            let (telegram_user_id, chat_id) = (backfill.telegram_user_id, backfill.chat_id);
+            match self.telegram_bot.send_text(chat_id, &backfill.text(), Priority::Digest).await {
+                Ok(()) => {
+                    info!("sent catch-up for user {} to chat {}", telegram_user_id, chat_id);
+                    for alert in &backfill.alerts {
+                        self.record_held_sent(alert);
+                    }
+                }
+                Err(e) if backfill.failed_sends + 1 < self.config.retry.max_attempts => {
+                    warn!(
+                        "couldn't send catch-up for user {} to chat {} (attempt {}/{}), holding it: {}",
+                        telegram_user_id, chat_id, backfill.failed_sends + 1, self.config.retry.max_attempts, e
+                    );
+                    let mut backfill = backfill;
+                    backfill.failed_sends += 1;
+                    self.catch_up.hold_again(backfill).await;
+                }
+                Err(e) => {
+                    for mut alert in backfill.alerts {
+                        alert.attempts = backfill.failed_sends + 1;
+                        self.give_up(alert, e.to_string()).await;
+                    }
+                }
+            }
+        }
+    }
+
+    async fn give_up(&self, alert: PendingAlert, error: String) {
+        self.delivery_guard.release(&alert.target, alert.trade_key);
+        if alert.spends_budget {
+            self.alert_budget.refund(alert.telegram_user_id);
+        }
+        self.metrics.record_alert_failed();
+        error!(
+            "Failed to send notification to user {} via {} after {} attempts: {}",
+            alert.telegram_user_id, alert.target, alert.attempts, error
+        );
+
+        let mut record = self.notification_record(&alert, false);
+        record.error = Some(error.clone());
+        let letter = DeadLetter {
+            id: 0,
+            telegram_user_id: alert.telegram_user_id,
+            telegram_chat_id: record.telegram_chat_id,
+            // sending it again needs the whole url
+            webhook_url: match &alert.target {
+                AlertTarget::Webhook(url) => Some(url.clone()),
+                AlertTarget::Chat(_) => None,
+            },
+            coin: alert.trade.coin.clone(),
+            side: alert.trade.side.clone(),
+            px: alert.trade.px.clone(),
+            sz: alert.trade.sz.clone(),
+            trade_time: alert.trade.time.and_then(chrono::DateTime::from_timestamp_millis),
+            tid: alert.trade.tid.map(|tid| tid as i64),
+            hash: alert.trade.hash.clone(),
+            notional_usd: alert.notional_usd,
+            trade_key: record.trade_key,
+            priority: alert.priority,
+            attempts: record.attempts,
+            error: error.clone(),
+            created_at: chrono::Utc::now(),
+        };
+        self.history.record_notification(record);
+
+        let queued = match self.database.add_dead_letter(&letter).await {
+            Ok(()) => "moved to the dead letter queue (/deadletters)",
+            Err(e) => {
+                error!("couldn't dead-letter {} alert for {}: {}", alert.trade.coin, alert.target, e);
+                "dropped, the dead letter queue couldn't take it"
+            }
+        };
+        let report = format!(
+            "{} alert for {} {} after {} attempts: {}",
+            alert.trade.coin,
+            alert.target,
+            queued,
+            alert.attempts,
+            redact::redact(&error)
+        );
+        if let Ok(mut dropped) = self.dropped.lock() {
+            dropped.push(report);
+        }
+    }
+
+    // one message for however many alerts were dropped since the last, so an
+    // outage doesn't become an admin DM per alert
+    async fn report_dropped(&self) {
+        let reports = match self.dropped.lock() {
+            Ok(mut dropped) => std::mem::take(&mut *dropped),
+            Err(_) => return,
+        };
+        if reports.is_empty() {
+            return;
+        }
+
+        let mut admin_msg = match reports.len() {
+            1 => String::new(),
+            dropped => format!("{} alerts were dropped in the last minute:\n\n", dropped),
+        };
+        admin_msg.push_str(&reports.iter().take(DROPPED_REPORT_LINES).cloned().collect::<Vec<_>>().join("\n\n"));
+        if reports.len() > DROPPED_REPORT_LINES {
+            admin_msg.push_str(&format!("\n\n…and {} more, see /deadletters", reports.len() - DROPPED_REPORT_LINES));
+        }
+        for admin_id in &self.config.telegram.admin_user_ids {
+            if let Err(e) = self.telegram_bot.send_text(*admin_id, &admin_msg, Priority::Alert).await {
+                warn!("couldn't notify admin {} of dropped alerts: {}", admin_id, e);
+            }
+        }
+    }
+
+    fn notification_record(&self, alert: &PendingAlert, delivered: bool) -> NotificationRecord {
+        // the log keeps only the host, the rest of a webhook url is its secret
+        let (telegram_chat_id, webhook_url) = match &alert.target {
+            AlertTarget::Chat(chat_id) => (Some(*chat_id), None),
+            AlertTarget::Webhook(url) => (None, Some(redact::url_host(url))),
+        };
+        NotificationRecord {
+            telegram_user_id: alert.telegram_user_id,
+            telegram_chat_id,
+            webhook_url,
+            coin: alert.trade.coin.clone(),
+            trade_key: alert.trade_key as i64,
+            side: alert.trade.side.clone(),
+            notional_usd: alert.notional_usd,
+            delivered,
+            attempts: alert.attempts as i32,
+            latency_ms: None,
+            error: None,
+            message_id: None,
+            experiment: None,
+            variant: None,
+        }
+    }
+
+    fn retry_delay(&self, attempts: u32) -> Duration {
+        let retry = &self.config.retry;
+        let delay = retry
+            .base_delay_ms
+            .saturating_mul(2_u64.saturating_pow(attempts.saturating_sub(1)))
+            .min(retry.max_delay_ms);
+        let jitter = (delay as f64 * 0.1 * rand::random::<f64>()) as u64;
+        Duration::from_millis(delay + jitter)
+    }
+}
+
+// how often admins hear about dropped alerts, and how many each report lists
+const DROPPED_REPORT_INTERVAL: Duration = Duration::from_secs(60);
+const DROPPED_REPORT_LINES: usize = 10;
+// how often digest buffers are checked against their users' intervals
+const DIGEST_INTERVAL: Duration = Duration::from_secs(30);
+// how often catch-ups are checked for a quiet spell
+const CATCH_UP_INTERVAL: Duration = Duration::from_secs(2);
+
+async fn run_digests(delivery: AlertDelivery) {
+    if let Err(e) = delivery.digest.reload().await {
+        error!("couldn't reload held digest alerts, they'll go out after the next restart: {}", e);
+    }
+    let mut ticker = interval(DIGEST_INTERVAL);
+    ticker.set_missed_tick_behavior(MissedTickBehavior::Delay);
+    loop {
+        ticker.tick().await;
+        delivery.send_digests().await;
+    }
+}
+
+async fn run_dropped_reports(delivery: AlertDelivery) {
+    let mut ticker = interval(DROPPED_REPORT_INTERVAL);
+    ticker.set_missed_tick_behavior(MissedTickBehavior::Delay);
+    loop {
+        ticker.tick().await;
+        delivery.report_dropped().await;
+    }
+}
+
+async fn run_catch_ups(delivery: AlertDelivery) {
+    let mut ticker = interval(CATCH_UP_INTERVAL);
+    ticker.set_missed_tick_behavior(MissedTickBehavior::Delay);
+    loop {
+        ticker.tick().await;
+        delivery.send_catch_ups().await;
+    }
+}
+
+async fn run_retry_queue(delivery: AlertDelivery, mut retry_rx: mpsc::UnboundedReceiver<PendingAlert>) {
+    while let Some(alert) = retry_rx.recv().await {
+        let delay = delivery.retry_delay(alert.attempts);
+        let delivery = delivery.clone();
+        tokio::spawn(async move {
+            sleep(delay).await;
+            delivery.attempt(alert).await;
+        });
+    }
+    info!("alert retry queue stopped");
+}
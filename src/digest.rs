@@ -0,0 +1,163 @@
+use anyhow::Result;
+use std::collections::HashMap;
+use tokio::time::{interval, Duration, MissedTickBehavior};
+use tracing::{error, info, warn};
+use crate::{
+    database::{Database, WeeklyDigestSchedule},
+    formatting,
+    hyperliquid::HyperliquidClient,
+    market,
+    outbound::Priority,
+    telegram::TelegramBot,
+};
+
+// sunday evening lasts six hours, so this catches everyone well inside it
+const CHECK_INTERVAL: Duration = Duration::from_secs(15 * 60);
+
+const DIGEST_DAYS: i64 = 7;
+const BIGGEST_TRADES: i64 = 5;
+
+// funding moves this much (hourly rate, as a fraction) before it reads as a trend
+const FUNDING_TREND_MIN: f64 = 0.000_005;
+
+// for users on /weeklydigest: a sunday evening recap of their coins' week,
+// mostly from the nightly trade rollups
+pub struct WeeklyDigest {
+    database: Database,
+    hyperliquid_client: HyperliquidClient,
+    telegram_bot: TelegramBot,
+}
+
+impl WeeklyDigest {
+    pub fn spawn(database: Database, hyperliquid_client: HyperliquidClient, telegram_bot: TelegramBot) {
+        let digest = WeeklyDigest {
+            database,
+            hyperliquid_client,
+            telegram_bot,
+        };
+
+        tokio::spawn(async move {
+            let mut ticker = interval(CHECK_INTERVAL);
+            ticker.set_missed_tick_behavior(MissedTickBehavior::Delay);
+            loop {
+                ticker.tick().await;
+                if let Err(e) = digest.send_due_digests().await {
+                    error!("couldn't send weekly digests: {}", e);
+                }
+            }
+        });
+        info!("weekly digest started");
+    }
+
+    async fn send_due_digests(&self) -> Result<()> {
+        for schedule in self.database.get_due_weekly_digests().await? {
+            if let Err(e) = self.send_digest(&schedule).await {
+                warn!("couldn't send weekly digest to user {}: {}", schedule.telegram_user_id, e);
+            }
+        }
+        Ok(())
+    }
+
+    async fn send_digest(&self, schedule: &WeeklyDigestSchedule) -> Result<()> {
+        let coins = self.database.get_user_subscriptions(schedule.telegram_user_id).await?;
+
+        // nothing to recap, but still move the clock forward
+        if !coins.is_empty() {
+            let digest = self.build_digest(schedule.telegram_user_id, &coins).await?;
+            self.telegram_bot.send_text(schedule.telegram_chat_id, &digest, Priority::Digest).await?;
+            info!("sent weekly digest to user {}", schedule.telegram_user_id);
+        }
+
+        self.database.mark_weekly_digest_sent(schedule.telegram_user_id).await
+    }
+
+    async fn build_digest(&self, telegram_user_id: i64, coins: &[String]) -> Result<String> {
+        let mut message = "Your Week on Hyperliquid".to_string();
+
+        let flows = self.database.coin_flows(coins, DIGEST_DAYS).await?;
+        if !flows.is_empty() {
+            message.push_str("\n\nWhale flow:");
+            for flow in &flows {
+                let total = flow.buy_usd + flow.sell_usd;
+                let sign = if flow.net_usd() >= 0.0 { "+" } else { "-" };
+                message.push_str(&format!(
+                    "\n{}: net {}{} ({:.0}% buy of {})",
+                    flow.coin,
+                    sign,
+                    formatting::format_usd(flow.net_usd().abs(), false),
+                    if total > 0.0 { flow.buy_usd / total * 100.0 } else { 0.0 },
+                    formatting::format_usd(total, false)
+                ));
+            }
+        }
+
+        let trades = self.database.biggest_trades(coins, DIGEST_DAYS, BIGGEST_TRADES).await?;
+        if !trades.is_empty() {
+            message.push_str("\n\nBiggest trades:");
+            for trade in &trades {
+                message.push_str(&format!(
+                    "\n{} {} {} · {}",
+                    trade.coin,
+                    if trade.side == "B" { "BUY" } else { "SELL" },
+                    formatting::format_usd(trade.notional_usd, false),
+                    trade.traded_at.format("%a %b %-d")
+                ));
+            }
+        }
+
+        match self.funding_text(coins).await {
+            Ok(text) if !text.is_empty() => message.push_str(&format!("\n\nFunding, 7d avg → now:{}", text)),
+            Ok(_) => {}
+            Err(e) => warn!("couldn't load funding for weekly digest: {}", e),
+        }
+
+        let alerts = self.database.alerts_received(telegram_user_id, DIGEST_DAYS).await?;
+        let total: i64 = alerts.iter().map(|(_, count)| count).sum();
+        message.push_str(&format!("\n\nAlerts you got: {}", total));
+        if !alerts.is_empty() {
+            let per_coin: Vec<String> = alerts.iter().map(|(coin, count)| format!("{} {}", coin, count)).collect();
+            message.push_str(&format!(" ({})", per_coin.join(", ")));
+        }
+
+        message.push_str("\n\nUse /weeklydigest off to stop these.");
+        Ok(message)
+    }
+
+    // a line per coin comparing the week's average hourly rate with the current one
+    async fn funding_text(&self, coins: &[String]) -> Result<String> {
+        let current: HashMap<String, f64> = market::snapshots(&self.hyperliquid_client, coins, 0)
+            .await?
+            .into_iter()
+            .map(|snapshot| (snapshot.coin, snapshot.funding))
+            .collect();
+        let since = (chrono::Utc::now() - chrono::Duration::days(DIGEST_DAYS)).timestamp_millis();
+
+        let mut text = String::new();
+        for coin in coins {
+            let Some(now) = current.get(coin) else {
+                continue;
+            };
+            let rates: Vec<f64> = self
+                .hyperliquid_client
+                .funding_history(coin, since)
+                .await?
+                .iter()
+                .filter_map(|rate| rate.funding_rate.parse().ok())
+                .collect();
+            if rates.is_empty() {
+                continue;
+            }
+
+            let average = rates.iter().sum::<f64>() / rates.len() as f64;
+            let trend = if now - average > FUNDING_TREND_MIN {
+                "rising"
+            } else if average - now > FUNDING_TREND_MIN {
+                "falling"
+            } else {
+                "steady"
+            };
+            text.push_str(&format!("\n{}: {:.4}% → {:.4}%/h ({})", coin, average * 100.0, now * 100.0, trend));
+        }
+        Ok(text)
+    }
+}
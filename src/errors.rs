@@ -0,0 +1,65 @@
+use std::collections::VecDeque;
+use std::sync::{Arc, Mutex};
+use chrono::{DateTime, Utc};
+use tracing::error;
+
+// how many recent errors /error can look up; older ones are only in the logs
+const RECENT_ERRORS: usize = 1000;
+
+const CROCKFORD: &[u8; 32] = b"0123456789ABCDEFGHJKMNPQRSTVWXYZ";
+
+#[derive(Debug, Clone)]
+pub struct ErrorReport {
+    pub id: String,
+    pub at: DateTime<Utc>,
+    pub user_id: i64,
+    pub chat_id: i64,
+    pub context: String,
+}
+
+/// Errors users were told about, each under an id they can quote back.
+#[derive(Clone, Default)]
+pub struct ErrorLog {
+    recent: Arc<Mutex<VecDeque<ErrorReport>>>,
+}
+
+impl ErrorLog {
+    pub fn new() -> Self {
+        ErrorLog::default()
+    }
+
+    /// Logs `context` under a fresh id and returns the id.
+    pub fn record(&self, user_id: i64, chat_id: i64, context: String) -> String {
+        let id = new_ulid();
+        error!("error {} (user {}, chat {}): {}", id, user_id, chat_id, context);
+
+        if let Ok(mut recent) = self.recent.lock() {
+            if recent.len() >= RECENT_ERRORS {
+                recent.pop_front();
+            }
+            recent.push_back(ErrorReport {
+                id: id.clone(),
+                at: Utc::now(),
+                user_id,
+                chat_id,
+                context,
+            });
+        }
+        id
+    }
+
+    pub fn get(&self, id: &str) -> Option<ErrorReport> {
+        let recent = self.recent.lock().ok()?;
+        recent.iter().rev().find(|report| report.id.eq_ignore_ascii_case(id)).cloned()
+    }
+}
+
+// 48 bits of unix millis then 80 random, in crockford base32, so ids sort by time
+fn new_ulid() -> String {
+    let millis = Utc::now().timestamp_millis() as u128 & ((1 << 48) - 1);
+    let value = (millis << 80) | (rand::random::<u128>() & ((1 << 80) - 1));
+    (0..26)
+        .rev()
+        .map(|i| CROCKFORD[((value >> (i * 5)) & 31) as usize] as char)
+        .collect()
+}
@@ -0,0 +1,71 @@
+use crate::{config::ExperimentConfig, database::VariantResult};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Variant {
+    A,
+    B,
+}
+
+impl Variant {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Variant::A => "a",
+            Variant::B => "b",
+        }
+    }
+}
+
+/// The variant a chat's alerts go out in, or `None` with no experiment
+/// running. A chat keeps its variant across restarts, and a group's members
+/// all see the same one.
+pub fn assign(config: &ExperimentConfig, chat_id: i64) -> Option<Variant> {
+    if config.name.is_empty() {
+        return None;
+    }
+    // fnv-1a, so the split doesn't move with the std hasher
+    let mut hash: u64 = 0xcbf2_9ce4_8422_2325;
+    for byte in config.name.bytes().chain(chat_id.to_le_bytes()) {
+        hash ^= u64::from(byte);
+        hash = hash.wrapping_mul(0x0100_0000_01b3);
+    }
+    Some(if hash % 100 < u64::from(config.b_percent.min(100)) { Variant::B } else { Variant::A })
+}
+
+/// The variant's alert template; `None` for the built-in format.
+pub fn template(config: &ExperimentConfig, variant: Variant) -> Option<&str> {
+    let template = match variant {
+        Variant::A => &config.variant_a,
+        Variant::B => &config.variant_b,
+    };
+    (!template.trim().is_empty()).then_some(template.as_str())
+}
+
+/// /experiment's summary: per variant, how many alerts went out and how many
+/// got a tap.
+pub fn report(config: &ExperimentConfig, results: &[VariantResult]) -> String {
+    let mut text = format!("Experiment {} ({}% of chats on b)\n", config.name, config.b_percent.min(100));
+    let mut rates = Vec::new();
+    for variant in [Variant::A, Variant::B] {
+        let result = results.iter().find(|result| result.variant == variant.as_str());
+        let (alerts, chats, tapped, taps) = result.map_or((0, 0, 0, 0), |r| (r.alerts, r.chats, r.tapped, r.taps));
+        let format = if template(config, variant).is_some() { "template" } else { "built-in" };
+        let rate = (alerts > 0).then(|| tapped as f64 / alerts as f64 * 100.0);
+        text.push_str(&format!(
+            "\n{} ({}): {} alerts to {} chats, {} tapped{} · {} taps",
+            variant.as_str().to_uppercase(),
+            format,
+            alerts,
+            chats,
+            tapped,
+            rate.map_or(String::new(), |rate| format!(" ({:.1}%)", rate)),
+            taps
+        ));
+        rates.push(rate);
+    }
+    if let [Some(a), Some(b)] = rates[..] {
+        if a > 0.0 {
+            text.push_str(&format!("\n\nB vs A: {:+.0}% tap rate", (b - a) / a * 100.0));
+        }
+    }
+    text
+}
@@ -0,0 +1,60 @@
+use std::sync::atomic::{AtomicBool, AtomicU32, Ordering};
+use std::sync::Arc;
+use teloxide::{ApiError, Bot, RequestError};
+
+/// The bot notifications go out through: the primary, until it fails enough
+/// times in a row with token or network errors that the backup takes over.
+/// Commands keep polling on the primary either way.
+#[derive(Clone)]
+pub struct BotFailover {
+    primary: Bot,
+    backup: Option<Bot>,
+    threshold: u32,
+    consecutive_failures: Arc<AtomicU32>,
+    failed_over: Arc<AtomicBool>,
+}
+
+impl BotFailover {
+    pub fn new(primary: Bot, backup: Option<Bot>, threshold: u32) -> Self {
+        BotFailover {
+            primary,
+            backup,
+            threshold: threshold.max(1),
+            consecutive_failures: Arc::new(AtomicU32::new(0)),
+            failed_over: Arc::new(AtomicBool::new(false)),
+        }
+    }
+
+    pub fn sender(&self) -> &Bot {
+        match &self.backup {
+            Some(backup) if self.failed_over.load(Ordering::Relaxed) => backup,
+            _ => &self.primary,
+        }
+    }
+
+    /// Counts a send's outcome, returning true on the send that tips it over
+    /// to the backup.
+    pub fn record<T>(&self, result: &Result<T, RequestError>) -> bool {
+        if self.backup.is_none() || self.failed_over.load(Ordering::Relaxed) {
+            return false;
+        }
+
+        match result {
+            Err(e) if is_bot_failure(e) => {
+                let failures = self.consecutive_failures.fetch_add(1, Ordering::Relaxed) + 1;
+                failures >= self.threshold && !self.failed_over.swap(true, Ordering::Relaxed)
+            }
+            // per-chat errors (blocked, chat not found, ...) say nothing about the token
+            Err(_) => false,
+            Ok(_) => {
+                self.consecutive_failures.store(0, Ordering::Relaxed);
+                false
+            }
+        }
+    }
+}
+
+// errors that mean the bot itself can't reach telegram, rather than one chat
+fn is_bot_failure(e: &RequestError) -> bool {
+    matches!(e, RequestError::Network(_) | RequestError::Api(ApiError::NotFound))
+}
@@ -0,0 +1,81 @@
+use std::collections::HashSet;
+use std::sync::Arc;
+use tokio::time::{interval, Duration, MissedTickBehavior};
+use tracing::{info, warn};
+use crate::{
+    config::FeedHealthConfig,
+    formatting,
+    hyperliquid::{FeedStatus, WebSocketManager},
+    metrics::Metrics,
+};
+
+// how long a busy feed can go without a message before it counts as quiet,
+// or None for feeds too new or too slow to judge
+fn quiet_after(status: &FeedStatus, config: &FeedHealthConfig) -> Option<Duration> {
+    let baseline = status.baseline_gap?;
+    if status.messages < config.min_messages || baseline > Duration::from_secs(config.busy_gap_secs) {
+        return None;
+    }
+    Some(baseline.mul_f64(config.quiet_multiple).max(Duration::from_secs(config.min_quiet_secs)))
+}
+
+pub fn is_quiet(status: &FeedStatus, config: &FeedHealthConfig) -> bool {
+    match (quiet_after(status, config), status.last_message_age) {
+        (Some(quiet_after), Some(age)) => age >= quiet_after,
+        _ => false,
+    }
+}
+
+// a socket can stay open with hl no longer sending on it, which no reconnect
+// logic catches; a busy coin going silent is the only sign
+pub struct FeedHealthMonitor {
+    config: FeedHealthConfig,
+    ws_manager: Arc<WebSocketManager>,
+    metrics: Metrics,
+    quiet: HashSet<String>,
+}
+
+impl FeedHealthMonitor {
+    pub fn spawn(config: FeedHealthConfig, ws_manager: Arc<WebSocketManager>, metrics: Metrics) {
+        let mut monitor = FeedHealthMonitor {
+            config,
+            ws_manager,
+            metrics,
+            quiet: HashSet::new(),
+        };
+
+        tokio::spawn(async move {
+            let mut ticker = interval(Duration::from_secs(monitor.config.check_interval_secs.max(1)));
+            ticker.set_missed_tick_behavior(MissedTickBehavior::Delay);
+            loop {
+                ticker.tick().await;
+                monitor.check().await;
+            }
+        });
+    }
+
+    async fn check(&mut self) {
+        let statuses = self.ws_manager.tenant_feed_statuses().await;
+        self.quiet.retain(|feed_key| statuses.iter().any(|status| &status.feed_key == feed_key));
+
+        for status in &statuses {
+            if !is_quiet(status, &self.config) {
+                if self.quiet.remove(&status.feed_key) {
+                    info!("{} is sending again", status.feed_key);
+                }
+                continue;
+            }
+            if !self.quiet.insert(status.feed_key.clone()) {
+                continue;
+            }
+
+            self.metrics.record_feed_quiet();
+            warn!(
+                "{} has gone quiet: no message for {}, usually one every {:.1}s",
+                status.feed_key,
+                status.last_message_age.map(formatting::format_duration).unwrap_or_default(),
+                status.baseline_gap.unwrap_or_default().as_secs_f64()
+            );
+        }
+    }
+}
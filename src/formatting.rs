@@ -0,0 +1,366 @@
+use tokio::time::Duration;
+use crate::hyperliquid::WsTrade;
+
+/// Formats a USD amount as `$1.25M` / `$830k`, or `$1,250,000.00` when
+/// `full_precision` is set.
+pub fn format_usd(value: f64, full_precision: bool) -> String {
+    let sign = if value < 0.0 { "-" } else { "" };
+    format!("{}${}", sign, format_amount(value.abs(), full_precision))
+}
+
+pub fn format_amount(value: f64, full_precision: bool) -> String {
+    if full_precision {
+        with_thousands(value, 2)
+    } else {
+        compact(value)
+    }
+}
+
+pub fn compact(value: f64) -> String {
+    let abs = value.abs();
+    let (scaled, suffix) = if abs >= 1e9 {
+        (value / 1e9, "B")
+    } else if abs >= 1e6 {
+        (value / 1e6, "M")
+    } else if abs >= 1e3 {
+        (value / 1e3, "k")
+    } else {
+        return format!("{:.2}", value);
+    };
+
+    let decimals = if scaled.abs() >= 100.0 {
+        0
+    } else if scaled.abs() >= 10.0 {
+        1
+    } else {
+        2
+    };
+
+    let mut number = format!("{:.*}", decimals, scaled);
+    if number.contains('.') {
+        let trimmed = number.trim_end_matches('0').trim_end_matches('.').len();
+        number.truncate(trimmed);
+    }
+
+    format!("{}{}", number, suffix)
+}
+
+pub fn with_thousands(value: f64, decimals: usize) -> String {
+    let formatted = format!("{:.*}", decimals, value.abs());
+    let (int_part, frac_part) = match formatted.split_once('.') {
+        Some((int_part, frac_part)) => (int_part, Some(frac_part)),
+        None => (formatted.as_str(), None),
+    };
+
+    let mut result = String::new();
+    if value < 0.0 {
+        result.push('-');
+    }
+    result.push_str(&group_digits(int_part));
+    if let Some(frac_part) = frac_part {
+        result.push('.');
+        result.push_str(frac_part);
+    }
+    result
+}
+
+/// Adds thousands separators to a price string from the exchange, keeping
+/// whatever precision it was quoted with.
+pub fn format_price(px: &str) -> String {
+    let (int_part, frac_part) = match px.split_once('.') {
+        Some((int_part, frac_part)) => (int_part, Some(frac_part)),
+        None => (px, None),
+    };
+
+    if int_part.is_empty() || !int_part.trim_start_matches('-').chars().all(|c| c.is_ascii_digit()) {
+        return px.to_string();
+    }
+
+    let (sign, digits) = match int_part.strip_prefix('-') {
+        Some(digits) => ("-", digits),
+        None => ("", int_part),
+    };
+
+    match frac_part {
+        Some(frac_part) => format!("{}{}.{}", sign, group_digits(digits), frac_part),
+        None => format!("{}{}", sign, group_digits(digits)),
+    }
+}
+
+fn group_digits(digits: &str) -> String {
+    let mut grouped = String::with_capacity(digits.len() + digits.len() / 3);
+    for (i, c) in digits.chars().enumerate() {
+        if i > 0 && (digits.len() - i).is_multiple_of(3) {
+            grouped.push(',');
+        }
+        grouped.push(c);
+    }
+    grouped
+}
+
+/// Renders values as unicode block characters, e.g. `▁▂▄▇█▅`.
+pub fn sparkline(values: &[f64]) -> String {
+    const BLOCKS: [char; 8] = ['▁', '▂', '▃', '▄', '▅', '▆', '▇', '█'];
+
+    let min = values.iter().copied().fold(f64::INFINITY, f64::min);
+    let max = values.iter().copied().fold(f64::NEG_INFINITY, f64::max);
+    let range = max - min;
+
+    values
+        .iter()
+        .map(|value| {
+            if range <= 0.0 {
+                BLOCKS[BLOCKS.len() / 2]
+            } else {
+                let index = ((value - min) / range * (BLOCKS.len() - 1) as f64).round() as usize;
+                BLOCKS[index.min(BLOCKS.len() - 1)]
+            }
+        })
+        .collect()
+}
+
+/// `+$1.2M` / `-$830k`, for pnl and other changes.
+pub fn format_signed_usd(value: f64) -> String {
+    let sign = if value < 0.0 { "-" } else { "+" };
+    format!("{}${}", sign, compact(value.abs()))
+}
+
+pub fn format_percent_change(change: f64) -> String {
+    format!("{:+.2}%", change)
+}
+
+pub fn format_duration(duration: Duration) -> String {
+    let secs = duration.as_secs();
+    let days = secs / 86_400;
+    let hours = (secs % 86_400) / 3_600;
+    let minutes = (secs % 3_600) / 60;
+
+    if days > 0 {
+        format!("{}d {}h {}m", days, hours, minutes)
+    } else if hours > 0 {
+        format!("{}h {}m", hours, minutes)
+    } else {
+        format!("{}m {}s", minutes, secs % 60)
+    }
+}
+
+/// Telegram's cap on a single message, in UTF-16 code units.
+pub const TELEGRAM_MESSAGE_LIMIT: usize = 4096;
+
+/// Telegram's cap on a photo's caption, in UTF-16 code units.
+pub const TELEGRAM_CAPTION_LIMIT: usize = 1024;
+
+/// Splits `text` into chunks of at most `limit` UTF-16 units, breaking at a
+/// blank line, then a newline, then a space, and only mid-word as a last resort.
+pub fn split_message(text: &str, limit: usize) -> Vec<String> {
+    let mut chunks = Vec::new();
+    let mut rest = text.trim();
+
+    while rest.encode_utf16().count() > limit {
+        let mut units = 0;
+        let mut cut = rest.len();
+        for (index, c) in rest.char_indices() {
+            if units + c.len_utf16() > limit {
+                cut = index;
+                break;
+            }
+            units += c.len_utf16();
+        }
+        if cut == 0 {
+            // limit is narrower than a single character; emit it anyway
+            cut = rest.chars().next().map_or(rest.len(), char::len_utf8);
+        }
+
+        let window = &rest[..cut];
+        let split_at = window
+            .rfind("\n\n")
+            .or_else(|| window.rfind('\n'))
+            .or_else(|| window.rfind(' '))
+            .filter(|index| *index > 0)
+            .unwrap_or(cut);
+
+        let chunk = rest[..split_at].trim_end();
+        if !chunk.is_empty() {
+            chunks.push(chunk.to_string());
+        }
+        rest = rest[split_at..].trim_start();
+    }
+
+    if !rest.is_empty() {
+        chunks.push(rest.to_string());
+    }
+    chunks
+}
+
+/// `0x1234…abcd` form of a wallet address.
+pub fn short_address(address: &str) -> String {
+    match (address.get(..6), address.get(address.len().saturating_sub(4)..)) {
+        (Some(head), Some(tail)) if address.len() > 12 => format!("{}…{}", head, tail),
+        _ => address.to_string(),
+    }
+}
+
+/// A trade alert's pieces, before they're laid out as a message.
+#[derive(Debug, Clone)]
+pub struct AlertText {
+    pub coin: String,
+    /// "Trade" or "Liquidation".
+    pub kind: &'static str,
+    /// Which side got liquidated, on liquidations.
+    pub liquidation: Option<String>,
+    pub amount: String,
+    /// "BUY" or "SELL".
+    pub side: &'static str,
+    pub price: String,
+    /// Leverage, volume share, trade link: one line each, if shown.
+    pub details: Vec<String>,
+}
+
+impl AlertText {
+    pub fn title(&self) -> String {
+        format!("{} {} Alert", self.coin, self.kind)
+    }
+
+    /// The built-in layout.
+    pub fn plain(&self) -> String {
+        let mut text = format!("{}\n\n", self.title());
+        if let Some(liquidation) = &self.liquidation {
+            text.push_str(&format!("{}\n", liquidation));
+        }
+        text.push_str(&format!("Amount: {}\nType: {}\n{}", self.amount, self.side, self.price));
+        for line in &self.details {
+            text.push_str(&format!("\n{}", line));
+        }
+        text
+    }
+
+    /// Lays the alert out per an operator's template. A line left blank only
+    /// because its placeholders were empty is dropped.
+    pub fn render(&self, template: &str) -> String {
+        let fields = [
+            ("{title}", self.title()),
+            ("{coin}", self.coin.clone()),
+            ("{liquidation}", self.liquidation.clone().unwrap_or_default()),
+            ("{amount}", self.amount.clone()),
+            ("{side}", self.side.to_string()),
+            ("{price}", self.price.clone()),
+            ("{details}", self.details.join("\n")),
+        ];
+
+        let mut lines = Vec::new();
+        for line in template.lines() {
+            let mut rendered = line.to_string();
+            for (placeholder, value) in &fields {
+                rendered = rendered.replace(placeholder, value);
+            }
+            if rendered.trim().is_empty() && !line.trim().is_empty() {
+                continue;
+            }
+            lines.push(rendered);
+        }
+        lines.join("\n")
+    }
+}
+
+/// A trade alert's text from what's been looked up for it: `fiat` is the
+/// amount in the user's currency, `details` the lines under the price and
+/// `liquidation` the side liquidated, if the trade was a liquidation.
+pub fn trade_alert_text(
+    trade: &WsTrade,
+    notional_usd: f64,
+    full_precision: bool,
+    fiat: Option<String>,
+    details: Vec<String>,
+    liquidation: Option<String>,
+) -> AlertText {
+    let mut amount = format_usd(notional_usd, full_precision);
+    if let Some(fiat) = fiat {
+        amount.push_str(&format!(" ({})", fiat));
+    }
+    let price = if trade.fills > 1 {
+        format!("Avg Price: ${} ({} fills)", format_price(&trade.px), trade.fills)
+    } else {
+        format!("Price: ${}", format_price(&trade.px))
+    };
+    AlertText {
+        coin: trade.coin.to_string(),
+        kind: if liquidation.is_some() { "Liquidation" } else { "Trade" },
+        liquidation,
+        amount,
+        side: if trade.side == "B" { "BUY" } else { "SELL" },
+        price,
+        details,
+    }
+}
+
+/// How much emoji a chat's alerts carry.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum EmojiStyle {
+    #[default]
+    Off,
+    /// A green or red dot for the side.
+    Sides,
+    /// The side dot, plus a whale or a burst for liquidations.
+    Full,
+}
+
+impl EmojiStyle {
+    pub fn parse(style: &str) -> Option<Self> {
+        match style.trim().to_lowercase().as_str() {
+            "off" | "none" => Some(EmojiStyle::Off),
+            "sides" => Some(EmojiStyle::Sides),
+            "full" => Some(EmojiStyle::Full),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            EmojiStyle::Off => "off",
+            EmojiStyle::Sides => "sides",
+            EmojiStyle::Full => "full",
+        }
+    }
+
+    fn marker(&self, text: &AlertText) -> Option<String> {
+        let side = if text.side == "BUY" { "🟢" } else { "🔴" };
+        match self {
+            EmojiStyle::Off => None,
+            EmojiStyle::Sides => Some(side.to_string()),
+            EmojiStyle::Full if text.liquidation.is_some() => Some(format!("💥{}", side)),
+            EmojiStyle::Full => Some(format!("🐋{}", side)),
+        }
+    }
+}
+
+/// A group or channel's own dressing for the alerts posted there: a line
+/// above, a line below and an emoji style. Applies on top of whatever layout
+/// the alert got.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Branding {
+    pub prefix: Option<String>,
+    pub suffix: Option<String>,
+    pub emoji: EmojiStyle,
+}
+
+impl Branding {
+    pub fn is_plain(&self) -> bool {
+        *self == Branding::default()
+    }
+
+    /// `message` is `text` already laid out.
+    pub fn apply(&self, text: &AlertText, message: String) -> String {
+        let mut branded = String::new();
+        if let Some(prefix) = &self.prefix {
+            branded.push_str(&format!("{}\n\n", prefix));
+        }
+        if let Some(marker) = self.emoji.marker(text) {
+            branded.push_str(&format!("{} ", marker));
+        }
+        branded.push_str(&message);
+        if let Some(suffix) = &self.suffix {
+            branded.push_str(&format!("\n\n{}", suffix));
+        }
+        branded
+    }
+}
@@ -0,0 +1,141 @@
+use anyhow::Result;
+use std::collections::BTreeMap;
+use tokio::time::{interval, Duration};
+use tracing::{error, info, warn};
+use crate::{
+    database::{Database, FundingSummarySchedule},
+    formatting,
+    hyperliquid::HyperliquidClient,
+    outbound::Priority,
+    telegram::TelegramBot,
+};
+
+// how often we look for summaries that have come due
+const CHECK_INTERVAL: Duration = Duration::from_secs(15 * 60);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FundingPeriod {
+    Daily,
+    Weekly,
+}
+
+impl FundingPeriod {
+    pub fn parse(period: &str) -> Option<Self> {
+        match period.trim().to_lowercase().as_str() {
+            "daily" => Some(FundingPeriod::Daily),
+            "weekly" => Some(FundingPeriod::Weekly),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            FundingPeriod::Daily => "daily",
+            FundingPeriod::Weekly => "weekly",
+        }
+    }
+
+    fn window(&self) -> chrono::Duration {
+        match self {
+            FundingPeriod::Daily => chrono::Duration::days(1),
+            FundingPeriod::Weekly => chrono::Duration::days(7),
+        }
+    }
+}
+
+// posts the daily/weekly funding summaries users opt into with /fundingsummary
+pub struct FundingReporter {
+    database: Database,
+    hyperliquid_client: HyperliquidClient,
+    telegram_bot: TelegramBot,
+}
+
+impl FundingReporter {
+    pub fn spawn(database: Database, hyperliquid_client: HyperliquidClient, telegram_bot: TelegramBot) {
+        let reporter = FundingReporter {
+            database,
+            hyperliquid_client,
+            telegram_bot,
+        };
+
+        tokio::spawn(async move {
+            let mut ticker = interval(CHECK_INTERVAL);
+            loop {
+                ticker.tick().await;
+                if let Err(e) = reporter.send_due_summaries().await {
+                    error!("couldn't send funding summaries: {}", e);
+                }
+            }
+        });
+        info!("funding reporter started");
+    }
+
+    async fn send_due_summaries(&self) -> Result<()> {
+        for schedule in self.database.get_due_funding_summaries().await? {
+            if let Err(e) = self.send_summary(&schedule).await {
+                warn!("couldn't send funding summary to user {}: {}", schedule.telegram_user_id, e);
+            }
+        }
+        Ok(())
+    }
+
+    async fn send_summary(&self, schedule: &FundingSummarySchedule) -> Result<()> {
+        let period = FundingPeriod::parse(&schedule.period).unwrap_or(FundingPeriod::Daily);
+        let addresses = self.database.get_linked_accounts(schedule.telegram_user_id).await?;
+
+        // nothing to report, but still move the clock forward
+        if !addresses.is_empty() {
+            let summary = self.build_summary(&addresses, period).await?;
+            self.telegram_bot.send_text(schedule.telegram_chat_id, &summary, Priority::Digest).await?;
+            info!("sent {} funding summary to user {}", period.as_str(), schedule.telegram_user_id);
+        }
+
+        self.database.mark_funding_summary_sent(schedule.telegram_user_id).await
+    }
+
+    async fn build_summary(&self, addresses: &[String], period: FundingPeriod) -> Result<String> {
+        let end = chrono::Utc::now();
+        let start = end - period.window();
+
+        let title = match period {
+            FundingPeriod::Daily => "Daily Funding Summary",
+            FundingPeriod::Weekly => "Weekly Funding Summary",
+        };
+        let mut message = title.to_string();
+        let mut net_total = 0.0;
+
+        for address in addresses {
+            let payments = self
+                .hyperliquid_client
+                .user_funding(address, start.timestamp_millis(), end.timestamp_millis())
+                .await?;
+
+            let mut per_coin: BTreeMap<String, f64> = BTreeMap::new();
+            for payment in payments {
+                if let Ok(usdc) = payment.delta.usdc.parse::<f64>() {
+                    *per_coin.entry(payment.delta.coin).or_default() += usdc;
+                }
+            }
+
+            message.push_str(&format!("\n\n{}", formatting::short_address(address)));
+            if per_coin.is_empty() {
+                message.push_str("\nNo funding payments");
+                continue;
+            }
+            for (coin, total) in &per_coin {
+                let direction = if *total >= 0.0 { "received" } else { "paid" };
+                message.push_str(&format!(
+                    "\n{}: {} ${}",
+                    coin,
+                    direction,
+                    formatting::with_thousands(total.abs(), 2)
+                ));
+                net_total += total;
+            }
+        }
+
+        let sign = if net_total < 0.0 { "-" } else { "+" };
+        message.push_str(&format!("\n\nNet: {}${}", sign, formatting::with_thousands(net_total.abs(), 2)));
+        Ok(message)
+    }
+}
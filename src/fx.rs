@@ -0,0 +1,126 @@
+use anyhow::Result;
+use reqwest::Client;
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::sync::Arc;
+use tokio::sync::RwLock;
+use tokio::time::{sleep, Duration, Instant};
+use tracing::{info, error, warn};
+use crate::{config::FxConfig, formatting};
+
+#[derive(Debug, Deserialize)]
+struct FxResponse {
+    rates: HashMap<String, f64>,
+}
+
+struct FxCache {
+    rates: HashMap<String, f64>,
+    fetched_at: Option<Instant>,
+}
+
+// usd -> fiat rates, refreshed hourly in the background
+#[derive(Clone)]
+pub struct FxRates {
+    client: Client,
+    config: FxConfig,
+    cache: Arc<RwLock<FxCache>>,
+}
+
+impl FxRates {
+    pub fn new(config: FxConfig) -> Self {
+        FxRates {
+            client: Client::new(),
+            config,
+            cache: Arc::new(RwLock::new(FxCache {
+                rates: HashMap::new(),
+                fetched_at: None,
+            })),
+        }
+    }
+
+    pub fn spawn_refresh_task(&self) {
+        let fx = self.clone();
+        let interval = Duration::from_secs(self.config.refresh_interval_secs);
+
+        tokio::spawn(async move {
+            loop {
+                if let Err(e) = fx.refresh().await {
+                    error!("couldn't refresh fx rates: {}", e);
+                }
+                sleep(interval).await;
+            }
+        });
+    }
+
+    async fn refresh(&self) -> Result<()> {
+        let response = self.client.get(&self.config.api_url).send().await?;
+
+        if !response.status().is_success() {
+            return Err(anyhow::anyhow!("fx api error, status: {}", response.status()));
+        }
+
+        let fx_response: FxResponse = response.json().await?;
+        let rates: HashMap<String, f64> = fx_response
+            .rates
+            .into_iter()
+            .map(|(code, rate)| (code.to_uppercase(), rate))
+            .collect();
+
+        info!("fetched {} fx rates", rates.len());
+
+        let mut cache = self.cache.write().await;
+        cache.rates = rates;
+        cache.fetched_at = Some(Instant::now());
+        Ok(())
+    }
+
+    async fn fresh_rate(&self, currency: &str) -> Option<f64> {
+        let cache = self.cache.read().await;
+        let fetched_at = cache.fetched_at?;
+
+        if fetched_at.elapsed() > Duration::from_secs(self.config.max_staleness_secs) {
+            warn!("fx rates are stale, falling back to usd only");
+            return None;
+        }
+
+        cache.rates.get(&currency.to_uppercase()).copied()
+    }
+
+    /// Converts a USD amount into `currency`, or `None` if there is no fresh rate for it.
+    pub async fn convert(&self, usd: f64, currency: &str) -> Option<f64> {
+        self.fresh_rate(currency).await.map(|rate| usd * rate)
+    }
+
+    /// Whether `currency` can be used as a display preference. Accepts any
+    /// three-letter code while rates haven't loaded yet.
+    pub async fn is_supported(&self, currency: &str) -> bool {
+        let currency = currency.to_uppercase();
+        if currency.len() != 3 || !currency.chars().all(|c| c.is_ascii_alphabetic()) {
+            return false;
+        }
+
+        let cache = self.cache.read().await;
+        cache.fetched_at.is_none() || cache.rates.contains_key(&currency)
+    }
+}
+
+pub fn currency_symbol(currency: &str) -> Option<&'static str> {
+    match currency {
+        "USD" => Some("$"),
+        "EUR" => Some("€"),
+        "GBP" => Some("£"),
+        "JPY" | "CNY" => Some("¥"),
+        "KRW" => Some("₩"),
+        "INR" => Some("₹"),
+        "TRY" => Some("₺"),
+        _ => None,
+    }
+}
+
+pub fn format_fiat(amount: f64, currency: &str, full_precision: bool) -> String {
+    let number = formatting::format_amount(amount, full_precision);
+    match currency_symbol(currency) {
+        Some(symbol) => format!("{}{}", symbol, number),
+        None => format!("{} {}", number, currency),
+    }
+}
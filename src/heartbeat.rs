@@ -0,0 +1,95 @@
+use anyhow::Result;
+use std::collections::HashMap;
+use tokio::time::{interval, Duration, MissedTickBehavior};
+use tracing::{error, info, warn};
+use crate::{
+    database::Database,
+    formatting,
+    hyperliquid::HyperliquidClient,
+    market,
+    outbound::Priority,
+    telegram::TelegramBot,
+};
+
+const CHECK_INTERVAL: Duration = Duration::from_secs(60 * 60);
+
+// a coin this long without a qualifying trade gets a note, and at most one
+// note per this long after that
+const QUIET_HOURS: i64 = 24;
+
+// for users on /heartbeat: a short note when a subscribed coin has gone a day
+// without a trade over their threshold, so silence reads as a quiet market
+// rather than a broken bot
+pub struct HeartbeatNotifier {
+    database: Database,
+    hyperliquid_client: HyperliquidClient,
+    telegram_bot: TelegramBot,
+    global_min: f64,
+}
+
+impl HeartbeatNotifier {
+    pub fn spawn(database: Database, hyperliquid_client: HyperliquidClient, telegram_bot: TelegramBot, global_min: f64) {
+        let notifier = HeartbeatNotifier {
+            database,
+            hyperliquid_client,
+            telegram_bot,
+            global_min,
+        };
+
+        tokio::spawn(async move {
+            let mut ticker = interval(CHECK_INTERVAL);
+            ticker.set_missed_tick_behavior(MissedTickBehavior::Delay);
+            loop {
+                ticker.tick().await;
+                if let Err(e) = notifier.send_due_notes().await {
+                    error!("couldn't send heartbeat notes: {}", e);
+                }
+            }
+        });
+        info!("heartbeat notifier started");
+    }
+
+    async fn send_due_notes(&self) -> Result<()> {
+        let quiet = self.database.get_quiet_subscriptions(self.global_min, QUIET_HOURS).await?;
+        if quiet.is_empty() {
+            return Ok(());
+        }
+
+        let mut coins: Vec<String> = quiet.iter().map(|subscription| subscription.coin.clone()).collect();
+        coins.sort();
+        coins.dedup();
+        let snapshots: HashMap<String, market::MarketSnapshot> = market::snapshots(&self.hyperliquid_client, &coins, 0)
+            .await?
+            .into_iter()
+            .map(|snapshot| (snapshot.coin.clone(), snapshot))
+            .collect();
+
+        for subscription in quiet {
+            // delisted or renamed; nothing useful to say about it
+            let Some(snapshot) = snapshots.get(&subscription.coin) else {
+                continue;
+            };
+
+            let change = snapshot
+                .change_24h
+                .map(|change| format!(" ({})", formatting::format_percent_change(change)))
+                .unwrap_or_default();
+            let note = format!(
+                "{} Is Quiet\n\nNo trades over your threshold in the last {}h. All's running, the market's just calm.\n\nPrice: ${}{}\n24h volume: {}\n\nUse /heartbeat off to stop these notes.",
+                subscription.coin,
+                QUIET_HOURS,
+                formatting::format_price(&snapshot.mark_px),
+                change,
+                formatting::format_usd(snapshot.volume_24h_usd, subscription.full_precision)
+            );
+            if let Err(e) = self.telegram_bot.send_text(subscription.chat_id, &note, Priority::Digest).await {
+                warn!("couldn't send {} heartbeat to user {}: {}", subscription.coin, subscription.telegram_user_id, e);
+                continue;
+            }
+            self.database
+                .mark_heartbeat_sent(subscription.telegram_user_id, &subscription.coin)
+                .await?;
+        }
+        Ok(())
+    }
+}
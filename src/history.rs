@@ -0,0 +1,113 @@
+use std::mem;
+use tokio::sync::mpsc;
+use tokio::time::{interval, Duration, MissedTickBehavior};
+use tracing::{error, info};
+use crate::{
+    config::HistoryConfig,
+    database::{Database, NotificationRecord, TradeRecord},
+    hyperliquid::WsTrade,
+};
+
+// postgres caps a statement at 65535 bind params, the widest row here has 12
+const MAX_BATCH_SIZE: usize = 5_000;
+
+enum HistoryEntry {
+    Trade(TradeRecord),
+    Notification(NotificationRecord),
+}
+
+// write-behind buffer for trade history and the delivery log. inserting per
+// event meant one round trip per alert, which is exactly when markets are busiest.
+#[derive(Clone)]
+pub struct HistoryWriter {
+    tx: mpsc::UnboundedSender<HistoryEntry>,
+}
+
+impl HistoryWriter {
+    pub fn spawn(database: Database, config: HistoryConfig) -> Self {
+        let (tx, rx) = mpsc::unbounded_channel();
+        tokio::spawn(run_writer(database, config, rx));
+        HistoryWriter { tx }
+    }
+
+    pub fn record_trade(&self, trade: &WsTrade, notional_usd: f64) {
+        let record = TradeRecord {
+            coin: trade.coin.clone(),
+            side: trade.side.clone(),
+            px: trade.px.clone(),
+            sz: trade.sz.clone(),
+            notional_usd,
+            tid: trade.tid.map(|tid| tid as i64),
+            hash: trade.hash.clone(),
+            buyer: trade.users.as_ref().map(|[buyer, _]| buyer.clone()),
+            seller: trade.users.as_ref().map(|[_, seller]| seller.clone()),
+            trade_time: trade.time.and_then(chrono::DateTime::from_timestamp_millis),
+        };
+        let _ = self.tx.send(HistoryEntry::Trade(record));
+    }
+
+    pub fn record_notification(&self, record: NotificationRecord) {
+        let _ = self.tx.send(HistoryEntry::Notification(record));
+    }
+}
+
+async fn run_writer(database: Database, config: HistoryConfig, mut rx: mpsc::UnboundedReceiver<HistoryEntry>) {
+    let batch_size = config.batch_size.clamp(1, MAX_BATCH_SIZE);
+    let mut ticker = interval(Duration::from_millis(config.flush_interval_ms.max(1)));
+    ticker.set_missed_tick_behavior(MissedTickBehavior::Delay);
+
+    let mut trades: Vec<TradeRecord> = Vec::new();
+    let mut notifications: Vec<NotificationRecord> = Vec::new();
+
+    loop {
+        tokio::select! {
+            entry = rx.recv() => {
+                match entry {
+                    Some(HistoryEntry::Trade(record)) => {
+                        trades.push(record);
+                        if trades.len() >= batch_size {
+                            flush_trades(&database, &mut trades).await;
+                        }
+                    }
+                    Some(HistoryEntry::Notification(record)) => {
+                        notifications.push(record);
+                        if notifications.len() >= batch_size {
+                            flush_notifications(&database, &mut notifications).await;
+                        }
+                    }
+                    None => break,
+                }
+            }
+
+            _ = ticker.tick() => {
+                flush_trades(&database, &mut trades).await;
+                flush_notifications(&database, &mut notifications).await;
+            }
+        }
+    }
+
+    flush_trades(&database, &mut trades).await;
+    flush_notifications(&database, &mut notifications).await;
+    info!("history writer stopped");
+}
+
+// a failed batch is dropped rather than retried so a db outage can't grow the buffer forever
+async fn flush_trades(database: &Database, trades: &mut Vec<TradeRecord>) {
+    if trades.is_empty() {
+        return;
+    }
+    let batch = mem::take(trades);
+    if let Err(e) = database.insert_trade_history(&batch).await {
+        error!("couldn't write {} trade history rows: {}", batch.len(), e);
+    }
+}
+
+async fn flush_notifications(database: &Database, notifications: &mut Vec<NotificationRecord>) {
+    if notifications.is_empty() {
+        return;
+    }
+    let batch = mem::take(notifications);
+    if let Err(e) = database.insert_notification_logs(&batch).await {
+        error!("couldn't write {} notification log rows: {}", batch.len(), e);
+    }
+}
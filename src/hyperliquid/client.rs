@@ -1,46 +1,165 @@
 use anyhow::Result;
-use reqwest::Client;
-use std::collections::HashSet;
+use reqwest::{Client, Response};
+use serde::Serialize;
+use std::collections::{HashMap, HashSet};
+use std::sync::{Arc, Mutex};
 use tokio::time::{Duration, Instant};
-use tracing::{info, error, warn};
+use tracing::{debug, info, error, warn};
 use crate::config::HyperliquidConfig;
-use super::{InfoRequest, MetaAndAssetCtxsResponse};
+use super::symbol::{dex_of, wire_name};
+use super::{
+    AssetCtx, AssetInfo, Candle, CandleSnapshotParams, CandleSnapshotRequest, InfoRequest, MetaAndAssetCtxsResponse,
+    CertPins, ClearinghouseState, FundingHistoryRequest, FundingRate, LedgerUpdate, SpotMetaResponse, UserFill, UserRangeRequest,
+    UserFunding, UserFundingRequest, UserStateRequest,
+};
+
+// the most fills userFillsByTime returns at once
+const USER_FILLS_PAGE: usize = 2000;
+// the coin list is refetched this often
+const COIN_CACHE_TTL: Duration = Duration::from_secs(6 * 60 * 60);
+// and no sooner than this after a failed attempt, so a blip doesn't turn every
+// /subscribe into another request
+const COIN_REFRESH_RETRY: Duration = Duration::from_secs(30);
+// quoted in these, px * sz is already USD
+const USD_QUOTES: &[&str] = &["USDC", "USD", "USDH", "USDT0", "USDE"];
+
+#[derive(Default)]
+struct CoinCache {
+    coins: Option<HashSet<String>>,
+    // when the full list last loaded; None while only the mids fallback has
+    fetched_at: Option<Instant>,
+    attempted_at: Option<Instant>,
+    // builder dexs whose coins couldn't be fetched last time; the list can't
+    // rule their coins out, and is refetched until it has them
+    missing_dexs: HashSet<String>,
+    // builder dex -> the token its markets are quoted in, for those not in USD
+    quote_assets: HashMap<String, String>,
+}
+
+// what one fetch of the coin list found
+struct CoinList {
+    coins: HashSet<String>,
+    missing_dexs: HashSet<String>,
+    quote_assets: HashMap<String, String>,
+}
+
+impl CoinCache {
+    fn fresh(&self) -> bool {
+        self.missing_dexs.is_empty() && self.fetched_at.is_some_and(|at| at.elapsed() < COIN_CACHE_TTL)
+    }
+}
 
 #[derive(Clone)]
 pub struct HyperliquidClient {
     client: Client,
     config: HyperliquidConfig,
-    // cache coins, refresh every 6 hours
-    valid_coins: Option<HashSet<String>>,
-    last_fetched: Option<Instant>,
+    // shared by every clone, so handlers don't each start cold
+    coin_cache: Arc<Mutex<CoinCache>>,
 }
 
 impl HyperliquidClient {
-    pub fn new(config: HyperliquidConfig) -> Self {
-        let client = Client::new();
+    pub fn new(config: HyperliquidConfig, cert_pins: CertPins) -> Result<Self> {
+        // any pins are checked in the handshake, before a request goes out
+        let client = cert_pins.configure(Client::builder()).build()?;
         
-        HyperliquidClient {
+        Ok(HyperliquidClient {
             client,
             config,
-            valid_coins: None,
-            last_fetched: None,
-        }
+            coin_cache: Arc::new(Mutex::new(CoinCache::default())),
+        })
     }
 
-    async fn fetch_valid_coins(&mut self) -> Result<()> {
+    async fn post_info<T: Serialize>(&self, body: &T) -> Result<Response> {
+        let response = self
+            .client
+            .post(format!("{}/info", self.config.rest_api_url))
+            .header("Content-Type", "application/json")
+            .json(body)
+            .send()
+            .await?;
+        Ok(response)
+    }
+
+    // the coins, the builder dexs whose coins are missing from them, and what
+    // each dex's markets are quoted in
+    async fn fetch_valid_coins(&self) -> Result<CoinList> {
         info!("fetching coins from hl...");
 
+        let (mut coins, _) = self.fetch_dex_coins(None).await?;
+        // a builder dex that's down or gone shouldn't cost the main list
+        let mut missing_dexs = HashSet::new();
+        let mut collateral = HashMap::new();
+        for dex in &self.config.perp_dexs {
+            match self.fetch_dex_coins(Some(dex)).await {
+                Ok((dex_coins, token)) => {
+                    coins.extend(dex_coins);
+                    if let Some(token) = token {
+                        collateral.insert(dex.to_lowercase(), token);
+                    }
+                }
+                Err(e) => {
+                    warn!("couldn't fetch coins for perp dex {}: {}", dex, e);
+                    missing_dexs.insert(dex.to_lowercase());
+                }
+            }
+        }
+
+        // a dex whose quote token can't be named would have its notionals
+        // taken as USD, so it counts as missing until it can
+        let mut quote_assets = HashMap::new();
+        if !collateral.is_empty() {
+            match self.spot_token_names().await {
+                Ok(names) => {
+                    for (dex, token) in collateral {
+                        match names.get(&token) {
+                            Some(name) if !USD_QUOTES.iter().any(|usd| usd.eq_ignore_ascii_case(name)) => {
+                                quote_assets.insert(dex, name.to_uppercase());
+                            }
+                            Some(_) => {}
+                            None => {
+                                warn!("perp dex {} is quoted in spot token {}, which spotMeta doesn't list", dex, token);
+                                missing_dexs.insert(dex);
+                            }
+                        }
+                    }
+                }
+                Err(e) => {
+                    warn!("couldn't fetch spot tokens for perp dex quotes: {}", e);
+                    missing_dexs.extend(collateral.into_keys());
+                }
+            }
+        }
+
+        info!("fetched {} valid coins from hl", coins.len());
+        Ok(CoinList { coins, missing_dexs, quote_assets })
+    }
+
+    // spot token index -> name
+    async fn spot_token_names(&self) -> Result<HashMap<usize, String>> {
+        let request_body = InfoRequest {
+            request_type: "spotMeta".to_string(),
+            dex: None,
+        };
+
+        let response = self.post_info(&request_body).await?;
+
+        if !response.status().is_success() {
+            error!("hl spot meta request failed, status: {}", response.status());
+            return Err(anyhow::anyhow!("hl api error"));
+        }
+
+        let meta: SpotMetaResponse = response.json().await?;
+        Ok(meta.tokens.into_iter().map(|token| (token.index, token.name)).collect())
+    }
+
+    // a dex's coins, and the spot token its markets are quoted in if it says
+    async fn fetch_dex_coins(&self, dex: Option<&str>) -> Result<(HashSet<String>, Option<usize>)> {
         let request_body = InfoRequest {
             request_type: "metaAndAssetCtxs".to_string(),
+            dex: dex.map(str::to_string),
         };
 
-        let response = self
-            .client
-            .post(&format!("{}/info", self.config.rest_api_url))
-            .header("Content-Type", "application/json")
-            .json(&request_body)
-            .send()
-            .await?;
+        let response = self.post_info(&request_body).await?;
 
         if !response.status().is_success() {
             error!("hl api request failed, status: {}", response.status());
@@ -65,43 +184,384 @@ impl HyperliquidClient {
             .universe
             .iter()
             .filter(|asset| !asset.is_delisted.unwrap_or(false)) // no delists
-            .map(|asset| asset.name.to_uppercase())
+            .map(|asset| qualified_name(dex, &asset.name))
             .collect();
 
-        info!("fetched {} valid coins from hl", coins.len());
-        self.valid_coins = Some(coins);
-        self.last_fetched = Some(Instant::now());
+        Ok((coins, meta_response.collateral_token))
+    }
 
-        Ok(())
+    /// The asset `coin`'s market is quoted in, or `None` for a USD market.
+    /// Loads the coin list first if it hasn't yet, since that's where the
+    /// quotes come from.
+    pub async fn quote_asset(&self, coin: &str) -> Option<String> {
+        let dex = dex_of(coin)?;
+        if let Err(e) = self.coin_exists(coin).await {
+            warn!("couldn't load {}'s quote asset: {}", coin, e);
+        }
+        self.coin_cache.lock().ok()?.quote_assets.get(&dex).cloned()
     }
 
-    pub async fn coin_exists(&mut self, coin: &str) -> Result<bool> {
-        // stale cache after 6 hours
-        let needs_refresh = match self.last_fetched {
-            None => true,
-            Some(last_time) => last_time.elapsed() >= Duration::from_secs(6 * 60 * 60),
+    pub async fn coin_exists(&self, coin: &str) -> Result<bool> {
+        let (fresh, may_retry) = match self.coin_cache.lock() {
+            Ok(cache) => (
+                cache.fresh(),
+                cache.attempted_at.is_none_or(|at| at.elapsed() >= COIN_REFRESH_RETRY),
+            ),
+            Err(_) => (false, true),
         };
 
-        if needs_refresh {
-            if let Err(e) = self.fetch_valid_coins().await {
-                error!("couldn't fetch valid coins: {}", e);
-                return Err(e);
-            }
+        if !fresh && may_retry {
+            self.refresh_coins().await;
         }
 
         let coin_upper = coin.to_uppercase();
-        let exists = self
-            .valid_coins
-            .as_ref()
-            .expect("no valid coins") 
-            .contains(&coin_upper);
+        let (exists, dex_missing) = self
+            .coin_cache
+            .lock()
+            .ok()
+            .and_then(|cache| {
+                let exists = cache.coins.as_ref()?.contains(&coin_upper);
+                let dex_missing = dex_of(&coin_upper).is_some_and(|dex| cache.missing_dexs.contains(&dex));
+                Some((exists, dex_missing))
+            })
+            .ok_or_else(|| anyhow::anyhow!("hl coin list unavailable"))?;
 
         if exists {
-            info!("{} is valid", coin_upper);
+            debug!("{} is valid", coin_upper);
+        } else if dex_missing {
+            return Err(anyhow::anyhow!("hl coin list is missing {}'s dex", coin_upper));
         } else {
             warn!("{} is not available on hl", coin_upper);
         }
 
         Ok(exists)
     }
-}
\ No newline at end of file
+
+    /// Loads the coin list unless a fresh one is cached; how many coins it has.
+    pub async fn warm_coins(&self) -> Result<usize> {
+        let fresh = self.coin_cache.lock().is_ok_and(|cache| cache.fresh());
+        if !fresh {
+            self.refresh_coins().await;
+        }
+
+        let cache = self.coin_cache.lock().map_err(|_| anyhow::anyhow!("coin cache poisoned"))?;
+        match (&cache.coins, cache.fetched_at) {
+            (Some(coins), Some(_)) => Ok(coins.len()),
+            (Some(_), None) => Err(anyhow::anyhow!("only the mids fallback loaded")),
+            (None, _) => Err(anyhow::anyhow!("hl coin list unavailable")),
+        }
+    }
+
+    // a stale list is kept if the fetch fails; with nothing cached yet the
+    // lighter mids request stands in, optimistic about delisted coins, until
+    // the full list loads
+    async fn refresh_coins(&self) {
+        if let Ok(mut cache) = self.coin_cache.lock() {
+            cache.attempted_at = Some(Instant::now());
+        }
+
+        let fetched = self.fetch_valid_coins().await;
+        let cold = self.coin_cache.lock().map_or(true, |cache| cache.coins.is_none());
+        let (coins, full, missing_dexs, quote_assets) = match fetched {
+            Ok(list) => (list.coins, true, list.missing_dexs, list.quote_assets),
+            Err(e) if cold => {
+                error!("couldn't fetch valid coins, falling back to mids: {}", e);
+                match self.all_mids().await {
+                    Ok(mids) => (
+                        mids.into_keys().map(|coin| coin.to_uppercase()).collect(),
+                        false,
+                        HashSet::new(),
+                        HashMap::new(),
+                    ),
+                    Err(e) => {
+                        error!("couldn't fetch mids for coin validation: {}", e);
+                        return;
+                    }
+                }
+            }
+            Err(e) => {
+                warn!("couldn't refresh valid coins, keeping the old list: {}", e);
+                return;
+            }
+        };
+
+        if let Ok(mut cache) = self.coin_cache.lock() {
+            let mut coins = coins;
+            // a dex that failed this time keeps the coins it had
+            if let Some(old) = &cache.coins {
+                coins.extend(
+                    old.iter()
+                        .filter(|coin| dex_of(coin).is_some_and(|dex| missing_dexs.contains(&dex)))
+                        .cloned(),
+                );
+            }
+            // a mids list never replaces a full one fetched meanwhile
+            if full || cache.fetched_at.is_none() {
+                cache.coins = Some(coins);
+            }
+            if full {
+                // and its quote asset
+                let mut quote_assets = quote_assets;
+                for (dex, asset) in &cache.quote_assets {
+                    if missing_dexs.contains(dex) {
+                        quote_assets.entry(dex.clone()).or_insert_with(|| asset.clone());
+                    }
+                }
+                cache.fetched_at = Some(Instant::now());
+                cache.missing_dexs = missing_dexs;
+                cache.quote_assets = quote_assets;
+            }
+        }
+    }
+
+    /// Metadata and market context for every listed coin, names uppercased,
+    /// builder dex markets included as `DEX:COIN`.
+    pub async fn asset_contexts(&self) -> Result<Vec<(AssetInfo, AssetCtx)>> {
+        let mut contexts = self.dex_asset_contexts(None).await?;
+        for dex in &self.config.perp_dexs {
+            match self.dex_asset_contexts(Some(dex)).await {
+                Ok(dex_contexts) => contexts.extend(dex_contexts),
+                Err(e) => warn!("couldn't fetch asset contexts for perp dex {}: {}", dex, e),
+            }
+        }
+        Ok(contexts)
+    }
+
+    async fn dex_asset_contexts(&self, dex: Option<&str>) -> Result<Vec<(AssetInfo, AssetCtx)>> {
+        let request_body = InfoRequest {
+            request_type: "metaAndAssetCtxs".to_string(),
+            dex: dex.map(str::to_string),
+        };
+
+        let response = self.post_info(&request_body).await?;
+
+        if !response.status().is_success() {
+            error!("hl asset ctx request failed, status: {}", response.status());
+            return Err(anyhow::anyhow!("hl api error"));
+        }
+
+        let (meta, ctxs): (MetaAndAssetCtxsResponse, Vec<AssetCtx>) = response.json().await?;
+        let contexts = meta
+            .universe
+            .into_iter()
+            .zip(ctxs)
+            .filter(|(asset, _)| !asset.is_delisted.unwrap_or(false))
+            .map(|(mut asset, ctx)| {
+                asset.name = qualified_name(dex, &asset.name);
+                (asset, ctx)
+            })
+            .collect();
+        Ok(contexts)
+    }
+
+    pub async fn candle_snapshot(
+        &self,
+        coin: &str,
+        interval: &str,
+        start_time: i64,
+        end_time: i64,
+    ) -> Result<Vec<Candle>> {
+        let request_body = CandleSnapshotRequest {
+            request_type: "candleSnapshot".to_string(),
+            req: CandleSnapshotParams {
+                coin: wire_name(coin),
+                interval: interval.to_string(),
+                start_time,
+                end_time,
+            },
+        };
+
+        let response = self.post_info(&request_body).await?;
+
+        if !response.status().is_success() {
+            error!("hl candle request failed, status: {}", response.status());
+            return Err(anyhow::anyhow!("hl api error"));
+        }
+
+        let candles: Vec<Candle> = response.json().await?;
+        Ok(candles)
+    }
+
+    /// Coins whose open interest is at hl's cap, where only reducing orders fill.
+    pub async fn perps_at_open_interest_cap(&self) -> Result<Vec<String>> {
+        let request_body = InfoRequest {
+            request_type: "perpsAtOpenInterestCap".to_string(),
+            dex: None,
+        };
+
+        let response = self.post_info(&request_body).await?;
+
+        if !response.status().is_success() {
+            error!("hl oi cap request failed, status: {}", response.status());
+            return Err(anyhow::anyhow!("hl api error"));
+        }
+
+        let coins: Vec<String> = response.json().await?;
+        Ok(coins.into_iter().map(|coin| coin.to_uppercase()).collect())
+    }
+
+    /// Mid price for every listed coin, keyed by coin name; builder dex
+    /// markets are keyed `DEX:COIN`.
+    pub async fn all_mids(&self) -> Result<HashMap<String, String>> {
+        let mut mids = self.dex_mids(None).await?;
+        for dex in &self.config.perp_dexs {
+            match self.dex_mids(Some(dex)).await {
+                Ok(dex_mids) => mids.extend(dex_mids),
+                Err(e) => warn!("couldn't fetch mids for perp dex {}: {}", dex, e),
+            }
+        }
+        Ok(mids)
+    }
+
+    async fn dex_mids(&self, dex: Option<&str>) -> Result<HashMap<String, String>> {
+        let request_body = InfoRequest {
+            request_type: "allMids".to_string(),
+            dex: dex.map(str::to_string),
+        };
+
+        let response = self.post_info(&request_body).await?;
+
+        if !response.status().is_success() {
+            error!("hl mids request failed, status: {}", response.status());
+            return Err(anyhow::anyhow!("hl api error"));
+        }
+
+        let mids: HashMap<String, String> = response.json().await?;
+        if dex.is_none() {
+            return Ok(mids);
+        }
+        Ok(mids.into_iter().map(|(coin, mid)| (qualified_name(dex, &coin), mid)).collect())
+    }
+
+    /// Funding payments for `address` between the two timestamps (ms).
+    pub async fn user_funding(&self, address: &str, start_time: i64, end_time: i64) -> Result<Vec<UserFunding>> {
+        let request_body = UserFundingRequest {
+            request_type: "userFunding".to_string(),
+            user: address.to_string(),
+            start_time,
+            end_time,
+        };
+
+        let response = self.post_info(&request_body).await?;
+
+        if !response.status().is_success() {
+            error!("hl funding request failed, status: {}", response.status());
+            return Err(anyhow::anyhow!("hl api error"));
+        }
+
+        let funding: Vec<UserFunding> = response.json().await?;
+        Ok(funding)
+    }
+
+    /// Hourly funding rates for `coin` since `start_time` (ms), oldest first.
+    pub async fn funding_history(&self, coin: &str, start_time: i64) -> Result<Vec<FundingRate>> {
+        let request_body = FundingHistoryRequest {
+            request_type: "fundingHistory".to_string(),
+            coin: wire_name(coin),
+            start_time,
+        };
+
+        let response = self.post_info(&request_body).await?;
+
+        if !response.status().is_success() {
+            error!("hl funding history request failed, status: {}", response.status());
+            return Err(anyhow::anyhow!("hl api error"));
+        }
+
+        let rates: Vec<FundingRate> = response.json().await?;
+        Ok(rates)
+    }
+
+    /// Open positions and account value for `address`.
+    pub async fn clearinghouse_state(&self, address: &str) -> Result<ClearinghouseState> {
+        let request_body = UserStateRequest {
+            request_type: "clearinghouseState".to_string(),
+            user: address.to_string(),
+        };
+
+        let response = self.post_info(&request_body).await?;
+
+        if !response.status().is_success() {
+            error!("hl clearinghouse state request failed, status: {}", response.status());
+            return Err(anyhow::anyhow!("hl api error"));
+        }
+
+        let state: ClearinghouseState = response.json().await?;
+        Ok(state)
+    }
+
+    /// Fills for `address` between the two timestamps (ms), oldest first.
+    pub async fn user_fills_by_time(&self, address: &str, start_time: i64, end_time: i64) -> Result<Vec<UserFill>> {
+        let mut fills: Vec<UserFill> = Vec::new();
+        let mut start_time = start_time;
+        loop {
+            let page = self.user_fills_page(address, start_time, end_time).await?;
+            let full = page.len() >= USER_FILLS_PAGE;
+            let last_time = page.last().map(|fill| fill.time);
+            // each page after the first starts at the last one's final
+            // millisecond, so fills from then may already be taken
+            let seen: HashSet<u64> = fills
+                .iter()
+                .rev()
+                .take_while(|fill| fill.time == start_time)
+                .map(|fill| fill.tid)
+                .collect();
+            let before = fills.len();
+            fills.extend(page.into_iter().filter(|fill| !seen.contains(&fill.tid)));
+
+            match last_time {
+                Some(last_time) if full && fills.len() > before => start_time = last_time,
+                _ => return Ok(fills),
+            }
+        }
+    }
+
+    // hl sends at most USER_FILLS_PAGE fills a request, the oldest first
+    async fn user_fills_page(&self, address: &str, start_time: i64, end_time: i64) -> Result<Vec<UserFill>> {
+        let request_body = UserRangeRequest {
+            request_type: "userFillsByTime".to_string(),
+            user: address.to_string(),
+            start_time,
+            end_time,
+        };
+
+        let response = self.post_info(&request_body).await?;
+
+        if !response.status().is_success() {
+            error!("hl fills request failed, status: {}", response.status());
+            return Err(anyhow::anyhow!("hl api error"));
+        }
+
+        let fills: Vec<UserFill> = response.json().await?;
+        Ok(fills)
+    }
+
+    /// Deposits, withdrawals and transfers for `address` since `start_time` (ms).
+    pub async fn user_ledger_updates(&self, address: &str, start_time: i64, end_time: i64) -> Result<Vec<LedgerUpdate>> {
+        let request_body = UserRangeRequest {
+            request_type: "userNonFundingLedgerUpdates".to_string(),
+            user: address.to_string(),
+            start_time,
+            end_time,
+        };
+
+        let response = self.post_info(&request_body).await?;
+
+        if !response.status().is_success() {
+            error!("hl ledger request failed, status: {}", response.status());
+            return Err(anyhow::anyhow!("hl api error"));
+        }
+
+        let updates: Vec<LedgerUpdate> = response.json().await?;
+        Ok(updates)
+    }
+}
+
+// `DEX:COIN` for a builder dex market whether or not hl prefixed the name,
+// uppercased like every other coin
+fn qualified_name(dex: Option<&str>, name: &str) -> String {
+    match dex {
+        Some(dex) if !name.contains(':') => format!("{}:{}", dex, name).to_uppercase(),
+        _ => name.to_uppercase(),
+    }
+}
@@ -1,42 +1,445 @@
 pub mod client;
+pub mod symbol;
+pub mod tls;
 pub mod websocket;
 
 use serde::{Deserialize, Serialize};
+use std::collections::hash_map::DefaultHasher;
+use std::collections::HashMap;
+use std::hash::{Hash, Hasher};
 
 
 #[derive(Serialize)]
 pub struct InfoRequest {
     #[serde(rename = "type")]
     pub request_type: String,
+    /// A builder-deployed perp dex; unset means the main dex.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub dex: Option<String>,
+}
+
+#[derive(Serialize)]
+pub struct CandleSnapshotRequest {
+    #[serde(rename = "type")]
+    pub request_type: String,
+    pub req: CandleSnapshotParams,
+}
+
+#[derive(Serialize)]
+pub struct CandleSnapshotParams {
+    pub coin: String,
+    pub interval: String,
+    #[serde(rename = "startTime")]
+    pub start_time: i64,
+    #[serde(rename = "endTime")]
+    pub end_time: i64,
+}
+
+#[derive(Serialize)]
+pub struct UserFundingRequest {
+    #[serde(rename = "type")]
+    pub request_type: String,
+    pub user: String,
+    #[serde(rename = "startTime")]
+    pub start_time: i64,
+    #[serde(rename = "endTime")]
+    pub end_time: i64,
+}
+
+#[derive(Serialize)]
+pub struct FundingHistoryRequest {
+    #[serde(rename = "type")]
+    pub request_type: String,
+    pub coin: String,
+    #[serde(rename = "startTime")]
+    pub start_time: i64,
+}
+
+/// Any of the info requests over a span of a user's history.
+#[derive(Serialize)]
+pub struct UserRangeRequest {
+    #[serde(rename = "type")]
+    pub request_type: String,
+    pub user: String,
+    #[serde(rename = "startTime")]
+    pub start_time: i64,
+    #[serde(rename = "endTime")]
+    pub end_time: i64,
+}
+
+#[derive(Debug, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct UserFill {
+    /// Realized on this fill, before fees.
+    pub closed_pnl: String,
+    pub fee: String,
+    /// In ms.
+    pub time: i64,
+    pub tid: u64,
+}
+
+/// A deposit, withdrawal or transfer from `userNonFundingLedgerUpdates`.
+#[derive(Debug, Deserialize, Clone)]
+pub struct LedgerUpdate {
+    /// In ms.
+    pub time: i64,
+    pub delta: LedgerDelta,
+}
+
+#[derive(Debug, Deserialize, Clone)]
+#[serde(tag = "type", rename_all = "camelCase")]
+pub enum LedgerDelta {
+    Deposit {
+        usdc: String,
+    },
+    Withdraw {
+        usdc: String,
+    },
+    InternalTransfer {
+        usdc: String,
+        user: String,
+        destination: String,
+    },
+    SubAccountTransfer {
+        usdc: String,
+        user: String,
+        destination: String,
+    },
+    #[serde(rename_all = "camelCase")]
+    SpotTransfer {
+        token: String,
+        amount: String,
+        usdc_value: Option<String>,
+        user: String,
+        destination: String,
+    },
+    VaultDeposit {
+        vault: String,
+        usdc: String,
+    },
+    #[serde(rename_all = "camelCase")]
+    VaultWithdraw {
+        vault: String,
+        net_withdrawn_usd: String,
+    },
+    /// Perp/spot class moves, liquidations and anything newer than this list.
+    #[serde(other)]
+    Other,
+}
+
+#[derive(Serialize)]
+pub struct UserStateRequest {
+    #[serde(rename = "type")]
+    pub request_type: String,
+    pub user: String,
+}
+
+/// A wallet's margin account from `clearinghouseState`.
+#[derive(Debug, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct ClearinghouseState {
+    pub margin_summary: MarginSummary,
+    pub asset_positions: Vec<AssetPosition>,
+}
+
+#[derive(Debug, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct MarginSummary {
+    pub account_value: String,
+}
+
+#[derive(Debug, Deserialize, Clone)]
+pub struct AssetPosition {
+    pub position: Position,
+}
+
+#[derive(Debug, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct Position {
+    pub coin: String,
+    /// Signed size, negative when short.
+    pub szi: String,
+    pub entry_px: Option<String>,
+    pub position_value: String,
+    pub unrealized_pnl: String,
+}
+
+/// One hourly funding settlement for a coin.
+#[derive(Debug, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct FundingRate {
+    pub funding_rate: String,
+}
+
+#[derive(Debug, Deserialize, Clone)]
+pub struct UserFunding {
+    pub delta: FundingDelta,
+}
+
+#[derive(Debug, Deserialize, Clone)]
+pub struct FundingDelta {
+    pub coin: String,
+    /// Positive when the account received funding.
+    pub usdc: String,
+}
+
+#[derive(Debug, Deserialize, Clone)]
+pub struct Candle {
+    /// Open time in ms.
+    #[serde(rename = "t")]
+    pub open_time: i64,
+    #[serde(rename = "o")]
+    pub open: String,
+    #[serde(rename = "h")]
+    pub high: String,
+    #[serde(rename = "l")]
+    pub low: String,
+    #[serde(rename = "c")]
+    pub close: String,
 }
 
 #[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
 pub struct MetaAndAssetCtxsResponse {
     pub universe: Vec<AssetInfo>,
+    /// The spot token a dex's markets are quoted in, as its index in
+    /// spotMeta's tokens; only builder dexs send it.
+    #[serde(default)]
+    pub collateral_token: Option<usize>,
 }
 
 #[derive(Debug, Deserialize)]
+pub struct SpotMetaResponse {
+    pub tokens: Vec<SpotToken>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct SpotToken {
+    pub name: String,
+    pub index: usize,
+}
+
+#[derive(Debug, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
 pub struct AssetInfo {
     pub name: String,
-    #[serde(rename = "isDelisted")]
     pub is_delisted: Option<bool>,
+    /// Sizes trade in steps of 10^-sz_decimals.
+    pub sz_decimals: u32,
+    pub max_leverage: u32,
+    /// Only tradable with isolated margin; hl leaves it out when false.
+    #[serde(default)]
+    pub only_isolated: bool,
+}
+
+/// Per-asset market state from `metaAndAssetCtxs`, in the same order as the universe.
+#[derive(Debug, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct AssetCtx {
+    /// Hourly funding rate.
+    pub funding: String,
+    /// In coin units.
+    pub open_interest: String,
+    pub prev_day_px: String,
+    pub day_ntl_vlm: String,
+    pub mark_px: String,
+    pub oracle_px: String,
 }
 
 #[derive(Debug, Deserialize, Clone)]
 pub struct WsTrade {
-    pub coin: String,
+    pub coin: CoinSymbol,
     pub side: String, 
     pub px: String, 
     pub sz: String, 
+    /// Exchange timestamp in ms.
+    pub time: Option<i64>,
+    pub tid: Option<u64>,
+    pub hash: Option<String>,
+    /// [buyer, seller] addresses.
+    pub users: Option<[String; 2]>,
+    /// How many exchange fills were merged into this trade.
+    #[serde(default = "single_fill")]
+    pub fills: u32,
+    /// USD value of one unit of the asset the market is quoted in; 1 for USDC.
+    #[serde(skip, default = "usd_quote")]
+    pub quote_usd: f64,
+}
+
+fn single_fill() -> u32 {
+    1
+}
+
+fn usd_quote() -> f64 {
+    1.0
+}
+
+// borrowed view of a trades-channel fill, read straight out of the text frame.
+// only fills that clear a feed's threshold are turned into an owned WsTrade.
+#[derive(Debug, Deserialize)]
+pub struct WsTradeRef<'a> {
+    pub coin: &'a str,
+    pub side: &'a str,
+    pub px: &'a str,
+    pub sz: &'a str,
+    pub time: Option<i64>,
+    pub tid: Option<u64>,
+    #[serde(borrow)]
+    pub hash: Option<&'a str>,
+    #[serde(borrow)]
+    pub users: Option<[&'a str; 2]>,
+}
+
+#[derive(Debug, Deserialize, Clone)]
+pub struct WsAllMids {
+    pub mids: HashMap<CoinSymbol, String>,
+}
+
+#[derive(Debug, Deserialize, Clone)]
+pub struct WsBook {
+    pub coin: CoinSymbol,
+    // [bids, asks], best level first
+    pub levels: Vec<Vec<WsLevel>>,
+}
+
+#[derive(Debug, Deserialize, Clone)]
+pub struct WsLevel {
+    pub px: String,
+    pub sz: String,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct BookImbalance {
+    pub mid: f64,
+    pub bid_depth_usd: f64,
+    pub ask_depth_usd: f64,
+    /// (bids - asks) / (bids + asks), in [-1, 1]; positive means bid-heavy.
+    pub imbalance: f64,
+}
+
+impl BookImbalance {
+    /// The larger side's share of the depth, in [0.5, 1]; what /imbalance
+    /// thresholds are set against.
+    pub fn dominant_share(&self) -> f64 {
+        self.bid_depth_usd.max(self.ask_depth_usd) / (self.bid_depth_usd + self.ask_depth_usd)
+    }
+}
+
+impl WsBook {
+    /// Bid/ask depth imbalance counting only levels within `band_bps` of mid.
+    pub fn imbalance_within_bps(&self, band_bps: f64) -> Option<BookImbalance> {
+        let bids = self.levels.first()?;
+        let asks = self.levels.get(1)?;
+        let best_bid: f64 = bids.first()?.px.parse().ok()?;
+        let best_ask: f64 = asks.first()?.px.parse().ok()?;
+        let mid = (best_bid + best_ask) / 2.0;
+
+        let band = mid * band_bps / 10_000.0;
+        let depth = |levels: &[WsLevel], in_band: &dyn Fn(f64) -> bool| -> f64 {
+            levels
+                .iter()
+                .filter_map(|level| Some((level.px.parse::<f64>().ok()?, level.sz.parse::<f64>().ok()?)))
+                .take_while(|(px, _)| in_band(*px))
+                .map(|(px, sz)| px * sz)
+                .sum()
+        };
+
+        let bid_depth_usd = depth(bids, &|px| px >= mid - band);
+        let ask_depth_usd = depth(asks, &|px| px <= mid + band);
+        let total = bid_depth_usd + ask_depth_usd;
+        if total <= 0.0 {
+            return None;
+        }
+
+        Some(BookImbalance {
+            mid,
+            bid_depth_usd,
+            ask_depth_usd,
+            imbalance: (bid_depth_usd - ask_depth_usd) / total,
+        })
+    }
 }
 
 impl WsTrade {
+    /// Stable identity for a fill: the exchange trade id, or a hash of the
+    /// fill's fields when the feed didn't include one.
+    pub fn dedup_key(&self) -> u64 {
+        if let Some(tid) = self.tid {
+            return tid;
+        }
+
+        let mut hasher = DefaultHasher::new();
+        (&self.coin, &self.side, &self.px, &self.sz, self.time, &self.hash).hash(&mut hasher);
+        hasher.finish()
+    }
+
     pub fn notional_usd(&self) -> anyhow::Result<f64> {
         let price: f64 = self.px.parse()?;
         let size: f64 = self.sz.parse()?;
-        Ok(price * size)
+        Ok(price * size * self.quote_usd)
+    }
+
+    /// The aggressor's address: the buyer on a buy, the seller on a sell.
+    pub fn taker(&self) -> Option<&str> {
+        let [buyer, seller] = self.users.as_ref()?;
+        Some(if self.side == "B" { buyer } else { seller })
+    }
+}
+
+impl WsTradeRef<'_> {
+    /// In the market's quote asset, which `TradeFilter` converts to USD.
+    pub fn notional_usd(&self) -> Option<f64> {
+        let price: f64 = self.px.parse().ok()?;
+        let size: f64 = self.sz.parse().ok()?;
+        Some(price * size)
+    }
+
+    pub fn taker(&self) -> Option<&str> {
+        let [buyer, seller] = self.users?;
+        Some(if self.side == "B" { buyer } else { seller })
+    }
+
+    /// Whether both fills come from the same taker order.
+    pub fn same_order(&self, other: &WsTradeRef<'_>) -> bool {
+        self.side == other.side && self.taker().is_some_and(|address| Some(address) == other.taker())
     }
+
+    pub fn to_trade(&self) -> WsTrade {
+        WsTrade {
+            coin: CoinSymbol::new(self.coin),
+            side: self.side.to_string(),
+            px: self.px.to_string(),
+            sz: self.sz.to_string(),
+            time: self.time,
+            tid: self.tid,
+            hash: self.hash.map(str::to_string),
+            users: self.users.map(|users| users.map(str::to_lowercase)),
+            fills: 1,
+            quote_usd: 1.0,
+        }
+    }
+}
+
+impl Candle {
+    /// (open, high, low, close) as floats; `None` if the exchange sent garbage.
+    pub fn ohlc(&self) -> Option<(f64, f64, f64, f64)> {
+        Some((
+            self.open.parse().ok()?,
+            self.high.parse().ok()?,
+            self.low.parse().ok()?,
+            self.close.parse().ok()?,
+        ))
+    }
+}
+
+/// Whether `address` looks like a 0x-prefixed 20-byte hex address.
+pub fn is_valid_address(address: &str) -> bool {
+    address.len() == 42
+        && address.starts_with("0x")
+        && address[2..].chars().all(|c| c.is_ascii_hexdigit())
 }
 
 pub use client::HyperliquidClient;
-pub use websocket::{WebSocketManager};
\ No newline at end of file
+pub use symbol::CoinSymbol;
+pub use tls::CertPins;
+pub use websocket::{FeedEvent, FeedKind, FeedStatus, FillSampler, SubscriptionError, TradeFilter, UnknownCoin, WebSocketManager};
\ No newline at end of file
@@ -0,0 +1,141 @@
+use serde::de::{self, Visitor};
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use std::borrow::{Borrow, Cow};
+use std::collections::HashSet;
+use std::fmt;
+use std::ops::Deref;
+use std::sync::{Arc, Mutex, OnceLock};
+
+// every trade, map key and subscriber row used to carry its own String copy of
+// the coin name. symbols are interned once (uppercased) and shared as Arc<str>,
+// so cloning one is just a refcount bump.
+#[derive(Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct CoinSymbol(Arc<str>);
+
+fn registry() -> &'static Mutex<HashSet<Arc<str>>> {
+    static REGISTRY: OnceLock<Mutex<HashSet<Arc<str>>>> = OnceLock::new();
+    REGISTRY.get_or_init(Default::default)
+}
+
+impl CoinSymbol {
+    /// Interns `coin`, normalised to uppercase like the rest of the pipeline.
+    pub fn new(coin: &str) -> Self {
+        let coin: Cow<str> = if coin.bytes().any(|b| b.is_ascii_lowercase()) {
+            Cow::Owned(coin.to_uppercase())
+        } else {
+            Cow::Borrowed(coin)
+        };
+
+        let mut interned = registry().lock().unwrap_or_else(|e| e.into_inner());
+        if let Some(existing) = interned.get(coin.as_ref()) {
+            return CoinSymbol(existing.clone());
+        }
+
+        let symbol: Arc<str> = Arc::from(coin.as_ref());
+        interned.insert(symbol.clone());
+        CoinSymbol(symbol)
+    }
+
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+
+    /// The builder-deployed perp dex this coin trades on, None for the main dex.
+    pub fn dex(&self) -> Option<String> {
+        dex_of(&self.0)
+    }
+}
+
+/// `xyz` for `XYZ:XYZ100`: builder-deployed perps are named `<dex>:<coin>`.
+pub fn dex_of(coin: &str) -> Option<String> {
+    let (dex, _) = coin.split_once(':')?;
+    Some(dex.to_lowercase())
+}
+
+/// The name hl expects in requests and subscriptions. Coins are uppercased
+/// everywhere else, but a builder dex prefix is lowercase on the exchange
+/// (`xyz:XYZ100`).
+pub fn wire_name(coin: &str) -> String {
+    match coin.split_once(':') {
+        Some((dex, coin)) => format!("{}:{}", dex.to_lowercase(), coin.to_uppercase()),
+        None => coin.to_uppercase(),
+    }
+}
+
+impl Deref for CoinSymbol {
+    type Target = str;
+
+    fn deref(&self) -> &str {
+        &self.0
+    }
+}
+
+impl AsRef<str> for CoinSymbol {
+    fn as_ref(&self) -> &str {
+        &self.0
+    }
+}
+
+impl Borrow<str> for CoinSymbol {
+    fn borrow(&self) -> &str {
+        &self.0
+    }
+}
+
+impl From<&str> for CoinSymbol {
+    fn from(coin: &str) -> Self {
+        CoinSymbol::new(coin)
+    }
+}
+
+impl From<String> for CoinSymbol {
+    fn from(coin: String) -> Self {
+        CoinSymbol::new(&coin)
+    }
+}
+
+impl PartialEq<str> for CoinSymbol {
+    fn eq(&self, other: &str) -> bool {
+        self.as_str() == other
+    }
+}
+
+impl fmt::Display for CoinSymbol {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+impl fmt::Debug for CoinSymbol {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Debug::fmt(&*self.0, f)
+    }
+}
+
+impl Serialize for CoinSymbol {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&self.0)
+    }
+}
+
+impl<'de> Deserialize<'de> for CoinSymbol {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        // a visitor rather than String::deserialize so the name is read straight
+        // out of the frame without an intermediate allocation
+        struct SymbolVisitor;
+
+        impl Visitor<'_> for SymbolVisitor {
+            type Value = CoinSymbol;
+
+            fn expecting(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                f.write_str("a coin symbol")
+            }
+
+            fn visit_str<E: de::Error>(self, coin: &str) -> Result<CoinSymbol, E> {
+                Ok(CoinSymbol::new(coin))
+            }
+        }
+
+        deserializer.deserialize_str(SymbolVisitor)
+    }
+}
@@ -0,0 +1,123 @@
+use anyhow::Result;
+use reqwest::ClientBuilder;
+use rustls::client::{ServerCertVerified, ServerCertVerifier, WebPkiVerifier};
+use rustls::{Certificate, ClientConfig, OwnedTrustAnchor, RootCertStore, ServerName};
+use sha2::{Digest, Sha256};
+use std::sync::Arc;
+use std::time::SystemTime;
+use tokio_tungstenite::Connector;
+use crate::config::TlsConfig;
+
+/// The TLS library the Hyperliquid connections use, and with rustls, the
+/// SHA-256 fingerprints their certificates must match, for deployments
+/// behind proxies or with a locked-down trust store. Pins are checked during
+/// the handshake, so nothing is sent to a server that doesn't match.
+#[derive(Clone, Default)]
+pub struct CertPins {
+    // None for native-tls
+    rustls: Option<Arc<ClientConfig>>,
+}
+
+impl CertPins {
+    pub fn new(config: &TlsConfig) -> Result<Self> {
+        let fingerprints = config
+            .pinned_sha256
+            .iter()
+            .map(|pin| parse_fingerprint(pin))
+            .collect::<Result<Vec<_>>>()?;
+
+        match config.backend.as_str() {
+            // native-tls has no hook to check a certificate before the request goes out
+            "native-tls" if !fingerprints.is_empty() => {
+                anyhow::bail!("hyperliquid.tls.pinned_sha256 needs hyperliquid.tls.backend = \"rustls\"")
+            }
+            "native-tls" => Ok(CertPins { rustls: None }),
+            "rustls" => Ok(CertPins {
+                rustls: Some(Arc::new(rustls_config(fingerprints))),
+            }),
+            other => anyhow::bail!("unknown hyperliquid.tls.backend {}, expected native-tls or rustls", other),
+        }
+    }
+
+    /// `builder` set up to use the configured backend.
+    pub fn configure(&self, builder: ClientBuilder) -> ClientBuilder {
+        match &self.rustls {
+            Some(config) => builder.use_preconfigured_tls(ClientConfig::clone(config)),
+            None => builder.use_native_tls(),
+        }
+    }
+
+    /// The websocket connector for the configured backend; None is native-tls.
+    pub fn connector(&self) -> Option<Connector> {
+        self.rustls.clone().map(Connector::Rustls)
+    }
+}
+
+fn rustls_config(fingerprints: Vec<[u8; 32]>) -> ClientConfig {
+    let mut roots = RootCertStore::empty();
+    roots.add_trust_anchors(webpki_roots::TLS_SERVER_ROOTS.iter().map(|anchor| {
+        OwnedTrustAnchor::from_subject_spki_name_constraints(anchor.subject, anchor.spki, anchor.name_constraints)
+    }));
+
+    ClientConfig::builder()
+        .with_safe_defaults()
+        .with_custom_certificate_verifier(Arc::new(PinnedVerifier {
+            roots: WebPkiVerifier::new(roots, None),
+            fingerprints,
+        }))
+        .with_no_client_auth()
+}
+
+// with no pins the usual root verification; with pins, a certificate matching
+// one is trusted in place of the roots, so a proxy's own certificate works,
+// and anything else is refused
+struct PinnedVerifier {
+    roots: WebPkiVerifier,
+    fingerprints: Vec<[u8; 32]>,
+}
+
+impl ServerCertVerifier for PinnedVerifier {
+    fn verify_server_cert(
+        &self,
+        end_entity: &Certificate,
+        intermediates: &[Certificate],
+        server_name: &ServerName,
+        scts: &mut dyn Iterator<Item = &[u8]>,
+        ocsp_response: &[u8],
+        now: SystemTime,
+    ) -> Result<ServerCertVerified, rustls::Error> {
+        if self.fingerprints.is_empty() {
+            return self
+                .roots
+                .verify_server_cert(end_entity, intermediates, server_name, scts, ocsp_response, now);
+        }
+
+        let fingerprint: [u8; 32] = Sha256::digest(&end_entity.0).into();
+        if !self.fingerprints.contains(&fingerprint) {
+            return Err(rustls::Error::General(format!(
+                "certificate {} doesn't match any pinned fingerprint",
+                to_hex(&fingerprint)
+            )));
+        }
+        Ok(ServerCertVerified::assertion())
+    }
+}
+
+// accepts `AB:CD:...` as printed by `openssl x509 -fingerprint -sha256`, or bare hex
+fn parse_fingerprint(pin: &str) -> Result<[u8; 32]> {
+    let hex: String = pin.chars().filter(|c| *c != ':' && !c.is_whitespace()).collect();
+    let invalid = || anyhow::anyhow!("pinned fingerprint {} isn't a sha-256 hex digest", pin);
+    if hex.len() != 64 || !hex.is_ascii() {
+        return Err(invalid());
+    }
+
+    let mut fingerprint = [0u8; 32];
+    for (i, byte) in fingerprint.iter_mut().enumerate() {
+        *byte = u8::from_str_radix(&hex[i * 2..i * 2 + 2], 16).map_err(|_| invalid())?;
+    }
+    Ok(fingerprint)
+}
+
+fn to_hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|byte| format!("{:02X}", byte)).collect::<Vec<_>>().join(":")
+}
@@ -1,218 +1,1084 @@
-use anyhow::{Result, anyhow};
 use serde::{Serialize, Deserialize};
-use std::collections::HashMap;
-use std::sync::Arc;
+use std::collections::{HashMap, HashSet};
+use std::sync::atomic::{AtomicU32, AtomicU64, Ordering};
+use std::sync::{Arc, Mutex, MutexGuard, PoisonError};
 use tokio::sync::{mpsc, RwLock};
-use tokio::time::{sleep, Duration};
-use tokio_tungstenite::{connect_async, tungstenite::Message};
+use tokio::time::{sleep, Duration, Instant};
+use tokio_tungstenite::{connect_async_tls_with_config, tungstenite::Message};
 use futures_util::{SinkExt, StreamExt};
 use tracing::{info, error, warn, debug};
-use super::WsTrade;
+use serde_json::value::RawValue;
+use super::{CertPins, CoinSymbol, HyperliquidClient, WsAllMids, WsBook, WsTrade, WsTradeRef};
+use crate::{
+    activity::ActivityTracker, config::DEFAULT_TENANT, payload_log::PayloadLog, prices::QuoteRate, recording::TradeRecorder,
+    vwap::VwapTracker,
+};
 
+// every channel except trades, which takes the borrowed path in `trades_frame`
 #[derive(Debug, Deserialize)]
-struct WsResponse {
-    data: Vec<WsTrade>,
+#[serde(tag = "channel", content = "data")]
+enum WsMessage {
+    #[serde(rename = "l2Book")]
+    L2Book(WsBook),
+    #[serde(rename = "allMids")]
+    AllMids(WsAllMids),
+    #[serde(rename = "subscriptionResponse")]
+    SubscriptionResponse(serde_json::Value),
+    #[serde(rename = "error")]
+    Error(String),
 }
-#[derive(Serialize)]
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum FeedKind {
+    Trades,
+    L2Book,
+    AllMids,
+}
+
+impl FeedKind {
+    fn subscription_type(&self) -> &'static str {
+        match self {
+            FeedKind::Trades => "trades",
+            FeedKind::L2Book => "l2Book",
+            FeedKind::AllMids => "allMids",
+        }
+    }
+
+    fn from_subscription_type(sub_type: &str) -> Option<Self> {
+        match sub_type {
+            "trades" => Some(FeedKind::Trades),
+            "l2Book" => Some(FeedKind::L2Book),
+            "allMids" => Some(FeedKind::AllMids),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SubscriptionError {
+    InvalidSubscription(String),
+    TooManySubscriptions(String),
+    Other(String),
+}
+
+impl SubscriptionError {
+    fn from_message(message: String) -> Self {
+        let lower = message.to_lowercase();
+        if lower.contains("invalid subscription") || lower.contains("unknown coin") {
+            SubscriptionError::InvalidSubscription(message)
+        } else if lower.contains("too many") || lower.contains("more than") {
+            SubscriptionError::TooManySubscriptions(message)
+        } else {
+            SubscriptionError::Other(message)
+        }
+    }
+}
+
+impl std::fmt::Display for SubscriptionError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SubscriptionError::InvalidSubscription(msg) => write!(f, "invalid subscription: {}", msg),
+            SubscriptionError::TooManySubscriptions(msg) => write!(f, "too many subscriptions: {}", msg),
+            SubscriptionError::Other(msg) => write!(f, "{}", msg),
+        }
+    }
+}
+
+/// A feed was asked for a coin that isn't in hl's coin list, so it was never
+/// subscribed; hl would only reject it on every reconnect.
+#[derive(Debug, Clone)]
+pub struct UnknownCoin(pub CoinSymbol);
+
+impl std::fmt::Display for UnknownCoin {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} isn't a coin hl lists", self.0)
+    }
+}
+
+impl std::error::Error for UnknownCoin {}
+
+/// Subscription lifecycle events surfaced to the coordinator.
+#[derive(Debug, Clone)]
+pub enum FeedEvent {
+    Subscribed {
+        kind: FeedKind,
+        coin: Option<CoinSymbol>,
+    },
+    SubscriptionFailed {
+        kind: FeedKind,
+        coin: Option<CoinSymbol>,
+        error: SubscriptionError,
+    },
+    /// The feed's connection gave up reconnecting, so the feed is gone and has
+    /// to be started again.
+    Dropped {
+        kind: FeedKind,
+        coin: Option<CoinSymbol>,
+    },
+}
+
+/// Pre-filter applied on the socket task so small fills are never allocated.
+#[derive(Debug, Clone)]
+pub struct TradeFilter {
+    pub min_notional_usd: f64,
+    // bumped for every fill on the feed, forwarded or not
+    pub seen: Arc<AtomicU64>,
+    // likewise fed every fill, since vwap needs the small ones too
+    pub vwap: VwapTracker,
+    // and the rolling 24h volume, which wants every fill unsampled
+    pub activity: ActivityTracker,
+    // judge a taker order's fills by their combined size, so its partial fills
+    // reach the coordinator to be merged
+    pub cluster_fills: bool,
+    // caps the sub-threshold fills that go into vwap on chatty coins
+    pub sampler: Option<Arc<FillSampler>>,
+    // gets the raw frame before any of the above
+    pub recorder: Option<TradeRecorder>,
+    // for markets not quoted in USDC; notionals are converted before any
+    // threshold sees them
+    pub quote_rate: Option<QuoteRate>,
+}
+
+impl TradeFilter {
+    /// A fill's notional in USD, converted at the quote asset's mid for
+    /// markets not quoted in USDC. Until that asset's first mid nothing on
+    /// the feed has a USD value, so nothing is alert-sized or counted as volume.
+    pub fn notional_usd(&self, trade: &WsTradeRef<'_>) -> Option<f64> {
+        let quote_usd = self.quote_rate.as_ref().map_or(Some(1.0), QuoteRate::usd);
+        Some(trade.notional_usd()? * quote_usd?)
+    }
+
+    /// The fill as sent on to the coordinator, carrying the rate it was
+    /// converted at.
+    pub fn to_trade(&self, trade: &WsTradeRef<'_>) -> WsTrade {
+        WsTrade {
+            quote_usd: self.quote_rate.as_ref().and_then(QuoteRate::usd).unwrap_or(1.0),
+            ..trade.to_trade()
+        }
+    }
+}
+
+/// Per-second budget of sub-threshold fills a feed spends on statistics.
+#[derive(Debug)]
+pub struct FillSampler {
+    per_sec: u32,
+    window: Mutex<(Instant, u32)>,
+}
+
+impl FillSampler {
+    pub fn new(per_sec: u32) -> Self {
+        FillSampler {
+            per_sec,
+            window: Mutex::new((Instant::now(), 0)),
+        }
+    }
+
+    // how many of `wanted` fills still fit in the current second
+    fn take(&self, wanted: usize) -> usize {
+        let Ok(mut window) = self.window.lock() else {
+            return wanted;
+        };
+        if window.0.elapsed() >= Duration::from_secs(1) {
+            *window = (Instant::now(), 0);
+        }
+        let granted = (self.per_sec.saturating_sub(window.1) as usize).min(wanted);
+        window.1 += granted as u32;
+        granted
+    }
+}
+
+#[derive(Clone)]
+enum FeedSender {
+    Trades(mpsc::UnboundedSender<WsTrade>, TradeFilter),
+    L2Book(mpsc::UnboundedSender<WsBook>),
+    AllMids(mpsc::UnboundedSender<WsAllMids>),
+}
+
+impl FeedSender {
+    fn kind(&self) -> FeedKind {
+        match self {
+            FeedSender::Trades(..) => FeedKind::Trades,
+            FeedSender::L2Book(_) => FeedKind::L2Book,
+            FeedSender::AllMids(_) => FeedKind::AllMids,
+        }
+    }
+
+    // false once the receiving side has gone away
+    fn forward(&self, message: &WsMessage) -> bool {
+        match (self, message) {
+            (FeedSender::L2Book(tx), WsMessage::L2Book(book)) => tx.send(book.clone()).is_ok(),
+            (FeedSender::AllMids(tx), WsMessage::AllMids(mids)) => tx.send(mids.clone()).is_ok(),
+            _ => true,
+        }
+    }
+
+    fn forward_trades(&self, frame: &str, trades: &[WsTradeRef<'_>]) -> bool {
+        let FeedSender::Trades(tx, filter) = self else {
+            return true;
+        };
+
+        if let Some(recorder) = &filter.recorder {
+            recorder.record(frame);
+        }
+
+        filter.seen.fetch_add(trades.len() as u64, Ordering::Relaxed);
+        let notional_usd = |trade: &WsTradeRef<'_>| filter.notional_usd(trade);
+        if let Some(first) = trades.first() {
+            let volume = trades.iter().filter_map(notional_usd).sum();
+            filter.activity.record(first.coin, volume, trades.len() as u64);
+        }
+        let alert_sized =
+            |trade: &WsTradeRef<'_>| notional_usd(trade).is_some_and(|notional| notional >= filter.min_notional_usd);
+        let to_trade = |trade: &WsTradeRef<'_>| filter.to_trade(trade);
+        // alert-sized fills always count; small ones only while the budget lasts
+        let mut budget = match &filter.sampler {
+            Some(sampler) => sampler.take(trades.iter().filter(|trade| !alert_sized(trade)).count()),
+            None => usize::MAX,
+        };
+        filter.vwap.record(
+            trades
+                .iter()
+                .filter(|trade| {
+                    if filter.sampler.is_none() || alert_sized(trade) {
+                        return true;
+                    }
+                    let sampled = budget > 0;
+                    budget = budget.saturating_sub(1);
+                    sampled
+                })
+                .filter_map(|trade| Some((trade.coin, trade.px.parse().ok()?, trade.sz.parse().ok()?))),
+        );
+        if !filter.cluster_fills {
+            return trades
+                .iter()
+                .filter(|trade| alert_sized(trade))
+                .all(|trade| tx.send(to_trade(trade)).is_ok());
+        }
+
+        trades
+            .chunk_by(|a, b| a.same_order(b))
+            .filter(|order| order.iter().filter_map(notional_usd).sum::<f64>() >= filter.min_notional_usd)
+            .flatten()
+            .all(|trade| tx.send(to_trade(trade)).is_ok())
+    }
+}
+
+// one tenant's end of a feed; tenants wanting the same feed share the
+// upstream subscription and each get every message
+struct FeedSink {
+    tenant: String,
+    sender: FeedSender,
+    events: mpsc::UnboundedSender<FeedEvent>,
+}
+
+#[derive(Serialize, Deserialize)]
 struct WsSubscription {
     method: String,
     subscription: WsSubscriptionData,
 }
 
-#[derive(Serialize)]
+#[derive(Serialize, Deserialize)]
 struct WsSubscriptionData {
     #[serde(rename = "type")]
     sub_type: String,
-    coin: String,
+    #[serde(skip_serializing_if = "Option::is_none", serialize_with = "serialize_wire_name")]
+    coin: Option<CoinSymbol>,
 }
 
-#[derive(Debug)]
-pub struct WebSocketHandle {
-    coin: String,
+// builder dex coins go out as `xyz:XYZ100`, hl rejects the uppercased prefix
+fn serialize_wire_name<S: serde::Serializer>(coin: &Option<CoinSymbol>, serializer: S) -> Result<S::Ok, S::Error> {
+    match coin {
+        Some(coin) => serializer.serialize_str(&super::symbol::wire_name(coin)),
+        None => serializer.serialize_none(),
+    }
+}
+
+impl WsSubscriptionData {
+    fn feed_key(&self) -> Option<String> {
+        let kind = FeedKind::from_subscription_type(&self.sub_type)?;
+        Some(feed_key(kind, self.coin.as_deref()))
+    }
+}
+
+// weight of the newest gap in a feed's baseline; small, so one burst or lull
+// barely moves it
+const GAP_BASELINE_WEIGHT: f64 = 0.05;
+
+#[derive(Default)]
+struct GapStats {
+    last_message_at: Option<Instant>,
+    // smoothed gap between messages, what the feed normally looks like
+    baseline: Option<Duration>,
+    max: Duration,
+}
+
+#[derive(Default)]
+struct FeedStats {
+    messages: AtomicU64,
+    reconnects: AtomicU32,
+    gaps: Mutex<GapStats>,
+}
+
+impl FeedStats {
+    fn record_message(&self) {
+        self.messages.fetch_add(1, Ordering::Relaxed);
+        if let Ok(mut gaps) = self.gaps.lock() {
+            let now = Instant::now();
+            if let Some(last_message_at) = gaps.last_message_at {
+                let gap = now.duration_since(last_message_at);
+                gaps.baseline = Some(match gaps.baseline {
+                    Some(baseline) => baseline.mul_f64(1.0 - GAP_BASELINE_WEIGHT) + gap.mul_f64(GAP_BASELINE_WEIGHT),
+                    None => gap,
+                });
+                gaps.max = gaps.max.max(gap);
+            }
+            gaps.last_message_at = Some(now);
+        }
+    }
+}
+
+/// Point-in-time view of a feed, for admin tooling.
+#[derive(Debug, Clone)]
+pub struct FeedStatus {
+    pub feed_key: String,
+    pub kind: FeedKind,
+    pub coin: Option<CoinSymbol>,
+    pub connection_id: usize,
+    pub uptime: Duration,
+    pub last_message_age: Option<Duration>,
+    pub messages: u64,
+    /// Smoothed gap between messages, the feed's normal pace.
+    pub baseline_gap: Option<Duration>,
+    /// Longest gap between two messages since the feed started.
+    pub max_gap: Duration,
+    pub messages_per_sec: f64,
+    pub reconnects: u32,
+}
+
+#[derive(Clone)]
+struct FeedSubscription {
+    feed_key: String,
+    kind: FeedKind,
+    coin: Option<CoinSymbol>,
+    sinks: Arc<Mutex<Vec<FeedSink>>>,
+    started_at: Instant,
+    stats: Arc<FeedStats>,
+}
+
+impl FeedSubscription {
+    fn status(&self, connection_id: usize) -> FeedStatus {
+        let uptime = self.started_at.elapsed();
+        let messages = self.stats.messages.load(Ordering::Relaxed);
+        let (last_message_age, baseline_gap, max_gap) = match self.stats.gaps.lock() {
+            Ok(gaps) => (gaps.last_message_at.map(|at| at.elapsed()), gaps.baseline, gaps.max),
+            Err(_) => (None, None, Duration::ZERO),
+        };
+
+        FeedStatus {
+            feed_key: self.feed_key.clone(),
+            kind: self.kind,
+            coin: self.coin.clone(),
+            connection_id,
+            uptime,
+            last_message_age,
+            messages,
+            baseline_gap,
+            max_gap,
+            messages_per_sec: messages as f64 / uptime.as_secs_f64().max(1.0),
+            reconnects: self.stats.reconnects.load(Ordering::Relaxed),
+        }
+    }
+
+    // tells a trade feed's activity trackers whether it's up, so an outage
+    // isn't taken for a quiet spell
+    fn mark_live(&self, live: bool) {
+        let (Some(coin), Ok(sinks)) = (&self.coin, self.sinks.lock()) else {
+            return;
+        };
+        for sink in sinks.iter() {
+            if let FeedSender::Trades(_, filter) = &sink.sender {
+                if live {
+                    filter.activity.feed_up(coin);
+                } else {
+                    filter.activity.feed_down(coin);
+                }
+            }
+        }
+    }
+
+    fn frame(&self, method: &str) -> anyhow::Result<Message> {
+        let subscription = WsSubscription {
+            method: method.to_string(),
+            subscription: WsSubscriptionData {
+                sub_type: self.kind.subscription_type().to_string(),
+                coin: self.coin.clone(),
+            },
+        };
+        Ok(Message::Text(serde_json::to_string(&subscription)?))
+    }
+
+    // a panic mid-forward leaves the list itself intact
+    fn sinks(&self) -> MutexGuard<'_, Vec<FeedSink>> {
+        self.sinks.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    // false once every tenant's receiver has gone away
+    fn forward(&self, message: &WsMessage) -> bool {
+        let mut sinks = self.sinks();
+        sinks.retain(|sink| sink.sender.forward(message));
+        !sinks.is_empty()
+    }
+
+    fn forward_trades(&self, frame: &str, trades: &[WsTradeRef<'_>]) -> bool {
+        let mut sinks = self.sinks();
+        sinks.retain(|sink| sink.sender.forward_trades(frame, trades));
+        !sinks.is_empty()
+    }
+
+    fn notify(&self, event: FeedEvent) {
+        for sink in self.sinks().iter() {
+            let _ = sink.events.send(event.clone());
+        }
+    }
+}
+
+enum ConnectionCommand {
+    Subscribe(FeedSubscription),
+    Unsubscribe(String),
+}
+
+struct PooledConnection {
+    command_tx: mpsc::UnboundedSender<ConnectionCommand>,
     shutdown_tx: mpsc::Sender<()>,
+    feed_keys: HashSet<String>,
+}
+
+// every feed is multiplexed onto a shared connection, capped per connection
+#[derive(Default)]
+struct ConnectionPool {
+    next_id: usize,
+    connections: HashMap<usize, PooledConnection>,
+    feeds: HashMap<String, (usize, FeedSubscription)>,
 }
 
-impl WebSocketHandle {
-    
-    pub async fn shutdown(self) {
-        let _ = self.shutdown_tx.send(()).await;
-        info!("shutdown signal for {}", self.coin);
+impl ConnectionPool {
+    fn remove_connection(&mut self, connection_id: usize) {
+        if let Some(connection) = self.connections.remove(&connection_id) {
+            for feed_key in connection.feed_keys {
+                self.feeds.remove(&feed_key);
+            }
+        }
     }
 }
 
+// where connections go and what they must present when they get there
+#[derive(Clone)]
+struct Endpoint {
+    url: String,
+    cert_pins: CertPins,
+    payload_log: PayloadLog,
+}
+
 pub struct WebSocketManager {
-    websocket_url: String,
-    active_websockets: Arc<RwLock<HashMap<String, WebSocketHandle>>>,
+    endpoint: Endpoint,
+    max_subscriptions_per_connection: usize,
+    // whose feeds this handle starts and stops; the pool is everyone's
+    tenant: String,
+    feed_event_tx: mpsc::UnboundedSender<FeedEvent>,
+    // the coin list shared with the rest of the bot, checked before a
+    // per-coin feed is subscribed
+    coins: HyperliquidClient,
+    pool: Arc<RwLock<ConnectionPool>>,
 }
 
 impl WebSocketManager {
-    pub fn new(websocket_url: String) -> Self {
+    pub fn new(
+        websocket_url: String,
+        cert_pins: CertPins,
+        payload_log: PayloadLog,
+        max_subscriptions_per_connection: usize,
+        feed_event_tx: mpsc::UnboundedSender<FeedEvent>,
+        coins: HyperliquidClient,
+    ) -> Self {
+        WebSocketManager {
+            endpoint: Endpoint {
+                url: websocket_url,
+                cert_pins,
+                payload_log,
+            },
+            max_subscriptions_per_connection: max_subscriptions_per_connection.max(1),
+            tenant: DEFAULT_TENANT.to_string(),
+            feed_event_tx,
+            coins,
+            pool: Arc::new(RwLock::new(ConnectionPool::default())),
+        }
+    }
+
+    /// A handle for another tenant on the same connections, with its own feed events.
+    pub fn for_tenant(&self, tenant: &str, feed_event_tx: mpsc::UnboundedSender<FeedEvent>) -> Self {
         WebSocketManager {
-            websocket_url,
-            active_websockets: Arc::new(RwLock::new(HashMap::new())),
+            endpoint: self.endpoint.clone(),
+            max_subscriptions_per_connection: self.max_subscriptions_per_connection,
+            tenant: tenant.to_string(),
+            feed_event_tx,
+            coins: self.coins.clone(),
+            pool: self.pool.clone(),
         }
     }
 
     pub async fn start_trade_feed(
         &self, 
-        coin: &str, 
+        coin: &CoinSymbol, 
+        filter: TradeFilter,
         trade_sender: mpsc::UnboundedSender<WsTrade>
-    ) -> anyhow::Result<WebSocketHandle> {
-        let coin = coin.to_uppercase();
-        
-        {
-            let websockets = self.active_websockets.read().await;
-            if websockets.contains_key(&coin) {
-                return Err(anyhow::anyhow!("ws alr exists for {}", coin));
+    ) -> anyhow::Result<()> {
+        self.check_coin(coin).await?;
+        self.start_feed(Some(coin.clone()), FeedSender::Trades(trade_sender, filter)).await
+    }
+
+    pub async fn start_book_feed(
+        &self,
+        coin: &CoinSymbol,
+        book_sender: mpsc::UnboundedSender<WsBook>
+    ) -> anyhow::Result<()> {
+        self.check_coin(coin).await?;
+        self.start_feed(Some(coin.clone()), FeedSender::L2Book(book_sender)).await
+    }
+
+    pub async fn start_mids_feed(
+        &self,
+        mids_sender: mpsc::UnboundedSender<WsAllMids>
+    ) -> anyhow::Result<()> {
+        self.start_feed(None, FeedSender::AllMids(mids_sender)).await
+    }
+
+    // fails with `UnknownCoin` only when the list says so; with no list to go
+    // on the feed starts anyway, and hl's own rejection still tears it down
+    async fn check_coin(&self, coin: &CoinSymbol) -> anyhow::Result<()> {
+        match self.coins.coin_exists(coin.as_str()).await {
+            Ok(true) => Ok(()),
+            Ok(false) => Err(UnknownCoin(coin.clone()).into()),
+            Err(e) => {
+                warn!("couldn't check {} against the coin list, starting its feed anyway: {}", coin, e);
+                Ok(())
             }
         }
+    }
+
+    async fn start_feed(&self, coin: Option<CoinSymbol>, sender: FeedSender) -> anyhow::Result<()> {
+        let kind = sender.kind();
+        let sink = FeedSink {
+            tenant: self.tenant.clone(),
+            sender,
+            events: self.feed_event_tx.clone(),
+        };
+
+        let mut pool = self.pool.write().await;
+        let key = feed_key(kind, coin.as_deref());
+        // another tenant already has hl sending this, so just listen in
+        if let Some((connection_id, subscription)) = pool.feeds.get(&key) {
+            let mut sinks = subscription.sinks();
+            if sinks.iter().any(|existing| existing.tenant == self.tenant) {
+                return Err(anyhow::anyhow!("ws alr exists for {}", key));
+            }
+            sinks.push(sink);
+            info!("tenant {} joined {} on ws connection {}", self.tenant, key, connection_id);
+            return Ok(());
+        }
+
+        let subscription = FeedSubscription {
+            feed_key: key,
+            kind,
+            coin,
+            sinks: Arc::new(Mutex::new(vec![sink])),
+            started_at: Instant::now(),
+            stats: Arc::new(FeedStats::default()),
+        };
 
-        let (shutdown_tx, mut shutdown_rx) = mpsc::channel::<()>(1);
-        let websocket_url = self.websocket_url.clone();
-        let coin_clone = coin.clone();
-        let active_websockets = self.active_websockets.clone();
+        let connection_id = match pool
+            .connections
+            .iter()
+            .filter(|(_, connection)| connection.feed_keys.len() < self.max_subscriptions_per_connection)
+            .min_by_key(|(_, connection)| connection.feed_keys.len())
+        {
+            Some((&connection_id, _)) => connection_id,
+            None => self.spawn_connection(&mut pool),
+        };
+
+        Self::assign(&mut pool, connection_id, subscription)
+    }
+
+    fn assign(pool: &mut ConnectionPool, connection_id: usize, subscription: FeedSubscription) -> anyhow::Result<()> {
+        let connection = pool
+            .connections
+            .get_mut(&connection_id)
+            .ok_or_else(|| anyhow::anyhow!("ws connection {} is gone", connection_id))?;
+
+        connection
+            .command_tx
+            .send(ConnectionCommand::Subscribe(subscription.clone()))
+            .map_err(|_| anyhow::anyhow!("ws connection {} is gone", connection_id))?;
+        connection.feed_keys.insert(subscription.feed_key.clone());
+
+        info!("assigned {} to ws connection {}", subscription.feed_key, connection_id);
+        pool.feeds.insert(subscription.feed_key.clone(), (connection_id, subscription));
+        Ok(())
+    }
+
+    fn spawn_connection(&self, pool: &mut ConnectionPool) -> usize {
+        let connection_id = pool.next_id;
+        pool.next_id += 1;
+
+        let (command_tx, command_rx) = mpsc::unbounded_channel();
+        let (shutdown_tx, shutdown_rx) = mpsc::channel::<()>(1);
+
+        pool.connections.insert(connection_id, PooledConnection {
+            command_tx,
+            shutdown_tx,
+            feed_keys: HashSet::new(),
+        });
+
+        let endpoint = self.endpoint.clone();
+        let pool = self.pool.clone();
 
         tokio::spawn(async move {
-            let mut retry_count = 0;
-            const MAX_RETRIES: u32 = 5;
-            const BASE_DELAY: u64 = 1000;
-            const MAX_DELAY: u64 = 30000;
-
-            loop {
-                if shutdown_rx.try_recv().is_ok() {
-                    break;
-                }
+            let dropped = Self::run_connection(connection_id, endpoint, command_rx, shutdown_rx).await;
 
-                info!("trying to connect to {} ws (attempt {})", coin_clone, retry_count + 1);
+            pool.write().await.remove_connection(connection_id);
+            info!("removed ws connection {}", connection_id);
 
-                match Self::websocket_connection(
-                    &websocket_url, 
-                    &coin_clone, 
-                    trade_sender.clone(), 
-                    &mut shutdown_rx
-                ).await {
-                    Ok(_) => {
-                        break; //websocket ended
+            // only once they're out of the pool, so a restart gets a fresh feed
+            for subscription in dropped {
+                subscription.notify(FeedEvent::Dropped {
+                    kind: subscription.kind,
+                    coin: subscription.coin.clone(),
+                });
+            }
+        });
+
+        info!("opened ws connection {}", connection_id);
+        connection_id
+    }
+
+    // the feeds it was carrying if it gave up reconnecting, none on shutdown
+    async fn run_connection(
+        connection_id: usize,
+        endpoint: Endpoint,
+        mut command_rx: mpsc::UnboundedReceiver<ConnectionCommand>,
+        mut shutdown_rx: mpsc::Receiver<()>,
+    ) -> Vec<FeedSubscription> {
+        let mut subscriptions: HashMap<String, FeedSubscription> = HashMap::new();
+        let mut retry_count = 0;
+        const MAX_RETRIES: u32 = 5;
+        const BASE_DELAY: u64 = 1000;
+        const MAX_DELAY: u64 = 30000;
+
+        loop {
+            if shutdown_rx.try_recv().is_ok() {
+                break;
+            }
+
+            info!("trying to connect ws connection {} (attempt {})", connection_id, retry_count + 1);
+
+            match Self::websocket_connection(
+                &endpoint,
+                connection_id,
+                &mut subscriptions,
+                &mut command_rx,
+                &mut shutdown_rx,
+                &mut retry_count,
+            ).await {
+                Ok(_) => {
+                    for subscription in subscriptions.values() {
+                        subscription.mark_live(false);
                     }
-                    Err(e) => {
-                        error!("ws connection for {} failed: {}", coin_clone, e);
-                        retry_count += 1;
-                        
-                        if retry_count >= MAX_RETRIES {
-                            error!("max retries reached for {}", coin_clone);
-                            break;
+                    break; //shut down
+                }
+                Err(e) => {
+                    error!("ws connection {} failed: {}", connection_id, e);
+                    for subscription in subscriptions.values() {
+                        subscription.mark_live(false);
+                    }
+                    retry_count += 1;
+                    
+                    if retry_count >= MAX_RETRIES {
+                        // closed first, so nothing else can be queued; subscribes
+                        // that never reached the socket are dropped with the rest
+                        command_rx.close();
+                        while let Ok(command) = command_rx.try_recv() {
+                            match command {
+                                ConnectionCommand::Subscribe(subscription) => {
+                                    subscriptions.insert(subscription.feed_key.clone(), subscription);
+                                }
+                                ConnectionCommand::Unsubscribe(feed_key) => {
+                                    subscriptions.remove(&feed_key);
+                                }
+                            }
                         }
+                        error!("max retries reached for ws connection {}, dropping its {} feeds", connection_id, subscriptions.len());
+                        return subscriptions.into_values().collect();
                     }
                 }
-
-                let delay = std::cmp::min(BASE_DELAY * 2_u64.pow(retry_count), MAX_DELAY);
-                let jitter = (delay as f64 * 0.1 * rand::random::<f64>()) as u64;
-                let total_delay = delay + jitter;
-                
-                warn!("retrying {} ws in {}ms", coin_clone, total_delay);
-                sleep(Duration::from_millis(total_delay)).await;
             }
 
-            let mut websockets = active_websockets.write().await;
-            websockets.remove(&coin_clone);
-            info!("removed {} ws", coin_clone);
-        });
-
-        let handle = WebSocketHandle {
-            coin: coin.clone(),
-            shutdown_tx: shutdown_tx.clone(),
-        };
-
-        {
-            let mut websockets = self.active_websockets.write().await;
-            websockets.insert(coin.clone(), handle);
+            let delay = std::cmp::min(BASE_DELAY * 2_u64.pow(retry_count), MAX_DELAY);
+            let jitter = (delay as f64 * 0.1 * rand::random::<f64>()) as u64;
+            let total_delay = delay + jitter;
+            
+            warn!("retrying ws connection {} in {}ms", connection_id, total_delay);
+            sleep(Duration::from_millis(total_delay)).await;
         }
-
-        Ok(WebSocketHandle {
-            coin,
-            shutdown_tx,
-        })
+        Vec::new()
     }
 
+    // runs one physical connection until shutdown (Ok) or failure (Err)
     async fn websocket_connection(
-        websocket_url: &str,
-        coin: &str,
-        trade_sender: mpsc::UnboundedSender<WsTrade>,
+        endpoint: &Endpoint,
+        connection_id: usize,
+        subscriptions: &mut HashMap<String, FeedSubscription>,
+        command_rx: &mut mpsc::UnboundedReceiver<ConnectionCommand>,
         shutdown_rx: &mut mpsc::Receiver<()>,
+        retry_count: &mut u32,
     ) -> anyhow::Result<()> {
-        let (ws_stream, _) = connect_async(websocket_url).await?;
+        // any pins are checked in the handshake, before the upgrade request
+        let (ws_stream, _) = connect_async_tls_with_config(&endpoint.url, None, false, endpoint.cert_pins.connector()).await?;
         let (mut ws_sender, mut ws_receiver) = ws_stream.split();
+        *retry_count = 0;
 
-        let subscription = WsSubscription {
-            method: "subscribe".to_string(),
-            subscription: WsSubscriptionData {
-                sub_type: "trades".to_string(),
-                coin: coin.to_string(),
-            },
-        };
-
-        let sub_message = serde_json::to_string(&subscription)?;
-        ws_sender.send(Message::Text(sub_message)).await?;
+        // resubscribe everything this connection owns after a reconnect
+        for subscription in subscriptions.values() {
+            subscription.stats.reconnects.fetch_add(1, Ordering::Relaxed);
+            ws_sender.send(subscription.frame("subscribe")?).await?;
+        }
 
         loop {
             tokio::select! {
                 _ = shutdown_rx.recv() => {
                     let _ = ws_sender.close().await;
-                    break;
+                    return Ok(());
+                }
+
+                Some(command) = command_rx.recv() => {
+                    match command {
+                        ConnectionCommand::Subscribe(subscription) => {
+                            ws_sender.send(subscription.frame("subscribe")?).await?;
+                            subscriptions.insert(subscription.feed_key.clone(), subscription);
+                        }
+                        ConnectionCommand::Unsubscribe(feed_key) => {
+                            if let Some(subscription) = subscriptions.remove(&feed_key) {
+                                subscription.mark_live(false);
+                                ws_sender.send(subscription.frame("unsubscribe")?).await?;
+                            }
+                        }
+                    }
                 }
                 
                 message = ws_receiver.next() => {
                     match message {
                         Some(Ok(Message::Text(text))) => {
-                            match serde_json::from_str::<WsResponse>(&text) {
-                                Ok(ws_response) => {
-                                    for trade in ws_response.data {
-                                        
-                                        if let Err(_) = trade_sender.send(trade) {
-                                            warn!("receiver dropped, closing {} ws", coin);
-                                            break;
+                            #[cfg(feature = "chaos")]
+                            if crate::chaos::drop_ws_connection() {
+                                return Err(anyhow::anyhow!("chaos: dropped ws connection {}", connection_id));
+                            }
+
+                            // the feed a frame was routed to, if its receiver has gone
+                            let dropped = match trades_frame(&text) {
+                                Some(trades) => route_trades(subscriptions, &text, &trades),
+                                None => match serde_json::from_str::<WsMessage>(&text) {
+                                    Ok(WsMessage::SubscriptionResponse(response)) => {
+                                        debug!("subscription confirmed on ws connection {}: {}", connection_id, response);
+                                        let confirmed = serde_json::from_value::<WsSubscription>(response)
+                                            .ok()
+                                            .and_then(|ack| ack.subscription.feed_key())
+                                            .and_then(|key| subscriptions.get(&key));
+                                        if let Some(subscription) = confirmed {
+                                            subscription.mark_live(true);
+                                            subscription.notify(FeedEvent::Subscribed {
+                                                kind: subscription.kind,
+                                                coin: subscription.coin.clone(),
+                                            });
                                         }
+                                        None
                                     }
-                                }
-                                Err(e) => {
-                                    debug!("parse error: {} (error msg: {})", text, e);
+                                    Ok(WsMessage::Error(message)) => {
+                                        let rejected = rejected_feed_key(&message).and_then(|key| subscriptions.get(&key));
+                                        let error = SubscriptionError::from_message(message);
+                                        match rejected {
+                                            Some(subscription) => {
+                                                error!("hl rejected {} subscription: {}", subscription.feed_key, error);
+                                                subscription.notify(FeedEvent::SubscriptionFailed {
+                                                    kind: subscription.kind,
+                                                    coin: subscription.coin.clone(),
+                                                    error,
+                                                });
+                                            }
+                                            None => {
+                                                error!("hl error on ws connection {}: {}", connection_id, error);
+                                            }
+                                        }
+                                        None
+                                    }
+                                    Ok(ws_message) => ws_message.feed_key().and_then(|key| {
+                                        let subscription = subscriptions.get(&key)?;
+                                        subscription.stats.record_message();
+                                        (!subscription.forward(&ws_message)).then_some(key)
+                                    }),
+                                    Err(e) => {
+                                        endpoint.payload_log.unparsable(connection_id, &text, &e);
+                                        None
+                                    }
+                                },
+                            };
+
+                            if let Some(key) = dropped {
+                                warn!("receiver dropped, unsubscribing {}", key);
+                                if let Some(subscription) = subscriptions.remove(&key) {
+                                    subscription.mark_live(false);
+                                    ws_sender.send(subscription.frame("unsubscribe")?).await?;
                                 }
                             }
                         }
                         Some(Ok(Message::Close(_))) => {
-                            info!("ws closed by server for {}", coin);
-                            break;
+                            info!("ws connection {} closed by server", connection_id);
+                            return Err(anyhow::anyhow!("closed by server"));
                         }
                         Some(Err(e)) => {
-                            error!("ws error for {}: {}", coin, e);
+                            error!("ws error on connection {}: {}", connection_id, e);
                             return Err(anyhow::anyhow!("ws error: {}", e));
                         }
                         None => {
-                            warn!("ws ended for {}", coin);
-                            break;
+                            warn!("ws connection {} ended", connection_id);
+                            return Err(anyhow::anyhow!("stream ended"));
                         }
                         _ => {
-                            debug!("received non-text message for {}", coin);
+                            debug!("received non-text message on ws connection {}", connection_id);
                         }
                     }
                 }
             }
         }
+    }
+
+    pub async fn active_feed_count(&self) -> usize {
+        self.pool.read().await.feeds.len()
+    }
+
+    pub async fn connection_count(&self) -> usize {
+        self.pool.read().await.connections.len()
+    }
+
+    pub async fn feed_statuses(&self) -> Vec<FeedStatus> {
+        let pool = self.pool.read().await;
+        let mut statuses: Vec<FeedStatus> = pool
+            .feeds
+            .values()
+            .map(|(connection_id, subscription)| subscription.status(*connection_id))
+            .collect();
+        statuses.sort_by(|a, b| a.feed_key.cmp(&b.feed_key));
+        statuses
+    }
+
+    /// Like `feed_statuses`, but only the feeds this handle's tenant listens to.
+    pub async fn tenant_feed_statuses(&self) -> Vec<FeedStatus> {
+        let pool = self.pool.read().await;
+        let mut statuses: Vec<FeedStatus> = pool
+            .feeds
+            .values()
+            .filter(|(_, subscription)| subscription.sinks().iter().any(|sink| sink.tenant == self.tenant))
+            .map(|(connection_id, subscription)| subscription.status(*connection_id))
+            .collect();
+        statuses.sort_by(|a, b| a.feed_key.cmp(&b.feed_key));
+        statuses
+    }
+
+    /// Unsubscribes and resubscribes a feed on its connection, resetting its stats.
+    pub async fn restart_feed(&self, feed_key: &str) -> anyhow::Result<()> {
+        let mut pool = self.pool.write().await;
+        let Some((connection_id, subscription)) = pool.feeds.remove(feed_key) else {
+            return Err(anyhow::anyhow!("no active ws for {}", feed_key));
+        };
+
+        if let Some(connection) = pool.connections.get_mut(&connection_id) {
+            connection.feed_keys.remove(feed_key);
+            let _ = connection.command_tx.send(ConnectionCommand::Unsubscribe(feed_key.to_string()));
+        }
+
+        let restarted = FeedSubscription {
+            started_at: Instant::now(),
+            stats: Arc::new(FeedStats::default()),
+            ..subscription
+        };
+        Self::assign(&mut pool, connection_id, restarted)?;
 
+        info!("restarted {} on ws connection {}", feed_key, connection_id);
         Ok(())
     }
 
-    pub async fn stop_trade_feed(&self, coin: &str) -> anyhow::Result<()> {
-        let coin = coin.to_uppercase();
-        
-        let mut websockets = self.active_websockets.write().await;
-        if let Some(handle) = websockets.remove(&coin) {
-            handle.shutdown().await;
-            Ok(())
-        } else {
-            warn!("no active ws for {}", coin);
-            Err(anyhow::anyhow!("no active ws for {}", coin))
+    pub async fn stop_trade_feed(&self, coin: &CoinSymbol) -> anyhow::Result<()> {
+        self.stop_feed(&feed_key(FeedKind::Trades, Some(coin))).await
+    }
+
+    pub async fn stop_book_feed(&self, coin: &CoinSymbol) -> anyhow::Result<()> {
+        self.stop_feed(&feed_key(FeedKind::L2Book, Some(coin))).await
+    }
+
+    async fn stop_feed(&self, feed_key: &str) -> anyhow::Result<()> {
+        let mut pool = self.pool.write().await;
+        let Some((_, subscription)) = pool.feeds.get(feed_key) else {
+            warn!("no active ws for {}", feed_key);
+            return Err(anyhow::anyhow!("no active ws for {}", feed_key));
+        };
 
+        // the upstream subscription stays while any other tenant still listens
+        let still_wanted = {
+            let mut sinks = subscription.sinks();
+            sinks.retain(|sink| sink.tenant != self.tenant);
+            !sinks.is_empty()
+        };
+        if still_wanted {
+            info!("tenant {} left {}", self.tenant, feed_key);
+            return Ok(());
         }
+
+        let Some((connection_id, _)) = pool.feeds.remove(feed_key) else {
+            return Ok(());
+        };
+
+        if let Some(connection) = pool.connections.get_mut(&connection_id) {
+            connection.feed_keys.remove(feed_key);
+            let _ = connection.command_tx.send(ConnectionCommand::Unsubscribe(feed_key.to_string()));
+        }
+
+        self.rebalance(&mut pool).await;
+        Ok(())
     }
-}
\ No newline at end of file
+
+    // closes empty connections and folds the least loaded ones into the rest
+    // whenever the remaining feeds fit on fewer connections
+    async fn rebalance(&self, pool: &mut ConnectionPool) {
+        let cap = self.max_subscriptions_per_connection;
+
+        loop {
+            let needed = pool.feeds.len().div_ceil(cap);
+            if pool.connections.len() <= needed {
+                break;
+            }
+
+            let Some((&victim_id, _)) = pool
+                .connections
+                .iter()
+                .min_by_key(|(_, connection)| connection.feed_keys.len())
+            else {
+                break;
+            };
+
+            let Some(victim) = pool.connections.remove(&victim_id) else {
+                break;
+            };
+
+            for feed_key in &victim.feed_keys {
+                let Some((_, subscription)) = pool.feeds.remove(feed_key) else {
+                    continue;
+                };
+
+                let target_id = pool
+                    .connections
+                    .iter()
+                    .filter(|(_, connection)| connection.feed_keys.len() < cap)
+                    .max_by_key(|(_, connection)| connection.feed_keys.len())
+                    .map(|(&id, _)| id);
+
+                match target_id {
+                    Some(target_id) => {
+                        if let Err(e) = Self::assign(pool, target_id, subscription) {
+                            error!("couldn't move {} off ws connection {}: {}", feed_key, victim_id, e);
+                        }
+                    }
+                    None => {
+                        error!("no room to move {} off ws connection {}", feed_key, victim_id);
+                    }
+                }
+            }
+
+            let _ = victim.shutdown_tx.send(()).await;
+            info!("closed ws connection {} while rebalancing", victim_id);
+        }
+    }
+}
+
+#[derive(Deserialize)]
+struct WsFrame<'a> {
+    channel: &'a str,
+    #[serde(borrow)]
+    data: &'a RawValue,
+}
+
+// trades frames are by far the busiest, so they're parsed borrowing from the
+// frame text instead of through WsMessage
+pub fn trades_frame(text: &str) -> Option<Vec<WsTradeRef<'_>>> {
+    let frame: WsFrame = serde_json::from_str(text).ok()?;
+    if frame.channel != "trades" {
+        return None;
+    }
+    serde_json::from_str(frame.data.get()).ok()
+}
+
+// the feed's key if its receiver has gone
+fn route_trades(
+    subscriptions: &HashMap<String, FeedSubscription>,
+    frame: &str,
+    trades: &[WsTradeRef<'_>],
+) -> Option<String> {
+    let (key, subscription) = trades_subscription(subscriptions, trades.first()?.coin)?;
+    subscription.stats.record_message();
+    let delivered = subscription.forward_trades(frame, trades);
+    (!delivered).then(|| key.clone())
+}
+
+// trades feeds are keyed by the uppercase coin, which is what hl sends for
+// the main dex; builder dexes come as `xyz:XYZ100`, uppercased on the stack
+fn trades_subscription<'a>(
+    subscriptions: &'a HashMap<String, FeedSubscription>,
+    coin: &str,
+) -> Option<(&'a String, &'a FeedSubscription)> {
+    if let Some(found) = subscriptions.get_key_value(coin) {
+        return Some(found);
+    }
+    let mut buffer = [0u8; 64];
+    match buffer.get_mut(..coin.len()) {
+        Some(upper) if coin.is_ascii() => {
+            upper.copy_from_slice(coin.as_bytes());
+            upper.make_ascii_uppercase();
+            subscriptions.get_key_value(std::str::from_utf8(upper).ok()?)
+        }
+        _ => subscriptions.get_key_value(&coin.to_uppercase()),
+    }
+}
+
+impl WsMessage {
+    // the feed a data frame belongs to, matching FeedSubscription::feed_key
+    fn feed_key(&self) -> Option<String> {
+        match self {
+            WsMessage::L2Book(book) => Some(feed_key(FeedKind::L2Book, Some(&book.coin))),
+            WsMessage::AllMids(_) => Some(feed_key(FeedKind::AllMids, None)),
+            WsMessage::SubscriptionResponse(_) | WsMessage::Error(_) => None,
+        }
+    }
+}
+
+// hl error frames embed the offending subscription json, e.g.
+// `Invalid subscription {"type":"trades","coin":"FAKE"}`
+fn rejected_feed_key(message: &str) -> Option<String> {
+    let start = message.find('{')?;
+    let end = message.rfind('}')?;
+    let subscription: WsSubscriptionData = serde_json::from_str(message.get(start..=end)?).ok()?;
+    subscription.feed_key()
+}
+
+fn feed_key(kind: FeedKind, coin: Option<&str>) -> String {
+    match (kind, coin) {
+        (FeedKind::Trades, Some(coin)) => coin.to_string(),
+        (FeedKind::L2Book, Some(coin)) => format!("{}:l2Book", coin),
+        (kind, _) => kind.subscription_type().to_string(),
+    }
+}
@@ -0,0 +1,79 @@
+use anyhow::Result;
+use std::collections::HashMap;
+use tokio::time::{Duration, Instant};
+use crate::{
+    config::ImbalanceConfig,
+    database::{Database, ImbalanceAlert},
+    hyperliquid::{BookImbalance, CoinSymbol, WsBook},
+};
+
+// keeps opted-in users per coin in memory since l2Book updates arrive
+// far too often to hit the db on each one
+pub struct ImbalanceMonitor {
+    database: Database,
+    config: ImbalanceConfig,
+    watchers: HashMap<CoinSymbol, Vec<ImbalanceAlert>>,
+    last_alerted: HashMap<(i64, CoinSymbol), Instant>,
+}
+
+impl ImbalanceMonitor {
+    pub fn new(database: Database, config: ImbalanceConfig) -> Self {
+        ImbalanceMonitor {
+            database,
+            config,
+            watchers: HashMap::new(),
+            last_alerted: HashMap::new(),
+        }
+    }
+
+    pub fn band_bps(&self) -> f64 {
+        self.config.band_bps
+    }
+
+    /// Reloads the watchers for `coin`, returning whether anyone still watches it.
+    pub async fn reload_coin(&mut self, coin: &CoinSymbol) -> Result<bool> {
+        let alerts = self.database.get_imbalance_alerts_for_coin(coin).await?;
+
+        if alerts.is_empty() {
+            self.watchers.remove(coin);
+            self.last_alerted.retain(|(_, alerted_coin), _| alerted_coin != coin);
+            return Ok(false);
+        }
+
+        self.watchers.insert(coin.clone(), alerts);
+        Ok(true)
+    }
+
+    /// Watchers whose threshold this book crosses and who aren't in cooldown.
+    pub fn evaluate(&mut self, book: &WsBook) -> Vec<(ImbalanceAlert, BookImbalance)> {
+        let Some(watchers) = self.watchers.get(&book.coin) else {
+            return Vec::new();
+        };
+        let Some(imbalance) = book.imbalance_within_bps(self.config.band_bps) else {
+            return Vec::new();
+        };
+
+        let cooldown = Duration::from_secs(self.config.cooldown_secs);
+        let mut triggered = Vec::new();
+
+        for watcher in watchers {
+            if imbalance.dominant_share() * 100.0 < watcher.threshold_pct {
+                continue;
+            }
+
+            let key = (watcher.telegram_user_id, watcher.coin.clone());
+            let cooling_down = self
+                .last_alerted
+                .get(&key)
+                .is_some_and(|last| last.elapsed() < cooldown);
+            if cooling_down {
+                continue;
+            }
+
+            self.last_alerted.insert(key, Instant::now());
+            triggered.push((watcher.clone(), imbalance));
+        }
+
+        triggered
+    }
+}
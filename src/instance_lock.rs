@@ -0,0 +1,108 @@
+use anyhow::Result;
+use sqlx::PgConnection;
+use tokio::time::{interval, Duration, MissedTickBehavior};
+use tracing::{error, info, warn};
+use crate::database::{self, Database};
+
+// the numeric half of the token, so the secret half never goes to the db
+fn bot_id(bot_token: &str) -> &str {
+    bot_token.split(':').next().unwrap_or(bot_token)
+}
+
+/// A postgres advisory lock on the bot's token, held for as long as the
+/// process runs the bot. Two copies against the same database and token
+/// would both send every alert; the second can't take the lock and stops.
+pub struct InstanceLock {
+    database: Database,
+    key: String,
+    connection: PgConnection,
+}
+
+impl InstanceLock {
+    pub async fn acquire(database: &Database, bot_token: &str) -> Result<Self> {
+        let key = format!("bot:{}", bot_id(bot_token));
+        let mut connection = database.dedicated_connection().await?;
+        if !database::try_advisory_lock(&mut connection, &key).await? {
+            anyhow::bail!(
+                "another instance of bot {} is already running against this database; stop it first, \
+                 or set database.instance_lock = false if running two is intended",
+                bot_id(bot_token)
+            );
+        }
+
+        info!("holding the instance lock for bot {}", bot_id(bot_token));
+        Ok(InstanceLock {
+            database: database.clone(),
+            key,
+            connection,
+        })
+    }
+
+    /// Standby: waits, connected, for the running instance's lock to come
+    /// free, which happens as soon as its db session ends.
+    pub async fn wait(database: &Database, bot_token: &str, poll: Duration) -> Result<Self> {
+        let key = format!("bot:{}", bot_id(bot_token));
+        info!("standing by for bot {}, taking over once the running instance stops", bot_id(bot_token));
+
+        let mut connection = database.dedicated_connection().await?;
+        let mut ticker = interval(poll);
+        ticker.set_missed_tick_behavior(MissedTickBehavior::Delay);
+        loop {
+            ticker.tick().await;
+            match database::try_advisory_lock(&mut connection, &key).await {
+                Ok(true) => break,
+                Ok(false) => {}
+                Err(e) => {
+                    warn!("standby couldn't check the lock for bot {}: {}", bot_id(bot_token), e);
+                    // the connection may be what failed
+                    if let Ok(fresh) = database.dedicated_connection().await {
+                        connection = fresh;
+                    }
+                }
+            }
+        }
+
+        warn!("bot {} is no longer running elsewhere, taking over", bot_id(bot_token));
+        Ok(InstanceLock {
+            database: database.clone(),
+            key,
+            connection,
+        })
+    }
+
+    /// Keeps an eye on the lock's connection, whose loss would release the
+    /// lock, and takes it again after a drop. If another instance got there
+    /// first this one exits rather than double up alerts. Checked `every`
+    /// standby poll, so a standby can't take over and send alongside this
+    /// one for longer than that.
+    pub fn spawn_watch(mut self, every: Duration) {
+        tokio::spawn(async move {
+            let mut ticker = interval(every);
+            ticker.set_missed_tick_behavior(MissedTickBehavior::Delay);
+            ticker.tick().await;
+            loop {
+                ticker.tick().await;
+                if sqlx::query("SELECT 1").execute(&mut self.connection).await.is_ok() {
+                    continue;
+                }
+
+                warn!("lost the connection holding the instance lock for {}, taking it again", self.key);
+                if let Err(e) = self.retake().await {
+                    // the db is still down, so nobody else can take it either
+                    warn!("couldn't take the instance lock for {} again: {}", self.key, e);
+                }
+            }
+        });
+    }
+
+    async fn retake(&mut self) -> Result<()> {
+        let mut connection = self.database.dedicated_connection().await?;
+        if !database::try_advisory_lock(&mut connection, &self.key).await? {
+            error!("another instance took the lock for {} while the db was unreachable, exiting", self.key);
+            std::process::exit(1);
+        }
+        info!("instance lock for {} taken again", self.key);
+        self.connection = connection;
+        Ok(())
+    }
+}
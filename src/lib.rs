@@ -0,0 +1,59 @@
+//! The bot's modules, split out of the binary so benches and integration
+//! tests can reach them. `main.rs` only wires them together.
+
+pub mod activity;
+pub mod alert_digest;
+pub mod archive;
+pub mod backtest;
+pub mod bench;
+pub mod backup;
+pub mod budget;
+pub mod candles;
+pub mod catch_up;
+#[cfg(feature = "chaos")]
+pub mod chaos;
+pub mod chart;
+pub mod clustering;
+pub mod config;
+pub mod formatting;
+pub mod funding;
+pub mod heartbeat;
+pub mod history;
+pub mod market;
+pub mod market_alerts;
+pub mod scheduler;
+pub mod fx;
+pub mod imbalance;
+pub mod instance_lock;
+pub mod liquidations;
+pub mod metrics;
+pub mod metrics_history;
+pub mod mutes;
+pub mod outbound;
+pub mod payload_log;
+pub mod prices;
+pub mod ranges;
+pub mod price_alerts;
+pub mod recording;
+pub mod redact;
+pub mod roles;
+pub mod schema;
+pub mod secrets;
+pub mod database;
+pub mod dedup;
+pub mod errors;
+pub mod experiments;
+pub mod failover;
+pub mod feed_health;
+pub mod delivery;
+pub mod digest;
+pub mod volatility;
+pub mod vwap;
+pub mod warmup;
+pub mod watchdog;
+pub mod telegram;
+pub mod ticker;
+pub mod tuning;
+pub mod wallets;
+pub mod hyperliquid;
+pub mod coordinator;
@@ -0,0 +1,32 @@
+use crate::hyperliquid::WsTrade;
+
+/// Which side of the book got liquidated in a fill.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LiquidatedSide {
+    Longs,
+    Shorts,
+}
+
+impl LiquidatedSide {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            LiquidatedSide::Longs => "Longs liquidated",
+            LiquidatedSide::Shorts => "Shorts liquidated",
+        }
+    }
+}
+
+// the public trades feed doesn't flag liquidations, but the liquidator shows up
+// as one side of the fill: it buys out liquidated longs and sells to close shorts
+pub fn classify(trade: &WsTrade, liquidators: &[String]) -> Option<LiquidatedSide> {
+    let [buyer, seller] = trade.users.as_ref()?;
+    let is_liquidator = |address: &str| liquidators.iter().any(|l| l.eq_ignore_ascii_case(address));
+
+    if is_liquidator(buyer) {
+        Some(LiquidatedSide::Longs)
+    } else if is_liquidator(seller) {
+        Some(LiquidatedSide::Shorts)
+    } else {
+        None
+    }
+}
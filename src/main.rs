@@ -1,69 +1,247 @@
 use anyhow::Result;
+use std::sync::Arc;
+use tokio::time::{Duration, Instant};
 use tracing::{info, error};
-use tracing_subscriber;
 
-mod config;
-mod database;
-mod telegram;
-mod hyperliquid;
-mod coordinator;
-
-use config::Config;
-use telegram::TelegramBot;
-use hyperliquid::{HyperliquidClient, WebSocketManager};
-use coordinator::TradeCoordinator;
+use hl_tg_bot::{archive, backtest, bench, database, hyperliquid, redact, scheduler};
+#[cfg(feature = "chaos")]
+use hl_tg_bot::chaos;
+use hl_tg_bot::activity::ActivityTracker;
+use hl_tg_bot::candles::CandleCache;
+use hl_tg_bot::config::Config;
+use hl_tg_bot::digest::WeeklyDigest;
+use hl_tg_bot::feed_health::FeedHealthMonitor;
+use hl_tg_bot::funding::FundingReporter;
+use hl_tg_bot::heartbeat::HeartbeatNotifier;
+use hl_tg_bot::instance_lock::InstanceLock;
+use hl_tg_bot::fx::FxRates;
+use hl_tg_bot::market_alerts::MarketContextMonitor;
+use hl_tg_bot::metrics::Metrics;
+use hl_tg_bot::metrics_history::MetricsHistory;
+use hl_tg_bot::mutes::MuteList;
+use hl_tg_bot::outbound::OutboundQueue;
+use hl_tg_bot::payload_log::PayloadLog;
+use hl_tg_bot::prices::PriceEngine;
+use hl_tg_bot::ranges::RangeMonitor;
+use hl_tg_bot::roles::RoleDirectory;
+use hl_tg_bot::scheduler::ReportScheduler;
+use hl_tg_bot::telegram::TelegramBot;
+use hl_tg_bot::tuning::AutoTuner;
+use hl_tg_bot::wallets::{LedgerMonitor, WalletReporter};
+use hl_tg_bot::hyperliquid::{CertPins, HyperliquidClient, WebSocketManager};
+use hl_tg_bot::coordinator::TradeCoordinator;
+use hl_tg_bot::watchdog::Watchdog;
 
 #[tokio::main]
-async fn main() -> Result<()> {
+async fn main() {
+    // the default error report would print config-derived strings unscrubbed
+    if let Err(e) = run().await {
+        eprintln!("Error: {}", redact::redact(&format!("{:?}", e)));
+        std::process::exit(1);
+    }
+}
+
+async fn run() -> Result<()> {
+    // loaded first so the profile can set the log level
+    let config = Config::load().await?;
+    let tenants = config.load_tenants().await?;
+    redact::init(std::iter::once(&config).chain(tenants.iter().map(|(_, tenant)| tenant)));
+
     tracing_subscriber::fmt()
-        .with_env_filter("hyperliquid_telegram_bot=debug,info")
+        .with_env_filter(config.logging.level.as_str())
+        .with_writer(redact::RedactingMakeWriter::new(std::io::stdout))
         .init();
 
-    info!("Starting Hyperliquid Telegram Bot");
+    #[cfg(feature = "chaos")]
+    chaos::init(&config.chaos);
+    #[cfg(not(feature = "chaos"))]
+    if config.chaos.is_active() {
+        tracing::warn!("[chaos] is set but this build doesn't have the chaos feature, ignoring it");
+    }
 
-    let config = Config::load()?;
-    info!("config loaded");
+    let started_at = Instant::now();
+    info!("Starting Hyperliquid Telegram Bot");
+    info!("config loaded ({} profile)", Config::environment().as_deref().unwrap_or("base"));
 
     let db = database::init(&config.database).await?;
     info!("connected to db");
 
-    let hyperliquid_client = HyperliquidClient::new(config.hyperliquid.clone());
+    // operator subcommands run against the db and exit without starting the bot
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    let standby = args.iter().any(|arg| arg == "--standby");
+    if !standby {
+        db.migrate().await?;
+    }
+    if archive::run_subcommand(&db, &args).await?
+        || backtest::run_subcommand(&config, &db, &args).await?
+        || bench::run_subcommand(&db, &args).await?
+    {
+        return Ok(());
+    }
+
+    // a second copy of the same bot would send every alert twice; a standby
+    // waits here, connected, until the running one stops, and only then
+    // migrates, so a newer build can't change the schema under it
+    if standby && !config.database.instance_lock {
+        anyhow::bail!("--standby needs database.instance_lock, it's how the running instance is watched");
+    }
+    if config.database.instance_lock {
+        let poll = Duration::from_secs(config.database.standby_poll_secs.max(1));
+        let configs = std::iter::once(&config).chain(tenants.iter().map(|(_, tenant)| tenant));
+        for bot_config in configs {
+            let lock = if standby {
+                InstanceLock::wait(&db, &bot_config.telegram.bot_token, poll).await?
+            } else {
+                InstanceLock::acquire(&db, &bot_config.telegram.bot_token).await?
+            };
+            lock.spawn_watch(poll);
+        }
+    }
+    if standby {
+        db.migrate().await?;
+    }
+
+    let cert_pins = CertPins::new(&config.hyperliquid.tls)?;
+    let hyperliquid_client = HyperliquidClient::new(config.hyperliquid.clone(), cert_pins.clone())?;
     info!("hl client init success");
 
-    let ws_manager = WebSocketManager::new(config.hyperliquid.websocket_url.clone());
+    let (feed_event_tx, feed_event_rx) = tokio::sync::mpsc::unbounded_channel();
+    let ws_manager = Arc::new(WebSocketManager::new(
+        config.hyperliquid.websocket_url.clone(),
+        cert_pins,
+        PayloadLog::new(config.hyperliquid.payload_logging.clone()),
+        config.hyperliquid.max_subscriptions_per_connection,
+        feed_event_tx,
+        hyperliquid_client.clone(),
+    ));
     info!("hl ws init success");
 
-    // Create dummy telegram bot for coordinator
-    let dummy_bot = TelegramBot::new(
-        config.telegram.bot_token.clone(),
+    let fx_rates = FxRates::new(config.fx.clone());
+    fx_rates.spawn_refresh_task();
+
+    let shared = Shared {
+        hyperliquid_client: hyperliquid_client.clone(),
+        fx_rates,
+        candles: CandleCache::new(hyperliquid_client, config.candles.clone()),
+        started_at,
+    };
+
+    let mut bots = Vec::new();
+    for (tenant, tenant_config) in tenants {
+        let tenant_db = db.for_tenant(&tenant_config.database, &tenant).await?;
+        let (feed_event_tx, feed_event_rx) = tokio::sync::mpsc::unbounded_channel();
+        let tenant_ws = Arc::new(ws_manager.for_tenant(&tenant, feed_event_tx));
+        bots.push(start_bot(tenant_config, tenant_db, &shared, tenant_ws, feed_event_rx).await?);
+        info!("tenant {} ready", tenant);
+    }
+    bots.push(start_bot(config, db, &shared, ws_manager, feed_event_rx).await?);
+
+    futures_util::future::try_join_all(bots.iter().map(|bot| bot.start())).await?;
+
+    Ok(())
+}
+
+// what every tenant's bot uses the same instance of
+struct Shared {
+    hyperliquid_client: HyperliquidClient,
+    fx_rates: FxRates,
+    candles: CandleCache,
+    started_at: Instant,
+}
+
+// everything one bot runs besides its telegram polling, which the caller starts
+async fn start_bot(
+    config: Config,
+    db: database::Database,
+    shared: &Shared,
+    ws_manager: Arc<WebSocketManager>,
+    feed_event_rx: tokio::sync::mpsc::UnboundedReceiver<hyperliquid::FeedEvent>,
+) -> Result<TelegramBot> {
+    let hyperliquid_client = shared.hyperliquid_client.clone();
+    let metrics = Metrics::new(config.metrics.latency_window);
+    let outbound = OutboundQueue::spawn(config.outbound.clone());
+    let roles = RoleDirectory::load(db.clone(), config.telegram.admin_user_ids.clone()).await?;
+    let mutes = MuteList::load(db.clone()).await?;
+    let activity = ActivityTracker::new();
+    let (revisit_tx, revisit_rx) = tokio::sync::mpsc::unbounded_channel();
+    let price_engine = PriceEngine::new(config.revisit.clone());
+    if let Err(e) = price_engine.start(&ws_manager, revisit_tx).await {
+        error!("couldn't start price engine: {}", e);
+    }
+
+    // the coordinator alerts through a clone of the command bot, so both see
+    // the same caches; only the command side needs a way back to it
+    let telegram_bot = TelegramBot::new(
+        config.clone(),
         db.clone(),
         hyperliquid_client.clone(),
-        tokio::sync::mpsc::unbounded_channel().0
+        tokio::sync::mpsc::unbounded_channel().0,
+        ws_manager.clone(),
+        shared.fx_rates.clone(),
+        price_engine.clone(),
+        metrics.clone(),
+        shared.candles.clone(),
+        outbound,
+        roles,
+        mutes,
+        activity.clone(),
+        shared.started_at,
     );
+    // before polling and the coordinator start, so neither the first commands
+    // nor the first alerts after a deploy are the ones paying for it
+    telegram_bot.warm_up().await?;
 
-    let (coordinator, event_sender, event_receiver) = TradeCoordinator::new(
+    let (coordinator, event_sender, inbox) = TradeCoordinator::new(
         db.clone(),
-        dummy_bot,
-        ws_manager,
-        config.clone()
+        telegram_bot.clone(),
+        ws_manager.clone(),
+        config.clone(),
+        metrics.clone(),
+        price_engine.clone(),
+        shared.candles.clone(),
     );
     info!("coordinator ready");
 
-    let telegram_bot = TelegramBot::new(
-        config.telegram.bot_token.clone(), 
+    let telegram_bot = telegram_bot.with_event_sender(event_sender);
+    info!("tg bot ready");
+
+    FeedHealthMonitor::spawn(config.feed_health.clone(), ws_manager, metrics.clone());
+    MetricsHistory::spawn(db.clone(), metrics, activity, config.metrics.clone());
+
+    FundingReporter::spawn(db.clone(), hyperliquid_client.clone(), telegram_bot.clone());
+    WeeklyDigest::spawn(db.clone(), hyperliquid_client.clone(), telegram_bot.clone());
+    MarketContextMonitor::spawn(config.market_alerts.clone(), db.clone(), hyperliquid_client.clone(), telegram_bot.clone());
+    RangeMonitor::spawn(
+        config.range_alerts.clone(),
         db.clone(),
-        hyperliquid_client,
-        event_sender
+        shared.candles.clone(),
+        price_engine,
+        telegram_bot.clone(),
+    );
+    WalletReporter::spawn(config.wallet_summaries.clone(), db.clone(), hyperliquid_client.clone(), telegram_bot.clone());
+    LedgerMonitor::spawn(config.ledger_alerts.clone(), db.clone(), hyperliquid_client.clone(), telegram_bot.clone());
+    HeartbeatNotifier::spawn(
+        db.clone(),
+        hyperliquid_client.clone(),
+        telegram_bot.clone(),
+        config.defaults.min_trade_value_usd,
+    );
+    ReportScheduler::spawn(&config, db.clone(), hyperliquid_client, telegram_bot.clone());
+    scheduler::spawn_daily_budget_reset(coordinator.alert_budget(), telegram_bot.clone());
+    scheduler::spawn_trade_rollups(db.clone());
+    AutoTuner::spawn(db.clone(), telegram_bot.clone(), config.defaults.min_trade_value_usd);
+    Watchdog::spawn(
+        coordinator.heartbeat(),
+        config.watchdog.clone(),
+        telegram_bot.clone(),
+        config.telegram.admin_user_ids.clone(),
     );
-    info!("tg bot ready");
 
     tokio::spawn(async move {
-        if let Err(e) = coordinator.start(event_receiver).await {
+        if let Err(e) = coordinator.start(inbox, feed_event_rx, revisit_rx).await {
             error!("coordinator error: {}", e);
         }
     });
 
-    telegram_bot.start().await?;
-
-    Ok(())
+    Ok(telegram_bot)
 }
\ No newline at end of file
@@ -0,0 +1,216 @@
+use anyhow::Result;
+use std::collections::HashMap;
+use std::sync::{Arc, RwLock};
+use tokio::time::{Duration, Instant};
+use tracing::warn;
+use crate::{
+    activity::{ActivityTracker, LiveActivity},
+    config::LinksConfig,
+    database::{Database, LiquidationTotals},
+    formatting,
+    hyperliquid::{AssetCtx, AssetInfo, HyperliquidClient},
+};
+
+// leverage and margin mode rarely change
+const ASSET_META_TTL: Duration = Duration::from_secs(6 * 60 * 60);
+const ASSET_META_RETRY: Duration = Duration::from_secs(60);
+
+// liquidation totals in snapshots cover this trailing window
+const LIQUIDATION_WINDOW_HOURS: i64 = 24;
+
+// hl pays funding hourly
+const FUNDING_PERIODS_PER_YEAR: f64 = 24.0 * 365.0;
+
+#[derive(Debug, Clone)]
+pub struct MarketSnapshot {
+    pub coin: String,
+    pub mark_px: String,
+    pub change_24h: Option<f64>,
+    pub volume_24h_usd: f64,
+    pub open_interest_usd: f64,
+    /// Hourly rate as a fraction.
+    pub funding: f64,
+    pub liquidations: Option<LiquidationTotals>,
+    /// From the trade feed, for coins someone's subscribed to.
+    pub live: Option<LiveActivity>,
+    pub asset: AssetInfo,
+}
+
+impl MarketSnapshot {
+    fn from_ctx(asset: AssetInfo, ctx: AssetCtx) -> Self {
+        let mark_px = ctx.mark_px.parse::<f64>().unwrap_or(0.0);
+        let prev_day_px = ctx.prev_day_px.parse::<f64>().unwrap_or(0.0);
+        let change_24h = (prev_day_px > 0.0).then(|| (mark_px - prev_day_px) / prev_day_px * 100.0);
+
+        MarketSnapshot {
+            coin: asset.name.clone(),
+            change_24h,
+            volume_24h_usd: ctx.day_ntl_vlm.parse().unwrap_or(0.0),
+            open_interest_usd: ctx.open_interest.parse::<f64>().unwrap_or(0.0) * mark_px,
+            funding: ctx.funding.parse().unwrap_or(0.0),
+            mark_px: ctx.mark_px,
+            liquidations: None,
+            live: None,
+            asset,
+        }
+    }
+}
+
+/// Snapshots for `coins`, or the `top_n` coins by 24h volume when `coins` is empty.
+pub async fn snapshots(client: &HyperliquidClient, coins: &[String], top_n: usize) -> Result<Vec<MarketSnapshot>> {
+    let mut snapshots: Vec<MarketSnapshot> = client
+        .asset_contexts()
+        .await?
+        .into_iter()
+        .filter(|(asset, _)| coins.is_empty() || coins.iter().any(|c| c.eq_ignore_ascii_case(&asset.name)))
+        .map(|(asset, ctx)| MarketSnapshot::from_ctx(asset, ctx))
+        .collect();
+
+    snapshots.sort_by(|a, b| b.volume_24h_usd.total_cmp(&a.volume_24h_usd));
+    if coins.is_empty() {
+        snapshots.truncate(top_n);
+    }
+    Ok(snapshots)
+}
+
+/// Fills in 24h long/short liquidation totals when liquidators are configured.
+pub async fn attach_liquidations(database: &Database, liquidators: &[String], snapshots: &mut [MarketSnapshot]) -> Result<()> {
+    if liquidators.is_empty() || snapshots.is_empty() {
+        return Ok(());
+    }
+
+    let coins: Vec<String> = snapshots.iter().map(|s| s.coin.clone()).collect();
+    let mut totals = database.liquidation_totals(&coins, liquidators, LIQUIDATION_WINDOW_HOURS).await?;
+    for snapshot in snapshots {
+        snapshot.liquidations = Some(totals.remove(&snapshot.coin).unwrap_or_default());
+    }
+    Ok(())
+}
+
+/// Fills in the feed's rolling 24h activity for coins it's tracking.
+pub fn attach_live_activity(activity: &ActivityTracker, snapshots: &mut [MarketSnapshot]) {
+    for snapshot in snapshots {
+        snapshot.live = activity.get(&snapshot.coin);
+    }
+}
+
+pub fn format_snapshot(title: &str, snapshots: &[MarketSnapshot], links: &LinksConfig) -> String {
+    let mut message = title.to_string();
+    for snapshot in snapshots {
+        message.push_str(&format!("\n\n{}: ${}", snapshot.coin, formatting::format_price(&snapshot.mark_px)));
+        if let Some(change) = snapshot.change_24h {
+            message.push_str(&format!(" ({})", formatting::format_percent_change(change)));
+        }
+        // the feed's own count once it has a full day, since the rest context can lag
+        let volume = match snapshot.live {
+            Some(live) if live.covers_day() => live.volume_usd,
+            _ => snapshot.volume_24h_usd,
+        };
+        message.push_str(&format!(
+            "\nVol {} | OI {}\nFunding {:.4}%/h ({:.1}% APR)",
+            formatting::format_usd(volume, false),
+            formatting::format_usd(snapshot.open_interest_usd, false),
+            snapshot.funding * 100.0,
+            snapshot.funding * FUNDING_PERIODS_PER_YEAR * 100.0
+        ));
+        let decimals = snapshot.asset.sz_decimals as usize;
+        message.push_str(&format!(
+            "\n{} | Size step {:.*}",
+            leverage_text(&snapshot.asset),
+            decimals,
+            10f64.powi(-(decimals as i32))
+        ));
+        if let Some(liquidations) = snapshot.liquidations {
+            message.push_str(&format!(
+                "\nLiqs 24h: longs {} | shorts {}",
+                formatting::format_usd(liquidations.longs_usd, false),
+                formatting::format_usd(liquidations.shorts_usd, false)
+            ));
+        }
+        if let Some(live) = snapshot.live {
+            message.push_str(&format!(
+                "\nLive {}: {} trades, {} | pace {}/h",
+                live.window_text(),
+                live.trades,
+                formatting::format_usd(live.volume_usd, false),
+                formatting::format_usd(live.pace_per_hour, false)
+            ));
+        }
+        // checked when the config loaded
+        if let Ok(Some(url)) = links.trade_url(&snapshot.coin) {
+            message.push_str(&format!("\n{}", url));
+        }
+    }
+    message
+}
+
+/// "Max Leverage 50x", noting isolated-only markets.
+pub fn leverage_text(asset: &AssetInfo) -> String {
+    let mut text = format!("Max Leverage {}x", asset.max_leverage);
+    if asset.only_isolated {
+        text.push_str(" (isolated only)");
+    }
+    text
+}
+
+/// Static per-coin metadata for alerts, refetched every few hours.
+#[derive(Clone)]
+pub struct AssetMetadata {
+    client: HyperliquidClient,
+    // only held to read or swap the map, never across a fetch
+    cache: Arc<RwLock<AssetMetaCache>>,
+}
+
+struct AssetMetaCache {
+    assets: HashMap<String, AssetInfo>,
+    refresh_at: Instant,
+}
+
+impl AssetMetadata {
+    pub fn new(client: HyperliquidClient) -> Self {
+        AssetMetadata {
+            client,
+            cache: Arc::new(RwLock::new(AssetMetaCache {
+                assets: HashMap::new(),
+                refresh_at: Instant::now(),
+            })),
+        }
+    }
+
+    /// Fills the cache up front; how many assets it now holds.
+    pub async fn warm(&self) -> Result<usize> {
+        let contexts = self.client.asset_contexts().await?;
+        let mut cache = self.cache.write().map_err(|_| anyhow::anyhow!("asset metadata cache poisoned"))?;
+        cache.assets = contexts.into_iter().map(|(asset, _)| (asset.name.clone(), asset)).collect();
+        cache.refresh_at = Instant::now() + ASSET_META_TTL;
+        Ok(cache.assets.len())
+    }
+
+    /// The coin's metadata. Once the cache is due, the first caller refetches
+    /// it while the rest carry on with what's there.
+    pub async fn get(&self, coin: &str) -> Option<AssetInfo> {
+        let due = {
+            let mut cache = self.cache.write().ok()?;
+            let due = cache.refresh_at <= Instant::now();
+            if due {
+                // claims the refresh; it's also when a failed one is retried
+                cache.refresh_at = Instant::now() + ASSET_META_RETRY;
+            }
+            due
+        };
+
+        if due {
+            match self.client.asset_contexts().await {
+                Ok(contexts) => {
+                    if let Ok(mut cache) = self.cache.write() {
+                        cache.assets = contexts.into_iter().map(|(asset, _)| (asset.name.clone(), asset)).collect();
+                        cache.refresh_at = Instant::now() + ASSET_META_TTL;
+                    }
+                }
+                // keep serving what we had, and don't retry on every alert
+                Err(e) => warn!("couldn't refresh asset metadata: {}", e),
+            }
+        }
+        self.cache.read().ok()?.assets.get(&coin.to_uppercase()).cloned()
+    }
+}
@@ -0,0 +1,220 @@
+use anyhow::Result;
+use std::collections::{HashMap, HashSet};
+use tokio::time::{interval, Duration, MissedTickBehavior};
+use tracing::{error, info, warn};
+use crate::{
+    config::MarketAlertsConfig,
+    database::{Database, UserSettings},
+    delivery::{self, AlertTarget},
+    formatting,
+    hyperliquid::{CoinSymbol, HyperliquidClient},
+    outbound::Priority,
+    telegram::TelegramBot,
+};
+
+// funding has to ease back this far below the warning level before it can
+// warn again, so a rate sitting on the line doesn't alert every poll
+const FUNDING_REARM_FRACTION: f64 = 0.8;
+// likewise for mark/oracle divergence
+const DIVERGENCE_REARM_FRACTION: f64 = 0.5;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum MarketWarning {
+    Funding(f64),
+    OpenInterest(f64),
+    Divergence { mark_px: f64, oracle_px: f64 },
+}
+
+impl MarketWarning {
+    // divergence is noisier than the caps, so only users who asked get it
+    fn opted_in(&self, settings: &UserSettings) -> bool {
+        match self {
+            MarketWarning::Divergence { .. } => settings.divergence_alerts,
+            _ => true,
+        }
+    }
+
+    fn message(&self, coin: &str, funding_cap: f64) -> String {
+        match *self {
+            MarketWarning::Funding(funding) => format!(
+                "{} Funding Cap Warning\n\nFunding is {:.4}%/h, {:.0}% of the {:.2}%/h cap\n{} are paying {}",
+                coin,
+                funding * 100.0,
+                funding.abs() / funding_cap * 100.0,
+                funding_cap * 100.0,
+                if funding > 0.0 { "Longs" } else { "Shorts" },
+                if funding > 0.0 { "shorts" } else { "longs" }
+            ),
+            MarketWarning::OpenInterest(open_interest_usd) => format!(
+                "{} Open Interest Cap\n\nOpen interest is at Hyperliquid's cap ({}). New positions can't open until it comes down.",
+                coin,
+                formatting::format_usd(open_interest_usd, false)
+            ),
+            MarketWarning::Divergence { mark_px, oracle_px } => format!(
+                "{} Mark/Oracle Divergence\n\nMark: ${}\nOracle: ${}\nMark is {:.2}% {} oracle, often a squeeze or a broken peg",
+                coin,
+                formatting::format_price(&mark_px.to_string()),
+                formatting::format_price(&oracle_px.to_string()),
+                divergence_pct(mark_px, oracle_px).abs(),
+                if mark_px > oracle_px { "above" } else { "below" }
+            ),
+        }
+    }
+}
+
+fn divergence_pct(mark_px: f64, oracle_px: f64) -> f64 {
+    (mark_px - oracle_px) / oracle_px * 100.0
+}
+
+struct CoinContext {
+    funding: f64,
+    open_interest_usd: f64,
+    mark_px: f64,
+    oracle_px: f64,
+}
+
+// polls asset contexts and warns a coin's subscribers as it reaches the
+// funding or open interest cap, and opted-in ones as its mark strays from
+// its oracle, once per crossing
+pub struct MarketContextMonitor {
+    config: MarketAlertsConfig,
+    database: Database,
+    hyperliquid_client: HyperliquidClient,
+    telegram_bot: TelegramBot,
+    funding_warned: HashSet<String>,
+    oi_capped: HashSet<String>,
+    diverged: HashSet<String>,
+}
+
+impl MarketContextMonitor {
+    pub fn spawn(config: MarketAlertsConfig, database: Database, hyperliquid_client: HyperliquidClient, telegram_bot: TelegramBot) {
+        if !config.enabled {
+            return;
+        }
+
+        let mut monitor = MarketContextMonitor {
+            config,
+            database,
+            hyperliquid_client,
+            telegram_bot,
+            funding_warned: HashSet::new(),
+            oi_capped: HashSet::new(),
+            diverged: HashSet::new(),
+        };
+
+        tokio::spawn(async move {
+            let mut ticker = interval(Duration::from_secs(monitor.config.poll_interval_secs.max(1)));
+            ticker.set_missed_tick_behavior(MissedTickBehavior::Delay);
+            loop {
+                ticker.tick().await;
+                if let Err(e) = monitor.poll().await {
+                    error!("couldn't check market contexts: {}", e);
+                }
+            }
+        });
+        info!("market context monitor started");
+    }
+
+    async fn poll(&mut self) -> Result<()> {
+        let subscribed: HashSet<String> = self
+            .database
+            .get_active_coins()
+            .await?
+            .into_iter()
+            .map(|coin| coin.to_string())
+            .collect();
+        if subscribed.is_empty() {
+            return Ok(());
+        }
+
+        let contexts: HashMap<String, CoinContext> = self
+            .hyperliquid_client
+            .asset_contexts()
+            .await?
+            .into_iter()
+            .filter(|(asset, _)| subscribed.contains(&asset.name))
+            .map(|(asset, ctx)| {
+                let mark_px = ctx.mark_px.parse::<f64>().unwrap_or(0.0);
+                let context = CoinContext {
+                    funding: ctx.funding.parse().unwrap_or(0.0),
+                    open_interest_usd: ctx.open_interest.parse::<f64>().unwrap_or(0.0) * mark_px,
+                    mark_px,
+                    oracle_px: ctx.oracle_px.parse().unwrap_or(0.0),
+                };
+                (asset.name, context)
+            })
+            .collect();
+        let at_oi_cap: HashSet<String> = self.hyperliquid_client.perps_at_open_interest_cap().await?.into_iter().collect();
+
+        let warn_at = self.config.funding_cap * self.config.funding_warn_ratio;
+        let diverge_at = self.config.divergence_pct;
+        let mut warnings = Vec::new();
+        for (coin, context) in &contexts {
+            if context.funding.abs() >= warn_at {
+                if self.funding_warned.insert(coin.clone()) {
+                    warnings.push((coin.clone(), MarketWarning::Funding(context.funding)));
+                }
+            } else if context.funding.abs() < warn_at * FUNDING_REARM_FRACTION {
+                self.funding_warned.remove(coin);
+            }
+
+            if at_oi_cap.contains(coin) {
+                if self.oi_capped.insert(coin.clone()) {
+                    warnings.push((coin.clone(), MarketWarning::OpenInterest(context.open_interest_usd)));
+                }
+            } else {
+                self.oi_capped.remove(coin);
+            }
+
+            // a market hl hasn't priced yet has nothing to diverge from
+            if context.mark_px <= 0.0 || context.oracle_px <= 0.0 {
+                continue;
+            }
+            let divergence = divergence_pct(context.mark_px, context.oracle_px).abs();
+            if divergence >= diverge_at {
+                if self.diverged.insert(coin.clone()) {
+                    warnings.push((
+                        coin.clone(),
+                        MarketWarning::Divergence { mark_px: context.mark_px, oracle_px: context.oracle_px },
+                    ));
+                }
+            } else if divergence < diverge_at * DIVERGENCE_REARM_FRACTION {
+                self.diverged.remove(coin);
+            }
+        }
+
+        // unsubscribed coins forget their state so a resubscribe starts fresh
+        self.funding_warned.retain(|coin| contexts.contains_key(coin));
+        self.oi_capped.retain(|coin| contexts.contains_key(coin));
+        self.diverged.retain(|coin| contexts.contains_key(coin));
+
+        for (coin, warning) in warnings {
+            info!("{} market warning: {:?}", coin, warning);
+            if let Err(e) = self.notify(&coin, &warning).await {
+                error!("couldn't send {} market warning: {}", coin, e);
+            }
+        }
+        Ok(())
+    }
+
+    async fn notify(&self, coin: &str, warning: &MarketWarning) -> Result<()> {
+        let message = warning.message(coin, self.config.funding_cap);
+        let subscribers = self.database.get_subscribers_for_coin(&CoinSymbol::new(coin)).await?;
+
+        let mut chats = HashSet::new();
+        for subscriber in subscribers.iter().filter(|subscriber| warning.opted_in(&subscriber.settings)) {
+            for (target, _) in delivery::alert_targets(subscriber) {
+                let AlertTarget::Chat(chat_id) = target else {
+                    continue;
+                };
+                if !chats.insert(chat_id) {
+                    continue;
+                }
+                if let Err(e) = self.telegram_bot.send_text(chat_id, &message, Priority::Alert).await {
+                    warn!("couldn't send {} market warning to chat {}: {}", coin, chat_id, e);
+                }
+            }
+        }
+        Ok(())
+    }
+}
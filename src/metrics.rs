@@ -0,0 +1,127 @@
+use std::collections::VecDeque;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+
+struct MetricsInner {
+    // shared with trade feeds, which count fills before pre-filtering them
+    trades_seen: Arc<AtomicU64>,
+    large_trades: AtomicU64,
+    alerts_sent: AtomicU64,
+    alerts_retried: AtomicU64,
+    alerts_failed: AtomicU64,
+    feeds_gone_quiet: AtomicU64,
+    latency_window: usize,
+    // most recent trade-time -> telegram-delivered latencies
+    alert_latencies_ms: Mutex<VecDeque<u64>>,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct LatencySummary {
+    pub samples: usize,
+    pub p50_ms: u64,
+    pub p95_ms: u64,
+    pub p99_ms: u64,
+    pub max_ms: u64,
+}
+
+#[derive(Debug, Clone)]
+pub struct MetricsSnapshot {
+    pub trades_seen: u64,
+    pub large_trades: u64,
+    pub alerts_sent: u64,
+    pub alerts_retried: u64,
+    pub alerts_failed: u64,
+    pub feeds_gone_quiet: u64,
+    pub latency: Option<LatencySummary>,
+}
+
+// in-process counters shared by the coordinator and telegram handlers
+#[derive(Clone)]
+pub struct Metrics {
+    inner: Arc<MetricsInner>,
+}
+
+impl Metrics {
+    pub fn new(latency_window: usize) -> Self {
+        Metrics {
+            inner: Arc::new(MetricsInner {
+                trades_seen: Arc::new(AtomicU64::new(0)),
+                large_trades: AtomicU64::new(0),
+                alerts_sent: AtomicU64::new(0),
+                alerts_retried: AtomicU64::new(0),
+                alerts_failed: AtomicU64::new(0),
+                feeds_gone_quiet: AtomicU64::new(0),
+                latency_window: latency_window.max(1),
+                alert_latencies_ms: Mutex::new(VecDeque::new()),
+            }),
+        }
+    }
+
+    pub fn trade_counter(&self) -> Arc<AtomicU64> {
+        self.inner.trades_seen.clone()
+    }
+
+    pub fn record_large_trade(&self) {
+        self.inner.large_trades.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn record_alert_sent(&self, latency_ms: Option<u64>) {
+        self.inner.alerts_sent.fetch_add(1, Ordering::Relaxed);
+
+        if let Some(latency_ms) = latency_ms {
+            if let Ok(mut latencies) = self.inner.alert_latencies_ms.lock() {
+                if latencies.len() >= self.inner.latency_window {
+                    latencies.pop_front();
+                }
+                latencies.push_back(latency_ms);
+            }
+        }
+    }
+
+    pub fn record_alert_retried(&self) {
+        self.inner.alerts_retried.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// An alert that ran out of retries.
+    pub fn record_alert_failed(&self) {
+        self.inner.alerts_failed.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// A busy feed that stopped sending without disconnecting.
+    pub fn record_feed_quiet(&self) {
+        self.inner.feeds_gone_quiet.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn snapshot(&self) -> MetricsSnapshot {
+        MetricsSnapshot {
+            trades_seen: self.inner.trades_seen.load(Ordering::Relaxed),
+            large_trades: self.inner.large_trades.load(Ordering::Relaxed),
+            alerts_sent: self.inner.alerts_sent.load(Ordering::Relaxed),
+            alerts_retried: self.inner.alerts_retried.load(Ordering::Relaxed),
+            alerts_failed: self.inner.alerts_failed.load(Ordering::Relaxed),
+            feeds_gone_quiet: self.inner.feeds_gone_quiet.load(Ordering::Relaxed),
+            latency: self.latency_summary(),
+        }
+    }
+
+    fn latency_summary(&self) -> Option<LatencySummary> {
+        let mut samples: Vec<u64> = self.inner.alert_latencies_ms.lock().ok()?.iter().copied().collect();
+        if samples.is_empty() {
+            return None;
+        }
+        samples.sort_unstable();
+
+        let percentile = |p: f64| -> u64 {
+            let rank = ((p * samples.len() as f64).ceil() as usize).clamp(1, samples.len());
+            samples[rank - 1]
+        };
+
+        Some(LatencySummary {
+            samples: samples.len(),
+            p50_ms: percentile(0.50),
+            p95_ms: percentile(0.95),
+            p99_ms: percentile(0.99),
+            max_ms: samples[samples.len() - 1],
+        })
+    }
+}
@@ -0,0 +1,85 @@
+use anyhow::Result;
+use std::collections::HashMap;
+use tokio::time::{interval, Duration, MissedTickBehavior};
+use tracing::{error, info};
+use crate::{
+    activity::ActivityTracker,
+    config::MetricsConfig,
+    database::{Database, MetricsInterval},
+    hyperliquid::CoinSymbol,
+    metrics::{Metrics, MetricsSnapshot},
+};
+
+// writes what the in-process counters gained each interval to the db, so
+// /stats can show days of history through restarts
+pub struct MetricsHistory {
+    database: Database,
+    metrics: Metrics,
+    activity: ActivityTracker,
+    config: MetricsConfig,
+    // counters as of the last write; everything starts at zero with the process
+    last: Option<MetricsSnapshot>,
+    last_coins: HashMap<CoinSymbol, (u64, f64)>,
+}
+
+impl MetricsHistory {
+    pub fn spawn(database: Database, metrics: Metrics, activity: ActivityTracker, config: MetricsConfig) {
+        if config.snapshot_interval_secs == 0 {
+            info!("metrics snapshots disabled");
+            return;
+        }
+
+        let every = config.snapshot_interval_secs;
+        let mut history = MetricsHistory {
+            database,
+            metrics,
+            activity,
+            config,
+            last: None,
+            last_coins: HashMap::new(),
+        };
+        tokio::spawn(async move {
+            let mut ticker = interval(Duration::from_secs(every));
+            ticker.set_missed_tick_behavior(MissedTickBehavior::Delay);
+            // the first tick is immediate and there'd be nothing to write yet
+            ticker.tick().await;
+            loop {
+                ticker.tick().await;
+                if let Err(e) = history.persist().await {
+                    error!("couldn't save metrics snapshot: {}", e);
+                }
+            }
+        });
+        info!("metrics snapshots every {}s", every);
+    }
+
+    async fn persist(&mut self) -> Result<()> {
+        let snapshot = self.metrics.snapshot();
+        let coins = self.activity.totals();
+
+        let since = |now: u64, last: Option<u64>| now.saturating_sub(last.unwrap_or(0)) as i64;
+        let last = self.last.as_ref();
+        let mut interval = MetricsInterval {
+            interval_secs: self.config.snapshot_interval_secs as i64,
+            trades_seen: since(snapshot.trades_seen, last.map(|last| last.trades_seen)),
+            large_trades: since(snapshot.large_trades, last.map(|last| last.large_trades)),
+            alerts_sent: since(snapshot.alerts_sent, last.map(|last| last.alerts_sent)),
+            alerts_retried: since(snapshot.alerts_retried, last.map(|last| last.alerts_retried)),
+            alerts_failed: since(snapshot.alerts_failed, last.map(|last| last.alerts_failed)),
+            p95_latency_ms: snapshot.latency.map(|latency| latency.p95_ms as i64),
+            coins: Vec::new(),
+        };
+        for (coin, (trades, volume_usd)) in &coins {
+            let (last_trades, last_volume) = self.last_coins.get(coin).copied().unwrap_or_default();
+            if *trades > last_trades {
+                interval.coins.push((coin.to_string(), (trades - last_trades) as i64, volume_usd - last_volume));
+            }
+        }
+
+        self.database.save_metrics_snapshot(&interval).await?;
+        self.last = Some(snapshot);
+        self.last_coins = coins;
+        self.database.prune_metrics_snapshots(self.config.snapshot_retention_days).await?;
+        Ok(())
+    }
+}
@@ -0,0 +1,150 @@
+use anyhow::Result;
+use std::collections::HashMap;
+use std::sync::{Arc, RwLock};
+use crate::{
+    database::{Database, MuteRule},
+    formatting,
+};
+
+// every rule is checked against every alert the user gets
+pub const MAX_MUTE_RULES: usize = 10;
+
+impl MuteRule {
+    /// `text` is the alert as the user would see it, lowercased.
+    pub fn matches(&self, text: &str, notional_usd: f64) -> bool {
+        self.under_usd.is_none_or(|under| notional_usd < under)
+            && self.keywords.iter().all(|keyword| text.contains(keyword.as_str()))
+    }
+
+    pub fn describe(&self) -> String {
+        let keywords = if self.keywords.is_empty() {
+            "any alert".to_string()
+        } else {
+            format!("alerts mentioning {}", self.keywords.join(" + "))
+        };
+        match self.under_usd {
+            Some(under) => format!("{} under {}", keywords, formatting::format_usd(under, false)),
+            None => keywords,
+        }
+    }
+}
+
+/// `sell under 100k` and the like: keywords, then optionally `under <usd>`.
+/// Needs at least one of the two.
+pub fn parse_rule(args: &str) -> Option<(Vec<String>, Option<f64>)> {
+    let mut words: Vec<String> = args.split_whitespace().map(str::to_lowercase).collect();
+    let under_usd = match words.iter().position(|word| word == "under") {
+        Some(at) if at + 2 == words.len() => {
+            let under = parse_usd(&words[at + 1])?;
+            words.truncate(at);
+            Some(under)
+        }
+        Some(_) => return None,
+        None => None,
+    };
+    if words.is_empty() && under_usd.is_none() {
+        return None;
+    }
+    Some((words, under_usd))
+}
+
+// `100000`, `$100,000`, `100k`, `1.5m`
+fn parse_usd(text: &str) -> Option<f64> {
+    let text = text.trim_start_matches('$').replace(',', "");
+    let (number, multiplier) = match text.chars().last()? {
+        'k' => (&text[..text.len() - 1], 1e3),
+        'm' => (&text[..text.len() - 1], 1e6),
+        'b' => (&text[..text.len() - 1], 1e9),
+        _ => (text.as_str(), 1.0),
+    };
+    let value = number.parse::<f64>().ok()? * multiplier;
+    (value.is_finite() && value > 0.0).then_some(value)
+}
+
+// checked for every alert on its way out, so kept in memory and written
+// through to the db like roles
+#[derive(Clone)]
+pub struct MuteList {
+    database: Database,
+    rules: Arc<RwLock<HashMap<i64, Vec<MuteRule>>>>,
+}
+
+impl MuteList {
+    pub async fn load(database: Database) -> Result<Self> {
+        let mut rules: HashMap<i64, Vec<MuteRule>> = HashMap::new();
+        for rule in database.get_mute_rules().await? {
+            rules.entry(rule.telegram_user_id).or_default().push(rule);
+        }
+
+        Ok(MuteList {
+            database,
+            rules: Arc::new(RwLock::new(rules)),
+        })
+    }
+
+    pub fn rules(&self, telegram_user_id: i64) -> Vec<MuteRule> {
+        self.rules
+            .read()
+            .ok()
+            .and_then(|rules| rules.get(&telegram_user_id).cloned())
+            .unwrap_or_default()
+    }
+
+    pub fn has_rules(&self, telegram_user_id: i64) -> bool {
+        self.rules.read().is_ok_and(|rules| rules.contains_key(&telegram_user_id))
+    }
+
+    /// The rules of every user in `users` that has any, under one read of the list.
+    pub fn rules_for(&self, users: impl IntoIterator<Item = i64>) -> HashMap<i64, Vec<MuteRule>> {
+        let Ok(rules) = self.rules.read() else {
+            return HashMap::new();
+        };
+        users
+            .into_iter()
+            .filter_map(|telegram_user_id| Some((telegram_user_id, rules.get(&telegram_user_id)?.clone())))
+            .collect()
+    }
+
+    /// The first of the user's rules that mutes this alert text.
+    pub fn matching(&self, telegram_user_id: i64, text: &str, notional_usd: f64) -> Option<MuteRule> {
+        let text = text.to_lowercase();
+        self.rules(telegram_user_id)
+            .into_iter()
+            .find(|rule| rule.matches(&text, notional_usd))
+    }
+
+    pub async fn add(&self, telegram_user_id: i64, keywords: &[String], under_usd: Option<f64>) -> Result<MuteRule> {
+        let rule = self.database.add_mute_rule(telegram_user_id, keywords, under_usd).await?;
+        if let Ok(mut rules) = self.rules.write() {
+            rules.entry(telegram_user_id).or_default().push(rule.clone());
+        }
+        Ok(rule)
+    }
+
+    /// Keeps a rule already written to the db some other way, e.g. by /import.
+    pub fn remember(&self, rule: MuteRule) {
+        if let Ok(mut rules) = self.rules.write() {
+            rules.entry(rule.telegram_user_id).or_default().push(rule);
+        }
+    }
+
+    /// Removes one rule, or all of the user's with `None`; returns how many went.
+    pub async fn remove(&self, telegram_user_id: i64, rule_id: Option<i64>) -> Result<u64> {
+        let removed = self.database.remove_mute_rules(telegram_user_id, rule_id).await?;
+        self.forget(telegram_user_id, rule_id);
+        Ok(removed)
+    }
+
+    /// Drops rules from memory that are already gone from the db, e.g. after /deletedata.
+    pub fn forget(&self, telegram_user_id: i64, rule_id: Option<i64>) {
+        let Ok(mut rules) = self.rules.write() else {
+            return;
+        };
+        if let Some(user_rules) = rules.get_mut(&telegram_user_id) {
+            user_rules.retain(|rule| rule_id.is_some_and(|id| rule.id != id));
+            if user_rules.is_empty() {
+                rules.remove(&telegram_user_id);
+            }
+        }
+    }
+}
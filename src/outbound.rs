@@ -0,0 +1,143 @@
+use anyhow::Result;
+use std::collections::{HashMap, VecDeque};
+use std::sync::{Arc, Mutex};
+use tokio::sync::{oneshot, Notify};
+use tokio::time::{sleep, sleep_until, Duration, Instant};
+use tracing::{info, warn};
+use crate::config::OutboundConfig;
+
+/// Who gets the next send slot first. Declaration order is precedence.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Priority {
+    Reply,
+    Alert,
+    Digest,
+}
+
+impl Priority {
+    const ALL: [Priority; 3] = [Priority::Reply, Priority::Alert, Priority::Digest];
+
+    fn index(self) -> usize {
+        self as usize
+    }
+}
+
+// waiters for one priority, served round-robin across chats
+#[derive(Default)]
+struct Lane {
+    turn_order: VecDeque<i64>,
+    waiting: HashMap<i64, VecDeque<oneshot::Sender<()>>>,
+}
+
+impl Lane {
+    fn pop(&mut self) -> Option<oneshot::Sender<()>> {
+        while let Some(chat_id) = self.turn_order.pop_front() {
+            let Some(queue) = self.waiting.get_mut(&chat_id) else {
+                continue;
+            };
+            let waiter = queue.pop_front();
+            if queue.is_empty() {
+                self.waiting.remove(&chat_id);
+            } else {
+                // back of the line so other chats get a turn first
+                self.turn_order.push_back(chat_id);
+            }
+            if waiter.is_some() {
+                return waiter;
+            }
+        }
+        None
+    }
+}
+
+struct QueueState {
+    lanes: [Lane; 3],
+    paused_until: Option<Instant>,
+}
+
+// global pacing for everything the bot sends. telegram rate limits the whole
+// bot, so one chat's flood of alerts would otherwise delay everyone else.
+#[derive(Clone)]
+pub struct OutboundQueue {
+    config: OutboundConfig,
+    state: Arc<Mutex<QueueState>>,
+    wake: Arc<Notify>,
+}
+
+impl OutboundQueue {
+    pub fn spawn(config: OutboundConfig) -> Self {
+        let queue = OutboundQueue {
+            config,
+            state: Arc::new(Mutex::new(QueueState {
+                lanes: Default::default(),
+                paused_until: None,
+            })),
+            wake: Arc::new(Notify::new()),
+        };
+        tokio::spawn(queue.clone().run());
+        queue
+    }
+
+    /// Waits for a send slot for `chat_id`. Fails straight away if the chat
+    /// already has too many sends queued at this priority.
+    pub async fn acquire(&self, chat_id: i64, priority: Priority) -> Result<()> {
+        let (tx, rx) = oneshot::channel();
+        {
+            let mut state = self.state.lock().map_err(|_| anyhow::anyhow!("outbound queue poisoned"))?;
+            let lane = &mut state.lanes[priority.index()];
+            let queue = lane.waiting.entry(chat_id).or_default();
+            if queue.len() >= self.config.max_pending_per_chat {
+                warn!("outbound queue full for chat {} at {:?} priority", chat_id, priority);
+                return Err(anyhow::anyhow!("too many messages queued for chat {}", chat_id));
+            }
+            if queue.is_empty() {
+                lane.turn_order.push_back(chat_id);
+            }
+            queue.push_back(tx);
+        }
+        self.wake.notify_one();
+
+        rx.await.map_err(|_| anyhow::anyhow!("outbound queue stopped"))
+    }
+
+    /// Holds every send back after telegram answers with a retry-after.
+    pub fn pause(&self, duration: Duration) {
+        if let Ok(mut state) = self.state.lock() {
+            let until = Instant::now() + duration;
+            if state.paused_until.is_none_or(|paused_until| paused_until < until) {
+                warn!("telegram rate limited us, pausing sends for {:?}", duration);
+                state.paused_until = Some(until);
+            }
+        }
+    }
+
+    fn next_waiter(&self) -> std::result::Result<Option<oneshot::Sender<()>>, Instant> {
+        let Ok(mut state) = self.state.lock() else {
+            return Ok(None);
+        };
+        if let Some(until) = state.paused_until.filter(|until| *until > Instant::now()) {
+            return Err(until);
+        }
+        Ok(Priority::ALL
+            .iter()
+            .find_map(|priority| state.lanes[priority.index()].pop()))
+    }
+
+    async fn run(self) {
+        let interval = Duration::from_secs_f64(1.0 / self.config.messages_per_sec.max(0.1));
+        info!("outbound queue started at {} msg/s", self.config.messages_per_sec);
+
+        loop {
+            match self.next_waiter() {
+                Err(paused_until) => sleep_until(paused_until).await,
+                Ok(None) => self.wake.notified().await,
+                Ok(Some(waiter)) => {
+                    // a caller that gave up doesn't use a slot
+                    if waiter.send(()).is_ok() {
+                        sleep(interval).await;
+                    }
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,175 @@
+use anyhow::Result;
+use std::fs::{self, File};
+use std::io::{BufWriter, Write};
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::mpsc::{self, Receiver, SyncSender, TrySendError};
+use std::sync::Arc;
+use tracing::{debug, error, info, warn};
+use crate::config::{PayloadLogMode, PayloadLoggingConfig};
+
+// payloads waiting on the disk before new ones are dropped
+const QUEUE_CAPACITY: usize = 1_000;
+
+const FILE_PREFIX: &str = "payloads-";
+
+/// What happens to ws payloads that don't parse. A bad upstream frame tends
+/// to repeat on every message, so by default only a truncated sample reaches
+/// the log; verbose mode also keeps every payload in full, in its own files.
+#[derive(Clone)]
+pub struct PayloadLog {
+    preview_chars: usize,
+    sample_every: u64,
+    seen: Arc<AtomicU64>,
+    full: Option<SyncSender<(String, String)>>,
+    dropped: Arc<AtomicU64>,
+}
+
+impl PayloadLog {
+    pub fn new(config: PayloadLoggingConfig) -> Self {
+        let dropped = Arc::new(AtomicU64::new(0));
+        let full = match config.mode {
+            PayloadLogMode::Sampled => None,
+            PayloadLogMode::Verbose => spawn_writer(config.clone(), dropped.clone()),
+        };
+
+        PayloadLog {
+            preview_chars: config.preview_chars,
+            sample_every: config.sample_every.max(1),
+            seen: Arc::new(AtomicU64::new(0)),
+            full,
+            dropped,
+        }
+    }
+
+    pub fn unparsable(&self, connection_id: usize, payload: &str, error: &serde_json::Error) {
+        let seen = self.seen.fetch_add(1, Ordering::Relaxed);
+        if seen.is_multiple_of(self.sample_every) {
+            debug!(
+                "parse error on ws connection {}: {} ({}; {} unparsable so far, 1 in {} logged)",
+                connection_id,
+                preview(payload, self.preview_chars),
+                error,
+                seen + 1,
+                self.sample_every
+            );
+        }
+
+        if let Some(full) = &self.full {
+            if let Err(TrySendError::Full(_)) = full.try_send((error.to_string(), payload.to_string())) {
+                self.dropped.fetch_add(1, Ordering::Relaxed);
+            }
+        }
+    }
+}
+
+// the first `chars` characters, and how much was cut
+fn preview(payload: &str, chars: usize) -> String {
+    match payload.char_indices().nth(chars) {
+        Some((end, _)) => format!("{}... ({} bytes)", &payload[..end], payload.len()),
+        None => payload.to_string(),
+    }
+}
+
+fn spawn_writer(config: PayloadLoggingConfig, dropped: Arc<AtomicU64>) -> Option<SyncSender<(String, String)>> {
+    let (tx, rx) = mpsc::sync_channel(QUEUE_CAPACITY);
+    let spawned = std::thread::Builder::new()
+        .name("payload-log".to_string())
+        .spawn(move || run_writer(config, rx, dropped));
+    match spawned {
+        Ok(_) => Some(tx),
+        Err(e) => {
+            error!("couldn't start the payload log, falling back to sampling: {}", e);
+            None
+        }
+    }
+}
+
+struct PayloadFile {
+    path: PathBuf,
+    writer: BufWriter<File>,
+    bytes: u64,
+}
+
+impl PayloadFile {
+    fn open(config: &PayloadLoggingConfig) -> Result<Self> {
+        fs::create_dir_all(&config.directory)?;
+        let name = format!("{}{}.log", FILE_PREFIX, chrono::Utc::now().format("%Y%m%dT%H%M%S%.3f"));
+        let path = PathBuf::from(&config.directory).join(name);
+        info!("writing unparsable ws payloads to {}", path.display());
+        Ok(PayloadFile {
+            writer: BufWriter::new(File::create(&path)?),
+            path,
+            bytes: 0,
+        })
+    }
+
+    fn write(&mut self, error: &str, payload: &str) -> Result<()> {
+        let entry = format!("{} {}\n{}\n", chrono::Utc::now().to_rfc3339(), error, payload);
+        self.writer.write_all(entry.as_bytes())?;
+        // written as they come, so the file is useful while the bot is still up
+        self.writer.flush()?;
+        self.bytes += entry.len() as u64;
+        Ok(())
+    }
+}
+
+fn run_writer(config: PayloadLoggingConfig, rx: Receiver<(String, String)>, dropped: Arc<AtomicU64>) {
+    let mut current: Option<PayloadFile> = None;
+
+    for (error, payload) in rx {
+        if current
+            .as_ref()
+            .is_some_and(|file| file.bytes >= config.max_file_mb.saturating_mul(1024 * 1024))
+        {
+            current = None;
+            let dropped = dropped.swap(0, Ordering::Relaxed);
+            if dropped > 0 {
+                warn!("payload log dropped {} payloads since the last rotation", dropped);
+            }
+        }
+
+        if current.is_none() {
+            match PayloadFile::open(&config) {
+                Ok(file) => {
+                    current = Some(file);
+                    prune(&config);
+                }
+                Err(e) => {
+                    error!("couldn't open a payload log in {}: {}", config.directory, e);
+                    dropped.fetch_add(1, Ordering::Relaxed);
+                    continue;
+                }
+            }
+        }
+        if let Some(file) = current.as_mut() {
+            if let Err(e) = file.write(&error, &payload) {
+                error!("couldn't write payload log {}: {}", file.path.display(), e);
+                current = None;
+            }
+        }
+    }
+}
+
+// timestamped names sort oldest first
+fn prune(config: &PayloadLoggingConfig) {
+    let Ok(entries) = fs::read_dir(&config.directory) else {
+        return;
+    };
+    let mut files: Vec<PathBuf> = entries
+        .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+        .filter(|path| {
+            path.file_name()
+                .and_then(|name| name.to_str())
+                .is_some_and(|name| name.starts_with(FILE_PREFIX) && name.ends_with(".log"))
+        })
+        .collect();
+    files.sort();
+
+    let excess = files.len().saturating_sub(config.max_files.max(1));
+    for path in &files[..excess] {
+        if let Err(e) = fs::remove_file(path) {
+            warn!("couldn't remove old payload log {}: {}", path.display(), e);
+        }
+    }
+}
@@ -0,0 +1,179 @@
+use anyhow::Result;
+use chrono::Utc;
+use std::collections::HashMap;
+use tokio::time::Duration;
+use tracing::warn;
+use crate::{
+    database::{Database, PriceAlert},
+    hyperliquid::CoinSymbol,
+    prices::PriceEngine,
+};
+
+// shorter than this and a repeating alert is just noise
+const MIN_REPEAT: Duration = Duration::from_secs(60);
+const MAX_REPEAT: Duration = Duration::from_secs(7 * 24 * 60 * 60);
+
+/// Where an alert is in its cycle. One-shot alerts fire from `Armed` and are
+/// deleted; repeating ones go armed -> cooling -> rearming -> armed, so once
+/// the cooldown is over they still wait for price to come back across the
+/// threshold rather than firing again on every check.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AlertState {
+    Armed,
+    Cooling,
+    Rearming,
+}
+
+impl AlertState {
+    fn as_str(&self) -> &'static str {
+        match self {
+            AlertState::Armed => "armed",
+            AlertState::Cooling => "cooling",
+            AlertState::Rearming => "rearming",
+        }
+    }
+
+    fn parse(state: &str) -> Self {
+        match state {
+            "cooling" => AlertState::Cooling,
+            "rearming" => AlertState::Rearming,
+            _ => AlertState::Armed,
+        }
+    }
+}
+
+struct WatchedAlert {
+    alert: PriceAlert,
+    state: AlertState,
+}
+
+impl WatchedAlert {
+    fn beyond(&self, mid: f64) -> bool {
+        if self.alert.above {
+            mid >= self.alert.price
+        } else {
+            mid <= self.alert.price
+        }
+    }
+
+    fn cooled_down(&self) -> bool {
+        let (Some(fired_at), Some(repeat_secs)) = (self.alert.fired_at, self.alert.repeat_secs) else {
+            return true;
+        };
+        (Utc::now() - fired_at).num_seconds() >= repeat_secs
+    }
+
+    // the state `mid` moves this alert to, and whether that fires it
+    fn step(&self, mid: f64) -> Option<(AlertState, bool)> {
+        match self.state {
+            AlertState::Armed if self.beyond(mid) => Some((AlertState::Cooling, true)),
+            AlertState::Cooling if self.cooled_down() => Some((AlertState::Rearming, false)),
+            AlertState::Rearming if !self.beyond(mid) => Some((AlertState::Armed, false)),
+            _ => None,
+        }
+    }
+}
+
+/// Steps every /pricealert against the live mids, keeping each alert's state
+/// in the db so a restart doesn't refire or forget a cooldown.
+pub struct PriceWatcher {
+    database: Database,
+    watchers: HashMap<CoinSymbol, Vec<WatchedAlert>>,
+}
+
+impl PriceWatcher {
+    pub fn new(database: Database) -> Self {
+        PriceWatcher {
+            database,
+            watchers: HashMap::new(),
+        }
+    }
+
+    pub async fn reload_coin(&mut self, coin: &CoinSymbol) -> Result<()> {
+        let alerts = self.database.get_price_alerts_for_coin(coin).await?;
+
+        if alerts.is_empty() {
+            self.watchers.remove(coin);
+            return Ok(());
+        }
+
+        let watched = alerts
+            .into_iter()
+            .map(|alert| WatchedAlert {
+                state: AlertState::parse(&alert.state),
+                alert,
+            })
+            .collect();
+        self.watchers.insert(coin.clone(), watched);
+        Ok(())
+    }
+
+    /// Alerts that fired on this check, with the mid that fired them. The
+    /// check's state changes are saved in one go at the end.
+    pub async fn evaluate(&mut self, price_engine: &PriceEngine) -> Vec<(PriceAlert, String)> {
+        let mut fired = Vec::new();
+        let mut removed = Vec::new();
+        let mut moved = Vec::new();
+
+        for (coin, watched) in self.watchers.iter_mut() {
+            let Some(mid_str) = price_engine.mid_str(coin).await else {
+                continue;
+            };
+            let Ok(mid) = mid_str.parse::<f64>() else {
+                continue;
+            };
+
+            for entry in watched.iter_mut() {
+                let Some((next, fires)) = entry.step(mid) else {
+                    continue;
+                };
+
+                // a one-shot is done once it fires
+                if fires && entry.alert.repeat_secs.is_none() {
+                    removed.push(entry.alert.id);
+                } else {
+                    moved.push((entry.alert.id, next.as_str(), fires));
+                }
+
+                entry.state = next;
+                if fires {
+                    entry.alert.fired_at = Some(Utc::now());
+                    fired.push((entry.alert.clone(), mid_str.clone()));
+                }
+            }
+
+            watched.retain(|entry| entry.alert.repeat_secs.is_some() || entry.state == AlertState::Armed);
+        }
+        self.watchers.retain(|_, watched| !watched.is_empty());
+
+        // moved on regardless, a failed write shouldn't mean a refire every check
+        if !(removed.is_empty() && moved.is_empty()) {
+            let changes = removed.len() + moved.len();
+            if let Err(e) = self.database.save_price_alert_steps(removed, moved).await {
+                warn!("couldn't save {} price alert changes: {}", changes, e);
+            }
+        }
+        fired
+    }
+}
+
+/// `30m`, `1h`, `2d` and so on, between a minute and a week.
+pub fn parse_repeat(text: &str) -> Option<Duration> {
+    let text = text.trim().to_lowercase();
+    let unit = text.chars().last()?;
+    let value: u64 = text[..text.len() - unit.len_utf8()].parse().ok()?;
+    let secs = match unit {
+        's' => value,
+        'm' => value.checked_mul(60)?,
+        'h' => value.checked_mul(60 * 60)?,
+        'd' => value.checked_mul(24 * 60 * 60)?,
+        _ => return None,
+    };
+    let repeat = Duration::from_secs(secs);
+    repeat_allowed(repeat).then_some(repeat)
+}
+
+/// Whether `repeat` is within what /pricealert accepts.
+pub fn repeat_allowed(repeat: Duration) -> bool {
+    (MIN_REPEAT..=MAX_REPEAT).contains(&repeat)
+}
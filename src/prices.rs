@@ -0,0 +1,228 @@
+use anyhow::Result;
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use tokio::sync::{mpsc, RwLock};
+use tokio::time::{Duration, Instant};
+use tracing::{info, warn};
+use crate::{
+    config::RevisitConfig,
+    hyperliquid::{CoinSymbol, WebSocketManager, WsAllMids},
+};
+
+// mids older than this are treated as missing so callers fall back to rest
+const MAX_MID_AGE: Duration = Duration::from_secs(60);
+
+struct MidsSnapshot {
+    mids: HashMap<CoinSymbol, String>,
+    updated_at: Option<Instant>,
+}
+
+/// A whale fill price to alert on again if the market comes back to it.
+#[derive(Debug, Clone)]
+pub struct LevelWatch {
+    pub chat_id: i64,
+    pub coin: CoinSymbol,
+    pub px: String,
+    pub side: String,
+    pub notional_usd: f64,
+}
+
+#[derive(Debug, Clone)]
+pub struct LevelRevisit {
+    pub watch: LevelWatch,
+    pub mid: f64,
+}
+
+struct ActiveWatch {
+    watch: LevelWatch,
+    level: f64,
+    expires_at: Instant,
+    // which side of the level price moved off to, once it's gone far enough
+    away_above: Option<bool>,
+}
+
+impl ActiveWatch {
+    fn revisited(&mut self, mid: f64, arm_distance_bps: f64) -> bool {
+        match self.away_above {
+            Some(true) => mid <= self.level,
+            Some(false) => mid >= self.level,
+            None => {
+                let distance_bps = (mid - self.level) / self.level * 10_000.0;
+                if distance_bps.abs() >= arm_distance_bps {
+                    self.away_above = Some(distance_bps > 0.0);
+                }
+                false
+            }
+        }
+    }
+}
+
+/// The USD mid of a quote asset, kept current off the allMids feed for the
+/// trade filters of markets that aren't quoted in USDC.
+#[derive(Debug, Clone)]
+pub struct QuoteRate {
+    asset: CoinSymbol,
+    // f64 bits; 0 until the asset's first mid
+    usd: Arc<AtomicU64>,
+}
+
+impl QuoteRate {
+    pub fn asset(&self) -> &CoinSymbol {
+        &self.asset
+    }
+
+    pub fn usd(&self) -> Option<f64> {
+        let usd = f64::from_bits(self.usd.load(Ordering::Relaxed));
+        (usd > 0.0).then_some(usd)
+    }
+
+    fn update(&self, mids: &HashMap<CoinSymbol, String>) {
+        if let Some(mid) = mids.get(&self.asset).and_then(|mid| mid.parse::<f64>().ok()) {
+            if mid.is_finite() && mid > 0.0 {
+                self.usd.store(mid.to_bits(), Ordering::Relaxed);
+            }
+        }
+    }
+}
+
+// one shared allMids feed backing every price-only feature
+#[derive(Clone)]
+pub struct PriceEngine {
+    snapshot: Arc<RwLock<MidsSnapshot>>,
+    config: RevisitConfig,
+    watches: Arc<Mutex<HashMap<CoinSymbol, Vec<ActiveWatch>>>>,
+    quote_rates: Arc<Mutex<HashMap<CoinSymbol, QuoteRate>>>,
+}
+
+impl PriceEngine {
+    pub fn new(config: RevisitConfig) -> Self {
+        PriceEngine {
+            snapshot: Arc::new(RwLock::new(MidsSnapshot {
+                mids: HashMap::new(),
+                updated_at: None,
+            })),
+            config,
+            watches: Arc::new(Mutex::new(HashMap::new())),
+            quote_rates: Arc::new(Mutex::new(HashMap::new())),
+        }
+    }
+
+    pub async fn start(&self, ws_manager: &WebSocketManager, revisit_tx: mpsc::UnboundedSender<LevelRevisit>) -> Result<()> {
+        let (mids_tx, mut mids_rx) = mpsc::unbounded_channel::<WsAllMids>();
+        ws_manager.start_mids_feed(mids_tx).await?;
+
+        let engine = self.clone();
+        tokio::spawn(async move {
+            while let Some(update) = mids_rx.recv().await {
+                let mut snapshot = engine.snapshot.write().await;
+                // allMids pushes full snapshots, but merge in case of partial frames
+                snapshot.mids.extend(update.mids);
+                snapshot.updated_at = Some(Instant::now());
+                engine.update_quote_rates(&snapshot.mids);
+
+                for revisit in engine.check_watches(&snapshot.mids) {
+                    let _ = revisit_tx.send(revisit);
+                }
+            }
+            warn!("allMids feed closed, price engine is no longer updating");
+        });
+
+        info!("price engine started");
+        Ok(())
+    }
+
+    /// Alerts `watch.chat_id` if price moves away from `watch.px` and comes back
+    /// within the configured window.
+    pub fn watch_level(&self, watch: LevelWatch) {
+        let Ok(level) = watch.px.parse::<f64>() else {
+            return;
+        };
+        if level <= 0.0 {
+            return;
+        }
+        if let Ok(mut watches) = self.watches.lock() {
+            watches.entry(watch.coin.clone()).or_default().push(ActiveWatch {
+                watch,
+                level,
+                expires_at: Instant::now() + Duration::from_secs(self.config.window_hours * 60 * 60),
+                away_above: None,
+            });
+        }
+    }
+
+    fn check_watches(&self, mids: &HashMap<CoinSymbol, String>) -> Vec<LevelRevisit> {
+        let mut revisits = Vec::new();
+        let Ok(mut watches) = self.watches.lock() else {
+            return revisits;
+        };
+
+        let now = Instant::now();
+        for (coin, coin_watches) in watches.iter_mut() {
+            let Some(mid) = mids.get(coin).and_then(|mid| mid.parse::<f64>().ok()) else {
+                continue;
+            };
+            coin_watches.retain_mut(|active| {
+                if active.expires_at <= now {
+                    return false;
+                }
+                if active.revisited(mid, self.config.arm_distance_bps) {
+                    revisits.push(LevelRevisit {
+                        watch: active.watch.clone(),
+                        mid,
+                    });
+                    return false;
+                }
+                true
+            });
+        }
+        watches.retain(|_, coin_watches| !coin_watches.is_empty());
+        revisits
+    }
+
+    /// Mids fetched over REST, to answer from until the allMids feed's first
+    /// snapshot lands. Ignored once the feed has sent one.
+    pub async fn seed(&self, mids: HashMap<String, String>) {
+        let mut snapshot = self.snapshot.write().await;
+        if snapshot.updated_at.is_some() {
+            return;
+        }
+        snapshot.mids = mids.into_iter().map(|(coin, mid)| (CoinSymbol::new(&coin), mid)).collect();
+        snapshot.updated_at = Some(Instant::now());
+        self.update_quote_rates(&snapshot.mids);
+    }
+
+    /// A live USD rate for `asset`, shared by every feed quoted in it.
+    pub async fn quote_rate(&self, asset: &str) -> QuoteRate {
+        let asset = CoinSymbol::new(asset);
+        let rate = {
+            let Ok(mut rates) = self.quote_rates.lock() else {
+                return QuoteRate { asset, usd: Arc::new(AtomicU64::new(0)) };
+            };
+            rates
+                .entry(asset.clone())
+                .or_insert_with(|| QuoteRate { asset, usd: Arc::new(AtomicU64::new(0)) })
+                .clone()
+        };
+        // a rate asked for between snapshots starts from the last one
+        rate.update(&self.snapshot.read().await.mids);
+        rate
+    }
+
+    fn update_quote_rates(&self, mids: &HashMap<CoinSymbol, String>) {
+        if let Ok(rates) = self.quote_rates.lock() {
+            for rate in rates.values() {
+                rate.update(mids);
+            }
+        }
+    }
+
+    /// Latest mid as quoted by the exchange, or `None` if unknown or stale.
+    pub async fn mid_str(&self, coin: &str) -> Option<String> {
+        let snapshot = self.snapshot.read().await;
+        if snapshot.updated_at.is_none_or(|at| at.elapsed() >= MAX_MID_AGE) {
+            return None;
+        }
+        snapshot.mids.get(coin.to_uppercase().as_str()).cloned()
+    }
+}
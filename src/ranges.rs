@@ -0,0 +1,200 @@
+use anyhow::Result;
+use std::collections::{HashMap, HashSet};
+use tokio::time::{interval, Duration, Instant, MissedTickBehavior};
+use tracing::{error, info, warn};
+use crate::{
+    candles::CandleCache,
+    config::RangeAlertsConfig,
+    database::Database,
+    delivery::{self, AlertTarget},
+    formatting,
+    hyperliquid::CoinSymbol,
+    outbound::Priority,
+    prices::PriceEngine,
+    telegram::TelegramBot,
+};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Breach {
+    High,
+    Low,
+}
+
+struct CoinRange {
+    high: f64,
+    low: f64,
+    refreshed_at: Instant,
+    high_alerted_at: Option<Instant>,
+    low_alerted_at: Option<Instant>,
+}
+
+impl CoinRange {
+    // widens the range to take in `mid`, returning the side it broke out of
+    fn push(&mut self, mid: f64) -> Option<(Breach, f64)> {
+        if mid > self.high {
+            let previous = self.high;
+            self.high = mid;
+            Some((Breach::High, previous))
+        } else if mid < self.low {
+            let previous = self.low;
+            self.low = mid;
+            Some((Breach::Low, previous))
+        } else {
+            None
+        }
+    }
+}
+
+// rolling highs and lows for coins /rangealerts users follow: the window comes
+// from daily candles, and live mids carry it between rebuilds
+pub struct RangeMonitor {
+    config: RangeAlertsConfig,
+    database: Database,
+    candles: CandleCache,
+    price_engine: PriceEngine,
+    telegram_bot: TelegramBot,
+    ranges: HashMap<CoinSymbol, CoinRange>,
+}
+
+impl RangeMonitor {
+    pub fn spawn(
+        config: RangeAlertsConfig,
+        database: Database,
+        candles: CandleCache,
+        price_engine: PriceEngine,
+        telegram_bot: TelegramBot,
+    ) {
+        if !config.enabled {
+            return;
+        }
+
+        let mut monitor = RangeMonitor {
+            config,
+            database,
+            candles,
+            price_engine,
+            telegram_bot,
+            ranges: HashMap::new(),
+        };
+
+        tokio::spawn(async move {
+            let mut ticker = interval(Duration::from_secs(monitor.config.check_interval_secs.max(1)));
+            ticker.set_missed_tick_behavior(MissedTickBehavior::Delay);
+            loop {
+                ticker.tick().await;
+                if let Err(e) = monitor.poll().await {
+                    error!("couldn't check {}d ranges: {}", monitor.config.window_days, e);
+                }
+            }
+        });
+        info!("range monitor started");
+    }
+
+    async fn poll(&mut self) -> Result<()> {
+        let coins: HashSet<CoinSymbol> = self.database.get_range_alert_coins().await?.into_iter().collect();
+        self.ranges.retain(|coin, _| coins.contains(coin));
+
+        let refresh = Duration::from_secs(self.config.refresh_mins * 60);
+        let cooldown = Duration::from_secs(self.config.cooldown_hours * 60 * 60);
+        for coin in &coins {
+            let Some(mid) = self.price_engine.mid_str(coin).await.and_then(|mid| mid.parse::<f64>().ok()) else {
+                continue;
+            };
+
+            if self.ranges.get(coin).is_none_or(|range| range.refreshed_at.elapsed() >= refresh) {
+                if let Err(e) = self.refresh(coin).await {
+                    warn!("couldn't load {}d range for {}: {}", self.config.window_days, coin, e);
+                    continue;
+                }
+            }
+            let Some(range) = self.ranges.get_mut(coin) else {
+                continue;
+            };
+
+            let Some((breach, previous)) = range.push(mid) else {
+                continue;
+            };
+            let alerted_at = match breach {
+                Breach::High => &mut range.high_alerted_at,
+                Breach::Low => &mut range.low_alerted_at,
+            };
+            if alerted_at.is_some_and(|at| at.elapsed() < cooldown) {
+                continue;
+            }
+            *alerted_at = Some(Instant::now());
+
+            let message = self.message(coin, breach, mid, previous);
+            info!("{} broke its {}d {:?} at {}", coin, self.config.window_days, breach, mid);
+            if let Err(e) = self.notify(coin, &message).await {
+                error!("couldn't send {} range alert: {}", coin, e);
+            }
+        }
+        Ok(())
+    }
+
+    // rebuilt from candles so old extremes age out of the window; the alert
+    // cooldowns carry over
+    async fn refresh(&mut self, coin: &CoinSymbol) -> Result<()> {
+        let lookback = Duration::from_secs(self.config.window_days.max(1) as u64 * 24 * 60 * 60);
+        let candles = self.candles.recent(coin, "1d", lookback).await?;
+
+        let highs = candles.iter().filter_map(|candle| candle.high.parse::<f64>().ok());
+        let lows = candles.iter().filter_map(|candle| candle.low.parse::<f64>().ok());
+        let (Some(high), Some(low)) = (highs.reduce(f64::max), lows.reduce(f64::min)) else {
+            anyhow::bail!("no candles");
+        };
+
+        let (high_alerted_at, low_alerted_at) = self
+            .ranges
+            .get(coin)
+            .map_or((None, None), |range| (range.high_alerted_at, range.low_alerted_at));
+        self.ranges.insert(
+            coin.clone(),
+            CoinRange {
+                high,
+                low,
+                refreshed_at: Instant::now(),
+                high_alerted_at,
+                low_alerted_at,
+            },
+        );
+        Ok(())
+    }
+
+    fn message(&self, coin: &str, breach: Breach, mid: f64, previous: f64) -> String {
+        let (label, verb) = match breach {
+            Breach::High => ("High", "above"),
+            Breach::Low => ("Low", "below"),
+        };
+        format!(
+            "{} New {}d {}\n\nNow: {} ({:.2}% {} the previous {})",
+            coin,
+            self.config.window_days,
+            label,
+            formatting::format_price(&mid.to_string()),
+            ((mid - previous) / previous * 100.0).abs(),
+            verb,
+            label.to_lowercase()
+        )
+    }
+
+    async fn notify(&self, coin: &CoinSymbol, message: &str) -> Result<()> {
+        let subscribers = self.database.get_subscribers_for_coin(coin).await?;
+
+        let mut chats = HashSet::new();
+        for subscriber in subscribers.iter().filter(|subscriber| subscriber.settings.range_alerts) {
+            for (target, _) in delivery::alert_targets(subscriber) {
+                let AlertTarget::Chat(chat_id) = target else {
+                    continue;
+                };
+                if !chats.insert(chat_id) {
+                    continue;
+                }
+                if let Err(e) = self.telegram_bot.send_text(chat_id, message, Priority::Alert).await {
+                    warn!("couldn't send {} range alert to chat {}: {}", coin, chat_id, e);
+                }
+            }
+        }
+        Ok(())
+    }
+}
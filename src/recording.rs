@@ -0,0 +1,152 @@
+use anyhow::Result;
+use std::fs::{self, File};
+use std::io::{BufWriter, Write};
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError, SyncSender, TrySendError};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tracing::{error, info, warn};
+use crate::config::RecordingConfig;
+
+// frames waiting on the disk before new ones are dropped
+const QUEUE_CAPACITY: usize = 10_000;
+
+// how often an idle writer wakes to check the file's age
+const IDLE_CHECK: Duration = Duration::from_secs(1);
+
+type Encoder = zstd::Encoder<'static, BufWriter<File>>;
+
+/// Appends raw trade frames to rotating `.jsonl.zst` files, one frame per line.
+/// Writing happens on its own thread so a slow disk never holds up a socket.
+#[derive(Clone)]
+pub struct TradeRecorder {
+    tx: SyncSender<String>,
+    dropped: Arc<AtomicU64>,
+}
+
+impl std::fmt::Debug for TradeRecorder {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("TradeRecorder").finish_non_exhaustive()
+    }
+}
+
+impl TradeRecorder {
+    /// Starts the writer thread, or returns `None` when recording is off.
+    pub fn spawn(config: RecordingConfig) -> Option<Self> {
+        if !config.enabled {
+            return None;
+        }
+
+        let (tx, rx) = mpsc::sync_channel(QUEUE_CAPACITY);
+        let dropped = Arc::new(AtomicU64::new(0));
+        let writer_dropped = dropped.clone();
+        let spawned = std::thread::Builder::new()
+            .name("trade-recorder".to_string())
+            .spawn(move || run_writer(config, rx, writer_dropped));
+        if let Err(e) = spawned {
+            error!("couldn't start trade recorder: {}", e);
+            return None;
+        }
+
+        Some(TradeRecorder { tx, dropped })
+    }
+
+    pub fn record(&self, frame: &str) {
+        if let Err(TrySendError::Full(_)) = self.tx.try_send(frame.to_string()) {
+            self.dropped.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+}
+
+struct RecordingFile {
+    path: PathBuf,
+    encoder: Encoder,
+    opened_at: Instant,
+    bytes: u64,
+}
+
+impl RecordingFile {
+    fn open(config: &RecordingConfig) -> Result<Self> {
+        fs::create_dir_all(&config.directory)?;
+        let name = format!("trades-{}.jsonl.zst", chrono::Utc::now().format("%Y%m%dT%H%M%S%.3f"));
+        let path = PathBuf::from(&config.directory).join(name);
+        let file = BufWriter::new(File::create(&path)?);
+        Ok(RecordingFile {
+            encoder: zstd::Encoder::new(file, config.compression_level)?,
+            path,
+            opened_at: Instant::now(),
+            bytes: 0,
+        })
+    }
+
+    fn write(&mut self, frame: &str) -> Result<()> {
+        self.encoder.write_all(frame.as_bytes())?;
+        self.encoder.write_all(b"\n")?;
+        self.bytes += frame.len() as u64 + 1;
+        Ok(())
+    }
+
+    // ends the zstd frame so the file decompresses cleanly on its own
+    fn finish(self) -> Result<()> {
+        self.encoder.finish()?.flush()?;
+        info!("closed trade recording {} ({} bytes uncompressed)", self.path.display(), self.bytes);
+        Ok(())
+    }
+
+    fn is_due(&self, config: &RecordingConfig) -> bool {
+        self.bytes >= config.max_file_mb.saturating_mul(1024 * 1024)
+            || self.opened_at.elapsed() >= Duration::from_secs(config.max_file_age_secs)
+    }
+}
+
+fn run_writer(config: RecordingConfig, rx: Receiver<String>, dropped: Arc<AtomicU64>) {
+    let mut current: Option<RecordingFile> = None;
+
+    loop {
+        let frame = match rx.recv_timeout(IDLE_CHECK) {
+            Ok(frame) => Some(frame),
+            Err(RecvTimeoutError::Timeout) => None,
+            Err(RecvTimeoutError::Disconnected) => break,
+        };
+
+        if current.as_ref().is_some_and(|file| file.is_due(&config)) {
+            rotate(&mut current, &dropped);
+        }
+        let Some(frame) = frame else {
+            continue;
+        };
+
+        if current.is_none() {
+            match RecordingFile::open(&config) {
+                Ok(file) => current = Some(file),
+                Err(e) => {
+                    error!("couldn't open trade recording in {}: {}", config.directory, e);
+                    dropped.fetch_add(1, Ordering::Relaxed);
+                    continue;
+                }
+            }
+        }
+        if let Some(file) = current.as_mut() {
+            if let Err(e) = file.write(&frame) {
+                error!("couldn't write trade recording {}: {}", file.path.display(), e);
+                // start over in a fresh file rather than keep writing to a broken one
+                current = None;
+            }
+        }
+    }
+
+    rotate(&mut current, &dropped);
+}
+
+fn rotate(current: &mut Option<RecordingFile>, dropped: &AtomicU64) {
+    if let Some(file) = current.take() {
+        if let Err(e) = file.finish() {
+            error!("couldn't finish trade recording: {}", e);
+        }
+    }
+    let dropped = dropped.swap(0, Ordering::Relaxed);
+    if dropped > 0 {
+        warn!("trade recorder dropped {} frames since the last rotation", dropped);
+    }
+}
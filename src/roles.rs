@@ -0,0 +1,143 @@
+use anyhow::Result;
+use std::collections::{HashMap, HashSet};
+use std::sync::{Arc, RwLock};
+use crate::database::Database;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Role {
+    Owner,
+    Admin,
+    Moderator,
+}
+
+impl Role {
+    pub fn parse(role: &str) -> Option<Self> {
+        match role.trim().to_lowercase().as_str() {
+            "owner" => Some(Role::Owner),
+            "admin" => Some(Role::Admin),
+            "moderator" | "mod" => Some(Role::Moderator),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Role::Owner => "owner",
+            Role::Admin => "admin",
+            Role::Moderator => "moderator",
+        }
+    }
+
+    pub fn allows(&self, permission: Permission) -> bool {
+        match self {
+            Role::Owner => true,
+            Role::Admin => !matches!(permission, Permission::ManageRoles),
+            Role::Moderator => matches!(permission, Permission::Ban | Permission::Stats),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Permission {
+    Broadcast,
+    Ban,
+    FeedControl,
+    Stats,
+    DeadLetters,
+    ManageRoles,
+}
+
+// roles and bans are read on every command, so they're kept in memory and
+// written through to the db; this process is the only writer
+#[derive(Clone)]
+pub struct RoleDirectory {
+    database: Database,
+    // `telegram.admin_user_ids` from config, always owners so a fresh
+    // deployment has someone who can hand out roles
+    config_owners: Vec<i64>,
+    roles: Arc<RwLock<HashMap<i64, Role>>>,
+    banned: Arc<RwLock<HashSet<i64>>>,
+}
+
+impl RoleDirectory {
+    pub async fn load(database: Database, config_owners: Vec<i64>) -> Result<Self> {
+        let roles = database
+            .get_roles()
+            .await?
+            .into_iter()
+            .filter_map(|(user_id, role)| Some((user_id, Role::parse(&role)?)))
+            .collect();
+        let banned = database.get_banned_users().await?.into_iter().collect();
+
+        Ok(RoleDirectory {
+            database,
+            config_owners,
+            roles: Arc::new(RwLock::new(roles)),
+            banned: Arc::new(RwLock::new(banned)),
+        })
+    }
+
+    pub fn is_config_owner(&self, user_id: i64) -> bool {
+        self.config_owners.contains(&user_id)
+    }
+
+    pub fn role(&self, user_id: i64) -> Option<Role> {
+        if self.is_config_owner(user_id) {
+            return Some(Role::Owner);
+        }
+        self.roles.read().ok()?.get(&user_id).copied()
+    }
+
+    pub fn allows(&self, user_id: i64, permission: Permission) -> bool {
+        self.role(user_id).is_some_and(|role| role.allows(permission))
+    }
+
+    pub fn is_banned(&self, user_id: i64) -> bool {
+        self.banned.read().is_ok_and(|banned| banned.contains(&user_id))
+    }
+
+    /// Everyone with a role, config owners first.
+    pub fn members(&self) -> Vec<(i64, Role)> {
+        let mut members: Vec<(i64, Role)> = self.config_owners.iter().map(|user_id| (*user_id, Role::Owner)).collect();
+        if let Ok(roles) = self.roles.read() {
+            let mut granted: Vec<(i64, Role)> = roles
+                .iter()
+                .filter(|(user_id, _)| !self.is_config_owner(**user_id))
+                .map(|(user_id, role)| (*user_id, *role))
+                .collect();
+            granted.sort_by_key(|(user_id, _)| *user_id);
+            members.extend(granted);
+        }
+        members
+    }
+
+    /// Grants `role`, or takes the user's role away with `None`.
+    pub async fn set_role(&self, user_id: i64, role: Option<Role>, granted_by: i64) -> Result<()> {
+        self.database.set_role(user_id, role.map(|role| role.as_str()), granted_by).await?;
+        if let Ok(mut roles) = self.roles.write() {
+            match role {
+                Some(role) => roles.insert(user_id, role),
+                None => roles.remove(&user_id),
+            };
+        }
+        Ok(())
+    }
+
+    /// Returns false if the user was already banned.
+    pub async fn ban(&self, user_id: i64, banned_by: i64) -> Result<bool> {
+        let banned = self.database.ban_user(user_id, banned_by).await?;
+        if let Ok(mut set) = self.banned.write() {
+            set.insert(user_id);
+        }
+        Ok(banned)
+    }
+
+    /// Returns false if the user wasn't banned.
+    pub async fn unban(&self, user_id: i64) -> Result<bool> {
+        let unbanned = self.database.unban_user(user_id).await?;
+        if let Ok(mut set) = self.banned.write() {
+            set.remove(&user_id);
+        }
+        Ok(unbanned)
+    }
+}
@@ -0,0 +1,131 @@
+use anyhow::Result;
+use cron::Schedule;
+use std::str::FromStr;
+use tokio::time::sleep;
+use tracing::{error, info, warn};
+use crate::{
+    budget::AlertBudget,
+    config::{Config, LinksConfig, ReportKind, ScheduleConfig},
+    database::Database,
+    hyperliquid::HyperliquidClient,
+    market,
+    telegram::TelegramBot,
+};
+
+const DAILY_RESET_CRON: &str = "0 0 0 * * *";
+
+// a few minutes past midnight, once the day's last trades are flushed
+const ROLLUP_CRON: &str = "0 5 0 * * *";
+// complete days recomputed each run, enough to cover a few nights down
+const ROLLUP_DAYS: i64 = 7;
+
+// posts the recurring reports defined under [[schedules]] in the config
+pub struct ReportScheduler {
+    config: ScheduleConfig,
+    schedule: Schedule,
+    liquidators: Vec<String>,
+    links: LinksConfig,
+    database: Database,
+    hyperliquid_client: HyperliquidClient,
+    telegram_bot: TelegramBot,
+}
+
+impl ReportScheduler {
+    pub fn spawn(config: &Config, database: Database, hyperliquid_client: HyperliquidClient, telegram_bot: TelegramBot) {
+        let liquidators = &config.liquidations.liquidator_addresses;
+        let links = &config.links;
+        for config in &config.schedules {
+            let schedule = match Schedule::from_str(&config.cron) {
+                Ok(schedule) => schedule,
+                Err(e) => {
+                    error!("bad cron expression {:?} for report {}: {}", config.cron, config.name, e);
+                    continue;
+                }
+            };
+
+            let scheduler = ReportScheduler {
+                config: config.clone(),
+                schedule,
+                liquidators: liquidators.clone(),
+                links: links.clone(),
+                database: database.clone(),
+                hyperliquid_client: hyperliquid_client.clone(),
+                telegram_bot: telegram_bot.clone(),
+            };
+            tokio::spawn(scheduler.run());
+            info!("scheduled report {} ({})", config.name, config.cron);
+        }
+    }
+
+    async fn run(self) {
+        while let Some(next) = self.schedule.upcoming(chrono::Utc).next() {
+            let wait = (next - chrono::Utc::now()).to_std().unwrap_or_default();
+            sleep(wait).await;
+
+            if let Err(e) = self.send_report().await {
+                error!("couldn't build report {}: {}", self.config.name, e);
+            }
+        }
+        warn!("report {} has no upcoming runs, stopping", self.config.name);
+    }
+
+    async fn send_report(&self) -> Result<()> {
+        let mut snapshots = market::snapshots(&self.hyperliquid_client, &self.config.coins, self.config.top_n).await?;
+        let title = match self.config.report {
+            ReportKind::Market => "Market Snapshot",
+            ReportKind::Funding => {
+                snapshots.sort_by(|a, b| b.funding.total_cmp(&a.funding));
+                "Funding Snapshot"
+            }
+        };
+        if let Err(e) = market::attach_liquidations(&self.database, &self.liquidators, &mut snapshots).await {
+            warn!("couldn't load liquidation totals for report {}: {}", self.config.name, e);
+        }
+        market::attach_live_activity(&self.telegram_bot.activity(), &mut snapshots);
+        let report = market::format_snapshot(title, &snapshots, &self.links);
+
+        for chat_id in &self.config.chat_ids {
+            if let Err(e) = self.telegram_bot.send_summary(*chat_id, &report).await {
+                warn!("couldn't send report {} to chat {}: {}", self.config.name, chat_id, e);
+            }
+        }
+        info!("sent report {} to {} chats", self.config.name, self.config.chat_ids.len());
+        Ok(())
+    }
+}
+
+// every midnight utc: summarize what capped users missed and reset the budgets
+pub fn spawn_daily_budget_reset(alert_budget: AlertBudget, telegram_bot: TelegramBot) {
+    let schedule = Schedule::from_str(DAILY_RESET_CRON).expect("daily reset cron is valid");
+    tokio::spawn(async move {
+        while let Some(next) = schedule.upcoming(chrono::Utc).next() {
+            let wait = (next - chrono::Utc::now()).to_std().unwrap_or_default();
+            sleep(wait).await;
+
+            for summary in alert_budget.roll_over() {
+                if let Err(e) = telegram_bot.send_summary(summary.chat_id, &summary.message).await {
+                    warn!("couldn't send daily cap summary to user {}: {}", summary.telegram_user_id, e);
+                }
+            }
+        }
+    });
+}
+
+/// Rolls trade_history up into trade_daily_rollups at startup, to fill any
+/// gap, and then nightly.
+pub fn spawn_trade_rollups(database: Database) {
+    let schedule = Schedule::from_str(ROLLUP_CRON).expect("rollup cron is valid");
+    tokio::spawn(async move {
+        loop {
+            match database.rollup_trade_days(ROLLUP_DAYS).await {
+                Ok(rows) => info!("rolled up {} coin-days of trade history", rows),
+                Err(e) => error!("couldn't roll up trade history: {}", e),
+            }
+
+            let Some(next) = schedule.upcoming(chrono::Utc).next() else {
+                break;
+            };
+            sleep((next - chrono::Utc::now()).to_std().unwrap_or_default()).await;
+        }
+    });
+}
@@ -0,0 +1,254 @@
+use anyhow::Result;
+use std::collections::HashSet;
+use tracing::info;
+use crate::database::Database;
+
+// every table and column the queries rely on, kept in step with ./migrations
+const EXPECTED_COLUMNS: &[(&str, &[&str])] = &[
+    (
+        "user_subscriptions",
+        &[
+            "id",
+            "telegram_user_id",
+            "telegram_chat_id",
+            "coin",
+            "created_at",
+            "min_notional_usd",
+            "removed_at",
+            "resubscribed_at",
+            "heartbeat_sent_at",
+            "tenant_id",
+            "priority",
+            "dex",
+            "muted_until",
+        ],
+    ),
+    (
+        "user_settings",
+        &[
+            "telegram_user_id",
+            "currency",
+            "updated_at",
+            "full_precision",
+            "charts_enabled",
+            "funding_summary",
+            "funding_summary_chat_id",
+            "funding_summary_sent_at",
+            "route_chat_id",
+            "revisit_alerts",
+            "duplicate_alerts",
+            "show_leverage",
+            "daily_alert_cap",
+            "autotune_alerts_per_day",
+            "silent_below_usd",
+            "ticker_mode",
+            "heartbeat",
+            "weekly_digest_offset_mins",
+            "weekly_digest_chat_id",
+            "weekly_digest_sent_at",
+            "tenant_id",
+            "range_alerts",
+            "digest_mins",
+            "divergence_alerts",
+        ],
+    ),
+    ("imbalance_alerts", &["telegram_user_id", "telegram_chat_id", "coin", "threshold_pct", "created_at", "tenant_id"]),
+    (
+        "trade_history",
+        &["id", "coin", "side", "px", "sz", "notional_usd", "tid", "hash", "trade_time", "recorded_at", "buyer", "seller", "tenant_id"],
+    ),
+    (
+        "notification_log",
+        &[
+            "id",
+            "telegram_user_id",
+            "telegram_chat_id",
+            "coin",
+            "trade_key",
+            "delivered",
+            "latency_ms",
+            "error",
+            "created_at",
+            "attempts",
+            "webhook_url",
+            "side",
+            "notional_usd",
+            "tenant_id",
+            "message_id",
+            "experiment",
+            "variant",
+            "taps",
+        ],
+    ),
+    ("linked_accounts", &["telegram_user_id", "address", "created_at", "tenant_id"]),
+    ("watched_wallets", &["telegram_user_id", "telegram_chat_id", "address", "created_at", "tenant_id"]),
+    (
+        "destinations",
+        &["id", "telegram_user_id", "chat_id", "webhook_url", "coin", "full_precision", "charts_enabled", "created_at", "tenant_id"],
+    ),
+    ("vwap_alerts", &["telegram_user_id", "telegram_chat_id", "coin", "threshold_pct", "created_at", "tenant_id"]),
+    ("volatility_alerts", &["telegram_user_id", "telegram_chat_id", "coin", "multiple", "created_at", "tenant_id"]),
+    ("user_roles", &["telegram_user_id", "role", "granted_by", "created_at", "tenant_id"]),
+    ("banned_users", &["telegram_user_id", "banned_by", "created_at", "tenant_id"]),
+    (
+        "wallet_snapshots",
+        &["id", "address", "account_value_usd", "taken_at", "realized_pnl_usd", "unrealized_pnl_usd", "tenant_id"],
+    ),
+    (
+        "wallet_snapshot_positions",
+        &["snapshot_id", "coin", "size", "entry_px", "position_value_usd", "unrealized_pnl_usd", "tenant_id"],
+    ),
+    (
+        "ticker_messages",
+        &["chat_id", "coin", "message_id", "day", "buys", "buy_usd", "sells", "sell_usd", "last_trade", "updated_at", "tenant_id"],
+    ),
+    (
+        "chat_settings",
+        &["chat_id", "pin_summaries", "pinned_summary_id", "updated_at", "tenant_id", "alert_prefix", "alert_suffix", "emoji_style"],
+    ),
+    (
+        "metrics_snapshots",
+        &[
+            "id",
+            "taken_at",
+            "interval_secs",
+            "trades_seen",
+            "large_trades",
+            "alerts_sent",
+            "alerts_retried",
+            "alerts_failed",
+            "p95_latency_ms",
+            "tenant_id",
+        ],
+    ),
+    ("metrics_snapshot_coins", &["snapshot_id", "coin", "trades", "volume_usd", "tenant_id"]),
+    ("trade_daily_rollups", &["day", "coin", "buys", "buy_usd", "sells", "sell_usd", "rolled_up_at", "tenant_id"]),
+    (
+        "price_alerts",
+        &[
+            "id",
+            "telegram_user_id",
+            "telegram_chat_id",
+            "coin",
+            "above",
+            "price",
+            "repeat_secs",
+            "state",
+            "fired_at",
+            "created_at",
+            "tenant_id",
+        ],
+    ),
+    ("mute_rules", &["id", "telegram_user_id", "keywords", "under_usd", "created_at", "tenant_id"]),
+    (
+        "dead_letter",
+        &[
+            "id",
+            "telegram_user_id",
+            "telegram_chat_id",
+            "webhook_url",
+            "coin",
+            "side",
+            "px",
+            "sz",
+            "trade_time",
+            "tid",
+            "hash",
+            "notional_usd",
+            "trade_key",
+            "priority",
+            "attempts",
+            "error",
+            "created_at",
+            "tenant_id",
+        ],
+    ),
+    (
+        "held_alerts",
+        &[
+            "id",
+            "telegram_user_id",
+            "telegram_chat_id",
+            "coin",
+            "side",
+            "px",
+            "sz",
+            "trade_time",
+            "tid",
+            "hash",
+            "notional_usd",
+            "trade_key",
+            "created_at",
+            "tenant_id",
+        ],
+    ),
+];
+
+// the lookups that would crawl without them
+const EXPECTED_INDEXES: &[&str] = &[
+    "user_subscriptions_coin_idx",
+    "user_subscriptions_user_idx",
+    "imbalance_alerts_coin_idx",
+    "trade_history_coin_time_idx",
+    "notification_log_chat_time_idx",
+    "notification_log_user_coin_idx",
+    "notification_log_chat_coin_idx",
+    "notification_log_experiment_idx",
+    "watched_wallets_address_idx",
+    "destinations_user_idx",
+    "vwap_alerts_coin_idx",
+    "volatility_alerts_coin_idx",
+    "wallet_snapshots_address_idx",
+    "metrics_snapshots_taken_at_idx",
+    "trade_daily_rollups_coin_idx",
+    "trade_history_recorded_at_idx",
+    "price_alerts_coin_idx",
+    "price_alerts_user_idx",
+    "mute_rules_user_idx",
+    "dead_letter_user_idx",
+    "held_alerts_user_idx",
+];
+
+/// Checks the database has everything this build queries, so a drifted schema
+/// fails at startup with a list of what's missing instead of mid-trade with
+/// `column does not exist`.
+pub async fn verify(database: &Database) -> Result<()> {
+    let mut problems = Vec::new();
+
+    for (version, description) in database.failed_migrations().await? {
+        problems.push(format!("migration {} ({}) failed partway and needs fixing by hand", version, description));
+    }
+
+    let columns: HashSet<(String, String)> = database.schema_columns().await?.into_iter().collect();
+    let tables: HashSet<&str> = columns.iter().map(|(table, _)| table.as_str()).collect();
+    for (table, expected) in EXPECTED_COLUMNS {
+        if !tables.contains(table) {
+            problems.push(format!("table {}", table));
+            continue;
+        }
+        for column in *expected {
+            if !columns.contains(&(table.to_string(), column.to_string())) {
+                problems.push(format!("column {}.{}", table, column));
+            }
+        }
+    }
+
+    let indexes: HashSet<String> = database.schema_indexes().await?.into_iter().collect();
+    for index in EXPECTED_INDEXES {
+        if !indexes.contains(*index) {
+            problems.push(format!("index {}", index));
+        }
+    }
+
+    if !problems.is_empty() {
+        anyhow::bail!(
+            "database schema doesn't match this build, missing:\n  - {}\n\
+             migrations run at startup and should have created these; if they were dropped or changed \
+             by hand, recreate them from ./migrations or restore from a backup",
+            problems.join("\n  - ")
+        );
+    }
+
+    info!("db schema verified ({} tables)", EXPECTED_COLUMNS.len());
+    Ok(())
+}
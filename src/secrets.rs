@@ -0,0 +1,118 @@
+use anyhow::{Context, Result};
+use serde_json::Value;
+use tokio::process::Command;
+
+// config values can point at a secret instead of holding it:
+//   env:NAME                      environment variable
+//   file:/run/secrets/bot_token   file contents, trimmed
+//   vault:secret/data/hl#token    vault kv v2, needs VAULT_ADDR and VAULT_TOKEN
+//   aws-sm:hl-bot/prod#token      aws secrets manager via the aws cli; `#key`
+//                                 picks a field out of a json secret
+// anything else is used as-is.
+enum SecretRef<'a> {
+    Plain(&'a str),
+    Env(&'a str),
+    File(&'a str),
+    Vault { path: &'a str, key: &'a str },
+    AwsSecretsManager { id: &'a str, key: Option<&'a str> },
+}
+
+impl<'a> SecretRef<'a> {
+    fn parse(value: &'a str) -> Result<Self> {
+        let Some((scheme, rest)) = value.split_once(':') else {
+            return Ok(SecretRef::Plain(value));
+        };
+
+        let secret_ref = match scheme {
+            "env" => SecretRef::Env(rest),
+            "file" => SecretRef::File(rest),
+            "vault" => {
+                let (path, key) = rest
+                    .split_once('#')
+                    .with_context(|| format!("vault secret {:?} needs a #key", value))?;
+                SecretRef::Vault { path, key }
+            }
+            "aws-sm" => match rest.split_once('#') {
+                Some((id, key)) => SecretRef::AwsSecretsManager { id, key: Some(key) },
+                None => SecretRef::AwsSecretsManager { id: rest, key: None },
+            },
+            _ => SecretRef::Plain(value),
+        };
+        Ok(secret_ref)
+    }
+}
+
+/// Resolves a config value that may be a secret reference.
+pub async fn resolve(value: &str) -> Result<String> {
+    let secret = match SecretRef::parse(value)? {
+        SecretRef::Plain(value) => return Ok(value.to_string()),
+        SecretRef::Env(name) => std::env::var(name).with_context(|| format!("env var {} is not set", name))?,
+        SecretRef::File(path) => read_file(path).await?,
+        SecretRef::Vault { path, key } => read_vault(path, key).await?,
+        SecretRef::AwsSecretsManager { id, key } => read_aws_secret(id, key).await?,
+    };
+    Ok(secret.trim().to_string())
+}
+
+/// Picks between an inline value and its `*_file` variant, then resolves it.
+pub async fn resolve_field(name: &str, value: &str, file: Option<&str>) -> Result<String> {
+    let resolved = match file {
+        Some(path) => read_file(path).await?.trim().to_string(),
+        None => resolve(value).await?,
+    };
+    if resolved.is_empty() {
+        anyhow::bail!("{} is not set", name);
+    }
+    Ok(resolved)
+}
+
+async fn read_file(path: &str) -> Result<String> {
+    tokio::fs::read_to_string(path)
+        .await
+        .with_context(|| format!("couldn't read secret file {}", path))
+}
+
+async fn read_vault(path: &str, key: &str) -> Result<String> {
+    let addr = std::env::var("VAULT_ADDR").context("VAULT_ADDR is not set")?;
+    let token = std::env::var("VAULT_TOKEN").context("VAULT_TOKEN is not set")?;
+
+    let response: Value = reqwest::Client::new()
+        .get(format!("{}/v1/{}", addr.trim_end_matches('/'), path.trim_start_matches('/')))
+        .header("X-Vault-Token", token)
+        .send()
+        .await?
+        .error_for_status()?
+        .json()
+        .await?;
+
+    response["data"]["data"][key]
+        .as_str()
+        .map(str::to_string)
+        .with_context(|| format!("vault secret {} has no {}", path, key))
+}
+
+async fn read_aws_secret(id: &str, key: Option<&str>) -> Result<String> {
+    let output = Command::new("aws")
+        .args(["secretsmanager", "get-secret-value", "--secret-id", id])
+        .args(["--query", "SecretString", "--output", "text"])
+        .output()
+        .await
+        .context("couldn't run the aws cli")?;
+    if !output.status.success() {
+        anyhow::bail!(
+            "aws secretsmanager lookup for {} failed: {}",
+            id,
+            String::from_utf8_lossy(&output.stderr).trim()
+        );
+    }
+
+    let secret = String::from_utf8(output.stdout)?;
+    let Some(key) = key else {
+        return Ok(secret);
+    };
+    let fields: Value = serde_json::from_str(&secret).with_context(|| format!("aws secret {} isn't json", id))?;
+    fields[key]
+        .as_str()
+        .map(str::to_string)
+        .with_context(|| format!("aws secret {} has no {}", id, key))
+}
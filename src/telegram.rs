@@ -1,12 +1,48 @@
 use anyhow::Result;
 use teloxide::{
+    net::Download,
+    ApiError,
     prelude::*,
-    types::Me,
+    types::{Chat, ChatMemberKind, Document, InlineKeyboardButton, InlineKeyboardMarkup, InputFile, Me, MessageId, Recipient},
     utils::command::BotCommands,
 };
-use tracing::{info, error};
+use tracing::{info, error, warn};
 use tokio::sync::mpsc;
-use crate::{database::Database, hyperliquid::HyperliquidClient, coordinator::SubscriptionEvent};
+use tokio::time::Instant;
+use std::future::IntoFuture;
+use std::collections::{hash_map::Entry, HashMap};
+use std::sync::Arc;
+use crate::{
+    activity::ActivityTracker,
+    errors::ErrorLog,
+    experiments,
+    backup::{DestinationBackup, UserBackup, BACKUP_VERSION},
+    candles::CandleCache,
+    chart::{ChartRenderer, PendingChart},
+    config::Config,
+    delivery::{self, AlertTarget},
+    database::{self, ChatSettings, Database, DuplicatePreference, MuteRule, PriceAlert, SubscriptionRecord, UserSettings, WalletPnl},
+    failover::BotFailover,
+    feed_health,
+    formatting::{self, AlertText, Branding, EmojiStyle},
+    funding::FundingPeriod,
+    liquidations,
+    market::{self, AssetMetadata},
+    fx::{self, FxRates},
+    outbound::{OutboundQueue, Priority},
+    hyperliquid::{self, BookImbalance, CoinSymbol, HyperliquidClient, WebSocketManager, WsTrade},
+    metrics::Metrics,
+    prices::{LevelRevisit, PriceEngine},
+    mutes::{self, MuteList, MAX_MUTE_RULES},
+    price_alerts,
+    redact,
+    roles::{Permission, Role, RoleDirectory},
+    tuning::{self, SUGGEST_LOOKBACK_DAYS},
+    volatility::VolatilityReading,
+    vwap::VwapReading,
+    warmup,
+    coordinator::CoordinatorCommand,
+};
 
 #[derive(BotCommands, Clone, Debug)]
 #[command(rename_rule = "lowercase", description = "Hyperliquid Trade Alerts")]
@@ -14,41 +50,445 @@ pub enum Command {
     #[command(description = "Start the bot")]
     Start,
     
-    #[command(description = "Subscribe to a coin (e.g. /subscribe ETH)")]
+    #[command(description = "Subscribe to one or more coins (e.g. /subscribe ETH, /subscribe ETH BTC SOL)")]
     Subscribe(String),
     
     #[command(description = "Unsubscribe from a coin (e.g. /unsubscribe ETH)")]
     Unsubscribe(String),
+
+    #[command(description = "Unsubscribe from every coin")]
+    UnsubscribeAll,
     
     #[command(description = "List your current subscriptions")]
     List,
     
     #[command(description = "Show help message")]
     Help,
+
+    #[command(description = "Show bot version and build info")]
+    Version,
+
+    #[command(description = "Also show alert amounts in a fiat currency (e.g. /currency EUR)")]
+    Currency(String),
+
+    #[command(description = "Toggle compact amounts like $1.25M (/compact on|off)")]
+    Compact(String),
+
+    #[command(description = "Attach a 1h candle chart to alerts (/charts on|off)")]
+    Charts(String),
+
+    #[command(description = "A Sunday evening recap of your coins' week, in your UTC offset (/weeklydigest on +2, /weeklydigest off)")]
+    WeeklyDigest(String),
+
+    #[command(description = "Alert again if price returns to a whale's entry within 24h (/revisit on|off)")]
+    Revisit(String),
+
+    #[command(description = "Show the coin's max leverage in alerts (/leverage on|off)")]
+    Leverage(String),
+
+    #[command(description = "Keep one edited running-totals message per coin instead of an alert per trade (/ticker on|off)")]
+    Ticker(String),
+
+    #[command(description = "A note with price and volume when a coin goes 24h without a trade over your threshold (/heartbeat on|off)")]
+    Heartbeat(String),
+
+    #[command(description = "Alert when a subscribed coin makes a new high or low over the range window (/rangealerts on|off)")]
+    RangeAlerts(String),
+
+    #[command(description = "Alert when a subscribed coin's mark price strays from its oracle price, a sign of squeezes and broken pegs (/divergence on|off)")]
+    Divergence(String),
+
+    #[command(description = "Pin each daily summary or report in this group, unpinning the last (/pinsummary on|off [chat_id|@channel])")]
+    PinSummary(String),
+
+    #[command(description = "Dress up alerts in a group or channel you admin (/branding prefix <text>, /branding suffix <text>, /branding emoji off|sides|full, /branding clear [chat_id|@channel first])")]
+    Branding(String),
+
+    #[command(description = "Most trade alerts per day, the rest summarized at midnight UTC (e.g. /dailycap 50, /dailycap off)")]
+    DailyCap(String),
+
+    #[command(description = "Get trade alerts as one digest every so many minutes, grouped by coin (e.g. /digestmode 60, /digestmode off)")]
+    DigestMode(String),
+
+    #[command(description = "Send a coin's alerts straight away, skipping digests, tickers and your daily cap (e.g. /priority BTC, /priority BTC off)")]
+    Priority(String),
+
+    #[command(description = "Deliver smaller alerts without a sound (e.g. /silent below 100000, /silent off)")]
+    Silent(String),
+
+    #[command(description = "Mute alerts on any coin whose text has these words, optionally only under a size (e.g. /mute sell under 100k)")]
+    Mute(String),
+
+    #[command(description = "Remove a mute rule (e.g. /unmute 3, /unmute all)")]
+    Unmute(String),
+
+    #[command(description = "When a group you're in gets the same alert as your DMs, keep both, the group copy or the DM (/duplicates both|group|dm)")]
+    Duplicates(String),
+
+    #[command(description = "Show the current price and 24h sparkline (e.g. /price ETH)")]
+    Price(String),
+
+    #[command(description = "Market snapshot: price, volume, OI and funding (e.g. /info ETH BTC, or /info for the top coins)")]
+    Info(String),
+
+    #[command(description = "Large-trade buy/sell split per day (e.g. /history ETH, /history ETH 30)")]
+    History(String),
+
+    #[command(description = "Show what an alert would look like with your settings (e.g. /preview ETH 750000 buy)")]
+    Preview(String),
+
+    #[command(description = "Alert on order book imbalance (e.g. /imbalance ETH 70, /imbalance ETH off)")]
+    Imbalance(String),
+
+    #[command(description = "Alert when price strays from its 1h VWAP (e.g. /vwapalert ETH 2, /vwapalert ETH off)")]
+    VwapAlert(String),
+
+    #[command(description = "Alert when volatility runs a multiple of normal (e.g. /volalert ETH 3, /volalert ETH off)")]
+    VolAlert(String),
+
+    #[command(description = "Alert when price crosses a level, optionally every time (e.g. /pricealert ETH > 3000 repeat 1h)")]
+    PriceAlert(String),
+
+    #[command(description = "Link a Hyperliquid address to your account (e.g. /link 0xabc...)")]
+    Link(String),
+
+    #[command(description = "Unlink a Hyperliquid address")]
+    Unlink(String),
+
+    #[command(description = "Funding summary for linked addresses (/fundingsummary daily|weekly|off)")]
+    FundingSummary(String),
+
+    #[command(description = "Alert on a wallet's large trades, transfers and position changes (e.g. /watch 0xabc... 0xdef...)")]
+    Watch(String),
+
+    #[command(description = "Stop watching wallets (e.g. /unwatch 0xabc..., /unwatch all)")]
+    Unwatch(String),
+
+    #[command(description = "Most active large traders on a coin over 24h (e.g. /topwallets ETH)")]
+    TopWallets(String),
+
+    #[command(description = "Best and worst watched wallets by 7d PnL")]
+    WalletBoard,
+
+    #[command(description = "Suggest a trade size threshold for about N alerts a day (e.g. /suggestthreshold ETH 10, /suggestthreshold ETH off)")]
+    SuggestThreshold(String),
+
+    #[command(description = "Keep each coin's threshold tuned to about N alerts a day (e.g. /autotune 10, /autotune off)")]
+    Autotune(String),
+
+    #[command(description = "Your alert stats for a coin over 7 days (e.g. /coinstats ETH)")]
+    CoinStats(String),
+
+    #[command(description = "Your subscriptions past and present, and what resubscribing would restore")]
+    MyStats,
+
+    #[command(description = "Send your alerts to a group or channel you admin (/route @channel, /route off)")]
+    Route(String),
+
+    #[command(description = "Extra places to send alerts (/destination add <chat|@channel|https://url> [coin] [compact|full] [charts|nocharts])")]
+    Destination(String),
+
+    #[command(description = "Download your subscriptions and settings as a file")]
+    Export,
+
+    #[command(description = "Restore from an /export file (send the file with /import as its caption)")]
+    Import,
+
+    #[command(description = "Erase your subscriptions, settings, alerts and history (/deletedata confirm)")]
+    DeleteData(String),
+
+    #[command(description = "off")]
+    Broadcast(String),
+
+    #[command(description = "off")]
+    Ban(String),
+
+    #[command(description = "off")]
+    Unban(String),
+
+    #[command(description = "off")]
+    Role(String),
+
+    #[command(description = "off")]
+    Feeds,
+
+    #[command(description = "off")]
+    Resync,
+
+    #[command(description = "off")]
+    Stats,
+
+    #[command(description = "off")]
+    Experiment,
+
+    #[command(description = "off")]
+    DeadLetters(String),
+
+    #[command(description = "off")]
+    Error(String),
+}
+
+impl Command {
+    /// What a user needs to run this command, if it's restricted.
+    fn required_permission(&self) -> Option<Permission> {
+        match self {
+            Command::Broadcast(_) => Some(Permission::Broadcast),
+            Command::Ban(_) | Command::Unban(_) => Some(Permission::Ban),
+            Command::Feeds | Command::Resync => Some(Permission::FeedControl),
+            Command::Stats | Command::Error(_) | Command::Experiment => Some(Permission::Stats),
+            Command::DeadLetters(_) => Some(Permission::DeadLetters),
+            Command::Role(_) => Some(Permission::ManageRoles),
+            _ => None,
+        }
+    }
+
+    /// Whether running this can change what the trade fan-out reads about the
+    /// caller: their subscriptions, alert settings, destinations or routing.
+    fn changes_fan_out(&self) -> bool {
+        matches!(
+            self,
+            Command::Unsubscribe(_)
+                | Command::Currency(_)
+                | Command::Compact(_)
+                | Command::Charts(_)
+                | Command::Revisit(_)
+                | Command::Leverage(_)
+                | Command::Ticker(_)
+                | Command::Heartbeat(_)
+                | Command::RangeAlerts(_)
+                | Command::Divergence(_)
+                | Command::DailyCap(_)
+                | Command::DigestMode(_)
+                | Command::Priority(_)
+                | Command::Silent(_)
+                | Command::Duplicates(_)
+                | Command::Autotune(_)
+                | Command::Route(_)
+                | Command::Destination(_)
+        )
+    }
+}
+
+const FEED_RESTART_PREFIX: &str = "feed_restart:";
+const WATCH_WALLET_PREFIX: &str = "watch_wallet:";
+const APPLY_THRESHOLD_PREFIX: &str = "apply_threshold:";
+const ALERT_ACTION_PREFIX: &str = "alert:";
+
+// how long an alert's mute button quiets the coin
+const ALERT_MUTE_HOURS: i64 = 1;
+
+// /suggestthreshold aims for this many alerts a day unless told otherwise
+const DEFAULT_ALERTS_PER_DAY: usize = 10;
+
+// /autotune above this is as good as no tuning
+const MAX_AUTOTUNE_ALERTS_PER_DAY: u32 = 500;
+
+// bare /info shows this many coins, busiest first
+const INFO_TOP_COINS: usize = 10;
+
+// /coinstats looks back this far in the notification log
+const COIN_STATS_DAYS: i64 = 7;
+
+// /history reads the nightly rollups, so only complete days
+const HISTORY_DEFAULT_DAYS: i64 = 7;
+const HISTORY_MAX_DAYS: i64 = 90;
+
+// /stats shows persisted counters per day over this window
+const STATS_TREND_DAYS: i64 = 7;
+const STATS_BUSIEST_COINS: i64 = 5;
+
+// /stats reports subscription churn over this window
+const CHURN_DAYS: i64 = 7;
+
+// /deadletters lists this many, newest first
+const DEAD_LETTERS_SHOWN: i64 = 20;
+
+// what's known of whether a user is in a chat
+#[derive(Clone, Copy)]
+enum Membership {
+    Known(bool, Instant),
+    // telegram couldn't say; counts as not a member until asked again
+    Failed(Instant),
+    // being looked up, with the last known answer meanwhile
+    Checking(bool),
 }
 
+type MembershipCache = HashMap<(i64, i64), Membership>;
+
+// /branding updates the cache as it writes; the ttl covers edits made straight
+// in the db
+const BRANDING_CACHE_TTL: std::time::Duration = std::time::Duration::from_secs(5 * 60);
+
+// a branding line is a channel's tagline, not a second alert
+const MAX_BRANDING_CHARS: usize = 200;
+
+const MEMBERSHIP_CACHE_TTL: std::time::Duration = std::time::Duration::from_secs(60 * 60);
+// a failed lookup is tried again this soon
+const MEMBERSHIP_RETRY_AFTER: std::time::Duration = std::time::Duration::from_secs(60);
+
+// /dailycap above this is as good as no cap
+const MAX_DAILY_ALERT_CAP: u32 = 1000;
+
+// /digestmode bounds, in minutes: quicker than this isn't a digest, and a day
+// is as long as anyone should wait for an alert
+const MIN_DIGEST_MINS: u32 = 5;
+const MAX_DIGEST_MINS: u32 = 24 * 60;
+
+// priority alerts jump everyone else's queue, so only a few coins get it
+const MAX_PRIORITY_COINS: usize = 3;
+
+// exports are a few KB; anything much bigger isn't one
+const MAX_IMPORT_BYTES: u32 = 256 * 1024;
+const EXPORT_FILE_NAME: &str = "hl-alerts-export.json";
+
+// /topwallets looks back this far in trade history
+const TOP_WALLETS_HOURS: i64 = 24;
+const TOP_WALLETS_LIMIT: i64 = 10;
+
+const LIVE_SHARE_MIN_COVERAGE: std::time::Duration = std::time::Duration::from_secs(60 * 60);
+
+const WALLET_BOARD_DAYS: i64 = 7;
+// wallets shown at each end of the board
+const WALLET_BOARD_SIZE: usize = 5;
+
 #[derive(Clone)]
 pub struct TelegramBot {
     bot: Bot,
+    config: Config,
     database: Database,
     hyperliquid_client: HyperliquidClient,
-    event_sender: mpsc::UnboundedSender<SubscriptionEvent>,
+    event_sender: mpsc::UnboundedSender<CoordinatorCommand>,
+    ws_manager: Arc<WebSocketManager>,
+    fx_rates: FxRates,
+    price_engine: PriceEngine,
+    metrics: Metrics,
+    candles: CandleCache,
+    chart_renderer: ChartRenderer,
+    // who alerts go out through; `bot` stays the primary for polling
+    failover: BotFailover,
+    asset_metadata: AssetMetadata,
+    outbound: OutboundQueue,
+    roles: RoleDirectory,
+    mutes: MuteList,
+    activity: ActivityTracker,
+    errors: ErrorLog,
+    // (chat, user) -> is a member, for resolving group/DM duplicates
+    memberships: Arc<std::sync::Mutex<MembershipCache>>,
+    // chat -> its /branding, for every alert to a group or channel
+    brandings: Arc<std::sync::Mutex<HashMap<i64, (Branding, Instant)>>>,
+    // set when the startup warmup didn't finish: what's still loading lazily
+    degraded: Arc<std::sync::OnceLock<String>>,
+    started_at: Instant,
 }
 
 impl TelegramBot {
+    #[allow(clippy::too_many_arguments)]
     pub fn new(
-        bot_token: String, 
+        config: Config,
         database: Database, 
         hyperliquid_client: HyperliquidClient,
-        event_sender: mpsc::UnboundedSender<SubscriptionEvent>
+        event_sender: mpsc::UnboundedSender<CoordinatorCommand>,
+        ws_manager: Arc<WebSocketManager>,
+        fx_rates: FxRates,
+        price_engine: PriceEngine,
+        metrics: Metrics,
+        candles: CandleCache,
+        outbound: OutboundQueue,
+        roles: RoleDirectory,
+        mutes: MuteList,
+        activity: ActivityTracker,
+        started_at: Instant,
     ) -> Self {
-        let bot = Bot::new(bot_token);
+        // the url was checked when the config loaded
+        let api_url = config.telegram.api_url().ok().flatten();
+        let new_bot = |token: &str| match &api_url {
+            Some(url) => Bot::new(token).set_api_url(url.clone()),
+            None => Bot::new(token),
+        };
+        let bot = new_bot(&config.telegram.bot_token);
+        let chart_renderer = ChartRenderer::new(candles.clone());
+        let asset_metadata = AssetMetadata::new(hyperliquid_client.clone());
+        let backup_bot = Some(&config.telegram.backup_bot_token)
+            .filter(|token| !token.is_empty())
+            .map(|token| new_bot(token));
+        let failover = BotFailover::new(bot.clone(), backup_bot, config.telegram.failover_after_errors);
         
         TelegramBot {
             bot,
+            config,
             database,
             hyperliquid_client,
             event_sender,
+            ws_manager,
+            fx_rates,
+            price_engine,
+            metrics,
+            candles,
+            chart_renderer,
+            failover,
+            asset_metadata,
+            outbound,
+            roles,
+            mutes,
+            activity,
+            errors: ErrorLog::new(),
+            memberships: Arc::new(std::sync::Mutex::new(HashMap::new())),
+            brandings: Arc::new(std::sync::Mutex::new(HashMap::new())),
+            degraded: Arc::new(std::sync::OnceLock::new()),
+            started_at,
+        }
+    }
+
+    /// The same bot, sharing every cache with this one, but telling
+    /// `event_sender` about the changes commands make.
+    pub fn with_event_sender(&self, event_sender: mpsc::UnboundedSender<CoordinatorCommand>) -> Self {
+        TelegramBot {
+            event_sender,
+            ..self.clone()
+        }
+    }
+
+    /// Logs a failed request under a fresh error id and returns the generic
+    /// apology tagged with it, so a user's report can be matched to the log.
+    fn error_reply(&self, user_id: i64, chat_id: i64, context: String) -> String {
+        self.error_reply_as(user_id, chat_id, "Sorry, there was an error. Please try again.", context)
+    }
+
+    fn error_reply_as(&self, user_id: i64, chat_id: i64, reply: &str, context: String) -> String {
+        let id = self.errors.record(user_id, chat_id, context);
+        format!("{}\n\nError ID: {}", reply, id)
+    }
+
+    /// The live 24h activity the trade feeds report into.
+    pub fn activity(&self) -> ActivityTracker {
+        self.activity.clone()
+    }
+
+    /// Loads what the first commands and alerts would otherwise fetch cold,
+    /// into caches every clone of this bot shares. Run before `start`; errors
+    /// only if it falls short and degraded mode isn't allowed.
+    pub async fn warm_up(&self) -> Result<()> {
+        let config = &self.config.warmup;
+        if !config.enabled {
+            return Ok(());
+        }
+
+        let report = warmup::run(config, &self.hyperliquid_client, &self.asset_metadata, &self.price_engine).await;
+        if report.is_complete() {
+            return Ok(());
+        }
+        if !config.allow_degraded {
+            anyhow::bail!("warmup didn't load {} within {}s", report.missing(), config.timeout_secs);
+        }
+        warn!("starting degraded, {} will load on first use", report.missing());
+        let _ = self.degraded.set(report.missing());
+        Ok(())
+    }
+
+    /// Tells the coordinator about a change it would otherwise only see in the db.
+    pub fn notify_coordinator(&self, command: CoordinatorCommand) {
+        if let Err(e) = self.event_sender.send(command) {
+            error!("couldn't send coordinator command: {}", e);
         }
     }
 
@@ -59,20 +499,56 @@ impl TelegramBot {
         info!("Bot started: @{}", me.username());
 
         let bot_clone = self.bot.clone();
-        let database_clone = self.database.clone();
-        let hyperliquid_clone = self.hyperliquid_client.clone();
-        let event_sender_clone = self.event_sender.clone();
+        let state = self.clone();
+        let auth_state = self.clone();
+        let callback_state = self.clone();
+        let import_state = self.clone();
         
-        let handler = Update::filter_message()
-            .filter_command::<Command>()
-            .endpoint(move |bot: Bot, msg: Message, cmd: Command| {
-                let database = database_clone.clone();
-                let hyperliquid_client = hyperliquid_clone.clone();
-                let event_sender = event_sender_clone.clone();
-                async move {
-                    handle_command(bot, msg, cmd, database, hyperliquid_client, event_sender).await
-                }
-            });
+        let handler = dptree::entry()
+            .branch(
+                // files can't carry a command, so /import rides in the caption
+                Update::filter_message()
+                    .filter(|msg: Message| {
+                        msg.document().is_some() && msg.caption().is_some_and(|caption| caption.trim_start().starts_with("/import"))
+                    })
+                    .endpoint(move |bot: Bot, msg: Message| {
+                        let state = import_state.clone();
+                        async move {
+                            handle_import_upload(bot, msg, state).await
+                        }
+                    }),
+            )
+            .branch(
+                Update::filter_message()
+                    .filter_command::<Command>()
+                    .filter_async(move |bot: Bot, msg: Message, cmd: Command| {
+                        let state = auth_state.clone();
+                        async move {
+                            authorize(&bot, &msg, &cmd, &state).await
+                        }
+                    })
+                    .endpoint(move |bot: Bot, msg: Message, cmd: Command| {
+                        let state = state.clone();
+                        async move {
+                            let changes_fan_out = cmd.changes_fan_out().then(|| msg.from().map_or(msg.chat.id.0, |user| user.id.0 as i64));
+                            let handled = handle_command(bot, msg, cmd, state.clone()).await;
+                            // even a failed command may have written something
+                            if let Some(telegram_user_id) = changes_fan_out {
+                                state.notify_coordinator(CoordinatorCommand::SettingsInvalidated { telegram_user_id });
+                            }
+                            handled
+                        }
+                    }),
+            )
+            .branch(
+                Update::filter_callback_query()
+                    .endpoint(move |bot: Bot, query: CallbackQuery| {
+                        let state = callback_state.clone();
+                        async move {
+                            handle_callback(bot, query, state).await
+                        }
+                    }),
+            );
 
         Dispatcher::builder(bot_clone, handler)
             .enable_ctrlc_handler()
@@ -83,181 +559,4153 @@ impl TelegramBot {
         Ok(())
     }
 
-    pub async fn send_trade_notification(
-        &self, 
-        chat_id: i64, 
-        coin: &str, 
-        side: &str,
-        price: &str,
-        notional_usd: f64
-    ) -> Result<()> {
-        let side_text = if side == "B" { "BUY" } else { "SELL" };
-        
-        let message = format!(
-            "{} Trade Alert\n\nAmount: ${:.2}\nType: {}\nPrice: ${}",
-            coin,
-            notional_usd,
-            side_text,
-            price
-        );
-
-        self.bot.send_message(ChatId(chat_id), message).await?;
-        info!("sent {} trade notification to chat {}", coin, chat_id);
-        Ok(())
-    }
-}
-
-async fn handle_command(
-    bot: Bot, 
-    msg: Message, 
-    cmd: Command, 
-    database: Database, 
-    mut hyperliquid_client: HyperliquidClient,
-    event_sender: mpsc::UnboundedSender<SubscriptionEvent>
-) -> ResponseResult<()> {
-    let chat_id = msg.chat.id.0;
-    let user_id = msg.from().map(|user| user.id.0 as i64).unwrap_or(chat_id);
-    
-    info!("Received command from user {}: {:?}", user_id, cmd);
+    /// Whether `user_id` is in `chat_id`, as far as is known. Fan-out asks on
+    /// every trade, so this never waits on telegram: an unknown or stale
+    /// answer is looked up in the background for later trades. Unknown counts
+    /// as not a member.
+    pub fn is_chat_member(&self, chat_id: i64, user_id: i64) -> bool {
+        let Ok(mut memberships) = self.memberships.lock() else {
+            return false;
+        };
+        let last_known = match memberships.get(&(chat_id, user_id)) {
+            Some(Membership::Known(is_member, checked_at)) if checked_at.elapsed() < MEMBERSHIP_CACHE_TTL => {
+                return *is_member;
+            }
+            Some(Membership::Failed(checked_at)) if checked_at.elapsed() < MEMBERSHIP_RETRY_AFTER => return false,
+            Some(Membership::Checking(last_known)) => return *last_known,
+            Some(Membership::Known(is_member, _)) => *is_member,
+            Some(Membership::Failed(_)) | None => false,
+        };
+        memberships.insert((chat_id, user_id), Membership::Checking(last_known));
+        drop(memberships);
 
-    match cmd {
-        Command::Start => {
-            //subscribe to btc for every new user
-            match database.add_subscription(user_id, chat_id, "BTC").await {
-                Ok(true) => {
-                    let welcome_msg = "Welcome to Hyperliquid Trade Alerts!\n\nYou've been automatically subscribed to BTC trades.\n\nUse /subscribe <coin> to add more coins!";
-                    bot.send_message(msg.chat.id, welcome_msg).await?;
-                    info!("new user {} auto-subscribed to BTC", user_id);
-                    
-                    if let Err(e) = event_sender.send(SubscriptionEvent::UserSubscribed { 
-                        coin: "BTC".to_string() 
-                    }) {
-                        error!("Failed to send BTC subscription event: {}", e);
-                    }
-                }
-                Ok(false) => {
-                    let welcome_msg = "Welcome back to Hyperliquid Trade Alerts!\n\nUse /subscribe <coin> to add more subscriptions!";
-                    bot.send_message(msg.chat.id, welcome_msg).await?;
-                }
+        let bot = self.bot.clone();
+        let memberships = self.memberships.clone();
+        tokio::spawn(async move {
+            let membership = match bot.get_chat_member(ChatId(chat_id), UserId(user_id as u64)).await {
+                Ok(member) => Membership::Known(member.kind.is_present(), Instant::now()),
                 Err(e) => {
-                    error!("Failed to auto-subscribe user {} to BTC: {}", user_id, e);
-                    let welcome_msg = "Welcome to Hyperliquid Trade Alerts!\n\nUse /subscribe <coin> to get started!";
-                    bot.send_message(msg.chat.id, welcome_msg).await?;
+                    warn!("couldn't check if user {} is in chat {}: {}", user_id, chat_id, e);
+                    Membership::Failed(Instant::now())
                 }
+            };
+            if let Ok(mut memberships) = memberships.lock() {
+                memberships.insert((chat_id, user_id), membership);
             }
+        });
+        last_known
+    }
+
+    /// A group or channel's /branding; DMs never have any.
+    async fn chat_branding(&self, chat_id: i64) -> Branding {
+        if chat_id >= 0 {
+            return Branding::default();
+        }
+        let cached = self
+            .brandings
+            .lock()
+            .ok()
+            .and_then(|brandings| brandings.get(&chat_id).cloned())
+            .filter(|(_, loaded_at)| loaded_at.elapsed() < BRANDING_CACHE_TTL);
+        if let Some((branding, _)) = cached {
+            return branding;
         }
-        
-        Command::Subscribe(coin_arg) => {
-            if coin_arg.trim().is_empty() {
-                bot.send_message(msg.chat.id, "Please specify a coin. Example: /subscribe ETH").await?;
-                return Ok(());
-            }
 
-            let coin = coin_arg.trim().to_uppercase();
-            
-            // make sure coin exists
-            match hyperliquid_client.coin_exists(&coin).await {
-                Ok(true) => {
-                    match database.add_subscription(user_id, chat_id, &coin).await {
-                        Ok(true) => {
-                            let success_msg = format!("Successfully subscribed to {} trades!", coin);
-                            bot.send_message(msg.chat.id, success_msg).await?;
-                            info!("user {} subscribed to {}", user_id, coin);
-                            
-                            //send to coordinator to open ws
-                            if let Err(e) = event_sender.send(SubscriptionEvent::UserSubscribed { 
-                                coin: coin.clone() 
-                            }) {
-                                error!("couldn't send subscription event for {}: {}", coin, e);
-                            }
-                        }
-                        Ok(false) => {
-                            let already_msg = format!("You're already subscribed to {} trades.", coin);
-                            bot.send_message(msg.chat.id, already_msg).await?;
-                        }
-                        Err(e) => {
-                            error!("db error for user {} subscribing to {}: {}", user_id, coin, e);
-                            bot.send_message(msg.chat.id, "Sorry, there was an error. Please try again.").await?;
-                        }
-                    }
-                }
-                Ok(false) => {
-                    let invalid_msg = format!("{} is not available on Hyperliquid. Use /help to see valid coins.", coin);
-                    bot.send_message(msg.chat.id, invalid_msg).await?;
-                }
-                Err(e) => {
-                    error!("couldn't validate {} for {}: {}", coin, user_id, e);
-                    bot.send_message(msg.chat.id, "Sorry, there was an error validating the coin. Please try again.").await?;
-                }
+        // an alert without its branding beats no alert
+        let branding = match self.database.get_chat_settings(chat_id).await {
+            Ok(settings) => settings.branding,
+            Err(e) => {
+                warn!("couldn't load branding for chat {}: {}", chat_id, e);
+                return Branding::default();
             }
+        };
+        self.remember_branding(chat_id, branding.clone());
+        branding
+    }
+
+    fn remember_branding(&self, chat_id: i64, branding: Branding) {
+        if let Ok(mut brandings) = self.brandings.lock() {
+            brandings.insert(chat_id, (branding, Instant::now()));
         }
+    }
 
-        Command::Unsubscribe(coin_arg) => {
-            if coin_arg.trim().is_empty() {
-                bot.send_message(msg.chat.id, "Please specify a coin. Example: /unsubscribe ETH").await?;
-                return Ok(());
+    pub async fn trade_chart(&self, coin: &str) -> Result<Arc<Vec<u8>>> {
+        self.chart_renderer.trade_chart(coin).await
+    }
+
+    pub fn spawn_trade_chart(&self, coin: &str) -> PendingChart {
+        self.chart_renderer.spawn_trade_chart(coin)
+    }
+
+    /// Alert text for a trade, shared by telegram sends and webhook payloads.
+    pub async fn trade_message(&self, trade: &WsTrade, notional_usd: f64, settings: &UserSettings) -> String {
+        self.alert_text(trade, notional_usd, settings).await.plain()
+    }
+
+    async fn alert_text(&self, trade: &WsTrade, notional_usd: f64, settings: &UserSettings) -> AlertText {
+        let mut fiat = None;
+        if let Some(currency) = settings.currency.as_deref().filter(|c| *c != "USD") {
+            if let Some(converted) = self.fx_rates.convert(notional_usd, currency).await {
+                fiat = Some(fx::format_fiat(converted, currency, settings.full_precision));
             }
+        }
 
-            let coin = coin_arg.trim().to_uppercase();
-            
-            match database.remove_subscription(user_id, &coin).await {
-                Ok(true) => {
-                    let success_msg = format!("Successfully unsubscribed from {} trades.", coin);
-                    bot.send_message(msg.chat.id, success_msg).await?;
-                    info!("user {} unsubscribed from {}", user_id, coin);
-                }
-                Ok(false) => {
-                    let not_subscribed_msg = format!("You weren't subscribed to {} trades.", coin);
-                    bot.send_message(msg.chat.id, not_subscribed_msg).await?;
-                }
-                Err(e) => {
-                    error!("db error for user {} unsubscribing from {}: {}", user_id, coin, e);
-                    bot.send_message(msg.chat.id, "Sorry, there was an error. Please try again.").await?;
-                }
+        let mut details = Vec::new();
+        if settings.show_leverage {
+            if let Some(asset) = self.asset_metadata.get(&trade.coin).await {
+                details.push(market::leverage_text(&asset));
             }
         }
-        
-        Command::List => {
-            match database.get_user_subscriptions(user_id).await {
-                Ok(coins) => {
-                    if coins.is_empty() {
-                        bot.send_message(
-                            msg.chat.id, 
-                            "You're not subscribed to any coins.\n\nUse /subscribe <coin> to get started!"
-                        ).await?;
-                    } else {
-                        let coins_list = coins.join(", ");
-                        let list_msg = format!("Your Subscriptions:\n\n{}", coins_list);
-                        bot.send_message(msg.chat.id, list_msg).await?;
-                    }
-                }
-                Err(e) => {
-                    error!("db error getting subscriptions for user {}: {}", user_id, e);
-                    bot.send_message(
-                        msg.chat.id, 
-                        "Sorry, there was an error retrieving your subscriptions. Please try again."
-                    ).await?;
-                }
+        // an hour of feed is the least that makes the share mean anything
+        if let Some(live) = self.activity.get(&trade.coin) {
+            if live.covered >= LIVE_SHARE_MIN_COVERAGE && live.volume_usd > 0.0 {
+                details.push(format!(
+                    "Share: {:.1}% of {} volume",
+                    notional_usd / live.volume_usd * 100.0,
+                    live.window_text()
+                ));
             }
         }
-        
-        Command::Help => {
-            let help_msg = "Hyperliquid Trade Alerts Help\n\n\
-                I monitor large trades ($50,000+) on Hyperliquid and send you notifications.\n\n\
-                Available Commands:\n\
-                /start - Get started and subscribe to BTC\n\
-                /subscribe <coin> - Subscribe to a coin (e.g. /subscribe ETH)\n\
-                /unsubscribe <coin> - Unsubscribe from a coin\n\
-                /list - Show your current subscriptions\n\
-                /help - Show this help message\n\n\
-                Examples:\n\
-                /subscribe SOL - Get SOL trade alerts\n\
-                /unsubscribe BTC - Stop BTC trade alerts\n\
-                /list - See all your subscriptions";
 
-            bot.send_message(msg.chat.id, help_msg).await?;
+        // checked when the config loaded
+        if let Ok(Some(url)) = self.config.links.trade_url(&trade.coin) {
+            details.push(url.to_string());
         }
+
+        let liquidation = liquidations::classify(trade, &self.config.liquidations.liquidator_addresses)
+            .map(|liquidated| liquidated.as_str().to_string());
+        formatting::trade_alert_text(trade, notional_usd, settings.full_precision, fiat, details, liquidation)
     }
 
-    Ok(())
-}
\ No newline at end of file
+    /// What `coin`'s market is quoted in, if not USD.
+    pub async fn quote_asset(&self, coin: &str) -> Option<String> {
+        self.hyperliquid_client.quote_asset(coin).await
+    }
+
+    /// For each target, its user's /mute rule that silences this alert,
+    /// matched against the text they'd get. The rules are read once for the
+    /// trade, and the text rendered once per way of showing it rather than
+    /// once per target.
+    pub async fn muted_by(&self, trade: &WsTrade, notional_usd: f64, targets: &[(i64, AlertTarget, UserSettings)]) -> Vec<Option<MuteRule>> {
+        let rules = self.mutes.rules_for(targets.iter().map(|(user_id, _, _)| *user_id));
+        // what the text depends on besides the trade
+        let mut texts: HashMap<(Option<String>, bool, bool), String> = HashMap::new();
+
+        let mut muted = Vec::with_capacity(targets.len());
+        for (user_id, _, settings) in targets {
+            let Some(user_rules) = rules.get(user_id) else {
+                muted.push(None);
+                continue;
+            };
+            let key = (settings.currency.clone(), settings.full_precision, settings.show_leverage);
+            let text = match texts.entry(key) {
+                Entry::Occupied(entry) => entry.into_mut(),
+                Entry::Vacant(entry) => entry.insert(self.trade_message(trade, notional_usd, settings).await.to_lowercase()),
+            };
+            muted.push(user_rules.iter().find(|rule| rule.matches(text, notional_usd)).cloned());
+        }
+        muted
+    }
+
+    /// Sends a trade alert, returning the id of the message it went out as.
+    pub async fn send_trade_notification(
+        &self, 
+        chat_id: i64, 
+        trade: &WsTrade,
+        notional_usd: f64,
+        settings: &UserSettings,
+        chart: Option<Arc<Vec<u8>>>,
+        priority: Priority,
+    ) -> Result<i32> {
+        let coin = &trade.coin;
+        let text = self.alert_text(trade, notional_usd, settings).await;
+        let message = match experiments::assign(&self.config.experiment, chat_id)
+            .and_then(|variant| experiments::template(&self.config.experiment, variant))
+        {
+            Some(template) => text.render(template),
+            None => text.plain(),
+        };
+        let message = self.chat_branding(chat_id).await.apply(&text, message);
+        // only the trades over the user's /silent line make the phone ring
+        let silent = settings.silent_below_usd.is_some_and(|below| notional_usd < below);
+        let keyboard = self.config.telegram.alert_buttons.then(|| alert_keyboard(coin));
+
+        // a template or branding can make an alert too long for a caption,
+        // and then it goes without its chart
+        let fits_caption = message.encode_utf16().count() <= formatting::TELEGRAM_CAPTION_LIMIT;
+        let sent = match chart.filter(|_| settings.charts_enabled && fits_caption) {
+            Some(png) => {
+                let mut request = self
+                    .failover
+                    .sender()
+                    .send_photo(ChatId(chat_id), InputFile::memory(png.to_vec()))
+                    .caption(message)
+                    .disable_notification(silent);
+                if let Some(keyboard) = keyboard {
+                    request = request.reply_markup(keyboard);
+                }
+                self.paced(chat_id, priority, request).await?
+            }
+            None => {
+                let mut request = self
+                    .failover
+                    .sender()
+                    .send_message(ChatId(chat_id), message)
+                    .disable_web_page_preview(true)
+                    .disable_notification(silent);
+                if let Some(keyboard) = keyboard {
+                    request = request.reply_markup(keyboard);
+                }
+                self.paced(chat_id, priority, request).await?
+            }
+        };
+        info!("sent {} trade notification to chat {}", coin, chat_id);
+        Ok(sent.id.0)
+    }
+
+    /// Posts a daily summary or scheduled report. Groups and channels that
+    /// turned on /pinsummary get it pinned in place of the previous one.
+    pub async fn send_summary(&self, chat_id: i64, text: &str) -> Result<()> {
+        // a dm has nobody else to pin it for
+        let settings = if chat_id < 0 {
+            self.database.get_chat_settings(chat_id).await?
+        } else {
+            ChatSettings::default()
+        };
+        if !settings.pin_summaries {
+            return self.send_text(chat_id, text, Priority::Digest).await;
+        }
+
+        let sender = self.failover.sender();
+        let mut first_id = None;
+        for chunk in formatting::split_message(text, formatting::TELEGRAM_MESSAGE_LIMIT) {
+            let request = sender.send_message(ChatId(chat_id), chunk).disable_web_page_preview(true);
+            let sent = self.paced(chat_id, Priority::Digest, request).await?;
+            first_id.get_or_insert(sent.id);
+        }
+        let Some(message_id) = first_id else {
+            return Ok(());
+        };
+
+        if let Some(previous) = settings.pinned_summary_id {
+            let unpin = sender.unpin_chat_message(ChatId(chat_id)).message_id(MessageId(previous));
+            if let Err(e) = self.paced(chat_id, Priority::Digest, unpin).await {
+                warn!("couldn't unpin the previous summary in chat {}: {}", chat_id, e);
+            }
+        }
+        let pin = sender.pin_chat_message(ChatId(chat_id), message_id).disable_notification(true);
+        match self.paced(chat_id, Priority::Digest, pin).await {
+            Ok(_) => self.database.set_pinned_summary(chat_id, Some(message_id.0)).await?,
+            Err(e) => {
+                // most likely an admin took the bot's pin rights away
+                warn!("couldn't pin summary in chat {}: {}", chat_id, e);
+                self.database.set_pinned_summary(chat_id, None).await?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Sends a chat's ticker message for a coin and pins it, returning its id.
+    /// Pinning is best effort, groups may not let the bot.
+    pub async fn post_ticker(&self, chat_id: i64, text: &str) -> Result<i32> {
+        let sender = self.failover.sender();
+        let request = sender.send_message(ChatId(chat_id), text).disable_notification(true);
+        let sent = self.paced(chat_id, Priority::Alert, request).await?;
+
+        let pin = sender.pin_chat_message(ChatId(chat_id), sent.id).disable_notification(true);
+        if let Err(e) = self.paced(chat_id, Priority::Alert, pin).await {
+            warn!("couldn't pin ticker message in chat {}: {}", chat_id, e);
+        }
+        Ok(sent.id.0)
+    }
+
+    /// Rewrites a ticker message, returning false once it's gone or can't be
+    /// edited anymore (deleted, or sent by the other bot before a failover).
+    pub async fn edit_ticker(&self, chat_id: i64, message_id: i32, text: &str) -> Result<bool> {
+        let request = self.failover.sender().edit_message_text(ChatId(chat_id), MessageId(message_id), text);
+        match self.paced(chat_id, Priority::Alert, request).await {
+            Ok(_) => Ok(true),
+            Err(e) => match e.downcast_ref::<teloxide::RequestError>() {
+                Some(teloxide::RequestError::Api(ApiError::MessageNotModified)) => Ok(true),
+                Some(teloxide::RequestError::Api(
+                    ApiError::MessageToEditNotFound | ApiError::MessageCantBeEdited | ApiError::MessageIdInvalid,
+                )) => Ok(false),
+                _ => Err(e),
+            },
+        }
+    }
+
+    pub async fn send_text(&self, chat_id: i64, text: &str, priority: Priority) -> Result<()> {
+        for chunk in formatting::split_message(text, formatting::TELEGRAM_MESSAGE_LIMIT) {
+            let request = self.failover.sender().send_message(ChatId(chat_id), chunk).disable_web_page_preview(true);
+            self.paced(chat_id, priority, request).await?;
+        }
+        Ok(())
+    }
+
+    // waits for a slot in the outbound queue before sending, and holds the
+    // whole queue back if telegram says we're going too fast
+    async fn paced<R, T>(&self, chat_id: i64, priority: Priority, request: R) -> Result<T>
+    where
+        R: IntoFuture<Output = ResponseResult<T>>,
+    {
+        self.outbound.acquire(chat_id, priority).await?;
+        #[cfg(feature = "chaos")]
+        crate::chaos::fail_telegram_send()?;
+        let result = request.await;
+        if self.failover.record(&result) {
+            self.announce_failover();
+        }
+        match result {
+            Ok(sent) => Ok(sent),
+            Err(e) => {
+                if let teloxide::RequestError::RetryAfter(retry_after) = &e {
+                    self.outbound.pause(*retry_after);
+                }
+                Err(e.into())
+            }
+        }
+    }
+
+    fn announce_failover(&self) {
+        error!("primary bot token keeps failing, sending alerts through the backup bot");
+        let bot = self.failover.sender().clone();
+        let admin_ids = self.config.telegram.admin_user_ids.clone();
+        tokio::spawn(async move {
+            let message = "Alert delivery switched to the backup bot: the primary token kept failing with auth or network errors. Commands still go through the primary.";
+            for admin_id in admin_ids {
+                if let Err(e) = bot.send_message(ChatId(admin_id), message).await {
+                    warn!("couldn't tell admin {} about the bot failover: {}", admin_id, e);
+                }
+            }
+        });
+    }
+
+    pub async fn send_wallet_notification(
+        &self,
+        chat_id: i64,
+        address: &str,
+        trade: &WsTrade,
+        notional_usd: f64,
+    ) -> Result<()> {
+        let bought = trade.users.as_ref().is_some_and(|[buyer, _]| buyer == address);
+        let message = format!(
+            "Watched Wallet Trade\n\n{} {} {} {}\nPrice: ${}",
+            formatting::short_address(address),
+            if bought { "bought" } else { "sold" },
+            formatting::format_usd(notional_usd, false),
+            trade.coin,
+            formatting::format_price(&trade.px)
+        );
+
+        self.paced(chat_id, Priority::Alert, self.failover.sender().send_message(ChatId(chat_id), message)).await?;
+        info!("sent {} wallet notification to chat {}", trade.coin, chat_id);
+        Ok(())
+    }
+
+    pub async fn send_revisit_notification(&self, revisit: &LevelRevisit) -> Result<()> {
+        let watch = &revisit.watch;
+        let side_text = if watch.side == "B" { "buy" } else { "sell" };
+        let message = format!(
+            "{} price back at the ${} whale {} level\n\nWhale {}: {} at ${}\nMid: ${}",
+            watch.coin,
+            formatting::format_price(&watch.px),
+            side_text,
+            side_text,
+            formatting::format_usd(watch.notional_usd, false),
+            formatting::format_price(&watch.px),
+            formatting::format_price(&revisit.mid.to_string())
+        );
+
+        self.paced(watch.chat_id, Priority::Alert, self.failover.sender().send_message(ChatId(watch.chat_id), message)).await?;
+        info!("sent {} revisit notification to chat {}", watch.coin, watch.chat_id);
+        Ok(())
+    }
+
+    pub async fn send_volatility_notification(&self, chat_id: i64, coin: &str, reading: &VolatilityReading) -> Result<()> {
+        let message = format!(
+            "{} volatility {:.1}x normal\n\nRecent: {:.0}% annualized\nBaseline: {:.0}% annualized",
+            coin,
+            reading.ratio(),
+            reading.recent * 100.0,
+            reading.baseline * 100.0
+        );
+
+        self.paced(chat_id, Priority::Alert, self.failover.sender().send_message(ChatId(chat_id), message)).await?;
+        info!("sent {} volatility notification to chat {}", coin, chat_id);
+        Ok(())
+    }
+
+    pub async fn send_price_alert_notification(&self, alert: &PriceAlert, mid: &str) -> Result<()> {
+        let footer = match alert.repeat_secs {
+            Some(repeat_secs) => format!(
+                "Fires again after {} once it's back {} {}.",
+                formatting::format_duration(std::time::Duration::from_secs(repeat_secs as u64)),
+                if alert.above { "below" } else { "above" },
+                formatting::format_price(&alert.price.to_string())
+            ),
+            None => "This was a one-time alert and has been removed.".to_string(),
+        };
+        let message = format!(
+            "{} {} {}\n\nNow: {}\n\n{}",
+            alert.coin,
+            if alert.above { "above" } else { "below" },
+            formatting::format_price(&alert.price.to_string()),
+            formatting::format_price(mid),
+            footer
+        );
+
+        self.paced(
+            alert.telegram_chat_id,
+            Priority::Alert,
+            self.failover.sender().send_message(ChatId(alert.telegram_chat_id), message),
+        )
+        .await?;
+        info!("sent {} price alert {} to chat {}", alert.coin, alert.id, alert.telegram_chat_id);
+        Ok(())
+    }
+
+    pub async fn send_vwap_notification(&self, chat_id: i64, coin: &str, reading: &VwapReading) -> Result<()> {
+        let deviation = reading.deviation_pct();
+        let last_px = reading.last_px.to_string();
+        // quote vwap to the same precision the exchange quotes price
+        let decimals = last_px.split_once('.').map_or(0, |(_, frac)| frac.len());
+        let message = format!(
+            "{} VWAP Deviation\n\nPrice is {:.2}% {} 1h VWAP\nPrice: ${}\nVWAP: ${}",
+            coin,
+            deviation.abs(),
+            if deviation > 0.0 { "above" } else { "below" },
+            formatting::format_price(&last_px),
+            formatting::with_thousands(reading.vwap, decimals)
+        );
+
+        self.paced(chat_id, Priority::Alert, self.failover.sender().send_message(ChatId(chat_id), message)).await?;
+        info!("sent {} vwap notification to chat {}", coin, chat_id);
+        Ok(())
+    }
+
+    pub async fn send_imbalance_notification(
+        &self,
+        chat_id: i64,
+        coin: &str,
+        imbalance: &BookImbalance,
+        band_bps: f64,
+    ) -> Result<()> {
+        let side_text = if imbalance.imbalance > 0.0 { "Bid-heavy" } else { "Ask-heavy" };
+
+        let message = format!(
+            "{} Order Book Imbalance\n\n{}: {:.0}% of depth within {} bps of mid\nBids: {}\nAsks: {}\nMid: ${}",
+            coin,
+            side_text,
+            imbalance.dominant_share() * 100.0,
+            band_bps,
+            formatting::format_usd(imbalance.bid_depth_usd, false),
+            formatting::format_usd(imbalance.ask_depth_usd, false),
+            formatting::with_thousands(imbalance.mid, 2)
+        );
+
+        self.paced(chat_id, Priority::Alert, self.failover.sender().send_message(ChatId(chat_id), message)).await?;
+        info!("sent {} imbalance notification to chat {}", coin, chat_id);
+        Ok(())
+    }
+}
+
+// `+2`, `-5:30`, `utc+1` -> minutes east of utc
+fn parse_utc_offset(offset: &str) -> Option<i32> {
+    let offset = offset.trim_start_matches("utc");
+    let (sign, rest) = match offset.split_at_checked(1)? {
+        ("+", rest) => (1, rest),
+        ("-", rest) => (-1, rest),
+        _ => (1, offset),
+    };
+    let (hours, mins) = match rest.split_once(':') {
+        Some((hours, mins)) => (hours.parse::<i32>().ok()?, mins.parse::<i32>().ok()?),
+        None => (rest.parse::<i32>().ok()?, 0),
+    };
+    let offset_mins = sign * (hours * 60 + mins);
+    ((0..60).contains(&mins) && (-12 * 60..=14 * 60).contains(&offset_mins)).then_some(offset_mins)
+}
+
+fn utc_offset_text(offset_mins: i32) -> String {
+    let sign = if offset_mins < 0 { "-" } else { "+" };
+    match offset_mins.abs() % 60 {
+        0 => format!("UTC{}{}", sign, offset_mins.abs() / 60),
+        mins => format!("UTC{}{}:{:02}", sign, offset_mins.abs() / 60, mins),
+    }
+}
+
+// the quick actions under a trade alert; they act on whoever taps them, so in
+// a group each member only changes their own subscription
+fn alert_keyboard(coin: &CoinSymbol) -> InlineKeyboardMarkup {
+    let button = |label: &str, action: &str| {
+        InlineKeyboardButton::callback(label.to_string(), format!("{}{}:{}", ALERT_ACTION_PREFIX, action, coin))
+    };
+    InlineKeyboardMarkup::new(vec![
+        vec![button(&format!("Mute {}h", ALERT_MUTE_HOURS), "mute"), button("Raise threshold", "raise")],
+        vec![button("Chart", "chart"), button("Unsubscribe", "unsubscribe")],
+    ])
+}
+
+// sends `text` as however many messages telegram's length cap needs; the
+// keyboard, if any, rides on the last one. The first goes out on the reply
+// slot its command already took, and each after it waits for one of its own
+async fn send_chunked(
+    state: &TelegramBot,
+    bot: &Bot,
+    chat_id: ChatId,
+    text: &str,
+    keyboard: Option<InlineKeyboardMarkup>,
+) -> ResponseResult<()> {
+    let chunks = formatting::split_message(text, formatting::TELEGRAM_MESSAGE_LIMIT);
+    let last = chunks.len().saturating_sub(1);
+
+    for (index, chunk) in chunks.into_iter().enumerate() {
+        let mut request = bot.send_message(chat_id, chunk).disable_web_page_preview(true);
+        if index == last {
+            if let Some(keyboard) = keyboard.clone() {
+                request = request.reply_markup(keyboard);
+            }
+        }
+        if index == 0 {
+            request.await?;
+            continue;
+        }
+        if let Err(e) = state.paced(chat_id.0, Priority::Reply, request).await {
+            return match e.downcast::<teloxide::RequestError>() {
+                Ok(e) => Err(e),
+                Err(e) => {
+                    warn!("dropping the rest of a reply to chat {}: {}", chat_id, e);
+                    Ok(())
+                }
+            };
+        }
+    }
+    Ok(())
+}
+
+// resolves a /route or /destination target and checks the bot can post there and the user
+// administers it, returning the chat id or a reason to show the user
+async fn verify_route(state: &TelegramBot, target: &str, user_id: i64, chat_id: i64) -> std::result::Result<i64, String> {
+    let bot = &state.bot;
+    let recipient = match target.parse::<i64>() {
+        Ok(id) => Recipient::Id(ChatId(id)),
+        Err(_) if target.starts_with('@') => Recipient::ChannelUsername(target.to_string()),
+        Err(_) => return Err("That isn't a chat id or @channel.".to_string()),
+    };
+
+    let chat = bot
+        .get_chat(recipient)
+        .await
+        .map_err(|_| "I can't see that chat. Add me to it first, then try again.".to_string())?;
+    if chat.is_private() {
+        return Err("Alerts can only be routed to a group or channel.".to_string());
+    }
+
+    let me = bot.get_me().await.map_err(|e| {
+        state.error_reply(user_id, chat_id, format!("couldn't fetch bot info: {}", e))
+    })?;
+    let bot_member = bot
+        .get_chat_member(chat.id, me.id)
+        .await
+        .map_err(|_| "I'm not a member of that chat. Add me to it first, then try again.".to_string())?;
+    let bot_can_post = if chat.is_channel() {
+        bot_member.kind.can_post_messages()
+    } else {
+        bot_member.kind.is_present()
+    };
+    if !bot_can_post {
+        return Err("I can't post there. Add me as a member (or as an admin with posting rights for channels).".to_string());
+    }
+
+    let user_member = bot
+        .get_chat_member(chat.id, UserId(user_id as u64))
+        .await
+        .map_err(|_| "Only admins of that chat can route alerts to it.".to_string())?;
+    if !user_member.kind.is_privileged() {
+        return Err("Only admins of that chat can route alerts to it.".to_string());
+    }
+
+    Ok(chat.id.0)
+}
+
+// resolves a chat id or @channel that the user administers, for settings only a
+// group's or channel's admins may change; `private` and `not_admin` are the
+// reasons shown for a private chat and for a user who isn't an admin
+async fn resolve_admin_chat(
+    state: &TelegramBot,
+    target: &str,
+    user_id: i64,
+    private: &str,
+    not_admin: &str,
+) -> std::result::Result<Chat, String> {
+    let bot = &state.bot;
+    let recipient = match target.parse::<i64>() {
+        Ok(id) => Recipient::Id(ChatId(id)),
+        Err(_) if target.starts_with('@') => Recipient::ChannelUsername(target.to_string()),
+        Err(_) => return Err("That isn't a chat id or @channel.".to_string()),
+    };
+
+    let chat = bot
+        .get_chat(recipient)
+        .await
+        .map_err(|_| "I can't see that chat. Add me to it first, then try again.".to_string())?;
+    if chat.is_private() {
+        return Err(private.to_string());
+    }
+
+    let user_member = bot
+        .get_chat_member(chat.id, UserId(user_id as u64))
+        .await
+        .map_err(|_| not_admin.to_string())?;
+    if !user_member.kind.is_privileged() {
+        return Err(not_admin.to_string());
+    }
+    Ok(chat)
+}
+
+// /pinsummary is for that chat's admins, and only works if the bot may pin there
+async fn verify_pin_rights(
+    state: &TelegramBot,
+    target: &str,
+    user_id: i64,
+    chat_id: i64,
+    enabling: bool,
+) -> std::result::Result<i64, String> {
+    let chat = resolve_admin_chat(
+        state,
+        target,
+        user_id,
+        "Pinning summaries only works in groups and channels. Run /pinsummary in the group.",
+        "Only admins of that chat can change what gets pinned.",
+    )
+    .await?;
+
+    if enabling {
+        let bot = &state.bot;
+        let me = bot.get_me().await.map_err(|e| {
+            state.error_reply(user_id, chat_id, format!("couldn't fetch bot info: {}", e))
+        })?;
+        let bot_member = bot
+            .get_chat_member(chat.id, me.id)
+            .await
+            .map_err(|_| "I'm not a member of that chat. Add me to it first, then try again.".to_string())?;
+        // channels fold pinning into the edit right
+        let can_pin = match &bot_member.kind {
+            ChatMemberKind::Owner(_) => true,
+            ChatMemberKind::Administrator(admin) if chat.is_channel() => admin.can_edit_messages,
+            ChatMemberKind::Administrator(admin) => admin.can_pin_messages,
+            _ => false,
+        };
+        if !can_pin {
+            return Err("I can't pin messages there. Make me an admin with the \"Pin messages\" right, then try again.".to_string());
+        }
+    }
+
+    Ok(chat.id.0)
+}
+
+// branding is for groups and channels, set by their admins
+async fn verify_branding_rights(state: &TelegramBot, target: &str, user_id: i64) -> std::result::Result<i64, String> {
+    let chat = resolve_admin_chat(
+        state,
+        target,
+        user_id,
+        "Branding is for groups and channels. Run /branding in the group, or name the channel first.",
+        "Only admins of that chat can change its branding.",
+    )
+    .await?;
+    Ok(chat.id.0)
+}
+
+struct DestinationSpec {
+    chat_id: Option<i64>,
+    webhook_url: Option<String>,
+    coin: Option<String>,
+    full_precision: Option<bool>,
+    charts_enabled: Option<bool>,
+}
+
+// parses `/destination add <chat_id|@channel|https://url> [coin] [compact|full] [charts|nocharts]`
+async fn parse_destination(state: &TelegramBot, args: &[&str], user_id: i64, chat_id: i64) -> std::result::Result<DestinationSpec, String> {
+    let (target, options) = args
+        .split_first()
+        .ok_or_else(|| "Usage: /destination add <chat_id|@channel|https://url> [coin] [compact|full] [charts|nocharts]".to_string())?;
+
+    let mut spec = DestinationSpec {
+        chat_id: None,
+        webhook_url: None,
+        coin: None,
+        full_precision: None,
+        charts_enabled: None,
+    };
+    if target.starts_with("https://") || target.starts_with("http://") {
+        delivery::validate_webhook_url(target).await?;
+        spec.webhook_url = Some(target.to_string());
+    } else {
+        spec.chat_id = Some(verify_route(state, target, user_id, chat_id).await?);
+    }
+
+    for option in options {
+        match option.to_lowercase().as_str() {
+            "compact" => spec.full_precision = Some(false),
+            "full" => spec.full_precision = Some(true),
+            "charts" => spec.charts_enabled = Some(true),
+            "nocharts" => spec.charts_enabled = Some(false),
+            _ if spec.coin.is_none() => spec.coin = Some(option.to_uppercase()),
+            _ => return Err(format!("Unknown option: {}", option)),
+        }
+    }
+    Ok(spec)
+}
+
+// runs ahead of every command: banned users are ignored, and restricted
+// commands need a role that grants their permission
+async fn authorize(bot: &Bot, msg: &Message, cmd: &Command, state: &TelegramBot) -> bool {
+    let user_id = msg.from().map(|user| user.id.0 as i64).unwrap_or(msg.chat.id.0);
+
+    if state.roles.is_banned(user_id) {
+        info!("ignoring command from banned user {}", user_id);
+        return false;
+    }
+
+    let Some(permission) = cmd.required_permission() else {
+        return true;
+    };
+    if state.roles.allows(user_id, permission) {
+        return true;
+    }
+
+    warn!("user {} denied {:?}", user_id, permission);
+    if state.outbound.acquire(msg.chat.id.0, Priority::Reply).await.is_ok() {
+        if let Err(e) = bot.send_message(msg.chat.id, "You don't have permission to use this command.").await {
+            error!("couldn't send permission denial to chat {}: {}", msg.chat.id, e);
+        }
+    }
+    false
+}
+
+async fn handle_command(
+    bot: Bot, 
+    msg: Message, 
+    cmd: Command, 
+    state: TelegramBot,
+) -> ResponseResult<()> {
+    let database = &state.database;
+    let hyperliquid_client = state.hyperliquid_client.clone();
+    let event_sender = &state.event_sender;
+    let chat_id = msg.chat.id.0;
+    let user_id = msg.from().map(|user| user.id.0 as i64).unwrap_or(chat_id);
+    
+    info!("Received command from user {}: {:?}", user_id, cmd);
+
+    // replies jump ahead of queued alerts and digests
+    if let Err(e) = state.outbound.acquire(chat_id, Priority::Reply).await {
+        warn!("dropping command from chat {}: {}", chat_id, e);
+        return Ok(());
+    }
+
+    match cmd {
+        Command::Start => {
+            //subscribe to btc for every new user
+            match database.add_subscription(user_id, chat_id, "BTC").await {
+                Ok(true) => {
+                    let welcome_msg = "Welcome to Hyperliquid Trade Alerts!\n\nYou've been automatically subscribed to BTC trades.\n\nUse /subscribe <coin> to add more coins!";
+                    bot.send_message(msg.chat.id, welcome_msg).await?;
+                    info!("new user {} auto-subscribed to BTC", user_id);
+                    
+                    if let Err(e) = event_sender.send(CoordinatorCommand::UserSubscribed { 
+                        coin: CoinSymbol::new("BTC") 
+                    }) {
+                        error!("Failed to send BTC subscription event: {}", e);
+                    }
+                }
+                Ok(false) => {
+                    let welcome_msg = "Welcome back to Hyperliquid Trade Alerts!\n\nUse /subscribe <coin> to add more subscriptions!";
+                    bot.send_message(msg.chat.id, welcome_msg).await?;
+                }
+                Err(e) => {
+                    error!("Failed to auto-subscribe user {} to BTC: {}", user_id, e);
+                    let welcome_msg = "Welcome to Hyperliquid Trade Alerts!\n\nUse /subscribe <coin> to get started!";
+                    bot.send_message(msg.chat.id, welcome_msg).await?;
+                }
+            }
+        }
+        
+        Command::Subscribe(coin_arg) => {
+            let coins: Vec<String> = coin_arg
+                .split(|c: char| c.is_whitespace() || c == ',')
+                .filter(|coin| !coin.is_empty())
+                .map(str::to_uppercase)
+                .collect();
+            if coins.is_empty() {
+                bot.send_message(msg.chat.id, "Please specify a coin. Example: /subscribe ETH").await?;
+                return Ok(());
+            }
+
+            // make sure coins exist
+            let mut valid = Vec::new();
+            let mut unknown = Vec::new();
+            for coin in coins {
+                match hyperliquid_client.coin_exists(&coin).await {
+                    Ok(true) if !valid.contains(&coin) => valid.push(coin),
+                    Ok(true) => {}
+                    Ok(false) => unknown.push(coin),
+                    Err(e) => {
+                        let reply = state.error_reply_as(
+                            user_id,
+                            chat_id,
+                            "Sorry, there was an error validating the coin. Please try again.",
+                            format!("couldn't validate {} for {}: {}", coin, user_id, e),
+                        );
+                        bot.send_message(msg.chat.id, reply).await?;
+                        return Ok(());
+                    }
+                }
+            }
+
+            if let [coin] = unknown.as_slice() {
+                if valid.is_empty() {
+                    let invalid_msg = format!("{} is not available on Hyperliquid. Use /help to see valid coins.", coin);
+                    bot.send_message(msg.chat.id, invalid_msg).await?;
+                    return Ok(());
+                }
+            }
+
+            // all or nothing, so a failure partway doesn't leave half the list subscribed
+            let added = match database.add_subscriptions(user_id, chat_id, &valid).await {
+                Ok(added) => added,
+                Err(e) => {
+                    let reply = state.error_reply(user_id, chat_id, format!("db error for user {} subscribing to {}: {}", user_id, valid.join(", "), e));
+                    bot.send_message(msg.chat.id, reply).await?;
+                    return Ok(());
+                }
+            };
+
+            for coin in &added {
+                info!("user {} subscribed to {}", user_id, coin);
+                //send to coordinator to open ws
+                if let Err(e) = event_sender.send(CoordinatorCommand::UserSubscribed { 
+                    coin: CoinSymbol::new(coin) 
+                }) {
+                    error!("couldn't send subscription event for {}: {}", coin, e);
+                }
+            }
+
+            let reply = match (valid.as_slice(), unknown.is_empty()) {
+                ([coin], true) if added.is_empty() => format!("You're already subscribed to {} trades.", coin),
+                ([coin], true) => format!("Successfully subscribed to {} trades!", coin),
+                _ => {
+                    let already: Vec<&str> = valid.iter().filter(|coin| !added.contains(coin)).map(String::as_str).collect();
+                    let mut lines = Vec::new();
+                    if !added.is_empty() {
+                        lines.push(format!("Subscribed to {} trades.", added.join(", ")));
+                    }
+                    if !already.is_empty() {
+                        lines.push(format!("Already subscribed to {}.", already.join(", ")));
+                    }
+                    if !unknown.is_empty() {
+                        lines.push(format!("Not available on Hyperliquid: {}.", unknown.join(", ")));
+                    }
+                    lines.join("\n")
+                }
+            };
+            bot.send_message(msg.chat.id, reply).await?;
+        }
+
+        Command::Unsubscribe(coin_arg) => {
+            if coin_arg.trim().is_empty() {
+                bot.send_message(msg.chat.id, "Please specify a coin. Example: /unsubscribe ETH").await?;
+                return Ok(());
+            }
+
+            let coin = coin_arg.trim().to_uppercase();
+            
+            match database.remove_subscription(user_id, &coin).await {
+                Ok(true) => {
+                    let success_msg = format!("Successfully unsubscribed from {} trades.", coin);
+                    bot.send_message(msg.chat.id, success_msg).await?;
+                    info!("user {} unsubscribed from {}", user_id, coin);
+                }
+                Ok(false) => {
+                    let not_subscribed_msg = format!("You weren't subscribed to {} trades.", coin);
+                    bot.send_message(msg.chat.id, not_subscribed_msg).await?;
+                }
+                Err(e) => {
+                    let reply = state.error_reply(user_id, chat_id, format!("db error for user {} unsubscribing from {}: {}", user_id, coin, e));
+                    bot.send_message(msg.chat.id, reply).await?;
+                }
+            }
+        }
+
+        Command::UnsubscribeAll => {
+            match database.remove_all_subscriptions(user_id).await {
+                Ok(coins) if coins.is_empty() => {
+                    bot.send_message(msg.chat.id, "You're not subscribed to any coins.").await?;
+                }
+                Ok(coins) => {
+                    state.notify_coordinator(CoordinatorCommand::UserPaused { telegram_user_id: user_id, paused: true });
+                    let success_msg = format!("Unsubscribed from {}.", coins.join(", "));
+                    send_chunked(&state, &bot, msg.chat.id, &success_msg, None).await?;
+                    info!("user {} unsubscribed from all {} coins", user_id, coins.len());
+                }
+                Err(e) => {
+                    let reply = state.error_reply(user_id, chat_id, format!("db error for user {} unsubscribing from everything: {}", user_id, e));
+                    bot.send_message(msg.chat.id, reply).await?;
+                }
+            }
+        }
+        
+        Command::List => {
+            match database.get_user_subscriptions(user_id).await {
+                Ok(coins) => {
+                    if coins.is_empty() {
+                        bot.send_message(
+                            msg.chat.id, 
+                            "You're not subscribed to any coins.\n\nUse /subscribe <coin> to get started!"
+                        ).await?;
+                    } else {
+                        let coins_list = coins.join(", ");
+                        let list_msg = format!("Your Subscriptions:\n\n{}", coins_list);
+                        send_chunked(&state, &bot, msg.chat.id, &list_msg, None).await?;
+                    }
+                }
+                Err(e) => {
+                    let reply = state.error_reply_as(
+                        user_id,
+                        chat_id,
+                        "Sorry, there was an error retrieving your subscriptions. Please try again.",
+                        format!("db error getting subscriptions for user {}: {}", user_id, e),
+                    );
+                    bot.send_message(
+                        msg.chat.id, 
+                        reply
+                    ).await?;
+                }
+            }
+        }
+        
+        Command::Help => {
+            let help_msg = format!(
+                "Hyperliquid Trade Alerts Help\n\n\
+                I monitor large trades ($50,000+) on Hyperliquid and send you notifications.\n\n\
+                Available Commands:\n\
+                /start - Get started and subscribe to BTC\n\
+                /subscribe <coins> - Subscribe to one or more coins (e.g. /subscribe ETH BTC)\n\
+                /unsubscribe <coin> - Unsubscribe from a coin\n\
+                /unsubscribeall - Unsubscribe from every coin\n\
+                /list - Show your current subscriptions\n\
+                /help - Show this help message\n\
+                /version - Show bot version and build info\n\
+                /currency <code> - Also show amounts in EUR, GBP, ... (/currency USD to reset)\n\
+                /compact <on|off> - Compact amounts ($1.25M) or full precision\n\
+                /charts <on|off> - Attach a 1h candle chart to alerts\n\
+                /revisit <on|off> - Alert when price returns to a whale's entry\n\
+                /leverage <on|off> - Show the coin's max leverage in alerts\n\
+                /ticker <on|off> - One pinned, updating message per coin with today's whale totals\n\
+                /heartbeat <on|off> - A note with price and volume when a coin goes 24h without a big trade\n\
+                /rangealerts <on|off> - Alerts on new {}d highs and lows for your coins\n\
+                /divergence <on|off> - Alerts when a coin's mark price strays from its oracle price\n\
+                /pinsummary <on|off> [chat] - Keep the latest summary or report pinned in a group (admins)\n\
+                /dailycap <count|off> - Cap trade alerts per day, with a summary of the rest\n\
+                /digestmode <minutes|off> - One message per interval with all your alerts, grouped by coin\n\
+                /priority <coin> [off] - Up to 3 coins whose alerts skip digests, tickers and caps (/priority to list)\n\
+                /silent below <usd>|off - Only alerts over this size make a sound\n\
+                /mute <words> [under <usd>] - Mute matching alerts on every coin (/mute to list, /unmute <id|all>)\n\
+                /duplicates <both|group|dm> - Which copy to keep when a group you're in gets your alerts too\n\
+                /price <coin> - Current price with a 24h sparkline\n\
+                /info [coins] - Price, volume, OI and funding snapshot\n\
+                /history <coin> [days] - Daily buy/sell split of large trades\n\
+                /preview <coin> <usd> [buy|sell] - See an alert as you'd get it\n\
+                /imbalance <coin> <percent|off> - Order book imbalance alerts\n\
+                /vwapalert <coin> <percent|off> - Price deviation from 1h VWAP alerts\n\
+                /volalert <coin> <multiple|off> - Volatility regime alerts\n\
+                /pricealert <coin> <>|<> <price> [repeat <1h>] - Price level alerts (<coin> off to remove)\n\
+                /link <address> - Link a Hyperliquid address (/link to list)\n\
+                /unlink <address> - Unlink an address\n\
+                /fundingsummary <daily|weekly|off> - Funding summaries for linked addresses\n\
+                /weeklydigest on [utc offset] - Sunday recap of your coins' week\n\
+                /watch <addresses> - Alert on wallets' large trades, transfers and position changes (/watch to list)\n\
+                /unwatch <addresses|all> - Stop watching wallets\n\
+                /topwallets <coin> - Most active large traders over 24h\n\
+                /walletboard - Best and worst 7d PnL across everyone's watched wallets\n\
+                /suggestthreshold <coin> [per day] - Suggest a trade size minimum for a coin (off to reset)\n\
+                /autotune <per day|off> - Adjust your thresholds automatically to about N alerts a day per coin\n\
+                /coinstats <coin> - Alerts you've had for a coin over 7 days\n\
+                /mystats - Your current and past subscriptions\n\
+                /route <chat_id|@channel|off> - Send alerts to a group or channel you admin\n\
+                /destination - List, add or remove extra alert destinations\n\
+                /export - Download your subscriptions and settings\n\
+                /import - Restore from an export (send the file with /import as its caption)\n\
+                /deletedata - Erase everything the bot has stored about you\n\n\
+                Examples:\n\
+                /subscribe SOL - Get SOL trade alerts\n\
+                /subscribe xyz:XYZ100 - Coins on a builder-deployed perp dex go as dex:coin\n\
+                /unsubscribe BTC - Stop BTC trade alerts\n\
+                /list - See all your subscriptions",
+                state.config.range_alerts.window_days
+            );
+
+            bot.send_message(msg.chat.id, help_msg).await?;
+        }
+
+        Command::Version => {
+            let built_at = env!("BUILD_TIMESTAMP")
+                .parse::<i64>()
+                .ok()
+                .and_then(|ts| chrono::DateTime::from_timestamp(ts, 0))
+                .map(|dt| dt.format("%Y-%m-%d %H:%M:%S UTC").to_string())
+                .unwrap_or_else(|| "unknown".to_string());
+
+            let version_msg = format!(
+                "hl-tg-bot v{}\n\nCommit: {}\nBuilt: {}\nUptime: {}\nActive feeds: {} ({} connections)",
+                env!("CARGO_PKG_VERSION"),
+                env!("GIT_HASH"),
+                built_at,
+                formatting::format_duration(state.started_at.elapsed()),
+                state.ws_manager.active_feed_count().await,
+                state.ws_manager.connection_count().await
+            );
+
+            bot.send_message(msg.chat.id, version_msg).await?;
+        }
+
+        Command::Currency(currency_arg) => {
+            let currency = currency_arg.trim().to_uppercase();
+
+            if currency.is_empty() {
+                let current = match database.get_user_settings(user_id).await {
+                    Ok(settings) => settings.currency.unwrap_or_else(|| "USD".to_string()),
+                    Err(e) => {
+                        let reply = state.error_reply(user_id, chat_id, format!("db error getting currency for user {}: {}", user_id, e));
+                        bot.send_message(msg.chat.id, reply).await?;
+                        return Ok(());
+                    }
+                };
+                let current_msg = format!(
+                    "Your display currency is {}.\n\nUse /currency <code> to change it (e.g. /currency EUR).",
+                    current
+                );
+                bot.send_message(msg.chat.id, current_msg).await?;
+                return Ok(());
+            }
+
+            if !state.fx_rates.is_supported(&currency).await {
+                let invalid_msg = format!("{} is not a supported currency.", currency);
+                bot.send_message(msg.chat.id, invalid_msg).await?;
+                return Ok(());
+            }
+
+            let stored = if currency == "USD" { None } else { Some(currency.as_str()) };
+            match database.set_user_currency(user_id, stored).await {
+                Ok(()) => {
+                    let success_msg = if stored.is_some() {
+                        format!("Alert amounts will also be shown in {}.", currency)
+                    } else {
+                        "Alert amounts will be shown in USD only.".to_string()
+                    };
+                    bot.send_message(msg.chat.id, success_msg).await?;
+                    info!("user {} set currency to {}", user_id, currency);
+                }
+                Err(e) => {
+                    let reply = state.error_reply(user_id, chat_id, format!("db error setting currency for user {}: {}", user_id, e));
+                    bot.send_message(msg.chat.id, reply).await?;
+                }
+            }
+        }
+
+        Command::Compact(mode_arg) => {
+            let full_precision = match mode_arg.trim().to_lowercase().as_str() {
+                "on" => false,
+                "off" => true,
+                _ => {
+                    bot.send_message(msg.chat.id, "Usage: /compact on or /compact off").await?;
+                    return Ok(());
+                }
+            };
+
+            match database.set_full_precision(user_id, full_precision).await {
+                Ok(()) => {
+                    let example = formatting::format_usd(1_250_000.0, full_precision);
+                    let success_msg = format!("Got it! Amounts will look like {}.", example);
+                    bot.send_message(msg.chat.id, success_msg).await?;
+                    info!("user {} set full_precision to {}", user_id, full_precision);
+                }
+                Err(e) => {
+                    let reply = state.error_reply(user_id, chat_id, format!("db error setting precision for user {}: {}", user_id, e));
+                    bot.send_message(msg.chat.id, reply).await?;
+                }
+            }
+        }
+
+        Command::Charts(mode_arg) => {
+            let charts_enabled = match mode_arg.trim().to_lowercase().as_str() {
+                "on" => true,
+                "off" => false,
+                _ => {
+                    bot.send_message(msg.chat.id, "Usage: /charts on or /charts off").await?;
+                    return Ok(());
+                }
+            };
+
+            match database.set_charts_enabled(user_id, charts_enabled).await {
+                Ok(()) => {
+                    let success_msg = if charts_enabled {
+                        "Alerts will now include a 1h candle chart."
+                    } else {
+                        "Alerts will no longer include charts."
+                    };
+                    bot.send_message(msg.chat.id, success_msg).await?;
+                    info!("user {} set charts_enabled to {}", user_id, charts_enabled);
+                }
+                Err(e) => {
+                    let reply = state.error_reply(user_id, chat_id, format!("db error setting charts for user {}: {}", user_id, e));
+                    bot.send_message(msg.chat.id, reply).await?;
+                }
+            }
+        }
+
+        Command::Revisit(mode_arg) => {
+            let revisit_alerts = match mode_arg.trim().to_lowercase().as_str() {
+                "on" => true,
+                "off" => false,
+                _ => {
+                    bot.send_message(msg.chat.id, "Usage: /revisit on or /revisit off").await?;
+                    return Ok(());
+                }
+            };
+
+            match database.set_revisit_alerts(user_id, revisit_alerts).await {
+                Ok(()) => {
+                    let success_msg = if revisit_alerts {
+                        "You'll get a follow-up alert if price returns to a whale's entry within 24h."
+                    } else {
+                        "Whale entry follow-up alerts are off."
+                    };
+                    bot.send_message(msg.chat.id, success_msg).await?;
+                    info!("user {} set revisit_alerts to {}", user_id, revisit_alerts);
+                }
+                Err(e) => {
+                    let reply = state.error_reply(user_id, chat_id, format!("db error setting revisit alerts for user {}: {}", user_id, e));
+                    bot.send_message(msg.chat.id, reply).await?;
+                }
+            }
+        }
+
+        Command::Leverage(mode_arg) => {
+            let show_leverage = match mode_arg.trim().to_lowercase().as_str() {
+                "on" => true,
+                "off" => false,
+                _ => {
+                    bot.send_message(msg.chat.id, "Usage: /leverage on or /leverage off").await?;
+                    return Ok(());
+                }
+            };
+
+            match database.set_show_leverage(user_id, show_leverage).await {
+                Ok(()) => {
+                    let success_msg = if show_leverage {
+                        "Alerts will show the coin's max leverage."
+                    } else {
+                        "Alerts won't show leverage."
+                    };
+                    bot.send_message(msg.chat.id, success_msg).await?;
+                    info!("user {} set show_leverage to {}", user_id, show_leverage);
+                }
+                Err(e) => {
+                    let reply = state.error_reply(user_id, chat_id, format!("db error setting show leverage for user {}: {}", user_id, e));
+                    bot.send_message(msg.chat.id, reply).await?;
+                }
+            }
+        }
+
+        Command::Ticker(mode_arg) => {
+            let ticker_mode = match mode_arg.trim().to_lowercase().as_str() {
+                "on" => true,
+                "off" => false,
+                _ => {
+                    bot.send_message(msg.chat.id, "Usage: /ticker on or /ticker off").await?;
+                    return Ok(());
+                }
+            };
+
+            match database.set_ticker_mode(user_id, ticker_mode).await {
+                Ok(()) => {
+                    let success_msg = if ticker_mode {
+                        "Ticker mode on. Instead of an alert per trade, each coin gets one pinned message with today's whale buy/sell totals, updated as trades come in."
+                    } else {
+                        "Ticker mode off. You'll get an alert for each trade again."
+                    };
+                    bot.send_message(msg.chat.id, success_msg).await?;
+                    info!("user {} set ticker_mode to {}", user_id, ticker_mode);
+                }
+                Err(e) => {
+                    let reply = state.error_reply(user_id, chat_id, format!("db error setting ticker mode for user {}: {}", user_id, e));
+                    bot.send_message(msg.chat.id, reply).await?;
+                }
+            }
+        }
+
+        Command::Heartbeat(mode_arg) => {
+            let heartbeat = match mode_arg.trim().to_lowercase().as_str() {
+                "on" => true,
+                "off" => false,
+                _ => {
+                    bot.send_message(msg.chat.id, "Usage: /heartbeat on or /heartbeat off").await?;
+                    return Ok(());
+                }
+            };
+
+            match database.set_heartbeat(user_id, heartbeat).await {
+                Ok(()) => {
+                    let success_msg = if heartbeat {
+                        "Heartbeat on. If one of your coins goes 24h without a trade over your threshold, you'll get a short note with its price and volume so you know it's just quiet."
+                    } else {
+                        "Heartbeat off. Quiet coins will stay quiet."
+                    };
+                    bot.send_message(msg.chat.id, success_msg).await?;
+                    info!("user {} set heartbeat to {}", user_id, heartbeat);
+                }
+                Err(e) => {
+                    let reply = state.error_reply(user_id, chat_id, format!("db error setting heartbeat for user {}: {}", user_id, e));
+                    bot.send_message(msg.chat.id, reply).await?;
+                }
+            }
+        }
+
+        Command::RangeAlerts(mode_arg) => {
+            let range_alerts = match mode_arg.trim().to_lowercase().as_str() {
+                "on" => true,
+                "off" => false,
+                _ => {
+                    bot.send_message(msg.chat.id, "Usage: /rangealerts on or /rangealerts off").await?;
+                    return Ok(());
+                }
+            };
+
+            match database.set_range_alerts(user_id, range_alerts).await {
+                Ok(()) => {
+                    let success_msg = if range_alerts {
+                        format!(
+                            "Range alerts on. You'll hear when one of your coins breaks its {} day high or low.",
+                            state.config.range_alerts.window_days
+                        )
+                    } else {
+                        "Range alerts off.".to_string()
+                    };
+                    bot.send_message(msg.chat.id, success_msg).await?;
+                    info!("user {} set range_alerts to {}", user_id, range_alerts);
+                }
+                Err(e) => {
+                    let reply = state.error_reply(user_id, chat_id, format!("db error setting range alerts for user {}: {}", user_id, e));
+                    bot.send_message(msg.chat.id, reply).await?;
+                }
+            }
+        }
+
+        Command::Divergence(mode_arg) => {
+            let divergence_alerts = match mode_arg.trim().to_lowercase().as_str() {
+                "on" => true,
+                "off" => false,
+                _ => {
+                    bot.send_message(msg.chat.id, "Usage: /divergence on or /divergence off").await?;
+                    return Ok(());
+                }
+            };
+
+            match database.set_divergence_alerts(user_id, divergence_alerts).await {
+                Ok(()) => {
+                    let success_msg = if divergence_alerts {
+                        format!(
+                            "Divergence alerts on. You'll hear when one of your coins' mark price gets {}% or more away from its oracle price.",
+                            state.config.market_alerts.divergence_pct
+                        )
+                    } else {
+                        "Divergence alerts off.".to_string()
+                    };
+                    bot.send_message(msg.chat.id, success_msg).await?;
+                    info!("user {} set divergence_alerts to {}", user_id, divergence_alerts);
+                }
+                Err(e) => {
+                    let reply = state.error_reply(user_id, chat_id, format!("db error setting divergence alerts for user {}: {}", user_id, e));
+                    bot.send_message(msg.chat.id, reply).await?;
+                }
+            }
+        }
+
+        Command::PinSummary(args) => {
+            let args: Vec<&str> = args.split_whitespace().collect();
+            let (pin_summaries, target) = match args.as_slice() {
+                [mode] => (mode.to_lowercase(), chat_id.to_string()),
+                [mode, target] => (mode.to_lowercase(), target.to_string()),
+                _ => (String::new(), String::new()),
+            };
+            let pin_summaries = match pin_summaries.as_str() {
+                "on" => true,
+                "off" => false,
+                _ => {
+                    bot.send_message(msg.chat.id, "Usage: /pinsummary on|off, in the group, or /pinsummary on|off <chat_id|@channel>").await?;
+                    return Ok(());
+                }
+            };
+
+            let target_chat_id = match verify_pin_rights(&state, &target, user_id, chat_id, pin_summaries).await {
+                Ok(target_chat_id) => target_chat_id,
+                Err(reason) => {
+                    bot.send_message(msg.chat.id, reason).await?;
+                    return Ok(());
+                }
+            };
+
+            match database.set_pin_summaries(target_chat_id, pin_summaries).await {
+                Ok(()) => {
+                    let success_msg = if pin_summaries {
+                        "Summaries and reports posted in that chat will be pinned, replacing the previous one."
+                    } else {
+                        "Summaries and reports in that chat won't be pinned anymore."
+                    };
+                    bot.send_message(msg.chat.id, success_msg).await?;
+                    info!("user {} set pin_summaries for chat {} to {}", user_id, target_chat_id, pin_summaries);
+                }
+                Err(e) => {
+                    let reply = state.error_reply(user_id, chat_id, format!("db error setting pin summaries for chat {}: {}", target_chat_id, e));
+                    bot.send_message(msg.chat.id, reply).await?;
+                }
+            }
+        }
+
+        Command::Branding(args) => {
+            let usage = "Usage, in the group: /branding prefix <text>, /branding suffix <text>, /branding emoji off|sides|full or /branding clear. \
+                For a channel, name it first: /branding @channel prefix <text>";
+            let args = args.trim();
+            // a channel can't run commands, so its admins name it instead
+            let (target, args) = match args.split_once(char::is_whitespace).unwrap_or((args, "")) {
+                (first, rest) if first.starts_with('@') || first.parse::<i64>().is_ok() => (first.to_string(), rest.trim()),
+                _ => (chat_id.to_string(), args),
+            };
+            let (setting, value) = args.split_once(char::is_whitespace).unwrap_or((args, ""));
+            let value = value.trim();
+
+            let target_chat_id = match verify_branding_rights(&state, &target, user_id).await {
+                Ok(target_chat_id) => target_chat_id,
+                Err(reason) => {
+                    bot.send_message(msg.chat.id, reason).await?;
+                    return Ok(());
+                }
+            };
+            let mut branding = match database.get_chat_settings(target_chat_id).await {
+                Ok(settings) => settings.branding,
+                Err(e) => {
+                    let reply = state.error_reply(user_id, chat_id, format!("db error getting chat settings for chat {}: {}", target_chat_id, e));
+                    bot.send_message(msg.chat.id, reply).await?;
+                    return Ok(());
+                }
+            };
+
+            let line = |value: &str| (!value.is_empty() && value != "off").then(|| value.to_string());
+            match setting.to_lowercase().as_str() {
+                "" => {
+                    let branding_msg = if branding.is_plain() {
+                        format!("Alerts in that chat aren't branded.\n\n{}", usage)
+                    } else {
+                        format!(
+                            "Prefix: {}\nSuffix: {}\nEmoji: {}",
+                            branding.prefix.as_deref().unwrap_or("none"),
+                            branding.suffix.as_deref().unwrap_or("none"),
+                            branding.emoji.as_str()
+                        )
+                    };
+                    bot.send_message(msg.chat.id, branding_msg).await?;
+                    return Ok(());
+                }
+                "prefix" | "suffix" if value.chars().count() > MAX_BRANDING_CHARS => {
+                    bot.send_message(msg.chat.id, format!("Keep it under {} characters.", MAX_BRANDING_CHARS)).await?;
+                    return Ok(());
+                }
+                "prefix" => branding.prefix = line(value),
+                "suffix" => branding.suffix = line(value),
+                "emoji" => match EmojiStyle::parse(value) {
+                    Some(emoji) => branding.emoji = emoji,
+                    None => {
+                        bot.send_message(msg.chat.id, "Usage: /branding emoji off|sides|full").await?;
+                        return Ok(());
+                    }
+                },
+                "clear" => branding = Branding::default(),
+                _ => {
+                    bot.send_message(msg.chat.id, usage).await?;
+                    return Ok(());
+                }
+            }
+
+            match database.set_chat_branding(target_chat_id, &branding).await {
+                Ok(()) => {
+                    state.remember_branding(target_chat_id, branding);
+                    bot.send_message(msg.chat.id, "Alerts posted in that chat will carry the new branding.").await?;
+                    info!("user {} changed branding for chat {}", user_id, target_chat_id);
+                }
+                Err(e) => {
+                    let reply = state.error_reply(user_id, chat_id, format!("db error setting branding for chat {}: {}", target_chat_id, e));
+                    bot.send_message(msg.chat.id, reply).await?;
+                }
+            }
+        }
+
+        Command::Silent(args) => {
+            let args = args.trim().to_lowercase();
+            let silent_below_usd = match args.split_whitespace().collect::<Vec<_>>().as_slice() {
+                [] => {
+                    let silent_msg = match database.get_user_settings(user_id).await {
+                        Ok(settings) => match settings.silent_below_usd {
+                            Some(below) => format!(
+                                "Trade alerts under {} arrive silently.\n\nUse /silent off to hear every alert.",
+                                formatting::format_usd(below, false)
+                            ),
+                            None => "Every trade alert makes a sound.\n\nUse /silent below <usd> to quiet the smaller ones (e.g. /silent below 100000).".to_string(),
+                        },
+                        Err(e) => state.error_reply(user_id, chat_id, format!("db error getting settings for user {}: {}", user_id, e)),
+                    };
+                    bot.send_message(msg.chat.id, silent_msg).await?;
+                    return Ok(());
+                }
+                ["off"] => None,
+                ["below", amount] => match amount.replace(',', "").trim_start_matches('$').parse::<f64>() {
+                    Ok(below) if below.is_finite() && below > 0.0 => Some(below),
+                    _ => {
+                        bot.send_message(msg.chat.id, "Usage: /silent below <usd> or /silent off").await?;
+                        return Ok(());
+                    }
+                },
+                _ => {
+                    bot.send_message(msg.chat.id, "Usage: /silent below <usd> or /silent off").await?;
+                    return Ok(());
+                }
+            };
+
+            match database.set_silent_below(user_id, silent_below_usd).await {
+                Ok(()) => {
+                    let success_msg = match silent_below_usd {
+                        Some(below) => format!(
+                            "Trade alerts under {} will arrive silently. Bigger ones still make a sound.",
+                            formatting::format_usd(below, false)
+                        ),
+                        None => "Every trade alert will make a sound again.".to_string(),
+                    };
+                    bot.send_message(msg.chat.id, success_msg).await?;
+                    info!("user {} set silent_below_usd to {:?}", user_id, silent_below_usd);
+                }
+                Err(e) => {
+                    let reply = state.error_reply(user_id, chat_id, format!("db error setting silent threshold for user {}: {}", user_id, e));
+                    bot.send_message(msg.chat.id, reply).await?;
+                }
+            }
+        }
+
+        Command::DailyCap(args) => {
+            let daily_alert_cap = match args.trim().to_lowercase().as_str() {
+                "" => {
+                    let cap_msg = match database.get_user_settings(user_id).await {
+                        Ok(settings) => match settings.daily_alert_cap {
+                            Some(cap) => format!("You get at most {} trade alerts per day (UTC).\n\nUse /dailycap off to remove the cap.", cap),
+                            None => "You have no daily alert cap.\n\nUse /dailycap <count> to set one (e.g. /dailycap 50).".to_string(),
+                        },
+                        Err(e) => state.error_reply(user_id, chat_id, format!("db error getting settings for user {}: {}", user_id, e)),
+                    };
+                    bot.send_message(msg.chat.id, cap_msg).await?;
+                    return Ok(());
+                }
+                "off" => None,
+                count => match count.parse::<u32>() {
+                    Ok(cap) if (1..=MAX_DAILY_ALERT_CAP).contains(&cap) => Some(cap),
+                    _ => {
+                        let usage_msg = format!("Usage: /dailycap <1-{}> or /dailycap off", MAX_DAILY_ALERT_CAP);
+                        bot.send_message(msg.chat.id, usage_msg).await?;
+                        return Ok(());
+                    }
+                },
+            };
+
+            match database.set_daily_alert_cap(user_id, daily_alert_cap).await {
+                Ok(()) => {
+                    let success_msg = match daily_alert_cap {
+                        Some(cap) => format!(
+                            "You'll get at most {} trade alerts per day. Anything past that is summarized at midnight UTC.",
+                            cap
+                        ),
+                        None => "Daily alert cap removed.".to_string(),
+                    };
+                    bot.send_message(msg.chat.id, success_msg).await?;
+                    info!("user {} set daily_alert_cap to {:?}", user_id, daily_alert_cap);
+                }
+                Err(e) => {
+                    let reply = state.error_reply(user_id, chat_id, format!("db error setting daily alert cap for user {}: {}", user_id, e));
+                    bot.send_message(msg.chat.id, reply).await?;
+                }
+            }
+        }
+
+        Command::DigestMode(args) => {
+            let digest_mins = match args.trim().to_lowercase().as_str() {
+                "" => {
+                    let digest_msg = match database.get_user_settings(user_id).await {
+                        Ok(settings) => match settings.digest_mins {
+                            Some(mins) => format!("Your trade alerts arrive as one digest every {} minutes.\n\nUse /digestmode off to get them as they happen.", mins),
+                            None => "Trade alerts arrive as they happen.\n\nUse /digestmode <minutes> to get them as one digest instead (e.g. /digestmode 60).".to_string(),
+                        },
+                        Err(e) => state.error_reply(user_id, chat_id, format!("db error getting settings for user {}: {}", user_id, e)),
+                    };
+                    bot.send_message(msg.chat.id, digest_msg).await?;
+                    return Ok(());
+                }
+                "off" => None,
+                mins => match mins.trim_end_matches('m').parse::<u32>() {
+                    Ok(mins) if (MIN_DIGEST_MINS..=MAX_DIGEST_MINS).contains(&mins) => Some(mins),
+                    _ => {
+                        let usage_msg = format!("Usage: /digestmode <{}-{} minutes> or /digestmode off", MIN_DIGEST_MINS, MAX_DIGEST_MINS);
+                        bot.send_message(msg.chat.id, usage_msg).await?;
+                        return Ok(());
+                    }
+                },
+            };
+
+            match database.set_digest_mins(user_id, digest_mins).await {
+                Ok(()) => {
+                    let success_msg = match digest_mins {
+                        Some(mins) => format!(
+                            "Digest mode on. Every {} minutes you'll get one message covering all your coins, with subtotals for each.",
+                            mins
+                        ),
+                        None => "Digest mode off. Alerts arrive as they happen again.".to_string(),
+                    };
+                    bot.send_message(msg.chat.id, success_msg).await?;
+                    info!("user {} set digest_mins to {:?}", user_id, digest_mins);
+                }
+                Err(e) => {
+                    let reply = state.error_reply(user_id, chat_id, format!("db error setting digest mode for user {}: {}", user_id, e));
+                    bot.send_message(msg.chat.id, reply).await?;
+                }
+            }
+        }
+
+        Command::Priority(args) => {
+            let args: Vec<String> = args.split_whitespace().map(str::to_uppercase).collect();
+            let priority_coins = match database.get_priority_coins(user_id).await {
+                Ok(coins) => coins,
+                Err(e) => {
+                    let reply = state.error_reply(user_id, chat_id, format!("db error getting priority coins for user {}: {}", user_id, e));
+                    bot.send_message(msg.chat.id, reply).await?;
+                    return Ok(());
+                }
+            };
+
+            let (coin, priority) = match args.as_slice() {
+                [] => {
+                    let list_msg = if priority_coins.is_empty() {
+                        format!(
+                            "You have no priority coins.\n\nUse /priority <coin> to send a coin's alerts straight away, skipping digests, tickers and your daily cap (up to {}).",
+                            MAX_PRIORITY_COINS
+                        )
+                    } else {
+                        format!("Priority coins: {}\n\nUse /priority <coin> off to remove one.", priority_coins.join(", "))
+                    };
+                    bot.send_message(msg.chat.id, list_msg).await?;
+                    return Ok(());
+                }
+                [coin] => (coin, true),
+                [coin, off] if off == "OFF" => (coin, false),
+                _ => {
+                    bot.send_message(msg.chat.id, "Usage: /priority <coin> or /priority <coin> off").await?;
+                    return Ok(());
+                }
+            };
+
+            if priority && !priority_coins.contains(coin) && priority_coins.len() >= MAX_PRIORITY_COINS {
+                let full_msg = format!(
+                    "You already have {} priority coins ({}). Remove one with /priority <coin> off first.",
+                    MAX_PRIORITY_COINS,
+                    priority_coins.join(", ")
+                );
+                bot.send_message(msg.chat.id, full_msg).await?;
+                return Ok(());
+            }
+
+            let reply = match database.set_subscription_priority(user_id, coin, priority).await {
+                Ok(true) => {
+                    info!("user {} set {} priority to {}", user_id, coin, priority);
+                    if priority {
+                        format!("{} is a priority coin. Its alerts go out straight away, skipping digests, tickers and your daily cap.", coin)
+                    } else {
+                        format!("{} is no longer a priority coin.", coin)
+                    }
+                }
+                Ok(false) => format!("You're not subscribed to {}.", coin),
+                Err(e) => state.error_reply(user_id, chat_id, format!("db error setting {} priority for user {}: {}", coin, user_id, e)),
+            };
+            bot.send_message(msg.chat.id, reply).await?;
+        }
+
+        Command::Mute(args) => {
+            if args.trim().is_empty() {
+                let rules = state.mutes.rules(user_id);
+                let list_msg = if rules.is_empty() {
+                    "You have no mute rules.\n\nUse /mute <words> [under <usd>] to mute matching alerts on every coin (e.g. /mute sell under 100k).".to_string()
+                } else {
+                    let lines: Vec<String> = rules.iter().map(|rule| format!("#{} {}", rule.id, rule.describe())).collect();
+                    format!("Muted:\n\n{}\n\nUse /unmute <id> or /unmute all to remove them.", lines.join("\n"))
+                };
+                send_chunked(&state, &bot, msg.chat.id, &list_msg, None).await?;
+                return Ok(());
+            }
+
+            let Some((keywords, under_usd)) = mutes::parse_rule(&args) else {
+                bot.send_message(msg.chat.id, "Usage: /mute <words> [under <usd>] (e.g. /mute sell under 100k, /mute liquidation)").await?;
+                return Ok(());
+            };
+            if state.mutes.rules(user_id).len() >= MAX_MUTE_RULES {
+                let full_msg = format!("You already have {} mute rules. Remove one with /unmute <id> first.", MAX_MUTE_RULES);
+                bot.send_message(msg.chat.id, full_msg).await?;
+                return Ok(());
+            }
+
+            match state.mutes.add(user_id, &keywords, under_usd).await {
+                Ok(rule) => {
+                    let success_msg = format!("Muted {} on every coin (rule #{}).", rule.describe(), rule.id);
+                    bot.send_message(msg.chat.id, success_msg).await?;
+                    info!("user {} added mute rule {}", user_id, rule.id);
+                }
+                Err(e) => {
+                    let reply = state.error_reply(user_id, chat_id, format!("db error adding mute rule for user {}: {}", user_id, e));
+                    bot.send_message(msg.chat.id, reply).await?;
+                }
+            }
+        }
+
+        Command::Unmute(args) => {
+            let rule_id = match args.trim().trim_start_matches('#') {
+                "all" => None,
+                id => match id.parse::<i64>() {
+                    Ok(id) => Some(id),
+                    Err(_) => {
+                        bot.send_message(msg.chat.id, "Usage: /unmute <id> or /unmute all (see /mute for ids)").await?;
+                        return Ok(());
+                    }
+                },
+            };
+
+            let reply = match state.mutes.remove(user_id, rule_id).await {
+                Ok(0) => "No matching mute rule, see /mute for yours.".to_string(),
+                Ok(removed) => {
+                    info!("user {} removed {} mute rules", user_id, removed);
+                    format!("Removed {} mute rule{}.", removed, if removed == 1 { "" } else { "s" })
+                }
+                Err(e) => state.error_reply(user_id, chat_id, format!("db error removing mute rules for user {}: {}", user_id, e)),
+            };
+            bot.send_message(msg.chat.id, reply).await?;
+        }
+
+        Command::Duplicates(mode_arg) => {
+            let Some(preference) = DuplicatePreference::parse(&mode_arg) else {
+                bot.send_message(msg.chat.id, "Usage: /duplicates both, /duplicates group or /duplicates dm").await?;
+                return Ok(());
+            };
+
+            match database.set_duplicate_alerts(user_id, preference).await {
+                Ok(()) => {
+                    let success_msg = match preference {
+                        DuplicatePreference::Both => "You'll get alerts both in DM and in groups you're in.",
+                        DuplicatePreference::Group => "When a group you're in gets an alert, you won't get it again in DM.",
+                        DuplicatePreference::Dm => "When you get an alert in DM, group copies only you set up are skipped.",
+                    };
+                    bot.send_message(msg.chat.id, success_msg).await?;
+                    info!("user {} set duplicate_alerts to {}", user_id, preference.as_str());
+                }
+                Err(e) => {
+                    let reply = state.error_reply(user_id, chat_id, format!("db error setting duplicate alerts for user {}: {}", user_id, e));
+                    bot.send_message(msg.chat.id, reply).await?;
+                }
+            }
+        }
+
+        Command::Price(coin_arg) => {
+            let coin = match coin_arg.trim() {
+                "" => state.config.defaults.default_symbol.to_uppercase(),
+                coin => coin.to_uppercase(),
+            };
+
+            let mid = match state.price_engine.mid_str(&coin).await {
+                Some(mid) => Some(mid),
+                None => match hyperliquid_client.all_mids().await {
+                    Ok(mut mids) => mids.remove(&coin),
+                    Err(e) => {
+                        let reply = state.error_reply_as(
+                            user_id,
+                            chat_id,
+                            "Sorry, there was an error fetching prices. Please try again.",
+                            format!("couldn't fetch mids for /price {}: {}", coin, e),
+                        );
+                        bot.send_message(msg.chat.id, reply).await?;
+                        return Ok(());
+                    }
+                },
+            };
+
+            let Some(mid) = mid else {
+                let invalid_msg = format!("{} is not available on Hyperliquid.", coin);
+                bot.send_message(msg.chat.id, invalid_msg).await?;
+                return Ok(());
+            };
+
+            let mut price_msg = format!("{}: ${}", coin, formatting::format_price(&mid));
+
+            match state.candles.recent(&coin, "1h", std::time::Duration::from_secs(24 * 60 * 60)).await {
+                Ok(candles) => {
+                    let closes: Vec<f64> = candles.iter().filter_map(|c| c.close.parse().ok()).collect();
+                    let first_open = candles.first().and_then(|c| c.open.parse::<f64>().ok());
+                    if let (Some(open), Ok(current)) = (first_open, mid.parse::<f64>()) {
+                        if open > 0.0 {
+                            let change = (current - open) / open * 100.0;
+                            price_msg.push_str(&format!("\n24h: {}", formatting::format_percent_change(change)));
+                        }
+                    }
+                    if !closes.is_empty() {
+                        price_msg.push_str(&format!("\n{}", formatting::sparkline(&closes)));
+                    }
+                }
+                Err(e) => {
+                    error!("couldn't fetch candles for /price {}: {}", coin, e);
+                }
+            }
+
+            bot.send_message(msg.chat.id, price_msg).await?;
+        }
+
+        Command::Info(args) => {
+            let coins: Vec<String> = args.split_whitespace().map(str::to_uppercase).collect();
+
+            match market::snapshots(&hyperliquid_client, &coins, INFO_TOP_COINS).await {
+                Ok(snapshots) if snapshots.is_empty() => {
+                    bot.send_message(msg.chat.id, "None of those coins are available on Hyperliquid.").await?;
+                }
+                Ok(mut snapshots) => {
+                    let liquidators = &state.config.liquidations.liquidator_addresses;
+                    if let Err(e) = market::attach_liquidations(database, liquidators, &mut snapshots).await {
+                        error!("couldn't load liquidation totals for /info: {}", e);
+                    }
+                    market::attach_live_activity(&state.activity, &mut snapshots);
+                    let info_msg = market::format_snapshot("Market Snapshot", &snapshots, &state.config.links);
+                    send_chunked(&state, &bot, msg.chat.id, &info_msg, None).await?;
+                }
+                Err(e) => {
+                    let reply = state.error_reply_as(
+                        user_id,
+                        chat_id,
+                        "Sorry, there was an error fetching market data. Please try again.",
+                        format!("couldn't fetch asset contexts for /info: {}", e),
+                    );
+                    bot.send_message(msg.chat.id, reply).await?;
+                }
+            }
+        }
+
+        Command::History(args) => {
+            let usage = "Usage: /history <coin> [days] (e.g. /history ETH 30)";
+            let args: Vec<&str> = args.split_whitespace().collect();
+            let (coin, days) = match args.as_slice() {
+                [coin] => (coin.to_uppercase(), HISTORY_DEFAULT_DAYS),
+                [coin, days] => match days.parse::<i64>() {
+                    Ok(days) if (1..=HISTORY_MAX_DAYS).contains(&days) => (coin.to_uppercase(), days),
+                    _ => {
+                        bot.send_message(msg.chat.id, format!("Days must be between 1 and {}.", HISTORY_MAX_DAYS)).await?;
+                        return Ok(());
+                    }
+                },
+                _ => {
+                    bot.send_message(msg.chat.id, usage).await?;
+                    return Ok(());
+                }
+            };
+
+            let rollups = match database.get_trade_rollups(&coin, days).await {
+                Ok(rollups) => rollups,
+                Err(e) => {
+                    let reply = state.error_reply(user_id, chat_id, format!("db error getting {} trade rollups: {}", coin, e));
+                    bot.send_message(msg.chat.id, reply).await?;
+                    return Ok(());
+                }
+            };
+            if rollups.is_empty() {
+                let empty_msg = format!("No large {} trades recorded in the last {} days.", coin, days);
+                bot.send_message(msg.chat.id, empty_msg).await?;
+                return Ok(());
+            }
+
+            let mut history_msg = format!("{} Large Trades by Day (UTC)\n", coin);
+            for rollup in &rollups {
+                history_msg.push_str(&format!(
+                    "\n{}: buys {} ({}) · sells {} ({})",
+                    rollup.day.format("%m-%d"),
+                    formatting::format_usd(rollup.buy_usd, false),
+                    rollup.buys,
+                    formatting::format_usd(rollup.sell_usd, false),
+                    rollup.sells
+                ));
+                if let Some(buy_pct) = rollup.buy_pct() {
+                    history_msg.push_str(&format!(" · {:.0}% buy", buy_pct));
+                }
+            }
+            let buy_usd: f64 = rollups.iter().map(|rollup| rollup.buy_usd).sum();
+            let sell_usd: f64 = rollups.iter().map(|rollup| rollup.sell_usd).sum();
+            if buy_usd + sell_usd > 0.0 {
+                history_msg.push_str(&format!(
+                    "\n\n{}d: {:.0}% buy of {}",
+                    days,
+                    buy_usd / (buy_usd + sell_usd) * 100.0,
+                    formatting::format_usd(buy_usd + sell_usd, false)
+                ));
+            }
+            send_chunked(&state, &bot, msg.chat.id, &history_msg, None).await?;
+        }
+
+        Command::Preview(args) => {
+            let usage = "Usage: /preview <coin> <usd> [buy|sell] (e.g. /preview ETH 750000 buy)";
+            let args: Vec<String> = args.split_whitespace().map(str::to_lowercase).collect();
+            let (coin, amount, side) = match args.as_slice() {
+                [coin, amount] => (coin.to_uppercase(), amount, "B"),
+                [coin, amount, side] if side == "buy" => (coin.to_uppercase(), amount, "B"),
+                [coin, amount, side] if side == "sell" => (coin.to_uppercase(), amount, "A"),
+                _ => {
+                    bot.send_message(msg.chat.id, usage).await?;
+                    return Ok(());
+                }
+            };
+            let notional_usd = match amount.replace(',', "").trim_start_matches('$').parse::<f64>() {
+                Ok(notional) if notional.is_finite() && notional > 0.0 => notional,
+                _ => {
+                    bot.send_message(msg.chat.id, usage).await?;
+                    return Ok(());
+                }
+            };
+
+            let mid = match state.price_engine.mid_str(&coin).await {
+                Some(mid) => Some(mid),
+                None => match hyperliquid_client.all_mids().await {
+                    Ok(mut mids) => mids.remove(&coin),
+                    Err(e) => {
+                        let reply = state.error_reply_as(
+                            user_id,
+                            chat_id,
+                            "Sorry, there was an error fetching prices. Please try again.",
+                            format!("couldn't fetch mids for /preview {}: {}", coin, e),
+                        );
+                        bot.send_message(msg.chat.id, reply).await?;
+                        return Ok(());
+                    }
+                },
+            };
+            let Some(px) = mid.filter(|mid| mid.parse::<f64>().is_ok_and(|px| px > 0.0)) else {
+                bot.send_message(msg.chat.id, format!("{} is not available on Hyperliquid.", coin)).await?;
+                return Ok(());
+            };
+            let settings = match database.get_user_settings(user_id).await {
+                Ok(settings) => settings,
+                Err(e) => {
+                    let reply = state.error_reply(user_id, chat_id, format!("db error getting settings for user {}: {}", user_id, e));
+                    bot.send_message(msg.chat.id, reply).await?;
+                    return Ok(());
+                }
+            };
+
+            // a single fill at the current mid, sent down the same path as a real alert
+            let trade = WsTrade {
+                sz: (notional_usd / px.parse::<f64>().unwrap_or(1.0)).to_string(),
+                coin: CoinSymbol::new(&coin),
+                side: side.to_string(),
+                px,
+                time: Some(chrono::Utc::now().timestamp_millis()),
+                tid: None,
+                hash: None,
+                users: None,
+                fills: 1,
+                quote_usd: 1.0,
+            };
+            let chart = if settings.charts_enabled {
+                match state.trade_chart(&coin).await {
+                    Ok(chart) => Some(chart),
+                    Err(e) => {
+                        warn!("couldn't render chart for /preview {}: {}", coin, e);
+                        None
+                    }
+                }
+            } else {
+                None
+            };
+
+            bot.send_message(msg.chat.id, format!("Preview of a {} alert with your current settings:", coin)).await?;
+            if let Err(e) = state.send_trade_notification(msg.chat.id.0, &trade, notional_usd, &settings, chart, Priority::Alert).await {
+                let reply = state.error_reply(user_id, chat_id, format!("couldn't send /preview {} to chat {}: {}", coin, msg.chat.id, e));
+                bot.send_message(msg.chat.id, reply).await?;
+            }
+        }
+
+        Command::Imbalance(args) => {
+            let args: Vec<&str> = args.split_whitespace().collect();
+
+            match args.as_slice() {
+                [] => {
+                    match database.get_user_imbalance_alerts(user_id).await {
+                        Ok(alerts) if alerts.is_empty() => {
+                            bot.send_message(
+                                msg.chat.id,
+                                "You have no imbalance alerts.\n\nUse /imbalance <coin> <percent> to add one (e.g. /imbalance ETH 70)."
+                            ).await?;
+                        }
+                        Ok(alerts) => {
+                            let lines: Vec<String> = alerts
+                                .iter()
+                                .map(|alert| format!("{}: {:.0}%", alert.coin, alert.threshold_pct))
+                                .collect();
+                            let list_msg = format!("Your Imbalance Alerts:\n\n{}", lines.join("\n"));
+                            send_chunked(&state, &bot, msg.chat.id, &list_msg, None).await?;
+                        }
+                        Err(e) => {
+                            let reply = state.error_reply(user_id, chat_id, format!("db error getting imbalance alerts for user {}: {}", user_id, e));
+                            bot.send_message(msg.chat.id, reply).await?;
+                        }
+                    }
+                }
+
+                [coin, "off"] => {
+                    let coin = coin.to_uppercase();
+                    match database.remove_imbalance_alert(user_id, &coin).await {
+                        Ok(true) => {
+                            let success_msg = format!("Removed your {} imbalance alert.", coin);
+                            bot.send_message(msg.chat.id, success_msg).await?;
+                            info!("user {} removed {} imbalance alert", user_id, coin);
+
+                            if let Err(e) = event_sender.send(CoordinatorCommand::ImbalanceAlertChanged {
+                                coin: CoinSymbol::new(&coin)
+                            }) {
+                                error!("couldn't send imbalance event for {}: {}", coin, e);
+                            }
+                        }
+                        Ok(false) => {
+                            let missing_msg = format!("You don't have a {} imbalance alert.", coin);
+                            bot.send_message(msg.chat.id, missing_msg).await?;
+                        }
+                        Err(e) => {
+                            let reply = state.error_reply(user_id, chat_id, format!("db error removing imbalance alert for user {}: {}", user_id, e));
+                            bot.send_message(msg.chat.id, reply).await?;
+                        }
+                    }
+                }
+
+                [coin, threshold] => {
+                    let coin = coin.to_uppercase();
+                    let threshold_pct = match threshold.trim_end_matches('%').parse::<f64>() {
+                        // the larger side always holds at least half
+                        Ok(pct) if pct > 50.0 && pct < 100.0 => pct,
+                        _ => {
+                            bot.send_message(msg.chat.id, "Threshold is the larger side's share of depth, a percentage between 50 and 100.").await?;
+                            return Ok(());
+                        }
+                    };
+
+                    match hyperliquid_client.coin_exists(&coin).await {
+                        Ok(true) => {}
+                        Ok(false) => {
+                            let invalid_msg = format!("{} is not available on Hyperliquid.", coin);
+                            bot.send_message(msg.chat.id, invalid_msg).await?;
+                            return Ok(());
+                        }
+                        Err(e) => {
+                            let reply = state.error_reply_as(
+                                user_id,
+                                chat_id,
+                                "Sorry, there was an error validating the coin. Please try again.",
+                                format!("couldn't validate {} for {}: {}", coin, user_id, e),
+                            );
+                            bot.send_message(msg.chat.id, reply).await?;
+                            return Ok(());
+                        }
+                    }
+
+                    match database.set_imbalance_alert(user_id, chat_id, &coin, threshold_pct).await {
+                        Ok(()) => {
+                            let success_msg = format!(
+                                "You'll be alerted when one side holds {:.0}%+ of {} book depth near mid.",
+                                threshold_pct, coin
+                            );
+                            bot.send_message(msg.chat.id, success_msg).await?;
+                            info!("user {} set {} imbalance alert at {}%", user_id, coin, threshold_pct);
+
+                            if let Err(e) = event_sender.send(CoordinatorCommand::ImbalanceAlertChanged {
+                                coin: CoinSymbol::new(&coin)
+                            }) {
+                                error!("couldn't send imbalance event for {}: {}", coin, e);
+                            }
+                        }
+                        Err(e) => {
+                            let reply = state.error_reply(user_id, chat_id, format!("db error setting imbalance alert for user {}: {}", user_id, e));
+                            bot.send_message(msg.chat.id, reply).await?;
+                        }
+                    }
+                }
+
+                _ => {
+                    bot.send_message(msg.chat.id, "Usage: /imbalance <coin> <percent> or /imbalance <coin> off").await?;
+                }
+            }
+        }
+
+        Command::VwapAlert(args) => {
+            let args: Vec<&str> = args.split_whitespace().collect();
+
+            match args.as_slice() {
+                [] => {
+                    match database.get_user_vwap_alerts(user_id).await {
+                        Ok(alerts) if alerts.is_empty() => {
+                            bot.send_message(
+                                msg.chat.id,
+                                "You have no VWAP alerts.\n\nUse /vwapalert <coin> <percent> to add one (e.g. /vwapalert ETH 2)."
+                            ).await?;
+                        }
+                        Ok(alerts) => {
+                            let lines: Vec<String> = alerts
+                                .iter()
+                                .map(|alert| format!("{}: {}%", alert.coin, alert.threshold_pct))
+                                .collect();
+                            let list_msg = format!("Your VWAP Alerts:\n\n{}", lines.join("\n"));
+                            send_chunked(&state, &bot, msg.chat.id, &list_msg, None).await?;
+                        }
+                        Err(e) => {
+                            let reply = state.error_reply(user_id, chat_id, format!("db error getting vwap alerts for user {}: {}", user_id, e));
+                            bot.send_message(msg.chat.id, reply).await?;
+                        }
+                    }
+                }
+
+                [coin, "off"] => {
+                    let coin = coin.to_uppercase();
+                    match database.remove_vwap_alert(user_id, &coin).await {
+                        Ok(true) => {
+                            let success_msg = format!("Removed your {} VWAP alert.", coin);
+                            bot.send_message(msg.chat.id, success_msg).await?;
+                            info!("user {} removed {} vwap alert", user_id, coin);
+
+                            if let Err(e) = event_sender.send(CoordinatorCommand::VwapAlertChanged {
+                                coin: CoinSymbol::new(&coin)
+                            }) {
+                                error!("couldn't send vwap event for {}: {}", coin, e);
+                            }
+                        }
+                        Ok(false) => {
+                            let missing_msg = format!("You don't have a {} VWAP alert.", coin);
+                            bot.send_message(msg.chat.id, missing_msg).await?;
+                        }
+                        Err(e) => {
+                            let reply = state.error_reply(user_id, chat_id, format!("db error removing vwap alert for user {}: {}", user_id, e));
+                            bot.send_message(msg.chat.id, reply).await?;
+                        }
+                    }
+                }
+
+                [coin, threshold] => {
+                    let coin = coin.to_uppercase();
+                    let threshold_pct = match threshold.trim_end_matches('%').parse::<f64>() {
+                        // the larger side always holds at least half
+                        Ok(pct) if pct > 50.0 && pct < 100.0 => pct,
+                        _ => {
+                            bot.send_message(msg.chat.id, "Threshold is the larger side's share of depth, a percentage between 50 and 100.").await?;
+                            return Ok(());
+                        }
+                    };
+
+                    match hyperliquid_client.coin_exists(&coin).await {
+                        Ok(true) => {}
+                        Ok(false) => {
+                            let invalid_msg = format!("{} is not available on Hyperliquid.", coin);
+                            bot.send_message(msg.chat.id, invalid_msg).await?;
+                            return Ok(());
+                        }
+                        Err(e) => {
+                            let reply = state.error_reply_as(
+                                user_id,
+                                chat_id,
+                                "Sorry, there was an error validating the coin. Please try again.",
+                                format!("couldn't validate {} for {}: {}", coin, user_id, e),
+                            );
+                            bot.send_message(msg.chat.id, reply).await?;
+                            return Ok(());
+                        }
+                    }
+
+                    match database.set_vwap_alert(user_id, chat_id, &coin, threshold_pct).await {
+                        Ok(()) => {
+                            let success_msg = format!(
+                                "You'll be alerted when {} trades {}%+ away from its 1h VWAP.",
+                                coin, threshold_pct
+                            );
+                            bot.send_message(msg.chat.id, success_msg).await?;
+                            info!("user {} set {} vwap alert at {}%", user_id, coin, threshold_pct);
+
+                            if let Err(e) = event_sender.send(CoordinatorCommand::VwapAlertChanged {
+                                coin: CoinSymbol::new(&coin)
+                            }) {
+                                error!("couldn't send vwap event for {}: {}", coin, e);
+                            }
+                        }
+                        Err(e) => {
+                            let reply = state.error_reply(user_id, chat_id, format!("db error setting vwap alert for user {}: {}", user_id, e));
+                            bot.send_message(msg.chat.id, reply).await?;
+                        }
+                    }
+                }
+
+                _ => {
+                    bot.send_message(msg.chat.id, "Usage: /vwapalert <coin> <percent> or /vwapalert <coin> off").await?;
+                }
+            }
+        }
+
+        Command::VolAlert(args) => {
+            let args: Vec<&str> = args.split_whitespace().collect();
+
+            match args.as_slice() {
+                [] => {
+                    match database.get_user_volatility_alerts(user_id).await {
+                        Ok(alerts) if alerts.is_empty() => {
+                            bot.send_message(
+                                msg.chat.id,
+                                "You have no volatility alerts.\n\nUse /volalert <coin> <multiple> to add one (e.g. /volalert ETH 3)."
+                            ).await?;
+                        }
+                        Ok(alerts) => {
+                            let lines: Vec<String> = alerts
+                                .iter()
+                                .map(|alert| format!("{}: {}x", alert.coin, alert.multiple))
+                                .collect();
+                            let list_msg = format!("Your Volatility Alerts:\n\n{}", lines.join("\n"));
+                            send_chunked(&state, &bot, msg.chat.id, &list_msg, None).await?;
+                        }
+                        Err(e) => {
+                            let reply = state.error_reply(user_id, chat_id, format!("db error getting volatility alerts for user {}: {}", user_id, e));
+                            bot.send_message(msg.chat.id, reply).await?;
+                        }
+                    }
+                }
+
+                [coin, "off"] => {
+                    let coin = coin.to_uppercase();
+                    match database.remove_volatility_alert(user_id, &coin).await {
+                        Ok(true) => {
+                            let success_msg = format!("Removed your {} volatility alert.", coin);
+                            bot.send_message(msg.chat.id, success_msg).await?;
+                            info!("user {} removed {} volatility alert", user_id, coin);
+
+                            if let Err(e) = event_sender.send(CoordinatorCommand::VolatilityAlertChanged {
+                                coin: CoinSymbol::new(&coin)
+                            }) {
+                                error!("couldn't send volatility event for {}: {}", coin, e);
+                            }
+                        }
+                        Ok(false) => {
+                            let missing_msg = format!("You don't have a {} volatility alert.", coin);
+                            bot.send_message(msg.chat.id, missing_msg).await?;
+                        }
+                        Err(e) => {
+                            let reply = state.error_reply(user_id, chat_id, format!("db error removing volatility alert for user {}: {}", user_id, e));
+                            bot.send_message(msg.chat.id, reply).await?;
+                        }
+                    }
+                }
+
+                [coin, multiple] => {
+                    let coin = coin.to_uppercase();
+                    let multiple = match multiple.trim_end_matches(['x', 'X', '×']).parse::<f64>() {
+                        Ok(multiple) if multiple > 1.0 && multiple <= 100.0 => multiple,
+                        _ => {
+                            bot.send_message(msg.chat.id, "Multiple must be a number above 1 (e.g. 3 for 3x normal).").await?;
+                            return Ok(());
+                        }
+                    };
+
+                    match hyperliquid_client.coin_exists(&coin).await {
+                        Ok(true) => {}
+                        Ok(false) => {
+                            let invalid_msg = format!("{} is not available on Hyperliquid.", coin);
+                            bot.send_message(msg.chat.id, invalid_msg).await?;
+                            return Ok(());
+                        }
+                        Err(e) => {
+                            let reply = state.error_reply_as(
+                                user_id,
+                                chat_id,
+                                "Sorry, there was an error validating the coin. Please try again.",
+                                format!("couldn't validate {} for {}: {}", coin, user_id, e),
+                            );
+                            bot.send_message(msg.chat.id, reply).await?;
+                            return Ok(());
+                        }
+                    }
+
+                    match database.set_volatility_alert(user_id, chat_id, &coin, multiple).await {
+                        Ok(()) => {
+                            let success_msg = format!(
+                                "You'll be alerted when {} volatility runs {}x its normal level.",
+                                coin, multiple
+                            );
+                            bot.send_message(msg.chat.id, success_msg).await?;
+                            info!("user {} set {} volatility alert at {}x", user_id, coin, multiple);
+
+                            if let Err(e) = event_sender.send(CoordinatorCommand::VolatilityAlertChanged {
+                                coin: CoinSymbol::new(&coin)
+                            }) {
+                                error!("couldn't send volatility event for {}: {}", coin, e);
+                            }
+                        }
+                        Err(e) => {
+                            let reply = state.error_reply(user_id, chat_id, format!("db error setting volatility alert for user {}: {}", user_id, e));
+                            bot.send_message(msg.chat.id, reply).await?;
+                        }
+                    }
+                }
+
+                _ => {
+                    bot.send_message(msg.chat.id, "Usage: /volalert <coin> <multiple> or /volalert <coin> off").await?;
+                }
+            }
+        }
+
+        Command::PriceAlert(args) => {
+            let args: Vec<&str> = args.split_whitespace().collect();
+            let usage = "Usage: /pricealert <coin> <>|<> <price> [repeat <30m|1h|1d>] or /pricealert <coin> off";
+
+            let (coin, direction, price, repeat) = match args.as_slice() {
+                [] => {
+                    match database.get_user_price_alerts(user_id).await {
+                        Ok(alerts) if alerts.is_empty() => {
+                            bot.send_message(
+                                msg.chat.id,
+                                "You have no price alerts.\n\nUse /pricealert <coin> <>|<> <price> to add one (e.g. /pricealert ETH > 3000 repeat 1h)."
+                            ).await?;
+                        }
+                        Ok(alerts) => {
+                            let lines: Vec<String> = alerts
+                                .iter()
+                                .map(|alert| {
+                                    let mode = match alert.repeat_secs {
+                                        Some(repeat_secs) => format!(
+                                            "every {}{}",
+                                            formatting::format_duration(std::time::Duration::from_secs(repeat_secs as u64)),
+                                            if alert.state == "armed" { "" } else { " (waiting to re-arm)" }
+                                        ),
+                                        None => "once".to_string(),
+                                    };
+                                    format!(
+                                        "{} {} {}, {}",
+                                        alert.coin,
+                                        if alert.above { ">" } else { "<" },
+                                        formatting::format_price(&alert.price.to_string()),
+                                        mode
+                                    )
+                                })
+                                .collect();
+                            let list_msg = format!("Your Price Alerts:\n\n{}", lines.join("\n"));
+                            send_chunked(&state, &bot, msg.chat.id, &list_msg, None).await?;
+                        }
+                        Err(e) => {
+                            let reply = state.error_reply(user_id, chat_id, format!("db error getting price alerts for user {}: {}", user_id, e));
+                            bot.send_message(msg.chat.id, reply).await?;
+                        }
+                    }
+                    return Ok(());
+                }
+
+                [coin, "off"] => {
+                    let coin = coin.to_uppercase();
+                    match database.remove_price_alerts(user_id, &coin).await {
+                        Ok(0) => {
+                            let missing_msg = format!("You don't have any {} price alerts.", coin);
+                            bot.send_message(msg.chat.id, missing_msg).await?;
+                        }
+                        Ok(removed) => {
+                            let success_msg = format!("Removed {} {} price alert{}.", removed, coin, if removed == 1 { "" } else { "s" });
+                            bot.send_message(msg.chat.id, success_msg).await?;
+                            info!("user {} removed {} {} price alerts", user_id, removed, coin);
+
+                            if let Err(e) = event_sender.send(CoordinatorCommand::PriceAlertChanged {
+                                coin: CoinSymbol::new(&coin)
+                            }) {
+                                error!("couldn't send price alert event for {}: {}", coin, e);
+                            }
+                        }
+                        Err(e) => {
+                            let reply = state.error_reply(user_id, chat_id, format!("db error removing price alerts for user {}: {}", user_id, e));
+                            bot.send_message(msg.chat.id, reply).await?;
+                        }
+                    }
+                    return Ok(());
+                }
+
+                [coin, direction, price] => (coin.to_uppercase(), *direction, *price, None),
+                [coin, direction, price, "repeat", every] => match price_alerts::parse_repeat(every) {
+                    Some(repeat) => (coin.to_uppercase(), *direction, *price, Some(repeat)),
+                    None => {
+                        bot.send_message(msg.chat.id, "Repeat must be between 1m and 7d (e.g. 30m, 1h, 1d).").await?;
+                        return Ok(());
+                    }
+                },
+
+                _ => {
+                    bot.send_message(msg.chat.id, usage).await?;
+                    return Ok(());
+                }
+            };
+
+            let above = match direction.to_lowercase().as_str() {
+                ">" | ">=" | "above" => true,
+                "<" | "<=" | "below" => false,
+                _ => {
+                    bot.send_message(msg.chat.id, usage).await?;
+                    return Ok(());
+                }
+            };
+            let price = match price.trim_start_matches('$').replace(',', "").parse::<f64>() {
+                Ok(price) if price > 0.0 && price.is_finite() => price,
+                _ => {
+                    bot.send_message(msg.chat.id, "Price must be a positive number (e.g. 3000).").await?;
+                    return Ok(());
+                }
+            };
+
+            match hyperliquid_client.coin_exists(&coin).await {
+                Ok(true) => {}
+                Ok(false) => {
+                    let invalid_msg = format!("{} is not available on Hyperliquid.", coin);
+                    bot.send_message(msg.chat.id, invalid_msg).await?;
+                    return Ok(());
+                }
+                Err(e) => {
+                    let reply = state.error_reply_as(
+                        user_id,
+                        chat_id,
+                        "Sorry, there was an error validating the coin. Please try again.",
+                        format!("couldn't validate {} for {}: {}", coin, user_id, e),
+                    );
+                    bot.send_message(msg.chat.id, reply).await?;
+                    return Ok(());
+                }
+            }
+
+            // it would only fire straight away
+            if let Some(mid) = state.price_engine.mid_str(&coin).await {
+                let already = mid.parse::<f64>().is_ok_and(|mid| if above { mid >= price } else { mid <= price });
+                if already {
+                    let already_msg = format!(
+                        "{} is already {} {} (now {}).",
+                        coin,
+                        if above { "above" } else { "below" },
+                        formatting::format_price(&price.to_string()),
+                        formatting::format_price(&mid)
+                    );
+                    bot.send_message(msg.chat.id, already_msg).await?;
+                    return Ok(());
+                }
+            }
+
+            let max_alerts = state.config.price_alerts.max_per_user;
+            match database.get_user_price_alerts(user_id).await {
+                Ok(alerts) if alerts.len() >= max_alerts => {
+                    let full_msg = format!(
+                        "You already have {} price alerts, the most allowed. Remove some with /pricealert <coin> off.",
+                        alerts.len()
+                    );
+                    bot.send_message(msg.chat.id, full_msg).await?;
+                    return Ok(());
+                }
+                Ok(_) => {}
+                Err(e) => {
+                    let reply = state.error_reply(user_id, chat_id, format!("db error counting price alerts for user {}: {}", user_id, e));
+                    bot.send_message(msg.chat.id, reply).await?;
+                    return Ok(());
+                }
+            }
+
+            let repeat_secs = repeat.map(|repeat| repeat.as_secs() as i64);
+            match database.add_price_alert(user_id, chat_id, &coin, above, price, repeat_secs).await {
+                Ok(id) => {
+                    let mode = match repeat {
+                        Some(repeat) => format!(
+                            "then again each time it crosses back, at most every {}",
+                            formatting::format_duration(repeat)
+                        ),
+                        None => "once".to_string(),
+                    };
+                    let success_msg = format!(
+                        "You'll be alerted when {} goes {} {}, {}.",
+                        coin,
+                        if above { "above" } else { "below" },
+                        formatting::format_price(&price.to_string()),
+                        mode
+                    );
+                    bot.send_message(msg.chat.id, success_msg).await?;
+                    info!("user {} set price alert {} on {} ({} {})", user_id, id, coin, if above { ">" } else { "<" }, price);
+
+                    if let Err(e) = event_sender.send(CoordinatorCommand::PriceAlertChanged {
+                        coin: CoinSymbol::new(&coin)
+                    }) {
+                        error!("couldn't send price alert event for {}: {}", coin, e);
+                    }
+                }
+                Err(e) => {
+                    let reply = state.error_reply(user_id, chat_id, format!("db error setting price alert for user {}: {}", user_id, e));
+                    bot.send_message(msg.chat.id, reply).await?;
+                }
+            }
+        }
+
+        Command::Link(address_arg) => {
+            let address = address_arg.trim().to_lowercase();
+
+            if address.is_empty() {
+                match database.get_linked_accounts(user_id).await {
+                    Ok(addresses) if addresses.is_empty() => {
+                        bot.send_message(
+                            msg.chat.id,
+                            "You have no linked addresses.\n\nUse /link <address> to add one."
+                        ).await?;
+                    }
+                    Ok(addresses) => {
+                        let list_msg = format!("Your Linked Addresses:\n\n{}", addresses.join("\n"));
+                        send_chunked(&state, &bot, msg.chat.id, &list_msg, None).await?;
+                    }
+                    Err(e) => {
+                        let reply = state.error_reply(user_id, chat_id, format!("db error getting linked accounts for user {}: {}", user_id, e));
+                        bot.send_message(msg.chat.id, reply).await?;
+                    }
+                }
+                return Ok(());
+            }
+
+            if !hyperliquid::is_valid_address(&address) {
+                bot.send_message(msg.chat.id, "That doesn't look like a valid address. Example: /link 0x1234...abcd").await?;
+                return Ok(());
+            }
+
+            match database.link_account(user_id, &address).await {
+                Ok(true) => {
+                    let success_msg = format!("Linked {}.", formatting::short_address(&address));
+                    bot.send_message(msg.chat.id, success_msg).await?;
+                    info!("user {} linked {}", user_id, address);
+                }
+                Ok(false) => {
+                    bot.send_message(msg.chat.id, "That address is already linked.").await?;
+                }
+                Err(e) => {
+                    let reply = state.error_reply(user_id, chat_id, format!("db error linking account for user {}: {}", user_id, e));
+                    bot.send_message(msg.chat.id, reply).await?;
+                }
+            }
+        }
+
+        Command::Unlink(address_arg) => {
+            let address = address_arg.trim().to_lowercase();
+            if address.is_empty() {
+                bot.send_message(msg.chat.id, "Please specify an address. Example: /unlink 0x1234...abcd").await?;
+                return Ok(());
+            }
+
+            match database.unlink_account(user_id, &address).await {
+                Ok(true) => {
+                    let success_msg = format!("Unlinked {}.", formatting::short_address(&address));
+                    bot.send_message(msg.chat.id, success_msg).await?;
+                    info!("user {} unlinked {}", user_id, address);
+                }
+                Ok(false) => {
+                    bot.send_message(msg.chat.id, "That address isn't linked.").await?;
+                }
+                Err(e) => {
+                    let reply = state.error_reply(user_id, chat_id, format!("db error unlinking account for user {}: {}", user_id, e));
+                    bot.send_message(msg.chat.id, reply).await?;
+                }
+            }
+        }
+
+        Command::WeeklyDigest(args) => {
+            let usage = "Usage: /weeklydigest on [utc offset], e.g. /weeklydigest on +2 or /weeklydigest on -5:30, or /weeklydigest off";
+            let args: Vec<String> = args.split_whitespace().map(str::to_lowercase).collect();
+            let offset_mins = match args.iter().map(String::as_str).collect::<Vec<_>>().as_slice() {
+                [] => {
+                    let current_msg = match database.get_weekly_digest(user_id).await {
+                        Ok(Some(offset_mins)) => format!(
+                            "You get a weekly digest on Sunday evenings ({}).\n\nUse /weeklydigest off to stop it.",
+                            utc_offset_text(offset_mins)
+                        ),
+                        Ok(None) => format!("The weekly digest is off.\n\n{}", usage),
+                        Err(e) => state.error_reply(user_id, chat_id, format!("db error getting weekly digest for user {}: {}", user_id, e)),
+                    };
+                    bot.send_message(msg.chat.id, current_msg).await?;
+                    return Ok(());
+                }
+                ["off"] => None,
+                ["on"] => Some(0),
+                ["on", offset] => match parse_utc_offset(offset) {
+                    Some(offset_mins) => Some(offset_mins),
+                    None => {
+                        bot.send_message(msg.chat.id, usage).await?;
+                        return Ok(());
+                    }
+                },
+                _ => {
+                    bot.send_message(msg.chat.id, usage).await?;
+                    return Ok(());
+                }
+            };
+
+            match database.set_weekly_digest(user_id, chat_id, offset_mins).await {
+                Ok(()) => {
+                    let success_msg = match offset_mins {
+                        Some(offset_mins) => format!(
+                            "You'll get a recap of your coins every Sunday from 18:00 {}: whale flow, biggest trades, funding and your alerts.",
+                            utc_offset_text(offset_mins)
+                        ),
+                        None => "Weekly digest turned off.".to_string(),
+                    };
+                    bot.send_message(msg.chat.id, success_msg).await?;
+                    info!("user {} set weekly digest offset to {:?}", user_id, offset_mins);
+                }
+                Err(e) => {
+                    let reply = state.error_reply(user_id, chat_id, format!("db error setting weekly digest for user {}: {}", user_id, e));
+                    bot.send_message(msg.chat.id, reply).await?;
+                }
+            }
+        }
+
+        Command::FundingSummary(period_arg) => {
+            let period_arg = period_arg.trim().to_lowercase();
+
+            if period_arg.is_empty() {
+                let current_msg = match database.get_funding_summary(user_id).await {
+                    Ok(Some(period)) => format!("You get {} funding summaries.\n\nUse /fundingsummary off to stop them.", period),
+                    Ok(None) => "Funding summaries are off.\n\nUse /fundingsummary daily or /fundingsummary weekly.".to_string(),
+                    Err(e) => state.error_reply(user_id, chat_id, format!("db error getting funding summary for user {}: {}", user_id, e)),
+                };
+                bot.send_message(msg.chat.id, current_msg).await?;
+                return Ok(());
+            }
+
+            let period = if period_arg == "off" {
+                None
+            } else {
+                match FundingPeriod::parse(&period_arg) {
+                    Some(period) => Some(period),
+                    None => {
+                        bot.send_message(msg.chat.id, "Usage: /fundingsummary daily, weekly or off").await?;
+                        return Ok(());
+                    }
+                }
+            };
+
+            match database.set_funding_summary(user_id, chat_id, period.map(|p| p.as_str())).await {
+                Ok(()) => {
+                    let success_msg = match period {
+                        Some(period) => {
+                            let mut success_msg = format!("You'll get a {} funding summary for your linked addresses.", period.as_str());
+                            if database.get_linked_accounts(user_id).await.is_ok_and(|a| a.is_empty()) {
+                                success_msg.push_str("\n\nLink an address first with /link <address>.");
+                            }
+                            success_msg
+                        }
+                        None => "Funding summaries turned off.".to_string(),
+                    };
+                    bot.send_message(msg.chat.id, success_msg).await?;
+                    info!("user {} set funding summary to {:?}", user_id, period);
+                }
+                Err(e) => {
+                    let reply = state.error_reply(user_id, chat_id, format!("db error setting funding summary for user {}: {}", user_id, e));
+                    bot.send_message(msg.chat.id, reply).await?;
+                }
+            }
+        }
+
+        Command::Watch(address_arg) => {
+            let address = address_arg.trim().to_lowercase();
+
+            if address.is_empty() {
+                match database.get_watched_wallets(user_id).await {
+                    Ok(addresses) if addresses.is_empty() => {
+                        bot.send_message(
+                            msg.chat.id,
+                            "You aren't watching any wallets.\n\nUse /watch <address> or find one with /topwallets <coin>."
+                        ).await?;
+                    }
+                    Ok(addresses) => {
+                        let list_msg = format!("Your Watched Wallets:\n\n{}", addresses.join("\n"));
+                        send_chunked(&state, &bot, msg.chat.id, &list_msg, None).await?;
+                    }
+                    Err(e) => {
+                        let reply = state.error_reply(user_id, chat_id, format!("db error getting watched wallets for user {}: {}", user_id, e));
+                        bot.send_message(msg.chat.id, reply).await?;
+                    }
+                }
+                return Ok(());
+            }
+
+            let addresses: Vec<String> = address.split_whitespace().map(str::to_string).collect();
+            if let Some(invalid) = addresses.iter().find(|address| !hyperliquid::is_valid_address(address)) {
+                let invalid_msg = if addresses.len() == 1 {
+                    "That doesn't look like a valid address. Example: /watch 0x1234...abcd".to_string()
+                } else {
+                    format!("{} doesn't look like a valid address, nothing was added.", invalid)
+                };
+                bot.send_message(msg.chat.id, invalid_msg).await?;
+                return Ok(());
+            }
+
+            if let [address] = addresses.as_slice() {
+                let reply = watch_wallet_reply(&state, user_id, chat_id, address).await;
+                bot.send_message(msg.chat.id, reply).await?;
+                return Ok(());
+            }
+
+            match database.watch_wallets(user_id, chat_id, &addresses).await {
+                Ok(added) => {
+                    let already = addresses.len() - added.len();
+                    let mut reply = if added.is_empty() {
+                        "You're already watching all of those.".to_string()
+                    } else {
+                        let short: Vec<String> = added.iter().map(|address| formatting::short_address(address)).collect();
+                        format!("Watching {}. You'll be alerted on their large trades.", short.join(", "))
+                    };
+                    if already > 0 && !added.is_empty() {
+                        reply.push_str(&format!("\n\nAlready watching {} of them.", already));
+                    }
+                    bot.send_message(msg.chat.id, reply).await?;
+                    info!("user {} watching {} more wallets", user_id, added.len());
+                }
+                Err(e) => {
+                    let reply = state.error_reply(user_id, chat_id, format!("db error watching wallets for user {}: {}", user_id, e));
+                    bot.send_message(msg.chat.id, reply).await?;
+                }
+            }
+        }
+
+        Command::Unwatch(address_arg) => {
+            let address = address_arg.trim().to_lowercase();
+            if address.is_empty() {
+                bot.send_message(msg.chat.id, "Please specify an address. Example: /unwatch 0x1234...abcd, or /unwatch all").await?;
+                return Ok(());
+            }
+
+            let addresses: Vec<String> = address.split_whitespace().map(str::to_string).collect();
+            let scope = if address == "all" { None } else { Some(addresses.as_slice()) };
+            match database.unwatch_wallets(user_id, scope).await {
+                Ok(removed) if removed.is_empty() => {
+                    let not_watching = if addresses.len() == 1 && scope.is_some() {
+                        "You aren't watching that wallet."
+                    } else {
+                        "You aren't watching any of those wallets."
+                    };
+                    bot.send_message(msg.chat.id, not_watching).await?;
+                }
+                Ok(removed) => {
+                    let short: Vec<String> = removed.iter().map(|address| formatting::short_address(address)).collect();
+                    let success_msg = format!("Stopped watching {}.", short.join(", "));
+                    send_chunked(&state, &bot, msg.chat.id, &success_msg, None).await?;
+                    info!("user {} unwatched {} wallets", user_id, removed.len());
+                }
+                Err(e) => {
+                    let reply = state.error_reply(user_id, chat_id, format!("db error unwatching wallet for user {}: {}", user_id, e));
+                    bot.send_message(msg.chat.id, reply).await?;
+                }
+            }
+        }
+
+        Command::WalletBoard => {
+            let board = match database.wallet_pnl_board(WALLET_BOARD_DAYS).await {
+                Ok(board) => board,
+                Err(e) => {
+                    let reply = state.error_reply(user_id, chat_id, format!("db error getting wallet pnl board: {}", e));
+                    bot.send_message(msg.chat.id, reply).await?;
+                    return Ok(());
+                }
+            };
+
+            if board.is_empty() {
+                bot.send_message(
+                    msg.chat.id,
+                    "No wallet PnL yet.\n\nWatched wallets are snapshotted every few hours; the board fills in after their second snapshot.",
+                )
+                .await?;
+                return Ok(());
+            }
+
+            let now = chrono::Utc::now();
+            let line = |rank: usize, wallet: &WalletPnl| {
+                let days = (now - wallet.since).num_hours() as f64 / 24.0;
+                let window = if days < WALLET_BOARD_DAYS as f64 - 0.5 { format!(" ({:.1}d)", days) } else { String::new() };
+                format!(
+                    "\n{}. {} {}{} · realized {}, uPnL {}",
+                    rank,
+                    formatting::short_address(&wallet.address),
+                    formatting::format_signed_usd(wallet.total_usd()),
+                    window,
+                    formatting::format_signed_usd(wallet.realized_pnl_usd),
+                    formatting::format_signed_usd(wallet.unrealized_change_usd)
+                )
+            };
+
+            let best = &board[..board.len().min(WALLET_BOARD_SIZE)];
+            // the worst end only shows wallets the best end didn't
+            let worst_start = board.len().saturating_sub(WALLET_BOARD_SIZE).max(best.len());
+            let mut board_msg = format!("Wallet Leaderboard ({}d PnL)\n\nBest:", WALLET_BOARD_DAYS);
+            for (i, wallet) in best.iter().enumerate() {
+                board_msg.push_str(&line(i + 1, wallet));
+            }
+            if worst_start < board.len() {
+                board_msg.push_str("\n\nWorst:");
+                for (i, wallet) in board.iter().enumerate().skip(worst_start).rev() {
+                    board_msg.push_str(&line(i + 1, wallet));
+                }
+            }
+
+            let buttons: Vec<Vec<InlineKeyboardButton>> = best
+                .chunks(2)
+                .map(|row| {
+                    row.iter()
+                        .map(|wallet| InlineKeyboardButton::callback(
+                            format!("Watch {}", formatting::short_address(&wallet.address)),
+                            format!("{}{}", WATCH_WALLET_PREFIX, wallet.address),
+                        ))
+                        .collect()
+                })
+                .collect();
+
+            send_chunked(&state, &bot, msg.chat.id, &board_msg, Some(InlineKeyboardMarkup::new(buttons))).await?;
+        }
+
+        Command::TopWallets(coin_arg) => {
+            let coin = coin_arg.trim().to_uppercase();
+            if coin.is_empty() {
+                bot.send_message(msg.chat.id, "Please specify a coin. Example: /topwallets ETH").await?;
+                return Ok(());
+            }
+
+            let wallets = match database.top_wallets(&coin, TOP_WALLETS_HOURS, TOP_WALLETS_LIMIT).await {
+                Ok(wallets) => wallets,
+                Err(e) => {
+                    let reply = state.error_reply(user_id, chat_id, format!("db error getting top wallets for {}: {}", coin, e));
+                    bot.send_message(msg.chat.id, reply).await?;
+                    return Ok(());
+                }
+            };
+
+            if wallets.is_empty() {
+                let empty_msg = format!(
+                    "No large {} trades recorded in the last {}h.\n\nWallets show up here once someone is subscribed to {}.",
+                    coin, TOP_WALLETS_HOURS, coin
+                );
+                bot.send_message(msg.chat.id, empty_msg).await?;
+                return Ok(());
+            }
+
+            let mut top_msg = format!("Top {} Wallets ({}h, large trades)\n", coin, TOP_WALLETS_HOURS);
+            for (rank, wallet) in wallets.iter().enumerate() {
+                top_msg.push_str(&format!(
+                    "\n{}. {} · {} in {} trades",
+                    rank + 1,
+                    formatting::short_address(&wallet.address),
+                    formatting::format_usd(wallet.volume_usd, false),
+                    wallet.trades
+                ));
+            }
+
+            let buttons: Vec<Vec<InlineKeyboardButton>> = wallets
+                .chunks(2)
+                .map(|row| {
+                    row.iter()
+                        .map(|wallet| InlineKeyboardButton::callback(
+                            format!("Watch {}", formatting::short_address(&wallet.address)),
+                            format!("{}{}", WATCH_WALLET_PREFIX, wallet.address),
+                        ))
+                        .collect()
+                })
+                .collect();
+
+            send_chunked(&state, &bot, msg.chat.id, &top_msg, Some(InlineKeyboardMarkup::new(buttons))).await?;
+        }
+
+        Command::SuggestThreshold(args) => {
+            let args: Vec<String> = args.split_whitespace().map(str::to_uppercase).collect();
+            let global_min = state.config.defaults.min_trade_value_usd;
+
+            let (coin, alerts_per_day) = match args.as_slice() {
+                [coin, off] if off == "OFF" => {
+                    let reply = match database.set_subscription_threshold(user_id, coin, None).await {
+                        Ok(true) => {
+                            state.notify_coordinator(CoordinatorCommand::ThresholdChanged {
+                                telegram_user_id: user_id,
+                                coin: CoinSymbol::new(coin),
+                                min_notional_usd: None,
+                            });
+                            format!("{} alerts are back to the default {} minimum.", coin, formatting::format_usd(global_min, false))
+                        }
+                        Ok(false) => format!("You're not subscribed to {}.", coin),
+                        Err(e) => state.error_reply(user_id, chat_id, format!("db error clearing {} threshold for user {}: {}", coin, user_id, e)),
+                    };
+                    bot.send_message(msg.chat.id, reply).await?;
+                    return Ok(());
+                }
+                [coin] => (coin.clone(), DEFAULT_ALERTS_PER_DAY),
+                [coin, count] => match count.parse::<usize>() {
+                    Ok(count) if count > 0 => (coin.clone(), count),
+                    _ => {
+                        bot.send_message(msg.chat.id, "Usage: /suggestthreshold <coin> [alerts per day]").await?;
+                        return Ok(());
+                    }
+                },
+                _ => {
+                    bot.send_message(msg.chat.id, "Usage: /suggestthreshold <coin> [alerts per day]").await?;
+                    return Ok(());
+                }
+            };
+
+            let trades = match database.get_recorded_trades(std::slice::from_ref(&coin), SUGGEST_LOOKBACK_DAYS).await {
+                Ok(trades) => trades,
+                Err(e) => {
+                    let reply = state.error_reply(user_id, chat_id, format!("db error loading {} trades for /suggestthreshold: {}", coin, e));
+                    bot.send_message(msg.chat.id, reply).await?;
+                    return Ok(());
+                }
+            };
+            let current = match database.get_subscription_threshold(user_id, &coin).await {
+                Ok(Some(min)) => formatting::format_usd(min, false),
+                _ => format!("{} (default)", formatting::format_usd(global_min, false)),
+            };
+
+            let days = tuning::history_days(&trades, SUGGEST_LOOKBACK_DAYS);
+            let notionals: Vec<f64> = trades.into_iter().map(|(_, _, notional)| notional).collect();
+
+            match tuning::suggest_threshold(notionals.clone(), (alerts_per_day as f64 * days).round() as usize) {
+                Some(threshold) if threshold > global_min => {
+                    let per_day = notionals.iter().filter(|notional| **notional >= threshold).count() as f64 / days;
+                    let suggest_msg = format!(
+                        "{} Threshold Suggestion\n\nOver the last {:.0} days, {} trades of {} or more came in about {:.0} times a day.\nYour current minimum: {}",
+                        coin,
+                        days,
+                        coin,
+                        formatting::format_usd(threshold, false),
+                        per_day,
+                        current
+                    );
+                    let button = InlineKeyboardButton::callback(
+                        format!("Use {}", formatting::format_usd(threshold, false)),
+                        format!("{}{}:{}", APPLY_THRESHOLD_PREFIX, coin, threshold),
+                    );
+                    bot.send_message(msg.chat.id, suggest_msg)
+                        .reply_markup(InlineKeyboardMarkup::new(vec![vec![button]]))
+                        .await?;
+                }
+                _ => {
+                    let per_day = notionals.len() as f64 / days;
+                    let quiet_msg = format!(
+                        "{} only had about {:.0} trades a day over {} recently, so the default minimum already gets you fewer than {} alerts a day.\nYour current minimum: {}",
+                        coin,
+                        per_day,
+                        formatting::format_usd(global_min, false),
+                        alerts_per_day,
+                        current
+                    );
+                    bot.send_message(msg.chat.id, quiet_msg).await?;
+                }
+            }
+        }
+
+        Command::Autotune(args) => {
+            let alerts_per_day = match args.trim().to_lowercase().as_str() {
+                "off" => None,
+                count => match count.parse::<u32>() {
+                    Ok(per_day) if (1..=MAX_AUTOTUNE_ALERTS_PER_DAY).contains(&per_day) => Some(per_day),
+                    _ => {
+                        let usage_msg = format!("Usage: /autotune <1-{}> or /autotune off", MAX_AUTOTUNE_ALERTS_PER_DAY);
+                        bot.send_message(msg.chat.id, usage_msg).await?;
+                        return Ok(());
+                    }
+                },
+            };
+
+            match database.set_autotune(user_id, alerts_per_day).await {
+                Ok(()) => {
+                    let success_msg = match alerts_per_day {
+                        Some(per_day) => format!(
+                            "Your thresholds will be adjusted every few hours to aim for about {} alerts a day per coin. You'll get a notice whenever one changes.",
+                            per_day
+                        ),
+                        None => "Auto-tuning is off. Your thresholds stay where they are; use /suggestthreshold <coin> off to reset one.".to_string(),
+                    };
+                    bot.send_message(msg.chat.id, success_msg).await?;
+                    info!("user {} set autotune_alerts_per_day to {:?}", user_id, alerts_per_day);
+                }
+                Err(e) => {
+                    let reply = state.error_reply(user_id, chat_id, format!("db error setting autotune for user {}: {}", user_id, e));
+                    bot.send_message(msg.chat.id, reply).await?;
+                }
+            }
+        }
+
+        Command::CoinStats(coin_arg) => {
+            let coin = coin_arg.trim().to_uppercase();
+            if coin.is_empty() {
+                bot.send_message(msg.chat.id, "Please specify a coin. Example: /coinstats ETH").await?;
+                return Ok(());
+            }
+
+            let stats_msg = match database.coin_alert_stats(user_id, &coin, COIN_STATS_DAYS).await {
+                Ok(stats) if stats.alerts == 0 => {
+                    format!("You haven't had any {} alerts in the last {} days.", coin, COIN_STATS_DAYS)
+                }
+                Ok(stats) => {
+                    let mut stats_msg = format!(
+                        "{} Alerts ({} days)\n\nAlerts: {}",
+                        coin, COIN_STATS_DAYS, stats.alerts
+                    );
+                    if let Some(avg) = stats.avg_notional_usd {
+                        stats_msg.push_str(&format!("\nAverage size: {}", formatting::format_usd(avg, false)));
+                    }
+                    if stats.buys + stats.sells > 0 {
+                        let buy_share = stats.buys as f64 / (stats.buys + stats.sells) as f64 * 100.0;
+                        stats_msg.push_str(&format!(
+                            "\nBuys/Sells: {}/{} ({:.0}% buys)",
+                            stats.buys, stats.sells, buy_share
+                        ));
+                    }
+                    stats_msg
+                }
+                Err(e) => state.error_reply(user_id, chat_id, format!("db error getting {} alert stats for user {}: {}", coin, user_id, e)),
+            };
+            bot.send_message(msg.chat.id, stats_msg).await?;
+        }
+
+        Command::MyStats => {
+            let history = match database.get_subscription_history(user_id).await {
+                Ok(history) => history,
+                Err(e) => {
+                    let reply = state.error_reply(user_id, chat_id, format!("db error getting subscription history for user {}: {}", user_id, e));
+                    bot.send_message(msg.chat.id, reply).await?;
+                    return Ok(());
+                }
+            };
+
+            if history.is_empty() {
+                bot.send_message(msg.chat.id, "You haven't subscribed to anything yet.\n\nUse /subscribe <coin> to get started!").await?;
+                return Ok(());
+            }
+
+            let date = |at: chrono::DateTime<chrono::Utc>| at.format("%Y-%m-%d").to_string();
+            let threshold = |record: &SubscriptionRecord| {
+                record
+                    .min_notional_usd
+                    .map(|min| format!(", alerts from {}", formatting::format_usd(min, false)))
+                    .unwrap_or_default()
+            };
+            let (active, removed): (Vec<&SubscriptionRecord>, Vec<&SubscriptionRecord>) =
+                history.iter().partition(|record| record.removed_at.is_none());
+
+            let mut stats_msg = format!("Your Subscriptions\n\nActive ({}):", active.len());
+            if active.is_empty() {
+                stats_msg.push_str("\nnone");
+            }
+            for record in &active {
+                let since = match record.resubscribed_at {
+                    Some(resubscribed_at) => format!("back since {}, first {}", date(resubscribed_at), date(record.created_at)),
+                    None => format!("since {}", date(record.created_at)),
+                };
+                stats_msg.push_str(&format!("\n{} · {}{}", record.coin, since, threshold(record)));
+            }
+            if !removed.is_empty() {
+                stats_msg.push_str(&format!("\n\nRemoved ({}):", removed.len()));
+                for record in &removed {
+                    let removed_at = record.removed_at.map(date).unwrap_or_default();
+                    stats_msg.push_str(&format!("\n{} · removed {}{}", record.coin, removed_at, threshold(record)));
+                }
+                stats_msg.push_str("\n\nResubscribing to a removed coin brings its threshold back.");
+            }
+            send_chunked(&state, &bot, msg.chat.id, &stats_msg, None).await?;
+        }
+
+        Command::Route(target_arg) => {
+            let target = target_arg.trim();
+
+            if target.is_empty() {
+                let current_msg = match database.get_user_settings(user_id).await {
+                    Ok(settings) => match settings.route_chat_id {
+                        Some(route_chat_id) => format!("Your alerts go to chat {}.\n\nUse /route off to get them here again.", route_chat_id),
+                        None => "Your alerts come to the chat you subscribed from.\n\nUse /route <chat_id|@channel> to send them elsewhere.".to_string(),
+                    },
+                    Err(e) => state.error_reply(user_id, chat_id, format!("db error getting settings for user {}: {}", user_id, e)),
+                };
+                bot.send_message(msg.chat.id, current_msg).await?;
+                return Ok(());
+            }
+
+            let route_chat_id = if target.eq_ignore_ascii_case("off") {
+                None
+            } else {
+                match verify_route(&state, target, user_id, chat_id).await {
+                    Ok(route_chat_id) => Some(route_chat_id),
+                    Err(reason) => {
+                        bot.send_message(msg.chat.id, reason).await?;
+                        return Ok(());
+                    }
+                }
+            };
+
+            match database.set_route_chat_id(user_id, route_chat_id).await {
+                Ok(()) => {
+                    let success_msg = match route_chat_id {
+                        Some(_) => format!("Your alerts will now be sent to {}.", target),
+                        None => "Your alerts will come to the chat you subscribed from again.".to_string(),
+                    };
+                    bot.send_message(msg.chat.id, success_msg).await?;
+                    info!("user {} routed alerts to {:?}", user_id, route_chat_id);
+                }
+                Err(e) => {
+                    let reply = state.error_reply(user_id, chat_id, format!("db error setting route for user {}: {}", user_id, e));
+                    bot.send_message(msg.chat.id, reply).await?;
+                }
+            }
+        }
+
+        Command::Destination(args) => {
+            let args: Vec<&str> = args.split_whitespace().collect();
+
+            match args.split_first() {
+                None => {
+                    let list_msg = match database.get_user_destinations(user_id).await {
+                        Ok(destinations) if destinations.is_empty() => {
+                            "You have no extra destinations.\n\nUse /destination add <chat_id|@channel|https://url> [coin] [compact|full] [charts|nocharts]".to_string()
+                        }
+                        Ok(destinations) => {
+                            let mut list_msg = "Your Destinations\n".to_string();
+                            for destination in destinations {
+                                let target = match (destination.chat_id, &destination.webhook_url) {
+                                    (Some(chat_id), _) => format!("chat {}", chat_id),
+                                    (None, Some(url)) => url.clone(),
+                                    (None, None) => continue,
+                                };
+                                list_msg.push_str(&format!(
+                                    "\n#{} {} ({})",
+                                    destination.id,
+                                    target,
+                                    destination.coin.as_deref().unwrap_or("all coins")
+                                ));
+                            }
+                            list_msg.push_str("\n\nUse /destination remove <id> to stop sending to one.");
+                            list_msg
+                        }
+                        Err(e) => state.error_reply(user_id, chat_id, format!("db error getting destinations for user {}: {}", user_id, e)),
+                    };
+                    send_chunked(&state, &bot, msg.chat.id, &list_msg, None).await?;
+                }
+
+                Some((&"add", rest)) => {
+                    let spec = match parse_destination(&state, rest, user_id, chat_id).await {
+                        Ok(spec) => spec,
+                        Err(reason) => {
+                            bot.send_message(msg.chat.id, reason).await?;
+                            return Ok(());
+                        }
+                    };
+
+                    let result = database
+                        .add_destination(
+                            user_id,
+                            spec.chat_id,
+                            spec.webhook_url.as_deref(),
+                            spec.coin.as_deref(),
+                            spec.full_precision,
+                            spec.charts_enabled,
+                        )
+                        .await;
+                    match result {
+                        Ok(id) => {
+                            bot.send_message(
+                                msg.chat.id,
+                                format!("Added destination #{}. Your alerts will also be sent there.", id),
+                            )
+                            .await?;
+                            info!("user {} added destination {}", user_id, id);
+                        }
+                        Err(e) => {
+                            let reply = state.error_reply(user_id, chat_id, format!("db error adding destination for user {}: {}", user_id, e));
+                            bot.send_message(msg.chat.id, reply).await?;
+                        }
+                    }
+                }
+
+                Some((&"remove", [id])) => {
+                    let Ok(id) = id.trim_start_matches('#').parse::<i64>() else {
+                        bot.send_message(msg.chat.id, "Usage: /destination remove <id>").await?;
+                        return Ok(());
+                    };
+
+                    match database.remove_destination(user_id, id).await {
+                        Ok(true) => {
+                            bot.send_message(msg.chat.id, format!("Removed destination #{}.", id)).await?;
+                            info!("user {} removed destination {}", user_id, id);
+                        }
+                        Ok(false) => {
+                            bot.send_message(msg.chat.id, format!("You have no destination #{}.", id)).await?;
+                        }
+                        Err(e) => {
+                            let reply = state.error_reply(user_id, chat_id, format!("db error removing destination for user {}: {}", user_id, e));
+                            bot.send_message(msg.chat.id, reply).await?;
+                        }
+                    }
+                }
+
+                Some(_) => {
+                    bot.send_message(
+                        msg.chat.id,
+                        "Usage: /destination, /destination add <chat_id|@channel|https://url> [coin] [compact|full] [charts|nocharts], /destination remove <id>",
+                    )
+                    .await?;
+                }
+            }
+        }
+
+        Command::Export => {
+            let backup = match UserBackup::collect(database, user_id).await {
+                Ok(backup) => backup,
+                Err(e) => {
+                    let reply = state.error_reply(user_id, chat_id, format!("db error exporting settings for user {}: {}", user_id, e));
+                    bot.send_message(msg.chat.id, reply).await?;
+                    return Ok(());
+                }
+            };
+            let contents = match serde_json::to_vec_pretty(&backup) {
+                Ok(contents) => contents,
+                Err(e) => {
+                    let reply = state.error_reply(user_id, chat_id, format!("couldn't serialize export for user {}: {}", user_id, e));
+                    bot.send_message(msg.chat.id, reply).await?;
+                    return Ok(());
+                }
+            };
+
+            bot.send_document(msg.chat.id, InputFile::memory(contents).file_name(EXPORT_FILE_NAME))
+                .caption("Your subscriptions and settings. Send this file with /import as its caption to restore them.")
+                .await?;
+            info!("user {} exported settings", user_id);
+        }
+
+        Command::Import => {
+            // replying /import to a previously sent file works too
+            let reply = match msg.reply_to_message().and_then(|reply| reply.document()) {
+                Some(document) => import_document(&bot, &state, document, user_id, chat_id).await,
+                None => "Send your /export file with /import as its caption, or reply to it with /import.\n\nImporting adds to what you already have.".to_string(),
+            };
+            send_chunked(&state, &bot, msg.chat.id, &reply, None).await?;
+        }
+
+        Command::DeleteData(confirm_arg) => {
+            if !confirm_arg.trim().eq_ignore_ascii_case("confirm") {
+                bot.send_message(
+                    msg.chat.id,
+                    "This erases your subscriptions, settings, alerts, linked and watched wallets, destinations \
+                     and alert history. It can't be undone, so grab an /export first if you might want them back.\n\n\
+                     Send /deletedata confirm to go ahead.",
+                )
+                .await?;
+                return Ok(());
+            }
+
+            match database.delete_user_data(user_id).await {
+                Ok(deleted) => {
+                    state.mutes.forget(user_id, None);
+                    state.notify_coordinator(CoordinatorCommand::UserPaused { telegram_user_id: user_id, paused: true });
+                    bot.send_message(msg.chat.id, "Done, everything the bot had stored for you has been erased.").await?;
+                    info!("user {} deleted their data ({} rows)", user_id, deleted);
+                }
+                Err(e) => {
+                    let reply = state.error_reply_as(
+                        user_id,
+                        chat_id,
+                        "Sorry, there was an error. Nothing was deleted, please try again.",
+                        format!("db error deleting data for user {}: {}", user_id, e),
+                    );
+                    bot.send_message(msg.chat.id, reply).await?;
+                }
+            }
+        }
+
+        Command::Broadcast(text) => {
+            let text = text.trim().to_string();
+            if text.is_empty() {
+                bot.send_message(msg.chat.id, "Usage: /broadcast <message>").await?;
+                return Ok(());
+            }
+
+            let chats = match database.get_broadcast_chats().await {
+                Ok(chats) => chats,
+                Err(e) => {
+                    let reply = state.error_reply(user_id, chat_id, format!("db error getting broadcast chats: {}", e));
+                    bot.send_message(msg.chat.id, reply).await?;
+                    return Ok(());
+                }
+            };
+
+            bot.send_message(msg.chat.id, format!("Broadcasting to {} chats...", chats.len())).await?;
+            info!("user {} broadcasting to {} chats", user_id, chats.len());
+
+            // goes out at digest priority so it doesn't hold up live alerts
+            let state = state.clone();
+            tokio::spawn(async move {
+                let mut failed = 0;
+                for target in &chats {
+                    if let Err(e) = state.send_text(*target, &text, Priority::Digest).await {
+                        warn!("couldn't deliver broadcast to chat {}: {}", target, e);
+                        failed += 1;
+                    }
+                }
+                let summary = format!("Broadcast sent to {} of {} chats.", chats.len() - failed, chats.len());
+                if let Err(e) = state.send_text(chat_id, &summary, Priority::Reply).await {
+                    warn!("couldn't send broadcast summary to chat {}: {}", chat_id, e);
+                }
+            });
+        }
+
+        Command::Ban(target_arg) => {
+            let Ok(target) = target_arg.trim().parse::<i64>() else {
+                bot.send_message(msg.chat.id, "Usage: /ban <user_id>").await?;
+                return Ok(());
+            };
+            if state.roles.role(target).is_some() {
+                bot.send_message(msg.chat.id, "Users with a role can't be banned. Remove their role first.").await?;
+                return Ok(());
+            }
+
+            match state.roles.ban(target, user_id).await {
+                Ok(true) => {
+                    state.notify_coordinator(CoordinatorCommand::UserPaused { telegram_user_id: target, paused: true });
+                    bot.send_message(msg.chat.id, format!("Banned {}. Their commands are ignored and trade alerts stop.", target)).await?;
+                    info!("user {} banned {}", user_id, target);
+                }
+                Ok(false) => {
+                    bot.send_message(msg.chat.id, format!("{} is already banned.", target)).await?;
+                }
+                Err(e) => {
+                    let reply = state.error_reply(user_id, chat_id, format!("db error banning user {}: {}", target, e));
+                    bot.send_message(msg.chat.id, reply).await?;
+                }
+            }
+        }
+
+        Command::Unban(target_arg) => {
+            let Ok(target) = target_arg.trim().parse::<i64>() else {
+                bot.send_message(msg.chat.id, "Usage: /unban <user_id>").await?;
+                return Ok(());
+            };
+
+            match state.roles.unban(target).await {
+                Ok(true) => {
+                    state.notify_coordinator(CoordinatorCommand::UserPaused { telegram_user_id: target, paused: false });
+                    bot.send_message(msg.chat.id, format!("Unbanned {}.", target)).await?;
+                    info!("user {} unbanned {}", user_id, target);
+                }
+                Ok(false) => {
+                    bot.send_message(msg.chat.id, format!("{} isn't banned.", target)).await?;
+                }
+                Err(e) => {
+                    let reply = state.error_reply(user_id, chat_id, format!("db error unbanning user {}: {}", target, e));
+                    bot.send_message(msg.chat.id, reply).await?;
+                }
+            }
+        }
+
+        Command::Role(args) => {
+            let args: Vec<&str> = args.split_whitespace().collect();
+
+            match args.as_slice() {
+                [] => {
+                    let lines: Vec<String> = state
+                        .roles
+                        .members()
+                        .iter()
+                        .map(|(member, role)| format!("{}: {}", member, role.as_str()))
+                        .collect();
+                    let list_msg = format!(
+                        "Roles\n\n{}\n\nUse /role <user_id> <owner|admin|moderator|off> to change one.",
+                        lines.join("\n")
+                    );
+                    send_chunked(&state, &bot, msg.chat.id, &list_msg, None).await?;
+                }
+
+                [target, role] => {
+                    let Ok(target) = target.parse::<i64>() else {
+                        bot.send_message(msg.chat.id, "Usage: /role <user_id> <owner|admin|moderator|off>").await?;
+                        return Ok(());
+                    };
+                    let role = match (*role, Role::parse(role)) {
+                        ("off", _) => None,
+                        (_, Some(role)) => Some(role),
+                        (_, None) => {
+                            bot.send_message(msg.chat.id, "Role must be owner, admin, moderator or off.").await?;
+                            return Ok(());
+                        }
+                    };
+                    if state.roles.is_config_owner(target) {
+                        bot.send_message(msg.chat.id, "That user is an owner through the bot's config, which can't be changed here.").await?;
+                        return Ok(());
+                    }
+
+                    match state.roles.set_role(target, role, user_id).await {
+                        Ok(()) => {
+                            let success_msg = match role {
+                                Some(role) => format!("{} is now {}.", target, role.as_str()),
+                                None => format!("Removed {}'s role.", target),
+                            };
+                            bot.send_message(msg.chat.id, success_msg).await?;
+                            info!("user {} set role of {} to {:?}", user_id, target, role);
+                        }
+                        Err(e) => {
+                            let reply = state.error_reply(user_id, chat_id, format!("db error setting role for user {}: {}", target, e));
+                            bot.send_message(msg.chat.id, reply).await?;
+                        }
+                    }
+                }
+
+                _ => {
+                    bot.send_message(msg.chat.id, "Usage: /role or /role <user_id> <owner|admin|moderator|off>").await?;
+                }
+            }
+        }
+
+        Command::Feeds => {
+            let statuses = state.ws_manager.feed_statuses().await;
+            if statuses.is_empty() {
+                bot.send_message(msg.chat.id, "No active feeds.").await?;
+                return Ok(());
+            }
+
+            let mut feeds_msg = format!(
+                "Active Feeds ({} on {} connections)\n",
+                statuses.len(),
+                state.ws_manager.connection_count().await
+            );
+            for status in &statuses {
+                let last_message = status
+                    .last_message_age
+                    .map(|age| format!("{} ago", formatting::format_duration(age)))
+                    .unwrap_or_else(|| "never".to_string());
+                let baseline_gap = status
+                    .baseline_gap
+                    .map(|gap| format!("{:.1}s", gap.as_secs_f64()))
+                    .unwrap_or_else(|| "-".to_string());
+                feeds_msg.push_str(&format!(
+                    "\n{} {:?} [conn {}]{}\nup {} · last msg {} · {} msgs · {:.2} msg/s · {} reconnects\ngap avg {} · max {:.1}s\n",
+                    status.coin.as_deref().unwrap_or("all coins"),
+                    status.kind,
+                    status.connection_id,
+                    if feed_health::is_quiet(status, &state.config.feed_health) { " QUIET" } else { "" },
+                    formatting::format_duration(status.uptime),
+                    last_message,
+                    status.messages,
+                    status.messages_per_sec,
+                    status.reconnects,
+                    baseline_gap,
+                    status.max_gap.as_secs_f64()
+                ));
+            }
+
+            let buttons: Vec<Vec<InlineKeyboardButton>> = statuses
+                .chunks(3)
+                .map(|row| {
+                    row.iter()
+                        .map(|status| InlineKeyboardButton::callback(
+                            format!("Restart {}", status.feed_key),
+                            format!("{}{}", FEED_RESTART_PREFIX, status.feed_key),
+                        ))
+                        .collect()
+                })
+                .collect();
+
+            send_chunked(&state, &bot, msg.chat.id, &feeds_msg, Some(InlineKeyboardMarkup::new(buttons))).await?;
+        }
+
+        Command::Resync => {
+            match event_sender.send(CoordinatorCommand::ResyncRequested { reply_chat_id: chat_id }) {
+                Ok(()) => {
+                    info!("admin {} requested a feed resync", user_id);
+                    bot.send_message(msg.chat.id, "Resyncing all feeds...").await?;
+                }
+                Err(e) => {
+                    error!("couldn't send resync event: {}", e);
+                    bot.send_message(msg.chat.id, "Sorry, the coordinator isn't running.").await?;
+                }
+            }
+        }
+
+        Command::Error(id) => {
+            let id = id.trim();
+            let error_msg = if id.is_empty() {
+                "Usage: /error <id>".to_string()
+            } else {
+                match state.errors.get(id) {
+                    Some(report) => format!(
+                        "Error {}\n\nAt: {}\nUser: {}\nChat: {}\n\n{}",
+                        report.id,
+                        report.at.format("%Y-%m-%d %H:%M:%S UTC"),
+                        report.user_id,
+                        report.chat_id,
+                        report.context
+                    ),
+                    None => format!("No error {} among the recent ones. Search the logs for it.", id),
+                }
+            };
+            send_chunked(&state, &bot, msg.chat.id, &error_msg, None).await?;
+        }
+
+        Command::Experiment => {
+            let experiment = &state.config.experiment;
+            let reply = if experiment.name.is_empty() {
+                "No alert experiment is running. Set experiment.name and a variant template in the config to start one.".to_string()
+            } else {
+                match database.experiment_results(&experiment.name).await {
+                    Ok(results) => experiments::report(experiment, &results),
+                    Err(e) => state.error_reply(user_id, chat_id, format!("db error getting results for experiment {}: {}", experiment.name, e)),
+                }
+            };
+            bot.send_message(msg.chat.id, reply).await?;
+        }
+
+        Command::DeadLetters(args) => {
+            let usage = "Usage: /deadletters, /deadletters retry <id|all> or /deadletters purge <id|all>";
+            let args = args.trim().to_lowercase();
+            let (action, which) = match args.split_whitespace().collect::<Vec<_>>().as_slice() {
+                [] => ("list", None),
+                [action, "all"] => (*action, None),
+                [action, id] => match id.parse::<i64>() {
+                    Ok(id) => (*action, Some(id)),
+                    Err(_) => {
+                        bot.send_message(msg.chat.id, usage).await?;
+                        return Ok(());
+                    }
+                },
+                _ => {
+                    bot.send_message(msg.chat.id, usage).await?;
+                    return Ok(());
+                }
+            };
+
+            match action {
+                "list" => match database.get_dead_letters(DEAD_LETTERS_SHOWN).await {
+                    Ok((letters, _)) if letters.is_empty() => {
+                        bot.send_message(msg.chat.id, "The dead letter queue is empty.").await?;
+                    }
+                    Ok((letters, total)) => {
+                        let mut letters_msg = format!("Dead letters ({}, newest first)\n", total);
+                        for letter in &letters {
+                            let target = delivery::dead_letter_target(letter)
+                                .map(|target| target.to_string())
+                                .unwrap_or_else(|| "nowhere".to_string());
+                            letters_msg.push_str(&format!(
+                                "\n#{} {} {} {} for user {} via {}\n{} · {} attempts\n{}\n",
+                                letter.id,
+                                letter.coin,
+                                letter.side,
+                                formatting::format_usd(letter.notional_usd, false),
+                                letter.telegram_user_id,
+                                target,
+                                letter.created_at.format("%Y-%m-%d %H:%M UTC"),
+                                letter.attempts,
+                                redact::redact(&letter.error)
+                            ));
+                        }
+                        letters_msg.push_str(&format!("\n{}", usage));
+                        send_chunked(&state, &bot, msg.chat.id, &letters_msg, None).await?;
+                    }
+                    Err(e) => {
+                        let reply = state.error_reply(user_id, chat_id, format!("db error getting dead letters: {}", e));
+                        bot.send_message(msg.chat.id, reply).await?;
+                    }
+                },
+                "retry" => match event_sender.send(CoordinatorCommand::DeadLettersReprocess { id: which, reply_chat_id: chat_id }) {
+                    Ok(()) => {
+                        info!("admin {} requested dead letter reprocess of {:?}", user_id, which);
+                        bot.send_message(msg.chat.id, "Reprocessing dead letters...").await?;
+                    }
+                    Err(e) => {
+                        error!("couldn't send dead letter reprocess event: {}", e);
+                        bot.send_message(msg.chat.id, "Sorry, the coordinator isn't running.").await?;
+                    }
+                },
+                "purge" => match database.purge_dead_letters(which).await {
+                    Ok(purged) => {
+                        info!("admin {} purged {} dead letters", user_id, purged);
+                        bot.send_message(msg.chat.id, format!("Purged {} dead letters.", purged)).await?;
+                    }
+                    Err(e) => {
+                        let reply = state.error_reply(user_id, chat_id, format!("db error purging dead letters: {}", e));
+                        bot.send_message(msg.chat.id, reply).await?;
+                    }
+                },
+                _ => {
+                    bot.send_message(msg.chat.id, usage).await?;
+                }
+            }
+        }
+
+        Command::Stats => {
+            let snapshot = state.metrics.snapshot();
+            let latency_text = match snapshot.latency {
+                Some(latency) => format!(
+                    "p50 {}ms · p95 {}ms · p99 {}ms · max {}ms ({} samples, slo {}ms)",
+                    latency.p50_ms,
+                    latency.p95_ms,
+                    latency.p99_ms,
+                    latency.max_ms,
+                    latency.samples,
+                    state.config.metrics.latency_slo_ms
+                ),
+                None => "no samples yet".to_string(),
+            };
+
+            let churn_text = match database.subscription_churn(CHURN_DAYS).await {
+                Ok(churn) => format!(
+                    "{} active ({} users) · {}d: +{} new, +{} back, -{} removed",
+                    churn.active, churn.subscribers, CHURN_DAYS, churn.added, churn.resubscribed, churn.removed
+                ),
+                Err(e) => {
+                    error!("db error getting subscription churn: {}", e);
+                    "unavailable".to_string()
+                }
+            };
+
+            // the in-memory counters reset on restart; the persisted ones don't
+            let mut trend_text = String::new();
+            match database.metrics_by_day(STATS_TREND_DAYS).await {
+                Ok(days) if !days.is_empty() => {
+                    trend_text.push_str(&format!("\n\nLast {} days:", STATS_TREND_DAYS));
+                    for day in days {
+                        trend_text.push_str(&format!(
+                            "\n{}: {} trades · {} large · {} alerts · {} failed",
+                            day.day.format("%m-%d"),
+                            formatting::with_thousands(day.trades_seen as f64, 0),
+                            formatting::with_thousands(day.large_trades as f64, 0),
+                            formatting::with_thousands(day.alerts_sent as f64, 0),
+                            day.alerts_failed
+                        ));
+                    }
+                }
+                Ok(_) => {}
+                Err(e) => error!("db error getting metrics history: {}", e),
+            }
+            match database.busiest_coins(STATS_TREND_DAYS, STATS_BUSIEST_COINS).await {
+                Ok(coins) if !coins.is_empty() => {
+                    let coins: Vec<String> = coins
+                        .iter()
+                        .map(|(coin, trades, volume_usd)| {
+                            format!("{} {} ({} trades)", coin, formatting::format_usd(*volume_usd, false), trades)
+                        })
+                        .collect();
+                    trend_text.push_str(&format!("\nBusiest: {}", coins.join(", ")));
+                }
+                Ok(_) => {}
+                Err(e) => error!("db error getting busiest coins: {}", e),
+            }
+
+            let mut stats_msg = format!(
+                "Bot Stats\n\nUptime: {}\nActive feeds: {}\nTrades seen: {}\nLarge trades: {}\nAlerts sent: {}\nAlerts retried: {}\nAlerts failed: {}\nFeeds gone quiet: {}\nAlert latency: {}\nSubscriptions: {}",
+                formatting::format_duration(state.started_at.elapsed()),
+                state.ws_manager.active_feed_count().await,
+                snapshot.trades_seen,
+                snapshot.large_trades,
+                snapshot.alerts_sent,
+                snapshot.alerts_retried,
+                snapshot.alerts_failed,
+                snapshot.feeds_gone_quiet,
+                latency_text,
+                churn_text
+            );
+            if let Some(missing) = state.degraded.get() {
+                stats_msg.push_str(&format!("\nDegraded: started without {}", missing));
+            }
+            stats_msg.push_str(&trend_text);
+            send_chunked(&state, &bot, msg.chat.id, &stats_msg, None).await?;
+        }
+    }
+
+    Ok(())
+}
+
+async fn handle_callback(bot: Bot, query: CallbackQuery, state: TelegramBot) -> ResponseResult<()> {
+    let user_id = query.from.id.0 as i64;
+    let chat_id = query.message.as_ref().map(|message| message.chat.id.0).unwrap_or(user_id);
+    let Some(data) = query.data.as_deref() else {
+        bot.answer_callback_query(query.id).await?;
+        return Ok(());
+    };
+
+    info!("Received callback from user {}: {}", user_id, data);
+
+    if state.roles.is_banned(user_id) {
+        bot.answer_callback_query(query.id).await?;
+        return Ok(());
+    }
+
+    // a tap on an alert's button is what alert experiments count as
+    // engagement, so other buttons don't count, and nothing does between experiments
+    let alert_tap = data.starts_with(ALERT_ACTION_PREFIX) && !state.config.experiment.name.is_empty();
+    if let Some(message) = query.message.as_ref().filter(|_| alert_tap) {
+        if let Err(e) = state.database.record_alert_tap(chat_id, message.id.0).await {
+            warn!("couldn't record a tap on message {} in chat {}: {}", message.id, chat_id, e);
+        }
+    }
+
+    if let Some(feed_key) = data.strip_prefix(FEED_RESTART_PREFIX) {
+        if !state.roles.allows(user_id, Permission::FeedControl) {
+            bot.answer_callback_query(query.id).text("You don't have permission to do that.").await?;
+            return Ok(());
+        }
+
+        info!("admin {} requested a restart of feed {}", user_id, feed_key);
+        state.notify_coordinator(CoordinatorCommand::FeedRestartRequested { feed_key: feed_key.to_string() });
+        bot.answer_callback_query(query.id).text(format!("Restarting {}", feed_key)).await?;
+        return Ok(());
+    }
+
+    if let Some((coin, threshold)) = data.strip_prefix(APPLY_THRESHOLD_PREFIX).and_then(|rest| rest.split_once(':')) {
+        // a forged callback could carry nan, inf or a negative amount
+        let Some(threshold) = threshold.parse::<f64>().ok().filter(|threshold| threshold.is_finite() && *threshold > 0.0) else {
+            bot.answer_callback_query(query.id).await?;
+            return Ok(());
+        };
+
+        let reply = match state.database.set_subscription_threshold(user_id, coin, Some(threshold)).await {
+            Ok(true) => {
+                info!("user {} set {} threshold to {}", user_id, coin, threshold);
+                state.notify_coordinator(CoordinatorCommand::ThresholdChanged {
+                    telegram_user_id: user_id,
+                    coin: CoinSymbol::new(coin),
+                    min_notional_usd: Some(threshold),
+                });
+                format!("{} alerts now start at {}", coin, formatting::format_usd(threshold, false))
+            }
+            Ok(false) => format!("Subscribe to {} first", coin),
+            Err(e) => state.error_reply(user_id, chat_id, format!("db error setting {} threshold for user {}: {}", coin, user_id, e)),
+        };
+        bot.answer_callback_query(query.id).text(reply).await?;
+        return Ok(());
+    }
+
+    if let Some((action, coin)) = data.strip_prefix(ALERT_ACTION_PREFIX).and_then(|rest| rest.split_once(':')) {
+        let reply = alert_action_reply(&bot, &state, user_id, chat_id, action, &CoinSymbol::new(coin)).await;
+        bot.answer_callback_query(query.id).text(reply).await?;
+        return Ok(());
+    }
+
+    if let Some(address) = data.strip_prefix(WATCH_WALLET_PREFIX) {
+        if !hyperliquid::is_valid_address(address) {
+            bot.answer_callback_query(query.id).await?;
+            return Ok(());
+        }
+
+        let reply = watch_wallet_reply(&state, user_id, chat_id, address).await;
+        bot.answer_callback_query(query.id).text(reply).await?;
+        return Ok(());
+    }
+
+    bot.answer_callback_query(query.id).await?;
+    Ok(())
+}
+
+async fn handle_import_upload(bot: Bot, msg: Message, state: TelegramBot) -> ResponseResult<()> {
+    let chat_id = msg.chat.id.0;
+    let user_id = msg.from().map(|user| user.id.0 as i64).unwrap_or(chat_id);
+    let Some(document) = msg.document() else {
+        return Ok(());
+    };
+
+    info!("Received import file from user {}", user_id);
+    if let Err(e) = state.outbound.acquire(chat_id, Priority::Reply).await {
+        warn!("dropping import from chat {}: {}", chat_id, e);
+        return Ok(());
+    }
+
+    let reply = import_document(&bot, &state, document, user_id, chat_id).await;
+    send_chunked(&state, &bot, msg.chat.id, &reply, None).await?;
+    Ok(())
+}
+
+async fn import_document(bot: &Bot, state: &TelegramBot, document: &Document, user_id: i64, chat_id: i64) -> String {
+    if document.file.size > MAX_IMPORT_BYTES {
+        return "That file is too big to be an export.".to_string();
+    }
+
+    let mut contents = Vec::new();
+    let downloaded = match bot.get_file(document.file.id.clone()).await {
+        Ok(file) => bot.download_file(&file.path, &mut contents).await.map_err(anyhow::Error::from),
+        Err(e) => Err(e.into()),
+    };
+    if let Err(e) = downloaded {
+        let reply = state.error_reply(user_id, chat_id, format!("couldn't download import file for user {}: {}", user_id, e));
+        return reply.to_string();
+    }
+
+    let backup: UserBackup = match serde_json::from_slice(&contents) {
+        Ok(backup) => backup,
+        Err(_) => return "That doesn't look like an /export file.".to_string(),
+    };
+    if backup.version > BACKUP_VERSION {
+        return "That export is from a newer version of the bot. Update this deployment first.".to_string();
+    }
+
+    match restore_backup(state, user_id, chat_id, backup).await {
+        Ok(reply) => {
+            info!("user {} imported settings", user_id);
+            reply
+        }
+        Err(e) => state.error_reply(user_id, chat_id, format!("error importing settings for user {}: {}", user_id, e)),
+    }
+}
+
+// applies an export on top of the user's current setup, with the same checks
+// the individual commands make; anything that fails them is listed, not fatal
+async fn restore_backup(state: &TelegramBot, user_id: i64, chat_id: i64, backup: UserBackup) -> Result<String> {
+    let database = &state.database;
+    let hyperliquid_client = state.hyperliquid_client.clone();
+    // settings, priorities and destinations all come back
+    let mut events = vec![CoordinatorCommand::SettingsInvalidated { telegram_user_id: user_id }];
+    let mut skipped = Vec::new();
+
+    // everything is checked first, and the writes then go in one transaction,
+    // so a failure part way leaves the account as it was
+    let mut coins = Vec::new();
+    for coin in &backup.subscriptions {
+        let coin = coin.to_uppercase();
+        if !hyperliquid_client.coin_exists(&coin).await? {
+            skipped.push(format!("{} subscription: not on Hyperliquid", coin));
+            continue;
+        }
+        events.push(CoordinatorCommand::UserSubscribed { coin: CoinSymbol::new(&coin) });
+        coins.push(coin);
+    }
+    let priority_coins: Vec<String> = backup.priority_coins.iter().map(|coin| coin.to_uppercase()).collect();
+    // a mute that ran out since the export has nothing left to do
+    let now = chrono::Utc::now();
+    let muted_subscriptions: Vec<(String, chrono::DateTime<chrono::Utc>)> = backup
+        .muted_subscriptions
+        .iter()
+        .filter(|mute| mute.until > now)
+        .map(|mute| (mute.coin.to_uppercase(), mute.until))
+        .collect();
+    let mut subscription_thresholds = Vec::new();
+    for threshold in &backup.subscription_thresholds {
+        let coin = threshold.coin.to_uppercase();
+        if !(threshold.min_notional_usd.is_finite() && threshold.min_notional_usd > 0.0) {
+            skipped.push(format!("{} threshold {}: not a positive amount", coin, threshold.min_notional_usd));
+            continue;
+        }
+        events.push(CoordinatorCommand::ThresholdChanged {
+            telegram_user_id: user_id,
+            coin: CoinSymbol::new(&coin),
+            min_notional_usd: Some(threshold.min_notional_usd),
+        });
+        subscription_thresholds.push((coin, threshold.min_notional_usd));
+    }
+
+    let settings = backup.settings;
+    let currency = match settings.currency.as_deref() {
+        Some(currency) if !state.fx_rates.is_supported(currency).await => {
+            skipped.push(format!("currency {}: not supported", currency));
+            None
+        }
+        currency => Some(currency.map(str::to_string)),
+    };
+    let autotune = match settings.autotune_alerts_per_day {
+        Some(per_day) if !(1..=MAX_AUTOTUNE_ALERTS_PER_DAY).contains(&per_day) => {
+            skipped.push(format!("autotune {}: out of range", per_day));
+            None
+        }
+        per_day => Some(per_day),
+    };
+    let silent_below = match settings.silent_below_usd {
+        Some(below) if !(below.is_finite() && below > 0.0) => {
+            skipped.push(format!("silent below {}: not a positive amount", below));
+            None
+        }
+        below => Some(below),
+    };
+    let daily_alert_cap = match settings.daily_alert_cap {
+        Some(cap) if !(1..=MAX_DAILY_ALERT_CAP).contains(&cap) => {
+            skipped.push(format!("daily cap {}: out of range", cap));
+            None
+        }
+        cap => Some(cap),
+    };
+    let digest_mins = match settings.digest_mins {
+        Some(mins) if !(MIN_DIGEST_MINS..=MAX_DIGEST_MINS).contains(&mins) => {
+            skipped.push(format!("digest mode {}m: out of range", mins));
+            None
+        }
+        mins => Some(mins),
+    };
+    let duplicate_alerts = settings.duplicate_alerts.as_deref().and_then(DuplicatePreference::parse);
+    let route_chat_id = match settings.route_chat_id {
+        Some(route_chat_id) => match verify_route(state, &route_chat_id.to_string(), user_id, chat_id).await {
+            Ok(route_chat_id) => Some(route_chat_id),
+            Err(reason) => {
+                skipped.push(format!("route to chat {}: {}", route_chat_id, reason));
+                None
+            }
+        },
+        None => None,
+    };
+
+    let mut imbalance_alerts = Vec::new();
+    for alert in &backup.imbalance_alerts {
+        let coin = alert.coin.to_uppercase();
+        let valid = alert.threshold_pct > 0.0 && alert.threshold_pct < 100.0 && hyperliquid_client.coin_exists(&coin).await?;
+        if !valid {
+            skipped.push(format!("{} imbalance alert", coin));
+            continue;
+        }
+        events.push(CoordinatorCommand::ImbalanceAlertChanged { coin: CoinSymbol::new(&coin) });
+        imbalance_alerts.push((coin, alert.threshold_pct));
+    }
+    let mut vwap_alerts = Vec::new();
+    for alert in &backup.vwap_alerts {
+        let coin = alert.coin.to_uppercase();
+        let valid = alert.threshold_pct > 0.0 && alert.threshold_pct < 100.0 && hyperliquid_client.coin_exists(&coin).await?;
+        if !valid {
+            skipped.push(format!("{} vwap alert", coin));
+            continue;
+        }
+        events.push(CoordinatorCommand::VwapAlertChanged { coin: CoinSymbol::new(&coin) });
+        vwap_alerts.push((coin, alert.threshold_pct));
+    }
+    let mut volatility_alerts = Vec::new();
+    for alert in &backup.volatility_alerts {
+        let coin = alert.coin.to_uppercase();
+        let valid = alert.multiple > 1.0 && alert.multiple <= 100.0 && hyperliquid_client.coin_exists(&coin).await?;
+        if !valid {
+            skipped.push(format!("{} volatility alert", coin));
+            continue;
+        }
+        events.push(CoordinatorCommand::VolatilityAlertChanged { coin: CoinSymbol::new(&coin) });
+        volatility_alerts.push((coin, alert.multiple));
+    }
+
+    let existing = database.get_user_price_alerts(user_id).await?;
+    let max_price_alerts = state.config.price_alerts.max_per_user;
+    let mut price_alerts = Vec::new();
+    for alert in &backup.price_alerts {
+        let coin = alert.coin.to_uppercase();
+        let known = existing.iter().any(|known| {
+            known.coin.as_str() == coin && known.above == alert.above && known.price == alert.price && known.repeat_secs == alert.repeat_secs
+        });
+        if known {
+            continue;
+        }
+        if existing.len() + price_alerts.len() >= max_price_alerts {
+            skipped.push(format!("{} price alert: over the limit of {}", coin, max_price_alerts));
+            continue;
+        }
+        let repeat_valid = alert
+            .repeat_secs
+            .is_none_or(|secs| secs > 0 && price_alerts::repeat_allowed(std::time::Duration::from_secs(secs as u64)));
+        let valid = alert.price.is_finite() && alert.price > 0.0 && repeat_valid && hyperliquid_client.coin_exists(&coin).await?;
+        if !valid {
+            skipped.push(format!("{} price alert", coin));
+            continue;
+        }
+        events.push(CoordinatorCommand::PriceAlertChanged { coin: CoinSymbol::new(&coin) });
+        price_alerts.push((coin, alert.above, alert.price, alert.repeat_secs));
+    }
+
+    let mut linked_addresses = Vec::new();
+    for address in &backup.linked_addresses {
+        let address = address.to_lowercase();
+        if !hyperliquid::is_valid_address(&address) {
+            skipped.push(format!("linked address {}", address));
+            continue;
+        }
+        linked_addresses.push(address);
+    }
+    let mut watched_wallets = Vec::new();
+    for address in &backup.watched_wallets {
+        let address = address.to_lowercase();
+        if !hyperliquid::is_valid_address(&address) {
+            skipped.push(format!("watched wallet {}", address));
+            continue;
+        }
+        watched_wallets.push(address);
+    }
+
+    let funding_summary = match backup.funding_summary.as_deref() {
+        Some(period) => match FundingPeriod::parse(period) {
+            Some(period) => Some(period.as_str()),
+            None => {
+                skipped.push(format!("{} funding summary", period));
+                None
+            }
+        },
+        None => None,
+    };
+    let weekly_digest_offset_mins = backup.weekly_digest_offset_mins;
+
+    let mut destinations = Vec::new();
+    let existing = database.get_user_destinations(user_id).await?;
+    for destination in backup.destinations {
+        let DestinationBackup { chat_id: dest_chat_id, webhook_url, coin, full_precision, charts_enabled } = destination;
+        let coin = coin.as_deref().map(str::to_uppercase);
+        let duplicate = existing.iter().any(|d| {
+            d.chat_id == dest_chat_id && d.webhook_url == webhook_url && d.coin == coin
+        });
+        if duplicate {
+            continue;
+        }
+
+        let dest_chat_id = match (dest_chat_id, webhook_url.as_deref()) {
+            (Some(dest_chat_id), _) => match verify_route(state, &dest_chat_id.to_string(), user_id, chat_id).await {
+                Ok(dest_chat_id) => Some(dest_chat_id),
+                Err(reason) => {
+                    skipped.push(format!("destination chat {}: {}", dest_chat_id, reason));
+                    continue;
+                }
+            },
+            (None, Some(url)) => match delivery::validate_webhook_url(url).await {
+                Ok(()) => None,
+                Err(reason) => {
+                    skipped.push(format!("webhook {}: {}", redact::url_host(url), reason));
+                    continue;
+                }
+            },
+            (None, None) => {
+                skipped.push("destination with no target".to_string());
+                continue;
+            }
+        };
+        let webhook_url = if dest_chat_id.is_some() { None } else { webhook_url };
+        destinations.push((dest_chat_id, webhook_url, coin, full_precision, charts_enabled));
+    }
+
+    let existing = state.mutes.rules(user_id);
+    let mut mute_rules: Vec<(Vec<String>, Option<f64>)> = Vec::new();
+    for rule in &backup.mute_rules {
+        let keywords: Vec<String> = rule.keywords.iter().map(|keyword| keyword.to_lowercase()).collect();
+        let known = existing.iter().any(|mute| mute.keywords == keywords && mute.under_usd == rule.under_usd)
+            || mute_rules.iter().any(|(known, under_usd)| *known == keywords && *under_usd == rule.under_usd);
+        if known {
+            continue;
+        }
+        if existing.len() + mute_rules.len() >= MAX_MUTE_RULES {
+            skipped.push(format!("mute {}: over the limit of {}", keywords.join(" "), MAX_MUTE_RULES));
+            continue;
+        }
+        if (keywords.is_empty() && rule.under_usd.is_none()) || rule.under_usd.is_some_and(|under| !(under.is_finite() && under > 0.0)) {
+            skipped.push(format!("mute {}: not a valid rule", keywords.join(" ")));
+            continue;
+        }
+        mute_rules.push((keywords, rule.under_usd));
+    }
+
+    let subscriptions = coins.len();
+    let alerts = imbalance_alerts.len() + vwap_alerts.len() + volatility_alerts.len() + price_alerts.len();
+    let addresses = linked_addresses.len() + watched_wallets.len();
+    let destination_count = destinations.len();
+    let (priority_skipped, added_mute_rules) = database
+        .transaction(move |tx| {
+            Box::pin(async move {
+                for coin in &coins {
+                    database::add_subscription(&mut **tx, user_id, chat_id, coin).await?;
+                }
+                let mut skipped = Vec::new();
+                for coin in &priority_coins {
+                    if database::get_priority_coins(&mut **tx, user_id).await?.len() >= MAX_PRIORITY_COINS {
+                        skipped.push(format!("{} priority: over the limit of {}", coin, MAX_PRIORITY_COINS));
+                    } else if !database::set_subscription_priority(&mut **tx, user_id, coin, true).await? {
+                        skipped.push(format!("{} priority: not subscribed", coin));
+                    }
+                }
+                for (coin, min_notional_usd) in &subscription_thresholds {
+                    if !database::set_subscription_threshold(&mut **tx, user_id, coin, Some(*min_notional_usd)).await? {
+                        skipped.push(format!("{} threshold: not subscribed", coin));
+                    }
+                }
+                for (coin, until) in &muted_subscriptions {
+                    if !database::mute_subscription(&mut **tx, user_id, coin, *until).await? {
+                        skipped.push(format!("{} mute: not subscribed", coin));
+                    }
+                }
+
+                if let Some(currency) = currency {
+                    database::set_user_currency(&mut **tx, user_id, currency.as_deref()).await?;
+                }
+                database::set_full_precision(&mut **tx, user_id, settings.full_precision).await?;
+                database::set_charts_enabled(&mut **tx, user_id, settings.charts_enabled).await?;
+                database::set_revisit_alerts(&mut **tx, user_id, settings.revisit_alerts).await?;
+                database::set_show_leverage(&mut **tx, user_id, settings.show_leverage).await?;
+                database::set_ticker_mode(&mut **tx, user_id, settings.ticker_mode).await?;
+                database::set_heartbeat(&mut **tx, user_id, settings.heartbeat).await?;
+                database::set_range_alerts(&mut **tx, user_id, settings.range_alerts).await?;
+                database::set_divergence_alerts(&mut **tx, user_id, settings.divergence_alerts).await?;
+                if let Some(per_day) = autotune {
+                    database::set_autotune(&mut **tx, user_id, per_day).await?;
+                }
+                if let Some(below) = silent_below {
+                    database::set_silent_below(&mut **tx, user_id, below).await?;
+                }
+                if let Some(cap) = daily_alert_cap {
+                    database::set_daily_alert_cap(&mut **tx, user_id, cap).await?;
+                }
+                if let Some(mins) = digest_mins {
+                    database::set_digest_mins(&mut **tx, user_id, mins).await?;
+                }
+                if let Some(preference) = duplicate_alerts {
+                    database::set_duplicate_alerts(&mut **tx, user_id, preference).await?;
+                }
+                if let Some(route_chat_id) = route_chat_id {
+                    database::set_route_chat_id(&mut **tx, user_id, Some(route_chat_id)).await?;
+                }
+
+                for (coin, threshold_pct) in &imbalance_alerts {
+                    database::set_imbalance_alert(&mut **tx, user_id, chat_id, coin, *threshold_pct).await?;
+                }
+                for (coin, threshold_pct) in &vwap_alerts {
+                    database::set_vwap_alert(&mut **tx, user_id, chat_id, coin, *threshold_pct).await?;
+                }
+                for (coin, multiple) in &volatility_alerts {
+                    database::set_volatility_alert(&mut **tx, user_id, chat_id, coin, *multiple).await?;
+                }
+                for (coin, above, price, repeat_secs) in &price_alerts {
+                    database::add_price_alert(&mut **tx, user_id, chat_id, coin, *above, *price, *repeat_secs).await?;
+                }
+
+                for address in &linked_addresses {
+                    database::link_account(&mut **tx, user_id, address).await?;
+                }
+                for address in &watched_wallets {
+                    database::watch_wallet(&mut **tx, user_id, chat_id, address).await?;
+                }
+                if let Some(period) = funding_summary {
+                    database::set_funding_summary(&mut **tx, user_id, chat_id, Some(period)).await?;
+                }
+                if weekly_digest_offset_mins.is_some() {
+                    database::set_weekly_digest(&mut **tx, user_id, chat_id, weekly_digest_offset_mins).await?;
+                }
+
+                for (dest_chat_id, webhook_url, coin, full_precision, charts_enabled) in &destinations {
+                    database::add_destination(
+                        &mut **tx,
+                        user_id,
+                        *dest_chat_id,
+                        webhook_url.as_deref(),
+                        coin.as_deref(),
+                        *full_precision,
+                        *charts_enabled,
+                    )
+                    .await?;
+                }
+
+                let mut added = Vec::new();
+                for (keywords, under_usd) in &mute_rules {
+                    added.push(database::add_mute_rule(&mut **tx, user_id, keywords, *under_usd).await?);
+                }
+                Ok((skipped, added))
+            })
+        })
+        .await?;
+    skipped.extend(priority_skipped);
+
+    // nothing outside the db hears about the import until it has all landed
+    for rule in added_mute_rules {
+        state.mutes.remember(rule);
+    }
+    for event in events {
+        if let Err(e) = state.event_sender.send(event) {
+            error!("couldn't send subscription event after import: {}", e);
+        }
+    }
+
+    let mut reply = format!(
+        "Import complete.\n\nSubscriptions: {}\nAlerts: {}\nAddresses: {}\nDestinations: {}\nSettings restored.",
+        subscriptions, alerts, addresses, destination_count
+    );
+    if !skipped.is_empty() {
+        reply.push_str(&format!("\n\nSkipped:\n{}", skipped.join("\n")));
+    }
+    Ok(reply)
+}
+
+async fn alert_action_reply(bot: &Bot, state: &TelegramBot, user_id: i64, chat_id: i64, action: &str, coin: &CoinSymbol) -> String {
+    let database = &state.database;
+    match action {
+        "mute" => {
+            let until = chrono::Utc::now() + chrono::Duration::hours(ALERT_MUTE_HOURS);
+            match database.mute_subscription(user_id, coin, until).await {
+                Ok(true) => {
+                    info!("user {} muted {} until {}", user_id, coin, until);
+                    state.notify_coordinator(CoordinatorCommand::SettingsInvalidated { telegram_user_id: user_id });
+                    format!("{} alerts muted until {} UTC", coin, until.format("%H:%M"))
+                }
+                Ok(false) => format!("You're not subscribed to {}", coin),
+                Err(e) => state.error_reply(user_id, chat_id, format!("db error muting {} for user {}: {}", coin, user_id, e)),
+            }
+        }
+        "raise" => {
+            let current = match database.get_subscription_threshold(user_id, coin).await {
+                Ok(current) => current,
+                Err(e) => return state.error_reply(user_id, chat_id, format!("db error getting {} threshold for user {}: {}", coin, user_id, e)),
+            };
+            // the global minimum is what applies until the user sets their own
+            let threshold = current.unwrap_or(0.0).max(state.config.defaults.min_trade_value_usd) * 2.0;
+            match database.set_subscription_threshold(user_id, coin, Some(threshold)).await {
+                Ok(true) => {
+                    info!("user {} raised {} threshold to {}", user_id, coin, threshold);
+                    state.notify_coordinator(CoordinatorCommand::ThresholdChanged {
+                        telegram_user_id: user_id,
+                        coin: coin.clone(),
+                        min_notional_usd: Some(threshold),
+                    });
+                    format!("{} alerts now start at {}", coin, formatting::format_usd(threshold, false))
+                }
+                Ok(false) => format!("You're not subscribed to {}", coin),
+                Err(e) => state.error_reply(user_id, chat_id, format!("db error setting {} threshold for user {}: {}", coin, user_id, e)),
+            }
+        }
+        "chart" => match state.trade_chart(coin).await {
+            Ok(png) => {
+                let sent = bot
+                    .send_photo(ChatId(chat_id), InputFile::memory(png.to_vec()))
+                    .caption(format!("{} 1h", coin))
+                    .await;
+                match sent {
+                    Ok(_) => String::new(),
+                    Err(e) => state.error_reply(user_id, chat_id, format!("couldn't send {} chart to chat {}: {}", coin, chat_id, e)),
+                }
+            }
+            Err(e) => state.error_reply(user_id, chat_id, format!("couldn't render {} chart: {}", coin, e)),
+        },
+        "unsubscribe" => match database.remove_subscription(user_id, coin).await {
+            Ok(true) => {
+                info!("user {} unsubscribed from {}", user_id, coin);
+                state.notify_coordinator(CoordinatorCommand::SettingsInvalidated { telegram_user_id: user_id });
+                format!("Unsubscribed from {}", coin)
+            }
+            Ok(false) => format!("You weren't subscribed to {}", coin),
+            Err(e) => state.error_reply(user_id, chat_id, format!("db error for user {} unsubscribing from {}: {}", user_id, coin, e)),
+        },
+        _ => String::new(),
+    }
+}
+
+async fn watch_wallet_reply(state: &TelegramBot, user_id: i64, chat_id: i64, address: &str) -> String {
+    match state.database.watch_wallet(user_id, chat_id, address).await {
+        Ok(true) => {
+            info!("user {} watching {}", user_id, address);
+            format!("Watching {}. You'll be alerted on its large trades.", formatting::short_address(address))
+        }
+        Ok(false) => format!("You're already watching {}.", formatting::short_address(address)),
+        Err(e) => state.error_reply(user_id, chat_id, format!("db error watching wallet for user {}: {}", user_id, e)),
+    }
+}
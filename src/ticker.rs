@@ -0,0 +1,199 @@
+use anyhow::Result;
+use std::collections::HashMap;
+use std::sync::Arc;
+use tokio::sync::Mutex;
+use tokio::time::{interval, Duration, Instant, MissedTickBehavior};
+use tracing::{info, warn};
+use crate::{
+    config::TickerConfig,
+    database::{Database, TickerRecord, UserSettings},
+    formatting,
+    hyperliquid::{CoinSymbol, WsTrade},
+    telegram::TelegramBot,
+};
+
+// how often pending totals are checked against the edit interval
+const FLUSH_INTERVAL: Duration = Duration::from_secs(1);
+
+struct TickerState {
+    record: TickerRecord,
+    full_precision: bool,
+    dirty: bool,
+    last_edit: Option<Instant>,
+}
+
+impl TickerState {
+    fn text(&self, coin: &CoinSymbol) -> String {
+        let record = &self.record;
+        let usd = |value: f64| formatting::format_usd(value, self.full_precision);
+        let mut text = format!(
+            "{} Whale Ticker · {} (UTC)\n\nBuys: {} ({})\nSells: {} ({})\nNet: {}",
+            coin,
+            record.day,
+            usd(record.buy_usd),
+            record.buys,
+            usd(record.sell_usd),
+            record.sells,
+            formatting::format_signed_usd(record.buy_usd - record.sell_usd)
+        );
+        if let Some(last_trade) = &record.last_trade {
+            text.push_str(&format!("\n\nLast: {}", last_trade));
+        }
+        text
+    }
+}
+
+/// Running whale totals for chats in /ticker mode: one message per coin per
+/// chat, edited as trades come in rather than a new alert for each. Edits are
+/// batched to one per `min_edit_interval_secs` so a busy coin can't flood
+/// telegram's edit limits.
+#[derive(Clone)]
+pub struct TickerBoard {
+    database: Database,
+    telegram_bot: TelegramBot,
+    tickers: Arc<Mutex<HashMap<(i64, CoinSymbol), TickerState>>>,
+}
+
+impl TickerBoard {
+    pub fn spawn(database: Database, telegram_bot: TelegramBot, config: TickerConfig) -> Self {
+        let board = TickerBoard {
+            database,
+            telegram_bot,
+            tickers: Arc::new(Mutex::new(HashMap::new())),
+        };
+
+        let flusher = board.clone();
+        let min_edit_interval = Duration::from_secs(config.min_edit_interval_secs.max(1));
+        tokio::spawn(async move {
+            let mut ticker = interval(FLUSH_INTERVAL);
+            ticker.set_missed_tick_behavior(MissedTickBehavior::Delay);
+            loop {
+                ticker.tick().await;
+                flusher.flush(min_edit_interval).await;
+            }
+        });
+        board
+    }
+
+    /// Adds a trade to the chat's totals; the message catches up on the next
+    /// flush. The totals are saved straight away, outside the lock.
+    pub async fn record(&self, chat_id: i64, trade: &WsTrade, notional_usd: f64, settings: &UserSettings) -> Result<()> {
+        let key = (chat_id, trade.coin.clone());
+        let today = chrono::Utc::now().date_naive();
+
+        // a chat's first trade on the coin picks its totals back up from the db
+        let known = self.tickers.lock().await.contains_key(&key);
+        let stored = if known {
+            None
+        } else {
+            self.database.get_ticker(chat_id, trade.coin.as_str()).await?
+        };
+
+        let record = {
+            let mut tickers = self.tickers.lock().await;
+            let state = tickers.entry(key).or_insert_with(|| TickerState {
+                record: stored.unwrap_or(TickerRecord {
+                    message_id: None,
+                    day: today,
+                    buys: 0,
+                    buy_usd: 0.0,
+                    sells: 0,
+                    sell_usd: 0.0,
+                    last_trade: None,
+                }),
+                full_precision: settings.full_precision,
+                dirty: false,
+                last_edit: None,
+            });
+
+            // a new day starts from zero in the same message
+            let record = &mut state.record;
+            if record.day != today {
+                record.day = today;
+                record.buys = 0;
+                record.buy_usd = 0.0;
+                record.sells = 0;
+                record.sell_usd = 0.0;
+            }
+            let side_text = if trade.side == "B" {
+                record.buys += 1;
+                record.buy_usd += notional_usd;
+                "BUY"
+            } else {
+                record.sells += 1;
+                record.sell_usd += notional_usd;
+                "SELL"
+            };
+            let traded_at = trade
+                .time
+                .and_then(chrono::DateTime::from_timestamp_millis)
+                .unwrap_or_else(chrono::Utc::now);
+            record.last_trade = Some(format!(
+                "{} {} @ ${} · {}",
+                side_text,
+                formatting::format_usd(notional_usd, settings.full_precision),
+                formatting::format_price(&trade.px),
+                traded_at.format("%H:%M:%S")
+            ));
+            state.full_precision = settings.full_precision;
+            state.dirty = true;
+            state.record.clone()
+        };
+
+        // the trade is counted either way; the next flush saves it again
+        if let Err(e) = self.database.save_ticker(chat_id, trade.coin.as_str(), &record).await {
+            warn!("couldn't save {} ticker for chat {}: {}", trade.coin, chat_id, e);
+        }
+        Ok(())
+    }
+
+    async fn flush(&self, min_edit_interval: Duration) {
+        // render under the lock, send without it so trades keep landing meanwhile
+        let due: Vec<((i64, CoinSymbol), Option<i32>, String)> = {
+            let mut tickers = self.tickers.lock().await;
+            tickers
+                .iter_mut()
+                .filter(|(_, state)| state.dirty && state.last_edit.is_none_or(|last| last.elapsed() >= min_edit_interval))
+                .map(|(key, state)| {
+                    state.dirty = false;
+                    state.last_edit = Some(Instant::now());
+                    (key.clone(), state.record.message_id, state.text(&key.1))
+                })
+                .collect()
+        };
+
+        for ((chat_id, coin), message_id, text) in due {
+            let posted = match message_id {
+                Some(message_id) => match self.telegram_bot.edit_ticker(chat_id, message_id, &text).await {
+                    Ok(true) => Ok(message_id),
+                    Ok(false) => {
+                        info!("{} ticker message in chat {} is gone, posting a new one", coin, chat_id);
+                        self.telegram_bot.post_ticker(chat_id, &text).await
+                    }
+                    Err(e) => Err(e),
+                },
+                None => self.telegram_bot.post_ticker(chat_id, &text).await,
+            };
+
+            let mut tickers = self.tickers.lock().await;
+            let Some(state) = tickers.get_mut(&(chat_id, coin.clone())) else {
+                continue;
+            };
+            match posted {
+                Ok(message_id) => state.record.message_id = Some(message_id),
+                Err(e) => {
+                    warn!("couldn't update {} ticker in chat {}: {}", coin, chat_id, e);
+                    // try again on a later flush
+                    state.dirty = true;
+                    continue;
+                }
+            }
+            let record = state.record.clone();
+            drop(tickers);
+
+            if let Err(e) = self.database.save_ticker(chat_id, coin.as_str(), &record).await {
+                warn!("couldn't save {} ticker for chat {}: {}", coin, chat_id, e);
+            }
+        }
+    }
+}
@@ -0,0 +1,126 @@
+use anyhow::Result;
+use chrono::{DateTime, Utc};
+use std::collections::HashMap;
+use tokio::time::{interval, Duration, MissedTickBehavior};
+use tracing::{error, info, warn};
+use crate::{
+    coordinator::CoordinatorCommand,
+    database::{AutotuneSubscription, Database},
+    formatting,
+    hyperliquid::CoinSymbol,
+    outbound::Priority,
+    telegram::TelegramBot,
+};
+
+/// Trade history thresholds are sized against.
+pub const SUGGEST_LOOKBACK_DAYS: i64 = 7;
+
+const AUTOTUNE_INTERVAL: Duration = Duration::from_secs(6 * 60 * 60);
+
+// a retune has to move the threshold at least this much, so it doesn't
+// nudge back and forth every run
+const MIN_RETUNE_CHANGE: f64 = 0.2;
+
+/// The size of the `target`-th largest trade, rounded down to two significant
+/// figures, or None when there weren't more trades than that to begin with.
+pub fn suggest_threshold(mut notionals: Vec<f64>, target: usize) -> Option<f64> {
+    if target == 0 || notionals.len() <= target {
+        return None;
+    }
+    notionals.sort_by(|a, b| b.total_cmp(a));
+    let value = notionals[target - 1];
+    let step = 10f64.powi(value.log10().floor() as i32 - 1);
+    Some((value / step).floor() * step)
+}
+
+/// How many days `trades` (oldest first) cover, so history younger than the
+/// lookback isn't averaged over all of it.
+pub fn history_days<T>(trades: &[(T, DateTime<Utc>, f64)], lookback_days: i64) -> f64 {
+    trades
+        .first()
+        .map(|(_, traded_at, _)| (Utc::now() - *traded_at).num_hours() as f64 / 24.0)
+        .unwrap_or(0.0)
+        .clamp(1.0, lookback_days as f64)
+}
+
+// for users on /autotune: moves each subscription's threshold to wherever it
+// would have produced their alerts/day over the last week, raising it when
+// busy and dropping it back toward the default when quiet
+pub struct AutoTuner {
+    database: Database,
+    telegram_bot: TelegramBot,
+    global_min: f64,
+}
+
+impl AutoTuner {
+    pub fn spawn(database: Database, telegram_bot: TelegramBot, global_min: f64) {
+        let tuner = AutoTuner {
+            database,
+            telegram_bot,
+            global_min,
+        };
+
+        tokio::spawn(async move {
+            let mut ticker = interval(AUTOTUNE_INTERVAL);
+            ticker.set_missed_tick_behavior(MissedTickBehavior::Delay);
+            loop {
+                ticker.tick().await;
+                if let Err(e) = tuner.retune().await {
+                    error!("couldn't auto-tune thresholds: {}", e);
+                }
+            }
+        });
+        info!("threshold auto-tuner started");
+    }
+
+    async fn retune(&self) -> Result<()> {
+        let subscriptions = self.database.get_autotune_subscriptions().await?;
+        let mut by_coin: HashMap<String, Vec<AutotuneSubscription>> = HashMap::new();
+        for subscription in subscriptions {
+            by_coin.entry(subscription.coin.clone()).or_default().push(subscription);
+        }
+
+        for (coin, subscriptions) in by_coin {
+            let trades = self.database.get_recorded_trades(std::slice::from_ref(&coin), SUGGEST_LOOKBACK_DAYS).await?;
+            let days = history_days(&trades, SUGGEST_LOOKBACK_DAYS);
+            let notionals: Vec<f64> = trades.into_iter().map(|(_, _, notional)| notional).collect();
+
+            for subscription in subscriptions {
+                let target = (subscription.alerts_per_day as f64 * days).round() as usize;
+                let tuned = suggest_threshold(notionals.clone(), target).filter(|threshold| *threshold > self.global_min);
+
+                let current = subscription.min_notional_usd.unwrap_or(self.global_min);
+                let next = tuned.unwrap_or(self.global_min);
+                if (next - current).abs() < current * MIN_RETUNE_CHANGE {
+                    continue;
+                }
+
+                self.database
+                    .set_subscription_threshold(subscription.telegram_user_id, &coin, tuned)
+                    .await?;
+                self.telegram_bot.notify_coordinator(CoordinatorCommand::ThresholdChanged {
+                    telegram_user_id: subscription.telegram_user_id,
+                    coin: CoinSymbol::new(&coin),
+                    min_notional_usd: tuned,
+                });
+                info!(
+                    "auto-tuned {} threshold for user {} from {} to {}",
+                    coin, subscription.telegram_user_id, current, next
+                );
+
+                let notice = format!(
+                    "{} Threshold Auto-Tuned\n\n{} {} to {} to keep you near {} alerts a day.\n\nUse /autotune off to stop adjusting it.",
+                    coin,
+                    if next > current { "Raised" } else { "Lowered" },
+                    formatting::format_usd(current, false),
+                    formatting::format_usd(next, false),
+                    subscription.alerts_per_day
+                );
+                if let Err(e) = self.telegram_bot.send_text(subscription.chat_id, &notice, Priority::Digest).await {
+                    warn!("couldn't send auto-tune notice to user {}: {}", subscription.telegram_user_id, e);
+                }
+            }
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,160 @@
+use anyhow::Result;
+use std::collections::HashMap;
+use tokio::time::{Duration, Instant};
+use tracing::warn;
+use crate::{
+    config::VolatilityConfig,
+    database::{Database, VolatilityAlert},
+    candles::CandleCache,
+    hyperliquid::CoinSymbol,
+};
+
+const MINUTES_PER_YEAR: f64 = 365.0 * 24.0 * 60.0;
+
+#[derive(Debug, Clone, Copy)]
+pub struct VolatilityReading {
+    /// Annualized, from the most recent `recent_minutes` of 1m candles.
+    pub recent: f64,
+    /// Annualized, from the `baseline_hours` before that.
+    pub baseline: f64,
+}
+
+impl VolatilityReading {
+    pub fn ratio(&self) -> f64 {
+        self.recent / self.baseline
+    }
+}
+
+// annualized stdev of 1m log returns
+fn realized_vol(closes: &[f64]) -> Option<f64> {
+    let returns: Vec<f64> = closes
+        .windows(2)
+        .filter(|pair| pair[0] > 0.0 && pair[1] > 0.0)
+        .map(|pair| (pair[1] / pair[0]).ln())
+        .collect();
+    if returns.len() < 2 {
+        return None;
+    }
+
+    let mean = returns.iter().sum::<f64>() / returns.len() as f64;
+    let variance = returns.iter().map(|r| (r - mean).powi(2)).sum::<f64>() / (returns.len() - 1) as f64;
+    Some(variance.sqrt() * MINUTES_PER_YEAR.sqrt())
+}
+
+/// Works out readings from 1m candles; a copy is taken out of the monitor so
+/// the candle fetches don't hold it.
+#[derive(Clone)]
+pub struct VolatilityReader {
+    candles: CandleCache,
+    config: VolatilityConfig,
+}
+
+impl VolatilityReader {
+    pub async fn reading(&self, coin: &CoinSymbol) -> Result<Option<VolatilityReading>> {
+        let recent_minutes = self.config.recent_minutes.max(2) as usize;
+        let baseline_minutes = (self.config.baseline_hours * 60) as usize;
+
+        let lookback = Duration::from_secs((recent_minutes + baseline_minutes) as u64 * 60);
+        let candles = self.candles.recent(coin, "1m", lookback).await?;
+        let closes: Vec<f64> = candles.iter().filter_map(|c| c.close.parse().ok()).collect();
+        if closes.len() <= recent_minutes * 2 {
+            return Ok(None);
+        }
+
+        // the boundary close belongs to both windows so neither loses a return
+        let split = closes.len() - recent_minutes;
+        let (Some(baseline), Some(recent)) = (realized_vol(&closes[..=split]), realized_vol(&closes[split..])) else {
+            return Ok(None);
+        };
+        if baseline <= 0.0 {
+            return Ok(None);
+        }
+        Ok(Some(VolatilityReading { recent, baseline }))
+    }
+
+    /// A reading for each coin that has one, fetched one coin at a time.
+    pub async fn readings(&self, coins: Vec<CoinSymbol>) -> Vec<(CoinSymbol, VolatilityReading)> {
+        let mut readings = Vec::new();
+        for coin in coins {
+            match self.reading(&coin).await {
+                Ok(Some(reading)) => readings.push((coin, reading)),
+                Ok(None) => {}
+                Err(e) => warn!("couldn't fetch {} candles for volatility: {}", coin, e),
+            }
+        }
+        readings
+    }
+}
+
+// opted-in users per coin, polled against 1m candles on a timer
+pub struct VolatilityMonitor {
+    database: Database,
+    reader: VolatilityReader,
+    watchers: HashMap<CoinSymbol, Vec<VolatilityAlert>>,
+    last_alerted: HashMap<(i64, CoinSymbol), Instant>,
+}
+
+impl VolatilityMonitor {
+    pub fn new(database: Database, candles: CandleCache, config: VolatilityConfig) -> Self {
+        VolatilityMonitor {
+            database,
+            reader: VolatilityReader { candles, config },
+            watchers: HashMap::new(),
+            last_alerted: HashMap::new(),
+        }
+    }
+
+    /// Reloads the watchers for `coin`, returning whether anyone still watches it.
+    pub async fn reload_coin(&mut self, coin: &CoinSymbol) -> Result<bool> {
+        let alerts = self.database.get_volatility_alerts_for_coin(coin).await?;
+
+        if alerts.is_empty() {
+            self.watchers.remove(coin);
+            self.last_alerted.retain(|(_, alerted_coin), _| alerted_coin != coin);
+            return Ok(false);
+        }
+
+        self.watchers.insert(coin.clone(), alerts);
+        Ok(true)
+    }
+
+    /// The coins a check covers and what reads them, so the check can fetch
+    /// candles with the monitor unlocked.
+    pub fn snapshot(&self) -> (Vec<CoinSymbol>, VolatilityReader) {
+        (self.watchers.keys().cloned().collect(), self.reader.clone())
+    }
+
+    /// Watchers whose multiple the current regime crosses and who aren't in
+    /// cooldown, given `readings` from the reader. Anyone who stopped
+    /// watching while they were fetched is left out.
+    pub fn evaluate(&mut self, readings: Vec<(CoinSymbol, VolatilityReading)>) -> Vec<(VolatilityAlert, VolatilityReading)> {
+        let cooldown = Duration::from_secs(self.reader.config.cooldown_secs);
+        let mut triggered = Vec::new();
+
+        for (coin, reading) in readings {
+            let Some(watchers) = self.watchers.get(&coin) else {
+                continue;
+            };
+
+            for watcher in watchers {
+                if reading.ratio() < watcher.multiple {
+                    continue;
+                }
+
+                let key = (watcher.telegram_user_id, coin.clone());
+                let cooling_down = self
+                    .last_alerted
+                    .get(&key)
+                    .is_some_and(|last| last.elapsed() < cooldown);
+                if cooling_down {
+                    continue;
+                }
+
+                self.last_alerted.insert(key, Instant::now());
+                triggered.push((watcher.clone(), reading));
+            }
+        }
+
+        triggered
+    }
+}
@@ -0,0 +1,178 @@
+use anyhow::Result;
+use std::collections::{HashMap, VecDeque};
+use std::sync::{Arc, Mutex};
+use tokio::time::{Duration, Instant};
+use crate::{
+    config::VwapConfig,
+    database::{Database, VwapAlert},
+    hyperliquid::CoinSymbol,
+};
+
+struct Bucket {
+    minute: i64,
+    notional: f64,
+    size: f64,
+}
+
+#[derive(Default)]
+struct CoinWindow {
+    // one bucket per minute keeps an hour of fills to ~60 entries
+    buckets: VecDeque<Bucket>,
+    last_px: f64,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct VwapReading {
+    pub vwap: f64,
+    pub last_px: f64,
+}
+
+impl VwapReading {
+    pub fn deviation_pct(&self) -> f64 {
+        (self.last_px - self.vwap) / self.vwap * 100.0
+    }
+}
+
+// rolling vwap per coin, fed every fill on the trade feeds before they're
+// pre-filtered down to alert-sized trades
+#[derive(Clone)]
+pub struct VwapTracker {
+    window_minutes: i64,
+    windows: Arc<Mutex<HashMap<CoinSymbol, CoinWindow>>>,
+}
+
+impl std::fmt::Debug for VwapTracker {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("VwapTracker").field("window_minutes", &self.window_minutes).finish()
+    }
+}
+
+impl VwapTracker {
+    pub fn new(config: &VwapConfig) -> Self {
+        VwapTracker {
+            window_minutes: (config.window_secs / 60).max(1) as i64,
+            windows: Arc::new(Mutex::new(HashMap::new())),
+        }
+    }
+
+    /// Adds (coin, px, sz) fills to the current minute's bucket.
+    pub fn record<'a>(&self, fills: impl IntoIterator<Item = (&'a str, f64, f64)>) {
+        let minute = chrono::Utc::now().timestamp() / 60;
+        let Ok(mut windows) = self.windows.lock() else {
+            return;
+        };
+
+        for (coin, px, sz) in fills {
+            let window = match windows.get_mut(coin) {
+                Some(window) => window,
+                None => windows.entry(CoinSymbol::new(coin)).or_default(),
+            };
+            window.last_px = px;
+            match window.buckets.back_mut() {
+                Some(bucket) if bucket.minute == minute => {
+                    bucket.notional += px * sz;
+                    bucket.size += sz;
+                }
+                _ => window.buckets.push_back(Bucket {
+                    minute,
+                    notional: px * sz,
+                    size: sz,
+                }),
+            }
+            while window.buckets.front().is_some_and(|bucket| bucket.minute <= minute - self.window_minutes) {
+                window.buckets.pop_front();
+            }
+        }
+    }
+
+    pub fn reading(&self, coin: &CoinSymbol) -> Option<VwapReading> {
+        let oldest = chrono::Utc::now().timestamp() / 60 - self.window_minutes;
+        let windows = self.windows.lock().ok()?;
+        let window = windows.get(coin)?;
+
+        let (notional, size) = window
+            .buckets
+            .iter()
+            .filter(|bucket| bucket.minute > oldest)
+            .fold((0.0, 0.0), |(notional, size), bucket| (notional + bucket.notional, size + bucket.size));
+        if size <= 0.0 {
+            return None;
+        }
+
+        Some(VwapReading {
+            vwap: notional / size,
+            last_px: window.last_px,
+        })
+    }
+}
+
+// same shape as the imbalance monitor: opted-in users per coin kept in memory,
+// checked on a timer with a per-user cooldown
+pub struct VwapMonitor {
+    database: Database,
+    config: VwapConfig,
+    watchers: HashMap<CoinSymbol, Vec<VwapAlert>>,
+    last_alerted: HashMap<(i64, CoinSymbol), Instant>,
+}
+
+impl VwapMonitor {
+    pub fn new(database: Database, config: VwapConfig) -> Self {
+        VwapMonitor {
+            database,
+            config,
+            watchers: HashMap::new(),
+            last_alerted: HashMap::new(),
+        }
+    }
+
+    pub fn is_watching(&self, coin: &CoinSymbol) -> bool {
+        self.watchers.contains_key(coin)
+    }
+
+    /// Reloads the watchers for `coin`, returning whether anyone still watches it.
+    pub async fn reload_coin(&mut self, coin: &CoinSymbol) -> Result<bool> {
+        let alerts = self.database.get_vwap_alerts_for_coin(coin).await?;
+
+        if alerts.is_empty() {
+            self.watchers.remove(coin);
+            self.last_alerted.retain(|(_, alerted_coin), _| alerted_coin != coin);
+            return Ok(false);
+        }
+
+        self.watchers.insert(coin.clone(), alerts);
+        Ok(true)
+    }
+
+    /// Watchers whose threshold the current deviation crosses and who aren't in cooldown.
+    pub fn evaluate(&mut self, tracker: &VwapTracker) -> Vec<(VwapAlert, VwapReading)> {
+        let cooldown = Duration::from_secs(self.config.cooldown_secs);
+        let mut triggered = Vec::new();
+
+        for (coin, watchers) in &self.watchers {
+            let Some(reading) = tracker.reading(coin) else {
+                continue;
+            };
+            let deviation = reading.deviation_pct().abs();
+
+            for watcher in watchers {
+                if deviation < watcher.threshold_pct {
+                    continue;
+                }
+
+                let key = (watcher.telegram_user_id, coin.clone());
+                let cooling_down = self
+                    .last_alerted
+                    .get(&key)
+                    .is_some_and(|last| last.elapsed() < cooldown);
+                if cooling_down {
+                    continue;
+                }
+
+                self.last_alerted.insert(key, Instant::now());
+                triggered.push((watcher.clone(), reading));
+            }
+        }
+
+        triggered
+    }
+}
@@ -0,0 +1,350 @@
+use anyhow::Result;
+use std::collections::{BTreeSet, HashMap};
+use tokio::time::{interval, Duration, MissedTickBehavior};
+use tracing::{error, info, warn};
+use crate::{
+    config::{LedgerAlertsConfig, WalletSummaryConfig},
+    database::{Database, WalletPosition, WalletSnapshot, WalletWatcher},
+    formatting,
+    hyperliquid::{ClearinghouseState, HyperliquidClient, LedgerDelta, LedgerUpdate},
+    outbound::Priority,
+    telegram::TelegramBot,
+};
+
+fn parse_positions(state: &ClearinghouseState) -> Vec<WalletPosition> {
+    state
+        .asset_positions
+        .iter()
+        .filter_map(|asset| {
+            let position = &asset.position;
+            let size: f64 = position.szi.parse().ok()?;
+            (size != 0.0).then(|| WalletPosition {
+                coin: position.coin.to_uppercase(),
+                size,
+                entry_px: position.entry_px.as_deref().and_then(|px| px.parse().ok()),
+                position_value_usd: position.position_value.parse().unwrap_or(0.0),
+                unrealized_pnl_usd: position.unrealized_pnl.parse().unwrap_or(0.0),
+            })
+        })
+        .collect()
+}
+
+fn side(size: f64) -> &'static str {
+    if size > 0.0 { "long" } else { "short" }
+}
+
+// sizes and entries keep whatever precision hl reported them with
+fn exact(value: f64) -> String {
+    formatting::format_price(&value.abs().to_string())
+}
+
+fn entry_text(position: &WalletPosition) -> String {
+    position.entry_px.map(|px| format!(" @ {}", exact(px))).unwrap_or_default()
+}
+
+// one line per coin whose position opened, closed, flipped or resized
+fn position_changes(before: &[WalletPosition], after: &[WalletPosition]) -> Vec<String> {
+    let before: HashMap<&str, &WalletPosition> = before.iter().map(|p| (p.coin.as_str(), p)).collect();
+    let after: HashMap<&str, &WalletPosition> = after.iter().map(|p| (p.coin.as_str(), p)).collect();
+    let coins: BTreeSet<&str> = before.keys().chain(after.keys()).copied().collect();
+
+    coins
+        .into_iter()
+        .filter_map(|coin| match (before.get(coin), after.get(coin)) {
+            (None, Some(new)) => Some(format!(
+                "{}: opened {} {}{} | uPnL {}",
+                coin,
+                side(new.size),
+                exact(new.size),
+                entry_text(new),
+                formatting::format_signed_usd(new.unrealized_pnl_usd)
+            )),
+            (Some(old), None) => Some(format!(
+                "{}: closed {} {} (last uPnL {})",
+                coin,
+                side(old.size),
+                exact(old.size),
+                formatting::format_signed_usd(old.unrealized_pnl_usd)
+            )),
+            (Some(old), Some(new)) if old.size != new.size => {
+                let change = if old.size.signum() != new.size.signum() {
+                    format!("flipped {} to {}", side(old.size), side(new.size))
+                } else if new.size.abs() > old.size.abs() {
+                    format!("added to {}", side(new.size))
+                } else {
+                    format!("reduced {}", side(new.size))
+                };
+                Some(format!(
+                    "{}: {} {} → {}{} | uPnL {} ({})",
+                    coin,
+                    change,
+                    exact(old.size),
+                    exact(new.size),
+                    entry_text(new),
+                    formatting::format_signed_usd(new.unrealized_pnl_usd),
+                    formatting::format_signed_usd(new.unrealized_pnl_usd - old.unrealized_pnl_usd)
+                ))
+            }
+            _ => None,
+        })
+        .collect()
+}
+
+fn format_summary(address: &str, previous: &WalletSnapshot, changes: &[String], current: &WalletSnapshot) -> String {
+    let elapsed = (current.taken_at - previous.taken_at).to_std().unwrap_or_default();
+
+    format!(
+        "Wallet {} Positions ({})\n\n{}\n\nRealized: {}\nOpen uPnL: {}\nAccount value: {} ({})",
+        formatting::short_address(address),
+        formatting::format_duration(elapsed),
+        changes.join("\n"),
+        formatting::format_signed_usd(current.realized_pnl_usd),
+        formatting::format_signed_usd(current.unrealized_pnl_usd()),
+        formatting::format_usd(current.account_value_usd, false),
+        formatting::format_signed_usd(current.account_value_usd - previous.account_value_usd)
+    )
+}
+
+// snapshots every watched wallet's clearinghouse state on a timer and sends
+// watchers what changed since the last snapshot, if anything did
+pub struct WalletReporter {
+    config: WalletSummaryConfig,
+    database: Database,
+    hyperliquid_client: HyperliquidClient,
+    telegram_bot: TelegramBot,
+}
+
+impl WalletReporter {
+    pub fn spawn(config: WalletSummaryConfig, database: Database, hyperliquid_client: HyperliquidClient, telegram_bot: TelegramBot) {
+        if !config.enabled {
+            return;
+        }
+
+        let reporter = WalletReporter {
+            config,
+            database,
+            hyperliquid_client,
+            telegram_bot,
+        };
+
+        tokio::spawn(async move {
+            let mut ticker = interval(Duration::from_secs(reporter.config.interval_hours.max(1) * 60 * 60));
+            ticker.set_missed_tick_behavior(MissedTickBehavior::Delay);
+            loop {
+                ticker.tick().await;
+                if let Err(e) = reporter.snapshot_all().await {
+                    error!("couldn't snapshot watched wallets: {}", e);
+                }
+            }
+        });
+        info!("wallet reporter started");
+    }
+
+    async fn snapshot_all(&self) -> Result<()> {
+        let mut by_address: HashMap<String, Vec<WalletWatcher>> = HashMap::new();
+        for watcher in self.database.get_all_wallet_watchers().await? {
+            by_address.entry(watcher.address.clone()).or_default().push(watcher);
+        }
+
+        for (address, watchers) in &by_address {
+            if let Err(e) = self.snapshot_wallet(address, watchers).await {
+                warn!("couldn't snapshot wallet {}: {}", address, e);
+            }
+        }
+
+        let pruned = self.database.prune_wallet_snapshots(self.config.retention_days).await?;
+        info!("snapshotted {} watched wallets, pruned {} old snapshots", by_address.len(), pruned);
+        Ok(())
+    }
+
+    async fn snapshot_wallet(&self, address: &str, watchers: &[WalletWatcher]) -> Result<()> {
+        let state = self.hyperliquid_client.clearinghouse_state(address).await?;
+        let previous = self.database.get_latest_wallet_snapshot(address).await?;
+        let taken_at = chrono::Utc::now();
+
+        // the first snapshot is only a baseline, so there's nothing realized before it
+        let realized_pnl_usd = match &previous {
+            Some(previous) => self
+                .hyperliquid_client
+                .user_fills_by_time(address, previous.taken_at.timestamp_millis() + 1, taken_at.timestamp_millis())
+                .await?
+                .iter()
+                .map(|fill| fill.closed_pnl.parse::<f64>().unwrap_or(0.0) - fill.fee.parse::<f64>().unwrap_or(0.0))
+                .sum(),
+            None => 0.0,
+        };
+
+        let snapshot = WalletSnapshot {
+            account_value_usd: state.margin_summary.account_value.parse().unwrap_or(0.0),
+            realized_pnl_usd,
+            taken_at,
+            positions: parse_positions(&state),
+        };
+        self.database.save_wallet_snapshot(address, &snapshot).await?;
+
+        let Some(previous) = previous else {
+            return Ok(());
+        };
+        let changes = position_changes(&previous.positions, &snapshot.positions);
+        if changes.is_empty() {
+            return Ok(());
+        }
+
+        let summary = format_summary(address, &previous, &changes, &snapshot);
+        for watcher in watchers {
+            if let Err(e) = self.telegram_bot.send_text(watcher.telegram_chat_id, &summary, Priority::Digest).await {
+                warn!("couldn't send wallet summary to user {}: {}", watcher.telegram_user_id, e);
+            }
+        }
+        Ok(())
+    }
+}
+
+// usd size and a title/line for a ledger update, from the watched wallet's side
+fn describe_ledger_update(address: &str, update: &LedgerUpdate) -> Option<(f64, &'static str, String)> {
+    let usd = |amount: &str| amount.parse::<f64>().ok();
+    let counterparty = |user: &str, destination: &str| {
+        if user.eq_ignore_ascii_case(address) {
+            format!("to {}", formatting::short_address(destination))
+        } else {
+            format!("from {}", formatting::short_address(user))
+        }
+    };
+    let sent = |user: &str| if user.eq_ignore_ascii_case(address) { "sent" } else { "received" };
+
+    match &update.delta {
+        LedgerDelta::Deposit { usdc } => {
+            let amount = usd(usdc)?;
+            Some((amount, "Deposit", format!("deposited {}", formatting::format_usd(amount, false))))
+        }
+        LedgerDelta::Withdraw { usdc } => {
+            let amount = usd(usdc)?;
+            Some((amount, "Withdrawal", format!("withdrew {}", formatting::format_usd(amount, false))))
+        }
+        LedgerDelta::InternalTransfer { usdc, user, destination } | LedgerDelta::SubAccountTransfer { usdc, user, destination } => {
+            let amount = usd(usdc)?;
+            Some((
+                amount,
+                "Transfer",
+                format!("{} {} {}", sent(user), formatting::format_usd(amount, false), counterparty(user, destination)),
+            ))
+        }
+        LedgerDelta::SpotTransfer { token, amount, usdc_value, user, destination } => {
+            let value = usdc_value.as_deref().and_then(usd).or_else(|| (token == "USDC").then(|| usd(amount)).flatten())?;
+            Some((
+                value,
+                "Transfer",
+                format!(
+                    "{} {} {} ({}) {}",
+                    sent(user),
+                    formatting::format_price(amount),
+                    token,
+                    formatting::format_usd(value, false),
+                    counterparty(user, destination)
+                ),
+            ))
+        }
+        LedgerDelta::VaultDeposit { vault, usdc } => {
+            let amount = usd(usdc)?;
+            Some((
+                amount,
+                "Vault Deposit",
+                format!("deposited {} into vault {}", formatting::format_usd(amount, false), formatting::short_address(vault)),
+            ))
+        }
+        LedgerDelta::VaultWithdraw { vault, net_withdrawn_usd } => {
+            let amount = usd(net_withdrawn_usd)?;
+            Some((
+                amount,
+                "Vault Withdrawal",
+                format!("withdrew {} from vault {}", formatting::format_usd(amount, false), formatting::short_address(vault)),
+            ))
+        }
+        LedgerDelta::Other => None,
+    }
+}
+
+// polls watched wallets' ledgers for deposits, withdrawals and transfers.
+// cursors start at the first poll that sees a wallet, so nothing from before
+// it was watched (or before a restart) gets replayed
+pub struct LedgerMonitor {
+    config: LedgerAlertsConfig,
+    database: Database,
+    hyperliquid_client: HyperliquidClient,
+    telegram_bot: TelegramBot,
+    cursors: HashMap<String, i64>,
+}
+
+impl LedgerMonitor {
+    pub fn spawn(config: LedgerAlertsConfig, database: Database, hyperliquid_client: HyperliquidClient, telegram_bot: TelegramBot) {
+        if !config.enabled {
+            return;
+        }
+
+        let mut monitor = LedgerMonitor {
+            config,
+            database,
+            hyperliquid_client,
+            telegram_bot,
+            cursors: HashMap::new(),
+        };
+
+        tokio::spawn(async move {
+            let mut ticker = interval(Duration::from_secs(monitor.config.poll_interval_secs.max(1)));
+            ticker.set_missed_tick_behavior(MissedTickBehavior::Delay);
+            loop {
+                ticker.tick().await;
+                if let Err(e) = monitor.poll().await {
+                    error!("couldn't check watched wallet ledgers: {}", e);
+                }
+            }
+        });
+        info!("wallet ledger monitor started");
+    }
+
+    async fn poll(&mut self) -> Result<()> {
+        let mut by_address: HashMap<String, Vec<WalletWatcher>> = HashMap::new();
+        for watcher in self.database.get_all_wallet_watchers().await? {
+            by_address.entry(watcher.address.clone()).or_default().push(watcher);
+        }
+
+        let now = chrono::Utc::now().timestamp_millis();
+        self.cursors.retain(|address, _| by_address.contains_key(address));
+
+        for (address, watchers) in &by_address {
+            let Some(&cursor) = self.cursors.get(address) else {
+                self.cursors.insert(address.clone(), now);
+                continue;
+            };
+
+            let updates = match self.hyperliquid_client.user_ledger_updates(address, cursor + 1, now).await {
+                Ok(updates) => updates,
+                Err(e) => {
+                    warn!("couldn't fetch ledger updates for {}: {}", address, e);
+                    continue;
+                }
+            };
+            if let Some(latest) = updates.iter().map(|update| update.time).max() {
+                self.cursors.insert(address.clone(), latest);
+            }
+
+            for update in &updates {
+                let Some((usd, title, line)) = describe_ledger_update(address, update) else {
+                    continue;
+                };
+                if usd < self.config.min_usd {
+                    continue;
+                }
+
+                let message = format!("Watched Wallet {}\n\n{} {}", title, formatting::short_address(address), line);
+                for watcher in watchers {
+                    if let Err(e) = self.telegram_bot.send_text(watcher.telegram_chat_id, &message, Priority::Alert).await {
+                        warn!("couldn't send ledger alert to user {}: {}", watcher.telegram_user_id, e);
+                    }
+                }
+                info!("sent {} ledger alert for {} to {} watchers", title.to_lowercase(), address, watchers.len());
+            }
+        }
+        Ok(())
+    }
+}
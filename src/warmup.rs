@@ -0,0 +1,84 @@
+use anyhow::Result;
+use std::future::Future;
+use tokio::time::{timeout_at, Duration, Instant};
+use tracing::{info, warn};
+use crate::{
+    config::WarmupConfig,
+    hyperliquid::HyperliquidClient,
+    market::AssetMetadata,
+    prices::PriceEngine,
+};
+
+/// What loaded before the bot started taking commands; `None` for anything
+/// that failed or ran out of time.
+#[derive(Debug, Default)]
+pub struct WarmupReport {
+    pub coins: Option<usize>,
+    pub assets: Option<usize>,
+    pub mids: Option<usize>,
+}
+
+impl WarmupReport {
+    pub fn is_complete(&self) -> bool {
+        self.coins.is_some() && self.assets.is_some() && self.mids.is_some()
+    }
+
+    /// The parts that didn't load, e.g. "coin list, mids".
+    pub fn missing(&self) -> String {
+        [(self.coins, "coin list"), (self.assets, "asset contexts"), (self.mids, "mids")]
+            .iter()
+            .filter(|(loaded, _)| loaded.is_none())
+            .map(|(_, part)| *part)
+            .collect::<Vec<_>>()
+            .join(", ")
+    }
+}
+
+// the first /subscribe after a deploy otherwise waits on the coin list, and
+// the first alerts on asset metadata; all three load side by side
+pub async fn run(
+    config: &WarmupConfig,
+    client: &HyperliquidClient,
+    asset_metadata: &AssetMetadata,
+    price_engine: &PriceEngine,
+) -> WarmupReport {
+    let started = Instant::now();
+    let deadline = started + Duration::from_secs(config.timeout_secs);
+
+    let (coins, assets, mids) = tokio::join!(
+        part("coin list", deadline, client.warm_coins()),
+        part("asset contexts", deadline, asset_metadata.warm()),
+        part("mids", deadline, async {
+            let mids = client.all_mids().await?;
+            let count = mids.len();
+            price_engine.seed(mids).await;
+            Ok(count)
+        }),
+    );
+
+    let report = WarmupReport { coins, assets, mids };
+    if report.is_complete() {
+        info!(
+            "warmed up in {:.1}s: {} coins, {} assets, {} mids",
+            started.elapsed().as_secs_f64(),
+            report.coins.unwrap_or_default(),
+            report.assets.unwrap_or_default(),
+            report.mids.unwrap_or_default()
+        );
+    }
+    report
+}
+
+async fn part(name: &str, deadline: Instant, load: impl Future<Output = Result<usize>>) -> Option<usize> {
+    match timeout_at(deadline, load).await {
+        Ok(Ok(count)) => Some(count),
+        Ok(Err(e)) => {
+            warn!("warmup couldn't load {}: {}", name, e);
+            None
+        }
+        Err(_) => {
+            warn!("warmup timed out loading {}", name);
+            None
+        }
+    }
+}
@@ -0,0 +1,112 @@
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use tokio::time::{interval, Duration, Instant, MissedTickBehavior};
+use tracing::{error, info, warn};
+use crate::{
+    config::WatchdogConfig,
+    formatting,
+    outbound::Priority,
+    telegram::TelegramBot,
+};
+
+/// Bumped by the coordinator loop on every pass, read by the watchdog.
+#[derive(Clone)]
+pub struct Heartbeat {
+    origin: Instant,
+    // ms since `origin` at the last beat
+    last_beat_ms: Arc<AtomicU64>,
+}
+
+impl Default for Heartbeat {
+    fn default() -> Self {
+        Heartbeat::new()
+    }
+}
+
+impl Heartbeat {
+    pub fn new() -> Self {
+        Heartbeat {
+            origin: Instant::now(),
+            last_beat_ms: Arc::new(AtomicU64::new(0)),
+        }
+    }
+
+    pub fn beat(&self) {
+        self.last_beat_ms.store(self.origin.elapsed().as_millis() as u64, Ordering::Relaxed);
+    }
+
+    pub fn age(&self) -> Duration {
+        let last_beat = Duration::from_millis(self.last_beat_ms.load(Ordering::Relaxed));
+        self.origin.elapsed().saturating_sub(last_beat)
+    }
+}
+
+// a stuck coordinator loop means no alerts at all while everything else looks
+// healthy, so the watchdog tells admins and can bounce the process
+pub struct Watchdog {
+    heartbeat: Heartbeat,
+    config: WatchdogConfig,
+    telegram_bot: TelegramBot,
+    admin_user_ids: Vec<i64>,
+}
+
+impl Watchdog {
+    pub fn spawn(heartbeat: Heartbeat, config: WatchdogConfig, telegram_bot: TelegramBot, admin_user_ids: Vec<i64>) {
+        let watchdog = Watchdog {
+            heartbeat,
+            config,
+            telegram_bot,
+            admin_user_ids,
+        };
+        tokio::spawn(watchdog.run());
+    }
+
+    async fn run(self) {
+        let stall_after = Duration::from_secs(self.config.stall_secs);
+        let mut ticker = interval(Duration::from_secs(self.config.check_interval_secs.max(1)));
+        ticker.set_missed_tick_behavior(MissedTickBehavior::Delay);
+        let mut stalled = false;
+
+        loop {
+            ticker.tick().await;
+            let age = self.heartbeat.age();
+
+            if age < stall_after {
+                if stalled {
+                    info!("coordinator loop recovered");
+                    self.notify_admins("Coordinator loop recovered, alerts are flowing again.").await;
+                    stalled = false;
+                }
+                continue;
+            }
+
+            if !stalled {
+                error!("coordinator loop has not come round in {:?}", age);
+                let mut alert = format!(
+                    "Coordinator loop stalled: no heartbeat for {}. Trade alerts are not going out.",
+                    formatting::format_duration(age)
+                );
+                if self.config.restart_on_stall {
+                    alert.push_str("\n\nRestarting the bot.");
+                }
+                self.notify_admins(&alert).await;
+                stalled = true;
+            }
+
+            if self.config.restart_on_stall {
+                // a deadlocked task can't be unwound from here; exiting lets
+                // the supervisor start a clean process
+                error!("exiting so the supervisor restarts the bot");
+                std::process::exit(1);
+            }
+        }
+    }
+
+    async fn notify_admins(&self, text: &str) {
+        for admin_id in &self.admin_user_ids {
+            if let Err(e) = self.telegram_bot.send_text(*admin_id, text, Priority::Alert).await {
+                warn!("couldn't notify admin {} from watchdog: {}", admin_id, e);
+            }
+        }
+    }
+}
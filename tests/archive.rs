@@ -0,0 +1,59 @@
+//! `hl-tg-bot backup` and `restore` against a freshly migrated postgres,
+//! so the round trip keeps working as migrations add columns.
+//!
+//! Needs docker, so it's ignored by default:
+//! `cargo test --test archive -- --ignored`. Setting `E2E_DATABASE_URL` uses
+//! that (throwaway) database instead of starting a container.
+
+use hl_tg_bot::{archive, config::DatabaseConfig, database::Database};
+use serde_json::json;
+use testcontainers_modules::{
+    postgres::Postgres,
+    testcontainers::{runners::AsyncRunner, ContainerAsync, ImageExt},
+};
+
+const USER_ID: i64 = 2002;
+
+async fn start_postgres() -> (Option<ContainerAsync<Postgres>>, String) {
+    if let Ok(url) = std::env::var("E2E_DATABASE_URL") {
+        return (None, url);
+    }
+    let postgres = Postgres::default().with_tag("16-alpine").start().await.expect("start postgres");
+    let host = postgres.get_host().await.expect("postgres host");
+    let port = postgres.get_host_port_ipv4(5432).await.expect("postgres port");
+    let url = format!("postgres://postgres:postgres@{}:{}/postgres", host, port);
+    (Some(postgres), url)
+}
+
+fn args(command: &str, path: &std::path::Path) -> Vec<String> {
+    vec![command.to_string(), path.display().to_string()]
+}
+
+#[tokio::test]
+#[ignore = "needs docker"]
+async fn backup_then_restore_round_trips() {
+    let (_postgres, database_url) = start_postgres().await;
+    let config: DatabaseConfig = serde_json::from_value(json!({ "url": database_url })).expect("database config");
+    let database = Database::new(&config).await.expect("connect and migrate");
+
+    // a builder-dex coin, so user_subscriptions' generated dex column is filled in
+    database.add_subscription(USER_ID, USER_ID, "BTC").await.expect("subscribe BTC");
+    database.add_subscription(USER_ID, USER_ID, "XYZ:FOO").await.expect("subscribe XYZ:FOO");
+    database.set_full_precision(USER_ID, true).await.expect("set full precision");
+
+    let path = std::env::temp_dir().join(format!("hl-tg-bot-archive-{}.json", std::process::id()));
+    assert!(archive::run_subcommand(&database, &args("backup", &path)).await.expect("backup"));
+
+    database.delete_user_data(USER_ID).await.expect("delete user data");
+    assert!(database.get_user_subscriptions(USER_ID).await.expect("subscriptions").is_empty());
+
+    let restored = archive::run_subcommand(&database, &args("restore", &path)).await;
+    let _ = std::fs::remove_file(&path);
+    assert!(restored.expect("restore"));
+
+    assert_eq!(
+        database.get_user_subscriptions(USER_ID).await.expect("subscriptions"),
+        vec!["BTC".to_string(), "XYZ:FOO".to_string()]
+    );
+    assert!(database.get_user_settings(USER_ID).await.expect("settings").full_precision);
+}
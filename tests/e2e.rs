@@ -0,0 +1,320 @@
+//! End to end: the real binary against postgres in a container, a mock
+//! Hyperliquid (REST and ws) and a fake Telegram Bot API, following a
+//! /subscribe through to the trade alert it should produce.
+//!
+//! Needs docker, so it's ignored by default:
+//! `cargo test --test e2e -- --ignored`. Setting `E2E_DATABASE_URL` uses
+//! that (throwaway) database instead of starting a container.
+
+use std::path::PathBuf;
+use std::process::Stdio;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use futures_util::{SinkExt, StreamExt};
+use serde_json::{json, Value};
+use testcontainers_modules::{
+    postgres::Postgres,
+    testcontainers::{runners::AsyncRunner, ContainerAsync, ImageExt},
+};
+use tokio::net::TcpListener;
+use tokio::process::{Child, Command};
+use tokio_tungstenite::tungstenite::Message;
+use wiremock::{
+    matchers::{body_partial_json, method, path, path_regex},
+    Mock, MockServer, ResponseTemplate,
+};
+
+const BOT_TOKEN: &str = "123456:e2e";
+const USER_ID: i64 = 1001;
+
+// the alert should arrive well inside this once the bot is up
+const ALERT_TIMEOUT: Duration = Duration::from_secs(60);
+
+fn now_ms() -> i64 {
+    SystemTime::now().duration_since(UNIX_EPOCH).map_or(0, |now| now.as_millis() as i64)
+}
+
+fn ok(result: Value) -> ResponseTemplate {
+    ResponseTemplate::new(200).set_body_json(json!({ "ok": true, "result": result }))
+}
+
+fn private_chat() -> Value {
+    json!({ "id": USER_ID, "type": "private", "first_name": "Whale" })
+}
+
+// teloxide names methods after its payload types, e.g. /bot<token>/GetMe
+async fn fake_telegram() -> MockServer {
+    let telegram = MockServer::start().await;
+
+    Mock::given(path_regex("/GetMe$"))
+        .respond_with(ok(json!({
+            "id": 42,
+            "is_bot": true,
+            "first_name": "Alerts",
+            "username": "e2e_alerts_bot",
+            "can_join_groups": true,
+            "can_read_all_group_messages": false,
+            "supports_inline_queries": false
+        })))
+        .mount(&telegram)
+        .await;
+    Mock::given(path_regex("/GetWebhookInfo$"))
+        .respond_with(ok(json!({ "url": "", "has_custom_certificate": false, "pending_update_count": 0 })))
+        .mount(&telegram)
+        .await;
+
+    // the user's /subscribe, then nothing; the delay stands in for long polling
+    Mock::given(path_regex("/GetUpdates$"))
+        .respond_with(ok(json!([{
+            "update_id": 1,
+            "message": {
+                "message_id": 1,
+                "date": now_ms() / 1000,
+                "chat": private_chat(),
+                "from": { "id": USER_ID, "is_bot": false, "first_name": "Whale" },
+                "text": "/subscribe BTC",
+                "entities": [{ "type": "bot_command", "offset": 0, "length": 10 }]
+            }
+        }])))
+        .up_to_n_times(1)
+        .with_priority(1)
+        .mount(&telegram)
+        .await;
+    Mock::given(path_regex("/GetUpdates$"))
+        .respond_with(ok(json!([])).set_delay(Duration::from_millis(500)))
+        .with_priority(2)
+        .mount(&telegram)
+        .await;
+
+    Mock::given(path_regex("/SendMessage$"))
+        .respond_with(ok(json!({
+            "message_id": 2,
+            "date": now_ms() / 1000,
+            "chat": private_chat(),
+            "text": "sent"
+        })))
+        .mount(&telegram)
+        .await;
+
+    // set_my_commands, pins and the like only need to succeed
+    Mock::given(method("POST"))
+        .respond_with(ok(json!(true)))
+        .with_priority(u8::MAX)
+        .mount(&telegram)
+        .await;
+
+    telegram
+}
+
+async fn mock_hyperliquid_rest() -> MockServer {
+    let hyperliquid = MockServer::start().await;
+
+    Mock::given(path("/info"))
+        .and(body_partial_json(json!({ "type": "metaAndAssetCtxs" })))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!([
+            { "universe": [{ "name": "BTC", "szDecimals": 5, "maxLeverage": 40 }] },
+            [{
+                "funding": "0.0000125",
+                "openInterest": "10000",
+                "prevDayPx": "60000",
+                "dayNtlVlm": "1000000000",
+                "markPx": "60000",
+                "oraclePx": "60000"
+            }]
+        ])))
+        .mount(&hyperliquid)
+        .await;
+    Mock::given(path("/info"))
+        .and(body_partial_json(json!({ "type": "allMids" })))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({ "BTC": "60000" })))
+        .mount(&hyperliquid)
+        .await;
+    Mock::given(path("/info"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!([])))
+        .with_priority(u8::MAX)
+        .mount(&hyperliquid)
+        .await;
+
+    hyperliquid
+}
+
+// acks every subscription, and once BTC trades are wanted sends a whale buy
+// every half second so the test doesn't race the bot's own setup
+async fn mock_hyperliquid_ws() -> String {
+    let listener = TcpListener::bind("127.0.0.1:0").await.expect("bind mock ws");
+    let url = format!("ws://{}", listener.local_addr().expect("mock ws address"));
+
+    tokio::spawn(async move {
+        while let Ok((stream, _)) = listener.accept().await {
+            tokio::spawn(async move {
+                let Ok(ws) = tokio_tungstenite::accept_async(stream).await else {
+                    return;
+                };
+                let (mut sender, mut receiver) = ws.split();
+                let mut trades_wanted = false;
+                let mut ticker = tokio::time::interval(Duration::from_millis(500));
+                let mut tid = 0u64;
+
+                loop {
+                    tokio::select! {
+                        message = receiver.next() => {
+                            let Some(Ok(Message::Text(text))) = message else {
+                                if matches!(message, None | Some(Err(_))) {
+                                    return;
+                                }
+                                continue;
+                            };
+                            let Ok(request) = serde_json::from_str::<Value>(&text) else {
+                                continue;
+                            };
+                            if request["method"] != "subscribe" {
+                                continue;
+                            }
+                            let subscription = &request["subscription"];
+                            if subscription["type"] == "trades" && subscription["coin"] == "BTC" {
+                                trades_wanted = true;
+                            }
+                            let ack = json!({ "channel": "subscriptionResponse", "data": request });
+                            if sender.send(Message::Text(ack.to_string())).await.is_err() {
+                                return;
+                            }
+                        }
+                        _ = ticker.tick(), if trades_wanted => {
+                            tid += 1;
+                            let trades = json!({
+                                "channel": "trades",
+                                "data": [{
+                                    "coin": "BTC",
+                                    "side": "B",
+                                    "px": "60000",
+                                    "sz": "10",
+                                    "time": now_ms(),
+                                    "tid": tid,
+                                    "hash": format!("0x{:064x}", tid),
+                                    "users": ["0x1111111111111111111111111111111111111111", "0x2222222222222222222222222222222222222222"]
+                                }]
+                            });
+                            if sender.send(Message::Text(trades.to_string())).await.is_err() {
+                                return;
+                            }
+                        }
+                    }
+                }
+            });
+        }
+    });
+
+    url
+}
+
+fn write_config(database_url: &str, telegram_url: &str, rest_url: &str, ws_url: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("hl-tg-bot-e2e-{}-{}", std::process::id(), now_ms()));
+    std::fs::create_dir_all(&dir).expect("create config dir");
+    let config = format!(
+        r#"
+[telegram]
+bot_token = "{BOT_TOKEN}"
+api_url = "{telegram_url}"
+admin_user_ids = []
+
+[hyperliquid]
+websocket_url = "{ws_url}"
+rest_api_url = "{rest_url}"
+
+[database]
+url = "{database_url}"
+
+[defaults]
+default_symbol = "BTC"
+min_trade_value_usd = 100000.0
+
+[retry]
+max_attempts = 3
+base_delay_ms = 100
+max_delay_ms = 1000
+
+[commands]
+subscribe_command = "subscribe"
+unsubscribe_command = "unsubscribe"
+list_command = "list"
+help_command = "help"
+
+[fx]
+api_url = "{rest_url}/fx"
+refresh_interval_secs = 3600
+max_staleness_secs = 86400
+"#
+    );
+    std::fs::write(dir.join("config.toml"), config).expect("write config");
+    dir
+}
+
+fn spawn_bot(config_dir: &PathBuf) -> Child {
+    Command::new(env!("CARGO_BIN_EXE_hl-tg-bot"))
+        .current_dir(config_dir)
+        .env_remove("APP_ENV")
+        // unoptimised, the command handler's future outgrows the default 2MB
+        // worker stacks; release builds don't need this
+        .env("RUST_MIN_STACK", (16 * 1024 * 1024).to_string())
+        .stdout(Stdio::inherit())
+        .stderr(Stdio::inherit())
+        .kill_on_drop(true)
+        .spawn()
+        .expect("start the bot")
+}
+
+async fn start_postgres() -> (Option<ContainerAsync<Postgres>>, String) {
+    if let Ok(url) = std::env::var("E2E_DATABASE_URL") {
+        return (None, url);
+    }
+    let postgres = Postgres::default().with_tag("16-alpine").start().await.expect("start postgres");
+    let host = postgres.get_host().await.expect("postgres host");
+    let port = postgres.get_host_port_ipv4(5432).await.expect("postgres port");
+    let url = format!("postgres://postgres:postgres@{}:{}/postgres", host, port);
+    (Some(postgres), url)
+}
+
+// texts the bot has sent to the user so far
+async fn sent_texts(telegram: &MockServer) -> Vec<String> {
+    telegram
+        .received_requests()
+        .await
+        .unwrap_or_default()
+        .iter()
+        .filter(|request| request.url.path().ends_with("/SendMessage"))
+        .filter_map(|request| serde_json::from_slice::<Value>(&request.body).ok())
+        .filter(|body| body["chat_id"] == USER_ID)
+        .filter_map(|body| body["text"].as_str().map(str::to_string))
+        .collect()
+}
+
+#[tokio::test]
+#[ignore = "needs docker"]
+async fn subscribe_then_whale_trade_sends_alert() {
+    let (_postgres, database_url) = start_postgres().await;
+    let telegram = fake_telegram().await;
+    let rest = mock_hyperliquid_rest().await;
+    let ws_url = mock_hyperliquid_ws().await;
+
+    let config_dir = write_config(&database_url, &telegram.uri(), &rest.uri(), &ws_url);
+    let mut bot = spawn_bot(&config_dir);
+
+    let deadline = tokio::time::Instant::now() + ALERT_TIMEOUT;
+    let (subscribed, alerted) = loop {
+        let texts = sent_texts(&telegram).await;
+        let subscribed = texts.iter().any(|text| text.contains("Successfully subscribed to BTC"));
+        let alerted = texts.iter().any(|text| text.contains("BTC") && text.contains("BUY") && text.contains("60,000"));
+        if (subscribed && alerted) || tokio::time::Instant::now() >= deadline {
+            break (subscribed, alerted);
+        }
+        if let Ok(Some(status)) = bot.try_wait() {
+            panic!("bot exited early: {}", status);
+        }
+        tokio::time::sleep(Duration::from_millis(250)).await;
+    };
+
+    let _ = bot.kill().await;
+    let _ = std::fs::remove_dir_all(&config_dir);
+
+    assert!(subscribed, "no /subscribe confirmation, sent: {:?}", sent_texts(&telegram).await);
+    assert!(alerted, "no whale alert, sent: {:?}", sent_texts(&telegram).await);
+}
@@ -0,0 +1,111 @@
+//! Markets not quoted in USDC: the quote asset comes from the dex's meta and
+//! spotMeta, and the socket's filter converts notionals at its mid.
+
+use hl_tg_bot::{
+    activity::ActivityTracker,
+    config::{HyperliquidConfig, PayloadLoggingConfig, RevisitConfig, TlsConfig, VwapConfig},
+    hyperliquid::{websocket::trades_frame, CertPins, HyperliquidClient, TradeFilter},
+    prices::PriceEngine,
+    vwap::VwapTracker,
+};
+use serde_json::json;
+use std::collections::HashMap;
+use std::sync::Arc;
+use wiremock::{
+    matchers::{body_partial_json, path},
+    Mock, MockServer, ResponseTemplate,
+};
+
+fn meta(coin: &str, collateral_token: Option<usize>) -> serde_json::Value {
+    let mut meta = json!({ "universe": [{ "name": coin, "szDecimals": 2, "maxLeverage": 10 }] });
+    if let Some(token) = collateral_token {
+        meta["collateralToken"] = json!(token);
+    }
+    json!([meta, [{ "funding": "0", "openInterest": "0", "prevDayPx": "1", "dayNtlVlm": "0", "markPx": "1", "oraclePx": "1" }]])
+}
+
+async fn mock_hyperliquid_rest() -> MockServer {
+    let hyperliquid = MockServer::start().await;
+
+    Mock::given(path("/info"))
+        .and(body_partial_json(json!({ "type": "metaAndAssetCtxs", "dex": "xyz" })))
+        .respond_with(ResponseTemplate::new(200).set_body_json(meta("xyz:XYZ100", Some(150))))
+        .with_priority(1)
+        .mount(&hyperliquid)
+        .await;
+    Mock::given(path("/info"))
+        .and(body_partial_json(json!({ "type": "metaAndAssetCtxs", "dex": "usd" })))
+        .respond_with(ResponseTemplate::new(200).set_body_json(meta("usd:GOLD", Some(0))))
+        .with_priority(1)
+        .mount(&hyperliquid)
+        .await;
+    Mock::given(path("/info"))
+        .and(body_partial_json(json!({ "type": "metaAndAssetCtxs" })))
+        .respond_with(ResponseTemplate::new(200).set_body_json(meta("BTC", None)))
+        .mount(&hyperliquid)
+        .await;
+    Mock::given(path("/info"))
+        .and(body_partial_json(json!({ "type": "spotMeta" })))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "tokens": [{ "name": "USDC", "index": 0 }, { "name": "HYPE", "index": 150 }],
+            "universe": []
+        })))
+        .mount(&hyperliquid)
+        .await;
+
+    hyperliquid
+}
+
+fn client(rest_url: &str) -> HyperliquidClient {
+    let config = HyperliquidConfig {
+        websocket_url: "ws://127.0.0.1:1".to_string(),
+        rest_api_url: rest_url.to_string(),
+        max_subscriptions_per_connection: 100,
+        tls: TlsConfig::default(),
+        perp_dexs: vec!["xyz".to_string(), "usd".to_string()],
+        payload_logging: PayloadLoggingConfig::default(),
+    };
+    HyperliquidClient::new(config, CertPins::new(&TlsConfig::default()).expect("no pins")).expect("client")
+}
+
+#[tokio::test]
+async fn quote_asset_comes_from_spot_meta() {
+    let hyperliquid = mock_hyperliquid_rest().await;
+    let client = client(&hyperliquid.uri());
+
+    // the first lookup loads the coin list itself
+    assert_eq!(client.quote_asset("XYZ:XYZ100").await.as_deref(), Some("HYPE"));
+    assert_eq!(client.quote_asset("xyz:XYZ100").await.as_deref(), Some("HYPE"));
+    // quoted in USDC, on the main dex or a builder one
+    assert_eq!(client.quote_asset("USD:GOLD").await, None);
+    assert_eq!(client.quote_asset("BTC").await, None);
+}
+
+#[tokio::test]
+async fn filter_converts_at_the_quote_mid() {
+    let prices = PriceEngine::new(RevisitConfig::default());
+    let filter = TradeFilter {
+        min_notional_usd: 100_000.0,
+        seen: Arc::default(),
+        vwap: VwapTracker::new(&VwapConfig::default()),
+        activity: ActivityTracker::new(),
+        cluster_fills: false,
+        sampler: None,
+        recorder: None,
+        quote_rate: Some(prices.quote_rate("HYPE").await),
+    };
+    let text = r#"{"channel":"trades","data":[{"coin":"xyz:XYZ100","side":"B","px":"2500","sz":"2","time":1718000000000,"tid":1,"hash":"0x0","users":["0x1","0x2"]}]}"#;
+    let trades = trades_frame(text).expect("trades frame");
+
+    // no USD value before the quote asset has a mid
+    assert_eq!(filter.notional_usd(&trades[0]), None);
+
+    prices.seed(HashMap::from([("HYPE".to_string(), "40".to_string())])).await;
+    assert_eq!(filter.notional_usd(&trades[0]), Some(200_000.0));
+    assert_eq!(filter.to_trade(&trades[0]).quote_usd, 40.0);
+
+    // a USDC market's notional is px * sz as it stands
+    let usdc = TradeFilter { quote_rate: None, ..filter };
+    assert_eq!(usdc.notional_usd(&trades[0]), Some(5_000.0));
+    assert_eq!(usdc.to_trade(&trades[0]).quote_usd, 1.0);
+}
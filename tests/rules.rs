@@ -0,0 +1,108 @@
+//! Properties of the alert rules users set today: per-subscription
+//! thresholds, /mute rules and /pricealert repeat intervals.
+
+use hl_tg_bot::{
+    database::{MuteRule, UserSettings, UserSubscription},
+    mutes::parse_rule,
+    price_alerts::parse_repeat,
+};
+use proptest::prelude::*;
+use std::time::Duration;
+
+fn subscription(min_notional_usd: Option<f64>) -> UserSubscription {
+    UserSubscription {
+        telegram_user_id: 1,
+        telegram_chat_id: 1,
+        settings: UserSettings::default(),
+        min_notional_usd,
+        destinations: Vec::new(),
+        priority: false,
+        muted_until: None,
+    }
+}
+
+fn mute(keywords: &[String], under_usd: Option<f64>) -> MuteRule {
+    MuteRule {
+        id: 1,
+        telegram_user_id: 1,
+        keywords: keywords.to_vec(),
+        under_usd,
+    }
+}
+
+fn notional() -> impl Strategy<Value = f64> {
+    0.0..50_000_000.0f64
+}
+
+fn unit_secs() -> impl Strategy<Value = (char, u64)> {
+    prop_oneof![Just(('s', 1)), Just(('m', 60)), Just(('h', 60 * 60)), Just(('d', 24 * 60 * 60))]
+}
+
+proptest! {
+    #[test]
+    fn stricter_threshold_never_fires_more(
+        trades in prop::collection::vec(notional(), 0..200),
+        a in notional(),
+        b in notional(),
+    ) {
+        let (looser, stricter) = (subscription(Some(a.min(b))), subscription(Some(a.max(b))));
+        for notional_usd in trades {
+            // anything the stricter rule lets through, the looser one does too
+            prop_assert!(!stricter.wants(notional_usd) || looser.wants(notional_usd));
+        }
+    }
+
+    #[test]
+    fn no_threshold_fires_on_everything(notional_usd in notional()) {
+        prop_assert!(subscription(None).wants(notional_usd));
+    }
+
+    #[test]
+    fn threshold_fires_exactly_at_and_above(min in notional(), notional_usd in notional()) {
+        prop_assert_eq!(subscription(Some(min)).wants(notional_usd), notional_usd >= min);
+    }
+
+    #[test]
+    fn narrower_mute_never_mutes_more(
+        text in "[a-z ]{0,60}",
+        keywords in prop::collection::vec("[a-z]{1,4}", 0..3),
+        extra in "[a-z]{1,4}",
+        under in prop::option::of(notional()),
+        notional_usd in notional(),
+    ) {
+        let broad = mute(&keywords, under);
+        let mut narrow_keywords = keywords.clone();
+        narrow_keywords.push(extra);
+        let narrow = mute(&narrow_keywords, under);
+        prop_assert!(!narrow.matches(&text, notional_usd) || broad.matches(&text, notional_usd));
+    }
+
+    #[test]
+    fn mute_rule_parses_back(keywords in prop::collection::vec("[a-z]{1,8}", 1..4), under in prop::option::of(1u32..10_000_000)) {
+        prop_assume!(!keywords.iter().any(|keyword| keyword == "under"));
+        let args = match under {
+            Some(under) => format!("{} under {}", keywords.join(" "), under),
+            None => keywords.join(" "),
+        };
+        prop_assert_eq!(parse_rule(&args.to_uppercase()), Some((keywords, under.map(f64::from))));
+    }
+
+    #[test]
+    fn repeat_round_trips((unit, secs) in unit_secs(), value in 0u64..20_000) {
+        let expected = Duration::from_secs(value * secs);
+        let in_range = (Duration::from_secs(60)..=Duration::from_secs(7 * 24 * 60 * 60)).contains(&expected);
+
+        let parsed = parse_repeat(&format!("{}{}", value, unit));
+        prop_assert_eq!(parsed, in_range.then_some(expected));
+        // case and surrounding whitespace don't matter
+        prop_assert_eq!(parse_repeat(&format!("  {}{} ", value, unit.to_ascii_uppercase())), parsed);
+    }
+
+    #[test]
+    fn repeat_never_panics(text in "\\PC*") {
+        if let Some(repeat) = parse_repeat(&text) {
+            prop_assert!(repeat >= Duration::from_secs(60));
+            prop_assert!(repeat <= Duration::from_secs(7 * 24 * 60 * 60));
+        }
+    }
+}